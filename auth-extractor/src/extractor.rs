@@ -0,0 +1,102 @@
+use axum::{
+    async_trait,
+    extract::{FromRef, FromRequestParts},
+    http::{request::Parts, HeaderMap},
+};
+
+use crate::{extract_token, AuthError, AuthVerifier, Claims};
+
+// Extractor for routes that need a logged-in user. Requests that already went
+// through `AuthLayer` reuse its result instead of verifying the token again.
+// Tokens issued to OAuth clients are turned away, see `AuthenticatedClient`.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub email: String,
+    pub claims: Claims,
+}
+
+impl AuthenticatedUser {
+    // For handlers that guard themselves, e.g. `user.require_permission("reports:read")?`
+    pub fn require_permission(&self, permission: &str) -> Result<(), AuthError> {
+        if self.claims.has_permission(permission) {
+            Ok(())
+        } else {
+            Err(AuthError::MissingPermission)
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthenticatedUser
+where
+    AuthVerifier: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(user) = parts.extensions.get::<AuthenticatedUser>() {
+            return Ok(user.clone());
+        }
+
+        authenticate(&parts.headers, &AuthVerifier::from_ref(state)).await
+    }
+}
+
+// Extractor for routes called by OAuth clients with the client credentials grant.
+// User tokens are turned away.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedClient {
+    pub client_id: String,
+    pub claims: Claims,
+}
+
+impl AuthenticatedClient {
+    pub fn require_permission(&self, permission: &str) -> Result<(), AuthError> {
+        if self.claims.has_permission(permission) {
+            Ok(())
+        } else {
+            Err(AuthError::MissingPermission)
+        }
+    }
+}
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthenticatedClient
+where
+    AuthVerifier: FromRef<S>,
+    S: Send + Sync,
+{
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let claims = verify(&parts.headers, &AuthVerifier::from_ref(state)).await?;
+
+        match claims.client_id.clone() {
+            Some(client_id) => Ok(AuthenticatedClient { client_id, claims }),
+            None => Err(AuthError::InvalidToken),
+        }
+    }
+}
+
+pub(crate) async fn authenticate(
+    headers: &HeaderMap,
+    verifier: &AuthVerifier,
+) -> Result<AuthenticatedUser, AuthError> {
+    let claims = verify(headers, verifier).await?;
+
+    // A client token's `sub` is the client id, which must never pass for an email
+    if claims.is_client() {
+        return Err(AuthError::InvalidToken);
+    }
+
+    Ok(AuthenticatedUser {
+        email: claims.sub.clone(),
+        claims,
+    })
+}
+
+async fn verify(headers: &HeaderMap, verifier: &AuthVerifier) -> Result<Claims, AuthError> {
+    let token = extract_token(headers).ok_or(AuthError::MissingToken)?;
+    verifier.verify(&token).await
+}
@@ -0,0 +1,97 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use axum::{
+    extract::Request,
+    response::{IntoResponse, Response},
+};
+use tower_layer::Layer;
+use tower_service::Service;
+
+use crate::{extractor::authenticate, AuthVerifier};
+
+// Rejects every request without a valid user token before it reaches the wrapped routes.
+// The verified user is stored in the request extensions, so handlers can take either
+// `AuthenticatedUser` or `Extension<AuthenticatedUser>`.
+#[derive(Clone)]
+pub struct AuthLayer {
+    verifier: AuthVerifier,
+    required_permission: Option<Arc<str>>,
+}
+
+impl AuthLayer {
+    pub fn new(verifier: AuthVerifier) -> Self {
+        Self {
+            verifier,
+            required_permission: None,
+        }
+    }
+
+    // Also turns away users whose token doesn't grant `permission`, with a 403
+    pub fn require_permission(mut self, permission: impl Into<Arc<str>>) -> Self {
+        self.required_permission = Some(permission.into());
+        self
+    }
+}
+
+impl<S> Layer<S> for AuthLayer {
+    type Service = AuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        AuthService {
+            inner,
+            verifier: self.verifier.clone(),
+            required_permission: self.required_permission.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AuthService<S> {
+    inner: S,
+    verifier: AuthVerifier,
+    required_permission: Option<Arc<str>>,
+}
+
+impl<S> Service<Request> for AuthService<S>
+where
+    S: Service<Request, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request) -> Self::Future {
+        let verifier = self.verifier.clone();
+        let required_permission = self.required_permission.clone();
+        // Use the service that was polled ready and leave a fresh clone in its place
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            let user = authenticate(request.headers(), &verifier)
+                .await
+                .and_then(|user| match &required_permission {
+                    Some(permission) => user.require_permission(permission).map(|_| user),
+                    None => Ok(user),
+                });
+
+            match user {
+                Ok(user) => {
+                    request.extensions_mut().insert(user);
+                    inner.call(request).await
+                }
+                Err(e) => Ok(e.into_response()),
+            }
+        })
+    }
+}
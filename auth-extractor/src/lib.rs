@@ -0,0 +1,15 @@
+pub mod error;
+pub mod extractor;
+pub mod jwks;
+pub mod layer;
+pub mod remote;
+pub mod token;
+pub mod verifier;
+
+pub use error::*;
+pub use extractor::*;
+pub use jwks::*;
+pub use layer::*;
+pub use remote::*;
+pub use token::*;
+pub use verifier::*;
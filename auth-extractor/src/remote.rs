@@ -0,0 +1,133 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use async_trait::async_trait;
+use reqwest::StatusCode;
+use serde::Serialize;
+
+use crate::{is_api_key, AuthError, Claims, TokenVerifier};
+
+// Tokens the auth service accepted are remembered this long, so a logout can take
+// up to this long to be noticed. API keys are never cached since revoking one takes
+// effect immediately.
+pub const DEFAULT_CACHE_TTL: Duration = Duration::from_secs(30);
+
+// Verifies tokens by calling the auth service's `/verify-token` route. The HTTP client
+// is shared between requests and only accepted tokens are cached.
+pub struct RemoteVerifier {
+    http_client: reqwest::Client,
+    verify_token_url: String,
+    cache_ttl: Duration,
+    cache: Mutex<HashMap<String, CachedClaims>>,
+}
+
+struct CachedClaims {
+    claims: Claims,
+    expires_at: Instant,
+}
+
+impl RemoteVerifier {
+    pub fn new(verify_token_url: impl Into<String>) -> Self {
+        Self {
+            http_client: reqwest::Client::new(),
+            verify_token_url: verify_token_url.into(),
+            cache_ttl: DEFAULT_CACHE_TTL,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_http_client(mut self, http_client: reqwest::Client) -> Self {
+        self.http_client = http_client;
+        self
+    }
+
+    // A zero TTL turns the cache off
+    pub fn with_cache_ttl(mut self, cache_ttl: Duration) -> Self {
+        self.cache_ttl = cache_ttl;
+        self
+    }
+
+    fn cached(&self, token: &str) -> Option<Claims> {
+        let cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+
+        cache
+            .get(token)
+            .filter(|entry| entry.expires_at > Instant::now())
+            .map(|entry| entry.claims.clone())
+    }
+
+    // Entries never outlive the token itself
+    fn remember(&self, token: &str, claims: &Claims) {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        let token_lifetime = Duration::from_secs(claims.exp as u64).saturating_sub(now);
+        let ttl = self.cache_ttl.min(token_lifetime);
+
+        if ttl.is_zero() {
+            return;
+        }
+
+        let mut cache = self.cache.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        cache.retain(|_, entry| entry.expires_at > now);
+        cache.insert(
+            token.to_owned(),
+            CachedClaims {
+                claims: claims.clone(),
+                expires_at: now + ttl,
+            },
+        );
+    }
+}
+
+#[async_trait]
+impl TokenVerifier for RemoteVerifier {
+    async fn verify(&self, token: &str) -> Result<Claims, AuthError> {
+        let cacheable = !is_api_key(token);
+        if cacheable {
+            if let Some(claims) = self.cached(token) {
+                return Ok(claims);
+            }
+        }
+
+        let response = self
+            .http_client
+            .post(&self.verify_token_url)
+            .json(&VerifyTokenRequest { token })
+            .send()
+            .await
+            .map_err(AuthError::unexpected)?;
+
+        let claims = match response.status() {
+            StatusCode::OK => response
+                .json::<Claims>()
+                .await
+                .map_err(AuthError::unexpected)?,
+            StatusCode::UNAUTHORIZED | StatusCode::BAD_REQUEST => {
+                return Err(AuthError::InvalidToken)
+            }
+            StatusCode::FORBIDDEN => return Err(AuthError::AccountNotActive),
+            status => {
+                return Err(AuthError::unexpected(format!(
+                    "unexpected status from auth service: {}",
+                    status
+                )))
+            }
+        };
+
+        if cacheable {
+            self.remember(token, &claims);
+        }
+
+        Ok(claims)
+    }
+}
+
+#[derive(Serialize)]
+struct VerifyTokenRequest<'a> {
+    token: &'a str,
+}
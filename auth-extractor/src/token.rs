@@ -0,0 +1,82 @@
+use axum::http::{header::AUTHORIZATION, HeaderMap};
+use axum_extra::extract::CookieJar;
+
+pub const JWT_COOKIE_NAME: &str = "jwt";
+
+// API keys are sent as bearer tokens like JWTs and start with this prefix. Only the
+// auth service can check them, so they need the remote verifier.
+pub const API_KEY_PREFIX: &str = "pat_";
+
+pub fn is_api_key(token: &str) -> bool {
+    token.starts_with(API_KEY_PREFIX)
+}
+
+// Reads the auth token from an `Authorization: Bearer` header, falling back to the
+// `jwt` cookie set by the auth service for browsers.
+pub fn extract_token(headers: &HeaderMap) -> Option<String> {
+    let bearer_token = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .filter(|token| !token.is_empty());
+
+    if let Some(token) = bearer_token {
+        return Some(token.to_owned());
+    }
+
+    CookieJar::from_headers(headers)
+        .get(JWT_COOKIE_NAME)
+        .map(|cookie| cookie.value().to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{header::COOKIE, HeaderValue};
+
+    use super::*;
+
+    fn headers(pairs: &[(axum::http::HeaderName, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(name, HeaderValue::from_str(value).unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn reads_jwt_cookie() {
+        let headers = headers(&[(COOKIE, "theme=dark; jwt=cookie-token")]);
+        assert_eq!(extract_token(&headers), Some("cookie-token".to_owned()));
+    }
+
+    #[test]
+    fn reads_bearer_header() {
+        let headers = headers(&[(AUTHORIZATION, "Bearer header-token")]);
+        assert_eq!(extract_token(&headers), Some("header-token".to_owned()));
+    }
+
+    #[test]
+    fn prefers_bearer_header_over_cookie() {
+        let headers = headers(&[
+            (AUTHORIZATION, "Bearer header-token"),
+            (COOKIE, "jwt=cookie-token"),
+        ]);
+        assert_eq!(extract_token(&headers), Some("header-token".to_owned()));
+    }
+
+    #[test]
+    fn ignores_other_authorization_schemes() {
+        let basic = "Basic dXNlcjpwYXNz";
+        assert_eq!(extract_token(&headers(&[(AUTHORIZATION, basic)])), None);
+
+        let headers = headers(&[(AUTHORIZATION, basic), (COOKIE, "jwt=cookie-token")]);
+        assert_eq!(extract_token(&headers), Some("cookie-token".to_owned()));
+    }
+
+    #[test]
+    fn returns_none_without_token() {
+        assert_eq!(extract_token(&HeaderMap::new()), None);
+        assert_eq!(extract_token(&headers(&[(AUTHORIZATION, "Bearer ")])), None);
+    }
+}
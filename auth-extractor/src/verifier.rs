@@ -0,0 +1,75 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use jsonwebtoken::jwk::JwkSet;
+use serde::{Deserialize, Serialize};
+
+use crate::{AuthError, JwksVerifier, RemoteVerifier};
+
+// The claims the auth service puts in its tokens
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Claims {
+    pub sub: String,
+    // The tenant `sub` belongs to. Emails are only unique within a tenant.
+    #[serde(default = "default_tenant")]
+    pub tenant: String,
+    pub exp: usize,
+    #[serde(default)]
+    pub auth_time: usize,
+    #[serde(default)]
+    pub amr: Vec<String>,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+    // Set when the token belongs to an OAuth client rather than a user, in which case
+    // `sub` is the client id
+    #[serde(default)]
+    pub client_id: Option<String>,
+}
+
+fn default_tenant() -> String {
+    "default".to_owned()
+}
+
+impl Claims {
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|granted| granted == permission)
+    }
+
+    pub fn is_client(&self) -> bool {
+        self.client_id.is_some()
+    }
+}
+
+#[async_trait]
+pub trait TokenVerifier {
+    async fn verify(&self, token: &str) -> Result<Claims, AuthError>;
+}
+
+// Cheap to clone handle to whichever verifier a service is configured with. Put it in
+// the router state (or anything implementing `FromRef` for it) to use the extractor.
+#[derive(Clone)]
+pub struct AuthVerifier(Arc<dyn TokenVerifier + Send + Sync>);
+
+impl AuthVerifier {
+    pub fn new(verifier: impl TokenVerifier + Send + Sync + 'static) -> Self {
+        Self(Arc::new(verifier))
+    }
+
+    // Asks the auth service's `/verify-token` route, which also catches logged out and
+    // revoked tokens and suspended accounts, at most `DEFAULT_CACHE_TTL` late
+    pub fn remote(verify_token_url: impl Into<String>) -> Self {
+        Self::new(RemoteVerifier::new(verify_token_url))
+    }
+
+    // Checks signatures locally against the auth service's public keys, without a round
+    // trip. Logged out and revoked tokens pass until they expire.
+    pub fn jwks(jwks: JwkSet) -> Self {
+        Self::new(JwksVerifier::new(jwks))
+    }
+
+    pub async fn verify(&self, token: &str) -> Result<Claims, AuthError> {
+        self.0.verify(token).await
+    }
+}
@@ -0,0 +1,153 @@
+use std::time::Duration;
+
+use auth_extractor::{AuthError, RemoteVerifier, TokenVerifier};
+use serde_json::json;
+use wiremock::{
+    matchers::{body_json, method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+fn claims() -> serde_json::Value {
+    json!({
+        "sub": "user@example.com",
+        "tenant": "acme",
+        "exp": jsonwebtoken::get_current_timestamp() + 600,
+        "auth_time": jsonwebtoken::get_current_timestamp(),
+        "amr": ["pwd"]
+    })
+}
+
+fn verifier(server: &MockServer) -> RemoteVerifier {
+    RemoteVerifier::new(format!("{}/verify-token", server.uri()))
+}
+
+#[tokio::test]
+async fn returns_claims_for_accepted_token() {
+    let server = MockServer::start().await;
+
+    Mock::given(path("/verify-token"))
+        .and(method("POST"))
+        .and(body_json(json!({ "token": "valid" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(claims()))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let claims = verifier(&server).verify("valid").await.unwrap();
+
+    assert_eq!(claims.sub, "user@example.com");
+    assert_eq!(claims.tenant, "acme");
+    assert_eq!(claims.amr, vec!["pwd".to_owned()]);
+}
+
+#[tokio::test]
+async fn caches_accepted_tokens() {
+    let server = MockServer::start().await;
+
+    Mock::given(path("/verify-token"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(claims()))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    let verifier = verifier(&server);
+
+    for _ in 0..3 {
+        assert!(verifier.verify("valid").await.is_ok());
+    }
+}
+
+#[tokio::test]
+async fn does_not_cache_api_keys() {
+    let server = MockServer::start().await;
+
+    Mock::given(path("/verify-token"))
+        .and(method("POST"))
+        .and(body_json(json!({ "token": "pat_abc_secret" })))
+        .respond_with(ResponseTemplate::new(200).set_body_json(claims()))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let verifier = verifier(&server);
+
+    for _ in 0..2 {
+        assert!(verifier.verify("pat_abc_secret").await.is_ok());
+    }
+}
+
+#[tokio::test]
+async fn asks_again_once_cache_entry_expires() {
+    let server = MockServer::start().await;
+
+    Mock::given(path("/verify-token"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(claims()))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let verifier = verifier(&server).with_cache_ttl(Duration::from_millis(100));
+
+    assert!(verifier.verify("valid").await.is_ok());
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(verifier.verify("valid").await.is_ok());
+}
+
+#[tokio::test]
+async fn does_not_cache_rejected_tokens() {
+    let server = MockServer::start().await;
+
+    Mock::given(path("/verify-token"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(401))
+        .expect(2)
+        .mount(&server)
+        .await;
+
+    let verifier = verifier(&server);
+
+    for _ in 0..2 {
+        assert!(matches!(
+            verifier.verify("banned").await,
+            Err(AuthError::InvalidToken)
+        ));
+    }
+}
+
+#[tokio::test]
+async fn rejects_tokens_of_inactive_accounts() {
+    let server = MockServer::start().await;
+
+    Mock::given(path("/verify-token"))
+        .and(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(403).set_body_json(json!({ "error": "Account suspended" })),
+        )
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    assert!(matches!(
+        verifier(&server).verify("valid").await,
+        Err(AuthError::AccountNotActive)
+    ));
+}
+
+#[tokio::test]
+async fn reports_auth_service_failures_as_unexpected() {
+    let server = MockServer::start().await;
+
+    Mock::given(path("/verify-token"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&server)
+        .await;
+
+    assert!(matches!(
+        verifier(&server).verify("valid").await,
+        Err(AuthError::UnexpectedError(_))
+    ));
+}
@@ -85,6 +85,14 @@ const signupForm = document.getElementById("signup-form");
 const signupButton = document.getElementById("signup-form-submit");
 const signupErrAlter = document.getElementById("signup-err-alert");
 
+// Invitation links open the sign up form; the email comes from the invitation itself
+const invitationToken = new URLSearchParams(window.location.search).get("invitation");
+if (invitationToken) {
+    loginSection.style.display = "none";
+    signupSection.style.display = "block";
+    signupForm.email.parentElement.style.display = "none";
+}
+
 signupButton.addEventListener("click", (e) => {
     e.preventDefault();
 
@@ -92,14 +100,21 @@ signupButton.addEventListener("click", (e) => {
     const password = signupForm.password.value;
     const requires2FA = signupForm.twoFA.checked;
 
-    fetch('/signup', {
+    const [url, body] = invitationToken
+        ? ['/invitations/accept', { token: invitationToken, password, requires2FA }]
+        : ['/signup', { email, password, requires2FA }];
+
+    fetch(url, {
         method: 'POST',
         headers: {
             'Content-Type': 'application/json',
         },
-        body: JSON.stringify({ email, password, requires2FA }),
+        body: JSON.stringify(body),
     }).then(response => {
         if (response.ok) {
+            if (invitationToken) {
+                window.history.replaceState(null, "", window.location.pathname);
+            }
             signupForm.email.value = "";
             signupForm.password.value = "";
             signupForm.twoFA.checked = false;
@@ -161,4 +176,34 @@ TwoFAButton.addEventListener("click", (e) => {
             });
         }
     });
-});
\ No newline at end of file
+});
+
+// Sign-ins through an identity provider that still need 2FA come back with the login
+// attempt in the URL fragment
+const federatedLogin = new URLSearchParams(window.location.hash.substring(1));
+if (federatedLogin.get("loginAttemptId")) {
+    TwoFAForm.email.value = federatedLogin.get("email");
+    TwoFAForm.login_attempt_id.value = federatedLogin.get("loginAttemptId");
+    window.history.replaceState(null, "", window.location.pathname);
+
+    loginSection.style.display = "none";
+    twoFASection.style.display = "block";
+    signupSection.style.display = "none";
+}
+// -----------------------------------------------------
+
+const ssoProviders = document.getElementById("sso-providers");
+
+fetch('/oidc/providers').then(response => {
+    if (response.ok) {
+        response.json().then(providers => {
+            providers.forEach(provider => {
+                const link = document.createElement("a");
+                link.className = "btn btn-outline-dark d-block w-100 mb-2";
+                link.href = provider.loginUrl;
+                link.textContent = `Sign in with ${provider.name}`;
+                ssoProviders.appendChild(link);
+            });
+        });
+    }
+});
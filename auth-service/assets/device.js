@@ -0,0 +1,72 @@
+// Approval page of the device grant. The user must already be logged in, since
+// the requests below are authenticated with the `jwt` cookie.
+const deviceForm = document.getElementById("device-form");
+const deviceButton = document.getElementById("device-form-submit");
+const deviceConfirm = document.getElementById("device-confirm");
+const deviceClient = document.getElementById("device-client");
+const deviceApprove = document.getElementById("device-approve");
+const deviceDeny = document.getElementById("device-deny");
+const deviceErrAlter = document.getElementById("device-err-alert");
+const deviceDoneAlert = document.getElementById("device-done-alert");
+
+let userCode = new URLSearchParams(window.location.search).get("user_code");
+if (userCode) {
+    deviceForm.user_code.value = userCode;
+}
+
+function showError(response) {
+    if (response.status === 400 || response.status === 401) {
+        deviceErrAlter.innerHTML = `<span><strong>Error: </strong>Please <a href="/">log in</a> again, then come back to this page.</span>`;
+        deviceErrAlter.style.display = "block";
+        return;
+    }
+
+    response.json().then(data => {
+        let error_msg = data.error;
+        if (error_msg !== undefined && error_msg !== null && error_msg !== "") {
+            deviceErrAlter.innerHTML = `<span><strong>Error: </strong>${error_msg}</span>`;
+            deviceErrAlter.style.display = "block";
+        } else {
+            deviceErrAlter.style.display = "none";
+        }
+    });
+}
+
+deviceButton.addEventListener("click", (e) => {
+    e.preventDefault();
+
+    userCode = deviceForm.user_code.value.trim();
+
+    fetch(`/device/authorizations/${encodeURIComponent(userCode)}`).then(response => {
+        if (response.ok) {
+            response.json().then(data => {
+                deviceClient.textContent = data.clientId;
+                deviceErrAlter.style.display = "none";
+                deviceForm.style.display = "none";
+                deviceConfirm.style.display = "block";
+            });
+        } else {
+            showError(response);
+        }
+    });
+});
+
+function decide(decision) {
+    fetch(`/device/authorizations/${encodeURIComponent(userCode)}/${decision}`, {
+        method: 'POST',
+    }).then(response => {
+        if (response.ok) {
+            deviceErrAlter.style.display = "none";
+            deviceConfirm.style.display = "none";
+            deviceDoneAlert.textContent = decision === "approve"
+                ? "Device approved. You can go back to your device."
+                : "Device denied.";
+            deviceDoneAlert.style.display = "block";
+        } else {
+            showError(response);
+        }
+    });
+}
+
+deviceApprove.addEventListener("click", () => decide("approve"));
+deviceDeny.addEventListener("click", () => decide("deny"));
@@ -0,0 +1,4 @@
+-- Add down migration script here
+ALTER TABLE users
+   DROP COLUMN IF EXISTS two_fa_channel,
+   DROP COLUMN IF EXISTS phone_number;
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- Add up migration script here
+ALTER TABLE users
+   ADD COLUMN phone_number TEXT,
+   ADD COLUMN two_fa_channel TEXT NOT NULL DEFAULT 'email';
\ No newline at end of file
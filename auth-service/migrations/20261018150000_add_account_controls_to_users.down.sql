@@ -0,0 +1,6 @@
+-- Add down migration script here
+DELETE FROM role_permissions WHERE role = 'admin' AND permission = 'users:manage';
+
+ALTER TABLE users
+   DROP COLUMN IF EXISTS password_reset_required,
+   DROP COLUMN IF EXISTS disabled;
\ No newline at end of file
@@ -0,0 +1,8 @@
+-- Add up migration script here
+ALTER TABLE users
+   ADD COLUMN IF NOT EXISTS disabled BOOLEAN NOT NULL DEFAULT FALSE,
+   ADD COLUMN IF NOT EXISTS password_reset_required BOOLEAN NOT NULL DEFAULT FALSE;
+
+INSERT INTO role_permissions (role, permission)
+VALUES ('admin', 'users:manage')
+ON CONFLICT DO NOTHING;
\ No newline at end of file
@@ -0,0 +1,9 @@
+-- Add down migration script here
+ALTER TABLE users ADD COLUMN IF NOT EXISTS disabled BOOLEAN NOT NULL DEFAULT FALSE;
+
+UPDATE users SET disabled = TRUE WHERE status <> 'active';
+
+ALTER TABLE users
+   DROP COLUMN IF EXISTS status_changed_at,
+   DROP COLUMN IF EXISTS status_reason,
+   DROP COLUMN IF EXISTS status;
\ No newline at end of file
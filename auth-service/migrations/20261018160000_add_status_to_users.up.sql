@@ -0,0 +1,9 @@
+-- Add up migration script here
+ALTER TABLE users
+   ADD COLUMN IF NOT EXISTS status TEXT NOT NULL DEFAULT 'active',
+   ADD COLUMN IF NOT EXISTS status_reason TEXT,
+   ADD COLUMN IF NOT EXISTS status_changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+UPDATE users SET status = 'suspended', status_reason = 'Disabled by an admin' WHERE disabled;
+
+ALTER TABLE users DROP COLUMN IF EXISTS disabled;
\ No newline at end of file
@@ -0,0 +1,24 @@
+-- Add down migration script here
+-- Only users of the default tenant fit back into a table keyed by email alone
+DELETE FROM users WHERE tenant_id <> 'default';
+
+DROP INDEX IF EXISTS trusted_devices_tenant_id_email_idx;
+ALTER TABLE trusted_devices DROP CONSTRAINT IF EXISTS trusted_devices_tenant_id_email_fkey;
+ALTER TABLE trusted_devices DROP COLUMN IF EXISTS tenant_id;
+
+ALTER TABLE user_roles DROP CONSTRAINT IF EXISTS user_roles_tenant_id_email_fkey;
+ALTER TABLE user_roles DROP CONSTRAINT IF EXISTS user_roles_pkey;
+ALTER TABLE user_roles DROP COLUMN IF EXISTS tenant_id;
+ALTER TABLE user_roles ADD PRIMARY KEY (email, role);
+
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_pkey;
+ALTER TABLE users DROP COLUMN IF EXISTS tenant_id;
+ALTER TABLE users ADD PRIMARY KEY (email);
+
+ALTER TABLE user_roles
+   ADD CONSTRAINT user_roles_email_fkey FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE;
+ALTER TABLE trusted_devices
+   ADD CONSTRAINT trusted_devices_email_fkey FOREIGN KEY (email) REFERENCES users(email) ON DELETE CASCADE;
+CREATE INDEX IF NOT EXISTS trusted_devices_email_idx ON trusted_devices(email);
+
+DROP TABLE IF EXISTS tenants;
\ No newline at end of file
@@ -0,0 +1,33 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS tenants(
+   id TEXT NOT NULL PRIMARY KEY,
+   hostname TEXT UNIQUE,
+   require_2fa BOOLEAN NOT NULL DEFAULT FALSE,
+   allowed_email_domains TEXT[] NOT NULL DEFAULT '{}'
+);
+
+-- Everything that existed before tenants belongs to the default one
+INSERT INTO tenants (id) VALUES ('default') ON CONFLICT DO NOTHING;
+
+ALTER TABLE trusted_devices DROP CONSTRAINT IF EXISTS trusted_devices_email_fkey;
+ALTER TABLE user_roles DROP CONSTRAINT IF EXISTS user_roles_email_fkey;
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS tenant_id TEXT NOT NULL DEFAULT 'default' REFERENCES tenants(id);
+ALTER TABLE users ALTER COLUMN tenant_id DROP DEFAULT;
+ALTER TABLE users DROP CONSTRAINT IF EXISTS users_pkey;
+ALTER TABLE users ADD PRIMARY KEY (tenant_id, email);
+
+ALTER TABLE user_roles ADD COLUMN IF NOT EXISTS tenant_id TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE user_roles ALTER COLUMN tenant_id DROP DEFAULT;
+ALTER TABLE user_roles DROP CONSTRAINT IF EXISTS user_roles_pkey;
+ALTER TABLE user_roles ADD PRIMARY KEY (tenant_id, email, role);
+ALTER TABLE user_roles
+   ADD FOREIGN KEY (tenant_id, email) REFERENCES users(tenant_id, email) ON DELETE CASCADE;
+
+ALTER TABLE trusted_devices ADD COLUMN IF NOT EXISTS tenant_id TEXT NOT NULL DEFAULT 'default';
+ALTER TABLE trusted_devices ALTER COLUMN tenant_id DROP DEFAULT;
+ALTER TABLE trusted_devices
+   ADD FOREIGN KEY (tenant_id, email) REFERENCES users(tenant_id, email) ON DELETE CASCADE;
+
+DROP INDEX IF EXISTS trusted_devices_email_idx;
+CREATE INDEX IF NOT EXISTS trusted_devices_tenant_id_email_idx ON trusted_devices(tenant_id, email);
\ No newline at end of file
@@ -0,0 +1,13 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS invitations(
+   id UUID NOT NULL PRIMARY KEY,
+   tenant_id TEXT NOT NULL REFERENCES tenants(id) ON DELETE CASCADE,
+   email TEXT NOT NULL,
+   role TEXT REFERENCES roles(name) ON DELETE SET NULL,
+   invited_by TEXT NOT NULL,
+   status TEXT NOT NULL DEFAULT 'pending',
+   created_at TIMESTAMPTZ NOT NULL,
+   expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS invitations_tenant_id_idx ON invitations(tenant_id);
\ No newline at end of file
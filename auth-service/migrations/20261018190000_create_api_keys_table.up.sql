@@ -0,0 +1,15 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS api_keys(
+   id UUID NOT NULL PRIMARY KEY,
+   tenant_id TEXT NOT NULL,
+   email TEXT NOT NULL,
+   name TEXT NOT NULL,
+   prefix TEXT NOT NULL UNIQUE,
+   key_hash TEXT NOT NULL,
+   scopes TEXT[] NOT NULL DEFAULT '{}',
+   created_at TIMESTAMPTZ NOT NULL,
+   expires_at TIMESTAMPTZ NOT NULL,
+   FOREIGN KEY (tenant_id, email) REFERENCES users(tenant_id, email) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS api_keys_tenant_id_email_idx ON api_keys(tenant_id, email);
\ No newline at end of file
@@ -0,0 +1,3 @@
+-- Add down migration script here
+DROP TABLE IF EXISTS federated_identities;
+DROP TABLE IF EXISTS identity_providers;
\ No newline at end of file
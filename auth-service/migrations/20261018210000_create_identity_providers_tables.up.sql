@@ -0,0 +1,25 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS identity_providers(
+   tenant_id TEXT NOT NULL REFERENCES tenants(id) ON DELETE CASCADE,
+   id TEXT NOT NULL,
+   name TEXT NOT NULL,
+   issuer TEXT NOT NULL,
+   client_id TEXT NOT NULL,
+   client_secret TEXT NOT NULL,
+   created_at TIMESTAMPTZ NOT NULL,
+   PRIMARY KEY (tenant_id, id)
+);
+
+-- Links accounts at a provider to users. Removing either side removes the link.
+CREATE TABLE IF NOT EXISTS federated_identities(
+   tenant_id TEXT NOT NULL,
+   provider_id TEXT NOT NULL,
+   subject TEXT NOT NULL,
+   email TEXT NOT NULL,
+   created_at TIMESTAMPTZ NOT NULL,
+   PRIMARY KEY (tenant_id, provider_id, subject),
+   FOREIGN KEY (tenant_id, provider_id) REFERENCES identity_providers(tenant_id, id) ON DELETE CASCADE,
+   FOREIGN KEY (tenant_id, email) REFERENCES users(tenant_id, email) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS federated_identities_tenant_id_email_idx ON federated_identities(tenant_id, email);
\ No newline at end of file
@@ -0,0 +1,4 @@
+-- Add up migration script here
+-- Directory users get a row here on first sign-in to hold local state like roles and 2FA
+-- settings; their password is checked by the directory
+ALTER TABLE users ADD COLUMN IF NOT EXISTS managed_by_directory BOOLEAN NOT NULL DEFAULT FALSE;
\ No newline at end of file
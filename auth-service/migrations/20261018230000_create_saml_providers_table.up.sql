@@ -0,0 +1,12 @@
+-- Add up migration script here
+CREATE TABLE IF NOT EXISTS saml_providers(
+   tenant_id TEXT NOT NULL REFERENCES tenants(id) ON DELETE CASCADE,
+   id TEXT NOT NULL,
+   name TEXT NOT NULL,
+   entity_id TEXT NOT NULL,
+   sso_url TEXT NOT NULL,
+   certificate TEXT NOT NULL,
+   email_attribute TEXT,
+   created_at TIMESTAMPTZ NOT NULL,
+   PRIMARY KEY (tenant_id, id)
+);
\ No newline at end of file
@@ -0,0 +1,20 @@
+-- Add up migration script here
+-- Groups pushed by a provisioning system through SCIM
+CREATE TABLE IF NOT EXISTS groups(
+   tenant_id TEXT NOT NULL REFERENCES tenants(id) ON DELETE CASCADE,
+   id UUID NOT NULL,
+   display_name TEXT NOT NULL,
+   created_at TIMESTAMPTZ NOT NULL,
+   PRIMARY KEY (tenant_id, id),
+   UNIQUE (tenant_id, display_name)
+);
+
+-- Deleting a user takes them out of their groups
+CREATE TABLE IF NOT EXISTS group_members(
+   tenant_id TEXT NOT NULL,
+   group_id UUID NOT NULL,
+   email TEXT NOT NULL,
+   PRIMARY KEY (tenant_id, group_id, email),
+   FOREIGN KEY (tenant_id, group_id) REFERENCES groups(tenant_id, id) ON DELETE CASCADE,
+   FOREIGN KEY (tenant_id, email) REFERENCES users(tenant_id, email) ON DELETE CASCADE
+);
\ No newline at end of file
@@ -0,0 +1,33 @@
+-- Add up migration script here
+-- Append-only: rows can be inserted but never updated or deleted
+CREATE TABLE IF NOT EXISTS audit_events(
+   sequence BIGSERIAL PRIMARY KEY,
+   id UUID NOT NULL UNIQUE,
+   tenant_id TEXT NOT NULL,
+   occurred_at TIMESTAMPTZ NOT NULL,
+   event TEXT NOT NULL,
+   outcome TEXT NOT NULL,
+   actor TEXT,
+   user_email TEXT,
+   target TEXT,
+   reason TEXT,
+   ip_address TEXT,
+   user_agent TEXT
+);
+
+CREATE INDEX IF NOT EXISTS audit_events_tenant_id_sequence_idx ON audit_events (tenant_id, sequence);
+CREATE INDEX IF NOT EXISTS audit_events_user_email_idx ON audit_events (tenant_id, user_email, sequence);
+
+CREATE OR REPLACE FUNCTION reject_audit_event_changes() RETURNS TRIGGER AS $$
+BEGIN
+   RAISE EXCEPTION 'audit events can not be changed or removed';
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER audit_events_append_only
+   BEFORE UPDATE OR DELETE ON audit_events
+   FOR EACH ROW EXECUTE FUNCTION reject_audit_event_changes();
+
+CREATE TRIGGER audit_events_no_truncate
+   BEFORE TRUNCATE ON audit_events
+   FOR EACH STATEMENT EXECUTE FUNCTION reject_audit_event_changes();
\ No newline at end of file
@@ -0,0 +1,20 @@
+-- Add up migration script here
+-- Events recorded before this migration are left unchained
+ALTER TABLE audit_events ADD COLUMN IF NOT EXISTS previous_hash TEXT;
+ALTER TABLE audit_events ADD COLUMN IF NOT EXISTS hash TEXT;
+
+-- Append-only, like the events they sign
+CREATE TABLE IF NOT EXISTS audit_checkpoints(
+   sequence BIGINT PRIMARY KEY,
+   hash TEXT NOT NULL,
+   signed_at TIMESTAMPTZ NOT NULL,
+   signature TEXT NOT NULL
+);
+
+CREATE TRIGGER audit_checkpoints_append_only
+   BEFORE UPDATE OR DELETE ON audit_checkpoints
+   FOR EACH ROW EXECUTE FUNCTION reject_audit_event_changes();
+
+CREATE TRIGGER audit_checkpoints_no_truncate
+   BEFORE TRUNCATE ON audit_checkpoints
+   FOR EACH STATEMENT EXECUTE FUNCTION reject_audit_event_changes();
\ No newline at end of file
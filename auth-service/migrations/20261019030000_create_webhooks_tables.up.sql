@@ -0,0 +1,32 @@
+-- Add up migration script here
+-- Downstream systems subscribed to a tenant's events. The secret signs what is sent to
+-- them, so it is stored as is.
+CREATE TABLE IF NOT EXISTS webhooks(
+   tenant_id TEXT NOT NULL REFERENCES tenants(id) ON DELETE CASCADE,
+   id UUID NOT NULL,
+   url TEXT NOT NULL,
+   events TEXT[] NOT NULL,
+   secret TEXT NOT NULL,
+   created_at TIMESTAMPTZ NOT NULL,
+   PRIMARY KEY (tenant_id, id)
+);
+
+-- The delivery queue. Deleting a webhook drops its deliveries.
+CREATE TABLE IF NOT EXISTS webhook_deliveries(
+   id UUID PRIMARY KEY,
+   tenant_id TEXT NOT NULL,
+   webhook_id UUID NOT NULL,
+   event_type TEXT NOT NULL,
+   payload TEXT NOT NULL,
+   status TEXT NOT NULL,
+   attempts INTEGER NOT NULL,
+   next_attempt_at TIMESTAMPTZ NOT NULL,
+   last_response_status INTEGER,
+   last_error TEXT,
+   created_at TIMESTAMPTZ NOT NULL,
+   delivered_at TIMESTAMPTZ,
+   FOREIGN KEY (tenant_id, webhook_id) REFERENCES webhooks(tenant_id, id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS webhook_deliveries_due_idx ON webhook_deliveries (next_attempt_at) WHERE status = 'pending';
+CREATE INDEX IF NOT EXISTS webhook_deliveries_tenant_id_created_at_idx ON webhook_deliveries (tenant_id, created_at);
\ No newline at end of file
@@ -0,0 +1,10 @@
+-- Add up migration script here
+ALTER TABLE users ADD COLUMN IF NOT EXISTS suspended_by TEXT;
+
+-- Until now, suspensions made through SCIM were told apart by their reason
+UPDATE users
+SET suspended_by = CASE
+    WHEN status_reason = 'Deprovisioned by the provisioning system' THEN 'provisioning'
+    ELSE 'admin'
+END
+WHERE status = 'suspended';
@@ -1,13 +1,66 @@
+use secrecy::Secret;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
-use crate::domain::{BannedTokenStore, TwoFACodeStore, UserStore, EmailClient};
+use crate::{
+    domain::{
+        ApiKeyStore, AuditLog, BannedTokenStore, DeviceAuthorizationStore, EmailClient,
+        FederatedIdentityStore, GroupStore, IdentityProviderStore, InvitationStore, MagicLinkStore,
+        OAuthClientStore, PhoneVerificationStore, RateLimitPolicy, RateLimiter, RiskEvaluator,
+        RoleStore, SamlProviderStore, SamlReplayCache, SmsClient, TenantStore, TrustedDeviceStore,
+        TwoFAClientPolicy, TwoFACodeStore, UserStore, WebhookStore, WebhookUrlPolicy,
+    },
+    services::{
+        data_stores::{
+            hashmap_api_key_store::HashmapApiKeyStore,
+            hashmap_device_authorization_store::HashmapDeviceAuthorizationStore,
+            hashmap_federated_identity_store::HashmapFederatedIdentityStore,
+            hashmap_group_store::HashmapGroupStore,
+            hashmap_identity_provider_store::HashmapIdentityProviderStore,
+            hashmap_invitation_store::HashmapInvitationStore,
+            hashmap_magic_link_store::HashmapMagicLinkStore,
+            hashmap_oauth_client_store::HashmapOAuthClientStore,
+            hashmap_phone_verification_store::HashmapPhoneVerificationStore,
+            hashmap_role_store::HashmapRoleStore,
+            hashmap_saml_provider_store::HashmapSamlProviderStore,
+            hashmap_saml_replay_cache::HashmapSamlReplayCache,
+            hashmap_tenant_store::HashmapTenantStore,
+            hashmap_trusted_device_store::HashmapTrustedDeviceStore,
+            hashmap_webhook_store::HashmapWebhookStore, mock_risk_evaluator::MockRiskEvaluator,
+            mock_sms_client::MockSmsClient, vec_audit_log::VecAuditLog,
+        },
+        oidc_client::OidcClient,
+        sliding_window_rate_limiter::SlidingWindowRateLimiter,
+    },
+    utils::constants::{
+        DEFAULT_MAX_AUTH_AGE_SECONDS, MAGIC_LINK_MAX_REQUESTS, MAGIC_LINK_RATE_LIMIT_WINDOW_MINUTES,
+    },
+};
 
 // Using a type alias to improve readability!
 pub type UserStoreType = Arc<RwLock<dyn UserStore + Send + Sync>>;
 pub type BannedTokenStoreType = Arc<RwLock<dyn BannedTokenStore + Send + Sync>>;
 pub type TwoFACodeStoreType = Arc<RwLock<dyn TwoFACodeStore + Send + Sync>>;
 pub type EmailClientType = Arc<RwLock<dyn EmailClient + Send + Sync>>;
+pub type MagicLinkStoreType = Arc<RwLock<dyn MagicLinkStore + Send + Sync>>;
+pub type SmsClientType = Arc<RwLock<dyn SmsClient + Send + Sync>>;
+pub type PhoneVerificationStoreType = Arc<RwLock<dyn PhoneVerificationStore + Send + Sync>>;
+pub type TrustedDeviceStoreType = Arc<RwLock<dyn TrustedDeviceStore + Send + Sync>>;
+pub type RiskEvaluatorType = Arc<RwLock<dyn RiskEvaluator + Send + Sync>>;
+pub type RoleStoreType = Arc<RwLock<dyn RoleStore + Send + Sync>>;
+pub type TenantStoreType = Arc<RwLock<dyn TenantStore + Send + Sync>>;
+pub type InvitationStoreType = Arc<RwLock<dyn InvitationStore + Send + Sync>>;
+pub type ApiKeyStoreType = Arc<RwLock<dyn ApiKeyStore + Send + Sync>>;
+pub type OAuthClientStoreType = Arc<RwLock<dyn OAuthClientStore + Send + Sync>>;
+pub type RateLimiterType = Arc<RwLock<dyn RateLimiter + Send + Sync>>;
+pub type DeviceAuthorizationStoreType = Arc<RwLock<dyn DeviceAuthorizationStore + Send + Sync>>;
+pub type IdentityProviderStoreType = Arc<RwLock<dyn IdentityProviderStore + Send + Sync>>;
+pub type FederatedIdentityStoreType = Arc<RwLock<dyn FederatedIdentityStore + Send + Sync>>;
+pub type SamlProviderStoreType = Arc<RwLock<dyn SamlProviderStore + Send + Sync>>;
+pub type SamlReplayCacheType = Arc<RwLock<dyn SamlReplayCache + Send + Sync>>;
+pub type GroupStoreType = Arc<RwLock<dyn GroupStore + Send + Sync>>;
+pub type AuditLogType = Arc<RwLock<dyn AuditLog + Send + Sync>>;
+pub type WebhookStoreType = Arc<RwLock<dyn WebhookStore + Send + Sync>>;
 
 #[derive(Clone)]
 pub struct AppState {
@@ -15,6 +68,31 @@ pub struct AppState {
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
     pub email_client: EmailClientType,
+    pub magic_link_store: MagicLinkStoreType,
+    pub magic_link_rate_limiter: RateLimiterType,
+    pub sms_client: SmsClientType,
+    pub phone_verification_store: PhoneVerificationStoreType,
+    pub trusted_device_store: TrustedDeviceStoreType,
+    pub risk_evaluator: RiskEvaluatorType,
+    pub role_store: RoleStoreType,
+    pub tenant_store: TenantStoreType,
+    pub invitation_store: InvitationStoreType,
+    pub api_key_store: ApiKeyStoreType,
+    pub oauth_client_store: OAuthClientStoreType,
+    pub client_rate_limiter: RateLimiterType,
+    pub device_authorization_store: DeviceAuthorizationStoreType,
+    pub identity_provider_store: IdentityProviderStoreType,
+    pub federated_identity_store: FederatedIdentityStoreType,
+    pub saml_provider_store: SamlProviderStoreType,
+    pub saml_replay_cache: SamlReplayCacheType,
+    pub group_store: GroupStoreType,
+    pub audit_log: AuditLogType,
+    pub webhook_store: WebhookStoreType,
+    pub webhook_url_policy: WebhookUrlPolicy,
+    pub oidc_client: OidcClient,
+    pub two_fa_client_policy: TwoFAClientPolicy,
+    pub max_auth_age_seconds: i64,
+    pub admin_token: Option<Secret<String>>,
 }
 
 impl AppState {
@@ -29,6 +107,193 @@ impl AppState {
             banned_token_store,
             two_fa_code_store,
             email_client,
+            magic_link_store: Arc::new(RwLock::new(HashmapMagicLinkStore::default())),
+            magic_link_rate_limiter: Arc::new(RwLock::new(
+                SlidingWindowRateLimiter::default().with_policy(RateLimitPolicy {
+                    max_attempts: MAGIC_LINK_MAX_REQUESTS,
+                    window: chrono::Duration::minutes(MAGIC_LINK_RATE_LIMIT_WINDOW_MINUTES),
+                }),
+            )),
+            sms_client: Arc::new(RwLock::new(MockSmsClient)),
+            phone_verification_store: Arc::new(RwLock::new(
+                HashmapPhoneVerificationStore::default(),
+            )),
+            trusted_device_store: Arc::new(RwLock::new(HashmapTrustedDeviceStore::default())),
+            risk_evaluator: Arc::new(RwLock::new(MockRiskEvaluator)),
+            role_store: Arc::new(RwLock::new(HashmapRoleStore::default())),
+            tenant_store: Arc::new(RwLock::new(HashmapTenantStore::default())),
+            invitation_store: Arc::new(RwLock::new(HashmapInvitationStore::default())),
+            api_key_store: Arc::new(RwLock::new(HashmapApiKeyStore::default())),
+            oauth_client_store: Arc::new(RwLock::new(HashmapOAuthClientStore::default())),
+            client_rate_limiter: Arc::new(RwLock::new(SlidingWindowRateLimiter::default())),
+            device_authorization_store: Arc::new(RwLock::new(
+                HashmapDeviceAuthorizationStore::default(),
+            )),
+            identity_provider_store: Arc::new(RwLock::new(HashmapIdentityProviderStore::default())),
+            federated_identity_store: Arc::new(RwLock::new(
+                HashmapFederatedIdentityStore::default(),
+            )),
+            saml_provider_store: Arc::new(RwLock::new(HashmapSamlProviderStore::default())),
+            saml_replay_cache: Arc::new(RwLock::new(HashmapSamlReplayCache::default())),
+            group_store: Arc::new(RwLock::new(HashmapGroupStore::default())),
+            audit_log: Arc::new(RwLock::new(VecAuditLog::default())),
+            webhook_store: Arc::new(RwLock::new(HashmapWebhookStore::default())),
+            webhook_url_policy: WebhookUrlPolicy::default(),
+            oidc_client: OidcClient::new(reqwest::Client::new()),
+            two_fa_client_policy: TwoFAClientPolicy::default(),
+            max_auth_age_seconds: DEFAULT_MAX_AUTH_AGE_SECONDS,
+            admin_token: None,
         }
     }
+
+    // Optional stores default to in-memory implementations and can be swapped
+    // out for shared ones (e.g. Redis) when running more than one replica.
+    pub fn with_magic_link_store(mut self, magic_link_store: MagicLinkStoreType) -> Self {
+        self.magic_link_store = magic_link_store;
+        self
+    }
+
+    // Limits how many sign-in links each address can be sent
+    pub fn with_magic_link_rate_limiter(
+        mut self,
+        magic_link_rate_limiter: RateLimiterType,
+    ) -> Self {
+        self.magic_link_rate_limiter = magic_link_rate_limiter;
+        self
+    }
+
+    pub fn with_sms_client(mut self, sms_client: SmsClientType) -> Self {
+        self.sms_client = sms_client;
+        self
+    }
+
+    pub fn with_phone_verification_store(
+        mut self,
+        phone_verification_store: PhoneVerificationStoreType,
+    ) -> Self {
+        self.phone_verification_store = phone_verification_store;
+        self
+    }
+
+    pub fn with_trusted_device_store(
+        mut self,
+        trusted_device_store: TrustedDeviceStoreType,
+    ) -> Self {
+        self.trusted_device_store = trusted_device_store;
+        self
+    }
+
+    pub fn with_risk_evaluator(mut self, risk_evaluator: RiskEvaluatorType) -> Self {
+        self.risk_evaluator = risk_evaluator;
+        self
+    }
+
+    pub fn with_role_store(mut self, role_store: RoleStoreType) -> Self {
+        self.role_store = role_store;
+        self
+    }
+
+    pub fn with_tenant_store(mut self, tenant_store: TenantStoreType) -> Self {
+        self.tenant_store = tenant_store;
+        self
+    }
+
+    pub fn with_invitation_store(mut self, invitation_store: InvitationStoreType) -> Self {
+        self.invitation_store = invitation_store;
+        self
+    }
+
+    pub fn with_api_key_store(mut self, api_key_store: ApiKeyStoreType) -> Self {
+        self.api_key_store = api_key_store;
+        self
+    }
+
+    pub fn with_oauth_client_store(mut self, oauth_client_store: OAuthClientStoreType) -> Self {
+        self.oauth_client_store = oauth_client_store;
+        self
+    }
+
+    // Limits how often each OAuth client may ask for a token
+    pub fn with_client_rate_limiter(mut self, client_rate_limiter: RateLimiterType) -> Self {
+        self.client_rate_limiter = client_rate_limiter;
+        self
+    }
+
+    pub fn with_device_authorization_store(
+        mut self,
+        device_authorization_store: DeviceAuthorizationStoreType,
+    ) -> Self {
+        self.device_authorization_store = device_authorization_store;
+        self
+    }
+
+    pub fn with_identity_provider_store(
+        mut self,
+        identity_provider_store: IdentityProviderStoreType,
+    ) -> Self {
+        self.identity_provider_store = identity_provider_store;
+        self
+    }
+
+    pub fn with_federated_identity_store(
+        mut self,
+        federated_identity_store: FederatedIdentityStoreType,
+    ) -> Self {
+        self.federated_identity_store = federated_identity_store;
+        self
+    }
+
+    pub fn with_saml_provider_store(mut self, saml_provider_store: SamlProviderStoreType) -> Self {
+        self.saml_provider_store = saml_provider_store;
+        self
+    }
+
+    pub fn with_saml_replay_cache(mut self, saml_replay_cache: SamlReplayCacheType) -> Self {
+        self.saml_replay_cache = saml_replay_cache;
+        self
+    }
+
+    pub fn with_group_store(mut self, group_store: GroupStoreType) -> Self {
+        self.group_store = group_store;
+        self
+    }
+
+    pub fn with_audit_log(mut self, audit_log: AuditLogType) -> Self {
+        self.audit_log = audit_log;
+        self
+    }
+
+    pub fn with_webhook_store(mut self, webhook_store: WebhookStoreType) -> Self {
+        self.webhook_store = webhook_store;
+        self
+    }
+
+    pub fn with_webhook_url_policy(mut self, webhook_url_policy: WebhookUrlPolicy) -> Self {
+        self.webhook_url_policy = webhook_url_policy;
+        self
+    }
+
+    pub fn with_oidc_client(mut self, oidc_client: OidcClient) -> Self {
+        self.oidc_client = oidc_client;
+        self
+    }
+
+    pub fn with_two_fa_client_policy(mut self, two_fa_client_policy: TwoFAClientPolicy) -> Self {
+        self.two_fa_client_policy = two_fa_client_policy;
+        self
+    }
+
+    // How long ago the user may have last proved who they are before sensitive
+    // routes ask them to reauthenticate
+    pub fn with_max_auth_age_seconds(mut self, max_auth_age_seconds: i64) -> Self {
+        self.max_auth_age_seconds = max_auth_age_seconds;
+        self
+    }
+
+    // A static bearer token that can call the `/admin` routes, e.g. for operator
+    // scripts. Without one, only users with the `users:manage` permission can.
+    pub fn with_admin_token(mut self, admin_token: Secret<String>) -> Self {
+        self.admin_token = Some(admin_token);
+        self
+    }
 }
@@ -0,0 +1,521 @@
+use std::{collections::VecDeque, fmt::Display, net::IpAddr};
+
+use chrono::{DateTime, SecondsFormat, SubsecRound, Utc};
+use color_eyre::eyre::{eyre, Result};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use super::{ClientFingerprint, Email, TenantId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AuditEventId(Uuid);
+
+impl AuditEventId {
+    pub fn parse(id: &str) -> Result<Self> {
+        Uuid::parse_str(id)
+            .map(Self)
+            .map_err(|_| eyre!("Invalid audit event id"))
+    }
+}
+
+impl Default for AuditEventId {
+    fn default() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl From<Uuid> for AuditEventId {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl AsRef<Uuid> for AuditEventId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for AuditEventId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditOutcome {
+    Success,
+    Failure,
+}
+
+impl AuditOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Failure => "failure",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "success" => Ok(Self::Success),
+            "failure" => Ok(Self::Failure),
+            _ => Err(eyre!("Invalid audit outcome: {}", s)),
+        }
+    }
+}
+
+// What a route reports about something that happened, e.g.
+// `AuditRecord::new("login").user(&email).failure("Incorrect credentials")`. The
+// tenant, time and client are added when it is recorded.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditRecord {
+    pub event: String,
+    pub outcome: AuditOutcome,
+    pub actor: Option<String>,
+    pub user: Option<String>,
+    pub target: Option<String>,
+    pub reason: Option<String>,
+}
+
+impl AuditRecord {
+    pub fn new(event: &str) -> Self {
+        Self {
+            event: event.to_owned(),
+            outcome: AuditOutcome::Success,
+            actor: None,
+            user: None,
+            target: None,
+            reason: None,
+        }
+    }
+
+    // The user the event is about. Also the actor, unless someone else is named.
+    pub fn user(mut self, email: &Email) -> Self {
+        self.user = Some(email.as_ref().expose_secret().to_owned());
+        self
+    }
+
+    // Who did it, e.g. an admin or an OAuth client
+    pub fn actor(mut self, actor: &str) -> Self {
+        self.actor = Some(actor.to_owned());
+        self
+    }
+
+    // What else the event is about, e.g. a role or an identity provider
+    pub fn target(mut self, target: impl Display) -> Self {
+        self.target = Some(target.to_string());
+        self
+    }
+
+    pub fn reason(mut self, reason: impl Display) -> Self {
+        self.reason = Some(reason.to_string());
+        self
+    }
+
+    pub fn failure(mut self, reason: impl Display) -> Self {
+        self.outcome = AuditOutcome::Failure;
+        self.reason(reason)
+    }
+}
+
+// The hash the first chained entry links to
+pub const AUDIT_CHAIN_GENESIS_HASH: &str =
+    "0000000000000000000000000000000000000000000000000000000000000000";
+
+// An entry of the audit log
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEvent {
+    pub id: AuditEventId,
+    pub tenant: TenantId,
+    pub occurred_at: DateTime<Utc>,
+    pub event: String,
+    pub outcome: AuditOutcome,
+    pub actor: Option<String>,
+    pub user: Option<String>,
+    pub target: Option<String>,
+    pub reason: Option<String>,
+    pub ip_address: Option<IpAddr>,
+    pub user_agent: Option<String>,
+}
+
+impl AuditEvent {
+    pub fn new(tenant: TenantId, record: AuditRecord, client: &ClientFingerprint) -> Self {
+        Self {
+            id: AuditEventId::default(),
+            tenant,
+            // The precision Postgres keeps, so the event hashes the same once stored
+            occurred_at: Utc::now().trunc_subsecs(6),
+            event: record.event,
+            outcome: record.outcome,
+            actor: record.actor.or_else(|| record.user.clone()),
+            user: record.user,
+            target: record.target,
+            reason: record.reason,
+            ip_address: client.ip_address,
+            user_agent: client.user_agent.clone(),
+        }
+    }
+
+    // The hex-encoded SHA-256 of the previous entry's hash and every field of the event.
+    // The fields are hashed as a JSON array, so no two events share the same input.
+    pub fn chain_hash(&self, previous_hash: &str) -> String {
+        let fields = serde_json::json!([
+            previous_hash,
+            self.id.to_string(),
+            self.tenant.as_ref(),
+            self.occurred_at
+                .to_rfc3339_opts(SecondsFormat::Micros, true),
+            self.event,
+            self.outcome.as_str(),
+            self.actor,
+            self.user,
+            self.target,
+            self.reason,
+            self.ip_address.map(|ip_address| ip_address.to_string()),
+            self.user_agent,
+        ]);
+
+        format!("{:x}", Sha256::digest(fields.to_string().as_bytes()))
+    }
+}
+
+// An event at its place in the log, linked to the entry appended before it. Events
+// recorded before the log was chained have no hashes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditLink {
+    pub sequence: i64,
+    pub event: AuditEvent,
+    pub previous_hash: Option<String>,
+    pub hash: Option<String>,
+}
+
+impl AuditLink {
+    pub fn new(sequence: i64, event: AuditEvent, previous_hash: &str) -> Self {
+        Self {
+            sequence,
+            hash: Some(event.chain_hash(previous_hash)),
+            previous_hash: Some(previous_hash.to_owned()),
+            event,
+        }
+    }
+}
+
+// The head of the chain at some point, signed with the service's signing key so the
+// entries up to it can't be rewritten without the key
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditCheckpoint {
+    pub sequence: i64,
+    pub hash: String,
+    pub signed_at: DateTime<Utc>,
+    pub signature: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BrokenAuditLink {
+    pub sequence: i64,
+    pub reason: &'static str,
+}
+
+impl std::fmt::Display for BrokenAuditLink {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "entry {}: {}", self.sequence, self.reason)
+    }
+}
+
+// Walks the log in the order it was appended and stops at the first entry that was
+// altered, removed or slipped in. `signed` tells whether a checkpoint's signature holds.
+pub struct AuditChainVerifier<F> {
+    signed: F,
+    checkpoints: VecDeque<AuditCheckpoint>,
+    previous_hash: Option<String>,
+    pub entries: u64,
+}
+
+impl<F: Fn(&AuditCheckpoint) -> bool> AuditChainVerifier<F> {
+    // `checkpoints` oldest first
+    pub fn new(checkpoints: Vec<AuditCheckpoint>, signed: F) -> Self {
+        Self {
+            signed,
+            checkpoints: checkpoints.into(),
+            previous_hash: None,
+            entries: 0,
+        }
+    }
+
+    pub fn check(&mut self, link: &AuditLink) -> Result<(), BrokenAuditLink> {
+        let broken = |reason| BrokenAuditLink {
+            sequence: link.sequence,
+            reason,
+        };
+
+        let hash = match (&link.previous_hash, &link.hash) {
+            // Recorded before the log was chained
+            (None, None) if self.previous_hash.is_none() => return Ok(()),
+            (Some(previous_hash), Some(hash)) => {
+                let expected = self
+                    .previous_hash
+                    .as_deref()
+                    .unwrap_or(AUDIT_CHAIN_GENESIS_HASH);
+                if previous_hash != expected {
+                    return Err(broken("does not link to the entry before it"));
+                }
+                if link.event.chain_hash(previous_hash) != *hash {
+                    return Err(broken("does not match its hash"));
+                }
+                hash
+            }
+            _ => return Err(broken("is not chained")),
+        };
+
+        self.check_checkpoints_before(link.sequence)?;
+        if let Some(checkpoint) = self
+            .checkpoints
+            .pop_front_if(|checkpoint| checkpoint.sequence == link.sequence)
+        {
+            if !(self.signed)(&checkpoint) {
+                return Err(broken("has a checkpoint with an invalid signature"));
+            }
+            if checkpoint.hash != *hash {
+                return Err(broken("does not match its signed checkpoint"));
+            }
+        }
+
+        self.previous_hash = Some(hash.clone());
+        self.entries += 1;
+        Ok(())
+    }
+
+    // Checkpointed entries can't go missing, even at the end of the log
+    pub fn finish(self) -> Result<u64, BrokenAuditLink> {
+        self.check_checkpoints_before(i64::MAX)?;
+        Ok(self.entries)
+    }
+
+    fn check_checkpoints_before(&self, sequence: i64) -> Result<(), BrokenAuditLink> {
+        match self.checkpoints.front() {
+            Some(checkpoint) if checkpoint.sequence < sequence => Err(BrokenAuditLink {
+                sequence: checkpoint.sequence,
+                reason: "is missing but was checkpointed",
+            }),
+            _ => Ok(()),
+        }
+    }
+}
+
+// Filters for reading the audit log. Unset filters match everything.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct AuditQuery {
+    pub event: Option<String>,
+    pub actor: Option<String>,
+    pub user: Option<String>,
+    pub outcome: Option<AuditOutcome>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub offset: u64,
+    pub limit: u64,
+}
+
+impl AuditQuery {
+    pub fn matches(&self, event: &AuditEvent) -> bool {
+        self.event
+            .as_ref()
+            .is_none_or(|filter| *filter == event.event)
+            && (self.actor.is_none() || self.actor == event.actor)
+            && (self.user.is_none() || self.user == event.user)
+            && self.outcome.is_none_or(|outcome| outcome == event.outcome)
+            && self.since.is_none_or(|since| event.occurred_at >= since)
+            && self.until.is_none_or(|until| event.occurred_at < until)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    #[test]
+    fn the_user_is_the_actor_unless_someone_else_is_named() {
+        let email = Email::parse(Secret::new("alice@example.com".to_owned())).unwrap();
+        let client = ClientFingerprint::new(Some("10.0.0.1".parse().unwrap()), None);
+
+        let event = AuditEvent::new(
+            TenantId::default(),
+            AuditRecord::new("login").user(&email),
+            &client,
+        );
+        assert_eq!(event.actor.as_deref(), Some("alice@example.com"));
+        assert_eq!(event.ip_address, client.ip_address);
+        assert_eq!(event.outcome, AuditOutcome::Success);
+
+        let record = AuditRecord::new("sessions_revoked")
+            .user(&email)
+            .actor("admin-token")
+            .failure("User not found");
+        let event = AuditEvent::new(TenantId::default(), record, &client);
+        assert_eq!(event.actor.as_deref(), Some("admin-token"));
+        assert_eq!(event.user.as_deref(), Some("alice@example.com"));
+        assert_eq!(event.outcome, AuditOutcome::Failure);
+        assert_eq!(event.reason.as_deref(), Some("User not found"));
+    }
+
+    #[test]
+    fn queries_match_on_every_filter_set() {
+        let record = AuditRecord::new("login")
+            .actor("alice@example.com")
+            .failure("Incorrect credentials");
+        let event = AuditEvent::new(TenantId::default(), record, &ClientFingerprint::default());
+
+        assert!(AuditQuery::default().matches(&event));
+        let query = AuditQuery {
+            event: Some("login".to_owned()),
+            outcome: Some(AuditOutcome::Failure),
+            since: Some(event.occurred_at),
+            ..AuditQuery::default()
+        };
+        assert!(query.matches(&event));
+
+        assert!(!AuditQuery {
+            outcome: Some(AuditOutcome::Success),
+            ..query.clone()
+        }
+        .matches(&event));
+        assert!(!AuditQuery {
+            user: Some("alice@example.com".to_owned()),
+            ..query.clone()
+        }
+        .matches(&event));
+        assert!(!AuditQuery {
+            until: Some(event.occurred_at),
+            ..query
+        }
+        .matches(&event));
+    }
+
+    // Links `count` login events, the first at sequence 1
+    fn chain(count: i64) -> Vec<AuditLink> {
+        let mut previous_hash = AUDIT_CHAIN_GENESIS_HASH.to_owned();
+        (1..=count)
+            .map(|sequence| {
+                let record = AuditRecord::new("login").actor("alice@example.com");
+                let event =
+                    AuditEvent::new(TenantId::default(), record, &ClientFingerprint::default());
+                let link = AuditLink::new(sequence, event, &previous_hash);
+                previous_hash = link.hash.clone().unwrap();
+                link
+            })
+            .collect()
+    }
+
+    fn checkpoint(link: &AuditLink, signature: &str) -> AuditCheckpoint {
+        AuditCheckpoint {
+            sequence: link.sequence,
+            hash: link.hash.clone().unwrap(),
+            signed_at: Utc::now(),
+            signature: signature.to_owned(),
+        }
+    }
+
+    fn verify(
+        links: &[AuditLink],
+        checkpoints: Vec<AuditCheckpoint>,
+    ) -> Result<u64, BrokenAuditLink> {
+        let mut verifier =
+            AuditChainVerifier::new(checkpoints, |checkpoint| checkpoint.signature == "valid");
+        for link in links {
+            verifier.check(link)?;
+        }
+        verifier.finish()
+    }
+
+    #[test]
+    fn an_untouched_chain_verifies() {
+        let links = chain(3);
+        assert_eq!(
+            links[0].previous_hash.as_deref(),
+            Some(AUDIT_CHAIN_GENESIS_HASH)
+        );
+        assert_eq!(links[1].previous_hash, links[0].hash);
+
+        let checkpoints = vec![checkpoint(&links[1], "valid")];
+        assert_eq!(verify(&links, checkpoints), Ok(3));
+    }
+
+    #[test]
+    fn reports_the_first_altered_or_removed_entry() {
+        let mut links = chain(4);
+        links[2].event.reason = Some("edited".to_owned());
+        links[3].event.outcome = AuditOutcome::Failure;
+        assert_eq!(
+            verify(&links, vec![]),
+            Err(BrokenAuditLink {
+                sequence: 3,
+                reason: "does not match its hash",
+            })
+        );
+
+        let mut links = chain(4);
+        links.remove(1);
+        assert_eq!(verify(&links, vec![]).unwrap_err().sequence, 3);
+        assert_eq!(
+            verify(&links, vec![]).unwrap_err().reason,
+            "does not link to the entry before it"
+        );
+    }
+
+    #[test]
+    fn checkpoints_catch_a_rewritten_chain() {
+        let links = chain(3);
+        let checkpoints = vec![checkpoint(&links[1], "forged")];
+        assert_eq!(
+            verify(&links, checkpoints).unwrap_err().reason,
+            "has a checkpoint with an invalid signature"
+        );
+
+        // Recomputing every hash after an edit still disagrees with what was signed
+        let signed = checkpoint(&links[1], "valid");
+        let mut edited = links[0].event.clone();
+        edited.outcome = AuditOutcome::Failure;
+        let first = AuditLink::new(1, edited, AUDIT_CHAIN_GENESIS_HASH);
+        let second = AuditLink::new(2, links[1].event.clone(), first.hash.as_deref().unwrap());
+        assert_eq!(
+            verify(&[first, second], vec![signed.clone()]),
+            Err(BrokenAuditLink {
+                sequence: 2,
+                reason: "does not match its signed checkpoint",
+            })
+        );
+
+        // Truncating the log removes checkpointed entries
+        assert_eq!(
+            verify(&links[..1], vec![signed]),
+            Err(BrokenAuditLink {
+                sequence: 2,
+                reason: "is missing but was checkpointed",
+            })
+        );
+    }
+
+    #[test]
+    fn entries_from_before_the_chain_are_skipped() {
+        let mut links = chain(2);
+        let legacy = AuditLink {
+            sequence: 0,
+            previous_hash: None,
+            hash: None,
+            ..links[0].clone()
+        };
+        links.insert(0, legacy.clone());
+        assert_eq!(verify(&links, vec![]), Ok(2));
+
+        links.push(AuditLink {
+            sequence: 3,
+            ..legacy
+        });
+        assert_eq!(verify(&links, vec![]).unwrap_err().reason, "is not chained");
+    }
+}
@@ -0,0 +1,85 @@
+use serde::{Deserialize, Serialize};
+
+// How a user proved who they are, carried in the `amr` claim of auth tokens.
+// Values follow RFC 8176 where one exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AuthMethod {
+    #[serde(rename = "pwd")]
+    Password,
+    #[serde(rename = "otp")]
+    OneTimeCode,
+    #[serde(rename = "email")]
+    MagicLink,
+    #[serde(rename = "mfa")]
+    MultiFactor,
+    // Not in RFC 8176: the request was made with one of the user's API keys
+    #[serde(rename = "api_key")]
+    ApiKey,
+    // Not in RFC 8176: a device signed in through the device grant, approved by the
+    // user in a browser
+    #[serde(rename = "device")]
+    Device,
+    // Not in RFC 8176: the user signed in with an upstream identity provider, which
+    // decided how they had to prove who they are
+    #[serde(rename = "federated")]
+    Federated,
+}
+
+impl AuthMethod {
+    // The value in the `amr` claim
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Password => "pwd",
+            Self::OneTimeCode => "otp",
+            Self::MagicLink => "email",
+            Self::MultiFactor => "mfa",
+            Self::ApiKey => "api_key",
+            Self::Device => "device",
+            Self::Federated => "federated",
+        }
+    }
+
+    // The methods recorded when a second factor was passed on top of `first_factor`
+    pub fn with_second_factor(first_factor: AuthMethod) -> Vec<AuthMethod> {
+        vec![
+            first_factor,
+            AuthMethod::OneTimeCode,
+            AuthMethod::MultiFactor,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serializes_to_rfc_8176_values() {
+        let amr = AuthMethod::with_second_factor(AuthMethod::Password);
+        assert_eq!(
+            serde_json::to_string(&amr).unwrap(),
+            r#"["pwd","otp","mfa"]"#
+        );
+    }
+
+    #[test]
+    fn as_str_matches_the_serialized_value() {
+        for method in [
+            AuthMethod::MagicLink,
+            AuthMethod::ApiKey,
+            AuthMethod::Federated,
+        ] {
+            assert_eq!(
+                serde_json::to_string(&method).unwrap(),
+                format!("\"{}\"", method.as_str())
+            );
+        }
+    }
+
+    #[test]
+    fn round_trips_through_json() {
+        let json = r#"["email"]"#;
+        let amr: Vec<AuthMethod> = serde_json::from_str(json).unwrap();
+        assert_eq!(amr, vec![AuthMethod::MagicLink]);
+    }
+}
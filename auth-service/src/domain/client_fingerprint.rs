@@ -0,0 +1,64 @@
+use std::net::IpAddr;
+
+use serde::{Deserialize, Serialize};
+
+// What we know about the client that started a login attempt
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientFingerprint {
+    pub ip_address: Option<IpAddr>,
+    pub user_agent: Option<String>,
+}
+
+impl ClientFingerprint {
+    pub fn new(ip_address: Option<IpAddr>, user_agent: Option<String>) -> Self {
+        Self {
+            ip_address,
+            user_agent,
+        }
+    }
+}
+
+// Whether a 2FA code may be redeemed by a different client than the one that requested it
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TwoFAClientPolicy {
+    #[default]
+    AnyClient,
+    SameClient,
+}
+
+impl TwoFAClientPolicy {
+    pub fn allows(&self, expected: &ClientFingerprint, actual: &ClientFingerprint) -> bool {
+        match self {
+            Self::AnyClient => true,
+            Self::SameClient => expected == actual,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fingerprint(ip: &str, user_agent: &str) -> ClientFingerprint {
+        ClientFingerprint::new(Some(ip.parse().unwrap()), Some(user_agent.to_owned()))
+    }
+
+    #[test]
+    fn any_client_policy_allows_different_clients() {
+        let policy = TwoFAClientPolicy::AnyClient;
+        assert!(policy.allows(
+            &fingerprint("10.0.0.1", "firefox"),
+            &fingerprint("10.0.0.2", "curl")
+        ));
+    }
+
+    #[test]
+    fn same_client_policy_requires_matching_ip_and_user_agent() {
+        let policy = TwoFAClientPolicy::SameClient;
+        let original = fingerprint("10.0.0.1", "firefox");
+
+        assert!(policy.allows(&original, &fingerprint("10.0.0.1", "firefox")));
+        assert!(!policy.allows(&original, &fingerprint("10.0.0.2", "firefox")));
+        assert!(!policy.allows(&original, &fingerprint("10.0.0.1", "curl")));
+    }
+}
@@ -1,24 +1,118 @@
-use super::{Email, Password, User};
+use std::hash::Hash;
+
+use super::{
+    AccountStatus, ApiKey, ApiKeyId, AuditCheckpoint, AuditEvent, AuditLink, AuditQuery,
+    AuthMethod, ClientFingerprint, ClientId, DeviceAuthorization, DeviceCode, Email,
+    FederatedIdentity, Grants, Group, GroupId, IdentityProvider, Invitation, InvitationId,
+    OAuthClient, Password, Permission, PhoneNumber, ProviderId, Role, SamlProvider, SuspendedBy,
+    Tenant, TenantId, TrustedDevice, TrustedDeviceId, TwoFAChannel, TwoFAClientPolicy, User,
+    UserCode, Webhook, WebhookDelivery, WebhookDeliveryId, WebhookDeliveryQuery, WebhookId,
+};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Context, Report, Result};
 use rand::Rng;
 use secrecy::{ExposeSecret, Secret};
 use thiserror::Error;
-use uuid::Uuid;
-use color_eyre::eyre::{eyre, Context, Report, Result};
 
+// Users are scoped to a tenant: the same email can belong to a different user in
+// each tenant, so every method takes the tenant the user belongs to.
 #[async_trait::async_trait]
 pub trait UserStore {
     // TODO: Add the `add_user`, `get_user`, and `validate_user` methods.
     // Make sure all methods are async so we can use async user stores in the future
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError>;
-    async fn get_user(&self, email: &Email) -> Result<User, UserStoreError>;
-    async fn validate_user(&self, email: &Email, password: &Password)
+    async fn add_user(&mut self, tenant: &TenantId, user: User) -> Result<(), UserStoreError>;
+    async fn get_user(&self, tenant: &TenantId, email: &Email) -> Result<User, UserStoreError>;
+    async fn validate_user(
+        &self,
+        tenant: &TenantId,
+        email: &Email,
+        password: &Password,
+    ) -> Result<(), UserStoreError>;
+    async fn set_phone_number(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        phone_number: PhoneNumber,
+    ) -> Result<(), UserStoreError>;
+    async fn set_two_fa_channel(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        channel: TwoFAChannel,
+    ) -> Result<(), UserStoreError>;
+    async fn list_users(
+        &self,
+        tenant: &TenantId,
+        query: &UserQuery,
+    ) -> Result<UserPage, UserStoreError>;
+    // Also records when the status changed. `suspended_by` is set when `status` is
+    // `Suspended` and `None` otherwise.
+    async fn set_status(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        status: AccountStatus,
+        reason: Option<String>,
+        suspended_by: Option<SuspendedBy>,
+    ) -> Result<(), UserStoreError>;
+    async fn require_password_reset(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<(), UserStoreError>;
+    // Also clears a pending password reset
+    async fn set_password(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError>;
+    // Drops the phone number and sends 2FA codes to the user's email again
+    async fn reset_two_fa(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<(), UserStoreError>;
+    // Also removes everything that belongs to the user, like their roles and devices
+    async fn delete_user(&mut self, tenant: &TenantId, email: &Email)
         -> Result<(), UserStoreError>;
 }
 
+// A page of users ordered by email, optionally narrowed to emails containing `search`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UserQuery {
+    pub search: Option<String>,
+    pub offset: u64,
+    pub limit: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct UserPage {
+    pub users: Vec<User>,
+    // Number of users matching the search across all pages
+    pub total: u64,
+}
+
 #[async_trait::async_trait]
 pub trait BannedTokenStore {
     async fn ban_token(&mut self, token: Secret<String>) -> Result<(), BannedTokenStoreError>;
-    async fn check_if_token_is_banned(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError>;
+    async fn check_if_token_is_banned(
+        &self,
+        token: &Secret<String>,
+    ) -> Result<bool, BannedTokenStoreError>;
+    // Bans every token of a user issued at or before `issued_up_to` (a Unix timestamp),
+    // which logs them out of all their sessions at once
+    async fn ban_user_tokens(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        issued_up_to: i64,
+    ) -> Result<(), BannedTokenStoreError>;
+    async fn get_user_tokens_ban(
+        &self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<Option<i64>, BannedTokenStoreError>;
 }
 
 #[derive(Debug, Error)]
@@ -29,6 +123,9 @@ pub enum UserStoreError {
     UserNotFound,
     #[error("Invalid credentials")]
     InvalidCredentials,
+    // The user, or the whole store, is managed by a directory this service can't write to
+    #[error("User is read-only")]
+    ReadOnly,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -40,6 +137,36 @@ impl PartialEq for UserStoreError {
             (Self::UserAlreadyExists, Self::UserAlreadyExists)
                 | (Self::UserNotFound, Self::UserNotFound)
                 | (Self::InvalidCredentials, Self::InvalidCredentials)
+                | (Self::ReadOnly, Self::ReadOnly)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Tenants and their settings, looked up for every request
+#[async_trait::async_trait]
+pub trait TenantStore {
+    async fn add_tenant(&mut self, tenant: Tenant) -> Result<(), TenantStoreError>;
+    async fn get_tenant(&self, id: &TenantId) -> Result<Tenant, TenantStoreError>;
+    async fn get_tenant_by_hostname(&self, hostname: &str) -> Result<Tenant, TenantStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum TenantStoreError {
+    #[error("Tenant already exists")]
+    TenantAlreadyExists,
+    #[error("Tenant not found")]
+    TenantNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for TenantStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::TenantAlreadyExists, Self::TenantAlreadyExists)
+                | (Self::TenantNotFound, Self::TenantNotFound)
                 | (Self::UnexpectedError(_), Self::UnexpectedError(_))
         )
     }
@@ -51,26 +178,104 @@ pub enum BannedTokenStoreError {
     UnexpectedError(#[source] Report),
 }
 
-// This trait represents the interface all concrete 2FA code stores should implement
+// This trait represents the interface all concrete 2FA code stores should implement.
+// Codes are keyed by login attempt so several pending logins for one account can coexist;
+// the email is checked on every lookup so an attempt can't be redeemed for another account.
 #[async_trait::async_trait]
 pub trait TwoFACodeStore {
     async fn add_code(
         &mut self,
-        email: Email,
         login_attempt_id: LoginAttemptId,
-        code: TwoFACode,
+        attempt: TwoFAAttempt,
+    ) -> Result<(), TwoFACodeStoreError>;
+    async fn remove_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
     ) -> Result<(), TwoFACodeStoreError>;
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError>;
     async fn get_code(
         &self,
+        login_attempt_id: &LoginAttemptId,
         email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError>;
+    ) -> Result<TwoFAAttempt, TwoFACodeStoreError>;
+    // Redeems the code of a pending login attempt in one step, so a code can't be used
+    // twice. A wrong code counts against the attempt, which is dropped after
+    // `MAX_TWO_FA_FAILED_ATTEMPTS` misses. A client the policy refuses is turned away
+    // before the code is looked at, and leaves the attempt as it was.
+    async fn consume_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        email: &Email,
+        code: &TwoFACode,
+        client: &ClientFingerprint,
+        client_policy: &TwoFAClientPolicy,
+    ) -> Result<TwoFAAttempt, TwoFACodeStoreError>;
+    // Replaces the code of a pending login attempt, enforcing the store's resend policy
+    async fn resend_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        email: &Email,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError>;
+}
+
+// A pending 2FA login: who is logging in, the code we sent them and the client that asked for it
+#[derive(Debug, Clone, PartialEq)]
+pub struct TwoFAAttempt {
+    pub tenant: TenantId,
+    pub email: Email,
+    pub code: TwoFACode,
+    pub client: ClientFingerprint,
+    // The factor the user passed before being asked for the code
+    pub first_factor: AuthMethod,
+}
+
+impl TwoFAAttempt {
+    pub fn new(tenant: TenantId, email: Email, code: TwoFACode, client: ClientFingerprint) -> Self {
+        Self {
+            tenant,
+            email,
+            code,
+            client,
+            first_factor: AuthMethod::Password,
+        }
+    }
+
+    pub fn with_first_factor(mut self, first_factor: AuthMethod) -> Self {
+        self.first_factor = first_factor;
+        self
+    }
+}
+
+pub const MAX_TWO_FA_FAILED_ATTEMPTS: u32 = 5;
+
+// Limits on how often the code of a single login attempt can be re-sent
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TwoFAResendPolicy {
+    pub cooldown_seconds: i64,
+    pub max_resends: u32,
+}
+
+impl Default for TwoFAResendPolicy {
+    fn default() -> Self {
+        Self {
+            cooldown_seconds: 30,
+            max_resends: 3,
+        }
+    }
 }
 
 #[derive(Debug, Error)]
 pub enum TwoFACodeStoreError {
     #[error("Login Attempt ID not found")]
     LoginAttemptIdNotFound,
+    #[error("Code was sent too recently")]
+    ResendCooldown,
+    #[error("Code has been re-sent too many times")]
+    ResendLimitReached,
+    #[error("Incorrect code")]
+    IncorrectCode,
+    #[error("Code presented by a client the policy refuses")]
+    ClientNotAllowed,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
@@ -80,6 +285,658 @@ impl PartialEq for TwoFACodeStoreError {
         matches!(
             (self, other),
             (Self::LoginAttemptIdNotFound, Self::LoginAttemptIdNotFound)
+                | (Self::ResendCooldown, Self::ResendCooldown)
+                | (Self::ResendLimitReached, Self::ResendLimitReached)
+                | (Self::IncorrectCode, Self::IncorrectCode)
+                | (Self::ClientNotAllowed, Self::ClientNotAllowed)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// This trait represents the interface all concrete magic link stores should implement
+#[async_trait::async_trait]
+pub trait MagicLinkStore {
+    async fn add_link(
+        &mut self,
+        link_id: MagicLinkId,
+        tenant: TenantId,
+        email: Email,
+    ) -> Result<(), MagicLinkStoreError>;
+    // Links are single-use, so reading one also removes it from the store
+    async fn consume_link(
+        &mut self,
+        link_id: &MagicLinkId,
+    ) -> Result<(TenantId, Email), MagicLinkStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum MagicLinkStoreError {
+    #[error("Magic link not found")]
+    LinkNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for MagicLinkStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::LinkNotFound, Self::LinkNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+pub const PHONE_VERIFICATION_COOLDOWN_SECONDS: i64 = 60;
+pub const PHONE_VERIFICATION_TTL_SECONDS: i64 = 600;
+
+// Holds phone numbers waiting for the user to confirm the code that was texted to them
+#[async_trait::async_trait]
+pub trait PhoneVerificationStore {
+    // Each pending verification is texted to the user, so a user can only start one
+    // every `PHONE_VERIFICATION_COOLDOWN_SECONDS`, whatever became of the last one
+    async fn add_pending(
+        &mut self,
+        tenant: TenantId,
+        email: Email,
+        phone_number: PhoneNumber,
+        code: TwoFACode,
+    ) -> Result<(), PhoneVerificationStoreError>;
+    // Pending verifications are single-use, so reading one also removes it from the store.
+    // They expire after `PHONE_VERIFICATION_TTL_SECONDS`.
+    async fn take_pending(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<(PhoneNumber, TwoFACode), PhoneVerificationStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum PhoneVerificationStoreError {
+    #[error("Pending verification not found")]
+    VerificationNotFound,
+    #[error("Verification was started too recently")]
+    Cooldown,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for PhoneVerificationStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::VerificationNotFound, Self::VerificationNotFound)
+                | (Self::Cooldown, Self::Cooldown)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Browsers a user chose to remember after completing 2FA.
+// Expired devices are never reported as trusted or listed.
+#[async_trait::async_trait]
+pub trait TrustedDeviceStore {
+    async fn add_device(&mut self, device: TrustedDevice) -> Result<(), TrustedDeviceStoreError>;
+    async fn is_trusted(
+        &self,
+        tenant: &TenantId,
+        email: &Email,
+        device_id: &TrustedDeviceId,
+    ) -> Result<bool, TrustedDeviceStoreError>;
+    async fn list_devices(
+        &self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<Vec<TrustedDevice>, TrustedDeviceStoreError>;
+    async fn revoke_device(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        device_id: &TrustedDeviceId,
+    ) -> Result<(), TrustedDeviceStoreError>;
+    async fn revoke_all_devices(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<(), TrustedDeviceStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum TrustedDeviceStoreError {
+    #[error("Trusted device not found")]
+    DeviceNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for TrustedDeviceStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::DeviceNotFound, Self::DeviceNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Roles bundle permissions and are assigned to users. The grants are copied into
+// auth tokens when they are issued, so changes apply from the user's next login.
+#[async_trait::async_trait]
+pub trait RoleStore {
+    async fn add_role(
+        &mut self,
+        role: Role,
+        permissions: Vec<Permission>,
+    ) -> Result<(), RoleStoreError>;
+    // Roles are shared by all tenants, but assigning one only grants it within the
+    // user's own tenant
+    async fn assign_role(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        role: &Role,
+    ) -> Result<(), RoleStoreError>;
+    async fn unassign_role(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        role: &Role,
+    ) -> Result<(), RoleStoreError>;
+    async fn get_grants(&self, tenant: &TenantId, email: &Email) -> Result<Grants, RoleStoreError>;
+    async fn role_exists(&self, role: &Role) -> Result<bool, RoleStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum RoleStoreError {
+    #[error("Role already exists")]
+    RoleAlreadyExists,
+    #[error("Role not found")]
+    RoleNotFound,
+    #[error("Role not assigned")]
+    RoleNotAssigned,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for RoleStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::RoleAlreadyExists, Self::RoleAlreadyExists)
+                | (Self::RoleNotFound, Self::RoleNotFound)
+                | (Self::RoleNotAssigned, Self::RoleNotAssigned)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Invitations to join a tenant. They are kept after being accepted or revoked so
+// admins can see what happened to them; only pending ones can change status.
+#[async_trait::async_trait]
+pub trait InvitationStore {
+    async fn add_invitation(&mut self, invitation: Invitation) -> Result<(), InvitationStoreError>;
+    async fn get_invitation(
+        &self,
+        tenant: &TenantId,
+        id: &InvitationId,
+    ) -> Result<Invitation, InvitationStoreError>;
+    // Newest first
+    async fn list_invitations(
+        &self,
+        tenant: &TenantId,
+    ) -> Result<Vec<Invitation>, InvitationStoreError>;
+    // Fails with `InvitationNotFound` unless the invitation is pending and not expired,
+    // so an invitation can only be accepted once
+    async fn accept_invitation(
+        &mut self,
+        tenant: &TenantId,
+        id: &InvitationId,
+    ) -> Result<(), InvitationStoreError>;
+    // Fails with `InvitationNotFound` unless the invitation is pending
+    async fn revoke_invitation(
+        &mut self,
+        tenant: &TenantId,
+        id: &InvitationId,
+    ) -> Result<(), InvitationStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum InvitationStoreError {
+    #[error("Invitation not found")]
+    InvitationNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for InvitationStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::InvitationNotFound, Self::InvitationNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// API keys of users. Stores keep the key itself hashed and look keys up by their
+// visible prefix.
+#[async_trait::async_trait]
+pub trait ApiKeyStore {
+    async fn add_key(
+        &mut self,
+        api_key: ApiKey,
+        key: Secret<String>,
+    ) -> Result<(), ApiKeyStoreError>;
+    // Fails with `InvalidApiKey` unless the key is known and matches its hash. Expired
+    // keys are still returned.
+    async fn validate_key(&self, key: &Secret<String>) -> Result<ApiKey, ApiKeyStoreError>;
+    async fn list_keys(
+        &self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<Vec<ApiKey>, ApiKeyStoreError>;
+    async fn revoke_key(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        id: &ApiKeyId,
+    ) -> Result<(), ApiKeyStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum ApiKeyStoreError {
+    #[error("API key not found")]
+    ApiKeyNotFound,
+    #[error("Invalid API key")]
+    InvalidApiKey,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for ApiKeyStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::ApiKeyNotFound, Self::ApiKeyNotFound)
+                | (Self::InvalidApiKey, Self::InvalidApiKey)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait OAuthClientStore {
+    async fn add_client(
+        &mut self,
+        client: OAuthClient,
+        secret: Secret<String>,
+    ) -> Result<(), OAuthClientStoreError>;
+    // Fails with `InvalidClient` unless the client is known and the secret matches its hash
+    async fn validate_client(
+        &self,
+        id: &ClientId,
+        secret: &Secret<String>,
+    ) -> Result<OAuthClient, OAuthClientStoreError>;
+    async fn list_clients(
+        &self,
+        tenant: &TenantId,
+    ) -> Result<Vec<OAuthClient>, OAuthClientStoreError>;
+    async fn delete_client(
+        &mut self,
+        tenant: &TenantId,
+        id: &ClientId,
+    ) -> Result<(), OAuthClientStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum OAuthClientStoreError {
+    #[error("Client not found")]
+    ClientNotFound,
+    #[error("Invalid client")]
+    InvalidClient,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for OAuthClientStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::ClientNotFound, Self::ClientNotFound)
+                | (Self::InvalidClient, Self::InvalidClient)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Authorizations are only kept until they expire, after which they are not found
+#[async_trait::async_trait]
+pub trait DeviceAuthorizationStore {
+    async fn add_authorization(
+        &mut self,
+        authorization: DeviceAuthorization,
+    ) -> Result<(), DeviceAuthorizationStoreError>;
+    async fn get_by_device_code(
+        &self,
+        device_code: &DeviceCode,
+    ) -> Result<DeviceAuthorization, DeviceAuthorizationStoreError>;
+    async fn get_by_user_code(
+        &self,
+        user_code: &UserCode,
+    ) -> Result<DeviceAuthorization, DeviceAuthorizationStoreError>;
+    // Saves changes to an authorization that is still stored, keeping its expiry
+    async fn update_authorization(
+        &mut self,
+        authorization: DeviceAuthorization,
+    ) -> Result<(), DeviceAuthorizationStoreError>;
+    async fn remove_authorization(
+        &mut self,
+        authorization: &DeviceAuthorization,
+    ) -> Result<(), DeviceAuthorizationStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum DeviceAuthorizationStoreError {
+    #[error("Device authorization not found")]
+    AuthorizationNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for DeviceAuthorizationStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::AuthorizationNotFound, Self::AuthorizationNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait IdentityProviderStore {
+    async fn add_provider(
+        &mut self,
+        provider: IdentityProvider,
+    ) -> Result<(), IdentityProviderStoreError>;
+    async fn get_provider(
+        &self,
+        tenant: &TenantId,
+        id: &ProviderId,
+    ) -> Result<IdentityProvider, IdentityProviderStoreError>;
+    async fn list_providers(
+        &self,
+        tenant: &TenantId,
+    ) -> Result<Vec<IdentityProvider>, IdentityProviderStoreError>;
+    async fn delete_provider(
+        &mut self,
+        tenant: &TenantId,
+        id: &ProviderId,
+    ) -> Result<(), IdentityProviderStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum IdentityProviderStoreError {
+    #[error("Identity provider already exists")]
+    ProviderAlreadyExists,
+    #[error("Identity provider not found")]
+    ProviderNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for IdentityProviderStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::ProviderAlreadyExists, Self::ProviderAlreadyExists)
+                | (Self::ProviderNotFound, Self::ProviderNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait FederatedIdentityStore {
+    async fn add_identity(
+        &mut self,
+        identity: FederatedIdentity,
+    ) -> Result<(), FederatedIdentityStoreError>;
+    async fn get_identity(
+        &self,
+        tenant: &TenantId,
+        provider: &ProviderId,
+        subject: &str,
+    ) -> Result<FederatedIdentity, FederatedIdentityStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum FederatedIdentityStoreError {
+    #[error("Federated identity not found")]
+    IdentityNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for FederatedIdentityStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::IdentityNotFound, Self::IdentityNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+#[async_trait::async_trait]
+pub trait SamlProviderStore {
+    async fn add_provider(&mut self, provider: SamlProvider) -> Result<(), SamlProviderStoreError>;
+    async fn get_provider(
+        &self,
+        tenant: &TenantId,
+        id: &ProviderId,
+    ) -> Result<SamlProvider, SamlProviderStoreError>;
+    async fn list_providers(
+        &self,
+        tenant: &TenantId,
+    ) -> Result<Vec<SamlProvider>, SamlProviderStoreError>;
+    async fn delete_provider(
+        &mut self,
+        tenant: &TenantId,
+        id: &ProviderId,
+    ) -> Result<(), SamlProviderStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum SamlProviderStoreError {
+    #[error("SAML provider already exists")]
+    ProviderAlreadyExists,
+    #[error("SAML provider not found")]
+    ProviderNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for SamlProviderStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::ProviderAlreadyExists, Self::ProviderAlreadyExists)
+                | (Self::ProviderNotFound, Self::ProviderNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Remembers the SAML assertions users have signed in with, so a captured assertion
+// can't be used again while it's still valid
+#[async_trait::async_trait]
+pub trait SamlReplayCache {
+    // Fails with `AssertionReplayed` if the provider's assertion was seen before. It's
+    // only remembered until `expires_at`, after which it's refused as expired anyway.
+    async fn record_assertion(
+        &mut self,
+        tenant: &TenantId,
+        provider: &ProviderId,
+        assertion_id: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), SamlReplayCacheError>;
+}
+
+#[derive(Debug, Error)]
+pub enum SamlReplayCacheError {
+    #[error("Assertion was already used")]
+    AssertionReplayed,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for SamlReplayCacheError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::AssertionReplayed, Self::AssertionReplayed)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Groups of users pushed by a provisioning system. Members are users of the group's
+// tenant, and leave their groups when they're deleted.
+#[async_trait::async_trait]
+pub trait GroupStore {
+    async fn add_group(&mut self, group: Group) -> Result<(), GroupStoreError>;
+    async fn get_group(&self, tenant: &TenantId, id: &GroupId) -> Result<Group, GroupStoreError>;
+    // Ordered by display name
+    async fn list_groups(&self, tenant: &TenantId) -> Result<Vec<Group>, GroupStoreError>;
+    // Replaces the display name and members of the group with the same id
+    async fn update_group(&mut self, group: Group) -> Result<(), GroupStoreError>;
+    async fn delete_group(
+        &mut self,
+        tenant: &TenantId,
+        id: &GroupId,
+    ) -> Result<(), GroupStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum GroupStoreError {
+    // Another group of the tenant has the display name
+    #[error("Group already exists")]
+    GroupAlreadyExists,
+    #[error("Group not found")]
+    GroupNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for GroupStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::GroupAlreadyExists, Self::GroupAlreadyExists)
+                | (Self::GroupNotFound, Self::GroupNotFound)
+                | (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// The security audit log. Events can only be appended, never changed or removed. Each
+// one is chained to the event appended before it, whatever its tenant.
+#[async_trait::async_trait]
+pub trait AuditLog {
+    async fn append(&mut self, event: AuditEvent) -> Result<(), AuditLogError>;
+    // Events of the tenant matching the query, newest first
+    async fn query(
+        &self,
+        tenant: &TenantId,
+        query: &AuditQuery,
+    ) -> Result<Vec<AuditEvent>, AuditLogError>;
+    // Up to `limit` entries after `after_sequence`, in the order they were appended
+    async fn chain(&self, after_sequence: i64, limit: u64)
+        -> Result<Vec<AuditLink>, AuditLogError>;
+    // The last chained entry
+    async fn chain_head(&self) -> Result<Option<AuditLink>, AuditLogError>;
+    async fn add_checkpoint(&mut self, checkpoint: AuditCheckpoint) -> Result<(), AuditLogError>;
+    // Oldest first
+    async fn checkpoints(&self) -> Result<Vec<AuditCheckpoint>, AuditLogError>;
+    async fn latest_checkpoint(&self) -> Result<Option<AuditCheckpoint>, AuditLogError>;
+}
+
+#[derive(Debug, Error)]
+pub enum AuditLogError {
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for AuditLogError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::UnexpectedError(_), Self::UnexpectedError(_))
+        )
+    }
+}
+
+// Webhook subscriptions and the queue of deliveries to them
+#[async_trait::async_trait]
+pub trait WebhookStore {
+    async fn add_webhook(&mut self, webhook: Webhook) -> Result<(), WebhookStoreError>;
+    async fn get_webhook(
+        &self,
+        tenant: &TenantId,
+        id: &WebhookId,
+    ) -> Result<Webhook, WebhookStoreError>;
+    // Oldest first
+    async fn list_webhooks(&self, tenant: &TenantId) -> Result<Vec<Webhook>, WebhookStoreError>;
+    // Its deliveries go with it
+    async fn delete_webhook(
+        &mut self,
+        tenant: &TenantId,
+        id: &WebhookId,
+    ) -> Result<(), WebhookStoreError>;
+    async fn add_delivery(&mut self, delivery: WebhookDelivery) -> Result<(), WebhookStoreError>;
+    async fn get_delivery(
+        &self,
+        tenant: &TenantId,
+        id: &WebhookDeliveryId,
+    ) -> Result<WebhookDelivery, WebhookStoreError>;
+    // Deliveries of the tenant matching the query, newest first
+    async fn list_deliveries(
+        &self,
+        tenant: &TenantId,
+        query: &WebhookDeliveryQuery,
+    ) -> Result<Vec<WebhookDelivery>, WebhookStoreError>;
+    // Up to `limit` pending deliveries of any tenant that are due at `now`. Their next
+    // attempt is pushed back by `lease` so no other worker picks them up in the meantime.
+    async fn claim_due_deliveries(
+        &mut self,
+        now: DateTime<Utc>,
+        lease: chrono::Duration,
+        limit: u64,
+    ) -> Result<Vec<WebhookDelivery>, WebhookStoreError>;
+    async fn update_delivery(
+        &mut self,
+        delivery: &WebhookDelivery,
+    ) -> Result<(), WebhookStoreError>;
+}
+
+#[derive(Debug, Error)]
+pub enum WebhookStoreError {
+    #[error("Webhook not found")]
+    WebhookNotFound,
+    #[error("Webhook delivery not found")]
+    DeliveryNotFound,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+impl PartialEq for WebhookStoreError {
+    fn eq(&self, other: &Self) -> bool {
+        matches!(
+            (self, other),
+            (Self::WebhookNotFound, Self::WebhookNotFound)
+                | (Self::DeliveryNotFound, Self::DeliveryNotFound)
                 | (Self::UnexpectedError(_), Self::UnexpectedError(_))
         )
     }
@@ -94,6 +951,14 @@ impl PartialEq for LoginAttemptId {
     }
 }
 
+impl Hash for LoginAttemptId {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.expose_secret().hash(state);
+    }
+}
+
+impl Eq for LoginAttemptId {}
+
 impl LoginAttemptId {
     pub fn parse(id: Secret<String>) -> Result<Self> {
         let id = uuid::Uuid::parse_str(id.expose_secret())
@@ -119,7 +984,10 @@ pub struct TwoFACode(Secret<String>);
 
 impl TwoFACode {
     pub fn parse(code: Secret<String>) -> Result<Self> {
-        let code_as_u32 = code.expose_secret().parse::<u32>().wrap_err("Invalid 2FA code")?;
+        let code_as_u32 = code
+            .expose_secret()
+            .parse::<u32>()
+            .wrap_err("Invalid 2FA code")?;
 
         if (100_000..=999_999).contains(&code_as_u32) {
             Ok(Self(code))
@@ -148,3 +1016,32 @@ impl AsRef<Secret<String>> for TwoFACode {
         &self.0
     }
 }
+
+#[derive(Debug, Clone)]
+pub struct MagicLinkId(Secret<String>);
+
+impl PartialEq for MagicLinkId {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl MagicLinkId {
+    pub fn parse(id: Secret<String>) -> Result<Self> {
+        let id = uuid::Uuid::parse_str(id.expose_secret())
+            .map_err(|_| eyre!("Invalid magic link id"))?;
+        Ok(Self(Secret::new(id.to_string())))
+    }
+}
+
+impl Default for MagicLinkId {
+    fn default() -> Self {
+        Self(Secret::new(uuid::Uuid::new_v4().to_string()))
+    }
+}
+
+impl AsRef<Secret<String>> for MagicLinkId {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
@@ -0,0 +1,271 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+use rand::{distributions::Alphanumeric, Rng};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use super::{ClientId, Email, TenantId};
+
+const DEVICE_CODE_LENGTH: usize = 40;
+// Consonants only, so user codes can't spell words and are easy to read out, as
+// RFC 8628 suggests
+const USER_CODE_CHARSET: &[u8] = b"BCDFGHJKLMNPQRSTVWXZ";
+const USER_CODE_LENGTH: usize = 8;
+// How much longer a device has to wait between polls each time it polls too fast
+const SLOW_DOWN_SECONDS: i64 = 5;
+
+// The code a device polls `/token` with. It never leaves the device, unlike the user code.
+#[derive(Debug, Clone)]
+pub struct DeviceCode(Secret<String>);
+
+impl DeviceCode {
+    pub fn parse(code: Secret<String>) -> Result<Self> {
+        let is_valid = code.expose_secret().len() == DEVICE_CODE_LENGTH
+            && code
+                .expose_secret()
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric());
+
+        if is_valid {
+            Ok(Self(code))
+        } else {
+            Err(eyre!("Invalid device code"))
+        }
+    }
+}
+
+impl PartialEq for DeviceCode {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl Default for DeviceCode {
+    fn default() -> Self {
+        let code = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(DEVICE_CODE_LENGTH)
+            .map(char::from)
+            .collect();
+        Self(Secret::new(code))
+    }
+}
+
+impl AsRef<Secret<String>> for DeviceCode {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+// The code the user types into the approval page, shown as e.g. `WDJB-MJHT`. Parsing
+// ignores case, spaces and dashes, since people type it in by hand.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct UserCode(String);
+
+impl UserCode {
+    pub fn parse(code: String) -> Result<Self> {
+        let code: String = code
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .map(|c| c.to_ascii_uppercase())
+            .collect();
+
+        let is_valid =
+            code.len() == USER_CODE_LENGTH && code.bytes().all(|c| USER_CODE_CHARSET.contains(&c));
+
+        if is_valid {
+            Ok(Self(code))
+        } else {
+            Err(eyre!("Invalid user code"))
+        }
+    }
+}
+
+impl Default for UserCode {
+    fn default() -> Self {
+        let mut rng = rand::thread_rng();
+        let code = (0..USER_CODE_LENGTH)
+            .map(|_| USER_CODE_CHARSET[rng.gen_range(0..USER_CODE_CHARSET.len())] as char)
+            .collect();
+        Self(code)
+    }
+}
+
+// Without the dash, which is only for display
+impl AsRef<str> for UserCode {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for UserCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (first, second) = self.0.split_at(USER_CODE_LENGTH / 2);
+        write!(f, "{}-{}", first, second)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeviceAuthorizationStatus {
+    Pending,
+    Approved,
+    Denied,
+}
+
+impl DeviceAuthorizationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Approved => "approved",
+            Self::Denied => "denied",
+        }
+    }
+
+    pub fn parse(status: &str) -> Result<Self> {
+        match status {
+            "pending" => Ok(Self::Pending),
+            "approved" => Ok(Self::Approved),
+            "denied" => Ok(Self::Denied),
+            _ => Err(eyre!(
+                "{} is not a valid device authorization status.",
+                status
+            )),
+        }
+    }
+}
+
+// A sign-in started on a device without a browser (RFC 8628). The device shows the
+// user code, the user approves it in a browser where they are logged in, and the
+// device, which has been polling with the device code, then gets a token for them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceAuthorization {
+    pub device_code: DeviceCode,
+    pub user_code: UserCode,
+    pub client_id: ClientId,
+    pub tenant: TenantId,
+    pub status: DeviceAuthorizationStatus,
+    // Who approved or denied the request
+    pub user: Option<Email>,
+    // How long the device has to wait between polls
+    pub interval_seconds: i64,
+    pub last_polled_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl DeviceAuthorization {
+    pub fn new(
+        client_id: ClientId,
+        tenant: TenantId,
+        interval_seconds: i64,
+        ttl: chrono::Duration,
+    ) -> Self {
+        Self {
+            device_code: DeviceCode::default(),
+            user_code: UserCode::default(),
+            client_id,
+            tenant,
+            status: DeviceAuthorizationStatus::Pending,
+            user: None,
+            interval_seconds,
+            last_polled_at: None,
+            expires_at: Utc::now() + ttl,
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        Utc::now() >= self.expires_at
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.status == DeviceAuthorizationStatus::Pending && !self.is_expired()
+    }
+
+    // Records a poll from the device. Returns false when it came sooner than the interval
+    // allows, in which case the device has to wait longer from now on.
+    pub fn record_poll(&mut self, now: DateTime<Utc>) -> bool {
+        let too_soon = self
+            .last_polled_at
+            .is_some_and(|last| (now - last).num_seconds() < self.interval_seconds);
+
+        self.last_polled_at = Some(now);
+        if too_soon {
+            self.interval_seconds += SLOW_DOWN_SECONDS;
+        }
+
+        !too_soon
+    }
+
+    pub fn decide(&mut self, user: Email, approved: bool) {
+        self.user = Some(user);
+        self.status = if approved {
+            DeviceAuthorizationStatus::Approved
+        } else {
+            DeviceAuthorizationStatus::Denied
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn authorization() -> DeviceAuthorization {
+        DeviceAuthorization::new(
+            ClientId::parse("cli".to_owned()).unwrap(),
+            TenantId::default(),
+            5,
+            chrono::Duration::minutes(15),
+        )
+    }
+
+    #[test]
+    fn user_code_parses_as_typed_by_hand() {
+        let code = UserCode::default();
+        let displayed = code.to_string();
+        assert_eq!(displayed.len(), USER_CODE_LENGTH + 1);
+
+        assert_eq!(UserCode::parse(displayed.to_lowercase()).unwrap(), code);
+        assert_eq!(
+            UserCode::parse(format!(" {} ", code.as_ref())).unwrap(),
+            code
+        );
+        assert!(UserCode::parse("ABCD-EFGH".to_owned()).is_err());
+        assert!(UserCode::parse("BCDF".to_owned()).is_err());
+    }
+
+    #[test]
+    fn device_code_round_trips() {
+        let code = DeviceCode::default();
+        assert_eq!(DeviceCode::parse(code.as_ref().clone()).unwrap(), code);
+        assert!(DeviceCode::parse(Secret::new("short".to_owned())).is_err());
+    }
+
+    #[test]
+    fn polling_too_fast_slows_the_device_down() {
+        let mut authorization = authorization();
+        let now = Utc::now();
+
+        assert!(authorization.record_poll(now));
+        assert!(!authorization.record_poll(now + chrono::Duration::seconds(1)));
+        assert_eq!(authorization.interval_seconds, 10);
+
+        // The wait is counted from the last poll, even a rejected one
+        assert!(!authorization.record_poll(now + chrono::Duration::seconds(6)));
+        assert!(authorization.record_poll(now + chrono::Duration::seconds(21)));
+        assert_eq!(authorization.interval_seconds, 15);
+    }
+
+    #[test]
+    fn decided_authorization_is_no_longer_pending() {
+        let mut authorization = authorization();
+        assert!(authorization.is_pending());
+
+        let email = Email::parse(Secret::new("device@example.com".to_owned())).unwrap();
+        authorization.decide(email.clone(), false);
+
+        assert_eq!(authorization.status, DeviceAuthorizationStatus::Denied);
+        assert_eq!(authorization.user, Some(email));
+        assert!(!authorization.is_pending());
+    }
+}
@@ -32,6 +32,14 @@ impl Email {
             )))
         }
     }
+
+    // The part after the `@`
+    pub fn domain(&self) -> Option<&str> {
+        self.0
+            .expose_secret()
+            .rsplit_once('@')
+            .map(|(_, domain)| domain)
+    }
 }
 
 impl AsRef<Secret<String>> for Email {
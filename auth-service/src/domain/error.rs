@@ -1,6 +1,8 @@
 use color_eyre::eyre::Report;
 use thiserror::Error;
 
+use super::AccountStatus;
+
 #[derive(Debug, Error)]
 pub enum AuthAPIError {
     #[error("User already exists")]
@@ -13,6 +15,126 @@ pub enum AuthAPIError {
     MissingToken,
     #[error("Invalid token")]
     InvalidToken,
+    #[error("Phone number not verified")]
+    PhoneNumberNotVerified,
+    #[error("Too many requests")]
+    TooManyRequests,
+    #[error("Trusted device not found")]
+    TrustedDeviceNotFound,
+    #[error("Reauthentication required")]
+    ReauthenticationRequired,
+    #[error("Missing permission")]
+    MissingPermission,
+    #[error("API keys can't be used here")]
+    ApiKeyNotAllowed,
+    #[error("User not found")]
+    UserNotFound,
+    #[error("Role not found")]
+    RoleNotFound,
+    #[error("Account is not active")]
+    AccountNotActive(AccountStatus),
+    #[error("Password reset required")]
+    PasswordResetRequired,
+    #[error("Tenant not found")]
+    TenantNotFound,
+    #[error("Email domain not allowed")]
+    EmailDomainNotAllowed,
+    #[error("Invitation not found")]
+    InvitationNotFound,
+    #[error("API key not found")]
+    ApiKeyNotFound,
+    #[error("OAuth client not found")]
+    OAuthClientNotFound,
+    #[error("Device code not found")]
+    DeviceCodeNotFound,
+    #[error("Identity provider not found")]
+    IdentityProviderNotFound,
+    #[error("Identity provider already exists")]
+    IdentityProviderAlreadyExists,
+    #[error("Federated login failed")]
+    FederatedLoginFailed,
+    #[error("No account for this identity")]
+    FederatedIdentityNotLinked,
+    #[error("Sign-up is disabled")]
+    SignupDisabled,
+    #[error("Account is managed by the directory")]
+    ManagedByDirectory,
+    #[error("Webhook not found")]
+    WebhookNotFound,
+    #[error("Webhook delivery not found")]
+    WebhookDeliveryNotFound,
     #[error("Unexpected error")]
     UnexpectedError(#[source] Report),
 }
+
+// Errors of the `/token` endpoint. OAuth clients expect these in the format of
+// RFC 6749 rather than as an `ErrorResponse`.
+#[derive(Debug, Error)]
+pub enum OAuthError {
+    #[error("Invalid request: {0}")]
+    InvalidRequest(&'static str),
+    #[error("Invalid client")]
+    InvalidClient,
+    #[error("Invalid scope")]
+    InvalidScope,
+    #[error("Unsupported grant type")]
+    UnsupportedGrantType,
+    #[error("Invalid grant")]
+    InvalidGrant,
+    // The device grant's answers while the device waits for the user (RFC 8628)
+    #[error("Authorization pending")]
+    AuthorizationPending,
+    #[error("Slow down")]
+    SlowDown,
+    #[error("Access denied")]
+    AccessDenied,
+    #[error("Expired token")]
+    ExpiredToken,
+    #[error("Too many requests")]
+    TooManyRequests,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+// Errors of the `/scim/v2` endpoints, which provisioning clients expect as SCIM error
+// messages (RFC 7644, section 3.12)
+#[derive(Debug, Error)]
+pub enum ScimError {
+    #[error("Invalid filter: {0}")]
+    InvalidFilter(&'static str),
+    #[error("Invalid syntax: {0}")]
+    InvalidSyntax(String),
+    #[error("Invalid path")]
+    InvalidPath,
+    #[error("No target")]
+    NoTarget,
+    #[error("Invalid value: {0}")]
+    InvalidValue(&'static str),
+    #[error("Mutability: {0}")]
+    Mutability(&'static str),
+    #[error("Uniqueness: {0}")]
+    Uniqueness(&'static str),
+    #[error("Not found: {0}")]
+    NotFound(&'static str),
+    #[error("Unauthorized")]
+    Unauthorized,
+    #[error("Forbidden")]
+    Forbidden,
+    #[error("Unexpected error")]
+    UnexpectedError(#[source] Report),
+}
+
+// Provisioning clients authenticate like any other caller of the admin API
+impl From<AuthAPIError> for ScimError {
+    fn from(e: AuthAPIError) -> Self {
+        match e {
+            AuthAPIError::MissingToken | AuthAPIError::InvalidToken => ScimError::Unauthorized,
+            AuthAPIError::MissingPermission | AuthAPIError::AccountNotActive(_) => {
+                ScimError::Forbidden
+            }
+            AuthAPIError::TenantNotFound => ScimError::NotFound("Tenant not found"),
+            AuthAPIError::UnexpectedError(e) => ScimError::UnexpectedError(e),
+            e => ScimError::UnexpectedError(e.into()),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+use secrecy::ExposeSecret;
+use uuid::Uuid;
+
+use super::{Email, TenantId};
+
+const MAX_DISPLAY_NAME_LENGTH: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct GroupId(Uuid);
+
+impl GroupId {
+    pub fn parse(id: &str) -> Result<Self> {
+        Uuid::parse_str(id)
+            .map(Self)
+            .map_err(|_| eyre!("Invalid group id"))
+    }
+}
+
+impl Default for GroupId {
+    fn default() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl From<Uuid> for GroupId {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl AsRef<Uuid> for GroupId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for GroupId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+// A group of users of a tenant, as kept in sync by a provisioning system like an HR
+// system. Display names are unique within the tenant.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Group {
+    pub id: GroupId,
+    pub tenant: TenantId,
+    pub display_name: String,
+    // Sorted and free of duplicates
+    pub members: Vec<Email>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Group {
+    pub fn new(tenant: TenantId, display_name: String, members: Vec<Email>) -> Result<Self> {
+        let mut group = Self {
+            id: GroupId::default(),
+            tenant,
+            display_name: String::new(),
+            members: Vec::new(),
+            created_at: Utc::now(),
+        };
+        group.set_display_name(display_name)?;
+        group.set_members(members);
+
+        Ok(group)
+    }
+
+    pub fn set_display_name(&mut self, display_name: String) -> Result<()> {
+        let display_name = display_name.trim();
+        if display_name.is_empty() || display_name.len() > MAX_DISPLAY_NAME_LENGTH {
+            return Err(eyre!("{} is not a valid group name.", display_name));
+        }
+
+        self.display_name = display_name.to_owned();
+        Ok(())
+    }
+
+    pub fn set_members(&mut self, mut members: Vec<Email>) {
+        members.sort_by(|a, b| a.as_ref().expose_secret().cmp(b.as_ref().expose_secret()));
+        members.dedup();
+        self.members = members;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn email(s: &str) -> Email {
+        Email::parse(Secret::new(s.to_owned())).unwrap()
+    }
+
+    #[test]
+    fn members_are_sorted_and_deduplicated() {
+        let group = Group::new(
+            TenantId::default(),
+            " Engineering ".to_owned(),
+            vec![
+                email("bob@example.com"),
+                email("alice@example.com"),
+                email("bob@example.com"),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(group.display_name, "Engineering");
+        assert_eq!(
+            group.members,
+            vec![email("alice@example.com"), email("bob@example.com")]
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_display_names() {
+        assert!(Group::new(TenantId::default(), "  ".to_owned(), vec![]).is_err());
+        assert!(Group::new(TenantId::default(), "x".repeat(257), vec![]).is_err());
+    }
+}
@@ -0,0 +1,136 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+use secrecy::Secret;
+
+use super::{Email, TenantId};
+
+const MAX_PROVIDER_ID_LENGTH: usize = 64;
+
+// Names a provider in URLs, e.g. `/oidc/okta/login`, so it is kept to lowercase
+// letters, digits and dashes
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ProviderId(String);
+
+impl ProviderId {
+    pub fn parse(s: String) -> Result<Self> {
+        let is_valid = !s.is_empty()
+            && s.len() <= MAX_PROVIDER_ID_LENGTH
+            && s.chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+
+        if is_valid {
+            Ok(Self(s))
+        } else {
+            Err(eyre!("{} is not a valid identity provider id.", s))
+        }
+    }
+}
+
+impl AsRef<str> for ProviderId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// An upstream OpenID Connect provider users of a tenant can sign in with, e.g. their
+// company's IdP. Everything else about it is found through discovery on the issuer.
+#[derive(Debug, Clone)]
+pub struct IdentityProvider {
+    pub id: ProviderId,
+    pub tenant: TenantId,
+    // Shown on the sign-in button
+    pub name: String,
+    pub issuer: String,
+    pub client_id: String,
+    // Sent to the provider's token endpoint, so unlike our own secrets it can't be hashed
+    pub client_secret: Secret<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl IdentityProvider {
+    pub fn new(
+        id: ProviderId,
+        tenant: TenantId,
+        name: String,
+        issuer: String,
+        client_id: String,
+        client_secret: Secret<String>,
+    ) -> Result<Self> {
+        // Issuers are compared exactly with the `iss` of ID tokens, which never ends
+        // with a slash
+        let issuer = issuer.trim().trim_end_matches('/').to_owned();
+        if !(issuer.starts_with("https://") || issuer.starts_with("http://")) {
+            return Err(eyre!("{} is not a valid issuer URL.", issuer));
+        }
+
+        if client_id.is_empty() {
+            return Err(eyre!("Client id must not be empty."));
+        }
+
+        Ok(Self {
+            id,
+            tenant,
+            name,
+            issuer,
+            client_id,
+            client_secret,
+            created_at: Utc::now(),
+        })
+    }
+}
+
+// Links an account at a provider, identified by its `sub`, to a user of ours. Once
+// linked, the user is found by the link even if their email at the provider changes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FederatedIdentity {
+    pub tenant: TenantId,
+    pub provider: ProviderId,
+    pub subject: String,
+    pub email: Email,
+    pub created_at: DateTime<Utc>,
+}
+
+impl FederatedIdentity {
+    pub fn new(tenant: TenantId, provider: ProviderId, subject: String, email: Email) -> Self {
+        Self {
+            tenant,
+            provider,
+            subject,
+            email,
+            created_at: Utc::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn provider(issuer: &str) -> Result<IdentityProvider> {
+        IdentityProvider::new(
+            ProviderId::parse("corp".to_owned()).unwrap(),
+            TenantId::default(),
+            "Corp SSO".to_owned(),
+            issuer.to_owned(),
+            "auth-service".to_owned(),
+            Secret::new("secret".to_owned()),
+        )
+    }
+
+    #[test]
+    fn provider_id_must_be_url_safe() {
+        assert!(ProviderId::parse("okta-prod".to_owned()).is_ok());
+        assert!(ProviderId::parse("".to_owned()).is_err());
+        assert!(ProviderId::parse("Okta".to_owned()).is_err());
+        assert!(ProviderId::parse("okta/prod".to_owned()).is_err());
+        assert!(ProviderId::parse("a".repeat(MAX_PROVIDER_ID_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn issuer_is_normalized_and_validated() {
+        let provider = provider(" https://idp.example.com/ ").unwrap();
+        assert_eq!(provider.issuer, "https://idp.example.com");
+
+        assert!(self::provider("idp.example.com").is_err());
+    }
+}
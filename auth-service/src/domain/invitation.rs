@@ -0,0 +1,150 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{Email, Role, TenantId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InvitationId(Uuid);
+
+impl InvitationId {
+    pub fn parse(id: &str) -> Result<Self> {
+        Uuid::parse_str(id)
+            .map(Self)
+            .map_err(|_| eyre!("Invalid invitation id"))
+    }
+}
+
+impl Default for InvitationId {
+    fn default() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl From<Uuid> for InvitationId {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl AsRef<Uuid> for InvitationId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for InvitationId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InvitationStatus {
+    #[default]
+    Pending,
+    Accepted,
+    Revoked,
+    // Never stored: a pending invitation past its expiry reads as expired
+    Expired,
+}
+
+impl InvitationStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            InvitationStatus::Pending => "pending",
+            InvitationStatus::Accepted => "accepted",
+            InvitationStatus::Revoked => "revoked",
+            InvitationStatus::Expired => "expired",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "pending" => Ok(InvitationStatus::Pending),
+            "accepted" => Ok(InvitationStatus::Accepted),
+            "revoked" => Ok(InvitationStatus::Revoked),
+            "expired" => Ok(InvitationStatus::Expired),
+            _ => Err(eyre!("{} is not a valid invitation status", s)),
+        }
+    }
+}
+
+// An admin's invitation for someone to join their tenant, optionally with a role
+// that is granted once the invitation is accepted
+#[derive(Debug, Clone, PartialEq)]
+pub struct Invitation {
+    pub id: InvitationId,
+    pub tenant: TenantId,
+    pub email: Email,
+    pub role: Option<Role>,
+    // Who sent the invitation, for the audit trail
+    pub invited_by: String,
+    pub status: InvitationStatus,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Invitation {
+    pub fn new(
+        tenant: TenantId,
+        email: Email,
+        role: Option<Role>,
+        invited_by: String,
+        ttl: chrono::Duration,
+    ) -> Self {
+        let created_at = Utc::now();
+        Self {
+            id: InvitationId::default(),
+            tenant,
+            email,
+            role,
+            invited_by,
+            status: InvitationStatus::Pending,
+            created_at,
+            expires_at: created_at + ttl,
+        }
+    }
+
+    pub fn current_status(&self) -> InvitationStatus {
+        match self.status {
+            InvitationStatus::Pending if Utc::now() >= self.expires_at => InvitationStatus::Expired,
+            status => status,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn invitation(ttl: chrono::Duration) -> Invitation {
+        let email = Email::parse(Secret::new("invitee@example.com".to_owned())).unwrap();
+        Invitation::new(
+            TenantId::default(),
+            email,
+            None,
+            "admin-token".to_owned(),
+            ttl,
+        )
+    }
+
+    #[test]
+    fn pending_invitations_expire() {
+        let invitation = invitation(chrono::Duration::days(7));
+        assert_eq!(invitation.current_status(), InvitationStatus::Pending);
+
+        let invitation = self::invitation(chrono::Duration::seconds(-1));
+        assert_eq!(invitation.current_status(), InvitationStatus::Expired);
+
+        let revoked = Invitation {
+            status: InvitationStatus::Revoked,
+            ..invitation
+        };
+        assert_eq!(revoked.current_status(), InvitationStatus::Revoked);
+    }
+}
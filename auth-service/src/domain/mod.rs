@@ -1,13 +1,53 @@
-pub mod user;
-pub mod error;
+pub mod api_key;
+pub mod audit;
+pub mod auth_method;
+pub mod client_fingerprint;
 pub mod data_stores;
-pub mod password;
+pub mod device_authorization;
 pub mod email;
 pub mod email_client;
+pub mod error;
+pub mod group;
+pub mod identity_provider;
+pub mod invitation;
+pub mod oauth_client;
+pub mod password;
+pub mod phone_number;
+pub mod rate_limit;
+pub mod risk;
+pub mod role;
+pub mod saml_provider;
+pub mod scim;
+pub mod sms_client;
+pub mod tenant;
+pub mod token_delivery;
+pub mod trusted_device;
+pub mod user;
+pub mod webhook;
 
-pub use user::*;
-pub use error::*;
+pub use api_key::*;
+pub use audit::*;
+pub use auth_method::*;
+pub use client_fingerprint::*;
 pub use data_stores::*;
-pub use password::*;
+pub use device_authorization::*;
 pub use email::*;
-pub use email_client::*;
\ No newline at end of file
+pub use email_client::*;
+pub use error::*;
+pub use group::*;
+pub use identity_provider::*;
+pub use invitation::*;
+pub use oauth_client::*;
+pub use password::*;
+pub use phone_number::*;
+pub use rate_limit::*;
+pub use risk::*;
+pub use role::*;
+pub use saml_provider::*;
+pub use scim::*;
+pub use sms_client::*;
+pub use tenant::*;
+pub use token_delivery::*;
+pub use trusted_device::*;
+pub use user::*;
+pub use webhook::*;
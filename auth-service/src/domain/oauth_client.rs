@@ -0,0 +1,133 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+use rand::{distributions::Alphanumeric, Rng};
+use secrecy::Secret;
+
+use super::{Permission, TenantId};
+
+const CLIENT_ID_PREFIX: &str = "client_";
+const CLIENT_ID_LENGTH: usize = 20;
+const CLIENT_SECRET_LENGTH: usize = 40;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ClientId(String);
+
+impl ClientId {
+    pub fn parse(s: String) -> Result<Self> {
+        let is_valid = !s.is_empty()
+            && s.len() <= 64
+            && s.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+
+        if is_valid {
+            Ok(Self(s))
+        } else {
+            Err(eyre!("{} is not a valid client id.", s))
+        }
+    }
+}
+
+impl AsRef<str> for ClientId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// A confidential client, e.g. a backend service, that gets tokens of its own through
+// the `client_credentials` grant. Its secret is only ever stored hashed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OAuthClient {
+    pub id: ClientId,
+    pub tenant: TenantId,
+    pub name: String,
+    // Everything the client may ask for; a token request can narrow this down
+    pub scopes: Vec<Permission>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl OAuthClient {
+    // Returns the new client along with its secret, which is never available again
+    pub fn register(
+        tenant: TenantId,
+        name: String,
+        mut scopes: Vec<Permission>,
+    ) -> (Self, Secret<String>) {
+        scopes.sort();
+        scopes.dedup();
+
+        let client = Self {
+            id: ClientId(format!(
+                "{}{}",
+                CLIENT_ID_PREFIX,
+                random_string(CLIENT_ID_LENGTH)
+            )),
+            tenant,
+            name,
+            scopes,
+            created_at: Utc::now(),
+        };
+
+        (client, Secret::new(random_string(CLIENT_SECRET_LENGTH)))
+    }
+
+    // The scopes granted for a token request: all of the client's scopes when none are
+    // asked for, otherwise the requested ones as long as the client has every one of them
+    pub fn grant_scopes(&self, requested: Option<&[Permission]>) -> Option<Vec<Permission>> {
+        match requested {
+            None => Some(self.scopes.clone()),
+            Some(requested) if requested.iter().all(|scope| self.scopes.contains(scope)) => {
+                let mut granted = requested.to_vec();
+                granted.sort();
+                granted.dedup();
+                Some(granted)
+            }
+            Some(_) => None,
+        }
+    }
+}
+
+fn random_string(length: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(s: &str) -> Permission {
+        Permission::parse(s.to_owned()).unwrap()
+    }
+
+    #[test]
+    fn registered_client_gets_valid_id() {
+        let (client, _) = OAuthClient::register(
+            TenantId::default(),
+            "billing".to_owned(),
+            vec![scope("users:manage")],
+        );
+
+        assert!(client.id.as_ref().starts_with(CLIENT_ID_PREFIX));
+        assert!(ClientId::parse(client.id.as_ref().to_owned()).is_ok());
+        assert!(ClientId::parse("not a client id".to_owned()).is_err());
+    }
+
+    #[test]
+    fn grants_requested_scopes_the_client_has() {
+        let (client, _) = OAuthClient::register(
+            TenantId::default(),
+            "billing".to_owned(),
+            vec![scope("users:manage"), scope("roles:manage")],
+        );
+
+        assert_eq!(client.grant_scopes(None), Some(client.scopes.clone()));
+        assert_eq!(
+            client.grant_scopes(Some(&[scope("users:manage")])),
+            Some(vec![scope("users:manage")])
+        );
+        assert_eq!(client.grant_scopes(Some(&[scope("app:admin")])), None);
+    }
+}
@@ -0,0 +1,82 @@
+use std::hash::Hash;
+
+use color_eyre::eyre::{eyre, Result};
+use secrecy::{ExposeSecret, Secret};
+
+// Phone numbers are stored in E.164 format, e.g. +14155552671
+#[derive(Debug, Clone)]
+pub struct PhoneNumber(Secret<String>);
+
+impl PartialEq for PhoneNumber {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.expose_secret() == other.0.expose_secret()
+    }
+}
+
+impl Hash for PhoneNumber {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.0.expose_secret().hash(state);
+    }
+}
+
+impl Eq for PhoneNumber {}
+
+impl PhoneNumber {
+    pub fn parse(s: Secret<String>) -> Result<PhoneNumber> {
+        if validate_phone_number(&s) {
+            Ok(Self(s))
+        } else {
+            Err(eyre!("Failed to parse string to a PhoneNumber type"))
+        }
+    }
+}
+
+fn validate_phone_number(s: &Secret<String>) -> bool {
+    match s.expose_secret().strip_prefix('+') {
+        Some(digits) => {
+            (8..=15).contains(&digits.len())
+                && !digits.starts_with('0')
+                && digits.chars().all(|c| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+impl AsRef<Secret<String>> for PhoneNumber {
+    fn as_ref(&self) -> &Secret<String> {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PhoneNumber;
+
+    use secrecy::Secret;
+
+    #[test]
+    fn empty_string_is_rejected() {
+        let phone_number = Secret::new("".to_string());
+        assert!(PhoneNumber::parse(phone_number).is_err());
+    }
+    #[test]
+    fn number_missing_plus_prefix_is_rejected() {
+        let phone_number = Secret::new("14155552671".to_string());
+        assert!(PhoneNumber::parse(phone_number).is_err());
+    }
+    #[test]
+    fn number_with_letters_is_rejected() {
+        let phone_number = Secret::new("+1415555CALL".to_string());
+        assert!(PhoneNumber::parse(phone_number).is_err());
+    }
+    #[test]
+    fn number_that_is_too_long_is_rejected() {
+        let phone_number = Secret::new("+1234567890123456".to_string());
+        assert!(PhoneNumber::parse(phone_number).is_err());
+    }
+    #[test]
+    fn e164_number_is_parsed_successfully() {
+        let phone_number = Secret::new("+14155552671".to_string());
+        assert!(PhoneNumber::parse(phone_number).is_ok());
+    }
+}
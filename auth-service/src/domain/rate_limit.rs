@@ -0,0 +1,25 @@
+use color_eyre::eyre::Result;
+
+// How many attempts a caller gets within a sliding window
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitPolicy {
+    pub max_attempts: u32,
+    pub window: chrono::Duration,
+}
+
+impl Default for RateLimitPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 30,
+            window: chrono::Duration::minutes(1),
+        }
+    }
+}
+
+// This trait represents the interface all concrete rate limiters should implement.
+// Every call counts as an attempt for `key`, whether or not it's allowed.
+#[async_trait::async_trait]
+pub trait RateLimiter {
+    // Whether the attempt is within the limit
+    async fn check(&mut self, key: &str) -> Result<bool>;
+}
@@ -0,0 +1,163 @@
+use std::{fmt, net::IpAddr};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Result;
+
+use super::{ClientFingerprint, Email, TenantId};
+
+// A login as seen by the risk evaluator. Emails are only unique within a tenant, so
+// the same address in two tenants is two accounts with a history each.
+#[derive(Debug, Clone)]
+pub struct LoginContext {
+    pub tenant: TenantId,
+    pub email: Email,
+    pub client: ClientFingerprint,
+    pub at: DateTime<Utc>,
+}
+
+impl LoginContext {
+    pub fn new(tenant: TenantId, email: Email, client: ClientFingerprint) -> Self {
+        Self {
+            tenant,
+            email,
+            client,
+            at: Utc::now(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RiskReason {
+    NewIpAddress,
+    NewUserAgent,
+    ImpossibleTravel {
+        distance_km: u32,
+        elapsed_minutes: i64,
+    },
+    FailureBurst {
+        failures: u32,
+    },
+}
+
+impl RiskReason {
+    pub fn weight(&self) -> u32 {
+        match self {
+            Self::NewIpAddress | Self::NewUserAgent => 1,
+            Self::ImpossibleTravel { .. } | Self::FailureBurst { .. } => 2,
+        }
+    }
+}
+
+impl fmt::Display for RiskReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::NewIpAddress => write!(f, "new IP address"),
+            Self::NewUserAgent => write!(f, "new user agent"),
+            Self::ImpossibleTravel {
+                distance_km,
+                elapsed_minutes,
+            } => write!(
+                f,
+                "impossible travel: {} km in {} minutes",
+                distance_km, elapsed_minutes
+            ),
+            Self::FailureBurst { failures } => {
+                write!(f, "{} recent failed logins", failures)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RiskAssessment {
+    pub reasons: Vec<RiskReason>,
+    pub step_up: bool,
+}
+
+impl RiskAssessment {
+    // The login is stepped up to 2FA once the combined weight of the reasons reaches the threshold
+    pub fn new(reasons: Vec<RiskReason>, step_up_score: u32) -> Self {
+        let score: u32 = reasons.iter().map(RiskReason::weight).sum();
+        Self {
+            step_up: score >= step_up_score,
+            reasons,
+        }
+    }
+
+    pub fn low() -> Self {
+        Self {
+            reasons: Vec::new(),
+            step_up: false,
+        }
+    }
+
+    pub fn score(&self) -> u32 {
+        self.reasons.iter().map(RiskReason::weight).sum()
+    }
+}
+
+// This trait represents the interface all concrete login risk evaluators should implement.
+// Evaluators learn from the outcome of each login, so they are told about successes and failures.
+#[async_trait::async_trait]
+pub trait RiskEvaluator {
+    async fn assess(&self, context: &LoginContext) -> Result<RiskAssessment>;
+    async fn record_success(&mut self, context: &LoginContext) -> Result<()>;
+    async fn record_failure(&mut self, context: &LoginContext) -> Result<()>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GeoLocation {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+impl GeoLocation {
+    // Great-circle distance using the haversine formula
+    pub fn distance_km(&self, other: &GeoLocation) -> f64 {
+        const EARTH_RADIUS_KM: f64 = 6371.0;
+
+        let (lat1, lat2) = (self.latitude.to_radians(), other.latitude.to_radians());
+        let d_lat = lat2 - lat1;
+        let d_lon = (other.longitude - self.longitude).to_radians();
+
+        let a = (d_lat / 2.0).sin().powi(2) + lat1.cos() * lat2.cos() * (d_lon / 2.0).sin().powi(2);
+        2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+    }
+}
+
+// Resolves an IP address to an approximate location
+pub trait GeoIpLookup {
+    fn locate(&self, ip_address: IpAddr) -> Option<GeoLocation>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance_between_known_cities() {
+        let london = GeoLocation {
+            latitude: 51.5074,
+            longitude: -0.1278,
+        };
+        let new_york = GeoLocation {
+            latitude: 40.7128,
+            longitude: -74.0060,
+        };
+
+        let distance = london.distance_km(&new_york);
+        assert!((5550.0..5600.0).contains(&distance), "got {}", distance);
+        assert_eq!(london.distance_km(&london), 0.0);
+    }
+
+    #[test]
+    fn assessment_steps_up_at_threshold() {
+        let assessment = RiskAssessment::new(vec![RiskReason::NewIpAddress], 2);
+        assert!(!assessment.step_up);
+
+        let assessment =
+            RiskAssessment::new(vec![RiskReason::NewIpAddress, RiskReason::NewUserAgent], 2);
+        assert!(assessment.step_up);
+        assert_eq!(assessment.score(), 2);
+    }
+}
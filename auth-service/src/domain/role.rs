@@ -0,0 +1,137 @@
+use color_eyre::eyre::{eyre, Result};
+
+// Names of roles and permissions are lowercase identifiers so they read the same in
+// the database, in token claims and in route guards, e.g. `admin` or `roles:manage`.
+fn is_valid_name(s: &str, allow_separator: bool) -> bool {
+    !s.is_empty()
+        && s.len() <= 64
+        && s.chars().all(|c| {
+            c.is_ascii_lowercase()
+                || c.is_ascii_digit()
+                || c == '_'
+                || c == '-'
+                || (allow_separator && c == ':')
+        })
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Role(String);
+
+impl Role {
+    pub fn parse(s: String) -> Result<Role> {
+        if is_valid_name(&s, false) {
+            Ok(Self(s))
+        } else {
+            Err(eyre!("{} is not a valid role name.", s))
+        }
+    }
+}
+
+impl AsRef<str> for Role {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Permission(String);
+
+impl Permission {
+    pub fn parse(s: String) -> Result<Permission> {
+        if is_valid_name(&s, true) {
+            Ok(Self(s))
+        } else {
+            Err(eyre!("{} is not a valid permission.", s))
+        }
+    }
+}
+
+impl AsRef<str> for Permission {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+// Everything a user has been granted: the roles assigned to them and the union of
+// the permissions those roles carry. Both lists are sorted and free of duplicates.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Grants {
+    pub roles: Vec<Role>,
+    pub permissions: Vec<Permission>,
+}
+
+impl Grants {
+    pub fn new(mut roles: Vec<Role>, mut permissions: Vec<Permission>) -> Self {
+        roles.sort();
+        roles.dedup();
+        permissions.sort();
+        permissions.dedup();
+        Self { roles, permissions }
+    }
+}
+
+// A permission checked by a route guard, named at compile time so a typo in a
+// route can't silently lock everyone out
+pub trait RequiredPermission {
+    const NAME: &'static str;
+}
+
+pub mod permissions {
+    use super::RequiredPermission;
+
+    // Assign and unassign roles
+    pub struct ManageRoles;
+
+    impl RequiredPermission for ManageRoles {
+        const NAME: &'static str = "roles:manage";
+    }
+
+    // Use the `/admin` user management routes
+    pub struct ManageUsers;
+
+    impl RequiredPermission for ManageUsers {
+        const NAME: &'static str = "users:manage";
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_lowercase_names() {
+        assert!(Role::parse("admin".to_owned()).is_ok());
+        assert!(Role::parse("support_agent-2".to_owned()).is_ok());
+        assert!(Permission::parse("roles:manage".to_owned()).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        assert!(Role::parse("".to_owned()).is_err());
+        assert!(Role::parse("Admin".to_owned()).is_err());
+        assert!(Role::parse("roles:manage".to_owned()).is_err());
+        assert!(Permission::parse("users read".to_owned()).is_err());
+        assert!(Permission::parse("x".repeat(65)).is_err());
+    }
+
+    #[test]
+    fn grants_are_sorted_and_deduplicated() {
+        let role = |s: &str| Role::parse(s.to_owned()).unwrap();
+        let permission = |s: &str| Permission::parse(s.to_owned()).unwrap();
+
+        let grants = Grants::new(
+            vec![role("support"), role("admin"), role("support")],
+            vec![
+                permission("users:read"),
+                permission("roles:manage"),
+                permission("users:read"),
+            ],
+        );
+
+        assert_eq!(grants.roles, vec![role("admin"), role("support")]);
+        assert_eq!(
+            grants.permissions,
+            vec![permission("roles:manage"), permission("users:read")]
+        );
+    }
+}
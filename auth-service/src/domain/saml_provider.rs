@@ -0,0 +1,152 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Context, Result};
+use x509_parser::{parse_x509_certificate, public_key::PublicKey};
+
+use super::{ProviderId, TenantId};
+
+// An upstream SAML 2.0 identity provider users of a tenant can sign in with. Unlike
+// OIDC there is no discovery: everything needed is copied from the provider's metadata.
+#[derive(Debug, Clone)]
+pub struct SamlProvider {
+    pub id: ProviderId,
+    pub tenant: TenantId,
+    // Shown on the sign-in button
+    pub name: String,
+    // The provider's entity ID, which has to be the issuer of its assertions
+    pub entity_id: String,
+    // Where users are sent to sign in, with the HTTP-Redirect binding
+    pub sso_url: String,
+    // The certificate the provider signs assertions with, as base64 DER like in its
+    // metadata. Keys sent along with an assertion are never trusted.
+    pub certificate: String,
+    // The attribute holding the user's email; the NameID is used when not set
+    pub email_attribute: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl SamlProvider {
+    pub fn new(
+        id: ProviderId,
+        tenant: TenantId,
+        name: String,
+        entity_id: String,
+        sso_url: String,
+        certificate: String,
+        email_attribute: Option<String>,
+    ) -> Result<Self> {
+        let entity_id = entity_id.trim().to_owned();
+        if entity_id.is_empty() {
+            return Err(eyre!("Entity ID must not be empty."));
+        }
+
+        let sso_url = sso_url.trim().to_owned();
+        if !(sso_url.starts_with("https://") || sso_url.starts_with("http://")) {
+            return Err(eyre!("{} is not a valid SSO URL.", sso_url));
+        }
+
+        let email_attribute = email_attribute
+            .map(|attribute| attribute.trim().to_owned())
+            .filter(|attribute| !attribute.is_empty());
+
+        let provider = Self {
+            id,
+            tenant,
+            name,
+            entity_id,
+            sso_url,
+            certificate: normalize_certificate(&certificate),
+            email_attribute,
+            created_at: Utc::now(),
+        };
+        provider.public_key()?;
+
+        Ok(provider)
+    }
+
+    // The RSA key of the certificate, as the DER `RSAPublicKey` signatures are checked with
+    pub fn public_key(&self) -> Result<Vec<u8>> {
+        let der = STANDARD
+            .decode(&self.certificate)
+            .wrap_err("certificate is not valid base64")?;
+        let (_, certificate) =
+            parse_x509_certificate(&der).map_err(|e| eyre!("invalid certificate: {}", e))?;
+
+        let key = certificate.public_key();
+        match key.parsed() {
+            Ok(PublicKey::RSA(_)) => Ok(key.subject_public_key.data.to_vec()),
+            _ => Err(eyre!("certificate doesn't hold an RSA key")),
+        }
+    }
+}
+
+// Accepts the certificate as PEM too, and drops the line breaks metadata often has
+fn normalize_certificate(certificate: &str) -> String {
+    certificate
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("-----"))
+        .flat_map(|line| line.split_whitespace())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Self-signed, with a 2048-bit RSA key
+    const CERTIFICATE_PEM: &str = "-----BEGIN CERTIFICATE-----
+MIIDFzCCAf+gAwIBAgIUTvhyNjws0JgTjEGKKSxT1QyFRKMwDQYJKoZIhvcNAQEL
+BQAwGjEYMBYGA1UEAwwPaWRwLmV4YW1wbGUuY29tMCAXDTI2MTAxODIyMTAxN1oY
+DzIxMjYwOTI0MjIxMDE3WjAaMRgwFgYDVQQDDA9pZHAuZXhhbXBsZS5jb20wggEi
+MA0GCSqGSIb3DQEBAQUAA4IBDwAwggEKAoIBAQCy6rj1O70z+RlhrJNZpbPDfKnK
+i4EiGcRZL9P3i00ba2dEdLN2Mq3gT5NfYEalB/EV4EV/xfa7MfhdvpN1hOQedftD
+zfllkU+UZLSGtaLuQXPEPAS1+32KNf5FdaMjFkZ6V1LTFk0kHCevpGiZnlFtWHv2
+JTYG0qpN3GJatNlOCLhbqq3VXKELSl2GGp05jmUWMYuXbpO6pr8BAE5ZKjQNxLeB
+5BGwOjm/cPCj8vxtZmTEhihso44osVfDAmU6Hlhl15Et+MOAnL1r/GJngBIBpSFl
+HOrIh8VbQUsD5TKjl0DVxuGUMlxv3k0M26R/Ris+ByPToFb4pk+nvK4ekOZZAgMB
+AAGjUzBRMB0GA1UdDgQWBBTmwD9yvcfSUfTg8G8iuxCH3vImYzAfBgNVHSMEGDAW
+gBTmwD9yvcfSUfTg8G8iuxCH3vImYzAPBgNVHRMBAf8EBTADAQH/MA0GCSqGSIb3
+DQEBCwUAA4IBAQBP9g+uUo3ioEsEzPYJSNQABOM3IAEZhGmreMLhdcEfYkdhBTFq
+s6uhgXzxuRElZujWfEhGFgm79NSBR1i6IiG0NRi/B0paN4Fu9Vkiam43bW4yIvB6
+5598XPYAPDIJ2Y3yVemdYF68C1SyeYlOC4TVrg5PSuWY6d+ezwM9GuprDHfozT38
+TcF0V6zoXekaEuyj7XRJVG3qDsjG+bOZYQAOs8q7bCFLG1VUZKMcy4AE84VG3r5g
+XUsaJIoqUrup+r1noUkOJrYc1ijBe6Wzd3Tcft1wcACZsVXmdJp4daAto5Fa0hSt
+SLpbw/FzUStCWU1vTnsIYdujgEC1i+jaYv4b
+-----END CERTIFICATE-----";
+
+    fn provider(certificate: &str) -> Result<SamlProvider> {
+        SamlProvider::new(
+            ProviderId::parse("corp".to_owned()).unwrap(),
+            TenantId::default(),
+            "Corp SSO".to_owned(),
+            "https://idp.example.com/metadata".to_owned(),
+            "https://idp.example.com/sso".to_owned(),
+            certificate.to_owned(),
+            None,
+        )
+    }
+
+    #[test]
+    fn certificate_is_normalized_to_base64_der() {
+        let from_pem = provider(CERTIFICATE_PEM).unwrap();
+        assert!(!from_pem.certificate.contains(char::is_whitespace));
+        assert!(!from_pem.certificate.contains("CERTIFICATE"));
+
+        // As it's found in metadata, wrapped and indented
+        let lines: Vec<&str> = from_pem
+            .certificate
+            .as_bytes()
+            .chunks(64)
+            .map(|line| std::str::from_utf8(line).unwrap())
+            .collect();
+        let from_metadata = provider(&format!("\n      {}\n    ", lines.join("\n      "))).unwrap();
+        assert_eq!(from_metadata.certificate, from_pem.certificate);
+        assert!(from_metadata.public_key().is_ok());
+    }
+
+    #[test]
+    fn invalid_certificates_are_rejected() {
+        assert!(provider("").is_err());
+        assert!(provider("bm90IGEgY2VydGlmaWNhdGU=").is_err());
+    }
+}
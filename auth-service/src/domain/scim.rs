@@ -0,0 +1,197 @@
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::ScimError;
+
+pub const SCIM_USER_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+pub const SCIM_GROUP_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:Group";
+pub const SCIM_LIST_RESPONSE_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:ListResponse";
+pub const SCIM_PATCH_OP_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:PatchOp";
+pub const SCIM_ERROR_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:Error";
+
+// A filter of the form `attribute eq "value"`, the only one provisioning clients need
+// to find the resource they are about to create or update (RFC 7644, section 3.4.2.2).
+// Attribute names are case-insensitive and kept lowercased.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScimFilter {
+    pub attribute: String,
+    pub value: String,
+}
+
+impl ScimFilter {
+    pub fn parse(filter: &str) -> Result<Self, ScimError> {
+        let filter = filter.trim();
+        let (attribute, rest) =
+            filter
+                .split_once(char::is_whitespace)
+                .ok_or(ScimError::InvalidFilter(
+                    "Expected a filter like userName eq \"value\"",
+                ))?;
+        let (operator, value) =
+            rest.trim_start()
+                .split_once(char::is_whitespace)
+                .ok_or(ScimError::InvalidFilter(
+                    "Expected a filter like userName eq \"value\"",
+                ))?;
+
+        if !operator.eq_ignore_ascii_case("eq") {
+            return Err(ScimError::InvalidFilter(
+                "Only the eq operator is supported",
+            ));
+        }
+
+        // The value is a JSON string, escapes included
+        let value: String = serde_json::from_str(value.trim())
+            .map_err(|_| ScimError::InvalidFilter("The filter value must be a quoted string"))?;
+
+        Ok(Self {
+            attribute: attribute.to_ascii_lowercase(),
+            value,
+        })
+    }
+}
+
+// The body of a PATCH request
+#[derive(Debug, Deserialize)]
+pub struct ScimPatchRequest {
+    #[serde(default)]
+    pub schemas: Vec<String>,
+    #[serde(rename = "Operations")]
+    pub operations: Vec<ScimPatchOperation>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ScimPatchOperation {
+    pub op: String,
+    pub path: Option<String>,
+    #[serde(default)]
+    pub value: Value,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScimPatchOp {
+    Add,
+    Replace,
+    Remove,
+}
+
+impl ScimPatchOperation {
+    // Some clients send the operation capitalized, e.g. `Replace`
+    pub fn op(&self) -> Result<ScimPatchOp, ScimError> {
+        match self.op.to_ascii_lowercase().as_str() {
+            "add" => Ok(ScimPatchOp::Add),
+            "replace" => Ok(ScimPatchOp::Replace),
+            "remove" => Ok(ScimPatchOp::Remove),
+            _ => Err(ScimError::InvalidValue("Unknown patch operation")),
+        }
+    }
+
+    // The attributes the operation sets, as (lowercased path, value) pairs. Without a
+    // path, the value is an object of attributes.
+    pub fn attributes(&self) -> Result<Vec<(String, &Value)>, ScimError> {
+        match &self.path {
+            Some(path) => Ok(vec![(path.trim().to_ascii_lowercase(), &self.value)]),
+            None => match &self.value {
+                Value::Object(attributes) => Ok(attributes
+                    .iter()
+                    .map(|(path, value)| (path.to_ascii_lowercase(), value))
+                    .collect()),
+                _ => Err(ScimError::NoTarget),
+            },
+        }
+    }
+}
+
+// `active` as a boolean. Some clients send it as the string "True" or "False".
+pub fn parse_scim_bool(value: &Value) -> Result<bool, ScimError> {
+    match value {
+        Value::Bool(active) => Ok(*active),
+        Value::String(active) if active.eq_ignore_ascii_case("true") => Ok(true),
+        Value::String(active) if active.eq_ignore_ascii_case("false") => Ok(false),
+        _ => Err(ScimError::InvalidValue("active must be a boolean")),
+    }
+}
+
+// The member a path like `members[value eq "alice@example.com"]` selects. `None` for
+// a path of all members.
+pub fn parse_member_path(path: &str) -> Result<Option<String>, ScimError> {
+    if path == "members" {
+        return Ok(None);
+    }
+
+    let filter = path
+        .strip_prefix("members[")
+        .and_then(|rest| rest.strip_suffix(']'))
+        .ok_or(ScimError::InvalidPath)?;
+    let filter = ScimFilter::parse(filter)?;
+    if filter.attribute != "value" {
+        return Err(ScimError::InvalidPath);
+    }
+
+    Ok(Some(filter.value))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parses_eq_filters() {
+        assert_eq!(
+            ScimFilter::parse(r#"userName eq "alice@example.com""#).unwrap(),
+            ScimFilter {
+                attribute: "username".to_owned(),
+                value: "alice@example.com".to_owned(),
+            }
+        );
+        assert_eq!(
+            ScimFilter::parse(r#" displayName  EQ "Sales \"EMEA\"" "#)
+                .unwrap()
+                .value,
+            r#"Sales "EMEA""#
+        );
+
+        assert!(ScimFilter::parse("userName").is_err());
+        assert!(ScimFilter::parse(r#"userName co "alice""#).is_err());
+        assert!(ScimFilter::parse("userName eq alice").is_err());
+        assert!(ScimFilter::parse(r#"userName eq "alice" and active eq true"#).is_err());
+    }
+
+    #[test]
+    fn parses_patch_operations() {
+        let request: ScimPatchRequest = serde_json::from_value(json!({
+            "schemas": [SCIM_PATCH_OP_SCHEMA],
+            "Operations": [
+                { "op": "Replace", "value": { "active": "False" } },
+                { "op": "remove", "path": "members[value eq \"bob@example.com\"]" },
+                { "op": "copy", "path": "active", "value": true },
+            ]
+        }))
+        .unwrap();
+
+        let replace = &request.operations[0];
+        assert_eq!(replace.op().unwrap(), ScimPatchOp::Replace);
+        let attributes = replace.attributes().unwrap();
+        assert_eq!(attributes[0].0, "active");
+        assert!(!parse_scim_bool(attributes[0].1).unwrap());
+
+        let remove = &request.operations[1];
+        assert_eq!(remove.op().unwrap(), ScimPatchOp::Remove);
+        assert_eq!(
+            parse_member_path(remove.path.as_deref().unwrap()).unwrap(),
+            Some("bob@example.com".to_owned())
+        );
+
+        assert!(request.operations[2].op().is_err());
+    }
+
+    #[test]
+    fn parses_member_paths() {
+        assert_eq!(parse_member_path("members").unwrap(), None);
+        assert!(parse_member_path("emails").is_err());
+        assert!(parse_member_path(r#"members[display eq "Bob"]"#).is_err());
+        assert!(parse_scim_bool(&json!(1)).is_err());
+    }
+}
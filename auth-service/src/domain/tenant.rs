@@ -0,0 +1,130 @@
+use color_eyre::eyre::{eyre, Result};
+
+use super::Email;
+
+// Tenants are customer organizations sharing the service. Every user belongs to
+// exactly one, so the same email can sign up separately with each of them.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TenantId(String);
+
+impl TenantId {
+    // Lowercase slugs so the id reads the same in hostnames, headers and token claims,
+    // e.g. `acme` or `acme-eu`
+    pub fn parse(s: String) -> Result<TenantId> {
+        let is_valid = !s.is_empty()
+            && s.len() <= 63
+            && !s.starts_with('-')
+            && !s.ends_with('-')
+            && s.chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+
+        if is_valid {
+            Ok(Self(s))
+        } else {
+            Err(eyre!("{} is not a valid tenant id.", s))
+        }
+    }
+}
+
+// Requests that don't name a tenant belong to this one, so a single-tenant
+// deployment never has to think about tenants at all
+impl Default for TenantId {
+    fn default() -> Self {
+        Self("default".to_owned())
+    }
+}
+
+impl AsRef<str> for TenantId {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tenant {
+    pub id: TenantId,
+    // Requests for this host belong to the tenant, e.g. `acme.auth.example.com`
+    pub hostname: Option<String>,
+    pub settings: TenantSettings,
+}
+
+impl Tenant {
+    pub fn new(id: TenantId) -> Self {
+        Self {
+            id,
+            hostname: None,
+            settings: TenantSettings::default(),
+        }
+    }
+
+    pub fn with_hostname(mut self, hostname: impl Into<String>) -> Self {
+        self.hostname = Some(hostname.into());
+        self
+    }
+
+    pub fn with_settings(mut self, settings: TenantSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TenantSettings {
+    // Every user of the tenant goes through 2FA, whatever they chose for themselves
+    pub require_2fa: bool,
+    // Only emails at these domains can sign up. Empty means any domain.
+    pub allowed_email_domains: Vec<String>,
+}
+
+impl TenantSettings {
+    pub fn allows_email(&self, email: &Email) -> bool {
+        if self.allowed_email_domains.is_empty() {
+            return true;
+        }
+
+        email.domain().is_some_and(|domain| {
+            self.allowed_email_domains
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(domain))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    #[test]
+    fn accepts_slugs_as_tenant_ids() {
+        assert!(TenantId::parse("acme".to_owned()).is_ok());
+        assert!(TenantId::parse("acme-eu-2".to_owned()).is_ok());
+        assert_eq!(TenantId::default().as_ref(), "default");
+    }
+
+    #[test]
+    fn rejects_invalid_tenant_ids() {
+        assert!(TenantId::parse("".to_owned()).is_err());
+        assert!(TenantId::parse("Acme".to_owned()).is_err());
+        assert!(TenantId::parse("-acme".to_owned()).is_err());
+        assert!(TenantId::parse("acme.com".to_owned()).is_err());
+        assert!(TenantId::parse("a".repeat(64)).is_err());
+    }
+
+    #[test]
+    fn allowed_email_domains_limit_emails() {
+        let email = |s: &str| Email::parse(Secret::new(s.to_owned())).unwrap();
+
+        assert!(TenantSettings::default().allows_email(&email("user@anywhere.com")));
+
+        let settings = TenantSettings {
+            require_2fa: false,
+            allowed_email_domains: vec!["acme.com".to_owned()],
+        };
+        assert!(settings.allows_email(&email("user@acme.com")));
+        assert!(settings.allows_email(&email("user@ACME.com")));
+        assert!(!settings.allows_email(&email("user@evil.com")));
+        assert!(!settings.allows_email(&email("user@sub.acme.com")));
+    }
+}
@@ -0,0 +1,28 @@
+use serde::Deserialize;
+
+// How a newly issued auth token is handed to the client. Browsers get the HttpOnly
+// cookie, while mobile apps and CLIs ask for it in the response body and send it
+// back in an `Authorization: Bearer` header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenDelivery {
+    #[default]
+    Cookie,
+    Body,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_lowercase_values() {
+        let delivery: TokenDelivery = serde_json::from_str(r#""body""#).unwrap();
+        assert_eq!(delivery, TokenDelivery::Body);
+
+        let delivery: TokenDelivery = serde_json::from_str(r#""cookie""#).unwrap();
+        assert_eq!(delivery, TokenDelivery::Cookie);
+
+        assert!(serde_json::from_str::<TokenDelivery>(r#""header""#).is_err());
+    }
+}
@@ -0,0 +1,83 @@
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+use uuid::Uuid;
+
+use super::{ClientFingerprint, Email, TenantId};
+
+// Identifies a browser that may skip 2FA. The id on its own grants nothing: it is
+// only honoured when it comes back inside a signed trusted-device cookie.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TrustedDeviceId(Uuid);
+
+impl TrustedDeviceId {
+    pub fn parse(id: &str) -> Result<Self> {
+        Uuid::parse_str(id)
+            .map(Self)
+            .map_err(|_| eyre!("Invalid trusted device id"))
+    }
+}
+
+impl Default for TrustedDeviceId {
+    fn default() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl From<Uuid> for TrustedDeviceId {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl AsRef<Uuid> for TrustedDeviceId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for TrustedDeviceId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrustedDevice {
+    pub id: TrustedDeviceId,
+    pub tenant: TenantId,
+    pub email: Email,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<IpAddr>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl TrustedDevice {
+    pub fn new(
+        tenant: TenantId,
+        email: Email,
+        client: ClientFingerprint,
+        ttl: chrono::Duration,
+    ) -> Self {
+        let created_at = Utc::now();
+        Self {
+            id: TrustedDeviceId::default(),
+            tenant,
+            email,
+            user_agent: client.user_agent,
+            ip_address: client.ip_address,
+            created_at,
+            expires_at: created_at + ttl,
+        }
+    }
+
+    pub fn belongs_to(&self, tenant: &TenantId, email: &Email) -> bool {
+        self.tenant == *tenant && self.email == *email
+    }
+
+    pub fn is_expired(&self) -> bool {
+        Utc::now() >= self.expires_at
+    }
+}
@@ -1,4 +1,10 @@
-use super::{Email, Password};
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{eyre, Result};
+use rand::{distributions::Alphanumeric, Rng};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use super::{Email, Password, PhoneNumber};
 
 // The User struct should contain 3 fields. email, which is a String; 
 // password, which is also a String; and requires_2fa, which is a boolean.
@@ -7,6 +13,22 @@ pub struct User {
     pub email: Email,
     pub password: Password,
     pub requires_2fa: bool,
+    // Only set once the user has confirmed a code sent to the number
+    pub phone_number: Option<PhoneNumber>,
+    pub two_fa_channel: TwoFAChannel,
+    // Only active accounts can log in or use their tokens. The reason is shown to admins,
+    // not to the user.
+    pub status: AccountStatus,
+    pub status_reason: Option<String>,
+    // Who suspended the account, while it's suspended. A provisioning system may only
+    // lift the suspensions it made itself.
+    pub suspended_by: Option<SuspendedBy>,
+    pub status_changed_at: DateTime<Utc>,
+    // Set by an admin. The user has to sign in with a magic link and choose a new password.
+    pub password_reset_required: bool,
+    // The user lives in a directory such as LDAP, which checks their password. The row
+    // here only holds local state like roles and 2FA settings.
+    pub managed_by_directory: bool,
 }
 
 impl User {
@@ -16,6 +38,121 @@ impl User {
             email,
             password,
             requires_2fa,
+            phone_number: None,
+            two_fa_channel: TwoFAChannel::default(),
+            status: AccountStatus::default(),
+            status_reason: None,
+            suspended_by: None,
+            status_changed_at: Utc::now(),
+            password_reset_required: false,
+            managed_by_directory: false,
+        }
+    }
+
+    // A directory user as first seen locally. The password is random and never checked.
+    pub fn from_directory(email: Email) -> Self {
+        Self {
+            managed_by_directory: true,
+            ..Self::new(email, random_password(), false)
+        }
+    }
+
+    // A user created by a provisioning system without a password. They sign in through
+    // single sign-on or a magic link, and can choose a password later on.
+    pub fn provisioned(email: Email) -> Self {
+        Self::new(email, random_password(), false)
+    }
+}
+
+// A password nobody knows
+fn random_password() -> Password {
+    let password: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(32)
+        .map(char::from)
+        .collect();
+
+    Password::parse(Secret::new(password)).expect("Random password is valid")
+}
+
+// The channel 2FA codes are delivered through
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TwoFAChannel {
+    #[default]
+    Email,
+    Sms,
+}
+
+impl TwoFAChannel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TwoFAChannel::Email => "email",
+            TwoFAChannel::Sms => "sms",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "email" => Ok(TwoFAChannel::Email),
+            "sms" => Ok(TwoFAChannel::Sms),
+            _ => Err(eyre!("{} is not a valid 2FA channel", s)),
         }
     }
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountStatus {
+    #[default]
+    Active,
+    // Blocked by an admin, e.g. for abuse
+    Suspended,
+    // Created but not yet allowed in, e.g. until the email address is confirmed
+    PendingVerification,
+}
+
+impl AccountStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountStatus::Active => "active",
+            AccountStatus::Suspended => "suspended",
+            AccountStatus::PendingVerification => "pending_verification",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "active" => Ok(AccountStatus::Active),
+            "suspended" => Ok(AccountStatus::Suspended),
+            "pending_verification" => Ok(AccountStatus::PendingVerification),
+            _ => Err(eyre!("{} is not a valid account status", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SuspendedBy {
+    // Through `/admin`
+    Admin,
+    // Through SCIM, when the provisioning system deactivates the user
+    Provisioning,
+}
+
+impl SuspendedBy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SuspendedBy::Admin => "admin",
+            SuspendedBy::Provisioning => "provisioning",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "admin" => Ok(SuspendedBy::Admin),
+            "provisioning" => Ok(SuspendedBy::Provisioning),
+            _ => Err(eyre!("{} is not a valid suspender", s)),
+        }
+    }
+}
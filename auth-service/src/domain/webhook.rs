@@ -0,0 +1,621 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::{eyre, Result};
+use rand::{distributions::Alphanumeric, Rng};
+use ring::hmac;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use url::{Host, Url};
+use uuid::Uuid;
+
+use super::{AuditEvent, AuditOutcome, TenantId};
+
+const SECRET_PREFIX: &str = "whsec_";
+const SECRET_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WebhookId(Uuid);
+
+impl WebhookId {
+    pub fn parse(id: &str) -> Result<Self> {
+        Uuid::parse_str(id)
+            .map(Self)
+            .map_err(|_| eyre!("Invalid webhook id"))
+    }
+}
+
+impl Default for WebhookId {
+    fn default() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl From<Uuid> for WebhookId {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl AsRef<Uuid> for WebhookId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for WebhookId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WebhookDeliveryId(Uuid);
+
+impl WebhookDeliveryId {
+    pub fn parse(id: &str) -> Result<Self> {
+        Uuid::parse_str(id)
+            .map(Self)
+            .map_err(|_| eyre!("Invalid webhook delivery id"))
+    }
+}
+
+impl Default for WebhookDeliveryId {
+    fn default() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl From<Uuid> for WebhookDeliveryId {
+    fn from(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl AsRef<Uuid> for WebhookDeliveryId {
+    fn as_ref(&self) -> &Uuid {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for WebhookDeliveryId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+// What a webhook can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum WebhookEventType {
+    #[serde(rename = "signup")]
+    Signup,
+    #[serde(rename = "login")]
+    Login,
+    #[serde(rename = "two_fa_failure")]
+    TwoFAFailure,
+    #[serde(rename = "logout")]
+    Logout,
+}
+
+impl WebhookEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Signup => "signup",
+            Self::Login => "login",
+            Self::TwoFAFailure => "two_fa_failure",
+            Self::Logout => "logout",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "signup" => Ok(Self::Signup),
+            "login" => Ok(Self::Login),
+            "two_fa_failure" => Ok(Self::TwoFAFailure),
+            "logout" => Ok(Self::Logout),
+            _ => Err(eyre!("Invalid webhook event type: {}", s)),
+        }
+    }
+
+    // The webhook event an audit event amounts to, if any. Every way of logging in
+    // records a successful `login`.
+    pub fn of(event: &AuditEvent) -> Option<Self> {
+        match (event.event.as_str(), event.outcome) {
+            ("signup", AuditOutcome::Success) => Some(Self::Signup),
+            ("login", AuditOutcome::Success) => Some(Self::Login),
+            ("two_fa_verification", AuditOutcome::Failure) => Some(Self::TwoFAFailure),
+            ("logout", AuditOutcome::Success) => Some(Self::Logout),
+            _ => None,
+        }
+    }
+}
+
+// A downstream system subscribed to some of a tenant's events. Its secret signs every
+// payload sent to it, so unlike our own secrets it can't be hashed.
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    pub id: WebhookId,
+    pub tenant: TenantId,
+    pub url: String,
+    pub events: Vec<WebhookEventType>,
+    pub secret: Secret<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Webhook {
+    pub fn new(tenant: TenantId, url: String, mut events: Vec<WebhookEventType>) -> Result<Self> {
+        let url = url.trim().to_owned();
+        match Url::parse(&url) {
+            Ok(parsed) if matches!(parsed.scheme(), "https" | "http") && parsed.has_host() => {}
+            _ => return Err(eyre!("{} is not a valid webhook URL.", url)),
+        }
+
+        events.sort_by_key(|event| event.as_str());
+        events.dedup();
+        if events.is_empty() {
+            return Err(eyre!("A webhook must subscribe to at least one event."));
+        }
+
+        let secret: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(SECRET_LENGTH)
+            .map(char::from)
+            .collect();
+
+        Ok(Self {
+            id: WebhookId::default(),
+            tenant,
+            url,
+            events,
+            secret: Secret::new(format!("{}{}", SECRET_PREFIX, secret)),
+            created_at: Utc::now(),
+        })
+    }
+
+    pub fn subscribes_to(&self, event_type: WebhookEventType) -> bool {
+        self.events.contains(&event_type)
+    }
+
+    // `sha256=` and the hex-encoded HMAC-SHA256 of `{timestamp}.{payload}`, keyed with
+    // the webhook's secret. The timestamp lets receivers turn away old payloads.
+    pub fn sign(&self, timestamp: i64, payload: &str) -> String {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, self.secret.expose_secret().as_bytes());
+        let tag = hmac::sign(&key, format!("{}.{}", timestamp, payload).as_bytes());
+
+        let hex: String = tag
+            .as_ref()
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        format!("sha256={}", hex)
+    }
+}
+
+// Which URLs webhooks may send to. Tenant admins choose them, so by default only https
+// URLs of hosts with public addresses are allowed, keeping the service from being used to
+// reach itself or the network it runs in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum WebhookUrlPolicy {
+    #[default]
+    PublicHttps,
+    // Any http(s) URL, including local ones. For development and tests.
+    Any,
+}
+
+impl WebhookUrlPolicy {
+    // Resolves the host, so this is checked both when a webhook is created and before
+    // every attempt, in case the host has since been pointed somewhere else. The host is
+    // resolved again when connecting, so that lookup has to be checked as well; see
+    // `PublicAddressResolver`.
+    pub async fn check(&self, url: &str) -> Result<()> {
+        let url = Url::parse(url).map_err(|_| eyre!("{} is not a valid webhook URL.", url))?;
+        let addresses: Vec<IpAddr> = match (self, url.scheme()) {
+            (Self::Any, "https" | "http") => return Ok(()),
+            (Self::PublicHttps, "https") => match url.host() {
+                Some(Host::Ipv4(ip)) => vec![IpAddr::V4(ip)],
+                Some(Host::Ipv6(ip)) => vec![IpAddr::V6(ip)],
+                Some(Host::Domain(domain)) => {
+                    let port = url.port_or_known_default().unwrap_or(443);
+                    tokio::net::lookup_host((domain, port))
+                        .await?
+                        .map(|address| address.ip())
+                        .collect()
+                }
+                None => vec![],
+            },
+            (_, scheme) => return Err(eyre!("Webhooks can't be sent over {}.", scheme)),
+        };
+
+        // Every address counts, since any of them may be the one connected to
+        if addresses.is_empty() || !addresses.into_iter().all(is_public_address) {
+            return Err(eyre!("{} doesn't resolve to a public address.", url));
+        }
+
+        Ok(())
+    }
+}
+
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => is_public_v4(ip),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_v4(ip),
+            None => is_public_v6(ip),
+        },
+    }
+}
+
+fn is_public_v4(ip: Ipv4Addr) -> bool {
+    let [first, second, ..] = ip.octets();
+    !(ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        || ip.is_documentation()
+        || ip.is_multicast()
+        // 0.0.0.0/8 and the carrier-grade NAT range 100.64.0.0/10
+        || first == 0
+        || (first == 100 && (second & 0xc0) == 64))
+}
+
+fn is_public_v6(ip: Ipv6Addr) -> bool {
+    // These reach the IPv4 address embedded in them, so it has to be public too
+    let embedded = |high: u16, low: u16| Ipv4Addr::from(((high as u32) << 16) | low as u32);
+    match ip.segments() {
+        // NAT64 (64:ff9b::/96)
+        [0x64, 0xff9b, 0, 0, 0, 0, high, low] => return is_public_v4(embedded(high, low)),
+        // 6to4 (2002::/16)
+        [0x2002, high, low, ..] => return is_public_v4(embedded(high, low)),
+        // IPv4-compatible (::a.b.c.d), which includes :: and ::1
+        [0, 0, 0, 0, 0, 0, high, low] => return is_public_v4(embedded(high, low)),
+        _ => {}
+    }
+
+    let first = ip.segments()[0];
+    !(ip.is_multicast()
+        // Unique local (fc00::/7) and link-local (fe80::/10) addresses
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80)
+}
+
+// The JSON body sent for an event. `id` is the audit event's, so receivers can tell
+// retries and replays of the same event apart from new ones.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WebhookPayload {
+    pub id: String,
+    #[serde(rename = "type")]
+    pub event_type: WebhookEventType,
+    pub tenant: String,
+    #[serde(rename = "occurredAt")]
+    pub occurred_at: String,
+    pub user: Option<String>,
+    pub reason: Option<String>,
+    #[serde(rename = "ipAddress")]
+    pub ip_address: Option<String>,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+}
+
+impl WebhookPayload {
+    pub fn new(event_type: WebhookEventType, event: &AuditEvent) -> Self {
+        Self {
+            id: event.id.to_string(),
+            event_type,
+            tenant: event.tenant.as_ref().to_owned(),
+            occurred_at: event.occurred_at.to_rfc3339(),
+            user: event.user.clone(),
+            reason: event.reason.clone(),
+            ip_address: event.ip_address.map(|ip| ip.to_string()),
+            user_agent: event.user_agent.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookDeliveryStatus {
+    Pending,
+    Delivered,
+    // Given up on after too many failed attempts, until an admin replays it
+    DeadLetter,
+}
+
+impl WebhookDeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pending => "pending",
+            Self::Delivered => "delivered",
+            Self::DeadLetter => "dead_letter",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<Self> {
+        match s {
+            "pending" => Ok(Self::Pending),
+            "delivered" => Ok(Self::Delivered),
+            "dead_letter" => Ok(Self::DeadLetter),
+            _ => Err(eyre!("Invalid webhook delivery status: {}", s)),
+        }
+    }
+}
+
+// Failed deliveries are retried after `base_delay_seconds`, twice as long after each
+// further failure, until `max_attempts` have failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WebhookRetryPolicy {
+    pub base_delay_seconds: i64,
+    pub max_attempts: u32,
+}
+
+impl Default for WebhookRetryPolicy {
+    // Eight attempts spread over a bit more than an hour
+    fn default() -> Self {
+        Self {
+            base_delay_seconds: 30,
+            max_attempts: 8,
+        }
+    }
+}
+
+impl WebhookRetryPolicy {
+    // How long to wait after the `attempts`th failed attempt
+    pub fn delay_after(&self, attempts: u32) -> Duration {
+        let factor = 2_i64.saturating_pow(attempts.saturating_sub(1));
+        Duration::seconds(self.base_delay_seconds.saturating_mul(factor))
+    }
+}
+
+// One event to send to one webhook, along with how sending it went so far
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebhookDelivery {
+    pub id: WebhookDeliveryId,
+    pub webhook_id: WebhookId,
+    pub tenant: TenantId,
+    pub event_type: WebhookEventType,
+    // Kept as sent, so retries and replays carry the same body and signature input
+    pub payload: String,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: u32,
+    pub next_attempt_at: DateTime<Utc>,
+    pub last_response_status: Option<u16>,
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub delivered_at: Option<DateTime<Utc>>,
+}
+
+impl WebhookDelivery {
+    pub fn new(webhook: &Webhook, event_type: WebhookEventType, payload: String) -> Self {
+        let now = Utc::now();
+        Self {
+            id: WebhookDeliveryId::default(),
+            webhook_id: webhook.id,
+            tenant: webhook.tenant.clone(),
+            event_type,
+            payload,
+            status: WebhookDeliveryStatus::Pending,
+            attempts: 0,
+            next_attempt_at: now,
+            last_response_status: None,
+            last_error: None,
+            created_at: now,
+            delivered_at: None,
+        }
+    }
+
+    pub fn record_success(&mut self, response_status: u16) {
+        self.attempts += 1;
+        self.status = WebhookDeliveryStatus::Delivered;
+        self.last_response_status = Some(response_status);
+        self.last_error = None;
+        self.delivered_at = Some(Utc::now());
+    }
+
+    // Schedules the next attempt, or dead-letters the delivery once the policy's
+    // attempts are used up
+    pub fn record_failure(
+        &mut self,
+        response_status: Option<u16>,
+        error: String,
+        policy: &WebhookRetryPolicy,
+    ) {
+        self.attempts += 1;
+        self.last_response_status = response_status;
+        self.last_error = Some(error);
+
+        if self.attempts >= policy.max_attempts {
+            self.status = WebhookDeliveryStatus::DeadLetter;
+        } else {
+            self.next_attempt_at = Utc::now() + policy.delay_after(self.attempts);
+        }
+    }
+
+    // Sends the delivery again as soon as possible, with a fresh set of attempts
+    pub fn replay(&mut self) {
+        self.status = WebhookDeliveryStatus::Pending;
+        self.attempts = 0;
+        self.next_attempt_at = Utc::now();
+        self.delivered_at = None;
+    }
+}
+
+// Filters for listing deliveries. Unset filters match everything.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WebhookDeliveryQuery {
+    pub webhook: Option<WebhookId>,
+    pub status: Option<WebhookDeliveryStatus>,
+    pub offset: u64,
+    pub limit: u64,
+}
+
+impl WebhookDeliveryQuery {
+    pub fn matches(&self, delivery: &WebhookDelivery) -> bool {
+        self.webhook
+            .is_none_or(|webhook| webhook == delivery.webhook_id)
+            && self.status.is_none_or(|status| status == delivery.status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{AuditRecord, ClientFingerprint};
+
+    fn webhook(events: Vec<WebhookEventType>) -> Result<Webhook> {
+        Webhook::new(
+            TenantId::default(),
+            "https://crm.example.com/hooks".to_owned(),
+            events,
+        )
+    }
+
+    #[test]
+    fn webhooks_need_a_url_and_events() {
+        let webhook = webhook(vec![WebhookEventType::Login, WebhookEventType::Login]).unwrap();
+        assert_eq!(webhook.events, vec![WebhookEventType::Login]);
+        assert!(webhook.secret.expose_secret().starts_with(SECRET_PREFIX));
+        assert!(webhook.subscribes_to(WebhookEventType::Login));
+        assert!(!webhook.subscribes_to(WebhookEventType::Logout));
+
+        assert!(self::webhook(vec![]).is_err());
+        assert!(Webhook::new(
+            TenantId::default(),
+            "ftp://crm.example.com".to_owned(),
+            vec![WebhookEventType::Login]
+        )
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn webhooks_only_reach_public_https_urls_by_default() {
+        let policy = WebhookUrlPolicy::default();
+        assert!(policy.check("https://93.184.215.14/hooks").await.is_ok());
+
+        for url in [
+            "http://93.184.215.14/hooks",
+            "https://127.0.0.1/hooks",
+            "https://localhost/hooks",
+            "https://10.0.0.8/hooks",
+            "https://192.168.1.1/hooks",
+            "https://169.254.169.254/latest/meta-data",
+            "https://100.64.0.1/hooks",
+            "https://0.0.0.0/hooks",
+            "https://[::1]/hooks",
+            "https://[fd00::1]/hooks",
+            "https://[::ffff:127.0.0.1]/hooks",
+            "https://[::]/hooks",
+            "https://[::7f00:1]/hooks",
+            "https://[64:ff9b::a9fe:a9fe]/hooks",
+            "https://[2002:a00:1::1]/hooks",
+            "https://[2002:7f00:1::]/hooks",
+        ] {
+            assert!(policy.check(url).await.is_err(), "{} was allowed", url);
+        }
+
+        assert!(WebhookUrlPolicy::Any
+            .check("http://127.0.0.1:8080/hooks")
+            .await
+            .is_ok());
+        assert!(WebhookUrlPolicy::Any
+            .check("ftp://127.0.0.1/hooks")
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn ipv6_addresses_embedding_ipv4_addresses_are_as_public_as_those() {
+        for (ip, public) in [
+            ("2606:4700::1111", true),
+            ("64:ff9b::5db8:d70e", true),
+            ("64:ff9b::a9fe:a9fe", false),
+            ("64:ff9b::7f00:1", false),
+            ("2002:5db8:d70e::1", true),
+            ("2002:a00:1::1", false),
+            ("2002:c0a8:101::", false),
+            ("::5db8:d70e", true),
+            ("::a00:8", false),
+            ("::7f00:1", false),
+            ("::1", false),
+            ("::", false),
+        ] {
+            assert_eq!(
+                is_public_address(ip.parse().unwrap()),
+                public,
+                "Failed for {}",
+                ip
+            );
+        }
+    }
+
+    #[test]
+    fn only_some_audit_events_are_sent_to_webhooks() {
+        let client = ClientFingerprint::default();
+        let event = |record| AuditEvent::new(TenantId::default(), record, &client);
+
+        assert_eq!(
+            WebhookEventType::of(&event(AuditRecord::new("login"))),
+            Some(WebhookEventType::Login)
+        );
+        assert_eq!(
+            WebhookEventType::of(&event(
+                AuditRecord::new("login").failure("Incorrect credentials")
+            )),
+            None
+        );
+        assert_eq!(
+            WebhookEventType::of(&event(
+                AuditRecord::new("two_fa_verification").failure("Incorrect credentials")
+            )),
+            Some(WebhookEventType::TwoFAFailure)
+        );
+        assert_eq!(
+            WebhookEventType::of(&event(AuditRecord::new("password_change"))),
+            None
+        );
+    }
+
+    #[test]
+    fn signatures_cover_the_timestamp_and_payload() {
+        let webhook = webhook(vec![WebhookEventType::Signup]).unwrap();
+
+        let signature = webhook.sign(1700000000, "{}");
+        assert!(signature.starts_with("sha256="));
+        assert_eq!(signature.len(), "sha256=".len() + 64);
+        assert_eq!(signature, webhook.sign(1700000000, "{}"));
+        assert_ne!(signature, webhook.sign(1700000001, "{}"));
+        assert_ne!(signature, webhook.sign(1700000000, "{ }"));
+    }
+
+    #[test]
+    fn failed_deliveries_back_off_exponentially_then_dead_letter() {
+        let policy = WebhookRetryPolicy {
+            base_delay_seconds: 10,
+            max_attempts: 3,
+        };
+        assert_eq!(policy.delay_after(1), Duration::seconds(10));
+        assert_eq!(policy.delay_after(2), Duration::seconds(20));
+        assert_eq!(policy.delay_after(3), Duration::seconds(40));
+
+        let webhook = webhook(vec![WebhookEventType::Signup]).unwrap();
+        let mut delivery =
+            WebhookDelivery::new(&webhook, WebhookEventType::Signup, "{}".to_owned());
+
+        delivery.record_failure(Some(500), "Receiver responded with 500".to_owned(), &policy);
+        assert_eq!(delivery.status, WebhookDeliveryStatus::Pending);
+        assert!(delivery.next_attempt_at > Utc::now() + Duration::seconds(5));
+        delivery.record_failure(None, "timed out".to_owned(), &policy);
+        assert_eq!(delivery.status, WebhookDeliveryStatus::Pending);
+        delivery.record_failure(Some(503), "Receiver responded with 503".to_owned(), &policy);
+        assert_eq!(delivery.status, WebhookDeliveryStatus::DeadLetter);
+        assert_eq!(delivery.attempts, 3);
+        assert_eq!(delivery.last_response_status, Some(503));
+
+        delivery.replay();
+        assert_eq!(delivery.status, WebhookDeliveryStatus::Pending);
+        assert_eq!(delivery.attempts, 0);
+        delivery.record_success(204);
+        assert_eq!(delivery.status, WebhookDeliveryStatus::Delivered);
+        assert_eq!(delivery.last_error, None);
+        assert!(delivery.delivered_at.is_some());
+    }
+}
@@ -1,26 +1,45 @@
 use crate::routes::{
-    login, 
-    logout, 
-    signup, 
-    verify_2fa, 
-    verify_token
+    accept_invitation, add_phone_number, approve_device, assign_role, change_password,
+    confirm_magic_link, consume_magic_link, create_api_key, create_identity_provider,
+    create_invitation, create_oauth_client, create_saml_provider, create_scim_group,
+    create_scim_user, create_webhook, delete_identity_provider, delete_oauth_client,
+    delete_saml_provider, delete_scim_group, delete_scim_user, delete_webhook, deny_device,
+    force_password_reset, get_device_authorization, get_scim_group, get_scim_user,
+    get_user_details, jwks, list_api_keys, list_audit_events, list_identity_providers,
+    list_invitations, list_oauth_clients, list_own_audit_events, list_saml_providers,
+    list_scim_groups, list_scim_users, list_sign_in_providers, list_trusted_devices,
+    list_user_roles, list_users, list_webhook_deliveries, list_webhooks, login, logout,
+    oidc_callback, oidc_login, patch_scim_group, patch_scim_user, reauthenticate,
+    replace_scim_group, replace_scim_user, replay_webhook_delivery, request_device_code,
+    request_magic_link, resend_2fa, reset_two_fa, revoke_api_key, revoke_invitation,
+    revoke_sessions, revoke_trusted_device, saml_acs, saml_login, saml_metadata,
+    set_two_fa_channel, set_user_status, signup, token, unassign_role, verify_2fa,
+    verify_phone_number, verify_token,
 };
 use app_state::AppState;
 use axum::{
-    http::{Method, StatusCode},
+    extract::{connect_info::IntoMakeServiceWithConnectInfo, ConnectInfo},
+    http::{
+        header::{CONTENT_TYPE, WWW_AUTHENTICATE},
+        Method, StatusCode,
+    },
+    middleware::AddExtension,
     response::{IntoResponse, Response},
-    routing::post,
+    routing::{delete, get, post, put},
     serve::Serve,
     Json, Router,
 };
-use domain::AuthAPIError;
+use domain::{AccountStatus, AuthAPIError, OAuthError, ScimError, SCIM_ERROR_SCHEMA};
 use redis::{Client, RedisResult};
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 use sqlx::{postgres::PgPoolOptions, PgPool};
-use std::error::Error;
+use std::{error::Error, net::SocketAddr};
 use tower_http::{cors::CorsLayer, services::ServeDir, trace::TraceLayer};
-use utils::tracing::{make_span_with_request_id, on_request, on_response};
+use utils::{
+    constants::SCIM_CONTENT_TYPE,
+    tracing::{make_span_with_request_id, on_request, on_response},
+};
 
 pub mod app_state;
 pub mod domain;
@@ -29,7 +48,10 @@ pub mod services;
 pub mod utils;
 
 pub struct Application {
-    server: Serve<Router, Router>,
+    server: Serve<
+        IntoMakeServiceWithConnectInfo<Router, SocketAddr>,
+        AddExtension<Router, ConnectInfo<SocketAddr>>,
+    >,
     pub address: String,
 }
 
@@ -42,7 +64,13 @@ impl Application {
         ];
 
         let cors = CorsLayer::new()
-            .allow_methods([Method::GET, Method::POST])
+            .allow_methods([
+                Method::GET,
+                Method::POST,
+                Method::PUT,
+                Method::PATCH,
+                Method::DELETE,
+            ])
             .allow_credentials(true)
             .allow_origin(allowed_origins);
 
@@ -50,9 +78,111 @@ impl Application {
             .nest_service("/", ServeDir::new("assets"))
             .route("/signup", post(signup))
             .route("/login", post(login))
+            .route("/login/magic-link", post(request_magic_link))
+            .route(
+                "/login/magic-link/consume",
+                get(confirm_magic_link).post(consume_magic_link),
+            )
             .route("/logout", post(logout))
             .route("/verify-2fa", post(verify_2fa))
+            .route("/resend-2fa", post(resend_2fa))
             .route("/verify-token", post(verify_token))
+            .route("/.well-known/jwks.json", get(jwks))
+            .route("/token", post(token))
+            .route("/oidc/providers", get(list_sign_in_providers))
+            .route("/oidc/:provider/login", get(oidc_login))
+            .route("/oidc/:provider/callback", get(oidc_callback))
+            .route("/saml/:provider/metadata", get(saml_metadata))
+            .route("/saml/:provider/login", get(saml_login))
+            .route("/saml/:provider/acs", post(saml_acs))
+            .route("/device/code", post(request_device_code))
+            .route(
+                "/device/authorizations/:user_code",
+                get(get_device_authorization),
+            )
+            .route(
+                "/device/authorizations/:user_code/approve",
+                post(approve_device),
+            )
+            .route("/device/authorizations/:user_code/deny", post(deny_device))
+            .route("/reauthenticate", post(reauthenticate))
+            .route("/password", post(change_password))
+            .route("/phone-number", post(add_phone_number))
+            .route("/phone-number/verify", post(verify_phone_number))
+            .route("/2fa-channel", post(set_two_fa_channel))
+            .route("/trusted-devices", get(list_trusted_devices))
+            .route("/trusted-devices/:id", delete(revoke_trusted_device))
+            .route("/api-keys", get(list_api_keys).post(create_api_key))
+            .route("/api-keys/:id", delete(revoke_api_key))
+            .route("/audit-events", get(list_own_audit_events))
+            .route("/users/:email/roles", get(list_user_roles))
+            .route(
+                "/users/:email/roles/:role",
+                put(assign_role).delete(unassign_role),
+            )
+            .route("/admin/users", get(list_users))
+            .route("/admin/users/:email", get(get_user_details))
+            .route("/admin/users/:email/status", put(set_user_status))
+            .route(
+                "/admin/users/:email/reset-password",
+                post(force_password_reset),
+            )
+            .route("/admin/users/:email/reset-2fa", post(reset_two_fa))
+            .route("/admin/users/:email/revoke-sessions", post(revoke_sessions))
+            .route(
+                "/admin/invitations",
+                get(list_invitations).post(create_invitation),
+            )
+            .route("/admin/invitations/:id", delete(revoke_invitation))
+            .route(
+                "/admin/clients",
+                get(list_oauth_clients).post(create_oauth_client),
+            )
+            .route("/admin/clients/:client_id", delete(delete_oauth_client))
+            .route(
+                "/admin/identity-providers",
+                get(list_identity_providers).post(create_identity_provider),
+            )
+            .route(
+                "/admin/identity-providers/:id",
+                delete(delete_identity_provider),
+            )
+            .route(
+                "/admin/saml-providers",
+                get(list_saml_providers).post(create_saml_provider),
+            )
+            .route("/admin/saml-providers/:id", delete(delete_saml_provider))
+            .route("/admin/audit-events", get(list_audit_events))
+            .route("/admin/webhooks", get(list_webhooks).post(create_webhook))
+            .route("/admin/webhooks/:id", delete(delete_webhook))
+            .route("/admin/webhook-deliveries", get(list_webhook_deliveries))
+            .route(
+                "/admin/webhook-deliveries/:id/replay",
+                post(replay_webhook_delivery),
+            )
+            .route("/invitations/accept", post(accept_invitation))
+            .route(
+                "/scim/v2/Users",
+                get(list_scim_users).post(create_scim_user),
+            )
+            .route(
+                "/scim/v2/Users/:id",
+                get(get_scim_user)
+                    .put(replace_scim_user)
+                    .patch(patch_scim_user)
+                    .delete(delete_scim_user),
+            )
+            .route(
+                "/scim/v2/Groups",
+                get(list_scim_groups).post(create_scim_group),
+            )
+            .route(
+                "/scim/v2/Groups/:id",
+                get(get_scim_group)
+                    .put(replace_scim_group)
+                    .patch(patch_scim_group)
+                    .delete(delete_scim_group),
+            )
             .with_state(app_state)
             .layer(cors)
             .layer(
@@ -64,7 +194,11 @@ impl Application {
 
         let listener = tokio::net::TcpListener::bind(address).await?;
         let address = listener.local_addr()?.to_string();
-        let server = axum::serve(listener, router);
+        // Connect info lets handlers see the peer address of each client
+        let server = axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<SocketAddr>(),
+        );
 
         Ok(Application { server, address })
     }
@@ -94,6 +228,60 @@ impl IntoResponse for AuthAPIError {
             }
             AuthAPIError::MissingToken => (StatusCode::BAD_REQUEST, "Missing token"),
             AuthAPIError::InvalidToken => (StatusCode::UNAUTHORIZED, "Invalid token"),
+            AuthAPIError::PhoneNumberNotVerified => {
+                (StatusCode::BAD_REQUEST, "Phone number not verified")
+            }
+            AuthAPIError::TooManyRequests => (StatusCode::TOO_MANY_REQUESTS, "Too many requests"),
+            AuthAPIError::TrustedDeviceNotFound => {
+                (StatusCode::NOT_FOUND, "Trusted device not found")
+            }
+            AuthAPIError::ReauthenticationRequired => {
+                (StatusCode::UNAUTHORIZED, "Reauthentication required")
+            }
+            AuthAPIError::MissingPermission => (StatusCode::FORBIDDEN, "Missing permission"),
+            AuthAPIError::ApiKeyNotAllowed => {
+                (StatusCode::FORBIDDEN, "API keys can't be used here")
+            }
+            AuthAPIError::UserNotFound => (StatusCode::NOT_FOUND, "User not found"),
+            AuthAPIError::RoleNotFound => (StatusCode::NOT_FOUND, "Role not found"),
+            AuthAPIError::AccountNotActive(status) => match status {
+                AccountStatus::Suspended => (StatusCode::FORBIDDEN, "Account suspended"),
+                AccountStatus::PendingVerification => {
+                    (StatusCode::FORBIDDEN, "Account pending verification")
+                }
+                AccountStatus::Active => (StatusCode::FORBIDDEN, "Account is not active"),
+            },
+            AuthAPIError::PasswordResetRequired => {
+                (StatusCode::FORBIDDEN, "Password reset required")
+            }
+            AuthAPIError::TenantNotFound => (StatusCode::NOT_FOUND, "Tenant not found"),
+            AuthAPIError::EmailDomainNotAllowed => {
+                (StatusCode::FORBIDDEN, "Email domain not allowed")
+            }
+            AuthAPIError::InvitationNotFound => (StatusCode::NOT_FOUND, "Invitation not found"),
+            AuthAPIError::ApiKeyNotFound => (StatusCode::NOT_FOUND, "API key not found"),
+            AuthAPIError::OAuthClientNotFound => (StatusCode::NOT_FOUND, "OAuth client not found"),
+            AuthAPIError::DeviceCodeNotFound => (StatusCode::NOT_FOUND, "Device code not found"),
+            AuthAPIError::IdentityProviderNotFound => {
+                (StatusCode::NOT_FOUND, "Identity provider not found")
+            }
+            AuthAPIError::IdentityProviderAlreadyExists => {
+                (StatusCode::CONFLICT, "Identity provider already exists")
+            }
+            AuthAPIError::FederatedLoginFailed => {
+                (StatusCode::UNAUTHORIZED, "Federated login failed")
+            }
+            AuthAPIError::FederatedIdentityNotLinked => {
+                (StatusCode::FORBIDDEN, "No account for this identity")
+            }
+            AuthAPIError::SignupDisabled => (StatusCode::FORBIDDEN, "Sign-up is disabled"),
+            AuthAPIError::ManagedByDirectory => {
+                (StatusCode::CONFLICT, "Account is managed by the directory")
+            }
+            AuthAPIError::WebhookNotFound => (StatusCode::NOT_FOUND, "Webhook not found"),
+            AuthAPIError::WebhookDeliveryNotFound => {
+                (StatusCode::NOT_FOUND, "Webhook delivery not found")
+            }
         };
         let body = Json(ErrorResponse {
             error: error_message.to_string(),
@@ -102,8 +290,132 @@ impl IntoResponse for AuthAPIError {
     }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OAuthErrorResponse {
+    pub error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_description: Option<String>,
+}
+
+impl IntoResponse for OAuthError {
+    fn into_response(self) -> Response {
+        log_error_chain(&self);
+        let (status, error, description) = match self {
+            OAuthError::InvalidRequest(description) => (
+                StatusCode::BAD_REQUEST,
+                "invalid_request",
+                Some(description),
+            ),
+            OAuthError::InvalidClient => (
+                StatusCode::UNAUTHORIZED,
+                "invalid_client",
+                Some("Client authentication failed"),
+            ),
+            OAuthError::InvalidScope => (StatusCode::BAD_REQUEST, "invalid_scope", None),
+            OAuthError::UnsupportedGrantType => {
+                (StatusCode::BAD_REQUEST, "unsupported_grant_type", None)
+            }
+            OAuthError::InvalidGrant => (StatusCode::BAD_REQUEST, "invalid_grant", None),
+            OAuthError::AuthorizationPending => {
+                (StatusCode::BAD_REQUEST, "authorization_pending", None)
+            }
+            OAuthError::SlowDown => (StatusCode::BAD_REQUEST, "slow_down", None),
+            OAuthError::AccessDenied => (StatusCode::BAD_REQUEST, "access_denied", None),
+            OAuthError::ExpiredToken => (StatusCode::BAD_REQUEST, "expired_token", None),
+            OAuthError::TooManyRequests => {
+                (StatusCode::TOO_MANY_REQUESTS, "too_many_requests", None)
+            }
+            OAuthError::UnexpectedError(_) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, "server_error", None)
+            }
+        };
+        let body = Json(OAuthErrorResponse {
+            error: error.to_owned(),
+            error_description: description.map(str::to_owned),
+        });
+
+        // Clients that sent their credentials with HTTP Basic are told to retry that way
+        if status == StatusCode::UNAUTHORIZED {
+            return (status, [(WWW_AUTHENTICATE, "Basic")], body).into_response();
+        }
+        (status, body).into_response()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScimErrorResponse {
+    pub schemas: Vec<String>,
+    // SCIM sends the HTTP status as a string
+    pub status: String,
+    #[serde(rename = "scimType", skip_serializing_if = "Option::is_none")]
+    pub scim_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl IntoResponse for ScimError {
+    fn into_response(self) -> Response {
+        log_error_chain(&self);
+        let (status, scim_type, detail) = match &self {
+            ScimError::InvalidFilter(detail) => (
+                StatusCode::BAD_REQUEST,
+                Some("invalidFilter"),
+                Some(detail.to_string()),
+            ),
+            ScimError::InvalidSyntax(detail) => (
+                StatusCode::BAD_REQUEST,
+                Some("invalidSyntax"),
+                Some(detail.clone()),
+            ),
+            ScimError::InvalidPath => (StatusCode::BAD_REQUEST, Some("invalidPath"), None),
+            ScimError::NoTarget => (StatusCode::BAD_REQUEST, Some("noTarget"), None),
+            ScimError::InvalidValue(detail) => (
+                StatusCode::BAD_REQUEST,
+                Some("invalidValue"),
+                Some(detail.to_string()),
+            ),
+            ScimError::Mutability(detail) => (
+                StatusCode::BAD_REQUEST,
+                Some("mutability"),
+                Some(detail.to_string()),
+            ),
+            ScimError::Uniqueness(detail) => (
+                StatusCode::CONFLICT,
+                Some("uniqueness"),
+                Some(detail.to_string()),
+            ),
+            ScimError::NotFound(detail) => (StatusCode::NOT_FOUND, None, Some(detail.to_string())),
+            ScimError::Unauthorized => (StatusCode::UNAUTHORIZED, None, None),
+            ScimError::Forbidden => (StatusCode::FORBIDDEN, None, None),
+            ScimError::UnexpectedError(_) => (StatusCode::INTERNAL_SERVER_ERROR, None, None),
+        };
+        let body = Json(ScimErrorResponse {
+            schemas: vec![SCIM_ERROR_SCHEMA.to_owned()],
+            status: status.as_u16().to_string(),
+            scim_type: scim_type.map(str::to_owned),
+            detail,
+        });
+
+        if status == StatusCode::UNAUTHORIZED {
+            return (
+                status,
+                [
+                    (WWW_AUTHENTICATE, "Bearer"),
+                    (CONTENT_TYPE, SCIM_CONTENT_TYPE),
+                ],
+                body,
+            )
+                .into_response();
+        }
+        (status, [(CONTENT_TYPE, SCIM_CONTENT_TYPE)], body).into_response()
+    }
+}
+
 pub async fn get_postgres_pool(url: &Secret<String>) -> Result<PgPool, sqlx::Error> {
-    PgPoolOptions::new().max_connections(5).connect(url.expose_secret()).await
+    PgPoolOptions::new()
+        .max_connections(5)
+        .connect(url.expose_secret())
+        .await
 }
 
 pub fn get_redis_client(redis_hostname: String) -> RedisResult<Client> {
@@ -1,37 +1,186 @@
 use auth_service::{
-    app_state::AppState, domain::Email, get_postgres_pool, get_redis_client, services::{data_stores::{
-        hashmap_two_fa_code_store::HashmapTwoFACodeStore, hashmap_user_store::HashmapUserStore, hashset_banned_token_store::HashsetBannedTokenStore, mock_email_client::MockEmailClient, postgres_user_store::PostgresUserStore, redis_banned_token_store::RedisBannedTokenStore
-    }, postmark_email_client::PostmarkEmailClient}, utils::{constants::{prod, DATABASE_URL, POSTMARK_AUTH_TOKEN, REDIS_HOST_NAME}, tracing::init_tracing}, Application
+    app_state::{
+        AppState, AuditLogType, SmsClientType, TwoFACodeStoreType, UserStoreType, WebhookStoreType,
+    },
+    domain::{Email, PhoneNumber, TenantId, TwoFAClientPolicy, WebhookUrlPolicy},
+    get_postgres_pool, get_redis_client,
+    services::{
+        data_stores::{
+            composite_user_store::{CompositeUserStore, SignupPolicy},
+            hashmap_two_fa_code_store::{spawn_expired_code_sweeper, HashmapTwoFACodeStore},
+            ldap_user_store::{LdapSettings, LdapUserStore},
+            postgres_api_key_store::PostgresApiKeyStore,
+            postgres_audit_log::PostgresAuditLog,
+            postgres_federated_identity_store::PostgresFederatedIdentityStore,
+            postgres_group_store::PostgresGroupStore,
+            postgres_identity_provider_store::PostgresIdentityProviderStore,
+            postgres_invitation_store::PostgresInvitationStore,
+            postgres_oauth_client_store::PostgresOAuthClientStore,
+            postgres_role_store::PostgresRoleStore,
+            postgres_saml_provider_store::PostgresSamlProviderStore,
+            postgres_tenant_store::PostgresTenantStore,
+            postgres_trusted_device_store::PostgresTrustedDeviceStore,
+            postgres_user_store::PostgresUserStore,
+            postgres_webhook_store::PostgresWebhookStore,
+            redis_banned_token_store::RedisBannedTokenStore,
+            redis_device_authorization_store::RedisDeviceAuthorizationStore,
+            redis_magic_link_store::RedisMagicLinkStore,
+            redis_phone_verification_store::RedisPhoneVerificationStore,
+            redis_saml_replay_cache::RedisSamlReplayCache,
+            redis_two_fa_code_store::RedisTwoFACodeStore,
+        },
+        disabled_sms_client::DisabledSmsClient,
+        heuristic_risk_evaluator::HeuristicRiskEvaluator,
+        maxmind_geoip::MaxMindGeoIp,
+        oidc_client::OidcClient,
+        postmark_email_client::PostmarkEmailClient,
+        twilio_sms_client::TwilioSmsClient,
+        webhook_dispatcher::{spawn_webhook_dispatcher, PublicAddressResolver, WebhookDispatcher},
+    },
+    utils::{
+        audit::{spawn_audit_checkpointer, verify_audit_chain},
+        constants::{
+            prod, ADMIN_API_TOKEN, DATABASE_URL, GEOIP_DATABASE_PATH, LDAP_BASE_DN, LDAP_BIND_DN,
+            LDAP_BIND_PASSWORD, LDAP_SIGNUPS, LDAP_TENANT, LDAP_URL, LDAP_USER_FILTER,
+            MAX_AUTH_AGE_SECONDS, POSTMARK_AUTH_TOKEN, REDIS_HOST_NAME, TWILIO_ACCOUNT_SID,
+            TWILIO_AUTH_TOKEN, TWO_FA_CODE_STORE, TWO_FA_REQUIRE_SAME_CLIENT,
+            WEBHOOK_ALLOW_PRIVATE_URLS,
+        },
+        tracing::init_tracing,
+    },
+    Application,
 };
+use jsonwebtoken::jwk::JwkSet;
 use reqwest::Client;
 use secrecy::Secret;
 use sqlx::PgPool;
-use std::sync::Arc;
+use std::{process::ExitCode, sync::Arc};
 use tokio::sync::RwLock;
 
 #[tokio::main]
-async fn main() {
+async fn main() -> ExitCode {
     color_eyre::install().expect("Failed to install color_eyre");
     init_tracing().expect("Failed to initialize tracing");
     let pg_pool = configure_postgresql().await;
-    let redis_conn = configure_redis();
-    let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-    let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(Arc::new(RwLock::new(redis_conn)))));
-    let two_fa_code_store = Arc::new(RwLock::new(HashmapTwoFACodeStore::default()));
+
+    // `auth-service verify-audit-log <jwks file>` checks the audit log instead of serving
+    // requests. It only needs the public keys the service publishes, not `JWT_SECRET`.
+    if std::env::args().nth(1).as_deref() == Some("verify-audit-log") {
+        let keys = read_jwks(std::env::args().nth(2));
+        let audit_log: AuditLogType = Arc::new(RwLock::new(PostgresAuditLog::new(pg_pool)));
+        return verify_audit_log(&audit_log, &keys).await;
+    }
+
+    let redis_conn = Arc::new(RwLock::new(configure_redis()));
+    let user_store = configure_user_store(pg_pool.clone());
+    let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_conn.clone())));
+    let two_fa_code_store = configure_two_fa_code_store(redis_conn.clone());
     let email_client = Arc::new(RwLock::new(configure_postmark_email_client()));
+    let magic_link_store = Arc::new(RwLock::new(RedisMagicLinkStore::new(redis_conn.clone())));
+    let sms_client = configure_sms_client();
+    let device_authorization_store = Arc::new(RwLock::new(RedisDeviceAuthorizationStore::new(
+        redis_conn.clone(),
+    )));
+    let phone_verification_store = Arc::new(RwLock::new(RedisPhoneVerificationStore::new(
+        redis_conn.clone(),
+    )));
+    let saml_replay_cache = Arc::new(RwLock::new(RedisSamlReplayCache::new(redis_conn)));
+    let role_store = Arc::new(RwLock::new(PostgresRoleStore::new(pg_pool.clone())));
+    let tenant_store = Arc::new(RwLock::new(PostgresTenantStore::new(pg_pool.clone())));
+    let invitation_store = Arc::new(RwLock::new(PostgresInvitationStore::new(pg_pool.clone())));
+    let api_key_store = Arc::new(RwLock::new(PostgresApiKeyStore::new(pg_pool.clone())));
+    let oauth_client_store = Arc::new(RwLock::new(PostgresOAuthClientStore::new(pg_pool.clone())));
+    let identity_provider_store = Arc::new(RwLock::new(PostgresIdentityProviderStore::new(
+        pg_pool.clone(),
+    )));
+    let federated_identity_store = Arc::new(RwLock::new(PostgresFederatedIdentityStore::new(
+        pg_pool.clone(),
+    )));
+    let saml_provider_store =
+        Arc::new(RwLock::new(PostgresSamlProviderStore::new(pg_pool.clone())));
+    let group_store = Arc::new(RwLock::new(PostgresGroupStore::new(pg_pool.clone())));
+    let audit_log: AuditLogType = Arc::new(RwLock::new(PostgresAuditLog::new(pg_pool.clone())));
+    spawn_audit_checkpointer(audit_log.clone(), prod::AUDIT_CHECKPOINT_INTERVAL);
+    let webhook_store: WebhookStoreType =
+        Arc::new(RwLock::new(PostgresWebhookStore::new(pg_pool.clone())));
+    spawn_webhook_dispatcher(
+        configure_webhook_dispatcher(webhook_store.clone()),
+        prod::webhook_dispatcher::INTERVAL,
+    );
+    let trusted_device_store = Arc::new(RwLock::new(PostgresTrustedDeviceStore::new(pg_pool)));
+    let risk_evaluator = Arc::new(RwLock::new(configure_risk_evaluator()));
 
     let app_state = AppState::new(
         user_store,
         banned_token_store,
         two_fa_code_store,
         email_client,
-    );
+    )
+    .with_magic_link_store(magic_link_store)
+    .with_sms_client(sms_client)
+    .with_phone_verification_store(phone_verification_store)
+    .with_trusted_device_store(trusted_device_store)
+    .with_role_store(role_store)
+    .with_tenant_store(tenant_store)
+    .with_invitation_store(invitation_store)
+    .with_api_key_store(api_key_store)
+    .with_oauth_client_store(oauth_client_store)
+    .with_device_authorization_store(device_authorization_store)
+    .with_identity_provider_store(identity_provider_store)
+    .with_federated_identity_store(federated_identity_store)
+    .with_saml_provider_store(saml_provider_store)
+    .with_saml_replay_cache(saml_replay_cache)
+    .with_group_store(group_store)
+    .with_audit_log(audit_log)
+    .with_webhook_store(webhook_store)
+    .with_webhook_url_policy(configure_webhook_url_policy())
+    .with_oidc_client(configure_oidc_client())
+    .with_risk_evaluator(risk_evaluator)
+    .with_two_fa_client_policy(configure_two_fa_client_policy())
+    .with_max_auth_age_seconds(*MAX_AUTH_AGE_SECONDS);
+
+    let app_state = match ADMIN_API_TOKEN.clone() {
+        Some(admin_token) => app_state.with_admin_token(admin_token),
+        None => app_state,
+    };
 
     let app = Application::build(app_state, prod::APP_ADDRESS)
         .await
         .expect("Failed to build app");
 
     app.run().await.expect("Failed to run app");
+    ExitCode::SUCCESS
+}
+
+// Walks the audit chain and reports the first broken link, if any
+async fn verify_audit_log(audit_log: &AuditLogType, keys: &JwkSet) -> ExitCode {
+    let report = verify_audit_chain(audit_log, keys)
+        .await
+        .expect("Failed to read the audit log");
+
+    match report.broken_link {
+        None => {
+            println!(
+                "Audit log intact: {} chained entries, {} signed checkpoints",
+                report.entries, report.checkpoints
+            );
+            ExitCode::SUCCESS
+        }
+        Some(broken_link) => {
+            println!(
+                "Audit log broken after {} intact entries: {}",
+                report.entries, broken_link
+            );
+            ExitCode::FAILURE
+        }
+    }
+}
+
+// The keys checkpoints are checked against, as saved from `/.well-known/jwks.json`
+fn read_jwks(path: Option<String>) -> JwkSet {
+    let path = path.expect("Usage: auth-service verify-audit-log <jwks file>");
+    let jwks = std::fs::read_to_string(&path).expect("Failed to read the JWKS file");
+    serde_json::from_str(&jwks).expect("Failed to parse the JWKS file")
 }
 
 async fn configure_postgresql() -> PgPool {
@@ -56,6 +205,72 @@ fn configure_redis() -> redis::Connection {
         .expect("Failed to get Redis connection")
 }
 
+// Users come from Postgres, and also from an LDAP directory when LDAP_URL is set
+fn configure_user_store(pg_pool: PgPool) -> UserStoreType {
+    let local: UserStoreType = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
+
+    let Some(url) = LDAP_URL.clone() else {
+        return local;
+    };
+
+    let directory = LdapUserStore::new(LdapSettings {
+        url,
+        base_dn: LDAP_BASE_DN.to_owned(),
+        user_filter: LDAP_USER_FILTER.to_owned(),
+        bind_dn: LDAP_BIND_DN.clone(),
+        bind_password: LDAP_BIND_PASSWORD.clone(),
+        tenant: TenantId::parse(LDAP_TENANT.to_owned()).expect("Invalid LDAP_TENANT"),
+        timeout: prod::ldap::TIMEOUT,
+    });
+    let signups = match LDAP_SIGNUPS.as_str() {
+        "delegate" => SignupPolicy::Delegate,
+        "reject" => SignupPolicy::Reject,
+        other => panic!(
+            "Unknown LDAP_SIGNUPS '{}': expected 'delegate' or 'reject'",
+            other
+        ),
+    };
+
+    Arc::new(RwLock::new(
+        CompositeUserStore::new(directory, local).with_signup_policy(signups),
+    ))
+}
+
+fn configure_two_fa_code_store(redis_conn: Arc<RwLock<redis::Connection>>) -> TwoFACodeStoreType {
+    match TWO_FA_CODE_STORE.as_str() {
+        "redis" => Arc::new(RwLock::new(RedisTwoFACodeStore::new(redis_conn))),
+        "memory" => {
+            let store = Arc::new(RwLock::new(HashmapTwoFACodeStore::default()));
+            spawn_expired_code_sweeper(store.clone(), prod::TWO_FA_CODE_SWEEP_INTERVAL);
+            store
+        }
+        other => panic!(
+            "Unknown TWO_FA_CODE_STORE '{}': expected 'memory' or 'redis'",
+            other
+        ),
+    }
+}
+
+fn configure_two_fa_client_policy() -> TwoFAClientPolicy {
+    if *TWO_FA_REQUIRE_SAME_CLIENT {
+        TwoFAClientPolicy::SameClient
+    } else {
+        TwoFAClientPolicy::AnyClient
+    }
+}
+
+// Impossible-travel checks only run when a GeoIP database is configured
+fn configure_risk_evaluator() -> HeuristicRiskEvaluator {
+    let evaluator = HeuristicRiskEvaluator::default();
+
+    match GEOIP_DATABASE_PATH.as_ref() {
+        Some(path) => evaluator.with_geoip(Arc::new(
+            MaxMindGeoIp::open(path).expect("Failed to open GeoIP database"),
+        )),
+        None => evaluator,
+    }
+}
+
 fn configure_postmark_email_client() -> PostmarkEmailClient {
     let http_client = Client::builder()
         .timeout(prod::email_client::TIMEOUT)
@@ -69,3 +284,61 @@ fn configure_postmark_email_client() -> PostmarkEmailClient {
         http_client,
     )
 }
+
+fn configure_oidc_client() -> OidcClient {
+    let http_client = Client::builder()
+        .timeout(prod::oidc_client::TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client");
+
+    OidcClient::new(http_client)
+}
+
+fn configure_webhook_dispatcher(webhook_store: WebhookStoreType) -> WebhookDispatcher {
+    let url_policy = configure_webhook_url_policy();
+
+    let mut http_client = Client::builder()
+        .timeout(prod::webhook_dispatcher::TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none());
+    // The addresses connected to have to pass the policy too, not only the URL
+    if url_policy == WebhookUrlPolicy::PublicHttps {
+        http_client = http_client.dns_resolver(Arc::new(PublicAddressResolver));
+    }
+    let http_client = http_client.build().expect("Failed to build HTTP client");
+
+    WebhookDispatcher::new(webhook_store, http_client).with_url_policy(url_policy)
+}
+
+// Local receivers are only allowed when developing
+fn configure_webhook_url_policy() -> WebhookUrlPolicy {
+    if *WEBHOOK_ALLOW_PRIVATE_URLS {
+        WebhookUrlPolicy::Any
+    } else {
+        WebhookUrlPolicy::PublicHttps
+    }
+}
+
+// SMS is optional; without Twilio, users can only get their 2FA codes by email
+fn configure_sms_client() -> SmsClientType {
+    match (TWILIO_ACCOUNT_SID.clone(), TWILIO_AUTH_TOKEN.clone()) {
+        (Some(account_sid), Some(auth_token)) => Arc::new(RwLock::new(
+            configure_twilio_sms_client(account_sid, auth_token),
+        )),
+        _ => Arc::new(RwLock::new(DisabledSmsClient)),
+    }
+}
+
+fn configure_twilio_sms_client(account_sid: String, auth_token: Secret<String>) -> TwilioSmsClient {
+    let http_client = Client::builder()
+        .timeout(prod::sms_client::TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client");
+
+    TwilioSmsClient::new(
+        prod::sms_client::BASE_URL.to_owned(),
+        PhoneNumber::parse(Secret::new(prod::sms_client::SENDER.to_owned())).unwrap(),
+        account_sid,
+        auth_token,
+        http_client,
+    )
+}
@@ -0,0 +1,354 @@
+use std::fmt::Display;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AccountStatus, AuditRecord, AuthAPIError, Email, SuspendedBy, User, UserQuery,
+        UserStoreError,
+    },
+    utils::{
+        auth::revoke_all_sessions,
+        extractors::{AdminCaller, Auditor, CurrentTenant},
+    },
+};
+
+const DEFAULT_PER_PAGE: u64 = 20;
+const MAX_PER_PAGE: u64 = 100;
+
+#[tracing::instrument(name = "Listing users", skip_all)]
+pub async fn list_users(
+    State(state): State<AppState>,
+    _admin: AdminCaller,
+    CurrentTenant(tenant): CurrentTenant,
+    Query(request): Query<ListUsersRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let page = request.page.unwrap_or(1).max(1);
+    let per_page = request
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+
+    let query = UserQuery {
+        search: request.search.filter(|search| !search.is_empty()),
+        offset: (page - 1).saturating_mul(per_page),
+        limit: per_page,
+    };
+
+    let user_page = state
+        .user_store
+        .read()
+        .await
+        .list_users(&tenant.id, &query)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(ListUsersResponse {
+            users: user_page.users.iter().map(Into::into).collect(),
+            page,
+            per_page,
+            total: user_page.total,
+        }),
+    ))
+}
+
+#[tracing::instrument(name = "Getting user details", skip_all)]
+pub async fn get_user_details(
+    State(state): State<AppState>,
+    _admin: AdminCaller,
+    CurrentTenant(tenant): CurrentTenant,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = parse_email(email)?;
+    let user = state
+        .user_store
+        .read()
+        .await
+        .get_user(&tenant.id, &email)
+        .await
+        .map_err(map_user_store_error)?;
+
+    let grants = state
+        .role_store
+        .read()
+        .await
+        .get_grants(&tenant.id, &email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(UserDetailsResponse {
+            user: (&user).into(),
+            roles: grants
+                .roles
+                .iter()
+                .map(|role| role.as_ref().to_owned())
+                .collect(),
+        }),
+    ))
+}
+
+// Anything but `active` blocks every way of logging in and ends the sessions the user
+// already has
+#[tracing::instrument(name = "Setting user status", skip_all)]
+pub async fn set_user_status(
+    State(state): State<AppState>,
+    admin: AdminCaller,
+    auditor: Auditor,
+    CurrentTenant(tenant): CurrentTenant,
+    Path(email): Path<String>,
+    Json(request): Json<SetUserStatusRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let reason = request.reason.filter(|reason| !reason.trim().is_empty());
+    let record = AuditRecord::new("user_status_changed").target(request.status.as_str());
+    let record = match &reason {
+        Some(reason) => record.reason(reason),
+        None => record,
+    };
+    let email = parse_target_email(&auditor, &admin, &record, email).await?;
+    let suspended_by = (request.status == AccountStatus::Suspended).then_some(SuspendedBy::Admin);
+    let result = async {
+        state
+            .user_store
+            .write()
+            .await
+            .set_status(&tenant.id, &email, request.status, reason, suspended_by)
+            .await
+            .map_err(map_user_store_error)?;
+
+        if request.status != AccountStatus::Active {
+            revoke_all_sessions(&tenant.id, &email, &state.banned_token_store)
+                .await
+                .map_err(AuthAPIError::UnexpectedError)?;
+        }
+
+        Ok(())
+    }
+    .await;
+
+    audit(&auditor, &admin, record.user(&email), &result).await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// The user can no longer log in with their password. They sign in with a magic link
+// instead and choose a new password through `/password`.
+#[tracing::instrument(name = "Forcing password reset", skip_all)]
+pub async fn force_password_reset(
+    State(state): State<AppState>,
+    admin: AdminCaller,
+    auditor: Auditor,
+    CurrentTenant(tenant): CurrentTenant,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let record = AuditRecord::new("password_reset_forced");
+    let email = parse_target_email(&auditor, &admin, &record, email).await?;
+    let result = async {
+        state
+            .user_store
+            .write()
+            .await
+            .require_password_reset(&tenant.id, &email)
+            .await
+            .map_err(map_user_store_error)?;
+
+        revoke_all_sessions(&tenant.id, &email, &state.banned_token_store)
+            .await
+            .map_err(AuthAPIError::UnexpectedError)
+    }
+    .await;
+
+    audit(&auditor, &admin, record.user(&email), &result).await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// For users who lost their phone: codes go back to their email and no browser can
+// skip 2FA until it is trusted again
+#[tracing::instrument(name = "Resetting 2FA", skip_all)]
+pub async fn reset_two_fa(
+    State(state): State<AppState>,
+    admin: AdminCaller,
+    auditor: Auditor,
+    CurrentTenant(tenant): CurrentTenant,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let record = AuditRecord::new("two_fa_reset");
+    let email = parse_target_email(&auditor, &admin, &record, email).await?;
+    let result = async {
+        state
+            .user_store
+            .write()
+            .await
+            .reset_two_fa(&tenant.id, &email)
+            .await
+            .map_err(map_user_store_error)?;
+
+        state
+            .trusted_device_store
+            .write()
+            .await
+            .revoke_all_devices(&tenant.id, &email)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+    }
+    .await;
+
+    audit(&auditor, &admin, record.user(&email), &result).await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[tracing::instrument(name = "Revoking sessions", skip_all)]
+pub async fn revoke_sessions(
+    State(state): State<AppState>,
+    admin: AdminCaller,
+    auditor: Auditor,
+    CurrentTenant(tenant): CurrentTenant,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let record = AuditRecord::new("sessions_revoked");
+    let email = parse_target_email(&auditor, &admin, &record, email).await?;
+    let result = async {
+        state
+            .user_store
+            .read()
+            .await
+            .get_user(&tenant.id, &email)
+            .await
+            .map_err(map_user_store_error)?;
+
+        revoke_all_sessions(&tenant.id, &email, &state.banned_token_store)
+            .await
+            .map_err(AuthAPIError::UnexpectedError)
+    }
+    .await;
+
+    audit(&auditor, &admin, record.user(&email), &result).await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn parse_email(email: String) -> Result<Email, AuthAPIError> {
+    Email::parse(Secret::new(email)).map_err(|_| AuthAPIError::UserNotFound)
+}
+
+fn map_user_store_error(e: UserStoreError) -> AuthAPIError {
+    match e {
+        UserStoreError::UserNotFound => AuthAPIError::UserNotFound,
+        UserStoreError::ReadOnly => AuthAPIError::ManagedByDirectory,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+// Records an action an admin took, naming them as the actor. Attempts that failed are
+// recorded too, with the error as the reason.
+pub(super) async fn audit<T, E: Display>(
+    auditor: &Auditor,
+    admin: &AdminCaller,
+    record: AuditRecord,
+    result: &Result<T, E>,
+) {
+    auditor
+        .record_result(record.actor(admin.name()), result)
+        .await;
+}
+
+// The email of the user an admin acts on. An address that isn't one is recorded as a
+// failed attempt at `record`.
+async fn parse_target_email(
+    auditor: &Auditor,
+    admin: &AdminCaller,
+    record: &AuditRecord,
+    email: String,
+) -> Result<Email, AuthAPIError> {
+    let email = parse_email(email);
+    if email.is_err() {
+        audit(auditor, admin, record.clone(), &email).await;
+    }
+    email
+}
+
+#[derive(Deserialize)]
+pub struct ListUsersRequest {
+    pub search: Option<String>,
+    pub page: Option<u64>,
+    #[serde(rename = "perPage")]
+    pub per_page: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct SetUserStatusRequest {
+    pub status: AccountStatus,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListUsersResponse {
+    pub users: Vec<UserSummaryResponse>,
+    pub page: u64,
+    #[serde(rename = "perPage")]
+    pub per_page: u64,
+    pub total: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserSummaryResponse {
+    pub email: String,
+    #[serde(rename = "requires2FA")]
+    pub requires_2fa: bool,
+    #[serde(rename = "twoFAChannel")]
+    pub two_fa_channel: String,
+    #[serde(rename = "phoneNumber")]
+    pub phone_number: Option<String>,
+    pub status: AccountStatus,
+    #[serde(rename = "statusReason")]
+    pub status_reason: Option<String>,
+    #[serde(rename = "suspendedBy")]
+    pub suspended_by: Option<SuspendedBy>,
+    #[serde(rename = "statusChangedAt")]
+    pub status_changed_at: String,
+    #[serde(rename = "passwordResetRequired")]
+    pub password_reset_required: bool,
+}
+
+impl From<&User> for UserSummaryResponse {
+    fn from(user: &User) -> Self {
+        Self {
+            email: user.email.as_ref().expose_secret().to_owned(),
+            requires_2fa: user.requires_2fa,
+            two_fa_channel: user.two_fa_channel.as_str().to_owned(),
+            phone_number: user
+                .phone_number
+                .as_ref()
+                .map(|phone_number| phone_number.as_ref().expose_secret().to_owned()),
+            status: user.status,
+            status_reason: user.status_reason.clone(),
+            suspended_by: user.suspended_by,
+            status_changed_at: user.status_changed_at.to_rfc3339(),
+            password_reset_required: user.password_reset_required,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserDetailsResponse {
+    #[serde(flatten)]
+    pub user: UserSummaryResponse,
+    pub roles: Vec<String>,
+}
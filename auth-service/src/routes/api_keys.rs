@@ -0,0 +1,195 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{ApiKey, ApiKeyId, ApiKeyStoreError, AuditRecord, AuthAPIError, Permission},
+    utils::{
+        constants::{API_KEY_DEFAULT_TTL_DAYS, API_KEY_MAX_TTL_DAYS},
+        extractors::{Auditor, InteractiveUser, RecentlyAuthenticatedUser},
+    },
+};
+
+const MAX_NAME_LENGTH: usize = 100;
+
+// Needs a recent login, so an API key can't be used to mint more keys
+#[tracing::instrument(name = "Creating API key", skip_all)]
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    RecentlyAuthenticatedUser(user): RecentlyAuthenticatedUser,
+    auditor: Auditor,
+    Json(request): Json<CreateApiKeyRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let record = AuditRecord::new("api_key_created").user(&user.email);
+    let result = async {
+        let name = request.name.trim().to_owned();
+        if name.is_empty() || name.len() > MAX_NAME_LENGTH {
+            return Err(AuthAPIError::InvalidCredentials);
+        }
+
+        let ttl_days = request.expires_in_days.unwrap_or(API_KEY_DEFAULT_TTL_DAYS);
+        if !(1..=API_KEY_MAX_TTL_DAYS).contains(&ttl_days) {
+            return Err(AuthAPIError::InvalidCredentials);
+        }
+
+        let scopes = request
+            .scopes
+            .into_iter()
+            .map(Permission::parse)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+        // Keys can't be given permissions their owner doesn't have
+        let grants = state
+            .role_store
+            .read()
+            .await
+            .get_grants(&user.tenant, &user.email)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+        if !scopes
+            .iter()
+            .all(|scope| grants.permissions.contains(scope))
+        {
+            return Err(AuthAPIError::MissingPermission);
+        }
+
+        let (api_key, key) = ApiKey::generate(
+            user.tenant.clone(),
+            user.email.clone(),
+            name,
+            scopes,
+            chrono::Duration::days(ttl_days),
+        );
+
+        state
+            .api_key_store
+            .write()
+            .await
+            .add_key(api_key.clone(), key.clone())
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        Ok((api_key, key))
+    }
+    .await;
+
+    let record = match &result {
+        Ok((api_key, _)) => record.target(api_key.id),
+        Err(_) => record,
+    };
+    auditor.record_result(record, &result).await;
+    let (api_key, key) = result?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateApiKeyResponse {
+            api_key: (&api_key).into(),
+            key: key.expose_secret().to_owned(),
+        }),
+    ))
+}
+
+#[tracing::instrument(name = "Listing API keys", skip_all)]
+pub async fn list_api_keys(
+    State(state): State<AppState>,
+    InteractiveUser(user): InteractiveUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let api_keys = state
+        .api_key_store
+        .read()
+        .await
+        .list_keys(&user.tenant, &user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response: Vec<ApiKeyResponse> = api_keys.iter().map(Into::into).collect();
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+// Keys are checked against the store on every request, so a revoked key stops
+// working straight away
+#[tracing::instrument(name = "Revoking API key", skip_all)]
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    InteractiveUser(user): InteractiveUser,
+    auditor: Auditor,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let record = AuditRecord::new("api_key_revoked")
+        .user(&user.email)
+        .target(&id);
+    let result = async {
+        let id = ApiKeyId::parse(&id).map_err(|_| AuthAPIError::ApiKeyNotFound)?;
+
+        state
+            .api_key_store
+            .write()
+            .await
+            .revoke_key(&user.tenant, &user.email, &id)
+            .await
+            .map_err(|e| match e {
+                ApiKeyStoreError::ApiKeyNotFound => AuthAPIError::ApiKeyNotFound,
+                e => AuthAPIError::UnexpectedError(e.into()),
+            })
+    }
+    .await;
+
+    auditor.record_result(record, &result).await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    #[serde(rename = "expiresInDays")]
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateApiKeyResponse {
+    #[serde(flatten)]
+    pub api_key: ApiKeyResponse,
+    // Only ever shown here
+    pub key: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ApiKeyResponse {
+    pub id: String,
+    pub name: String,
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+}
+
+impl From<&ApiKey> for ApiKeyResponse {
+    fn from(api_key: &ApiKey) -> Self {
+        Self {
+            id: api_key.id.to_string(),
+            name: api_key.name.clone(),
+            prefix: api_key.prefix.clone(),
+            scopes: api_key
+                .scopes
+                .iter()
+                .map(|scope| scope.as_ref().to_owned())
+                .collect(),
+            created_at: api_key.created_at.to_rfc3339(),
+            expires_at: api_key.expires_at.to_rfc3339(),
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditEvent, AuditOutcome, AuditQuery, AuthAPIError},
+    utils::extractors::{AdminCaller, CurrentTenant, InteractiveUser},
+};
+
+const DEFAULT_PER_PAGE: u64 = 20;
+const MAX_PER_PAGE: u64 = 100;
+const RECENT_ACTIVITY_LIMIT: u64 = 50;
+
+// Newest first. Every filter is optional; `since` and `until` are RFC 3339 timestamps.
+#[tracing::instrument(name = "Listing audit events", skip_all)]
+pub async fn list_audit_events(
+    State(state): State<AppState>,
+    _admin: AdminCaller,
+    CurrentTenant(tenant): CurrentTenant,
+    Query(request): Query<ListAuditEventsRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let page = request.page.unwrap_or(1).max(1);
+    let per_page = request
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+
+    let outcome = request
+        .outcome
+        .map(|outcome| AuditOutcome::parse(&outcome))
+        .transpose()
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let query = AuditQuery {
+        event: request.event.filter(|event| !event.is_empty()),
+        actor: request.actor.filter(|actor| !actor.is_empty()),
+        user: request.user.filter(|user| !user.is_empty()),
+        outcome,
+        since: request.since.as_deref().map(parse_timestamp).transpose()?,
+        until: request.until.as_deref().map(parse_timestamp).transpose()?,
+        offset: (page - 1).saturating_mul(per_page),
+        limit: per_page,
+    };
+
+    let events = state
+        .audit_log
+        .read()
+        .await
+        .query(&tenant.id, &query)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(ListAuditEventsResponse {
+            events: events.iter().map(Into::into).collect(),
+            page,
+            per_page,
+        }),
+    ))
+}
+
+// The user's own recent security activity, including what admins did to their account
+#[tracing::instrument(name = "Listing own audit events", skip_all)]
+pub async fn list_own_audit_events(
+    State(state): State<AppState>,
+    InteractiveUser(user): InteractiveUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let query = AuditQuery {
+        user: Some(user.email.as_ref().expose_secret().to_owned()),
+        limit: RECENT_ACTIVITY_LIMIT,
+        ..AuditQuery::default()
+    };
+
+    let events = state
+        .audit_log
+        .read()
+        .await
+        .query(&user.tenant, &query)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response: Vec<AuditEventResponse> = events.iter().map(Into::into).collect();
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+fn parse_timestamp(timestamp: &str) -> Result<DateTime<Utc>, AuthAPIError> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .map(|timestamp| timestamp.with_timezone(&Utc))
+        .map_err(|_| AuthAPIError::InvalidCredentials)
+}
+
+#[derive(Deserialize)]
+pub struct ListAuditEventsRequest {
+    pub event: Option<String>,
+    pub actor: Option<String>,
+    pub user: Option<String>,
+    pub outcome: Option<String>,
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub page: Option<u64>,
+    #[serde(rename = "perPage")]
+    pub per_page: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListAuditEventsResponse {
+    pub events: Vec<AuditEventResponse>,
+    pub page: u64,
+    #[serde(rename = "perPage")]
+    pub per_page: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditEventResponse {
+    pub id: String,
+    #[serde(rename = "occurredAt")]
+    pub occurred_at: String,
+    pub event: String,
+    pub outcome: AuditOutcome,
+    pub actor: Option<String>,
+    pub user: Option<String>,
+    pub target: Option<String>,
+    pub reason: Option<String>,
+    #[serde(rename = "ipAddress")]
+    pub ip_address: Option<String>,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+}
+
+impl From<&AuditEvent> for AuditEventResponse {
+    fn from(event: &AuditEvent) -> Self {
+        Self {
+            id: event.id.to_string(),
+            occurred_at: event.occurred_at.to_rfc3339(),
+            event: event.event.clone(),
+            outcome: event.outcome,
+            actor: event.actor.clone(),
+            user: event.user.clone(),
+            target: event.target.clone(),
+            reason: event.reason.clone(),
+            ip_address: event.ip_address.map(|ip| ip.to_string()),
+            user_agent: event.user_agent.clone(),
+        }
+    }
+}
@@ -0,0 +1,326 @@
+use axum::{
+    extract::{Path, State},
+    http::{header::CACHE_CONTROL, StatusCode},
+    response::IntoResponse,
+    Form, Json,
+};
+use chrono::Utc;
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AccountStatus, AuditRecord, AuthAPIError, AuthMethod, ClientId, DeviceAuthorization,
+        DeviceAuthorizationStatus, DeviceAuthorizationStoreError, DeviceCode, OAuthError, UserCode,
+        UserStoreError,
+    },
+    utils::{
+        audit::record_audit_event,
+        auth::{generate_auth_token, TOKEN_TTL_SECONDS},
+        constants::{AUTH_SERVICE_URL, DEVICE_CODE_POLL_INTERVAL_SECONDS, DEVICE_CODE_TTL_SECONDS},
+        extractors::{
+            Auditor, AuthenticatedUser, CurrentTenant, InteractiveUser, RecentlyAuthenticatedUser,
+        },
+    },
+};
+
+use super::{AccessTokenRequest, AccessTokenResponse};
+
+// Starts a device sign-in (RFC 8628). The device shows the user code and verification
+// URI to the user, then polls `/token` with the device code until they decide.
+#[tracing::instrument(name = "Requesting device code", skip_all)]
+pub async fn request_device_code(
+    State(state): State<AppState>,
+    CurrentTenant(tenant): CurrentTenant,
+    auditor: Auditor,
+    Form(request): Form<DeviceCodeRequest>,
+) -> Result<impl IntoResponse, OAuthError> {
+    let client_id = request
+        .client_id
+        .ok_or(OAuthError::InvalidRequest("Missing client_id"))?;
+    let client_id = ClientId::parse(client_id).map_err(|_| OAuthError::InvalidClient)?;
+
+    let authorization = DeviceAuthorization::new(
+        client_id,
+        tenant.id,
+        DEVICE_CODE_POLL_INTERVAL_SECONDS,
+        chrono::Duration::seconds(DEVICE_CODE_TTL_SECONDS),
+    );
+
+    state
+        .device_authorization_store
+        .write()
+        .await
+        .add_authorization(authorization.clone())
+        .await
+        .map_err(|e| OAuthError::UnexpectedError(e.into()))?;
+
+    let record = AuditRecord::new("device_code_requested").actor(authorization.client_id.as_ref());
+    auditor.record(record).await;
+
+    let verification_uri = format!("{}/device.html", AUTH_SERVICE_URL.as_str());
+    Ok((
+        StatusCode::OK,
+        [(CACHE_CONTROL, "no-store")],
+        Json(DeviceCodeResponse {
+            device_code: authorization
+                .device_code
+                .as_ref()
+                .expose_secret()
+                .to_owned(),
+            user_code: authorization.user_code.to_string(),
+            verification_uri_complete: format!(
+                "{}?user_code={}",
+                verification_uri, authorization.user_code
+            ),
+            verification_uri,
+            expires_in: DEVICE_CODE_TTL_SECONDS,
+            interval: authorization.interval_seconds,
+        }),
+    ))
+}
+
+// Lets the approval page show which client is asking before the user decides
+#[tracing::instrument(name = "Getting device authorization", skip_all)]
+pub async fn get_device_authorization(
+    State(state): State<AppState>,
+    InteractiveUser(user): InteractiveUser,
+    Path(user_code): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let authorization = pending_authorization(&state, &user, user_code).await?;
+
+    Ok((
+        StatusCode::OK,
+        Json(DeviceAuthorizationResponse {
+            user_code: authorization.user_code.to_string(),
+            client_id: authorization.client_id.as_ref().to_owned(),
+            expires_at: authorization.expires_at.to_rfc3339(),
+        }),
+    ))
+}
+
+// The device gets a token for the user, so this needs a recent login like other
+// sensitive routes
+#[tracing::instrument(name = "Approving device", skip_all)]
+pub async fn approve_device(
+    State(state): State<AppState>,
+    RecentlyAuthenticatedUser(user): RecentlyAuthenticatedUser,
+    auditor: Auditor,
+    Path(user_code): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    decide(&state, &auditor, user, user_code, true).await
+}
+
+#[tracing::instrument(name = "Denying device", skip_all)]
+pub async fn deny_device(
+    State(state): State<AppState>,
+    InteractiveUser(user): InteractiveUser,
+    auditor: Auditor,
+    Path(user_code): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    decide(&state, &auditor, user, user_code, false).await
+}
+
+async fn decide(
+    state: &AppState,
+    auditor: &Auditor,
+    user: AuthenticatedUser,
+    user_code: String,
+    approved: bool,
+) -> Result<StatusCode, AuthAPIError> {
+    let event = if approved {
+        "device_approved"
+    } else {
+        "device_denied"
+    };
+    let record = AuditRecord::new(event).user(&user.email);
+    let result = async {
+        let mut authorization = pending_authorization(state, &user, user_code).await?;
+        authorization.decide(user.email.clone(), approved);
+
+        state
+            .device_authorization_store
+            .write()
+            .await
+            .update_authorization(authorization.clone())
+            .await
+            .map_err(map_device_authorization_store_error)?;
+
+        Ok(authorization)
+    }
+    .await;
+
+    let record = match &result {
+        Ok(authorization) => record.target(authorization.client_id.as_ref()),
+        Err(_) => record,
+    };
+    auditor.record_result(record, &result).await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Codes started for another tenant, or already decided, are treated as unknown
+async fn pending_authorization(
+    state: &AppState,
+    user: &AuthenticatedUser,
+    user_code: String,
+) -> Result<DeviceAuthorization, AuthAPIError> {
+    let user_code = UserCode::parse(user_code).map_err(|_| AuthAPIError::DeviceCodeNotFound)?;
+
+    let authorization = state
+        .device_authorization_store
+        .read()
+        .await
+        .get_by_user_code(&user_code)
+        .await
+        .map_err(map_device_authorization_store_error)?;
+
+    if authorization.tenant != user.tenant || !authorization.is_pending() {
+        return Err(AuthAPIError::DeviceCodeNotFound);
+    }
+
+    Ok(authorization)
+}
+
+fn map_device_authorization_store_error(e: DeviceAuthorizationStoreError) -> AuthAPIError {
+    match e {
+        DeviceAuthorizationStoreError::AuthorizationNotFound => AuthAPIError::DeviceCodeNotFound,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+// The `/token` side of the device grant. Devices that poll faster than their interval
+// are told to slow down; once the user has decided, the device code can't be used again.
+pub(super) async fn device_code_grant(
+    state: AppState,
+    auditor: &Auditor,
+    request: AccessTokenRequest,
+) -> Result<impl IntoResponse, OAuthError> {
+    let client_id = request
+        .client_id
+        .ok_or(OAuthError::InvalidRequest("Missing client_id"))?;
+    let client_id = ClientId::parse(client_id).map_err(|_| OAuthError::InvalidClient)?;
+    let device_code = request
+        .device_code
+        .ok_or(OAuthError::InvalidRequest("Missing device_code"))?;
+    let device_code = DeviceCode::parse(device_code).map_err(|_| OAuthError::InvalidGrant)?;
+
+    let mut store = state.device_authorization_store.write().await;
+
+    // Codes are forgotten once they expire, so unknown codes are reported as expired
+    let mut authorization = match store.get_by_device_code(&device_code).await {
+        Ok(authorization) => authorization,
+        Err(DeviceAuthorizationStoreError::AuthorizationNotFound) => {
+            return Err(OAuthError::ExpiredToken)
+        }
+        Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+    };
+    if authorization.client_id != client_id {
+        return Err(OAuthError::InvalidGrant);
+    }
+
+    let email = match (authorization.status, authorization.user.clone()) {
+        (DeviceAuthorizationStatus::Approved, Some(email)) => email,
+        (DeviceAuthorizationStatus::Pending, _) => {
+            let in_time = authorization.record_poll(Utc::now());
+            store
+                .update_authorization(authorization)
+                .await
+                .map_err(|e| OAuthError::UnexpectedError(e.into()))?;
+
+            return Err(if in_time {
+                OAuthError::AuthorizationPending
+            } else {
+                OAuthError::SlowDown
+            });
+        }
+        _ => {
+            store
+                .remove_authorization(&authorization)
+                .await
+                .map_err(|e| OAuthError::UnexpectedError(e.into()))?;
+            return Err(OAuthError::AccessDenied);
+        }
+    };
+
+    store
+        .remove_authorization(&authorization)
+        .await
+        .map_err(|e| OAuthError::UnexpectedError(e.into()))?;
+    drop(store);
+
+    // The account may have been suspended since the user approved the device
+    let tenant = authorization.tenant;
+    match state
+        .user_store
+        .read()
+        .await
+        .get_user(&tenant, &email)
+        .await
+    {
+        Ok(user) if user.status == AccountStatus::Active => {}
+        Ok(_) | Err(UserStoreError::UserNotFound) => return Err(OAuthError::AccessDenied),
+        Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+    }
+
+    let grants = state
+        .role_store
+        .read()
+        .await
+        .get_grants(&tenant, &email)
+        .await
+        .map_err(|e| OAuthError::UnexpectedError(e.into()))?;
+
+    let access_token = generate_auth_token(&tenant, &email, &[AuthMethod::Device], &grants)
+        .map_err(OAuthError::UnexpectedError)?;
+    let scope = grants
+        .permissions
+        .iter()
+        .map(|permission| permission.as_ref())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let record = AuditRecord::new("device_token")
+        .user(&email)
+        .actor(authorization.client_id.as_ref())
+        .reason(&scope);
+    record_audit_event(&state, &tenant, auditor.client(), record).await;
+    Ok((
+        StatusCode::OK,
+        [(CACHE_CONTROL, "no-store")],
+        Json(AccessTokenResponse {
+            access_token,
+            token_type: "Bearer".to_owned(),
+            expires_in: TOKEN_TTL_SECONDS,
+            scope,
+        }),
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct DeviceCodeRequest {
+    pub client_id: Option<String>,
+}
+
+// Field names follow RFC 8628
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeviceCodeResponse {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: String,
+    pub expires_in: i64,
+    pub interval: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DeviceAuthorizationResponse {
+    #[serde(rename = "userCode")]
+    pub user_code: String,
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+}
@@ -0,0 +1,338 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        permissions::ManageRoles, AuditRecord, AuthAPIError, ClientFingerprint, Email, Invitation,
+        InvitationId, InvitationStatus, InvitationStoreError, Password, RequiredPermission, Role,
+        TenantId, User, UserStoreError,
+    },
+    utils::{
+        audit::record_audit_event,
+        auth::{generate_invitation_token, validate_invitation_token},
+        constants::{AUTH_SERVICE_URL, INVITATION_TTL_DAYS},
+        extractors::{AdminCaller, Auditor, CurrentTenant},
+    },
+};
+
+use super::{admin::audit, signup::add_new_user};
+
+#[tracing::instrument(name = "Creating invitation", skip_all)]
+pub async fn create_invitation(
+    State(state): State<AppState>,
+    admin: AdminCaller,
+    auditor: Auditor,
+    CurrentTenant(tenant): CurrentTenant,
+    Json(request): Json<CreateInvitationRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let record = AuditRecord::new("invitation_created");
+    let result = async {
+        let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+        let role = match request.role {
+            Some(role) => {
+                // Preassigning a role amounts to granting it, which takes `roles:manage` like
+                // `/roles` does. The static admin token isn't limited.
+                let may_grant = match &admin {
+                    AdminCaller::Token => true,
+                    AdminCaller::User(user) => user.claims.has_permission(ManageRoles::NAME),
+                    AdminCaller::Client(_) => false,
+                };
+                if !may_grant {
+                    return Err(AuthAPIError::MissingPermission);
+                }
+
+                let role = Role::parse(role).map_err(|_| AuthAPIError::RoleNotFound)?;
+                let exists = state
+                    .role_store
+                    .read()
+                    .await
+                    .role_exists(&role)
+                    .await
+                    .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+                if !exists {
+                    return Err(AuthAPIError::RoleNotFound);
+                }
+                Some(role)
+            }
+            None => None,
+        };
+
+        let invitation = Invitation::new(
+            tenant.id.clone(),
+            email.clone(),
+            role,
+            admin.name().to_owned(),
+            chrono::Duration::days(INVITATION_TTL_DAYS),
+        );
+        let token =
+            generate_invitation_token(&invitation).map_err(AuthAPIError::UnexpectedError)?;
+
+        state
+            .invitation_store
+            .write()
+            .await
+            .add_invitation(invitation.clone())
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        let link = format!(
+            "{}/?invitation={}",
+            AUTH_SERVICE_URL.as_str(),
+            token.expose_secret()
+        );
+
+        state
+            .email_client
+            .read()
+            .await
+            .send_email(&email, "You have been invited", &link)
+            .await
+            .map_err(AuthAPIError::UnexpectedError)?;
+
+        Ok(invitation)
+    }
+    .await;
+
+    let record = match &result {
+        Ok(invitation) => record.user(&invitation.email).target(invitation.id),
+        Err(_) => record,
+    };
+    audit(&auditor, &admin, record, &result).await;
+    let invitation = result?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(InvitationResponse::from(&invitation)),
+    ))
+}
+
+#[tracing::instrument(name = "Listing invitations", skip_all)]
+pub async fn list_invitations(
+    State(state): State<AppState>,
+    _admin: AdminCaller,
+    CurrentTenant(tenant): CurrentTenant,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let invitations = state
+        .invitation_store
+        .read()
+        .await
+        .list_invitations(&tenant.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(ListInvitationsResponse {
+            invitations: invitations.iter().map(Into::into).collect(),
+        }),
+    ))
+}
+
+#[tracing::instrument(name = "Revoking invitation", skip_all)]
+pub async fn revoke_invitation(
+    State(state): State<AppState>,
+    admin: AdminCaller,
+    auditor: Auditor,
+    CurrentTenant(tenant): CurrentTenant,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let record = AuditRecord::new("invitation_revoked").target(&id);
+    let result = async {
+        let id = InvitationId::parse(&id).map_err(|_| AuthAPIError::InvitationNotFound)?;
+
+        let mut invitation_store = state.invitation_store.write().await;
+        let invitation = invitation_store
+            .get_invitation(&tenant.id, &id)
+            .await
+            .map_err(map_invitation_store_error)?;
+        invitation_store
+            .revoke_invitation(&tenant.id, &id)
+            .await
+            .map_err(map_invitation_store_error)?;
+
+        Ok(invitation)
+    }
+    .await;
+
+    let record = match &result {
+        Ok(invitation) => record.user(&invitation.email),
+        Err(_) => record,
+    };
+    audit(&auditor, &admin, record, &result).await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Accepting an invitation creates the account, or adds the invited role to an account
+// that already exists in the tenant. Whoever holds the token has proven they own the
+// invited address, so the tenant's email domain restriction does not apply.
+#[tracing::instrument(name = "Accepting invitation", skip_all)]
+pub async fn accept_invitation(
+    State(state): State<AppState>,
+    client: ClientFingerprint,
+    Json(request): Json<AcceptInvitationRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let claims =
+        validate_invitation_token(&request.token).map_err(|_| AuthAPIError::InvalidToken)?;
+    let tenant = TenantId::parse(claims.tenant).map_err(|_| AuthAPIError::InvalidToken)?;
+    let id = InvitationId::parse(&claims.jti).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let invitation = match state
+        .invitation_store
+        .read()
+        .await
+        .get_invitation(&tenant, &id)
+        .await
+    {
+        Ok(invitation) => invitation,
+        Err(InvitationStoreError::InvitationNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    if invitation.email.as_ref().expose_secret() != &claims.sub
+        || invitation.current_status() != InvitationStatus::Pending
+    {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    let existing_user = match state
+        .user_store
+        .read()
+        .await
+        .get_user(&tenant, &invitation.email)
+        .await
+    {
+        Ok(_) => true,
+        Err(UserStoreError::UserNotFound) => false,
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+
+    // Checked before the invitation is used up so a bad password can be retried
+    let new_user = match (existing_user, request.password) {
+        (true, _) => None,
+        (false, Some(password)) => {
+            let password =
+                Password::parse(password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+            Some(User::new(
+                invitation.email.clone(),
+                password,
+                request.requires_2fa,
+            ))
+        }
+        (false, None) => return Err(AuthAPIError::InvalidCredentials),
+    };
+
+    // Only one request can accept the invitation, whatever the others were checking
+    match state
+        .invitation_store
+        .write()
+        .await
+        .accept_invitation(&tenant, &id)
+        .await
+    {
+        Ok(()) => {}
+        Err(InvitationStoreError::InvitationNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let (status, message) = match new_user {
+        Some(user) => {
+            add_new_user(&state, &tenant, user).await?;
+            (StatusCode::CREATED, "User created successfully!")
+        }
+        None => (StatusCode::OK, "Invitation accepted"),
+    };
+
+    if let Some(role) = &invitation.role {
+        state
+            .role_store
+            .write()
+            .await
+            .assign_role(&tenant, &invitation.email, role)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    }
+
+    let record = AuditRecord::new("invitation_accepted")
+        .user(&invitation.email)
+        .target(id);
+    record_audit_event(&state, &tenant, &client, record).await;
+
+    Ok((
+        status,
+        Json(AcceptInvitationResponse {
+            message: message.to_owned(),
+        }),
+    ))
+}
+
+fn map_invitation_store_error(e: InvitationStoreError) -> AuthAPIError {
+    match e {
+        InvitationStoreError::InvitationNotFound => AuthAPIError::InvitationNotFound,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateInvitationRequest {
+    pub email: Secret<String>,
+    pub role: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AcceptInvitationRequest {
+    pub token: Secret<String>,
+    // Only needed when the invitation creates a new account
+    pub password: Option<Secret<String>>,
+    #[serde(rename = "requires2FA", default)]
+    pub requires_2fa: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AcceptInvitationResponse {
+    pub message: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListInvitationsResponse {
+    pub invitations: Vec<InvitationResponse>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InvitationResponse {
+    pub id: String,
+    pub email: String,
+    pub role: Option<String>,
+    #[serde(rename = "invitedBy")]
+    pub invited_by: String,
+    pub status: InvitationStatus,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+}
+
+impl From<&Invitation> for InvitationResponse {
+    fn from(invitation: &Invitation) -> Self {
+        Self {
+            id: invitation.id.to_string(),
+            email: invitation.email.as_ref().expose_secret().to_owned(),
+            role: invitation
+                .role
+                .as_ref()
+                .map(|role| role.as_ref().to_owned()),
+            invited_by: invitation.invited_by.clone(),
+            status: invitation.current_status(),
+            created_at: invitation.created_at.to_rfc3339(),
+            expires_at: invitation.expires_at.to_rfc3339(),
+        }
+    }
+}
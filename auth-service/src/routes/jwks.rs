@@ -0,0 +1,9 @@
+use axum::{response::IntoResponse, Json};
+
+use crate::utils::auth::token_signing_jwks;
+
+// The public key auth tokens are signed with, for services checking them locally
+#[tracing::instrument(name = "Publishing JWKS", skip_all)]
+pub async fn jwks() -> impl IntoResponse {
+    Json(token_signing_jwks())
+}
@@ -1,78 +1,338 @@
-use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Redirect},
+    Json,
+};
 use axum_extra::extract::CookieJar;
+use color_eyre::eyre::Result;
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
+use url::form_urlencoded;
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, LoginAttemptId, Password, TwoFACode},
-    utils::auth::generate_auth_cookie,
+    domain::{
+        data_stores::UserStoreError, AccountStatus, AuditRecord, AuthAPIError, AuthMethod,
+        ClientFingerprint, Email, LoginAttemptId, LoginContext, Password, RiskAssessment, Tenant,
+        TenantId, TokenDelivery, TrustedDeviceId, TwoFAAttempt, TwoFAChannel, TwoFACode, User,
+    },
+    utils::{
+        audit::record_audit_event,
+        auth::{create_auth_cookie, generate_auth_token, validate_trusted_device_token},
+        constants::TRUSTED_DEVICE_COOKIE_NAME,
+        extractors::{Auditor, CurrentTenant},
+    },
 };
 
 #[tracing::instrument(name = "Login", skip_all)]
 pub async fn login(
     State(state): State<AppState>,
+    CurrentTenant(tenant): CurrentTenant,
+    client: ClientFingerprint,
+    auditor: Auditor,
     jar: CookieJar,
     Json(request): Json<LoginRequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
     let email = match Email::parse(request.email) {
         Ok(email) => email,
-        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+        Err(_) => {
+            let e = login_failed(&auditor, None, AuthAPIError::InvalidCredentials).await;
+            return (jar, Err(e));
+        }
     };
     let password = match Password::parse(request.password) {
         Ok(password) => password,
-        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+        Err(_) => {
+            let e = login_failed(&auditor, Some(&email), AuthAPIError::InvalidCredentials).await;
+            return (jar, Err(e));
+        }
     };
 
-    let user_store = &state.user_store.read().await;
+    let user = {
+        let user_store = &state.user_store.read().await;
+
+        if let Err(e) = user_store
+            .validate_user(&tenant.id, &email, &password)
+            .await
+        {
+            // Only accounts that exist have a history worth keeping; anyone can make up emails
+            if matches!(e, UserStoreError::InvalidCredentials) {
+                let context = LoginContext::new(tenant.id.clone(), email.clone(), client);
+                record_login_failure(&state, &context).await;
+            }
+            let e = login_failed(&auditor, Some(&email), AuthAPIError::IncorrectCredentials).await;
+            return (jar, Err(e));
+        }
+
+        match user_store.get_user(&tenant.id, &email).await {
+            Ok(user) => user,
+            Err(_) => {
+                let e = AuthAPIError::IncorrectCredentials;
+                return (jar, Err(login_failed(&auditor, Some(&email), e).await));
+            }
+        }
+    };
 
-    if user_store.validate_user(&email, &password).await.is_err() {
-        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    if let Err(e) = ensure_account_active(&user) {
+        return (jar, Err(login_failed(&auditor, Some(&email), e).await));
     }
 
-    let user = match user_store.get_user(&email).await {
-        Ok(user) => user,
-        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+    // The old password may be known to an attacker, so only a magic link gets the user in
+    if user.password_reset_required {
+        let e = login_failed(&auditor, Some(&email), AuthAPIError::PasswordResetRequired).await;
+        return (jar, Err(e));
+    }
+
+    complete_login(
+        &tenant,
+        &user,
+        AuthMethod::Password,
+        request.token_delivery,
+        client,
+        &state,
+        jar,
+    )
+    .await
+}
+
+// Records a login that was refused, and hands back the error to answer it with
+async fn login_failed(auditor: &Auditor, email: Option<&Email>, e: AuthAPIError) -> AuthAPIError {
+    let record = match email {
+        Some(email) => AuditRecord::new("login").user(email),
+        None => AuditRecord::new("login"),
     };
+    auditor.record(record.failure(&e)).await;
+    e
+}
 
-    match user.requires_2fa {
-        true => handle_2fa(&user.email, &state, jar).await,
-        false => handle_no_2fa(&user.email, jar).await,
+// The audit record of a successful login, naming the methods the user signed in with
+pub(super) fn login_record(amr: &[AuthMethod]) -> AuditRecord {
+    let amr: Vec<&str> = amr.iter().map(AuthMethod::as_str).collect();
+    AuditRecord::new("login").reason(amr.join(" "))
+}
+
+// Checked only once the user has proven who they are, so it can't be used to probe
+// which accounts exist
+pub(super) fn ensure_account_active(user: &User) -> Result<(), AuthAPIError> {
+    match user.status {
+        AccountStatus::Active => Ok(()),
+        status => Err(AuthAPIError::AccountNotActive(status)),
     }
 }
 
-#[tracing::instrument(name = "Handle 2FA", skip_all)]
-async fn handle_2fa(
-    email: &Email,
+// Decides whether a user who proved their first factor still has to pass 2FA: either
+// because they opted in or their tenant requires it and this browser isn't trusted,
+// or because the login looks risky.
+#[tracing::instrument(name = "Completing login", skip_all)]
+pub(super) async fn complete_login(
+    tenant: &Tenant,
+    user: &User,
+    first_factor: AuthMethod,
+    token_delivery: TokenDelivery,
+    client: ClientFingerprint,
     state: &AppState,
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
-    let login_attempt_id = LoginAttemptId::default();
-    let two_fa_code = TwoFACode::default();
+    let context = LoginContext::new(tenant.id.clone(), user.email.clone(), client);
+    let assessment = assess_login_risk(state, &context).await;
+
+    let wants_2fa = user.requires_2fa || tenant.settings.require_2fa;
+    let requires_2fa = assessment.step_up
+        || (wants_2fa && !is_trusted_device(&jar, &tenant.id, &user.email, state).await);
+
+    match requires_2fa {
+        true => handle_2fa(&tenant.id, user, first_factor, context.client, state, jar).await,
+        false => handle_no_2fa(&context, first_factor, token_delivery, state, jar).await,
+    }
+}
+
+// Finishes a sign-in that happened in a browser, like a federated login or a magic link,
+// with a redirect rather than a JSON response. Users who have to pass 2FA are sent to the
+// start page with the login attempt in the URL fragment, which browsers keep to
+// themselves.
+pub(super) async fn complete_browser_login(
+    tenant: &Tenant,
+    user: &User,
+    first_factor: AuthMethod,
+    client: ClientFingerprint,
+    state: &AppState,
+    jar: CookieJar,
+) -> (CookieJar, Result<Redirect, AuthAPIError>) {
+    let (jar, result) = complete_login(
+        tenant,
+        user,
+        first_factor,
+        TokenDelivery::Cookie,
+        client,
+        state,
+        jar,
+    )
+    .await;
+
+    let redirect = match result {
+        Ok((_, Json(LoginResponse::TwoFactorAuth(response)))) => {
+            let fragment = form_urlencoded::Serializer::new(String::new())
+                .append_pair("loginAttemptId", &response.login_attempt_id)
+                .append_pair("email", user.email.as_ref().expose_secret())
+                .finish();
+            Redirect::to(&format!("/#{}", fragment))
+        }
+        Ok(_) => Redirect::to("/"),
+        Err(e) => return (jar, Err(e)),
+    };
 
+    (jar, Ok(redirect))
+}
+
+// Risk decisions go to the audit trail so a forced 2FA prompt can be explained later
+#[tracing::instrument(name = "Assessing login risk", skip_all)]
+async fn assess_login_risk(state: &AppState, context: &LoginContext) -> RiskAssessment {
+    let assessment = match state.risk_evaluator.read().await.assess(context).await {
+        Ok(assessment) => assessment,
+        Err(e) => {
+            // If we can't score the login, err on the side of asking for 2FA
+            tracing::error!("Failed to assess login risk: {:?}", e);
+            RiskAssessment {
+                reasons: Vec::new(),
+                step_up: true,
+            }
+        }
+    };
+
+    let reasons: Vec<String> = assessment.reasons.iter().map(ToString::to_string).collect();
+    let record = AuditRecord::new("login_risk_assessed")
+        .user(&context.email)
+        .reason(format!(
+            "score {}, step-up {}: {}",
+            assessment.score(),
+            assessment.step_up,
+            reasons.join(", ")
+        ));
+    record_audit_event(state, &context.tenant, &context.client, record).await;
+
+    assessment
+}
+
+// Lets the risk evaluator learn the user's usual clients, and adds the login to the
+// audit log
+#[tracing::instrument(name = "Recording successful login", skip_all)]
+pub(super) async fn record_login_success(
+    state: &AppState,
+    context: &LoginContext,
+    record: AuditRecord,
+) {
     if let Err(e) = state
-        .two_fa_code_store
+        .risk_evaluator
         .write()
         .await
-        .add_code(email.clone(), login_attempt_id.clone(), two_fa_code.clone())
+        .record_success(context)
         .await
     {
-        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+        tracing::error!("Failed to record successful login: {:?}", e);
     }
 
+    let record = record.user(&context.email);
+    record_audit_event(state, &context.tenant, &context.client, record).await;
+}
+
+#[tracing::instrument(name = "Recording failed login", skip_all)]
+pub(super) async fn record_login_failure(state: &AppState, context: &LoginContext) {
     if let Err(e) = state
-        .email_client
+        .risk_evaluator
+        .write()
+        .await
+        .record_failure(context)
+        .await
+    {
+        tracing::error!("Failed to record failed login: {:?}", e);
+    }
+}
+
+// A trusted-device cookie lets the user skip 2FA on this browser until the device
+// expires or is revoked. Anything wrong with the cookie simply means 2FA is required.
+#[tracing::instrument(name = "Checking trusted device", skip_all)]
+async fn is_trusted_device(
+    jar: &CookieJar,
+    tenant: &TenantId,
+    email: &Email,
+    state: &AppState,
+) -> bool {
+    let Some(cookie) = jar.get(TRUSTED_DEVICE_COOKIE_NAME) else {
+        return false;
+    };
+
+    let Ok(claims) = validate_trusted_device_token(cookie.value()) else {
+        return false;
+    };
+
+    if &claims.sub != email.as_ref().expose_secret() {
+        return false;
+    }
+
+    let Ok(device_id) = TrustedDeviceId::parse(&claims.jti) else {
+        return false;
+    };
+
+    match state
+        .trusted_device_store
         .read()
         .await
-        .send_email(email, "Your code!", two_fa_code.as_ref().expose_secret())
+        .is_trusted(tenant, email, &device_id)
+        .await
+    {
+        Ok(trusted) => trusted,
+        Err(e) => {
+            tracing::error!("Failed to check trusted device: {:?}", e);
+            false
+        }
+    }
+}
+
+#[tracing::instrument(name = "Handle 2FA", skip_all)]
+pub(super) async fn handle_2fa(
+    tenant: &TenantId,
+    user: &User,
+    first_factor: AuthMethod,
+    client: ClientFingerprint,
+    state: &AppState,
+    jar: CookieJar,
+) -> (
+    CookieJar,
+    Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
+) {
+    let login_attempt_id = LoginAttemptId::default();
+    let two_fa_code = TwoFACode::default();
+    let attempt = TwoFAAttempt::new(
+        tenant.clone(),
+        user.email.clone(),
+        two_fa_code.clone(),
+        client.clone(),
+    )
+    .with_first_factor(first_factor);
+
+    if let Err(e) = state
+        .two_fa_code_store
+        .write()
+        .await
+        .add_code(login_attempt_id.clone(), attempt)
         .await
     {
+        return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
+    }
+
+    if let Err(e) = send_two_fa_code(user, &two_fa_code, state).await {
         return (jar, Err(AuthAPIError::UnexpectedError(e)));
     }
 
+    let record = AuditRecord::new("two_fa_challenge")
+        .user(&user.email)
+        .reason(first_factor.as_str());
+    record_audit_event(state, tenant, &client, record).await;
+
     let response = Json(LoginResponse::TwoFactorAuth(TwoFactorAuthResponse {
         message: "2FA required".to_owned(),
         login_attempt_id: login_attempt_id.as_ref().expose_secret().to_owned(),
@@ -81,34 +341,100 @@ async fn handle_2fa(
     (jar, Ok((StatusCode::PARTIAL_CONTENT, response)))
 }
 
+// Delivers the code through the channel the user picked, falling back to email
+// if SMS was picked without a verified phone number
+#[tracing::instrument(name = "Sending 2FA code", skip_all)]
+pub(super) async fn send_two_fa_code(
+    user: &User,
+    two_fa_code: &TwoFACode,
+    state: &AppState,
+) -> Result<()> {
+    let code = two_fa_code.as_ref().expose_secret();
+
+    match (user.two_fa_channel, &user.phone_number) {
+        (TwoFAChannel::Sms, Some(phone_number)) => {
+            state
+                .sms_client
+                .read()
+                .await
+                .send_sms(phone_number, &format!("Your code is {}", code))
+                .await
+        }
+        _ => {
+            state
+                .email_client
+                .read()
+                .await
+                .send_email(&user.email, "Your code!", code)
+                .await
+        }
+    }
+}
+
 #[tracing::instrument(name = "Handle no 2FA", skip_all)]
 async fn handle_no_2fa(
-    email: &Email,
+    context: &LoginContext,
+    first_factor: AuthMethod,
+    token_delivery: TokenDelivery,
+    state: &AppState,
     jar: CookieJar,
 ) -> (
     CookieJar,
     Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
 ) {
-    let auth_cookie = match generate_auth_cookie(&email) {
-        Ok(cookie) => cookie,
+    let tenant = &context.tenant;
+    let token = match issue_auth_token(state, tenant, &context.email, &[first_factor]).await {
+        Ok(token) => token,
         Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
     };
 
-    let updated_jar = jar.add(auth_cookie);
+    record_login_success(state, context, login_record(&[first_factor])).await;
 
-    (
-        updated_jar,
-        Ok((StatusCode::OK, Json(LoginResponse::RegularAuth))),
-    )
+    let (updated_jar, response) = deliver_auth_token(jar, token, token_delivery);
+
+    (updated_jar, Ok((StatusCode::OK, Json(response))))
 }
 
-// The login route can return 2 possible success responses.
+// Issues an auth token carrying the roles and permissions the user holds right now
+#[tracing::instrument(name = "Issuing auth token", skip_all)]
+pub(super) async fn issue_auth_token(
+    state: &AppState,
+    tenant: &TenantId,
+    email: &Email,
+    amr: &[AuthMethod],
+) -> Result<String> {
+    let grants = state
+        .role_store
+        .read()
+        .await
+        .get_grants(tenant, email)
+        .await?;
+    generate_auth_token(tenant, email, amr, &grants)
+}
+
+// Hands a freshly issued auth token to the client the way it asked for it
+pub(super) fn deliver_auth_token(
+    jar: CookieJar,
+    token: String,
+    token_delivery: TokenDelivery,
+) -> (CookieJar, LoginResponse) {
+    match token_delivery {
+        TokenDelivery::Cookie => (
+            jar.add(create_auth_cookie(token)),
+            LoginResponse::RegularAuth,
+        ),
+        TokenDelivery::Body => (jar, LoginResponse::Token(TokenResponse { token })),
+    }
+}
+
+// The login route can return 3 possible success responses.
 // This enum models each response!
 #[derive(Debug, Serialize)]
 #[serde(untagged)]
 pub enum LoginResponse {
     RegularAuth,
     TwoFactorAuth(TwoFactorAuthResponse),
+    Token(TokenResponse),
 }
 
 // If a user requires 2FA, this JSON body should be returned!
@@ -119,8 +445,16 @@ pub struct TwoFactorAuthResponse {
     pub login_attempt_id: String,
 }
 
+// Returned instead of the cookie when the client asked for `"tokenDelivery": "body"`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenResponse {
+    pub token: String,
+}
+
 #[derive(Deserialize)]
 pub struct LoginRequest {
     email: Secret<String>,
     password: Secret<String>,
+    #[serde(rename = "tokenDelivery", default)]
+    token_delivery: TokenDelivery,
 }
@@ -1,32 +1,40 @@
 use axum::{extract::State, http::StatusCode, response::IntoResponse};
 use axum_extra::extract::{cookie, CookieJar};
-use secrecy::Secret;
 
 use crate::{
-    app_state::AppState, domain::AuthAPIError, utils::{auth::validate_token, constants::JWT_COOKIE_NAME}
+    app_state::AppState,
+    domain::{AuditRecord, AuthAPIError},
+    utils::{
+        constants::JWT_COOKIE_NAME,
+        extractors::{Auditor, InteractiveUser},
+    },
 };
 
+// Bans whichever token authenticated the request, whether it came from the `jwt`
+// cookie or a bearer header
 #[tracing::instrument(name = "Logging out", skip_all)]
-pub async fn logout(State(state): State<AppState>, jar: CookieJar) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
-    // Retrieve JWT cookie from the `CookieJar`
-    // Return AuthAPIError::MissingToken is the cookie is not found
-    let cookie = match jar.get(JWT_COOKIE_NAME) {
-        Some(cookie) => cookie,
-        None => return (jar, Err(AuthAPIError::MissingToken)),
+pub async fn logout(
+    State(state): State<AppState>,
+    InteractiveUser(user): InteractiveUser,
+    auditor: Auditor,
+    jar: CookieJar,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let jar = match jar.get(JWT_COOKIE_NAME) {
+        Some(_) => jar.remove(cookie::Cookie::from(JWT_COOKIE_NAME)),
+        None => jar,
     };
 
-    let token = Secret::new(cookie.value().to_owned());
+    let result = state
+        .banned_token_store
+        .write()
+        .await
+        .ban_token(user.token)
+        .await;
+    auditor
+        .record_result(AuditRecord::new("logout").user(&user.email), &result)
+        .await;
 
-    // TODO: Validate JWT token by calling `validate_token` from the auth service.
-    // If the token is valid you can ignore the returned claims for now.
-    // Return AuthAPIError::InvalidToken is validation fails.
-    if validate_token(&token, state.banned_token_store.clone()).await.is_err() {
-        return (jar, Err(AuthAPIError::InvalidToken));
-    }
-
-    let jar = jar.remove(cookie::Cookie::from(JWT_COOKIE_NAME));
-
-    if let Err(e) = state.banned_token_store.write().await.ban_token(token).await {
+    if let Err(e) = result {
         return (jar, Err(AuthAPIError::UnexpectedError(e.into())));
     }
 
@@ -0,0 +1,237 @@
+use axum::{
+    extract::{Query, State},
+    http::{header::CACHE_CONTROL, StatusCode},
+    response::{Html, IntoResponse, Redirect},
+    Form, Json,
+};
+use axum_extra::extract::CookieJar;
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuditRecord, AuthAPIError, AuthMethod, ClientFingerprint, Email, MagicLinkId,
+        MagicLinkStoreError, TenantId, UserStoreError,
+    },
+    utils::{
+        audit::record_audit_event,
+        auth::{generate_magic_link_token, validate_magic_link_token},
+        constants::AUTH_SERVICE_URL,
+        extractors::{Auditor, CurrentTenant},
+    },
+};
+
+use super::login::{complete_browser_login, ensure_account_active};
+
+const CONFIRMATION_PAGE: &str = r#"<!DOCTYPE html>
+<html lang="en">
+
+<head>
+    <meta charset="UTF-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <meta name="referrer" content="no-referrer">
+    <title>Auth</title>
+    <link rel="stylesheet" href="https://cdn.jsdelivr.net/npm/bootstrap@5.2.2/dist/css/bootstrap.min.css">
+</head>
+
+<body>
+    <section class="position-relative py-4 py-xl-5">
+        <div class="container text-center">
+            <h2>Sign in</h2>
+            <p class="text-muted">Continue to sign in with the link you were emailed.</p>
+            <form method="post" action="/login/magic-link/consume">
+                <input type="hidden" name="token" value="{token}">
+                <button class="btn btn-dark" type="submit">Sign in</button>
+            </form>
+        </div>
+    </section>
+</body>
+
+</html>
+"#;
+
+#[tracing::instrument(name = "Requesting magic link", skip_all)]
+pub async fn request_magic_link(
+    State(state): State<AppState>,
+    CurrentTenant(tenant): CurrentTenant,
+    auditor: Auditor,
+    Json(request): Json<MagicLinkRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+    let record = AuditRecord::new("magic_link_requested").user(&email);
+
+    // Limits how many links an inbox can be sent. Requests for unknown accounts count too,
+    // so the limit doesn't give away which addresses have one. The address is the key in
+    // every tenant, as they all send to the same inbox.
+    let allowed = state
+        .magic_link_rate_limiter
+        .write()
+        .await
+        .check(&email.as_ref().expose_secret().to_lowercase())
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+    if !allowed {
+        auditor
+            .record(record.failure(AuthAPIError::TooManyRequests))
+            .await;
+        return Err(AuthAPIError::TooManyRequests);
+    }
+
+    // Unknown accounts get the same response so the route can't be used to probe for users
+    match state
+        .user_store
+        .read()
+        .await
+        .get_user(&tenant.id, &email)
+        .await
+    {
+        Ok(_) => {
+            let result = send_magic_link(&tenant.id, &email, &state).await;
+            auditor.record_result(record, &result).await;
+            result?
+        }
+        Err(UserStoreError::UserNotFound) => {
+            auditor
+                .record(record.failure(AuthAPIError::UserNotFound))
+                .await
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let response = Json(MagicLinkResponse {
+        message: "If the account exists, a sign-in link has been sent".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Sending magic link", skip_all)]
+async fn send_magic_link(
+    tenant: &TenantId,
+    email: &Email,
+    state: &AppState,
+) -> Result<(), AuthAPIError> {
+    let link_id = MagicLinkId::default();
+    let token =
+        generate_magic_link_token(email, &link_id).map_err(AuthAPIError::UnexpectedError)?;
+
+    state
+        .magic_link_store
+        .write()
+        .await
+        .add_link(link_id, tenant.clone(), email.clone())
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let link = format!(
+        "{}/login/magic-link/consume?token={}",
+        AUTH_SERVICE_URL.as_str(),
+        token.expose_secret()
+    );
+
+    state
+        .email_client
+        .read()
+        .await
+        .send_email(email, "Your sign-in link", &link)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)
+}
+
+// Opening the emailed link only shows a page that asks the user to sign in. Mail scanners
+// and link previews fetch links like this one, so a GET mustn't use it up or get the
+// session cookie. The page posts the token to `consume_magic_link`.
+#[tracing::instrument(name = "Confirming magic link", skip_all)]
+pub async fn confirm_magic_link(
+    Query(request): Query<ConsumeMagicLinkRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    // Only tokens we signed make it into the page, so there's nothing in them to escape
+    validate_magic_link_token(&request.token).map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let page = CONFIRMATION_PAGE.replace("{token}", request.token.expose_secret());
+
+    Ok(([(CACHE_CONTROL, "no-store")], Html(page)))
+}
+
+#[tracing::instrument(name = "Consuming magic link", skip_all)]
+pub async fn consume_magic_link(
+    State(state): State<AppState>,
+    client: ClientFingerprint,
+    jar: CookieJar,
+    Form(request): Form<ConsumeMagicLinkRequest>,
+) -> (CookieJar, Result<Redirect, AuthAPIError>) {
+    let claims = match validate_magic_link_token(&request.token) {
+        Ok(claims) => claims,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    let link_id = match MagicLinkId::parse(Secret::new(claims.jti)) {
+        Ok(link_id) => link_id,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidToken)),
+    };
+
+    // Consuming the link first makes it single-use even if the rest of the flow fails.
+    // The link belongs to the tenant it was requested for, whatever host opens it.
+    let (tenant_id, email) = match state
+        .magic_link_store
+        .write()
+        .await
+        .consume_link(&link_id)
+        .await
+    {
+        Ok(link) => link,
+        Err(MagicLinkStoreError::LinkNotFound) => return (jar, Err(AuthAPIError::InvalidToken)),
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    let failed = |e: AuthAPIError| async {
+        let record = AuditRecord::new("login").user(&email).failure(&e);
+        record_audit_event(&state, &tenant_id, &client, record).await;
+        e
+    };
+
+    if email.as_ref().expose_secret() != &claims.sub {
+        return (jar, Err(failed(AuthAPIError::InvalidToken).await));
+    }
+
+    let tenant = match state.tenant_store.read().await.get_tenant(&tenant_id).await {
+        Ok(tenant) => tenant,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    let user = match state
+        .user_store
+        .read()
+        .await
+        .get_user(&tenant.id, &email)
+        .await
+    {
+        Ok(user) => user,
+        Err(_) => return (jar, Err(failed(AuthAPIError::IncorrectCredentials).await)),
+    };
+
+    if let Err(e) = ensure_account_active(&user) {
+        return (jar, Err(failed(e).await));
+    }
+
+    // The link is opened in a browser, so the token goes in a cookie and the user is
+    // sent on to the app
+    let first_factor = AuthMethod::MagicLink;
+    complete_browser_login(&tenant, &user, first_factor, client, &state, jar).await
+}
+
+#[derive(Deserialize)]
+pub struct MagicLinkRequest {
+    pub email: Secret<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MagicLinkResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+pub struct ConsumeMagicLinkRequest {
+    pub token: Secret<String>,
+}
@@ -1,12 +1,50 @@
+mod admin;
+mod api_keys;
+mod audit;
+mod device;
+mod invitations;
+mod jwks;
 mod login;
 mod logout;
+mod magic_link;
+mod oauth;
+mod oidc;
+mod password;
+mod phone_number;
+mod reauthenticate;
+mod resend_2fa;
+mod roles;
+mod saml;
+mod scim;
 mod signup;
+mod trusted_devices;
+mod two_fa_channel;
 mod verify_2fa;
 mod verify_token;
+mod webhooks;
 
 // re-export items from sub-modules
+pub use admin::*;
+pub use api_keys::*;
+pub use audit::*;
+pub use device::*;
+pub use invitations::*;
+pub use jwks::*;
 pub use login::*;
 pub use logout::*;
+pub use magic_link::*;
+pub use oauth::*;
+pub use oidc::*;
+pub use password::*;
+pub use phone_number::*;
+pub use reauthenticate::*;
+pub use resend_2fa::*;
+pub use roles::*;
+pub use saml::*;
+pub use scim::*;
 pub use signup::*;
+pub use trusted_devices::*;
+pub use two_fa_channel::*;
 pub use verify_2fa::*;
 pub use verify_token::*;
+pub use webhooks::*;
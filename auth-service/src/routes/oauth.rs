@@ -0,0 +1,356 @@
+use axum::{
+    extract::{Path, State},
+    http::{
+        header::{AUTHORIZATION, CACHE_CONTROL},
+        HeaderMap, StatusCode,
+    },
+    response::{IntoResponse, Response},
+    Form, Json,
+};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuditRecord, AuthAPIError, ClientId, OAuthClient, OAuthClientStoreError, OAuthError,
+        Permission,
+    },
+    utils::{
+        audit::record_audit_event,
+        auth::{generate_client_token, TOKEN_TTL_SECONDS},
+        extractors::{AdminCaller, Auditor, CurrentTenant},
+    },
+};
+
+use super::{admin::audit, device::device_code_grant};
+
+const MAX_NAME_LENGTH: usize = 100;
+const CLIENT_CREDENTIALS_GRANT: &str = "client_credentials";
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+#[tracing::instrument(name = "Registering OAuth client", skip_all)]
+pub async fn create_oauth_client(
+    State(state): State<AppState>,
+    admin: AdminCaller,
+    auditor: Auditor,
+    CurrentTenant(tenant): CurrentTenant,
+    Json(request): Json<CreateOAuthClientRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let record = AuditRecord::new("oauth_client_registered");
+    let result = async {
+        let name = request.name.trim().to_owned();
+        if name.is_empty() || name.len() > MAX_NAME_LENGTH {
+            return Err(AuthAPIError::InvalidCredentials);
+        }
+
+        let scopes = request
+            .scopes
+            .into_iter()
+            .map(Permission::parse)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+        // Admins can't hand a client permissions they don't have themselves. The static
+        // admin token isn't limited.
+        let held = match &admin {
+            AdminCaller::Token => None,
+            AdminCaller::User(user) => Some(&user.claims),
+            AdminCaller::Client(_) => return Err(AuthAPIError::MissingPermission),
+        };
+        if let Some(claims) = held {
+            if !scopes
+                .iter()
+                .all(|scope| claims.has_permission(scope.as_ref()))
+            {
+                return Err(AuthAPIError::MissingPermission);
+            }
+        }
+
+        let (client, secret) = OAuthClient::register(tenant.id.clone(), name, scopes);
+
+        state
+            .oauth_client_store
+            .write()
+            .await
+            .add_client(client.clone(), secret.clone())
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        Ok((client, secret))
+    }
+    .await;
+
+    let record = match &result {
+        Ok((client, _)) => record.target(client.id.as_ref()),
+        Err(_) => record,
+    };
+    audit(&auditor, &admin, record, &result).await;
+    let (client, secret) = result?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateOAuthClientResponse {
+            client: (&client).into(),
+            client_secret: secret.expose_secret().to_owned(),
+        }),
+    ))
+}
+
+#[tracing::instrument(name = "Listing OAuth clients", skip_all)]
+pub async fn list_oauth_clients(
+    State(state): State<AppState>,
+    _admin: AdminCaller,
+    CurrentTenant(tenant): CurrentTenant,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let clients = state
+        .oauth_client_store
+        .read()
+        .await
+        .list_clients(&tenant.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response: Vec<OAuthClientResponse> = clients.iter().map(Into::into).collect();
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+// The client can't get new tokens afterwards, but the ones it already has stay valid
+// until they expire
+#[tracing::instrument(name = "Deleting OAuth client", skip_all)]
+pub async fn delete_oauth_client(
+    State(state): State<AppState>,
+    admin: AdminCaller,
+    auditor: Auditor,
+    CurrentTenant(tenant): CurrentTenant,
+    Path(client_id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let record = AuditRecord::new("oauth_client_deleted").target(&client_id);
+    let result = async {
+        let client_id =
+            ClientId::parse(client_id).map_err(|_| AuthAPIError::OAuthClientNotFound)?;
+
+        state
+            .oauth_client_store
+            .write()
+            .await
+            .delete_client(&tenant.id, &client_id)
+            .await
+            .map_err(|e| match e {
+                OAuthClientStoreError::ClientNotFound => AuthAPIError::OAuthClientNotFound,
+                e => AuthAPIError::UnexpectedError(e.into()),
+            })?;
+
+        Ok(())
+    }
+    .await;
+
+    audit(&auditor, &admin, record, &result).await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// The OAuth 2.0 token endpoint. With the `client_credentials` grant, clients
+// authenticate with HTTP Basic or `client_id`/`client_secret` in the body and get a
+// token for themselves, limited to `scope` when it's given. With the device code grant,
+// devices poll for a token of the user who approves them.
+//
+// Tokens are recorded in the audit log of the client's tenant. Clients that can't be
+// identified are recorded in the tenant the request was made to.
+#[tracing::instrument(name = "Issuing token", skip_all)]
+pub async fn token(
+    State(state): State<AppState>,
+    auditor: Auditor,
+    headers: HeaderMap,
+    Form(request): Form<AccessTokenRequest>,
+) -> Result<Response, OAuthError> {
+    match request.grant_type.as_deref() {
+        Some(CLIENT_CREDENTIALS_GRANT) => {
+            client_credentials_grant(state, &auditor, &headers, request)
+                .await
+                .map(IntoResponse::into_response)
+        }
+        Some(DEVICE_CODE_GRANT) => device_code_grant(state, &auditor, request)
+            .await
+            .map(IntoResponse::into_response),
+        Some(_) => Err(OAuthError::UnsupportedGrantType),
+        None => Err(OAuthError::InvalidRequest("Missing grant_type")),
+    }
+}
+
+async fn client_credentials_grant(
+    state: AppState,
+    auditor: &Auditor,
+    headers: &HeaderMap,
+    request: AccessTokenRequest,
+) -> Result<impl IntoResponse, OAuthError> {
+    let (client_id, secret) = client_credentials(headers, &request)?;
+
+    // Every attempt counts, so secrets can't be guessed faster than tokens are handed out
+    let allowed = state
+        .client_rate_limiter
+        .write()
+        .await
+        .check(client_id.as_ref())
+        .await
+        .map_err(OAuthError::UnexpectedError)?;
+    if !allowed {
+        let record = AuditRecord::new("client_token")
+            .actor(client_id.as_ref())
+            .failure("Too many requests");
+        auditor.record(record).await;
+        return Err(OAuthError::TooManyRequests);
+    }
+
+    let client = match state
+        .oauth_client_store
+        .read()
+        .await
+        .validate_client(&client_id, &secret)
+        .await
+    {
+        Ok(client) => client,
+        Err(OAuthClientStoreError::InvalidClient) => {
+            let record = AuditRecord::new("client_token")
+                .actor(client_id.as_ref())
+                .failure("Invalid client credentials");
+            auditor.record(record).await;
+            return Err(OAuthError::InvalidClient);
+        }
+        Err(e) => return Err(OAuthError::UnexpectedError(e.into())),
+    };
+
+    let requested = match request.scope.as_deref().map(str::split_whitespace) {
+        Some(scopes) => Some(
+            scopes
+                .map(|scope| Permission::parse(scope.to_owned()))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| OAuthError::InvalidScope)?,
+        ),
+        None => None,
+    };
+    let scopes = client
+        .grant_scopes(requested.as_deref())
+        .ok_or(OAuthError::InvalidScope)?;
+
+    let access_token =
+        generate_client_token(&client, &scopes).map_err(OAuthError::UnexpectedError)?;
+    let scope = scopes
+        .iter()
+        .map(|scope| scope.as_ref())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    let record = AuditRecord::new("client_token")
+        .actor(client.id.as_ref())
+        .reason(&scope);
+    record_audit_event(&state, &client.tenant, auditor.client(), record).await;
+    Ok((
+        StatusCode::OK,
+        [(CACHE_CONTROL, "no-store")],
+        Json(AccessTokenResponse {
+            access_token,
+            token_type: "Bearer".to_owned(),
+            expires_in: TOKEN_TTL_SECONDS,
+            scope,
+        }),
+    ))
+}
+
+// Reads the client's credentials from an `Authorization: Basic` header or the body.
+// Using both at once is an error, as RFC 6749 asks.
+fn client_credentials(
+    headers: &HeaderMap,
+    request: &AccessTokenRequest,
+) -> Result<(ClientId, Secret<String>), OAuthError> {
+    let basic = headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Basic "));
+
+    let (client_id, secret) = match (basic, &request.client_id, &request.client_secret) {
+        (Some(_), Some(_), _) | (Some(_), _, Some(_)) => {
+            return Err(OAuthError::InvalidRequest(
+                "Client credentials must be sent one way only",
+            ))
+        }
+        (Some(basic), None, None) => {
+            let decoded = STANDARD
+                .decode(basic.trim())
+                .ok()
+                .and_then(|decoded| String::from_utf8(decoded).ok())
+                .ok_or(OAuthError::InvalidClient)?;
+            let (client_id, secret) = decoded.split_once(':').ok_or(OAuthError::InvalidClient)?;
+            (client_id.to_owned(), secret.to_owned())
+        }
+        (None, Some(client_id), Some(secret)) => {
+            (client_id.clone(), secret.expose_secret().clone())
+        }
+        (None, _, _) => return Err(OAuthError::InvalidClient),
+    };
+
+    let client_id = ClientId::parse(client_id).map_err(|_| OAuthError::InvalidClient)?;
+    Ok((client_id, Secret::new(secret)))
+}
+
+#[derive(Deserialize)]
+pub struct CreateOAuthClientRequest {
+    pub name: String,
+    #[serde(default)]
+    pub scopes: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateOAuthClientResponse {
+    #[serde(flatten)]
+    pub client: OAuthClientResponse,
+    // Only ever shown here
+    #[serde(rename = "clientSecret")]
+    pub client_secret: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OAuthClientResponse {
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    pub name: String,
+    pub scopes: Vec<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+impl From<&OAuthClient> for OAuthClientResponse {
+    fn from(client: &OAuthClient) -> Self {
+        Self {
+            client_id: client.id.as_ref().to_owned(),
+            name: client.name.clone(),
+            scopes: client
+                .scopes
+                .iter()
+                .map(|scope| scope.as_ref().to_owned())
+                .collect(),
+            created_at: client.created_at.to_rfc3339(),
+        }
+    }
+}
+
+// Field names follow RFC 6749, like the responses
+#[derive(Deserialize)]
+pub struct AccessTokenRequest {
+    pub grant_type: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<Secret<String>>,
+    pub scope: Option<String>,
+    pub device_code: Option<Secret<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AccessTokenResponse {
+    pub access_token: String,
+    pub token_type: String,
+    pub expires_in: i64,
+    pub scope: String,
+}
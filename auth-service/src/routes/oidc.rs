@@ -0,0 +1,505 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect},
+    Json,
+};
+use axum_extra::extract::{cookie::Cookie, CookieJar};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuditRecord, AuthAPIError, AuthMethod, ClientFingerprint, Email, FederatedIdentity,
+        FederatedIdentityStoreError, IdentityProvider, IdentityProviderStoreError, ProviderId,
+        TenantId, UserStoreError,
+    },
+    services::oidc_client::OidcAuthorizationRequest,
+    utils::{
+        audit::record_audit_event,
+        auth::{generate_oidc_state_cookie, validate_oidc_state_token, OidcStateClaims},
+        constants::{AUTH_SERVICE_URL, OIDC_STATE_COOKIE_NAME},
+        extractors::{AdminCaller, Auditor, CurrentTenant},
+    },
+};
+
+use super::{
+    admin::audit,
+    login::{complete_browser_login, ensure_account_active},
+};
+
+pub(super) const MAX_NAME_LENGTH: usize = 100;
+
+#[tracing::instrument(name = "Adding identity provider", skip_all)]
+pub async fn create_identity_provider(
+    State(state): State<AppState>,
+    admin: AdminCaller,
+    auditor: Auditor,
+    CurrentTenant(tenant): CurrentTenant,
+    Json(request): Json<CreateIdentityProviderRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let record = AuditRecord::new("identity_provider_added").target(&request.id);
+    let result = async {
+        let id = ProviderId::parse(request.id).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+        let name = request.name.trim().to_owned();
+        if name.is_empty() || name.len() > MAX_NAME_LENGTH {
+            return Err(AuthAPIError::InvalidCredentials);
+        }
+
+        let provider = IdentityProvider::new(
+            id,
+            tenant.id.clone(),
+            name,
+            request.issuer,
+            request.client_id,
+            request.client_secret,
+        )
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+        state
+            .identity_provider_store
+            .write()
+            .await
+            .add_provider(provider.clone())
+            .await
+            .map_err(|e| match e {
+                IdentityProviderStoreError::ProviderAlreadyExists => {
+                    AuthAPIError::IdentityProviderAlreadyExists
+                }
+                e => AuthAPIError::UnexpectedError(e.into()),
+            })?;
+
+        Ok(provider)
+    }
+    .await;
+
+    audit(&auditor, &admin, record, &result).await;
+    let provider = result?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(IdentityProviderResponse::from(&provider)),
+    ))
+}
+
+#[tracing::instrument(name = "Listing identity providers", skip_all)]
+pub async fn list_identity_providers(
+    State(state): State<AppState>,
+    _admin: AdminCaller,
+    CurrentTenant(tenant): CurrentTenant,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let providers = state
+        .identity_provider_store
+        .read()
+        .await
+        .list_providers(&tenant.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response: Vec<IdentityProviderResponse> = providers.iter().map(Into::into).collect();
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+// Users can't sign in with the provider anymore, and its links to their accounts are
+// forgotten
+#[tracing::instrument(name = "Deleting identity provider", skip_all)]
+pub async fn delete_identity_provider(
+    State(state): State<AppState>,
+    admin: AdminCaller,
+    auditor: Auditor,
+    CurrentTenant(tenant): CurrentTenant,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let record = AuditRecord::new("identity_provider_deleted").target(&id);
+    let result = async {
+        let id = ProviderId::parse(id).map_err(|_| AuthAPIError::IdentityProviderNotFound)?;
+
+        state
+            .identity_provider_store
+            .write()
+            .await
+            .delete_provider(&tenant.id, &id)
+            .await
+            .map_err(map_identity_provider_store_error)?;
+
+        Ok(())
+    }
+    .await;
+
+    audit(&auditor, &admin, record, &result).await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// What the login page needs to show a "Sign in with" button for each provider
+#[tracing::instrument(name = "Listing sign-in providers", skip_all)]
+pub async fn list_sign_in_providers(
+    State(state): State<AppState>,
+    CurrentTenant(tenant): CurrentTenant,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let providers = state
+        .identity_provider_store
+        .read()
+        .await
+        .list_providers(&tenant.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let saml_providers = state
+        .saml_provider_store
+        .read()
+        .await
+        .list_providers(&tenant.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let oidc_providers = providers.iter().map(|provider| SignInProviderResponse {
+        id: provider.id.as_ref().to_owned(),
+        name: provider.name.clone(),
+        login_url: format!("/oidc/{}/login", provider.id.as_ref()),
+    });
+    let saml_providers = saml_providers
+        .iter()
+        .map(|provider| SignInProviderResponse {
+            id: provider.id.as_ref().to_owned(),
+            name: provider.name.clone(),
+            login_url: format!("/saml/{}/login", provider.id.as_ref()),
+        });
+    let response: Vec<SignInProviderResponse> = oidc_providers.chain(saml_providers).collect();
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+// Sends the user to the provider to sign in. The state, nonce and PKCE verifier of
+// the sign-in are kept in a signed cookie until the provider sends them back.
+#[tracing::instrument(name = "Starting OIDC login", skip_all)]
+pub async fn oidc_login(
+    State(state): State<AppState>,
+    CurrentTenant(tenant): CurrentTenant,
+    jar: CookieJar,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let provider = get_provider(&state, &tenant.id, id).await?;
+
+    let metadata = state
+        .oidc_client
+        .discover(&provider)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let request = OidcAuthorizationRequest::new(redirect_uri(&provider.id));
+    let url = state
+        .oidc_client
+        .authorization_url(&metadata, &provider, &request)
+        .map_err(AuthAPIError::UnexpectedError)?;
+    let cookie = generate_oidc_state_cookie(&tenant.id, &provider.id, &request)
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok((jar.add(cookie), Redirect::to(url.as_str())))
+}
+
+// Where the provider sends the user back to. Once the ID token checks out, the login is
+// completed like any other and the user is sent to the start page.
+#[tracing::instrument(name = "Completing OIDC login", skip_all)]
+pub async fn oidc_callback(
+    State(state): State<AppState>,
+    client: ClientFingerprint,
+    jar: CookieJar,
+    Path(id): Path<String>,
+    Query(request): Query<OidcCallbackRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let Some(state_cookie) = jar
+        .get(OIDC_STATE_COOKIE_NAME)
+        .map(|cookie| cookie.value().to_owned())
+    else {
+        return (jar, Err(AuthAPIError::FederatedLoginFailed));
+    };
+    // Each sign-in can only be completed once, whatever the outcome
+    let jar = jar.remove(Cookie::build(OIDC_STATE_COOKIE_NAME).path("/"));
+
+    let (tenant, email) = match federated_login(&state, &client, &state_cookie, &id, request).await
+    {
+        Ok(login) => login,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    complete_federated_login(&state, &tenant, &email, client, jar).await
+}
+
+// A federated sign-in is the first factor like a password is, so the tenant's 2FA
+// requirement, the user's own and the risk evaluator's step-up all still apply
+pub(super) async fn complete_federated_login(
+    state: &AppState,
+    tenant: &TenantId,
+    email: &Email,
+    client: ClientFingerprint,
+    jar: CookieJar,
+) -> (CookieJar, Result<Redirect, AuthAPIError>) {
+    let tenant = match state.tenant_store.read().await.get_tenant(tenant).await {
+        Ok(tenant) => tenant,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    let user = match state
+        .user_store
+        .read()
+        .await
+        .get_user(&tenant.id, email)
+        .await
+    {
+        Ok(user) => user,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    let first_factor = AuthMethod::Federated;
+    complete_browser_login(&tenant, &user, first_factor, client, state, jar).await
+}
+
+// Checks the sign-in the provider sent the user back with and finds the user it is for.
+// Once the tenant is known, a failed sign-in is recorded in its audit log.
+async fn federated_login(
+    state: &AppState,
+    client: &ClientFingerprint,
+    state_cookie: &str,
+    id: &str,
+    request: OidcCallbackRequest,
+) -> Result<(TenantId, Email), AuthAPIError> {
+    let claims =
+        validate_oidc_state_token(state_cookie).map_err(|_| AuthAPIError::FederatedLoginFailed)?;
+
+    // The state has to match what this browser sent the provider, so nobody can log
+    // a victim in with their own sign-in
+    if claims.provider != id
+        || request.state.as_ref().map(|state| state.expose_secret()) != Some(&claims.state)
+    {
+        return Err(AuthAPIError::FederatedLoginFailed);
+    }
+
+    let tenant = TenantId::parse(claims.tenant.clone()).map_err(AuthAPIError::UnexpectedError)?;
+    match provider_login(state, client, &tenant, claims, request).await {
+        Ok(email) => Ok((tenant, email)),
+        Err(e) => {
+            let record = AuditRecord::new("login").target(id).failure(&e);
+            record_audit_event(state, &tenant, client, record).await;
+            Err(e)
+        }
+    }
+}
+
+async fn provider_login(
+    state: &AppState,
+    client: &ClientFingerprint,
+    tenant: &TenantId,
+    claims: OidcStateClaims,
+    request: OidcCallbackRequest,
+) -> Result<Email, AuthAPIError> {
+    let provider = get_provider(state, tenant, claims.provider).await?;
+
+    let code = match (request.code, request.error) {
+        (Some(code), None) => code,
+        (_, error) => {
+            tracing::warn!(
+                provider = provider.id.as_ref(),
+                error = error.as_deref().unwrap_or("missing_code"),
+                "Identity provider did not complete the sign-in",
+            );
+            return Err(AuthAPIError::FederatedLoginFailed);
+        }
+    };
+
+    let authorization_request = OidcAuthorizationRequest {
+        redirect_uri: redirect_uri(&provider.id),
+        state: Secret::new(claims.state),
+        nonce: Secret::new(claims.nonce),
+        code_verifier: Secret::new(claims.code_verifier),
+    };
+
+    let metadata = state
+        .oidc_client
+        .discover(&provider)
+        .await
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    let id_token = match state
+        .oidc_client
+        .authenticate(&metadata, &provider, &authorization_request, &code)
+        .await
+    {
+        Ok(id_token) => id_token,
+        Err(e) => {
+            tracing::warn!(
+                provider = provider.id.as_ref(),
+                error = %e,
+                "Identity provider's ID token was rejected",
+            );
+            return Err(AuthAPIError::FederatedLoginFailed);
+        }
+    };
+
+    let email = linked_account(
+        state,
+        client,
+        &provider,
+        id_token.sub,
+        id_token.email,
+        id_token.email_verified,
+    )
+    .await?;
+
+    let user = match state.user_store.read().await.get_user(tenant, &email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::FederatedIdentityNotLinked),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    ensure_account_active(&user)?;
+
+    Ok(email)
+}
+
+// Finds the account the provider's user is linked to. Without a link yet, an account
+// with the same email is linked, but only if the provider has verified the email:
+// otherwise anyone could claim an account by setting its address at the provider.
+async fn linked_account(
+    state: &AppState,
+    client: &ClientFingerprint,
+    provider: &IdentityProvider,
+    subject: String,
+    email: Option<String>,
+    email_verified: bool,
+) -> Result<Email, AuthAPIError> {
+    match state
+        .federated_identity_store
+        .read()
+        .await
+        .get_identity(&provider.tenant, &provider.id, &subject)
+        .await
+    {
+        Ok(identity) => return Ok(identity.email),
+        Err(FederatedIdentityStoreError::IdentityNotFound) => {}
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let email = email
+        .filter(|_| email_verified)
+        .and_then(|email| Email::parse(Secret::new(email)).ok())
+        .ok_or(AuthAPIError::FederatedIdentityNotLinked)?;
+
+    match state
+        .user_store
+        .read()
+        .await
+        .get_user(&provider.tenant, &email)
+        .await
+    {
+        Ok(_) => {}
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::FederatedIdentityNotLinked),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    let identity = FederatedIdentity::new(
+        provider.tenant.clone(),
+        provider.id.clone(),
+        subject,
+        email.clone(),
+    );
+    state
+        .federated_identity_store
+        .write()
+        .await
+        .add_identity(identity)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let record = AuditRecord::new("federated_identity_linked")
+        .user(&email)
+        .target(provider.id.as_ref());
+    record_audit_event(state, &provider.tenant, client, record).await;
+    Ok(email)
+}
+
+async fn get_provider(
+    state: &AppState,
+    tenant: &TenantId,
+    id: String,
+) -> Result<IdentityProvider, AuthAPIError> {
+    let id = ProviderId::parse(id).map_err(|_| AuthAPIError::IdentityProviderNotFound)?;
+
+    state
+        .identity_provider_store
+        .read()
+        .await
+        .get_provider(tenant, &id)
+        .await
+        .map_err(map_identity_provider_store_error)
+}
+
+fn map_identity_provider_store_error(e: IdentityProviderStoreError) -> AuthAPIError {
+    match e {
+        IdentityProviderStoreError::ProviderNotFound => AuthAPIError::IdentityProviderNotFound,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+// Has to be registered with the provider as is
+fn redirect_uri(provider: &ProviderId) -> String {
+    format!(
+        "{}/oidc/{}/callback",
+        AUTH_SERVICE_URL.as_str(),
+        provider.as_ref()
+    )
+}
+
+#[derive(Deserialize)]
+pub struct CreateIdentityProviderRequest {
+    pub id: String,
+    pub name: String,
+    pub issuer: String,
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    #[serde(rename = "clientSecret")]
+    pub client_secret: Secret<String>,
+}
+
+// Never includes the client secret
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IdentityProviderResponse {
+    pub id: String,
+    pub name: String,
+    pub issuer: String,
+    #[serde(rename = "clientId")]
+    pub client_id: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+impl From<&IdentityProvider> for IdentityProviderResponse {
+    fn from(provider: &IdentityProvider) -> Self {
+        Self {
+            id: provider.id.as_ref().to_owned(),
+            name: provider.name.clone(),
+            issuer: provider.issuer.clone(),
+            client_id: provider.client_id.clone(),
+            created_at: provider.created_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SignInProviderResponse {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "loginUrl")]
+    pub login_url: String,
+}
+
+// Field names follow the OAuth 2.0 authorization response
+#[derive(Deserialize)]
+pub struct OidcCallbackRequest {
+    pub code: Option<Secret<String>>,
+    pub state: Option<Secret<String>>,
+    pub error: Option<String>,
+}
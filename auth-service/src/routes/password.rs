@@ -0,0 +1,72 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditRecord, AuthAPIError, Password, TokenDelivery, UserStoreError},
+    utils::{
+        auth::{generate_replacement_token, revoke_all_sessions},
+        extractors::{Auditor, RecentlyAuthenticatedUser},
+    },
+};
+
+use super::login::deliver_auth_token;
+
+// Also how users finish a password reset forced by an admin, after signing in
+// with a magic link. Whoever knew the old password is logged out everywhere; the
+// session that made the change gets a new token.
+#[tracing::instrument(name = "Changing password", skip_all)]
+pub async fn change_password(
+    State(state): State<AppState>,
+    RecentlyAuthenticatedUser(user): RecentlyAuthenticatedUser,
+    auditor: Auditor,
+    jar: CookieJar,
+    Json(request): Json<ChangePasswordRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let result = async {
+        let password =
+            Password::parse(request.new_password).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+        state
+            .user_store
+            .write()
+            .await
+            .set_password(&user.tenant, &user.email, password)
+            .await
+            .map_err(|e| match e {
+                UserStoreError::ReadOnly => AuthAPIError::ManagedByDirectory,
+                e => AuthAPIError::UnexpectedError(e.into()),
+            })?;
+
+        let revoked_at = revoke_all_sessions(&user.tenant, &user.email, &state.banned_token_store)
+            .await
+            .map_err(AuthAPIError::UnexpectedError)?;
+
+        generate_replacement_token(&user.claims, revoked_at).map_err(AuthAPIError::UnexpectedError)
+    }
+    .await;
+    auditor
+        .record_result(
+            AuditRecord::new("password_change").user(&user.email),
+            &result,
+        )
+        .await;
+
+    let token = match result {
+        Ok(token) => token,
+        Err(e) => return (jar, Err(e)),
+    };
+    let (jar, response) = deliver_auth_token(jar, token, request.token_delivery);
+
+    (jar, Ok((StatusCode::OK, Json(response))))
+}
+
+#[derive(Deserialize)]
+pub struct ChangePasswordRequest {
+    #[serde(rename = "newPassword")]
+    pub new_password: Secret<String>,
+    #[serde(rename = "tokenDelivery", default)]
+    pub token_delivery: TokenDelivery,
+}
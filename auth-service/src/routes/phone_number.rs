@@ -0,0 +1,135 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditRecord, AuthAPIError, PhoneNumber, PhoneVerificationStoreError, TwoFACode},
+    utils::extractors::{Auditor, InteractiveUser, RecentlyAuthenticatedUser},
+};
+
+#[tracing::instrument(name = "Adding phone number", skip_all)]
+pub async fn add_phone_number(
+    State(state): State<AppState>,
+    RecentlyAuthenticatedUser(user): RecentlyAuthenticatedUser,
+    auditor: Auditor,
+    Json(request): Json<AddPhoneNumberRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let result = async {
+        let phone_number = PhoneNumber::parse(request.phone_number)
+            .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+        let code = TwoFACode::default();
+
+        // Every call texts a code, so the store only lets a user do this once in a while
+        match state
+            .phone_verification_store
+            .write()
+            .await
+            .add_pending(
+                user.tenant.clone(),
+                user.email.clone(),
+                phone_number.clone(),
+                code.clone(),
+            )
+            .await
+        {
+            Ok(()) => {}
+            Err(PhoneVerificationStoreError::Cooldown) => {
+                return Err(AuthAPIError::TooManyRequests)
+            }
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        }
+
+        state
+            .sms_client
+            .read()
+            .await
+            .send_sms(
+                &phone_number,
+                &format!(
+                    "Your verification code is {}",
+                    code.as_ref().expose_secret()
+                ),
+            )
+            .await
+            .map_err(AuthAPIError::UnexpectedError)
+    }
+    .await;
+    let record = AuditRecord::new("phone_number_verification_requested").user(&user.email);
+    auditor.record_result(record, &result).await;
+    result?;
+
+    let response = Json(PhoneNumberResponse {
+        message: "Verification code sent".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[tracing::instrument(name = "Verifying phone number", skip_all)]
+pub async fn verify_phone_number(
+    State(state): State<AppState>,
+    InteractiveUser(user): InteractiveUser,
+    auditor: Auditor,
+    Json(request): Json<VerifyPhoneNumberRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let result = async {
+        let code = TwoFACode::parse(Secret::new(request.code))
+            .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+        // A wrong code also discards the pending verification, so codes can't be brute
+        // forced
+        let (phone_number, expected_code) = match state
+            .phone_verification_store
+            .write()
+            .await
+            .take_pending(&user.tenant, &user.email)
+            .await
+        {
+            Ok(pending) => pending,
+            Err(PhoneVerificationStoreError::VerificationNotFound) => {
+                return Err(AuthAPIError::IncorrectCredentials)
+            }
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        };
+
+        if code != expected_code {
+            return Err(AuthAPIError::IncorrectCredentials);
+        }
+
+        state
+            .user_store
+            .write()
+            .await
+            .set_phone_number(&user.tenant, &user.email, phone_number)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+    }
+    .await;
+    let record = AuditRecord::new("phone_number_verification").user(&user.email);
+    auditor.record_result(record, &result).await;
+    result?;
+
+    let response = Json(PhoneNumberResponse {
+        message: "Phone number verified".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PhoneNumberResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+pub struct AddPhoneNumberRequest {
+    #[serde(rename = "phoneNumber")]
+    pub phone_number: Secret<String>,
+}
+
+#[derive(Deserialize)]
+pub struct VerifyPhoneNumberRequest {
+    pub code: String,
+}
@@ -0,0 +1,224 @@
+use axum::{extract::State, http::StatusCode, Json};
+use axum_extra::extract::CookieJar;
+use secrecy::Secret;
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        data_stores::TwoFACodeStoreError, AuditRecord, AuthAPIError, AuthMethod, ClientFingerprint,
+        Email, LoginAttemptId, LoginContext, Password, TenantId, TokenDelivery, TwoFACode,
+    },
+    utils::extractors::{Auditor, AuthenticatedUser, InteractiveUser},
+};
+
+use super::login::{
+    deliver_auth_token, handle_2fa, issue_auth_token, record_login_failure, LoginResponse,
+};
+
+// Refreshes `auth_time` for a user who is already logged in, so they can reach
+// routes guarded by `RecentlyAuthenticatedUser`. Users with 2FA enabled get a code
+// after the password step and finish by posting the code back here.
+#[tracing::instrument(name = "Reauthenticating", skip_all)]
+pub async fn reauthenticate(
+    State(state): State<AppState>,
+    InteractiveUser(user): InteractiveUser,
+    client: ClientFingerprint,
+    auditor: Auditor,
+    jar: CookieJar,
+    Json(request): Json<ReauthenticateRequest>,
+) -> (
+    CookieJar,
+    Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
+) {
+    let token_delivery = request.token_delivery;
+    let record = AuditRecord::new("reauthentication").user(&user.email);
+
+    let (jar, result) = match request.challenge {
+        ReauthenticateChallenge::Password { password } => {
+            reauthenticate_with_password(user, password, token_delivery, client, &state, jar).await
+        }
+        ReauthenticateChallenge::TwoFactor {
+            login_attempt_id,
+            two_fa_code,
+        } => {
+            let (id, code) = (login_attempt_id, two_fa_code);
+            reauthenticate_with_2fa(user, id, code, token_delivery, client, &state, jar).await
+        }
+    };
+
+    // Users with 2FA have only done the first step once they get a code
+    match &result {
+        Ok((_, Json(LoginResponse::TwoFactorAuth(_)))) => {}
+        Ok(_) => auditor.record(record).await,
+        Err(e) => auditor.record(record.failure(e)).await,
+    }
+
+    (jar, result)
+}
+
+#[tracing::instrument(name = "Reauthenticating with password", skip_all)]
+async fn reauthenticate_with_password(
+    user: AuthenticatedUser,
+    password: Secret<String>,
+    token_delivery: TokenDelivery,
+    client: ClientFingerprint,
+    state: &AppState,
+    jar: CookieJar,
+) -> (
+    CookieJar,
+    Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
+) {
+    let password = match Password::parse(password) {
+        Ok(password) => password,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    let stored_user = {
+        let user_store = state.user_store.read().await;
+
+        if user_store
+            .validate_user(&user.tenant, &user.email, &password)
+            .await
+            .is_err()
+        {
+            let context = LoginContext::new(user.tenant, user.email, client);
+            record_login_failure(state, &context).await;
+            return (jar, Err(AuthAPIError::IncorrectCredentials));
+        }
+
+        match user_store.get_user(&user.tenant, &user.email).await {
+            Ok(stored_user) => stored_user,
+            Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+        }
+    };
+
+    // As with login, the old password may be known to an attacker, so it can't make the
+    // session count as recent either
+    if stored_user.password_reset_required {
+        return (jar, Err(AuthAPIError::PasswordResetRequired));
+    }
+
+    let tenant = match state
+        .tenant_store
+        .read()
+        .await
+        .get_tenant(&user.tenant)
+        .await
+    {
+        Ok(tenant) => tenant,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e.into()))),
+    };
+
+    // Trusted devices don't skip this step: the point is to prove the user is present
+    if stored_user.requires_2fa || tenant.settings.require_2fa {
+        let first_factor = AuthMethod::Password;
+        return handle_2fa(&tenant.id, &stored_user, first_factor, client, state, jar).await;
+    }
+
+    let amr = [AuthMethod::Password];
+    refresh_auth_token(state, &user.tenant, &user.email, &amr, token_delivery, jar).await
+}
+
+#[tracing::instrument(name = "Reauthenticating with 2FA", skip_all)]
+async fn reauthenticate_with_2fa(
+    user: AuthenticatedUser,
+    login_attempt_id: String,
+    two_fa_code: String,
+    token_delivery: TokenDelivery,
+    client: ClientFingerprint,
+    state: &AppState,
+    jar: CookieJar,
+) -> (
+    CookieJar,
+    Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
+) {
+    let login_attempt_id = match LoginAttemptId::parse(Secret::new(login_attempt_id)) {
+        Ok(login_attempt_id) => login_attempt_id,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    let two_fa_code = match TwoFACode::parse(Secret::new(two_fa_code)) {
+        Ok(two_fa_code) => two_fa_code,
+        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+    };
+
+    // Only attempts started for the logged-in user are found
+    let policy = &state.two_fa_client_policy;
+    let attempt = match state
+        .two_fa_code_store
+        .write()
+        .await
+        .consume_code(
+            &login_attempt_id,
+            &user.email,
+            &two_fa_code,
+            &client,
+            policy,
+        )
+        .await
+    {
+        Ok(attempt) => attempt,
+        Err(TwoFACodeStoreError::ClientNotAllowed) => {
+            tracing::warn!(
+                "2FA code presented by a different client than the one that requested it"
+            );
+            return (jar, Err(AuthAPIError::IncorrectCredentials));
+        }
+        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+    };
+
+    if attempt.tenant != user.tenant {
+        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    }
+
+    let amr = AuthMethod::with_second_factor(attempt.first_factor);
+    refresh_auth_token(state, &user.tenant, &user.email, &amr, token_delivery, jar).await
+}
+
+// Swaps the current token for one with a fresh `auth_time`. The old token stays valid
+// until it expires, but only ever counts as a stale authentication.
+#[tracing::instrument(name = "Refreshing auth token", skip_all)]
+async fn refresh_auth_token(
+    state: &AppState,
+    tenant: &TenantId,
+    email: &Email,
+    amr: &[AuthMethod],
+    token_delivery: TokenDelivery,
+    jar: CookieJar,
+) -> (
+    CookieJar,
+    Result<(StatusCode, Json<LoginResponse>), AuthAPIError>,
+) {
+    let token = match issue_auth_token(state, tenant, email, amr).await {
+        Ok(token) => token,
+        Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
+    };
+
+    let (jar, response) = deliver_auth_token(jar, token, token_delivery);
+
+    (jar, Ok((StatusCode::OK, Json(response))))
+}
+
+#[derive(Deserialize)]
+pub struct ReauthenticateRequest {
+    #[serde(flatten)]
+    challenge: ReauthenticateChallenge,
+    #[serde(rename = "tokenDelivery", default)]
+    token_delivery: TokenDelivery,
+}
+
+// Either the password step or the 2FA step of reauthentication
+#[derive(Deserialize)]
+#[serde(untagged)]
+pub enum ReauthenticateChallenge {
+    TwoFactor {
+        #[serde(rename = "loginAttemptId")]
+        login_attempt_id: String,
+        #[serde(rename = "2FACode")]
+        two_fa_code: String,
+    },
+    Password {
+        password: Secret<String>,
+    },
+}
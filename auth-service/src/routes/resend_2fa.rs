@@ -0,0 +1,96 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use secrecy::Secret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuditRecord, AuthAPIError, ClientFingerprint, Email, LoginAttemptId, TwoFACode,
+        TwoFACodeStoreError,
+    },
+    utils::audit::record_audit_event,
+};
+
+use super::login::send_two_fa_code;
+
+#[tracing::instrument(name = "Re-sending 2FA code", skip_all)]
+pub async fn resend_2fa(
+    State(state): State<AppState>,
+    client: ClientFingerprint,
+    Json(request): Json<Resend2FARequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let email = Email::parse(request.email).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    let login_attempt_id = LoginAttemptId::parse(Secret::new(request.login_attempt_id))
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+    // The code goes to the account in the tenant the login was started for
+    let attempt = state
+        .two_fa_code_store
+        .read()
+        .await
+        .get_code(&login_attempt_id, &email)
+        .await
+        .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+    let result = async {
+        let user = state
+            .user_store
+            .read()
+            .await
+            .get_user(&attempt.tenant, &email)
+            .await
+            .map_err(|_| AuthAPIError::IncorrectCredentials)?;
+
+        // The old code stops working once a new one has been sent
+        let two_fa_code = TwoFACode::default();
+
+        match state
+            .two_fa_code_store
+            .write()
+            .await
+            .resend_code(&login_attempt_id, &email, two_fa_code.clone())
+            .await
+        {
+            Ok(()) => {}
+            Err(TwoFACodeStoreError::LoginAttemptIdNotFound) => {
+                return Err(AuthAPIError::IncorrectCredentials)
+            }
+            Err(TwoFACodeStoreError::ResendCooldown | TwoFACodeStoreError::ResendLimitReached) => {
+                return Err(AuthAPIError::TooManyRequests)
+            }
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        }
+
+        send_two_fa_code(&user, &two_fa_code, &state)
+            .await
+            .map_err(AuthAPIError::UnexpectedError)
+    }
+    .await;
+
+    let record = AuditRecord::new("two_fa_code_resent").user(&email);
+    let record = match &result {
+        Ok(()) => record,
+        Err(e) => record.failure(e),
+    };
+    record_audit_event(&state, &attempt.tenant, &client, record).await;
+    result?;
+
+    let response = Json(Resend2FAResponse {
+        message: "2FA code sent".to_owned(),
+    });
+
+    Ok((StatusCode::OK, response))
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Resend2FAResponse {
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+pub struct Resend2FARequest {
+    pub email: Secret<String>,
+    #[serde(rename = "loginAttemptId")]
+    pub login_attempt_id: String,
+}
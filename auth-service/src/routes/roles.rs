@@ -0,0 +1,157 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        permissions::ManageRoles, AuditRecord, AuthAPIError, Email, Grants, Role, RoleStoreError,
+        TenantId, UserStoreError,
+    },
+    utils::extractors::{Auditor, RequirePermission},
+};
+
+#[tracing::instrument(name = "Listing user roles", skip_all)]
+pub async fn list_user_roles(
+    State(state): State<AppState>,
+    RequirePermission { user: admin, .. }: RequirePermission<ManageRoles>,
+    Path(email): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    // Role managers only see the users of their own tenant
+    let tenant = admin.tenant;
+    let email = find_user(&state, &tenant, email).await?;
+
+    let grants = state
+        .role_store
+        .read()
+        .await
+        .get_grants(&tenant, &email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok((StatusCode::OK, Json(UserRolesResponse::from(&grants))))
+}
+
+#[tracing::instrument(name = "Assigning role", skip_all)]
+pub async fn assign_role(
+    State(state): State<AppState>,
+    RequirePermission { user: admin, .. }: RequirePermission<ManageRoles>,
+    auditor: Auditor,
+    Path((email, role)): Path<(String, String)>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let record = AuditRecord::new("role_assigned")
+        .actor(admin.email.as_ref().expose_secret())
+        .target(&role);
+    let result = async {
+        let email = find_user(&state, &admin.tenant, email).await?;
+        let role = Role::parse(role).map_err(|_| AuthAPIError::RoleNotFound)?;
+
+        state
+            .role_store
+            .write()
+            .await
+            .assign_role(&admin.tenant, &email, &role)
+            .await
+            .map_err(map_role_store_error)?;
+
+        Ok(email)
+    }
+    .await;
+
+    let record = match &result {
+        Ok(email) => record.user(email),
+        Err(_) => record,
+    };
+    auditor.record_result(record, &result).await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[tracing::instrument(name = "Unassigning role", skip_all)]
+pub async fn unassign_role(
+    State(state): State<AppState>,
+    RequirePermission { user: admin, .. }: RequirePermission<ManageRoles>,
+    auditor: Auditor,
+    Path((email, role)): Path<(String, String)>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let record = AuditRecord::new("role_unassigned")
+        .actor(admin.email.as_ref().expose_secret())
+        .target(&role);
+    let result = async {
+        let email = find_user(&state, &admin.tenant, email).await?;
+        let role = Role::parse(role).map_err(|_| AuthAPIError::RoleNotFound)?;
+
+        state
+            .role_store
+            .write()
+            .await
+            .unassign_role(&admin.tenant, &email, &role)
+            .await
+            .map_err(map_role_store_error)?;
+
+        Ok(email)
+    }
+    .await;
+
+    let record = match &result {
+        Ok(email) => record.user(email),
+        Err(_) => record,
+    };
+    auditor.record_result(record, &result).await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Roles can only be managed for users that exist
+async fn find_user(
+    state: &AppState,
+    tenant: &TenantId,
+    email: String,
+) -> Result<Email, AuthAPIError> {
+    let email = Email::parse(Secret::new(email)).map_err(|_| AuthAPIError::UserNotFound)?;
+
+    match state.user_store.read().await.get_user(tenant, &email).await {
+        Ok(_) => Ok(email),
+        Err(UserStoreError::UserNotFound) => Err(AuthAPIError::UserNotFound),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
+fn map_role_store_error(e: RoleStoreError) -> AuthAPIError {
+    match e {
+        RoleStoreError::RoleNotFound | RoleStoreError::RoleNotAssigned => {
+            AuthAPIError::RoleNotFound
+        }
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UserRolesResponse {
+    pub roles: Vec<String>,
+    pub permissions: Vec<String>,
+}
+
+impl From<&Grants> for UserRolesResponse {
+    fn from(grants: &Grants) -> Self {
+        Self {
+            roles: grants
+                .roles
+                .iter()
+                .map(|role| role.as_ref().to_owned())
+                .collect(),
+            permissions: grants
+                .permissions
+                .iter()
+                .map(|permission| permission.as_ref().to_owned())
+                .collect(),
+        }
+    }
+}
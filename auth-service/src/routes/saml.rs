@@ -0,0 +1,381 @@
+use axum::{
+    extract::{Path, State},
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::{IntoResponse, Redirect},
+    Form, Json,
+};
+use axum_extra::extract::{cookie::Cookie, CookieJar};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        AuditRecord, AuthAPIError, ClientFingerprint, Email, ProviderId, SamlProvider,
+        SamlProviderStoreError, SamlReplayCacheError, TenantId, UserStoreError,
+    },
+    services::saml_service_provider::{SamlAuthnRequest, SamlServiceProvider},
+    utils::{
+        audit::record_audit_event,
+        auth::{generate_saml_state_cookie, validate_saml_state_token, SamlStateClaims},
+        constants::{AUTH_SERVICE_URL, SAML_STATE_COOKIE_NAME},
+        extractors::{AdminCaller, Auditor, CurrentTenant},
+    },
+};
+
+use super::{
+    admin::audit,
+    login::ensure_account_active,
+    oidc::{complete_federated_login, MAX_NAME_LENGTH},
+};
+
+const METADATA_CONTENT_TYPE: &str = "application/samlmetadata+xml";
+
+#[tracing::instrument(name = "Adding SAML provider", skip_all)]
+pub async fn create_saml_provider(
+    State(state): State<AppState>,
+    admin: AdminCaller,
+    auditor: Auditor,
+    CurrentTenant(tenant): CurrentTenant,
+    Json(request): Json<CreateSamlProviderRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let record = AuditRecord::new("saml_provider_added").target(&request.id);
+    let result = async {
+        let id = ProviderId::parse(request.id).map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+        let name = request.name.trim().to_owned();
+        if name.is_empty() || name.len() > MAX_NAME_LENGTH {
+            return Err(AuthAPIError::InvalidCredentials);
+        }
+
+        let provider = SamlProvider::new(
+            id,
+            tenant.id.clone(),
+            name,
+            request.entity_id,
+            request.sso_url,
+            request.certificate,
+            request.email_attribute,
+        )
+        .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+        state
+            .saml_provider_store
+            .write()
+            .await
+            .add_provider(provider.clone())
+            .await
+            .map_err(|e| match e {
+                SamlProviderStoreError::ProviderAlreadyExists => {
+                    AuthAPIError::IdentityProviderAlreadyExists
+                }
+                e => AuthAPIError::UnexpectedError(e.into()),
+            })?;
+
+        Ok(provider)
+    }
+    .await;
+
+    audit(&auditor, &admin, record, &result).await;
+    let provider = result?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(SamlProviderResponse::from(&provider)),
+    ))
+}
+
+#[tracing::instrument(name = "Listing SAML providers", skip_all)]
+pub async fn list_saml_providers(
+    State(state): State<AppState>,
+    _admin: AdminCaller,
+    CurrentTenant(tenant): CurrentTenant,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let providers = state
+        .saml_provider_store
+        .read()
+        .await
+        .list_providers(&tenant.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response: Vec<SamlProviderResponse> = providers.iter().map(Into::into).collect();
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+#[tracing::instrument(name = "Deleting SAML provider", skip_all)]
+pub async fn delete_saml_provider(
+    State(state): State<AppState>,
+    admin: AdminCaller,
+    auditor: Auditor,
+    CurrentTenant(tenant): CurrentTenant,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let record = AuditRecord::new("saml_provider_deleted").target(&id);
+    let result = async {
+        let id = ProviderId::parse(id).map_err(|_| AuthAPIError::IdentityProviderNotFound)?;
+
+        state
+            .saml_provider_store
+            .write()
+            .await
+            .delete_provider(&tenant.id, &id)
+            .await
+            .map_err(map_saml_provider_store_error)?;
+
+        Ok(())
+    }
+    .await;
+
+    audit(&auditor, &admin, record, &result).await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Our side of the setup, for the provider to import
+#[tracing::instrument(name = "Serving SAML metadata", skip_all)]
+pub async fn saml_metadata(
+    State(state): State<AppState>,
+    CurrentTenant(tenant): CurrentTenant,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let provider = get_provider(&state, &tenant.id, id).await?;
+
+    Ok((
+        [(CONTENT_TYPE, METADATA_CONTENT_TYPE)],
+        service_provider(&provider.id).metadata(),
+    ))
+}
+
+// Sends the user to the provider with an AuthnRequest. Its ID is kept in a signed
+// cookie, and the provider's response has to be in response to it.
+#[tracing::instrument(name = "Starting SAML login", skip_all)]
+pub async fn saml_login(
+    State(state): State<AppState>,
+    CurrentTenant(tenant): CurrentTenant,
+    jar: CookieJar,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let provider = get_provider(&state, &tenant.id, id).await?;
+
+    let request = SamlAuthnRequest::new();
+    let url = service_provider(&provider.id)
+        .authn_request_url(&provider, &request)
+        .map_err(AuthAPIError::UnexpectedError)?;
+    let cookie = generate_saml_state_cookie(&tenant.id, &provider.id, &request)
+        .map_err(AuthAPIError::UnexpectedError)?;
+
+    Ok((jar.add(cookie), Redirect::to(url.as_str())))
+}
+
+// The Assertion Consumer Service the provider posts its response to. Once the
+// assertion checks out, the login is completed like with any other provider.
+#[tracing::instrument(name = "Completing SAML login", skip_all)]
+pub async fn saml_acs(
+    State(state): State<AppState>,
+    client: ClientFingerprint,
+    jar: CookieJar,
+    Path(id): Path<String>,
+    Form(request): Form<SamlAcsRequest>,
+) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
+    let Some(state_cookie) = jar
+        .get(SAML_STATE_COOKIE_NAME)
+        .map(|cookie| cookie.value().to_owned())
+    else {
+        return (jar, Err(AuthAPIError::FederatedLoginFailed));
+    };
+    // Each sign-in can only be completed once, whatever the outcome
+    let jar = jar.remove(Cookie::build(SAML_STATE_COOKIE_NAME).path("/"));
+
+    let (tenant, email) = match saml_login_user(&state, &client, &state_cookie, &id, request).await
+    {
+        Ok(login) => login,
+        Err(e) => return (jar, Err(e)),
+    };
+
+    complete_federated_login(&state, &tenant, &email, client, jar).await
+}
+
+// Checks the provider's response and finds the user it is for. Once the tenant is
+// known, a failed sign-in is recorded in its audit log.
+async fn saml_login_user(
+    state: &AppState,
+    client: &ClientFingerprint,
+    state_cookie: &str,
+    id: &str,
+    request: SamlAcsRequest,
+) -> Result<(TenantId, Email), AuthAPIError> {
+    let claims =
+        validate_saml_state_token(state_cookie).map_err(|_| AuthAPIError::FederatedLoginFailed)?;
+    if claims.provider != id {
+        return Err(AuthAPIError::FederatedLoginFailed);
+    }
+
+    let tenant = TenantId::parse(claims.tenant.clone()).map_err(AuthAPIError::UnexpectedError)?;
+    match provider_login(state, &tenant, claims, request).await {
+        Ok(email) => Ok((tenant, email)),
+        Err(e) => {
+            let record = AuditRecord::new("login").target(id).failure(&e);
+            record_audit_event(state, &tenant, client, record).await;
+            Err(e)
+        }
+    }
+}
+
+async fn provider_login(
+    state: &AppState,
+    tenant: &TenantId,
+    claims: SamlStateClaims,
+    request: SamlAcsRequest,
+) -> Result<Email, AuthAPIError> {
+    let provider = get_provider(state, tenant, claims.provider).await?;
+    let authn_request = SamlAuthnRequest {
+        id: claims.request_id,
+    };
+
+    let assertion = match service_provider(&provider.id).validate_response(
+        &provider,
+        &authn_request,
+        request.saml_response.expose_secret(),
+    ) {
+        Ok(assertion) => assertion,
+        Err(e) => {
+            tracing::warn!(
+                provider = provider.id.as_ref(),
+                error = %e,
+                "SAML response was rejected",
+            );
+            return Err(AuthAPIError::FederatedLoginFailed);
+        }
+    };
+
+    // A captured response could otherwise be posted again along with a copy of the
+    // state cookie for as long as both are valid
+    match state
+        .saml_replay_cache
+        .write()
+        .await
+        .record_assertion(tenant, &provider.id, &assertion.id, assertion.expires_at)
+        .await
+    {
+        Ok(()) => {}
+        Err(SamlReplayCacheError::AssertionReplayed) => {
+            tracing::warn!(
+                provider = provider.id.as_ref(),
+                "SAML assertion was replayed"
+            );
+            return Err(AuthAPIError::FederatedLoginFailed);
+        }
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+
+    // Only existing accounts can sign in; the provider decides which one
+    let email = assertion
+        .email(provider.email_attribute.as_deref())
+        .and_then(|email| Email::parse(Secret::new(email.to_owned())).ok())
+        .ok_or(AuthAPIError::FederatedIdentityNotLinked)?;
+
+    let user = match state.user_store.read().await.get_user(tenant, &email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::FederatedIdentityNotLinked),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    ensure_account_active(&user)?;
+
+    Ok(email)
+}
+
+async fn get_provider(
+    state: &AppState,
+    tenant: &TenantId,
+    id: String,
+) -> Result<SamlProvider, AuthAPIError> {
+    let id = ProviderId::parse(id).map_err(|_| AuthAPIError::IdentityProviderNotFound)?;
+
+    state
+        .saml_provider_store
+        .read()
+        .await
+        .get_provider(tenant, &id)
+        .await
+        .map_err(map_saml_provider_store_error)
+}
+
+fn map_saml_provider_store_error(e: SamlProviderStoreError) -> AuthAPIError {
+    match e {
+        SamlProviderStoreError::ProviderNotFound => AuthAPIError::IdentityProviderNotFound,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+// Both URLs have to be registered with the provider as they are
+fn service_provider(provider: &ProviderId) -> SamlServiceProvider {
+    let base_url = format!("{}/saml/{}", AUTH_SERVICE_URL.as_str(), provider.as_ref());
+
+    SamlServiceProvider::new(
+        format!("{}/metadata", base_url),
+        format!("{}/acs", base_url),
+    )
+}
+
+#[derive(Deserialize)]
+pub struct CreateSamlProviderRequest {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "entityId")]
+    pub entity_id: String,
+    #[serde(rename = "ssoUrl")]
+    pub sso_url: String,
+    pub certificate: String,
+    #[serde(rename = "emailAttribute")]
+    pub email_attribute: Option<String>,
+}
+
+// Includes what the provider has to be set up with on its side
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SamlProviderResponse {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "entityId")]
+    pub entity_id: String,
+    #[serde(rename = "ssoUrl")]
+    pub sso_url: String,
+    pub certificate: String,
+    #[serde(rename = "emailAttribute")]
+    pub email_attribute: Option<String>,
+    #[serde(rename = "spEntityId")]
+    pub sp_entity_id: String,
+    #[serde(rename = "acsUrl")]
+    pub acs_url: String,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+impl From<&SamlProvider> for SamlProviderResponse {
+    fn from(provider: &SamlProvider) -> Self {
+        let service_provider = service_provider(&provider.id);
+
+        Self {
+            id: provider.id.as_ref().to_owned(),
+            name: provider.name.clone(),
+            entity_id: provider.entity_id.clone(),
+            sso_url: provider.sso_url.clone(),
+            certificate: provider.certificate.clone(),
+            email_attribute: provider.email_attribute.clone(),
+            sp_entity_id: service_provider.entity_id,
+            acs_url: service_provider.acs_url,
+            created_at: provider.created_at.to_rfc3339(),
+        }
+    }
+}
+
+// Field names follow the HTTP-POST binding
+#[derive(Deserialize)]
+pub struct SamlAcsRequest {
+    #[serde(rename = "SAMLResponse")]
+    pub saml_response: Secret<String>,
+    #[serde(rename = "RelayState")]
+    pub relay_state: Option<String>,
+}
@@ -0,0 +1,792 @@
+use axum::{
+    extract::{
+        rejection::{JsonRejection, QueryRejection},
+        Path, Query, State,
+    },
+    http::{header::CONTENT_TYPE, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        data_stores::{GroupStoreError, UserQuery},
+        parse_member_path, parse_scim_bool, AccountStatus, AuditRecord, Email, Group, GroupId,
+        Password, ScimError, ScimFilter, ScimPatchOp, ScimPatchRequest, SuspendedBy, TenantId,
+        User, UserStoreError, SCIM_GROUP_SCHEMA, SCIM_LIST_RESPONSE_SCHEMA, SCIM_USER_SCHEMA,
+    },
+    utils::{
+        auth::revoke_all_sessions,
+        constants::{AUTH_SERVICE_URL, SCIM_CONTENT_TYPE},
+        extractors::{AdminCaller, Auditor, ScimCaller},
+    },
+};
+
+use super::admin::audit;
+
+const MAX_PAGE_SIZE: u64 = 100;
+const DEPROVISIONED_REASON: &str = "Deprovisioned by the provisioning system";
+
+#[tracing::instrument(name = "Listing SCIM users", skip_all)]
+pub async fn list_scim_users(
+    State(state): State<AppState>,
+    ScimCaller { tenant, .. }: ScimCaller,
+    query: Result<Query<ScimListRequest>, QueryRejection>,
+) -> Result<impl IntoResponse, ScimError> {
+    let Query(request) = query.map_err(|e| ScimError::InvalidSyntax(e.body_text()))?;
+    let start_index = request.start_index.unwrap_or(1).max(1);
+    let count = request.count.unwrap_or(MAX_PAGE_SIZE).min(MAX_PAGE_SIZE);
+    let user_store = state.user_store.read().await;
+
+    let (users, total) = match request.filter {
+        Some(filter) => {
+            let filter = ScimFilter::parse(&filter)?;
+            if filter.attribute != "username" {
+                return Err(ScimError::InvalidFilter(
+                    "Users can only be filtered by userName",
+                ));
+            }
+
+            // A user name that isn't an email matches nobody
+            let user = match Email::parse(Secret::new(filter.value)) {
+                Ok(email) => match user_store.get_user(&tenant.id, &email).await {
+                    Ok(user) => Some(user),
+                    Err(UserStoreError::UserNotFound) => None,
+                    Err(e) => return Err(ScimError::UnexpectedError(e.into())),
+                },
+                Err(_) => None,
+            };
+            let total = u64::from(user.is_some());
+            let users = user
+                .into_iter()
+                .skip((start_index - 1) as usize)
+                .take(count as usize);
+            (users.collect(), total)
+        }
+        None => {
+            let query = UserQuery {
+                search: None,
+                offset: start_index - 1,
+                limit: count,
+            };
+            let page = user_store
+                .list_users(&tenant.id, &query)
+                .await
+                .map_err(|e| ScimError::UnexpectedError(e.into()))?;
+            (page.users, page.total)
+        }
+    };
+
+    let resources: Vec<ScimUserResponse> = users.iter().map(Into::into).collect();
+    Ok(scim_json(
+        StatusCode::OK,
+        ScimListResponse::new(resources, total, start_index),
+    ))
+}
+
+#[tracing::instrument(name = "Getting SCIM user", skip_all)]
+pub async fn get_scim_user(
+    State(state): State<AppState>,
+    ScimCaller { tenant, .. }: ScimCaller,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ScimError> {
+    let email = parse_user_id(id)?;
+    let user = get_user(&state, &tenant.id, &email).await?;
+
+    Ok(scim_json(StatusCode::OK, ScimUserResponse::from(&user)))
+}
+
+#[tracing::instrument(name = "Provisioning SCIM user", skip_all)]
+pub async fn create_scim_user(
+    State(state): State<AppState>,
+    ScimCaller { admin, tenant }: ScimCaller,
+    auditor: Auditor,
+    request: Result<Json<ScimUserRequest>, JsonRejection>,
+) -> Result<impl IntoResponse, ScimError> {
+    let record = AuditRecord::new("user_provisioned");
+    let result = async {
+        let Json(request) = request.map_err(|e| ScimError::InvalidSyntax(e.body_text()))?;
+        let email = Email::parse(Secret::new(request.user_name))
+            .map_err(|_| ScimError::InvalidValue("userName must be an email address"))?;
+
+        let mut user = match request.password {
+            Some(password) => User::new(email.clone(), parse_password(password)?, false),
+            None => User::provisioned(email.clone()),
+        };
+        if !request
+            .active
+            .as_ref()
+            .map(parse_scim_bool)
+            .transpose()?
+            .unwrap_or(true)
+        {
+            user.status = AccountStatus::Suspended;
+            user.status_reason = Some(DEPROVISIONED_REASON.to_owned());
+            user.suspended_by = Some(SuspendedBy::Provisioning);
+        }
+
+        {
+            let mut user_store = state.user_store.write().await;
+            if user_store.get_user(&tenant.id, &email).await.is_ok() {
+                return Err(ScimError::Uniqueness(
+                    "A user with this userName already exists",
+                ));
+            }
+            user_store
+                .add_user(&tenant.id, user.clone())
+                .await
+                .map_err(map_user_store_error)?;
+        }
+
+        Ok(user)
+    }
+    .await;
+
+    let record = match &result {
+        Ok(user) => record.user(&user.email),
+        Err(_) => record,
+    };
+    audit(&auditor, &admin, record, &result).await;
+    let user = result?;
+
+    Ok(scim_json(
+        StatusCode::CREATED,
+        ScimUserResponse::from(&user),
+    ))
+}
+
+// Replaces the attributes this service keeps, when given: whether the user is active
+// and their password
+#[tracing::instrument(name = "Replacing SCIM user", skip_all)]
+pub async fn replace_scim_user(
+    State(state): State<AppState>,
+    ScimCaller { admin, tenant }: ScimCaller,
+    auditor: Auditor,
+    Path(id): Path<String>,
+    request: Result<Json<ScimUserRequest>, JsonRejection>,
+) -> Result<impl IntoResponse, ScimError> {
+    let Json(request) = request.map_err(|e| ScimError::InvalidSyntax(e.body_text()))?;
+    let email = parse_user_id(id)?;
+    let user = get_user(&state, &tenant.id, &email).await?;
+    ensure_same_user_name(&email, &request.user_name)?;
+
+    if let Some(password) = request.password {
+        state
+            .user_store
+            .write()
+            .await
+            .set_password(&tenant.id, &email, parse_password(password)?)
+            .await
+            .map_err(map_user_store_error)?;
+    }
+
+    if let Some(active) = request.active.as_ref().map(parse_scim_bool).transpose()? {
+        set_active(&state, &auditor, &admin, &tenant.id, &user, active).await?;
+    }
+
+    let user = get_user(&state, &tenant.id, &email).await?;
+    Ok(scim_json(StatusCode::OK, ScimUserResponse::from(&user)))
+}
+
+// Provisioning clients mostly patch `active` to deprovision a user. Attributes this
+// service doesn't keep, like the user's name, are ignored.
+#[tracing::instrument(name = "Patching SCIM user", skip_all)]
+pub async fn patch_scim_user(
+    State(state): State<AppState>,
+    ScimCaller { admin, tenant }: ScimCaller,
+    auditor: Auditor,
+    Path(id): Path<String>,
+    request: Result<Json<ScimPatchRequest>, JsonRejection>,
+) -> Result<impl IntoResponse, ScimError> {
+    let Json(request) = request.map_err(|e| ScimError::InvalidSyntax(e.body_text()))?;
+    let email = parse_user_id(id)?;
+    let mut user = get_user(&state, &tenant.id, &email).await?;
+
+    for operation in &request.operations {
+        let op = operation.op()?;
+        for (path, value) in operation.attributes()? {
+            match (op, path.as_str()) {
+                (ScimPatchOp::Remove, "active" | "username") => {
+                    return Err(ScimError::Mutability(
+                        "Required attributes can't be removed",
+                    ));
+                }
+                (_, "active") => {
+                    set_active(
+                        &state,
+                        &auditor,
+                        &admin,
+                        &tenant.id,
+                        &user,
+                        parse_scim_bool(value)?,
+                    )
+                    .await?;
+                    user = get_user(&state, &tenant.id, &email).await?;
+                }
+                (_, "username") => {
+                    let user_name = value
+                        .as_str()
+                        .ok_or(ScimError::InvalidValue("userName must be a string"))?;
+                    ensure_same_user_name(&email, user_name)?;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    Ok(scim_json(StatusCode::OK, ScimUserResponse::from(&user)))
+}
+
+// Removes the account for good, unlike setting `active` to false
+#[tracing::instrument(name = "Deleting SCIM user", skip_all)]
+pub async fn delete_scim_user(
+    State(state): State<AppState>,
+    ScimCaller { admin, tenant }: ScimCaller,
+    auditor: Auditor,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ScimError> {
+    let record = AuditRecord::new("user_deleted");
+    let result: Result<_, ScimError> = async {
+        let email = parse_user_id(id)?;
+        state
+            .user_store
+            .write()
+            .await
+            .delete_user(&tenant.id, &email)
+            .await
+            .map_err(map_user_store_error)?;
+
+        revoke_all_sessions(&tenant.id, &email, &state.banned_token_store)
+            .await
+            .map_err(ScimError::UnexpectedError)?;
+
+        Ok(email)
+    }
+    .await;
+
+    let record = match &result {
+        Ok(email) => record.user(email),
+        Err(_) => record,
+    };
+    audit(&auditor, &admin, record, &result).await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[tracing::instrument(name = "Listing SCIM groups", skip_all)]
+pub async fn list_scim_groups(
+    State(state): State<AppState>,
+    ScimCaller { tenant, .. }: ScimCaller,
+    query: Result<Query<ScimListRequest>, QueryRejection>,
+) -> Result<impl IntoResponse, ScimError> {
+    let Query(request) = query.map_err(|e| ScimError::InvalidSyntax(e.body_text()))?;
+    let start_index = request.start_index.unwrap_or(1).max(1);
+    let count = request.count.unwrap_or(MAX_PAGE_SIZE).min(MAX_PAGE_SIZE);
+
+    let mut groups = state
+        .group_store
+        .read()
+        .await
+        .list_groups(&tenant.id)
+        .await
+        .map_err(|e| ScimError::UnexpectedError(e.into()))?;
+
+    if let Some(filter) = request.filter {
+        let filter = ScimFilter::parse(&filter)?;
+        if filter.attribute != "displayname" {
+            return Err(ScimError::InvalidFilter(
+                "Groups can only be filtered by displayName",
+            ));
+        }
+        groups.retain(|group| group.display_name == filter.value);
+    }
+
+    let total = groups.len() as u64;
+    let resources: Vec<ScimGroupResponse> = groups
+        .iter()
+        .skip((start_index - 1) as usize)
+        .take(count as usize)
+        .map(Into::into)
+        .collect();
+
+    Ok(scim_json(
+        StatusCode::OK,
+        ScimListResponse::new(resources, total, start_index),
+    ))
+}
+
+#[tracing::instrument(name = "Getting SCIM group", skip_all)]
+pub async fn get_scim_group(
+    State(state): State<AppState>,
+    ScimCaller { tenant, .. }: ScimCaller,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ScimError> {
+    let group = get_group(&state, &tenant.id, &id).await?;
+
+    Ok(scim_json(StatusCode::OK, ScimGroupResponse::from(&group)))
+}
+
+#[tracing::instrument(name = "Provisioning SCIM group", skip_all)]
+pub async fn create_scim_group(
+    State(state): State<AppState>,
+    ScimCaller { admin, tenant }: ScimCaller,
+    auditor: Auditor,
+    request: Result<Json<ScimGroupRequest>, JsonRejection>,
+) -> Result<impl IntoResponse, ScimError> {
+    let record = AuditRecord::new("group_provisioned");
+    let result: Result<_, ScimError> = async {
+        let Json(request) = request.map_err(|e| ScimError::InvalidSyntax(e.body_text()))?;
+        let members = resolve_members(&state, &tenant.id, &request.members).await?;
+        let group = Group::new(tenant.id.clone(), request.display_name, members)
+            .map_err(|_| ScimError::InvalidValue("Invalid displayName"))?;
+
+        state
+            .group_store
+            .write()
+            .await
+            .add_group(group.clone())
+            .await
+            .map_err(map_group_store_error)?;
+
+        Ok(group)
+    }
+    .await;
+
+    let record = match &result {
+        Ok(group) => record.target(group.id),
+        Err(_) => record,
+    };
+    audit(&auditor, &admin, record, &result).await;
+    let group = result?;
+
+    Ok(scim_json(
+        StatusCode::CREATED,
+        ScimGroupResponse::from(&group),
+    ))
+}
+
+#[tracing::instrument(name = "Replacing SCIM group", skip_all)]
+pub async fn replace_scim_group(
+    State(state): State<AppState>,
+    ScimCaller { admin, tenant }: ScimCaller,
+    auditor: Auditor,
+    Path(id): Path<String>,
+    request: Result<Json<ScimGroupRequest>, JsonRejection>,
+) -> Result<impl IntoResponse, ScimError> {
+    let record = AuditRecord::new("group_updated").target(&id);
+    let result: Result<_, ScimError> = async {
+        let Json(request) = request.map_err(|e| ScimError::InvalidSyntax(e.body_text()))?;
+        let mut group = get_group(&state, &tenant.id, &id).await?;
+
+        group
+            .set_display_name(request.display_name)
+            .map_err(|_| ScimError::InvalidValue("Invalid displayName"))?;
+        group.set_members(resolve_members(&state, &tenant.id, &request.members).await?);
+        update_group(&state, &group).await?;
+
+        Ok(group)
+    }
+    .await;
+
+    audit(&auditor, &admin, record, &result).await;
+    let group = result?;
+
+    Ok(scim_json(StatusCode::OK, ScimGroupResponse::from(&group)))
+}
+
+// Supports renaming the group and adding or removing members, including by a path like
+// `members[value eq "alice@example.com"]`
+#[tracing::instrument(name = "Patching SCIM group", skip_all)]
+pub async fn patch_scim_group(
+    State(state): State<AppState>,
+    ScimCaller { admin, tenant }: ScimCaller,
+    auditor: Auditor,
+    Path(id): Path<String>,
+    request: Result<Json<ScimPatchRequest>, JsonRejection>,
+) -> Result<impl IntoResponse, ScimError> {
+    let record = AuditRecord::new("group_updated").target(&id);
+    let result = async {
+        let Json(request) = request.map_err(|e| ScimError::InvalidSyntax(e.body_text()))?;
+        let mut group = get_group(&state, &tenant.id, &id).await?;
+        let mut members = group.members.clone();
+
+        for operation in &request.operations {
+            let op = operation.op()?;
+
+            // A removal by filter has no value to go with the path
+            if let (ScimPatchOp::Remove, Some(path)) = (op, &operation.path) {
+                if let Some(member) = parse_member_path(path)? {
+                    members.retain(|email| email.as_ref().expose_secret() != &member);
+                    continue;
+                }
+            }
+
+            for (path, value) in operation.attributes()? {
+                match (op, path.as_str()) {
+                    (ScimPatchOp::Remove, "displayname") => {
+                        return Err(ScimError::Mutability(
+                            "Required attributes can't be removed",
+                        ));
+                    }
+                    (_, "displayname") => {
+                        let display_name = value
+                            .as_str()
+                            .ok_or(ScimError::InvalidValue("displayName must be a string"))?;
+                        group
+                            .set_display_name(display_name.to_owned())
+                            .map_err(|_| ScimError::InvalidValue("Invalid displayName"))?;
+                    }
+                    (_, "members") => {
+                        let changed = match value {
+                            Value::Null => vec![],
+                            value => {
+                                let changed: Vec<ScimMember> =
+                                    serde_json::from_value(value.clone()).map_err(|_| {
+                                        ScimError::InvalidValue("members must be a list of members")
+                                    })?;
+                                resolve_members(&state, &tenant.id, &changed).await?
+                            }
+                        };
+
+                        match op {
+                            ScimPatchOp::Add => members.extend(changed),
+                            ScimPatchOp::Replace => members = changed,
+                            // Without a value, every member is removed
+                            ScimPatchOp::Remove if value.is_null() => members.clear(),
+                            ScimPatchOp::Remove => members.retain(|email| !changed.contains(email)),
+                        }
+                    }
+                    _ => return Err(ScimError::InvalidPath),
+                }
+            }
+        }
+
+        group.set_members(members);
+        update_group(&state, &group).await?;
+
+        Ok(group)
+    }
+    .await;
+
+    audit(&auditor, &admin, record, &result).await;
+    let group = result?;
+
+    Ok(scim_json(StatusCode::OK, ScimGroupResponse::from(&group)))
+}
+
+#[tracing::instrument(name = "Deleting SCIM group", skip_all)]
+pub async fn delete_scim_group(
+    State(state): State<AppState>,
+    ScimCaller { admin, tenant }: ScimCaller,
+    auditor: Auditor,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, ScimError> {
+    let record = AuditRecord::new("group_deleted").target(&id);
+    let result: Result<_, ScimError> = async {
+        let group = get_group(&state, &tenant.id, &id).await?;
+        state
+            .group_store
+            .write()
+            .await
+            .delete_group(&tenant.id, &group.id)
+            .await
+            .map_err(map_group_store_error)?;
+
+        Ok(())
+    }
+    .await;
+
+    audit(&auditor, &admin, record, &result).await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Moves the user between `active` and suspended. Deprovisioning also ends every
+// session the user has. Reactivating only lifts a suspension deprovisioning caused, going
+// by `suspended_by` rather than the reason an admin can type freely, so syncs leave alone
+// users an admin suspended and those still pending verification.
+async fn set_active(
+    state: &AppState,
+    auditor: &Auditor,
+    admin: &AdminCaller,
+    tenant: &TenantId,
+    user: &User,
+    active: bool,
+) -> Result<(), ScimError> {
+    let (status, reason, suspended_by, event) = match active {
+        true if user.status == AccountStatus::Suspended
+            && user.suspended_by == Some(SuspendedBy::Provisioning) =>
+        {
+            (AccountStatus::Active, None, None, "user_reactivated")
+        }
+        false if user.status != AccountStatus::Suspended => (
+            AccountStatus::Suspended,
+            Some(DEPROVISIONED_REASON.to_owned()),
+            Some(SuspendedBy::Provisioning),
+            "user_deprovisioned",
+        ),
+        _ => return Ok(()),
+    };
+
+    let record = AuditRecord::new(event).user(&user.email);
+    let result = async {
+        state
+            .user_store
+            .write()
+            .await
+            .set_status(tenant, &user.email, status, reason, suspended_by)
+            .await
+            .map_err(map_user_store_error)?;
+
+        if !active {
+            revoke_all_sessions(tenant, &user.email, &state.banned_token_store)
+                .await
+                .map_err(ScimError::UnexpectedError)?;
+        }
+
+        Ok(())
+    }
+    .await;
+
+    audit(auditor, admin, record, &result).await;
+    result
+}
+
+async fn get_user(state: &AppState, tenant: &TenantId, email: &Email) -> Result<User, ScimError> {
+    state
+        .user_store
+        .read()
+        .await
+        .get_user(tenant, email)
+        .await
+        .map_err(map_user_store_error)
+}
+
+async fn get_group(state: &AppState, tenant: &TenantId, id: &str) -> Result<Group, ScimError> {
+    let id = GroupId::parse(id).map_err(|_| ScimError::NotFound("Group not found"))?;
+    state
+        .group_store
+        .read()
+        .await
+        .get_group(tenant, &id)
+        .await
+        .map_err(map_group_store_error)
+}
+
+async fn update_group(state: &AppState, group: &Group) -> Result<(), ScimError> {
+    state
+        .group_store
+        .write()
+        .await
+        .update_group(group.clone())
+        .await
+        .map_err(map_group_store_error)
+}
+
+// Members are referenced by user name and must be users of the tenant
+async fn resolve_members(
+    state: &AppState,
+    tenant: &TenantId,
+    members: &[ScimMember],
+) -> Result<Vec<Email>, ScimError> {
+    let user_store = state.user_store.read().await;
+    let mut emails = Vec::with_capacity(members.len());
+
+    for member in members {
+        let email = Email::parse(Secret::new(member.value.clone()))
+            .map_err(|_| ScimError::InvalidValue("Members must be existing users"))?;
+        match user_store.get_user(tenant, &email).await {
+            Ok(_) => emails.push(email),
+            Err(UserStoreError::UserNotFound) => {
+                return Err(ScimError::InvalidValue("Members must be existing users"))
+            }
+            Err(e) => return Err(ScimError::UnexpectedError(e.into())),
+        }
+    }
+
+    Ok(emails)
+}
+
+// Users are identified by their user name, which is their email
+fn parse_user_id(id: String) -> Result<Email, ScimError> {
+    Email::parse(Secret::new(id)).map_err(|_| ScimError::NotFound("User not found"))
+}
+
+fn parse_password(password: Secret<String>) -> Result<Password, ScimError> {
+    Password::parse(password).map_err(|_| ScimError::InvalidValue("Invalid password"))
+}
+
+fn ensure_same_user_name(email: &Email, user_name: &str) -> Result<(), ScimError> {
+    match Email::parse(Secret::new(user_name.to_owned())) {
+        Ok(other) if &other == email => Ok(()),
+        _ => Err(ScimError::Mutability("userName can't be changed")),
+    }
+}
+
+fn map_user_store_error(e: UserStoreError) -> ScimError {
+    match e {
+        UserStoreError::UserNotFound => ScimError::NotFound("User not found"),
+        UserStoreError::UserAlreadyExists => {
+            ScimError::Uniqueness("A user with this userName already exists")
+        }
+        UserStoreError::ReadOnly => ScimError::Mutability("The user is managed by the directory"),
+        e => ScimError::UnexpectedError(e.into()),
+    }
+}
+
+fn map_group_store_error(e: GroupStoreError) -> ScimError {
+    match e {
+        GroupStoreError::GroupNotFound => ScimError::NotFound("Group not found"),
+        GroupStoreError::GroupAlreadyExists => {
+            ScimError::Uniqueness("A group with this displayName already exists")
+        }
+        e => ScimError::UnexpectedError(e.into()),
+    }
+}
+
+fn scim_json<T: Serialize>(status: StatusCode, body: T) -> Response {
+    (status, [(CONTENT_TYPE, SCIM_CONTENT_TYPE)], Json(body)).into_response()
+}
+
+#[derive(Deserialize)]
+pub struct ScimListRequest {
+    pub filter: Option<String>,
+    #[serde(rename = "startIndex")]
+    pub start_index: Option<u64>,
+    pub count: Option<u64>,
+}
+
+#[derive(Deserialize)]
+pub struct ScimUserRequest {
+    #[serde(rename = "userName")]
+    pub user_name: String,
+    // A boolean, or a string like "True" from some clients
+    pub active: Option<Value>,
+    pub password: Option<Secret<String>>,
+}
+
+#[derive(Deserialize)]
+pub struct ScimGroupRequest {
+    #[serde(rename = "displayName")]
+    pub display_name: String,
+    #[serde(default)]
+    pub members: Vec<ScimMember>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScimMember {
+    pub value: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScimListResponse<T> {
+    pub schemas: Vec<String>,
+    #[serde(rename = "totalResults")]
+    pub total_results: u64,
+    #[serde(rename = "itemsPerPage")]
+    pub items_per_page: u64,
+    #[serde(rename = "startIndex")]
+    pub start_index: u64,
+    #[serde(rename = "Resources")]
+    pub resources: Vec<T>,
+}
+
+impl<T> ScimListResponse<T> {
+    fn new(resources: Vec<T>, total_results: u64, start_index: u64) -> Self {
+        Self {
+            schemas: vec![SCIM_LIST_RESPONSE_SCHEMA.to_owned()],
+            total_results,
+            items_per_page: resources.len() as u64,
+            start_index,
+            resources,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScimMeta {
+    #[serde(rename = "resourceType")]
+    pub resource_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created: Option<String>,
+    pub location: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScimEmail {
+    pub value: String,
+    pub primary: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScimUserResponse {
+    pub schemas: Vec<String>,
+    pub id: String,
+    #[serde(rename = "userName")]
+    pub user_name: String,
+    pub active: bool,
+    pub emails: Vec<ScimEmail>,
+    pub meta: ScimMeta,
+}
+
+impl From<&User> for ScimUserResponse {
+    fn from(user: &User) -> Self {
+        let email = user.email.as_ref().expose_secret().to_owned();
+        Self {
+            schemas: vec![SCIM_USER_SCHEMA.to_owned()],
+            meta: ScimMeta {
+                resource_type: "User".to_owned(),
+                created: None,
+                location: format!("{}/scim/v2/Users/{}", AUTH_SERVICE_URL.as_str(), email),
+            },
+            id: email.clone(),
+            user_name: email.clone(),
+            active: user.status == AccountStatus::Active,
+            emails: vec![ScimEmail {
+                value: email,
+                primary: true,
+            }],
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ScimGroupResponse {
+    pub schemas: Vec<String>,
+    pub id: String,
+    #[serde(rename = "displayName")]
+    pub display_name: String,
+    pub members: Vec<ScimMember>,
+    pub meta: ScimMeta,
+}
+
+impl From<&Group> for ScimGroupResponse {
+    fn from(group: &Group) -> Self {
+        Self {
+            schemas: vec![SCIM_GROUP_SCHEMA.to_owned()],
+            id: group.id.to_string(),
+            display_name: group.display_name.clone(),
+            members: group
+                .members
+                .iter()
+                .map(|email| ScimMember {
+                    value: email.as_ref().expose_secret().to_owned(),
+                    display: Some(email.as_ref().expose_secret().to_owned()),
+                })
+                .collect(),
+            meta: ScimMeta {
+                resource_type: "Group".to_owned(),
+                created: Some(group.created_at.to_rfc3339()),
+                location: format!("{}/scim/v2/Groups/{}", AUTH_SERVICE_URL.as_str(), group.id),
+            },
+        }
+    }
+}
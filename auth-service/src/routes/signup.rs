@@ -4,39 +4,43 @@ use serde::{Deserialize, Serialize};
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, Password, User},
+    domain::{AuditRecord, AuthAPIError, Email, Password, TenantId, User, UserStoreError},
+    utils::extractors::{Auditor, CurrentTenant},
 };
 
 #[tracing::instrument(name = "Signup", skip_all)]
 pub async fn signup(
     State(state): State<AppState>,
+    CurrentTenant(tenant): CurrentTenant,
+    auditor: Auditor,
     Json(request): Json<SignupRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
     let email =
         Email::parse(request.email.clone()).map_err(|_| AuthAPIError::InvalidCredentials)?;
-    let password =
-        Password::parse(request.password.clone()).map_err(|_| AuthAPIError::InvalidCredentials)?;
 
-    // TODO: early return AuthAPIError::InvalidCredentials if:
-    // - email is empty or does not contain '@'
-    // - password is less than 8 characters
-    // if email.is_empty() || password.len() < 8 || !email.contains('@') {
-    //     return Err(AuthAPIError::InvalidCredentials);
-    // }
+    let result = async {
+        let password = Password::parse(request.password.clone())
+            .map_err(|_| AuthAPIError::InvalidCredentials)?;
 
-    let user = User::new(email, password, request.requires_2fa);
+        // TODO: early return AuthAPIError::InvalidCredentials if:
+        // - email is empty or does not contain '@'
+        // - password is less than 8 characters
+        // if email.is_empty() || password.len() < 8 || !email.contains('@') {
+        //     return Err(AuthAPIError::InvalidCredentials);
+        // }
 
-    let mut user_store = state.user_store.write().await;
-
-    // TODO: early return AuthAPIError::UserAlreadyExists if email exists in user_store.
-    if user_store.get_user(&user.email).await.is_ok() {
-        return Err(AuthAPIError::UserAlreadyExists);
-    }
+        if !tenant.settings.allows_email(&email) {
+            return Err(AuthAPIError::EmailDomainNotAllowed);
+        }
 
-    // TODO: instead of using unwrap, early return AuthAPIError::UnexpectedError if add_user() fails.
-    if let Err(e) = user_store.add_user(user).await {
-        return Err(AuthAPIError::UnexpectedError(e.into()));
+        let user = User::new(email.clone(), password, request.requires_2fa);
+        add_new_user(&state, &tenant.id, user).await
     }
+    .await;
+    auditor
+        .record_result(AuditRecord::new("signup").user(&email), &result)
+        .await;
+    result?;
 
     let response = Json(SignupResponse {
         message: "User created successfully!".to_string(),
@@ -45,6 +49,28 @@ pub async fn signup(
     Ok((StatusCode::CREATED, response))
 }
 
+// Shared with accepting an invitation, which creates accounts the same way
+pub(super) async fn add_new_user(
+    state: &AppState,
+    tenant: &TenantId,
+    user: User,
+) -> Result<(), AuthAPIError> {
+    let mut user_store = state.user_store.write().await;
+
+    // TODO: early return AuthAPIError::UserAlreadyExists if email exists in user_store.
+    if user_store.get_user(tenant, &user.email).await.is_ok() {
+        return Err(AuthAPIError::UserAlreadyExists);
+    }
+
+    // TODO: instead of using unwrap, early return AuthAPIError::UnexpectedError if add_user() fails.
+    match user_store.add_user(tenant, user).await {
+        Ok(()) => Ok(()),
+        // Only people in the directory can have accounts
+        Err(UserStoreError::ReadOnly) => Err(AuthAPIError::SignupDisabled),
+        Err(e) => Err(AuthAPIError::UnexpectedError(e.into())),
+    }
+}
+
 #[derive(Serialize)]
 pub struct SignupResponse {
     pub message: String,
@@ -0,0 +1,89 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditRecord, AuthAPIError, TrustedDevice, TrustedDeviceId, TrustedDeviceStoreError},
+    utils::extractors::{Auditor, InteractiveUser},
+};
+
+#[tracing::instrument(name = "Listing trusted devices", skip_all)]
+pub async fn list_trusted_devices(
+    State(state): State<AppState>,
+    InteractiveUser(user): InteractiveUser,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let devices = state
+        .trusted_device_store
+        .read()
+        .await
+        .list_devices(&user.tenant, &user.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response: Vec<TrustedDeviceResponse> = devices.iter().map(Into::into).collect();
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+#[tracing::instrument(name = "Revoking trusted device", skip_all)]
+pub async fn revoke_trusted_device(
+    State(state): State<AppState>,
+    InteractiveUser(user): InteractiveUser,
+    auditor: Auditor,
+    Path(device_id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let record = AuditRecord::new("trusted_device_revoked")
+        .user(&user.email)
+        .target(&device_id);
+    let result = async {
+        let device_id =
+            TrustedDeviceId::parse(&device_id).map_err(|_| AuthAPIError::TrustedDeviceNotFound)?;
+
+        state
+            .trusted_device_store
+            .write()
+            .await
+            .revoke_device(&user.tenant, &user.email, &device_id)
+            .await
+            .map_err(|e| match e {
+                TrustedDeviceStoreError::DeviceNotFound => AuthAPIError::TrustedDeviceNotFound,
+                e => AuthAPIError::UnexpectedError(e.into()),
+            })
+    }
+    .await;
+
+    auditor.record_result(record, &result).await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrustedDeviceResponse {
+    pub id: String,
+    #[serde(rename = "userAgent")]
+    pub user_agent: Option<String>,
+    #[serde(rename = "ipAddress")]
+    pub ip_address: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: String,
+}
+
+impl From<&TrustedDevice> for TrustedDeviceResponse {
+    fn from(device: &TrustedDevice) -> Self {
+        Self {
+            id: device.id.to_string(),
+            user_agent: device.user_agent.clone(),
+            ip_address: device.ip_address.map(|ip| ip.to_string()),
+            created_at: device.created_at.to_rfc3339(),
+            expires_at: device.expires_at.to_rfc3339(),
+        }
+    }
+}
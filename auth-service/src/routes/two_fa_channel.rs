@@ -0,0 +1,48 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde::Deserialize;
+
+use crate::{
+    app_state::AppState,
+    domain::{AuditRecord, AuthAPIError, TwoFAChannel},
+    utils::extractors::{Auditor, RecentlyAuthenticatedUser},
+};
+
+#[tracing::instrument(name = "Setting 2FA channel", skip_all)]
+pub async fn set_two_fa_channel(
+    State(state): State<AppState>,
+    RecentlyAuthenticatedUser(user): RecentlyAuthenticatedUser,
+    auditor: Auditor,
+    Json(request): Json<SetTwoFAChannelRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let result = async {
+        let mut user_store = state.user_store.write().await;
+
+        let stored_user = user_store
+            .get_user(&user.tenant, &user.email)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        if request.channel == TwoFAChannel::Sms && stored_user.phone_number.is_none() {
+            return Err(AuthAPIError::PhoneNumberNotVerified);
+        }
+
+        user_store
+            .set_two_fa_channel(&user.tenant, &user.email, request.channel)
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))
+    }
+    .await;
+
+    let record = AuditRecord::new("two_fa_channel_changed")
+        .user(&user.email)
+        .target(request.channel.as_str());
+    auditor.record_result(record, &result).await;
+    result?;
+
+    Ok(StatusCode::OK)
+}
+
+#[derive(Deserialize)]
+pub struct SetTwoFAChannelRequest {
+    pub channel: TwoFAChannel,
+}
@@ -1,62 +1,158 @@
 use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
-use axum_extra::extract::CookieJar;
+use axum_extra::extract::{cookie::Cookie, CookieJar};
 use secrecy::Secret;
 use serde::Deserialize;
 
 use crate::{
     app_state::AppState,
-    domain::{AuthAPIError, Email, LoginAttemptId, TwoFACode},
-    utils::auth::generate_auth_cookie,
+    domain::{
+        data_stores::TwoFACodeStoreError, AuditRecord, AuthAPIError, AuthMethod, ClientFingerprint,
+        Email, LoginAttemptId, LoginContext, TenantId, TokenDelivery, TrustedDevice, TwoFACode,
+    },
+    utils::{
+        audit::record_audit_event, auth::generate_trusted_device_cookie,
+        constants::TRUSTED_DEVICE_TTL_DAYS, extractors::Auditor,
+    },
+};
+
+use super::login::{
+    deliver_auth_token, ensure_account_active, issue_auth_token, login_record,
+    record_login_success, LoginResponse,
 };
 
 #[tracing::instrument(name = "Verifying 2FA", skip_all)]
 pub async fn verify_2fa(
-    State(state): State<AppState>,    
+    State(state): State<AppState>,
+    client: ClientFingerprint,
+    auditor: Auditor,
     jar: CookieJar,
     Json(request): Json<Verify2FARequest>,
 ) -> (CookieJar, Result<impl IntoResponse, AuthAPIError>) {
     let email = match Email::parse(request.email) {
         Ok(email) => email,
-        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+        Err(_) => {
+            let e = verification_failed(&auditor, None, AuthAPIError::InvalidCredentials).await;
+            return (jar, Err(e));
+        }
     };
 
+    let failed = |e| verification_failed(&auditor, Some(&email), e);
+
     let login_attempt_id = match LoginAttemptId::parse(Secret::new(request.login_attempt_id)) {
         Ok(login_attempt_id) => login_attempt_id,
-        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+        Err(_) => return (jar, Err(failed(AuthAPIError::InvalidCredentials).await)),
     };
 
     let two_fa_code = match TwoFACode::parse(Secret::new(request.two_fa_code)) {
         Ok(two_fa_code) => two_fa_code,
-        Err(_) => return (jar, Err(AuthAPIError::InvalidCredentials)),
+        Err(_) => return (jar, Err(failed(AuthAPIError::InvalidCredentials).await)),
+    };
+
+    // The store only hands back the attempt if it was started for this email, and
+    // removes it once redeemed. The attempt also remembers the tenant the user logged in to.
+    let policy = &state.two_fa_client_policy;
+    let attempt = match state
+        .two_fa_code_store
+        .write()
+        .await
+        .consume_code(&login_attempt_id, &email, &two_fa_code, &client, policy)
+        .await
+    {
+        Ok(attempt) => attempt,
+        Err(TwoFACodeStoreError::ClientNotAllowed) => {
+            tracing::warn!("2FA code presented by a different client than the one that logged in");
+            return (jar, Err(failed(AuthAPIError::IncorrectCredentials).await));
+        }
+        Err(_) => return (jar, Err(failed(AuthAPIError::IncorrectCredentials).await)),
     };
 
-    // New!
-    let mut two_fa_code_store = state.two_fa_code_store.write().await;
+    let tenant = attempt.tenant;
 
-    // Call `two_fa_code_store.get_code`. If the call fails
-    // return a `AuthAPIError::IncorrectCredentials`.
-    let code_tuple = match two_fa_code_store.get_code(&email).await {
-        Ok(code_tuple) => code_tuple,
-        Err(_) => return (jar, Err(AuthAPIError::IncorrectCredentials)),
+    // The account may have been suspended while the code was in flight
+    let user = match state
+        .user_store
+        .read()
+        .await
+        .get_user(&tenant, &email)
+        .await
+    {
+        Ok(user) => user,
+        Err(_) => return (jar, Err(failed(AuthAPIError::IncorrectCredentials).await)),
     };
 
-    // TODO: Validate that the `login_attempt_id` and `two_fa_code`
-    // in the request body matches values in the `code_tuple`.
-    // If not, return a `AuthAPIError::IncorrectCredentials`.
-    if code_tuple.0 != login_attempt_id || code_tuple.1 != two_fa_code {
-        return (jar, Err(AuthAPIError::IncorrectCredentials));
+    if let Err(e) = ensure_account_active(&user) {
+        return (jar, Err(failed(e).await));
     }
 
-    let _ = two_fa_code_store.remove_code(&email).await;
-
-    let auth_cookie = match generate_auth_cookie(&email) {
-        Ok(cookie) => cookie,
+    let amr = AuthMethod::with_second_factor(attempt.first_factor);
+    let token = match issue_auth_token(&state, &tenant, &email, &amr).await {
+        Ok(token) => token,
         Err(e) => return (jar, Err(AuthAPIError::UnexpectedError(e))),
     };
 
-    let updated_jar = jar.add(auth_cookie);
+    let (mut updated_jar, response) = deliver_auth_token(jar, token, request.token_delivery);
+
+    let context = LoginContext::new(tenant.clone(), email.clone(), client.clone());
+    record_login_success(&state, &context, login_record(&amr)).await;
+
+    if request.remember_device {
+        let device_cookie = match remember_device(&state, tenant, email, client).await {
+            Ok(cookie) => cookie,
+            Err(e) => return (updated_jar, Err(e)),
+        };
+        updated_jar = updated_jar.add(device_cookie);
+    }
+
+    let response = match response {
+        LoginResponse::RegularAuth => StatusCode::OK.into_response(),
+        response => (StatusCode::OK, Json(response)).into_response(),
+    };
+
+    (updated_jar, Ok(response))
+}
+
+// Records a 2FA code that was refused, and hands back the error to answer it with
+async fn verification_failed(
+    auditor: &Auditor,
+    email: Option<&Email>,
+    e: AuthAPIError,
+) -> AuthAPIError {
+    let record = match email {
+        Some(email) => AuditRecord::new("two_fa_verification").user(email),
+        None => AuditRecord::new("two_fa_verification"),
+    };
+    auditor.record(record.failure(&e)).await;
+    e
+}
+
+#[tracing::instrument(name = "Remembering device", skip_all)]
+async fn remember_device(
+    state: &AppState,
+    tenant: TenantId,
+    email: Email,
+    client: ClientFingerprint,
+) -> Result<Cookie<'static>, AuthAPIError> {
+    let record = AuditRecord::new("trusted_device_added").user(&email);
+    let device = TrustedDevice::new(
+        tenant.clone(),
+        email,
+        client.clone(),
+        chrono::Duration::days(*TRUSTED_DEVICE_TTL_DAYS),
+    );
+
+    let cookie = generate_trusted_device_cookie(&device).map_err(AuthAPIError::UnexpectedError)?;
+
+    state
+        .trusted_device_store
+        .write()
+        .await
+        .add_device(device)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    record_audit_event(state, &tenant, &client, record).await;
 
-    (updated_jar, Ok(StatusCode::OK.into_response()))
+    Ok(cookie)
 }
 
 // TODO: implement the Verify2FARequest struct. See the verify-2fa route contract in step 1 for the expected JSON body.
@@ -67,4 +163,9 @@ pub struct Verify2FARequest {
     pub login_attempt_id: String,
     #[serde(rename = "2FACode")]
     pub two_fa_code: String,
+    // Issue a trusted-device cookie so this browser can skip 2FA next time
+    #[serde(rename = "rememberDevice", default)]
+    pub remember_device: bool,
+    #[serde(rename = "tokenDelivery", default)]
+    pub token_delivery: TokenDelivery,
 }
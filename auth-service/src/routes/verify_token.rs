@@ -2,20 +2,50 @@ use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
 use secrecy::Secret;
 use serde::Deserialize;
 
-use crate::{app_state::AppState, domain::AuthAPIError, utils::auth::validate_token};
+use crate::{
+    app_state::AppState,
+    domain::{AuditRecord, AuthAPIError},
+    utils::{auth::validate_credential, extractors::Auditor},
+};
 
+// Only rejected tokens are recorded: other services call this for every request they
+// serve
 #[tracing::instrument(name = "Verifying token", skip_all)]
 pub async fn verify_token(
     State(state): State<AppState>,
+    auditor: Auditor,
     Json(request): Json<VerifyTokenRequest>,
 ) -> Result<impl IntoResponse, AuthAPIError> {
-    match validate_token(&request.token, state.banned_token_store.clone()).await {
-        Ok(_) => return Ok(StatusCode::OK.into_response()),
-        Err(_) => return Err(AuthAPIError::InvalidToken),
+    // API keys are accepted too, and come back as claims for the key's owner
+    let claims = match validate_credential(&request.token, &state).await {
+        Ok(claims) => claims,
+        Err(e) => {
+            auditor
+                .record(AuditRecord::new("token_verification").failure(&e))
+                .await;
+            return Err(e);
+        }
     };
+
+    // Lets other services guard a route by permission without decoding the token
+    if let Some(permission) = &request.required_permission {
+        if !claims.has_permission(permission) {
+            let record = AuditRecord::new("token_verification")
+                .actor(&claims.sub)
+                .target(permission)
+                .failure(AuthAPIError::MissingPermission);
+            auditor.record(record).await;
+            return Err(AuthAPIError::MissingPermission);
+        }
+    }
+
+    // The claims go back to the caller so other services know whose token it is
+    Ok((StatusCode::OK, Json(claims)))
 }
 
 #[derive(Deserialize)]
 pub struct VerifyTokenRequest {
     pub token: Secret<String>,
+    #[serde(rename = "requiredPermission", default)]
+    pub required_permission: Option<String>,
 }
@@ -0,0 +1,316 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use secrecy::ExposeSecret;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        data_stores::WebhookStoreError, AuditRecord, AuthAPIError, Webhook, WebhookDelivery,
+        WebhookDeliveryId, WebhookDeliveryQuery, WebhookDeliveryStatus, WebhookEventType,
+        WebhookId,
+    },
+    utils::extractors::{AdminCaller, Auditor, CurrentTenant},
+};
+
+use super::admin::audit;
+
+const DEFAULT_PER_PAGE: u64 = 20;
+const MAX_PER_PAGE: u64 = 100;
+
+#[tracing::instrument(name = "Creating webhook", skip_all)]
+pub async fn create_webhook(
+    State(state): State<AppState>,
+    admin: AdminCaller,
+    auditor: Auditor,
+    CurrentTenant(tenant): CurrentTenant,
+    Json(request): Json<CreateWebhookRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let record = AuditRecord::new("webhook_created");
+    let result = async {
+        let events = request
+            .events
+            .iter()
+            .map(|event| WebhookEventType::parse(event))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| AuthAPIError::InvalidCredentials)?;
+        let webhook = Webhook::new(tenant.id.clone(), request.url, events)
+            .map_err(|_| AuthAPIError::InvalidCredentials)?;
+        state
+            .webhook_url_policy
+            .check(&webhook.url)
+            .await
+            .map_err(|_| AuthAPIError::InvalidCredentials)?;
+
+        state
+            .webhook_store
+            .write()
+            .await
+            .add_webhook(webhook.clone())
+            .await
+            .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+        Ok(webhook)
+    }
+    .await;
+
+    let record = match &result {
+        Ok(webhook) => record.target(webhook.id),
+        Err(_) => record,
+    };
+    audit(&auditor, &admin, record, &result).await;
+    let webhook = result?;
+
+    Ok((
+        StatusCode::CREATED,
+        Json(CreateWebhookResponse {
+            webhook: (&webhook).into(),
+            secret: webhook.secret.expose_secret().to_owned(),
+        }),
+    ))
+}
+
+#[tracing::instrument(name = "Listing webhooks", skip_all)]
+pub async fn list_webhooks(
+    State(state): State<AppState>,
+    _admin: AdminCaller,
+    CurrentTenant(tenant): CurrentTenant,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let webhooks = state
+        .webhook_store
+        .read()
+        .await
+        .list_webhooks(&tenant.id)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    let response: Vec<WebhookResponse> = webhooks.iter().map(Into::into).collect();
+
+    Ok((StatusCode::OK, Json(response)))
+}
+
+// Deliveries still queued for the webhook are dropped with it
+#[tracing::instrument(name = "Deleting webhook", skip_all)]
+pub async fn delete_webhook(
+    State(state): State<AppState>,
+    admin: AdminCaller,
+    auditor: Auditor,
+    CurrentTenant(tenant): CurrentTenant,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let record = AuditRecord::new("webhook_deleted").target(&id);
+    let result = async {
+        let id = WebhookId::parse(&id).map_err(|_| AuthAPIError::WebhookNotFound)?;
+
+        state
+            .webhook_store
+            .write()
+            .await
+            .delete_webhook(&tenant.id, &id)
+            .await
+            .map_err(map_webhook_store_error)?;
+
+        Ok(())
+    }
+    .await;
+
+    audit(&auditor, &admin, record, &result).await;
+    result?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+// Newest first, optionally only those of one webhook or with one status
+#[tracing::instrument(name = "Listing webhook deliveries", skip_all)]
+pub async fn list_webhook_deliveries(
+    State(state): State<AppState>,
+    _admin: AdminCaller,
+    CurrentTenant(tenant): CurrentTenant,
+    Query(request): Query<ListWebhookDeliveriesRequest>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let page = request.page.unwrap_or(1).max(1);
+    let per_page = request
+        .per_page
+        .unwrap_or(DEFAULT_PER_PAGE)
+        .clamp(1, MAX_PER_PAGE);
+
+    let query = WebhookDeliveryQuery {
+        webhook: request
+            .webhook_id
+            .map(|id| WebhookId::parse(&id))
+            .transpose()
+            .map_err(|_| AuthAPIError::InvalidCredentials)?,
+        status: request
+            .status
+            .map(|status| WebhookDeliveryStatus::parse(&status))
+            .transpose()
+            .map_err(|_| AuthAPIError::InvalidCredentials)?,
+        offset: (page - 1).saturating_mul(per_page),
+        limit: per_page,
+    };
+
+    let deliveries = state
+        .webhook_store
+        .read()
+        .await
+        .list_deliveries(&tenant.id, &query)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+
+    Ok((
+        StatusCode::OK,
+        Json(ListWebhookDeliveriesResponse {
+            deliveries: deliveries.iter().map(Into::into).collect(),
+            page,
+            per_page,
+        }),
+    ))
+}
+
+// Queues the delivery to be sent again, whatever became of it, with a fresh set of
+// attempts. This is how dead-lettered deliveries are retried once the receiver is fixed.
+#[tracing::instrument(name = "Replaying webhook delivery", skip_all)]
+pub async fn replay_webhook_delivery(
+    State(state): State<AppState>,
+    admin: AdminCaller,
+    auditor: Auditor,
+    CurrentTenant(tenant): CurrentTenant,
+    Path(id): Path<String>,
+) -> Result<impl IntoResponse, AuthAPIError> {
+    let record = AuditRecord::new("webhook_delivery_replayed").target(&id);
+    let result = async {
+        let id =
+            WebhookDeliveryId::parse(&id).map_err(|_| AuthAPIError::WebhookDeliveryNotFound)?;
+
+        let mut webhook_store = state.webhook_store.write().await;
+        let mut delivery = webhook_store
+            .get_delivery(&tenant.id, &id)
+            .await
+            .map_err(map_webhook_store_error)?;
+        delivery.replay();
+        webhook_store
+            .update_delivery(&delivery)
+            .await
+            .map_err(map_webhook_store_error)?;
+        drop(webhook_store);
+
+        Ok(delivery)
+    }
+    .await;
+
+    audit(&auditor, &admin, record, &result).await;
+    let delivery = result?;
+
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(WebhookDeliveryResponse::from(&delivery)),
+    ))
+}
+
+fn map_webhook_store_error(e: WebhookStoreError) -> AuthAPIError {
+    match e {
+        WebhookStoreError::WebhookNotFound => AuthAPIError::WebhookNotFound,
+        WebhookStoreError::DeliveryNotFound => AuthAPIError::WebhookDeliveryNotFound,
+        e => AuthAPIError::UnexpectedError(e.into()),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    pub events: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateWebhookResponse {
+    pub webhook: WebhookResponse,
+    // Shown only once, to verify the signatures of what the webhook receives
+    pub secret: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookResponse {
+    pub id: String,
+    pub url: String,
+    pub events: Vec<WebhookEventType>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+}
+
+impl From<&Webhook> for WebhookResponse {
+    fn from(webhook: &Webhook) -> Self {
+        Self {
+            id: webhook.id.to_string(),
+            url: webhook.url.clone(),
+            events: webhook.events.clone(),
+            created_at: webhook.created_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct ListWebhookDeliveriesRequest {
+    #[serde(rename = "webhookId")]
+    pub webhook_id: Option<String>,
+    pub status: Option<String>,
+    pub page: Option<u64>,
+    #[serde(rename = "perPage")]
+    pub per_page: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ListWebhookDeliveriesResponse {
+    pub deliveries: Vec<WebhookDeliveryResponse>,
+    pub page: u64,
+    #[serde(rename = "perPage")]
+    pub per_page: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct WebhookDeliveryResponse {
+    pub id: String,
+    #[serde(rename = "webhookId")]
+    pub webhook_id: String,
+    #[serde(rename = "eventType")]
+    pub event_type: WebhookEventType,
+    pub status: WebhookDeliveryStatus,
+    pub attempts: u32,
+    #[serde(rename = "nextAttemptAt")]
+    pub next_attempt_at: Option<String>,
+    #[serde(rename = "lastResponseStatus")]
+    pub last_response_status: Option<u16>,
+    #[serde(rename = "lastError")]
+    pub last_error: Option<String>,
+    #[serde(rename = "createdAt")]
+    pub created_at: String,
+    #[serde(rename = "deliveredAt")]
+    pub delivered_at: Option<String>,
+    // The body that was or will be sent
+    pub payload: serde_json::Value,
+}
+
+impl From<&WebhookDelivery> for WebhookDeliveryResponse {
+    fn from(delivery: &WebhookDelivery) -> Self {
+        Self {
+            id: delivery.id.to_string(),
+            webhook_id: delivery.webhook_id.to_string(),
+            event_type: delivery.event_type,
+            status: delivery.status,
+            attempts: delivery.attempts,
+            // Only pending deliveries have another attempt coming
+            next_attempt_at: (delivery.status == WebhookDeliveryStatus::Pending)
+                .then(|| delivery.next_attempt_at.to_rfc3339()),
+            last_response_status: delivery.last_response_status,
+            last_error: delivery.last_error.clone(),
+            created_at: delivery.created_at.to_rfc3339(),
+            delivered_at: delivery
+                .delivered_at
+                .map(|delivered_at| delivered_at.to_rfc3339()),
+            payload: serde_json::from_str(&delivery.payload).unwrap_or(serde_json::Value::Null),
+        }
+    }
+}
@@ -0,0 +1,316 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, MutexGuard},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    app_state::UserStoreType,
+    domain::{
+        data_stores::{UserStore, UserStoreError},
+        AccountStatus, Email, Password, PhoneNumber, SuspendedBy, TenantId, TwoFAChannel, User,
+        UserPage, UserQuery,
+    },
+};
+
+use super::ldap_user_store::LdapUserStore;
+
+// How long the directory's word on whether someone is one of its users is trusted.
+// Every token check looks the user up, and asking the directory each time would cost a
+// connection, a bind and a search per request. Passwords are always checked live.
+const DIRECTORY_ANSWER_TTL: Duration = Duration::from_secs(60);
+// Past this many remembered answers the expired ones are dropped
+const MAX_DIRECTORY_ANSWERS: usize = 10_000;
+
+// What happens when someone the directory doesn't know signs up in its tenant
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SignupPolicy {
+    // They become a local user
+    #[default]
+    Delegate,
+    // Only people in the directory can have accounts
+    Reject,
+}
+
+// Users from an LDAP directory alongside local ones. The directory is asked first and
+// decides who its users are and checks their passwords; everyone else is a local user.
+// Directory users get a local row the first time they're seen, which holds what the
+// directory doesn't, like their status, 2FA settings and roles.
+pub struct CompositeUserStore {
+    directory: LdapUserStore,
+    local: UserStoreType,
+    signups: SignupPolicy,
+    // Whether the directory knew a user, and when it was asked
+    directory_answers: Mutex<HashMap<(TenantId, Email), (bool, Instant)>>,
+}
+
+impl CompositeUserStore {
+    pub fn new(directory: LdapUserStore, local: UserStoreType) -> Self {
+        Self {
+            directory,
+            local,
+            signups: SignupPolicy::default(),
+            directory_answers: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn with_signup_policy(mut self, signups: SignupPolicy) -> Self {
+        self.signups = signups;
+        self
+    }
+
+    // Asks the directory whether it knows the user, unless it was asked recently.
+    // Failures to reach it aren't remembered.
+    async fn knows_user(&self, tenant: &TenantId, email: &Email) -> Result<bool, UserStoreError> {
+        let key = (tenant.clone(), email.clone());
+        if let Some((known, asked_at)) = self.answers().get(&key) {
+            if asked_at.elapsed() < DIRECTORY_ANSWER_TTL {
+                return Ok(*known);
+            }
+        }
+
+        let known = match self.directory.get_user(tenant, email).await {
+            Ok(_) => true,
+            Err(UserStoreError::UserNotFound) => false,
+            Err(e) => return Err(e),
+        };
+        self.remember_answer(tenant, email, known);
+        Ok(known)
+    }
+
+    fn remember_answer(&self, tenant: &TenantId, email: &Email, known: bool) {
+        let mut answers = self.answers();
+        if answers.len() >= MAX_DIRECTORY_ANSWERS {
+            answers.retain(|_, (_, asked_at)| asked_at.elapsed() < DIRECTORY_ANSWER_TTL);
+        }
+        answers.insert((tenant.clone(), email.clone()), (known, Instant::now()));
+    }
+
+    fn answers(&self) -> MutexGuard<'_, HashMap<(TenantId, Email), (bool, Instant)>> {
+        // The map is only ever left consistent, so a panic elsewhere doesn't spoil it
+        self.directory_answers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // The local row of a directory user, added the first time they're seen
+    async fn directory_user(
+        &self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<User, UserStoreError> {
+        let mut local = self.local.write().await;
+
+        match local.get_user(tenant, email).await {
+            Err(UserStoreError::UserNotFound) => {
+                local
+                    .add_user(tenant, User::from_directory(email.clone()))
+                    .await?;
+                local.get_user(tenant, email).await
+            }
+            result => result,
+        }
+    }
+
+    // A local user. Rows of directory users the directory didn't vouch for, because
+    // they were removed from it or it can't be reached, are hidden behind `error`.
+    async fn local_user(
+        &self,
+        tenant: &TenantId,
+        email: &Email,
+        error: UserStoreError,
+    ) -> Result<User, UserStoreError> {
+        let user = self.local.read().await.get_user(tenant, email).await?;
+
+        if user.managed_by_directory {
+            Err(error)
+        } else {
+            Ok(user)
+        }
+    }
+
+    // Local users don't depend on the directory, so they can still sign in while it's
+    // down. Returns the error to fall back with, or the one to give up with.
+    fn fall_back_on(e: UserStoreError) -> Result<UserStoreError, UserStoreError> {
+        match e {
+            UserStoreError::UserNotFound => Ok(e),
+            UserStoreError::UnexpectedError(ref report) => {
+                tracing::error!("Failed to reach the directory: {:?}", report);
+                Ok(e)
+            }
+            e => Err(e),
+        }
+    }
+
+    // Passwords of directory users can only be changed in the directory, and the users
+    // only removed there
+    async fn ensure_local_user(
+        &self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<(), UserStoreError> {
+        let user = self.local.read().await.get_user(tenant, email).await?;
+
+        if user.managed_by_directory {
+            Err(UserStoreError::ReadOnly)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl UserStore for CompositeUserStore {
+    async fn add_user(&mut self, tenant: &TenantId, user: User) -> Result<(), UserStoreError> {
+        if tenant == self.directory.tenant() {
+            match self.directory.get_user(tenant, &user.email).await {
+                Ok(_) => return Err(UserStoreError::UserAlreadyExists),
+                Err(UserStoreError::UserNotFound) if self.signups == SignupPolicy::Reject => {
+                    return Err(UserStoreError::ReadOnly)
+                }
+                Err(UserStoreError::UserNotFound) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        self.local.write().await.add_user(tenant, user).await
+    }
+
+    async fn get_user(&self, tenant: &TenantId, email: &Email) -> Result<User, UserStoreError> {
+        if tenant != self.directory.tenant() {
+            return self
+                .local_user(tenant, email, UserStoreError::UserNotFound)
+                .await;
+        }
+
+        let error = match self.knows_user(tenant, email).await {
+            Ok(true) => return self.directory_user(tenant, email).await,
+            Ok(false) => UserStoreError::UserNotFound,
+            Err(e) => Self::fall_back_on(e)?,
+        };
+
+        self.local_user(tenant, email, error).await
+    }
+
+    async fn validate_user(
+        &self,
+        tenant: &TenantId,
+        email: &Email,
+        password: &Password,
+    ) -> Result<(), UserStoreError> {
+        // A live answer also refreshes what `get_user` goes by, so someone removed from
+        // the directory is locked out everywhere as soon as they fail to log in
+        let error = match self.directory.validate_user(tenant, email, password).await {
+            Ok(()) => {
+                self.remember_answer(tenant, email, true);
+                return Ok(());
+            }
+            Err(UserStoreError::UserNotFound) => {
+                self.remember_answer(tenant, email, false);
+                UserStoreError::UserNotFound
+            }
+            Err(e) => Self::fall_back_on(e)?,
+        };
+
+        self.local_user(tenant, email, error).await?;
+        self.local
+            .read()
+            .await
+            .validate_user(tenant, email, password)
+            .await
+    }
+
+    async fn set_phone_number(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        phone_number: PhoneNumber,
+    ) -> Result<(), UserStoreError> {
+        self.local
+            .write()
+            .await
+            .set_phone_number(tenant, email, phone_number)
+            .await
+    }
+
+    async fn set_two_fa_channel(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        channel: TwoFAChannel,
+    ) -> Result<(), UserStoreError> {
+        self.local
+            .write()
+            .await
+            .set_two_fa_channel(tenant, email, channel)
+            .await
+    }
+
+    // Directory users are only listed once they've been seen
+    async fn list_users(
+        &self,
+        tenant: &TenantId,
+        query: &UserQuery,
+    ) -> Result<UserPage, UserStoreError> {
+        self.local.read().await.list_users(tenant, query).await
+    }
+
+    async fn set_status(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        status: AccountStatus,
+        reason: Option<String>,
+        suspended_by: Option<SuspendedBy>,
+    ) -> Result<(), UserStoreError> {
+        self.local
+            .write()
+            .await
+            .set_status(tenant, email, status, reason, suspended_by)
+            .await
+    }
+
+    async fn require_password_reset(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<(), UserStoreError> {
+        self.ensure_local_user(tenant, email).await?;
+        self.local
+            .write()
+            .await
+            .require_password_reset(tenant, email)
+            .await
+    }
+
+    async fn set_password(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        self.ensure_local_user(tenant, email).await?;
+        self.local
+            .write()
+            .await
+            .set_password(tenant, email, password)
+            .await
+    }
+
+    async fn reset_two_fa(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<(), UserStoreError> {
+        self.local.write().await.reset_two_fa(tenant, email).await
+    }
+
+    async fn delete_user(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<(), UserStoreError> {
+        self.ensure_local_user(tenant, email).await?;
+        self.local.write().await.delete_user(tenant, email).await
+    }
+}
@@ -0,0 +1,157 @@
+use std::collections::HashMap;
+
+use secrecy::{ExposeSecret, Secret};
+
+use crate::{
+    domain::{
+        data_stores::{ApiKeyStore, ApiKeyStoreError},
+        ApiKey, ApiKeyId, Email, TenantId,
+    },
+    utils::hashing::constant_time_eq,
+};
+
+// Keeps keys in the clear, which is only fine because nothing is persisted
+#[derive(Default)]
+pub struct HashmapApiKeyStore {
+    keys: HashMap<ApiKeyId, (ApiKey, Secret<String>)>,
+}
+
+#[async_trait::async_trait]
+impl ApiKeyStore for HashmapApiKeyStore {
+    async fn add_key(
+        &mut self,
+        api_key: ApiKey,
+        key: Secret<String>,
+    ) -> Result<(), ApiKeyStoreError> {
+        self.keys.insert(api_key.id, (api_key, key));
+        Ok(())
+    }
+
+    async fn validate_key(&self, key: &Secret<String>) -> Result<ApiKey, ApiKeyStoreError> {
+        let prefix =
+            ApiKey::prefix_of(key.expose_secret()).ok_or(ApiKeyStoreError::InvalidApiKey)?;
+
+        self.keys
+            .values()
+            .find(|(api_key, _)| api_key.prefix == prefix)
+            .filter(|(_, stored_key)| {
+                constant_time_eq(
+                    stored_key.expose_secret().as_bytes(),
+                    key.expose_secret().as_bytes(),
+                )
+            })
+            .map(|(api_key, _)| api_key.clone())
+            .ok_or(ApiKeyStoreError::InvalidApiKey)
+    }
+
+    async fn list_keys(
+        &self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<Vec<ApiKey>, ApiKeyStoreError> {
+        let mut keys: Vec<ApiKey> = self
+            .keys
+            .values()
+            .map(|(api_key, _)| api_key)
+            .filter(|api_key| api_key.belongs_to(tenant, email) && !api_key.is_expired())
+            .cloned()
+            .collect();
+        keys.sort_by_key(|api_key| api_key.created_at);
+        Ok(keys)
+    }
+
+    async fn revoke_key(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        id: &ApiKeyId,
+    ) -> Result<(), ApiKeyStoreError> {
+        match self.keys.get(id) {
+            Some((api_key, _)) if api_key.belongs_to(tenant, email) => {
+                self.keys.remove(id);
+                Ok(())
+            }
+            _ => Err(ApiKeyStoreError::ApiKeyNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn new_key(email: &str, ttl: chrono::Duration) -> (ApiKey, Secret<String>) {
+        let email = Email::parse(Secret::new(email.to_owned())).unwrap();
+        ApiKey::generate(TenantId::default(), email, "ci".to_owned(), vec![], ttl)
+    }
+
+    #[tokio::test]
+    async fn test_add_and_validate_key() {
+        let mut store = HashmapApiKeyStore::default();
+        let (api_key, key) = new_key("keys@example.com", chrono::Duration::days(1));
+
+        store.add_key(api_key.clone(), key.clone()).await.unwrap();
+
+        assert_eq!(store.validate_key(&key).await, Ok(api_key.clone()));
+        assert_eq!(
+            store.list_keys(&api_key.tenant, &api_key.email).await,
+            Ok(vec![api_key.clone()])
+        );
+
+        // Right prefix, wrong secret
+        let forged = Secret::new(format!("{}_{}", api_key.prefix, "x".repeat(32)));
+        assert_eq!(
+            store.validate_key(&forged).await,
+            Err(ApiKeyStoreError::InvalidApiKey)
+        );
+        assert_eq!(
+            store
+                .validate_key(&Secret::new("not-a-key".to_owned()))
+                .await,
+            Err(ApiKeyStoreError::InvalidApiKey)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_key_is_not_listed() {
+        let mut store = HashmapApiKeyStore::default();
+        let (api_key, key) = new_key("expired@example.com", chrono::Duration::zero());
+
+        store.add_key(api_key.clone(), key.clone()).await.unwrap();
+
+        assert_eq!(
+            store.list_keys(&api_key.tenant, &api_key.email).await,
+            Ok(vec![])
+        );
+        assert!(store.validate_key(&key).await.unwrap().is_expired());
+    }
+
+    #[tokio::test]
+    async fn test_revoke_key() {
+        let mut store = HashmapApiKeyStore::default();
+        let (api_key, key) = new_key("revoke@example.com", chrono::Duration::days(1));
+        let other = Email::parse(Secret::new("other@example.com".to_owned())).unwrap();
+
+        store.add_key(api_key.clone(), key.clone()).await.unwrap();
+
+        assert_eq!(
+            store.revoke_key(&api_key.tenant, &other, &api_key.id).await,
+            Err(ApiKeyStoreError::ApiKeyNotFound)
+        );
+        store
+            .revoke_key(&api_key.tenant, &api_key.email, &api_key.id)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.validate_key(&key).await,
+            Err(ApiKeyStoreError::InvalidApiKey)
+        );
+        assert_eq!(
+            store
+                .revoke_key(&api_key.tenant, &api_key.email, &api_key.id)
+                .await,
+            Err(ApiKeyStoreError::ApiKeyNotFound)
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use secrecy::ExposeSecret;
+
+use crate::domain::{
+    data_stores::{DeviceAuthorizationStore, DeviceAuthorizationStoreError},
+    DeviceAuthorization, DeviceCode, UserCode,
+};
+
+// Keyed by device code. Expired authorizations are left in place until the next one
+// is added, but are never returned.
+#[derive(Default)]
+pub struct HashmapDeviceAuthorizationStore {
+    authorizations: HashMap<String, DeviceAuthorization>,
+}
+
+impl HashmapDeviceAuthorizationStore {
+    fn find(
+        &self,
+        predicate: impl Fn(&DeviceAuthorization) -> bool,
+    ) -> Result<DeviceAuthorization, DeviceAuthorizationStoreError> {
+        self.authorizations
+            .values()
+            .find(|authorization| !authorization.is_expired() && predicate(authorization))
+            .cloned()
+            .ok_or(DeviceAuthorizationStoreError::AuthorizationNotFound)
+    }
+}
+
+#[async_trait::async_trait]
+impl DeviceAuthorizationStore for HashmapDeviceAuthorizationStore {
+    async fn add_authorization(
+        &mut self,
+        authorization: DeviceAuthorization,
+    ) -> Result<(), DeviceAuthorizationStoreError> {
+        self.authorizations
+            .retain(|_, authorization| !authorization.is_expired());
+        self.authorizations.insert(
+            authorization
+                .device_code
+                .as_ref()
+                .expose_secret()
+                .to_owned(),
+            authorization,
+        );
+        Ok(())
+    }
+
+    async fn get_by_device_code(
+        &self,
+        device_code: &DeviceCode,
+    ) -> Result<DeviceAuthorization, DeviceAuthorizationStoreError> {
+        self.find(|authorization| authorization.device_code == *device_code)
+    }
+
+    async fn get_by_user_code(
+        &self,
+        user_code: &UserCode,
+    ) -> Result<DeviceAuthorization, DeviceAuthorizationStoreError> {
+        self.find(|authorization| authorization.user_code == *user_code)
+    }
+
+    async fn update_authorization(
+        &mut self,
+        authorization: DeviceAuthorization,
+    ) -> Result<(), DeviceAuthorizationStoreError> {
+        match self
+            .authorizations
+            .get_mut(authorization.device_code.as_ref().expose_secret())
+        {
+            Some(stored) if !stored.is_expired() => {
+                *stored = authorization;
+                Ok(())
+            }
+            _ => Err(DeviceAuthorizationStoreError::AuthorizationNotFound),
+        }
+    }
+
+    async fn remove_authorization(
+        &mut self,
+        authorization: &DeviceAuthorization,
+    ) -> Result<(), DeviceAuthorizationStoreError> {
+        self.authorizations
+            .remove(authorization.device_code.as_ref().expose_secret());
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+    use crate::domain::{ClientId, DeviceAuthorizationStatus, Email, TenantId};
+
+    fn new_authorization(ttl: chrono::Duration) -> DeviceAuthorization {
+        let client_id = ClientId::parse("cli".to_owned()).unwrap();
+        DeviceAuthorization::new(client_id, TenantId::default(), 5, ttl)
+    }
+
+    #[tokio::test]
+    async fn test_add_and_update_authorization() {
+        let mut store = HashmapDeviceAuthorizationStore::default();
+        let mut authorization = new_authorization(chrono::Duration::minutes(15));
+
+        store
+            .add_authorization(authorization.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            store.get_by_user_code(&authorization.user_code).await,
+            Ok(authorization.clone())
+        );
+
+        let email = Email::parse(Secret::new("device@example.com".to_owned())).unwrap();
+        authorization.decide(email, true);
+        store
+            .update_authorization(authorization.clone())
+            .await
+            .unwrap();
+
+        let stored = store
+            .get_by_device_code(&authorization.device_code)
+            .await
+            .unwrap();
+        assert_eq!(stored.status, DeviceAuthorizationStatus::Approved);
+
+        store.remove_authorization(&authorization).await.unwrap();
+        assert_eq!(
+            store.get_by_device_code(&authorization.device_code).await,
+            Err(DeviceAuthorizationStoreError::AuthorizationNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_authorization_is_not_found() {
+        let mut store = HashmapDeviceAuthorizationStore::default();
+        let authorization = new_authorization(chrono::Duration::zero());
+
+        store
+            .add_authorization(authorization.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.get_by_device_code(&authorization.device_code).await,
+            Err(DeviceAuthorizationStoreError::AuthorizationNotFound)
+        );
+        assert_eq!(
+            store.get_by_user_code(&authorization.user_code).await,
+            Err(DeviceAuthorizationStoreError::AuthorizationNotFound)
+        );
+        assert_eq!(
+            store.update_authorization(authorization).await,
+            Err(DeviceAuthorizationStoreError::AuthorizationNotFound)
+        );
+    }
+}
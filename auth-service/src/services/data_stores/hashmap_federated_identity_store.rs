@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{FederatedIdentityStore, FederatedIdentityStoreError},
+    FederatedIdentity, ProviderId, TenantId,
+};
+
+#[derive(Default)]
+pub struct HashmapFederatedIdentityStore {
+    identities: HashMap<(TenantId, ProviderId, String), FederatedIdentity>,
+}
+
+#[async_trait::async_trait]
+impl FederatedIdentityStore for HashmapFederatedIdentityStore {
+    async fn add_identity(
+        &mut self,
+        identity: FederatedIdentity,
+    ) -> Result<(), FederatedIdentityStoreError> {
+        let key = (
+            identity.tenant.clone(),
+            identity.provider.clone(),
+            identity.subject.clone(),
+        );
+        self.identities.insert(key, identity);
+        Ok(())
+    }
+
+    async fn get_identity(
+        &self,
+        tenant: &TenantId,
+        provider: &ProviderId,
+        subject: &str,
+    ) -> Result<FederatedIdentity, FederatedIdentityStoreError> {
+        self.identities
+            .get(&(tenant.clone(), provider.clone(), subject.to_owned()))
+            .cloned()
+            .ok_or(FederatedIdentityStoreError::IdentityNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+    use crate::domain::Email;
+
+    #[tokio::test]
+    async fn test_add_and_get_identity() {
+        let mut store = HashmapFederatedIdentityStore::default();
+        let provider = ProviderId::parse("corp".to_owned()).unwrap();
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let identity = FederatedIdentity::new(
+            TenantId::default(),
+            provider.clone(),
+            "248289761001".to_owned(),
+            email,
+        );
+
+        store.add_identity(identity.clone()).await.unwrap();
+
+        assert_eq!(
+            store
+                .get_identity(&identity.tenant, &provider, "248289761001")
+                .await,
+            Ok(identity.clone())
+        );
+        assert_eq!(
+            store
+                .get_identity(&identity.tenant, &provider, "other")
+                .await,
+            Err(FederatedIdentityStoreError::IdentityNotFound)
+        );
+
+        let other_provider = ProviderId::parse("other".to_owned()).unwrap();
+        assert_eq!(
+            store
+                .get_identity(&identity.tenant, &other_provider, "248289761001")
+                .await,
+            Err(FederatedIdentityStoreError::IdentityNotFound)
+        );
+    }
+}
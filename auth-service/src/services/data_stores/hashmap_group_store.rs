@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{GroupStore, GroupStoreError},
+    Group, GroupId, TenantId,
+};
+
+#[derive(Default)]
+pub struct HashmapGroupStore {
+    groups: HashMap<(TenantId, GroupId), Group>,
+}
+
+impl HashmapGroupStore {
+    fn name_taken(&self, group: &Group) -> bool {
+        self.groups.values().any(|other| {
+            other.tenant == group.tenant
+                && other.id != group.id
+                && other.display_name == group.display_name
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl GroupStore for HashmapGroupStore {
+    async fn add_group(&mut self, group: Group) -> Result<(), GroupStoreError> {
+        let key = (group.tenant.clone(), group.id);
+        if self.groups.contains_key(&key) || self.name_taken(&group) {
+            return Err(GroupStoreError::GroupAlreadyExists);
+        }
+
+        self.groups.insert(key, group);
+        Ok(())
+    }
+
+    async fn get_group(&self, tenant: &TenantId, id: &GroupId) -> Result<Group, GroupStoreError> {
+        self.groups
+            .get(&(tenant.clone(), *id))
+            .cloned()
+            .ok_or(GroupStoreError::GroupNotFound)
+    }
+
+    async fn list_groups(&self, tenant: &TenantId) -> Result<Vec<Group>, GroupStoreError> {
+        let mut groups: Vec<Group> = self
+            .groups
+            .values()
+            .filter(|group| group.tenant == *tenant)
+            .cloned()
+            .collect();
+        groups.sort_by(|a, b| a.display_name.cmp(&b.display_name));
+        Ok(groups)
+    }
+
+    async fn update_group(&mut self, group: Group) -> Result<(), GroupStoreError> {
+        let name_taken = self.name_taken(&group);
+        let existing = self
+            .groups
+            .get_mut(&(group.tenant.clone(), group.id))
+            .ok_or(GroupStoreError::GroupNotFound)?;
+        if name_taken {
+            return Err(GroupStoreError::GroupAlreadyExists);
+        }
+
+        existing.display_name = group.display_name;
+        existing.members = group.members;
+        Ok(())
+    }
+
+    async fn delete_group(
+        &mut self,
+        tenant: &TenantId,
+        id: &GroupId,
+    ) -> Result<(), GroupStoreError> {
+        self.groups
+            .remove(&(tenant.clone(), *id))
+            .map(|_| ())
+            .ok_or(GroupStoreError::GroupNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+    use crate::domain::Email;
+
+    fn group(tenant: &TenantId, display_name: &str) -> Group {
+        Group::new(tenant.clone(), display_name.to_owned(), vec![]).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_group() {
+        let mut store = HashmapGroupStore::default();
+        let tenant = TenantId::default();
+        let other_tenant = TenantId::parse("other".to_owned()).unwrap();
+        let engineering = group(&tenant, "Engineering");
+
+        store.add_group(engineering.clone()).await.unwrap();
+        assert_eq!(
+            store.get_group(&tenant, &engineering.id).await,
+            Ok(engineering.clone())
+        );
+        assert_eq!(
+            store.get_group(&other_tenant, &engineering.id).await,
+            Err(GroupStoreError::GroupNotFound)
+        );
+
+        // Display names are only unique within a tenant
+        assert_eq!(
+            store.add_group(group(&tenant, "Engineering")).await,
+            Err(GroupStoreError::GroupAlreadyExists)
+        );
+        store
+            .add_group(group(&other_tenant, "Engineering"))
+            .await
+            .unwrap();
+
+        store.add_group(group(&tenant, "Design")).await.unwrap();
+        let names: Vec<String> = store
+            .list_groups(&tenant)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|group| group.display_name)
+            .collect();
+        assert_eq!(names, vec!["Design", "Engineering"]);
+    }
+
+    #[tokio::test]
+    async fn test_update_and_delete_group() {
+        let mut store = HashmapGroupStore::default();
+        let tenant = TenantId::default();
+        let mut engineering = group(&tenant, "Engineering");
+        store.add_group(engineering.clone()).await.unwrap();
+        store.add_group(group(&tenant, "Design")).await.unwrap();
+
+        let member = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        engineering.set_display_name("Platform".to_owned()).unwrap();
+        engineering.set_members(vec![member.clone()]);
+        store.update_group(engineering.clone()).await.unwrap();
+        let stored = store.get_group(&tenant, &engineering.id).await.unwrap();
+        assert_eq!(stored.display_name, "Platform");
+        assert_eq!(stored.members, vec![member]);
+
+        engineering.set_display_name("Design".to_owned()).unwrap();
+        assert_eq!(
+            store.update_group(engineering.clone()).await,
+            Err(GroupStoreError::GroupAlreadyExists)
+        );
+
+        assert_eq!(store.delete_group(&tenant, &engineering.id).await, Ok(()));
+        assert_eq!(
+            store.delete_group(&tenant, &engineering.id).await,
+            Err(GroupStoreError::GroupNotFound)
+        );
+        assert_eq!(
+            store.update_group(engineering).await,
+            Err(GroupStoreError::GroupNotFound)
+        );
+    }
+}
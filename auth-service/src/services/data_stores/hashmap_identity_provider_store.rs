@@ -0,0 +1,137 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{IdentityProviderStore, IdentityProviderStoreError},
+    IdentityProvider, ProviderId, TenantId,
+};
+
+#[derive(Default)]
+pub struct HashmapIdentityProviderStore {
+    providers: HashMap<(TenantId, ProviderId), IdentityProvider>,
+}
+
+#[async_trait::async_trait]
+impl IdentityProviderStore for HashmapIdentityProviderStore {
+    async fn add_provider(
+        &mut self,
+        provider: IdentityProvider,
+    ) -> Result<(), IdentityProviderStoreError> {
+        let key = (provider.tenant.clone(), provider.id.clone());
+        if self.providers.contains_key(&key) {
+            return Err(IdentityProviderStoreError::ProviderAlreadyExists);
+        }
+
+        self.providers.insert(key, provider);
+        Ok(())
+    }
+
+    async fn get_provider(
+        &self,
+        tenant: &TenantId,
+        id: &ProviderId,
+    ) -> Result<IdentityProvider, IdentityProviderStoreError> {
+        self.providers
+            .get(&(tenant.clone(), id.clone()))
+            .cloned()
+            .ok_or(IdentityProviderStoreError::ProviderNotFound)
+    }
+
+    async fn list_providers(
+        &self,
+        tenant: &TenantId,
+    ) -> Result<Vec<IdentityProvider>, IdentityProviderStoreError> {
+        let mut providers: Vec<IdentityProvider> = self
+            .providers
+            .values()
+            .filter(|provider| provider.tenant == *tenant)
+            .cloned()
+            .collect();
+        providers.sort_by_key(|provider| provider.created_at);
+        Ok(providers)
+    }
+
+    async fn delete_provider(
+        &mut self,
+        tenant: &TenantId,
+        id: &ProviderId,
+    ) -> Result<(), IdentityProviderStoreError> {
+        self.providers
+            .remove(&(tenant.clone(), id.clone()))
+            .map(|_| ())
+            .ok_or(IdentityProviderStoreError::ProviderNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn provider(tenant: TenantId) -> IdentityProvider {
+        IdentityProvider::new(
+            ProviderId::parse("corp".to_owned()).unwrap(),
+            tenant,
+            "Corp SSO".to_owned(),
+            "https://idp.example.com".to_owned(),
+            "auth-service".to_owned(),
+            Secret::new("secret".to_owned()),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_provider() {
+        let mut store = HashmapIdentityProviderStore::default();
+        let provider = provider(TenantId::default());
+        let other_tenant = TenantId::parse("other".to_owned()).unwrap();
+
+        store.add_provider(provider.clone()).await.unwrap();
+        assert_eq!(
+            store.add_provider(provider.clone()).await,
+            Err(IdentityProviderStoreError::ProviderAlreadyExists)
+        );
+
+        let stored = store
+            .get_provider(&provider.tenant, &provider.id)
+            .await
+            .unwrap();
+        assert_eq!(stored.issuer, provider.issuer);
+        assert_eq!(
+            store.list_providers(&provider.tenant).await.unwrap().len(),
+            1
+        );
+
+        // Providers with the same id may exist in other tenants
+        assert_eq!(
+            store.get_provider(&other_tenant, &provider.id).await.err(),
+            Some(IdentityProviderStoreError::ProviderNotFound)
+        );
+        store
+            .add_provider(self::provider(other_tenant))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_provider() {
+        let mut store = HashmapIdentityProviderStore::default();
+        let provider = provider(TenantId::default());
+
+        store.add_provider(provider.clone()).await.unwrap();
+        store
+            .delete_provider(&provider.tenant, &provider.id)
+            .await
+            .unwrap();
+
+        assert!(store
+            .list_providers(&provider.tenant)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            store.delete_provider(&provider.tenant, &provider.id).await,
+            Err(IdentityProviderStoreError::ProviderNotFound)
+        );
+    }
+}
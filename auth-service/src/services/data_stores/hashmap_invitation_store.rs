@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+use chrono::Utc;
+
+use crate::domain::{
+    data_stores::{InvitationStore, InvitationStoreError},
+    Invitation, InvitationId, InvitationStatus, TenantId,
+};
+
+#[derive(Default)]
+pub struct HashmapInvitationStore {
+    invitations: HashMap<InvitationId, Invitation>,
+}
+
+impl HashmapInvitationStore {
+    fn pending_mut(
+        &mut self,
+        tenant: &TenantId,
+        id: &InvitationId,
+    ) -> Result<&mut Invitation, InvitationStoreError> {
+        self.invitations
+            .get_mut(id)
+            .filter(|invitation| {
+                invitation.tenant == *tenant && invitation.status == InvitationStatus::Pending
+            })
+            .ok_or(InvitationStoreError::InvitationNotFound)
+    }
+}
+
+#[async_trait::async_trait]
+impl InvitationStore for HashmapInvitationStore {
+    async fn add_invitation(&mut self, invitation: Invitation) -> Result<(), InvitationStoreError> {
+        self.invitations.insert(invitation.id, invitation);
+        Ok(())
+    }
+
+    async fn get_invitation(
+        &self,
+        tenant: &TenantId,
+        id: &InvitationId,
+    ) -> Result<Invitation, InvitationStoreError> {
+        self.invitations
+            .get(id)
+            .filter(|invitation| invitation.tenant == *tenant)
+            .cloned()
+            .ok_or(InvitationStoreError::InvitationNotFound)
+    }
+
+    async fn list_invitations(
+        &self,
+        tenant: &TenantId,
+    ) -> Result<Vec<Invitation>, InvitationStoreError> {
+        let mut invitations: Vec<Invitation> = self
+            .invitations
+            .values()
+            .filter(|invitation| invitation.tenant == *tenant)
+            .cloned()
+            .collect();
+        invitations.sort_by_key(|invitation| std::cmp::Reverse(invitation.created_at));
+
+        Ok(invitations)
+    }
+
+    async fn accept_invitation(
+        &mut self,
+        tenant: &TenantId,
+        id: &InvitationId,
+    ) -> Result<(), InvitationStoreError> {
+        let invitation = self.pending_mut(tenant, id)?;
+        if invitation.expires_at <= Utc::now() {
+            return Err(InvitationStoreError::InvitationNotFound);
+        }
+
+        invitation.status = InvitationStatus::Accepted;
+        Ok(())
+    }
+
+    async fn revoke_invitation(
+        &mut self,
+        tenant: &TenantId,
+        id: &InvitationId,
+    ) -> Result<(), InvitationStoreError> {
+        self.pending_mut(tenant, id)?.status = InvitationStatus::Revoked;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+    use crate::domain::Email;
+
+    fn invitation(tenant: &TenantId, ttl: chrono::Duration) -> Invitation {
+        let email = Email::parse(Secret::new("invitee@example.com".to_owned())).unwrap();
+        Invitation::new(tenant.clone(), email, None, "admin-token".to_owned(), ttl)
+    }
+
+    #[tokio::test]
+    async fn test_invitations_are_accepted_once() {
+        let mut store = HashmapInvitationStore::default();
+        let tenant = TenantId::default();
+        let invitation = invitation(&tenant, chrono::Duration::days(7));
+
+        store.add_invitation(invitation.clone()).await.unwrap();
+
+        let other_tenant = TenantId::parse("acme".to_owned()).unwrap();
+        assert_eq!(
+            store.accept_invitation(&other_tenant, &invitation.id).await,
+            Err(InvitationStoreError::InvitationNotFound)
+        );
+
+        assert_eq!(
+            store.accept_invitation(&tenant, &invitation.id).await,
+            Ok(())
+        );
+        assert_eq!(
+            store.accept_invitation(&tenant, &invitation.id).await,
+            Err(InvitationStoreError::InvitationNotFound)
+        );
+
+        let stored = store.get_invitation(&tenant, &invitation.id).await.unwrap();
+        assert_eq!(stored.status, InvitationStatus::Accepted);
+    }
+
+    #[tokio::test]
+    async fn test_revoked_and_expired_invitations_cannot_be_accepted() {
+        let mut store = HashmapInvitationStore::default();
+        let tenant = TenantId::default();
+        let revoked = invitation(&tenant, chrono::Duration::days(7));
+        let expired = invitation(&tenant, chrono::Duration::seconds(-1));
+
+        store.add_invitation(revoked.clone()).await.unwrap();
+        store.add_invitation(expired.clone()).await.unwrap();
+
+        assert_eq!(store.revoke_invitation(&tenant, &revoked.id).await, Ok(()));
+        assert_eq!(
+            store.revoke_invitation(&tenant, &revoked.id).await,
+            Err(InvitationStoreError::InvitationNotFound)
+        );
+        assert_eq!(
+            store.accept_invitation(&tenant, &revoked.id).await,
+            Err(InvitationStoreError::InvitationNotFound)
+        );
+        assert_eq!(
+            store.accept_invitation(&tenant, &expired.id).await,
+            Err(InvitationStoreError::InvitationNotFound)
+        );
+
+        let invitations = store.list_invitations(&tenant).await.unwrap();
+        assert_eq!(invitations.len(), 2);
+    }
+}
@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+use secrecy::ExposeSecret;
+
+use crate::{
+    domain::{
+        data_stores::{MagicLinkId, MagicLinkStore, MagicLinkStoreError},
+        email::Email,
+        TenantId,
+    },
+    utils::auth::MAGIC_LINK_TTL_SECONDS,
+};
+
+#[derive(Default)]
+pub struct HashmapMagicLinkStore {
+    links: HashMap<String, (TenantId, Email, DateTime<Utc>)>,
+}
+
+#[async_trait::async_trait]
+impl MagicLinkStore for HashmapMagicLinkStore {
+    async fn add_link(
+        &mut self,
+        link_id: MagicLinkId,
+        tenant: TenantId,
+        email: Email,
+    ) -> Result<(), MagicLinkStoreError> {
+        // Expired links are forgotten as new ones come in, which keeps the map small
+        let now = Utc::now();
+        self.links.retain(|_, (_, _, expires_at)| *expires_at > now);
+
+        let expires_at = now + Duration::seconds(MAGIC_LINK_TTL_SECONDS);
+        self.links.insert(
+            link_id.as_ref().expose_secret().to_owned(),
+            (tenant, email, expires_at),
+        );
+        Ok(())
+    }
+
+    async fn consume_link(
+        &mut self,
+        link_id: &MagicLinkId,
+    ) -> Result<(TenantId, Email), MagicLinkStoreError> {
+        match self.links.remove(link_id.as_ref().expose_secret()) {
+            Some((tenant, email, expires_at)) if expires_at > Utc::now() => Ok((tenant, email)),
+            _ => Err(MagicLinkStoreError::LinkNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_link() {
+        let mut store = HashmapMagicLinkStore::default();
+        let email = Email::parse(Secret::new("magic@example.com".to_owned())).unwrap();
+        let link_id = MagicLinkId::default();
+
+        let result = store
+            .add_link(link_id.clone(), TenantId::default(), email)
+            .await;
+        assert!(result.is_ok());
+        assert!(store.links.contains_key(link_id.as_ref().expose_secret()));
+    }
+
+    #[tokio::test]
+    async fn test_consume_link() {
+        let mut store = HashmapMagicLinkStore::default();
+        let email = Email::parse(Secret::new("magic@example.com".to_owned())).unwrap();
+        let link_id = MagicLinkId::default();
+
+        store
+            .add_link(link_id.clone(), TenantId::default(), email.clone())
+            .await
+            .unwrap();
+
+        let result = store.consume_link(&link_id).await;
+        assert_eq!(result.unwrap(), (TenantId::default(), email));
+
+        // A link can only be used once
+        let result = store.consume_link(&link_id).await;
+        assert_eq!(result, Err(MagicLinkStoreError::LinkNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_consume_expired_link() {
+        let mut store = HashmapMagicLinkStore::default();
+        let email = Email::parse(Secret::new("magic@example.com".to_owned())).unwrap();
+        let link_id = MagicLinkId::default();
+        let expires_at = Utc::now() - Duration::seconds(1);
+        store.links.insert(
+            link_id.as_ref().expose_secret().to_owned(),
+            (TenantId::default(), email, expires_at),
+        );
+
+        let result = store.consume_link(&link_id).await;
+        assert_eq!(result, Err(MagicLinkStoreError::LinkNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_consume_unknown_link() {
+        let mut store = HashmapMagicLinkStore::default();
+
+        let result = store.consume_link(&MagicLinkId::default()).await;
+        assert_eq!(result, Err(MagicLinkStoreError::LinkNotFound));
+    }
+}
@@ -0,0 +1,136 @@
+use std::collections::HashMap;
+
+use secrecy::{ExposeSecret, Secret};
+
+use crate::domain::{
+    data_stores::{OAuthClientStore, OAuthClientStoreError},
+    ClientId, OAuthClient, TenantId,
+};
+
+// Keeps secrets in the clear, which is only fine because nothing is persisted
+#[derive(Default)]
+pub struct HashmapOAuthClientStore {
+    clients: HashMap<ClientId, (OAuthClient, Secret<String>)>,
+}
+
+#[async_trait::async_trait]
+impl OAuthClientStore for HashmapOAuthClientStore {
+    async fn add_client(
+        &mut self,
+        client: OAuthClient,
+        secret: Secret<String>,
+    ) -> Result<(), OAuthClientStoreError> {
+        self.clients.insert(client.id.clone(), (client, secret));
+        Ok(())
+    }
+
+    async fn validate_client(
+        &self,
+        id: &ClientId,
+        secret: &Secret<String>,
+    ) -> Result<OAuthClient, OAuthClientStoreError> {
+        self.clients
+            .get(id)
+            .filter(|(_, stored_secret)| stored_secret.expose_secret() == secret.expose_secret())
+            .map(|(client, _)| client.clone())
+            .ok_or(OAuthClientStoreError::InvalidClient)
+    }
+
+    async fn list_clients(
+        &self,
+        tenant: &TenantId,
+    ) -> Result<Vec<OAuthClient>, OAuthClientStoreError> {
+        let mut clients: Vec<OAuthClient> = self
+            .clients
+            .values()
+            .map(|(client, _)| client)
+            .filter(|client| client.tenant == *tenant)
+            .cloned()
+            .collect();
+        clients.sort_by_key(|client| client.created_at);
+        Ok(clients)
+    }
+
+    async fn delete_client(
+        &mut self,
+        tenant: &TenantId,
+        id: &ClientId,
+    ) -> Result<(), OAuthClientStoreError> {
+        match self.clients.get(id) {
+            Some((client, _)) if client.tenant == *tenant => {
+                self.clients.remove(id);
+                Ok(())
+            }
+            _ => Err(OAuthClientStoreError::ClientNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_add_and_validate_client() {
+        let mut store = HashmapOAuthClientStore::default();
+        let (client, secret) =
+            OAuthClient::register(TenantId::default(), "billing".to_owned(), vec![]);
+
+        store
+            .add_client(client.clone(), secret.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.validate_client(&client.id, &secret).await,
+            Ok(client.clone())
+        );
+        assert_eq!(
+            store.list_clients(&client.tenant).await,
+            Ok(vec![client.clone()])
+        );
+        assert_eq!(
+            store
+                .validate_client(&client.id, &Secret::new("wrong".to_owned()))
+                .await,
+            Err(OAuthClientStoreError::InvalidClient)
+        );
+
+        let unknown = ClientId::parse("client_unknown".to_owned()).unwrap();
+        assert_eq!(
+            store.validate_client(&unknown, &secret).await,
+            Err(OAuthClientStoreError::InvalidClient)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_client() {
+        let mut store = HashmapOAuthClientStore::default();
+        let (client, secret) =
+            OAuthClient::register(TenantId::default(), "billing".to_owned(), vec![]);
+        let other_tenant = TenantId::parse("other".to_owned()).unwrap();
+
+        store
+            .add_client(client.clone(), secret.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.delete_client(&other_tenant, &client.id).await,
+            Err(OAuthClientStoreError::ClientNotFound)
+        );
+        store
+            .delete_client(&client.tenant, &client.id)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.validate_client(&client.id, &secret).await,
+            Err(OAuthClientStoreError::InvalidClient)
+        );
+        assert_eq!(
+            store.delete_client(&client.tenant, &client.id).await,
+            Err(OAuthClientStoreError::ClientNotFound)
+        );
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::domain::{
+    data_stores::{
+        PhoneVerificationStore, PhoneVerificationStoreError, TwoFACode,
+        PHONE_VERIFICATION_COOLDOWN_SECONDS, PHONE_VERIFICATION_TTL_SECONDS,
+    },
+    Email, PhoneNumber, TenantId,
+};
+
+#[derive(Default)]
+pub struct HashmapPhoneVerificationStore {
+    pending: HashMap<(TenantId, Email), PendingVerification>,
+    // When each user last started a verification, kept apart from `pending` so taking a
+    // verification doesn't end the cooldown
+    started_at: HashMap<(TenantId, Email), DateTime<Utc>>,
+}
+
+struct PendingVerification {
+    phone_number: PhoneNumber,
+    code: TwoFACode,
+    expires_at: DateTime<Utc>,
+}
+
+#[async_trait::async_trait]
+impl PhoneVerificationStore for HashmapPhoneVerificationStore {
+    async fn add_pending(
+        &mut self,
+        tenant: TenantId,
+        email: Email,
+        phone_number: PhoneNumber,
+        code: TwoFACode,
+    ) -> Result<(), PhoneVerificationStoreError> {
+        let now = Utc::now();
+        let cooldown = Duration::seconds(PHONE_VERIFICATION_COOLDOWN_SECONDS);
+        self.started_at
+            .retain(|_, started_at| now - *started_at < cooldown);
+
+        let key = (tenant, email);
+        if self.started_at.contains_key(&key) {
+            return Err(PhoneVerificationStoreError::Cooldown);
+        }
+        self.started_at.insert(key.clone(), now);
+
+        self.pending.retain(|_, pending| pending.expires_at > now);
+        let pending = PendingVerification {
+            phone_number,
+            code,
+            expires_at: now + Duration::seconds(PHONE_VERIFICATION_TTL_SECONDS),
+        };
+        self.pending.insert(key, pending);
+        Ok(())
+    }
+
+    async fn take_pending(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<(PhoneNumber, TwoFACode), PhoneVerificationStoreError> {
+        match self.pending.remove(&(tenant.clone(), email.clone())) {
+            Some(pending) if pending.expires_at > Utc::now() => {
+                Ok((pending.phone_number, pending.code))
+            }
+            _ => Err(PhoneVerificationStoreError::VerificationNotFound),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_take_pending() {
+        let mut store = HashmapPhoneVerificationStore::default();
+        let email = Email::parse(Secret::new("phone@example.com".to_owned())).unwrap();
+        let phone_number = PhoneNumber::parse(Secret::new("+14155552671".to_owned())).unwrap();
+        let code = TwoFACode::default();
+        let tenant = TenantId::default();
+
+        let result = store
+            .add_pending(
+                tenant.clone(),
+                email.clone(),
+                phone_number.clone(),
+                code.clone(),
+            )
+            .await;
+        assert!(result.is_ok());
+
+        let other_tenant = TenantId::parse("other".to_owned()).unwrap();
+        let result = store.take_pending(&other_tenant, &email).await;
+        assert_eq!(
+            result,
+            Err(PhoneVerificationStoreError::VerificationNotFound)
+        );
+
+        let result = store.take_pending(&tenant, &email).await;
+        assert_eq!(result.unwrap(), (phone_number, code));
+
+        let result = store.take_pending(&tenant, &email).await;
+        assert_eq!(
+            result,
+            Err(PhoneVerificationStoreError::VerificationNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_pending_cooldown() {
+        let mut store = HashmapPhoneVerificationStore::default();
+        let email = Email::parse(Secret::new("phone@example.com".to_owned())).unwrap();
+        let phone_number = PhoneNumber::parse(Secret::new("+14155552671".to_owned())).unwrap();
+        let tenant = TenantId::default();
+
+        let result = store
+            .add_pending(
+                tenant.clone(),
+                email.clone(),
+                phone_number.clone(),
+                TwoFACode::default(),
+            )
+            .await;
+        assert!(result.is_ok());
+
+        // Guessing wrong doesn't allow texting a new code right away
+        assert!(store.take_pending(&tenant, &email).await.is_ok());
+        let result = store
+            .add_pending(
+                tenant.clone(),
+                email.clone(),
+                phone_number.clone(),
+                TwoFACode::default(),
+            )
+            .await;
+        assert_eq!(result, Err(PhoneVerificationStoreError::Cooldown));
+
+        let other_tenant = TenantId::parse("other".to_owned()).unwrap();
+        let result = store
+            .add_pending(other_tenant, email, phone_number, TwoFACode::default())
+            .await;
+        assert!(result.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_take_pending_expired() {
+        let mut store = HashmapPhoneVerificationStore::default();
+        let email = Email::parse(Secret::new("phone@example.com".to_owned())).unwrap();
+        let phone_number = PhoneNumber::parse(Secret::new("+14155552671".to_owned())).unwrap();
+        let tenant = TenantId::default();
+
+        let result = store
+            .add_pending(
+                tenant.clone(),
+                email.clone(),
+                phone_number,
+                TwoFACode::default(),
+            )
+            .await;
+        assert!(result.is_ok());
+
+        let key = (tenant.clone(), email.clone());
+        store.pending.get_mut(&key).unwrap().expires_at = Utc::now() - Duration::seconds(1);
+
+        let result = store.take_pending(&tenant, &email).await;
+        assert_eq!(
+            result,
+            Err(PhoneVerificationStoreError::VerificationNotFound)
+        );
+        assert!(store.pending.is_empty());
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::domain::{
+    data_stores::{RoleStore, RoleStoreError},
+    Email, Grants, Permission, Role, TenantId,
+};
+
+#[derive(Default)]
+pub struct HashmapRoleStore {
+    roles: HashMap<Role, Vec<Permission>>,
+    assignments: HashMap<(TenantId, Email), HashSet<Role>>,
+}
+
+#[async_trait::async_trait]
+impl RoleStore for HashmapRoleStore {
+    async fn add_role(
+        &mut self,
+        role: Role,
+        permissions: Vec<Permission>,
+    ) -> Result<(), RoleStoreError> {
+        if self.roles.contains_key(&role) {
+            return Err(RoleStoreError::RoleAlreadyExists);
+        }
+
+        self.roles.insert(role, permissions);
+        Ok(())
+    }
+
+    async fn assign_role(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        role: &Role,
+    ) -> Result<(), RoleStoreError> {
+        if !self.roles.contains_key(role) {
+            return Err(RoleStoreError::RoleNotFound);
+        }
+
+        self.assignments
+            .entry((tenant.clone(), email.clone()))
+            .or_default()
+            .insert(role.clone());
+        Ok(())
+    }
+
+    async fn unassign_role(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        role: &Role,
+    ) -> Result<(), RoleStoreError> {
+        let removed = self
+            .assignments
+            .get_mut(&(tenant.clone(), email.clone()))
+            .is_some_and(|roles| roles.remove(role));
+
+        if removed {
+            Ok(())
+        } else {
+            Err(RoleStoreError::RoleNotAssigned)
+        }
+    }
+
+    async fn get_grants(&self, tenant: &TenantId, email: &Email) -> Result<Grants, RoleStoreError> {
+        let roles: Vec<Role> = self
+            .assignments
+            .get(&(tenant.clone(), email.clone()))
+            .map(|roles| roles.iter().cloned().collect())
+            .unwrap_or_default();
+
+        let permissions = roles
+            .iter()
+            .filter_map(|role| self.roles.get(role))
+            .flatten()
+            .cloned()
+            .collect();
+
+        Ok(Grants::new(roles, permissions))
+    }
+
+    async fn role_exists(&self, role: &Role) -> Result<bool, RoleStoreError> {
+        Ok(self.roles.contains_key(role))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+
+    fn email(s: &str) -> Email {
+        Email::parse(Secret::new(s.to_owned())).unwrap()
+    }
+
+    fn role(s: &str) -> Role {
+        Role::parse(s.to_owned()).unwrap()
+    }
+
+    fn permission(s: &str) -> Permission {
+        Permission::parse(s.to_owned()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_assigned_roles_grant_their_permissions() {
+        let mut store = HashmapRoleStore::default();
+        let tenant = TenantId::default();
+        let user = email("user@example.com");
+
+        store
+            .add_role(
+                role("admin"),
+                vec![permission("roles:manage"), permission("users:read")],
+            )
+            .await
+            .unwrap();
+        store
+            .add_role(role("support"), vec![permission("users:read")])
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.get_grants(&tenant, &user).await,
+            Ok(Grants::default())
+        );
+
+        store
+            .assign_role(&tenant, &user, &role("admin"))
+            .await
+            .unwrap();
+        store
+            .assign_role(&tenant, &user, &role("support"))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.get_grants(&tenant, &user).await,
+            Ok(Grants::new(
+                vec![role("admin"), role("support")],
+                vec![permission("roles:manage"), permission("users:read")]
+            ))
+        );
+
+        let other_tenant = TenantId::parse("acme".to_owned()).unwrap();
+        assert_eq!(
+            store.get_grants(&other_tenant, &user).await,
+            Ok(Grants::default())
+        );
+
+        store
+            .unassign_role(&tenant, &user, &role("admin"))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.get_grants(&tenant, &user).await,
+            Ok(Grants::new(
+                vec![role("support")],
+                vec![permission("users:read")]
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unknown_roles_are_rejected() {
+        let mut store = HashmapRoleStore::default();
+        let tenant = TenantId::default();
+        let user = email("user@example.com");
+
+        assert_eq!(
+            store.assign_role(&tenant, &user, &role("admin")).await,
+            Err(RoleStoreError::RoleNotFound)
+        );
+        assert_eq!(
+            store.unassign_role(&tenant, &user, &role("admin")).await,
+            Err(RoleStoreError::RoleNotAssigned)
+        );
+
+        assert_eq!(store.role_exists(&role("admin")).await, Ok(false));
+        store.add_role(role("admin"), vec![]).await.unwrap();
+        assert_eq!(store.role_exists(&role("admin")).await, Ok(true));
+        assert_eq!(
+            store.add_role(role("admin"), vec![]).await,
+            Err(RoleStoreError::RoleAlreadyExists)
+        );
+    }
+}
@@ -0,0 +1,134 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{SamlProviderStore, SamlProviderStoreError},
+    ProviderId, SamlProvider, TenantId,
+};
+
+#[derive(Default)]
+pub struct HashmapSamlProviderStore {
+    providers: HashMap<(TenantId, ProviderId), SamlProvider>,
+}
+
+#[async_trait::async_trait]
+impl SamlProviderStore for HashmapSamlProviderStore {
+    async fn add_provider(&mut self, provider: SamlProvider) -> Result<(), SamlProviderStoreError> {
+        let key = (provider.tenant.clone(), provider.id.clone());
+        if self.providers.contains_key(&key) {
+            return Err(SamlProviderStoreError::ProviderAlreadyExists);
+        }
+
+        self.providers.insert(key, provider);
+        Ok(())
+    }
+
+    async fn get_provider(
+        &self,
+        tenant: &TenantId,
+        id: &ProviderId,
+    ) -> Result<SamlProvider, SamlProviderStoreError> {
+        self.providers
+            .get(&(tenant.clone(), id.clone()))
+            .cloned()
+            .ok_or(SamlProviderStoreError::ProviderNotFound)
+    }
+
+    async fn list_providers(
+        &self,
+        tenant: &TenantId,
+    ) -> Result<Vec<SamlProvider>, SamlProviderStoreError> {
+        let mut providers: Vec<SamlProvider> = self
+            .providers
+            .values()
+            .filter(|provider| provider.tenant == *tenant)
+            .cloned()
+            .collect();
+        providers.sort_by_key(|provider| provider.created_at);
+        Ok(providers)
+    }
+
+    async fn delete_provider(
+        &mut self,
+        tenant: &TenantId,
+        id: &ProviderId,
+    ) -> Result<(), SamlProviderStoreError> {
+        self.providers
+            .remove(&(tenant.clone(), id.clone()))
+            .map(|_| ())
+            .ok_or(SamlProviderStoreError::ProviderNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Stores don't look at the certificate, so it's skipped to keep the test short
+    fn provider(tenant: TenantId) -> SamlProvider {
+        SamlProvider {
+            id: ProviderId::parse("corp".to_owned()).unwrap(),
+            tenant,
+            name: "Corp SSO".to_owned(),
+            entity_id: "https://idp.example.com/metadata".to_owned(),
+            sso_url: "https://idp.example.com/sso".to_owned(),
+            certificate: String::new(),
+            email_attribute: None,
+            created_at: chrono::Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_add_and_get_provider() {
+        let mut store = HashmapSamlProviderStore::default();
+        let provider = provider(TenantId::default());
+        let other_tenant = TenantId::parse("other".to_owned()).unwrap();
+
+        store.add_provider(provider.clone()).await.unwrap();
+        assert_eq!(
+            store.add_provider(provider.clone()).await,
+            Err(SamlProviderStoreError::ProviderAlreadyExists)
+        );
+
+        let stored = store
+            .get_provider(&provider.tenant, &provider.id)
+            .await
+            .unwrap();
+        assert_eq!(stored.entity_id, provider.entity_id);
+        assert_eq!(
+            store.list_providers(&provider.tenant).await.unwrap().len(),
+            1
+        );
+
+        // Providers with the same id may exist in other tenants
+        assert_eq!(
+            store.get_provider(&other_tenant, &provider.id).await.err(),
+            Some(SamlProviderStoreError::ProviderNotFound)
+        );
+        store
+            .add_provider(self::provider(other_tenant))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_delete_provider() {
+        let mut store = HashmapSamlProviderStore::default();
+        let provider = provider(TenantId::default());
+
+        store.add_provider(provider.clone()).await.unwrap();
+        store
+            .delete_provider(&provider.tenant, &provider.id)
+            .await
+            .unwrap();
+
+        assert!(store
+            .list_providers(&provider.tenant)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            store.delete_provider(&provider.tenant, &provider.id).await,
+            Err(SamlProviderStoreError::ProviderNotFound)
+        );
+    }
+}
@@ -0,0 +1,87 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Utc};
+
+use crate::domain::{
+    data_stores::{SamlReplayCache, SamlReplayCacheError},
+    ProviderId, TenantId,
+};
+
+#[derive(Default)]
+pub struct HashmapSamlReplayCache {
+    assertions: HashMap<(TenantId, ProviderId, String), DateTime<Utc>>,
+}
+
+#[async_trait::async_trait]
+impl SamlReplayCache for HashmapSamlReplayCache {
+    async fn record_assertion(
+        &mut self,
+        tenant: &TenantId,
+        provider: &ProviderId,
+        assertion_id: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), SamlReplayCacheError> {
+        // Expired assertions are forgotten as new ones come in, which keeps the map small
+        let now = Utc::now();
+        self.assertions.retain(|_, expires_at| *expires_at > now);
+
+        let key = (tenant.clone(), provider.clone(), assertion_id.to_owned());
+        if self.assertions.contains_key(&key) {
+            return Err(SamlReplayCacheError::AssertionReplayed);
+        }
+
+        self.assertions.insert(key, expires_at);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_assertion_can_only_be_recorded_once() {
+        let mut cache = HashmapSamlReplayCache::default();
+        let tenant = TenantId::default();
+        let provider = ProviderId::parse("corp".to_owned()).unwrap();
+        let expires_at = Utc::now() + Duration::minutes(5);
+
+        cache
+            .record_assertion(&tenant, &provider, "_a1", expires_at)
+            .await
+            .unwrap();
+        assert_eq!(
+            cache
+                .record_assertion(&tenant, &provider, "_a1", expires_at)
+                .await,
+            Err(SamlReplayCacheError::AssertionReplayed)
+        );
+
+        // Assertion IDs are only unique per provider
+        let other_provider = ProviderId::parse("other".to_owned()).unwrap();
+        cache
+            .record_assertion(&tenant, &other_provider, "_a1", expires_at)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_expired_assertions_are_forgotten() {
+        let mut cache = HashmapSamlReplayCache::default();
+        let tenant = TenantId::default();
+        let provider = ProviderId::parse("corp".to_owned()).unwrap();
+
+        cache
+            .record_assertion(&tenant, &provider, "_a1", Utc::now() - Duration::seconds(1))
+            .await
+            .unwrap();
+        cache
+            .record_assertion(&tenant, &provider, "_a2", Utc::now() + Duration::minutes(5))
+            .await
+            .unwrap();
+
+        assert_eq!(cache.assertions.len(), 1);
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{TenantStore, TenantStoreError},
+    Tenant, TenantId,
+};
+
+pub struct HashmapTenantStore {
+    tenants: HashMap<TenantId, Tenant>,
+}
+
+// Like the tenants table, the store always knows the default tenant
+impl Default for HashmapTenantStore {
+    fn default() -> Self {
+        let tenant = Tenant::new(TenantId::default());
+
+        Self {
+            tenants: HashMap::from([(tenant.id.clone(), tenant)]),
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl TenantStore for HashmapTenantStore {
+    async fn add_tenant(&mut self, tenant: Tenant) -> Result<(), TenantStoreError> {
+        let hostname_taken = tenant.hostname.is_some()
+            && self.tenants.values().any(|t| t.hostname == tenant.hostname);
+
+        if self.tenants.contains_key(&tenant.id) || hostname_taken {
+            return Err(TenantStoreError::TenantAlreadyExists);
+        }
+
+        self.tenants.insert(tenant.id.clone(), tenant);
+        Ok(())
+    }
+
+    async fn get_tenant(&self, id: &TenantId) -> Result<Tenant, TenantStoreError> {
+        self.tenants
+            .get(id)
+            .cloned()
+            .ok_or(TenantStoreError::TenantNotFound)
+    }
+
+    async fn get_tenant_by_hostname(&self, hostname: &str) -> Result<Tenant, TenantStoreError> {
+        self.tenants
+            .values()
+            .find(|tenant| tenant.hostname.as_deref() == Some(hostname))
+            .cloned()
+            .ok_or(TenantStoreError::TenantNotFound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tenant_id(s: &str) -> TenantId {
+        TenantId::parse(s.to_owned()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_knows_the_default_tenant() {
+        let store = HashmapTenantStore::default();
+
+        let result = store.get_tenant(&TenantId::default()).await;
+        assert_eq!(result, Ok(Tenant::new(TenantId::default())));
+    }
+
+    #[tokio::test]
+    async fn test_get_tenant_by_hostname() {
+        let mut store = HashmapTenantStore::default();
+        let tenant = Tenant::new(tenant_id("acme")).with_hostname("acme.example.com");
+
+        store.add_tenant(tenant.clone()).await.unwrap();
+
+        assert_eq!(
+            store.get_tenant(&tenant_id("acme")).await,
+            Ok(tenant.clone())
+        );
+        assert_eq!(
+            store.get_tenant_by_hostname("acme.example.com").await,
+            Ok(tenant)
+        );
+        assert_eq!(
+            store.get_tenant_by_hostname("other.example.com").await,
+            Err(TenantStoreError::TenantNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_rejects_duplicate_tenants() {
+        let mut store = HashmapTenantStore::default();
+        store
+            .add_tenant(Tenant::new(tenant_id("acme")).with_hostname("acme.example.com"))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.add_tenant(Tenant::new(tenant_id("acme"))).await,
+            Err(TenantStoreError::TenantAlreadyExists)
+        );
+        assert_eq!(
+            store
+                .add_tenant(Tenant::new(tenant_id("globex")).with_hostname("acme.example.com"))
+                .await,
+            Err(TenantStoreError::TenantAlreadyExists)
+        );
+    }
+}
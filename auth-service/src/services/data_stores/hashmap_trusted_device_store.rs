@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+
+use crate::domain::{
+    data_stores::{TrustedDeviceStore, TrustedDeviceStoreError},
+    Email, TenantId, TrustedDevice, TrustedDeviceId,
+};
+
+#[derive(Default)]
+pub struct HashmapTrustedDeviceStore {
+    devices: HashMap<TrustedDeviceId, TrustedDevice>,
+}
+
+#[async_trait::async_trait]
+impl TrustedDeviceStore for HashmapTrustedDeviceStore {
+    async fn add_device(&mut self, device: TrustedDevice) -> Result<(), TrustedDeviceStoreError> {
+        self.devices.insert(device.id, device);
+        Ok(())
+    }
+
+    async fn is_trusted(
+        &self,
+        tenant: &TenantId,
+        email: &Email,
+        device_id: &TrustedDeviceId,
+    ) -> Result<bool, TrustedDeviceStoreError> {
+        Ok(self
+            .devices
+            .get(device_id)
+            .is_some_and(|device| device.belongs_to(tenant, email) && !device.is_expired()))
+    }
+
+    async fn list_devices(
+        &self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<Vec<TrustedDevice>, TrustedDeviceStoreError> {
+        let mut devices: Vec<TrustedDevice> = self
+            .devices
+            .values()
+            .filter(|device| device.belongs_to(tenant, email) && !device.is_expired())
+            .cloned()
+            .collect();
+        devices.sort_by_key(|device| device.created_at);
+        Ok(devices)
+    }
+
+    async fn revoke_device(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        device_id: &TrustedDeviceId,
+    ) -> Result<(), TrustedDeviceStoreError> {
+        match self.devices.get(device_id) {
+            Some(device) if device.belongs_to(tenant, email) => {
+                self.devices.remove(device_id);
+                Ok(())
+            }
+            _ => Err(TrustedDeviceStoreError::DeviceNotFound),
+        }
+    }
+
+    async fn revoke_all_devices(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<(), TrustedDeviceStoreError> {
+        self.devices
+            .retain(|_, device| !device.belongs_to(tenant, email));
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+    use crate::domain::ClientFingerprint;
+
+    fn new_device(email: &str, ttl: chrono::Duration) -> TrustedDevice {
+        let email = Email::parse(Secret::new(email.to_owned())).unwrap();
+        TrustedDevice::new(
+            TenantId::default(),
+            email,
+            ClientFingerprint::default(),
+            ttl,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_add_and_check_device() {
+        let mut store = HashmapTrustedDeviceStore::default();
+        let device = new_device("trusted@example.com", chrono::Duration::days(1));
+
+        store.add_device(device.clone()).await.unwrap();
+
+        assert_eq!(
+            store
+                .is_trusted(&device.tenant, &device.email, &device.id)
+                .await,
+            Ok(true)
+        );
+        assert_eq!(
+            store.list_devices(&device.tenant, &device.email).await,
+            Ok(vec![device])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_device_is_bound_to_its_owner() {
+        let mut store = HashmapTrustedDeviceStore::default();
+        let device = new_device("owner@example.com", chrono::Duration::days(1));
+        let other = Email::parse(Secret::new("other@example.com".to_owned())).unwrap();
+
+        store.add_device(device.clone()).await.unwrap();
+
+        assert_eq!(
+            store.is_trusted(&device.tenant, &other, &device.id).await,
+            Ok(false)
+        );
+
+        let other_tenant = TenantId::parse("acme".to_owned()).unwrap();
+        assert_eq!(
+            store
+                .is_trusted(&other_tenant, &device.email, &device.id)
+                .await,
+            Ok(false)
+        );
+        assert_eq!(
+            store
+                .revoke_device(&device.tenant, &other, &device.id)
+                .await,
+            Err(TrustedDeviceStoreError::DeviceNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_device_is_not_trusted() {
+        let mut store = HashmapTrustedDeviceStore::default();
+        let device = new_device("expired@example.com", chrono::Duration::zero());
+
+        store.add_device(device.clone()).await.unwrap();
+
+        assert_eq!(
+            store
+                .is_trusted(&device.tenant, &device.email, &device.id)
+                .await,
+            Ok(false)
+        );
+        assert_eq!(
+            store.list_devices(&device.tenant, &device.email).await,
+            Ok(vec![])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revoke_device() {
+        let mut store = HashmapTrustedDeviceStore::default();
+        let device = new_device("revoke@example.com", chrono::Duration::days(1));
+
+        store.add_device(device.clone()).await.unwrap();
+        store
+            .revoke_device(&device.tenant, &device.email, &device.id)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store
+                .is_trusted(&device.tenant, &device.email, &device.id)
+                .await,
+            Ok(false)
+        );
+        assert_eq!(
+            store
+                .revoke_device(&device.tenant, &device.email, &device.id)
+                .await,
+            Err(TrustedDeviceStoreError::DeviceNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revoke_all_devices() {
+        let mut store = HashmapTrustedDeviceStore::default();
+        let first = new_device("revoke@example.com", chrono::Duration::days(1));
+        let second = new_device("revoke@example.com", chrono::Duration::days(1));
+        let other = new_device("other@example.com", chrono::Duration::days(1));
+
+        for device in [&first, &second, &other] {
+            store.add_device(device.clone()).await.unwrap();
+        }
+        store
+            .revoke_all_devices(&first.tenant, &first.email)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.list_devices(&first.tenant, &first.email).await,
+            Ok(vec![])
+        );
+        assert_eq!(
+            store
+                .is_trusted(&other.tenant, &other.email, &other.id)
+                .await,
+            Ok(true)
+        );
+    }
+}
@@ -1,32 +1,121 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc, time::Duration};
 
+use chrono::Utc;
 use color_eyre::eyre::eyre;
+use tokio::{sync::RwLock, task::JoinHandle};
 
 use crate::domain::{
-    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
+    data_stores::{
+        LoginAttemptId, TwoFAAttempt, TwoFACode, TwoFACodeStore, TwoFACodeStoreError,
+        TwoFAResendPolicy, MAX_TWO_FA_FAILED_ATTEMPTS,
+    },
     email::Email,
+    ClientFingerprint, TwoFAClientPolicy,
 };
 
-#[derive(Default, Debug)]
 pub struct HashmapTwoFACodeStore {
-    codes: HashMap<Email, (LoginAttemptId, TwoFACode)>,
+    codes: HashMap<LoginAttemptId, TwoFACodeEntry>,
+    resend_policy: TwoFAResendPolicy,
+    ttl_seconds: i64,
+}
+
+struct TwoFACodeEntry {
+    attempt: TwoFAAttempt,
+    resend_count: u32,
+    last_sent_at: i64,
+    failed_attempts: u32,
+    expires_at: i64,
+}
+
+impl TwoFACodeEntry {
+    fn is_expired(&self, now: i64) -> bool {
+        now >= self.expires_at
+    }
+}
+
+impl Default for HashmapTwoFACodeStore {
+    fn default() -> Self {
+        Self {
+            codes: HashMap::new(),
+            resend_policy: TwoFAResendPolicy::default(),
+            ttl_seconds: TEN_MINUTES_IN_SECONDS,
+        }
+    }
+}
+
+impl HashmapTwoFACodeStore {
+    pub fn with_resend_policy(mut self, resend_policy: TwoFAResendPolicy) -> Self {
+        self.resend_policy = resend_policy;
+        self
+    }
+
+    pub fn with_ttl_seconds(mut self, ttl_seconds: i64) -> Self {
+        self.ttl_seconds = ttl_seconds;
+        self
+    }
+
+    // Drops every code whose TTL has elapsed and returns how many were removed.
+    pub fn remove_expired(&mut self) -> usize {
+        let now = Utc::now().timestamp();
+        let before = self.codes.len();
+        self.codes.retain(|_, entry| !entry.is_expired(now));
+        before - self.codes.len()
+    }
+
+    fn get_live_entry(
+        &self,
+        login_attempt_id: &LoginAttemptId,
+        email: &Email,
+    ) -> Option<&TwoFACodeEntry> {
+        let now = Utc::now().timestamp();
+        self.codes
+            .get(login_attempt_id)
+            .filter(|entry| entry.attempt.email == *email && !entry.is_expired(now))
+    }
+}
+
+// Periodically evicts expired codes so abandoned login attempts don't pile up in memory.
+// Lookups already ignore expired entries; the sweeper only reclaims the space.
+pub fn spawn_expired_code_sweeper(
+    store: Arc<RwLock<HashmapTwoFACodeStore>>,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            let removed = store.write().await.remove_expired();
+            if removed > 0 {
+                tracing::debug!("Removed {} expired 2FA codes", removed);
+            }
+        }
+    })
 }
 
-// TODO: implement TwoFACodeStore for HashmapTwoFACodeStore
 #[async_trait::async_trait]
 impl TwoFACodeStore for HashmapTwoFACodeStore {
     async fn add_code(
         &mut self,
-        email: Email,
         login_attempt_id: LoginAttemptId,
-        code: TwoFACode,
+        attempt: TwoFAAttempt,
     ) -> Result<(), TwoFACodeStoreError> {
-        self.codes.insert(email, (login_attempt_id, code));
+        let now = Utc::now().timestamp();
+        let entry = TwoFACodeEntry {
+            attempt,
+            resend_count: 0,
+            last_sent_at: now,
+            failed_attempts: 0,
+            expires_at: now + self.ttl_seconds,
+        };
+        self.codes.insert(login_attempt_id, entry);
         Ok(())
     }
 
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        match self.codes.remove(email) {
+    async fn remove_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError> {
+        match self.codes.remove(login_attempt_id) {
             Some(_) => Ok(()),
             None => Err(TwoFACodeStoreError::UnexpectedError(eyre!("oops"))),
         }
@@ -34,64 +123,407 @@ impl TwoFACodeStore for HashmapTwoFACodeStore {
 
     async fn get_code(
         &self,
+        login_attempt_id: &LoginAttemptId,
         email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-        println!("{:?}", self.codes);
-        match self.codes.get(email) {
-            Some((login_attempt_id, code)) => Ok((login_attempt_id.clone(), code.clone())),
+    ) -> Result<TwoFAAttempt, TwoFACodeStoreError> {
+        match self.get_live_entry(login_attempt_id, email) {
+            Some(entry) => Ok(entry.attempt.clone()),
+            None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+        }
+    }
+
+    async fn consume_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        email: &Email,
+        code: &TwoFACode,
+        client: &ClientFingerprint,
+        client_policy: &TwoFAClientPolicy,
+    ) -> Result<TwoFAAttempt, TwoFACodeStoreError> {
+        let now = Utc::now().timestamp();
+        let entry = match self.codes.get_mut(login_attempt_id) {
+            Some(entry) if entry.attempt.email == *email && !entry.is_expired(now) => entry,
+            _ => return Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+        };
+
+        if !client_policy.allows(&entry.attempt.client, client) {
+            return Err(TwoFACodeStoreError::ClientNotAllowed);
+        }
+
+        if entry.attempt.code != *code {
+            entry.failed_attempts += 1;
+            if entry.failed_attempts >= MAX_TWO_FA_FAILED_ATTEMPTS {
+                self.codes.remove(login_attempt_id);
+            }
+            return Err(TwoFACodeStoreError::IncorrectCode);
+        }
+
+        match self.codes.remove(login_attempt_id) {
+            Some(entry) => Ok(entry.attempt),
             None => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
         }
     }
+
+    async fn resend_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        email: &Email,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let now = Utc::now().timestamp();
+        let entry = match self.codes.get_mut(login_attempt_id) {
+            Some(entry) if entry.attempt.email == *email && !entry.is_expired(now) => entry,
+            _ => return Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+        };
+
+        if entry.resend_count >= self.resend_policy.max_resends {
+            return Err(TwoFACodeStoreError::ResendLimitReached);
+        }
+
+        if now - entry.last_sent_at < self.resend_policy.cooldown_seconds {
+            return Err(TwoFACodeStoreError::ResendCooldown);
+        }
+
+        entry.attempt.code = code;
+        entry.resend_count += 1;
+        entry.last_sent_at = now;
+
+        Ok(())
+    }
 }
 
+const TEN_MINUTES_IN_SECONDS: i64 = 600;
+
 #[cfg(test)]
 mod tests {
-    use secrecy::Secret;
+    use secrecy::{ExposeSecret, Secret};
 
     use super::*;
+    use crate::domain::TenantId;
+
+    fn new_attempt(email: &str) -> (Email, TwoFAAttempt) {
+        let email = Email::parse(Secret::new(email.to_string())).unwrap();
+        let attempt = TwoFAAttempt::new(
+            TenantId::default(),
+            email.clone(),
+            TwoFACode::default(),
+            ClientFingerprint::default(),
+        );
+        (email, attempt)
+    }
 
     #[tokio::test]
     async fn test_add_code() {
         let mut code_store = HashmapTwoFACodeStore::default();
-        let email = Email::parse(Secret::new("rando@gmail.com".to_string())).unwrap();
+        let (_, attempt) = new_attempt("rando@gmail.com");
         let login_attempt_id = LoginAttemptId::default();
-        let code = TwoFACode::default();
 
-        let result = code_store.add_code(email.clone(), login_attempt_id, code).await;
+        let result = code_store.add_code(login_attempt_id.clone(), attempt).await;
         assert!(result.is_ok());
-        assert!(code_store.codes.contains_key(&email));
+        assert!(code_store.codes.contains_key(&login_attempt_id));
     }
 
     #[tokio::test]
     async fn test_remove_code() {
         let mut code_store = HashmapTwoFACodeStore::default();
-        let email = Email::parse(Secret::new("random@gmail.com".to_string())).unwrap();
+        let (_, attempt) = new_attempt("random@gmail.com");
         let login_attempt_id = LoginAttemptId::default();
-        let code = TwoFACode::default();
 
-        let result = code_store.add_code(email.clone(), login_attempt_id, code).await;
+        let result = code_store.add_code(login_attempt_id.clone(), attempt).await;
         assert!(result.is_ok());
-        assert!(code_store.codes.contains_key(&email));
+        assert!(code_store.codes.contains_key(&login_attempt_id));
 
-        let result = code_store.remove_code(&email).await;
+        let result = code_store.remove_code(&login_attempt_id).await;
         assert!(result.is_ok());
-        assert!(!code_store.codes.contains_key(&email));
+        assert!(!code_store.codes.contains_key(&login_attempt_id));
     }
 
     #[tokio::test]
     async fn test_get_code() {
         let mut code_store = HashmapTwoFACodeStore::default();
-        let email = Email::parse(Secret::new("random1@gmail.com".to_string())).unwrap();
+        let (email, attempt) = new_attempt("random1@gmail.com");
         let login_attempt_id = LoginAttemptId::default();
-        let code = TwoFACode::default();
 
-        let result = code_store.add_code(email.clone(), login_attempt_id.clone(), code.clone()).await;
+        let result = code_store
+            .add_code(login_attempt_id.clone(), attempt.clone())
+            .await;
         assert!(result.is_ok());
-        assert!(code_store.codes.contains_key(&email));
 
-        let result = code_store.get_code(&email).await;
-        assert!(result.is_ok());
-        assert!(code_store.codes.contains_key(&email));
-        assert_eq!(result.unwrap(), (login_attempt_id, code));
+        let result = code_store.get_code(&login_attempt_id, &email).await;
+        assert_eq!(result, Ok(attempt));
+        assert!(code_store.codes.contains_key(&login_attempt_id));
+    }
+
+    #[tokio::test]
+    async fn test_get_code_checks_email() {
+        let mut code_store = HashmapTwoFACodeStore::default();
+        let (_, attempt) = new_attempt("owner@gmail.com");
+        let (other_email, _) = new_attempt("other@gmail.com");
+        let login_attempt_id = LoginAttemptId::default();
+
+        code_store
+            .add_code(login_attempt_id.clone(), attempt)
+            .await
+            .unwrap();
+
+        let result = code_store.get_code(&login_attempt_id, &other_email).await;
+        assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_attempts_for_same_email_coexist() {
+        let mut code_store = HashmapTwoFACodeStore::default();
+        let (email, first_attempt) = new_attempt("concurrent@gmail.com");
+        let (_, second_attempt) = new_attempt("concurrent@gmail.com");
+        let first_id = LoginAttemptId::default();
+        let second_id = LoginAttemptId::default();
+
+        code_store
+            .add_code(first_id.clone(), first_attempt.clone())
+            .await
+            .unwrap();
+        code_store
+            .add_code(second_id.clone(), second_attempt.clone())
+            .await
+            .unwrap();
+
+        assert_eq!(
+            code_store.get_code(&first_id, &email).await,
+            Ok(first_attempt)
+        );
+        assert_eq!(
+            code_store.get_code(&second_id, &email).await,
+            Ok(second_attempt)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_consume_code_removes_attempt() {
+        let mut code_store = HashmapTwoFACodeStore::default();
+        let (email, attempt) = new_attempt("consume@gmail.com");
+        let login_attempt_id = LoginAttemptId::default();
+        let (code, client) = (attempt.code.clone(), attempt.client.clone());
+        let policy = TwoFAClientPolicy::SameClient;
+
+        code_store
+            .add_code(login_attempt_id.clone(), attempt.clone())
+            .await
+            .unwrap();
+
+        let result = code_store
+            .consume_code(&login_attempt_id, &email, &code, &client, &policy)
+            .await;
+        assert_eq!(result, Ok(attempt));
+
+        let result = code_store
+            .consume_code(&login_attempt_id, &email, &code, &client, &policy)
+            .await;
+        assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_consume_code_drops_attempt_after_too_many_misses() {
+        let mut code_store = HashmapTwoFACodeStore::default();
+        let (email, attempt) = new_attempt("misses@gmail.com");
+        let login_attempt_id = LoginAttemptId::default();
+        let (code, client) = (attempt.code.clone(), attempt.client.clone());
+        let wrong_code = TwoFACode::parse(Secret::new(
+            if code.as_ref().expose_secret() == "111111" {
+                "222222"
+            } else {
+                "111111"
+            }
+            .to_owned(),
+        ))
+        .unwrap();
+        let policy = TwoFAClientPolicy::AnyClient;
+
+        code_store
+            .add_code(login_attempt_id.clone(), attempt)
+            .await
+            .unwrap();
+
+        for _ in 0..MAX_TWO_FA_FAILED_ATTEMPTS {
+            let result = code_store
+                .consume_code(&login_attempt_id, &email, &wrong_code, &client, &policy)
+                .await;
+            assert_eq!(result, Err(TwoFACodeStoreError::IncorrectCode));
+        }
+
+        // Even the right code is refused once the attempt is gone
+        let result = code_store
+            .consume_code(&login_attempt_id, &email, &code, &client, &policy)
+            .await;
+        assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_consume_code_keeps_attempt_for_refused_client() {
+        let mut code_store = HashmapTwoFACodeStore::default();
+        let (email, attempt) = new_attempt("refused@gmail.com");
+        let login_attempt_id = LoginAttemptId::default();
+        let code = attempt.code.clone();
+        let other_client = ClientFingerprint::new(None, Some("other-browser".to_owned()));
+        let policy = TwoFAClientPolicy::SameClient;
+
+        code_store
+            .add_code(login_attempt_id.clone(), attempt.clone())
+            .await
+            .unwrap();
+
+        let result = code_store
+            .consume_code(&login_attempt_id, &email, &code, &other_client, &policy)
+            .await;
+        assert_eq!(result, Err(TwoFACodeStoreError::ClientNotAllowed));
+
+        let result = code_store
+            .consume_code(&login_attempt_id, &email, &code, &attempt.client, &policy)
+            .await;
+        assert_eq!(result, Ok(attempt));
+    }
+
+    #[tokio::test]
+    async fn test_resend_code_rotates_code() {
+        let mut code_store =
+            HashmapTwoFACodeStore::default().with_resend_policy(TwoFAResendPolicy {
+                cooldown_seconds: 0,
+                max_resends: 1,
+            });
+        let (email, attempt) = new_attempt("resend@gmail.com");
+        let login_attempt_id = LoginAttemptId::default();
+
+        code_store
+            .add_code(login_attempt_id.clone(), attempt)
+            .await
+            .unwrap();
+
+        let new_code = TwoFACode::default();
+        let result = code_store
+            .resend_code(&login_attempt_id, &email, new_code.clone())
+            .await;
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            code_store
+                .get_code(&login_attempt_id, &email)
+                .await
+                .unwrap()
+                .code,
+            new_code
+        );
+
+        let result = code_store
+            .resend_code(&login_attempt_id, &email, TwoFACode::default())
+            .await;
+        assert_eq!(result, Err(TwoFACodeStoreError::ResendLimitReached));
+    }
+
+    #[tokio::test]
+    async fn test_resend_code_keeps_expiry() {
+        let mut code_store =
+            HashmapTwoFACodeStore::default().with_resend_policy(TwoFAResendPolicy {
+                cooldown_seconds: 0,
+                max_resends: 1,
+            });
+        let (email, attempt) = new_attempt("expiry@gmail.com");
+        let login_attempt_id = LoginAttemptId::default();
+
+        code_store
+            .add_code(login_attempt_id.clone(), attempt)
+            .await
+            .unwrap();
+        let expires_at = code_store.codes[&login_attempt_id].expires_at;
+        code_store.ttl_seconds *= 2;
+
+        code_store
+            .resend_code(&login_attempt_id, &email, TwoFACode::default())
+            .await
+            .unwrap();
+        assert_eq!(code_store.codes[&login_attempt_id].expires_at, expires_at);
+    }
+
+    #[tokio::test]
+    async fn test_resend_code_enforces_cooldown() {
+        let mut code_store = HashmapTwoFACodeStore::default();
+        let (email, attempt) = new_attempt("cooldown@gmail.com");
+        let login_attempt_id = LoginAttemptId::default();
+
+        code_store
+            .add_code(login_attempt_id.clone(), attempt)
+            .await
+            .unwrap();
+
+        let result = code_store
+            .resend_code(&login_attempt_id, &email, TwoFACode::default())
+            .await;
+        assert_eq!(result, Err(TwoFACodeStoreError::ResendCooldown));
+    }
+
+    #[tokio::test]
+    async fn test_resend_code_requires_matching_login_attempt() {
+        let mut code_store = HashmapTwoFACodeStore::default();
+        let (email, attempt) = new_attempt("mismatch@gmail.com");
+
+        code_store
+            .add_code(LoginAttemptId::default(), attempt)
+            .await
+            .unwrap();
+
+        let result = code_store
+            .resend_code(&LoginAttemptId::default(), &email, TwoFACode::default())
+            .await;
+        assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_get_code_ignores_expired_code() {
+        let mut code_store = HashmapTwoFACodeStore::default().with_ttl_seconds(0);
+        let (email, attempt) = new_attempt("expired@gmail.com");
+        let login_attempt_id = LoginAttemptId::default();
+
+        code_store
+            .add_code(login_attempt_id.clone(), attempt)
+            .await
+            .unwrap();
+
+        let result = code_store.get_code(&login_attempt_id, &email).await;
+        assert_eq!(result, Err(TwoFACodeStoreError::LoginAttemptIdNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_remove_expired_keeps_live_codes() {
+        let mut code_store = HashmapTwoFACodeStore::default();
+        let (_, live) = new_attempt("live@gmail.com");
+        let (_, stale) = new_attempt("stale@gmail.com");
+        let live_id = LoginAttemptId::default();
+        let stale_id = LoginAttemptId::default();
+
+        code_store.add_code(live_id.clone(), live).await.unwrap();
+        code_store.ttl_seconds = 0;
+        code_store.add_code(stale_id.clone(), stale).await.unwrap();
+
+        assert_eq!(code_store.remove_expired(), 1);
+        assert!(code_store.codes.contains_key(&live_id));
+        assert!(!code_store.codes.contains_key(&stale_id));
+    }
+
+    #[tokio::test]
+    async fn test_sweeper_removes_expired_codes() {
+        let store = Arc::new(RwLock::new(
+            HashmapTwoFACodeStore::default().with_ttl_seconds(0),
+        ));
+        let (_, attempt) = new_attempt("sweep@gmail.com");
+
+        store
+            .write()
+            .await
+            .add_code(LoginAttemptId::default(), attempt)
+            .await
+            .unwrap();
+
+        let sweeper = spawn_expired_code_sweeper(store.clone(), Duration::from_millis(10));
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        sweeper.abort();
+
+        assert!(store.read().await.codes.is_empty());
     }
 }
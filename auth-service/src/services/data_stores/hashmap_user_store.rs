@@ -1,25 +1,40 @@
 use std::collections::HashMap;
 
-use crate::domain::{Email, Password, User, UserStore, UserStoreError};
+use chrono::Utc;
+use secrecy::ExposeSecret;
+
+use crate::domain::{
+    AccountStatus, Email, Password, PhoneNumber, SuspendedBy, TenantId, TwoFAChannel, User,
+    UserPage, UserQuery, UserStore, UserStoreError,
+};
 
 // TODO: Create a new struct called `HashmapUserStore` containing a `users` field
 // which stores a `HashMap`` of email `String`s mapped to `User` objects.
 // Derive the `Default` trait for `HashmapUserStore`.
 #[derive(Default)]
 pub struct HashmapUserStore {
-    users: HashMap<Email, User>,
+    users: HashMap<(TenantId, Email), User>,
+}
+
+impl HashmapUserStore {
+    fn user_mut(&mut self, tenant: &TenantId, email: &Email) -> Result<&mut User, UserStoreError> {
+        self.users
+            .get_mut(&(tenant.clone(), email.clone()))
+            .ok_or(UserStoreError::UserNotFound)
+    }
 }
 
 #[async_trait::async_trait]
 impl UserStore for HashmapUserStore {
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
+    async fn add_user(&mut self, tenant: &TenantId, user: User) -> Result<(), UserStoreError> {
         // Return `UserStoreError::UserAlreadyExists` if the user already exists,
         // otherwise insert the user into the hashmap and return `Ok(())`.
-        if self.users.contains_key(&user.email) {
+        let key = (tenant.clone(), user.email.clone());
+        if self.users.contains_key(&key) {
             return Err(UserStoreError::UserAlreadyExists);
         }
 
-        self.users.insert(user.email.clone(), user);
+        self.users.insert(key, user);
         Ok(())
     }
 
@@ -28,8 +43,8 @@ impl UserStore for HashmapUserStore {
     // This function should return a `Result` type containing either a
     // `User` object or a `UserStoreError`.
     // Return `UserStoreError::UserNotFound` if the user can not be found.
-    async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
-        match self.users.get(email) {
+    async fn get_user(&self, tenant: &TenantId, email: &Email) -> Result<User, UserStoreError> {
+        match self.users.get(&(tenant.clone(), email.clone())) {
             Some(user) => Ok(user.clone()),
             None => Err(UserStoreError::UserNotFound),
         }
@@ -41,8 +56,13 @@ impl UserStore for HashmapUserStore {
     // unit type `()` if the email/password passed in match an existing user, or a `UserStoreError`.
     // Return `UserStoreError::UserNotFound` if the user can not be found.
     // Return `UserStoreError::InvalidCredentials` if the password is incorrect.
-    async fn validate_user(&self, email: &Email, password: &Password) -> Result<(), UserStoreError> {
-        match self.users.get(email) {
+    async fn validate_user(
+        &self,
+        tenant: &TenantId,
+        email: &Email,
+        password: &Password,
+    ) -> Result<(), UserStoreError> {
+        match self.users.get(&(tenant.clone(), email.clone())) {
             Some(user) => {
                 if user.password == *password {
                     Ok(())
@@ -53,6 +73,128 @@ impl UserStore for HashmapUserStore {
             None => Err(UserStoreError::UserNotFound),
         }
     }
+
+    async fn set_phone_number(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        phone_number: PhoneNumber,
+    ) -> Result<(), UserStoreError> {
+        let user = self.user_mut(tenant, email)?;
+        user.phone_number = Some(phone_number);
+        Ok(())
+    }
+
+    async fn set_two_fa_channel(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        channel: TwoFAChannel,
+    ) -> Result<(), UserStoreError> {
+        let user = self.user_mut(tenant, email)?;
+        user.two_fa_channel = channel;
+        Ok(())
+    }
+
+    async fn list_users(
+        &self,
+        tenant: &TenantId,
+        query: &UserQuery,
+    ) -> Result<UserPage, UserStoreError> {
+        let search = query.search.as_deref().map(str::to_lowercase);
+
+        let mut users: Vec<&User> = self
+            .users
+            .iter()
+            .filter(|((user_tenant, _), _)| user_tenant == tenant)
+            .map(|(_, user)| user)
+            .filter(|user| match &search {
+                Some(search) => user
+                    .email
+                    .as_ref()
+                    .expose_secret()
+                    .to_lowercase()
+                    .contains(search),
+                None => true,
+            })
+            .collect();
+        users.sort_by(|a, b| {
+            a.email
+                .as_ref()
+                .expose_secret()
+                .cmp(b.email.as_ref().expose_secret())
+        });
+
+        let total = users.len() as u64;
+        let users = users
+            .into_iter()
+            .skip(query.offset as usize)
+            .take(query.limit as usize)
+            .cloned()
+            .collect();
+
+        Ok(UserPage { users, total })
+    }
+
+    async fn set_status(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        status: AccountStatus,
+        reason: Option<String>,
+        suspended_by: Option<SuspendedBy>,
+    ) -> Result<(), UserStoreError> {
+        let user = self.user_mut(tenant, email)?;
+        user.status = status;
+        user.status_reason = reason;
+        user.suspended_by = suspended_by;
+        user.status_changed_at = Utc::now();
+        Ok(())
+    }
+
+    async fn require_password_reset(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<(), UserStoreError> {
+        let user = self.user_mut(tenant, email)?;
+        user.password_reset_required = true;
+        Ok(())
+    }
+
+    async fn set_password(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let user = self.user_mut(tenant, email)?;
+        user.password = password;
+        user.password_reset_required = false;
+        Ok(())
+    }
+
+    async fn reset_two_fa(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<(), UserStoreError> {
+        let user = self.user_mut(tenant, email)?;
+        user.phone_number = None;
+        user.two_fa_channel = TwoFAChannel::Email;
+        Ok(())
+    }
+
+    async fn delete_user(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<(), UserStoreError> {
+        self.users
+            .remove(&(tenant.clone(), email.clone()))
+            .map(|_| ())
+            .ok_or(UserStoreError::UserNotFound)
+    }
 }
 
 // TODO: Add unit tests for your `HashmapUserStore` implementation
@@ -65,50 +207,286 @@ mod tests {
     #[tokio::test]
     async fn test_add_user() {
         let mut user_store = HashmapUserStore::default();
-        let user = User::new(Email::parse(Secret::new("garik@garik.com".to_string())).unwrap(), Password::parse(Secret::new("kirag1234".to_string())).unwrap(), false);
+        let tenant = TenantId::default();
+        let user = User::new(
+            Email::parse(Secret::new("garik@garik.com".to_string())).unwrap(),
+            Password::parse(Secret::new("kirag1234".to_string())).unwrap(),
+            false,
+        );
 
-        let result = user_store.add_user(user.clone()).await;
+        let result = user_store.add_user(&tenant, user.clone()).await;
         assert!(result.is_ok());
 
-        let result = user_store.add_user(user.clone()).await;
+        let result = user_store.add_user(&tenant, user.clone()).await;
         assert_eq!(result, Err(UserStoreError::UserAlreadyExists));
     }
 
     #[tokio::test]
     async fn test_get_user() {
         let mut user_store = HashmapUserStore::default();
-        let user = User::new(Email::parse(Secret::new("test@test.com".to_string())).unwrap(), Password::parse(Secret::new("test1234".to_string())).unwrap(), false);
+        let tenant = TenantId::default();
+        let user = User::new(
+            Email::parse(Secret::new("test@test.com".to_string())).unwrap(),
+            Password::parse(Secret::new("test1234".to_string())).unwrap(),
+            false,
+        );
 
-        user_store.users.insert(user.email.clone(), user.clone());
-        let result = user_store.get_user(&user.email).await;
+        user_store
+            .users
+            .insert((tenant.clone(), user.email.clone()), user.clone());
+        let result = user_store.get_user(&tenant, &user.email).await;
         assert_eq!(result, Ok(user));
 
         let bad_user = Email::parse(Secret::new("noway@jose.com".to_string())).unwrap();
-        let result = user_store.get_user(&bad_user).await;
+        let result = user_store.get_user(&tenant, &bad_user).await;
         assert_eq!(result, Err(UserStoreError::UserNotFound));
     }
 
     #[tokio::test]
     async fn test_validate_user() {
         let mut user_store = HashmapUserStore::default();
+        let tenant = TenantId::default();
         let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
         let password = Password::parse(Secret::new("password".to_owned())).unwrap();
         let user = User::new(email.clone(), password.clone(), false);
 
         // Test validating a user that exists with correct password
-        user_store.users.insert(email.clone(), user.clone());
-        let result = user_store.validate_user(&email, &password).await;
+        user_store
+            .users
+            .insert((tenant.clone(), email.clone()), user.clone());
+        let result = user_store.validate_user(&tenant, &email, &password).await;
         assert_eq!(result, Ok(()));
 
         // Test validating a user that exists with incorrect password
         let wrong_password = Password::parse(Secret::new("incorrect".to_owned())).unwrap();
-        let result = user_store.validate_user(&email, &wrong_password).await;
+        let result = user_store
+            .validate_user(&tenant, &email, &wrong_password)
+            .await;
         assert_eq!(result, Err(UserStoreError::InvalidCredentials));
 
         // Test validating a user that doesn't exist
         let bad_user = Email::parse(Secret::new("nope@no.com".to_string())).unwrap();
-        let result = user_store.validate_user(&bad_user, &password).await;
+        let result = user_store
+            .validate_user(&tenant, &bad_user, &password)
+            .await;
 
         assert_eq!(result, Err(UserStoreError::UserNotFound));
     }
+
+    #[tokio::test]
+    async fn test_set_phone_number_and_channel() {
+        let mut user_store = HashmapUserStore::default();
+        let tenant = TenantId::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let password = Password::parse(Secret::new("password".to_owned())).unwrap();
+        let phone_number = PhoneNumber::parse(Secret::new("+14155552671".to_owned())).unwrap();
+        user_store.users.insert(
+            (tenant.clone(), email.clone()),
+            User::new(email.clone(), password, true),
+        );
+
+        let result = user_store
+            .set_phone_number(&tenant, &email, phone_number.clone())
+            .await;
+        assert_eq!(result, Ok(()));
+
+        let result = user_store
+            .set_two_fa_channel(&tenant, &email, TwoFAChannel::Sms)
+            .await;
+        assert_eq!(result, Ok(()));
+
+        let user = user_store.get_user(&tenant, &email).await.unwrap();
+        assert_eq!(user.phone_number, Some(phone_number.clone()));
+        assert_eq!(user.two_fa_channel, TwoFAChannel::Sms);
+
+        let bad_user = Email::parse(Secret::new("nope@no.com".to_string())).unwrap();
+        let result = user_store
+            .set_phone_number(&tenant, &bad_user, phone_number)
+            .await;
+        assert_eq!(result, Err(UserStoreError::UserNotFound));
+    }
+
+    #[tokio::test]
+    async fn test_list_users_searches_and_paginates() {
+        let mut user_store = HashmapUserStore::default();
+        let tenant = TenantId::default();
+        let password = Password::parse(Secret::new("password".to_owned())).unwrap();
+        for address in [
+            "carol@test.com",
+            "alice@test.com",
+            "bob@other.com",
+            "Dave@Test.com",
+        ] {
+            let email = Email::parse(Secret::new(address.to_owned())).unwrap();
+            user_store
+                .add_user(&tenant, User::new(email, password.clone(), false))
+                .await
+                .unwrap();
+        }
+
+        let emails = |page: &UserPage| -> Vec<String> {
+            page.users
+                .iter()
+                .map(|user| user.email.as_ref().expose_secret().to_owned())
+                .collect()
+        };
+
+        let query = UserQuery {
+            search: Some("test.com".to_owned()),
+            offset: 0,
+            limit: 2,
+        };
+        let page = user_store.list_users(&tenant, &query).await.unwrap();
+        assert_eq!(page.total, 3);
+        assert_eq!(emails(&page), vec!["Dave@Test.com", "alice@test.com"]);
+
+        let query = UserQuery { offset: 2, ..query };
+        let page = user_store.list_users(&tenant, &query).await.unwrap();
+        assert_eq!(emails(&page), vec!["carol@test.com"]);
+
+        let query = UserQuery {
+            search: None,
+            offset: 0,
+            limit: 10,
+        };
+        assert_eq!(
+            user_store.list_users(&tenant, &query).await.unwrap().total,
+            4
+        );
+    }
+
+    #[tokio::test]
+    async fn test_admin_account_controls() {
+        let mut user_store = HashmapUserStore::default();
+        let tenant = TenantId::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let password = Password::parse(Secret::new("password".to_owned())).unwrap();
+        let phone_number = PhoneNumber::parse(Secret::new("+14155552671".to_owned())).unwrap();
+        let mut user = User::new(email.clone(), password, true);
+        user.phone_number = Some(phone_number);
+        user.two_fa_channel = TwoFAChannel::Sms;
+        user_store.add_user(&tenant, user).await.unwrap();
+
+        user_store
+            .set_status(
+                &tenant,
+                &email,
+                AccountStatus::Suspended,
+                Some("Spam".to_owned()),
+                Some(SuspendedBy::Admin),
+            )
+            .await
+            .unwrap();
+        user_store
+            .require_password_reset(&tenant, &email)
+            .await
+            .unwrap();
+        user_store.reset_two_fa(&tenant, &email).await.unwrap();
+
+        let user = user_store.get_user(&tenant, &email).await.unwrap();
+        assert_eq!(user.status, AccountStatus::Suspended);
+        assert_eq!(user.status_reason.as_deref(), Some("Spam"));
+        assert_eq!(user.suspended_by, Some(SuspendedBy::Admin));
+        assert!(user.password_reset_required);
+        assert_eq!(user.phone_number, None);
+        assert_eq!(user.two_fa_channel, TwoFAChannel::Email);
+        assert!(user.requires_2fa);
+
+        let new_password = Password::parse(Secret::new("newpassword".to_owned())).unwrap();
+        user_store
+            .set_password(&tenant, &email, new_password.clone())
+            .await
+            .unwrap();
+        assert_eq!(
+            user_store
+                .validate_user(&tenant, &email, &new_password)
+                .await,
+            Ok(())
+        );
+        assert!(
+            !user_store
+                .get_user(&tenant, &email)
+                .await
+                .unwrap()
+                .password_reset_required
+        );
+
+        let bad_user = Email::parse(Secret::new("nope@no.com".to_string())).unwrap();
+        assert_eq!(
+            user_store
+                .set_status(&tenant, &bad_user, AccountStatus::Suspended, None, None)
+                .await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_delete_user() {
+        let mut user_store = HashmapUserStore::default();
+        let tenant = TenantId::default();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        user_store
+            .add_user(&tenant, User::provisioned(email.clone()))
+            .await
+            .unwrap();
+
+        assert_eq!(user_store.delete_user(&tenant, &email).await, Ok(()));
+        assert_eq!(
+            user_store.get_user(&tenant, &email).await,
+            Err(UserStoreError::UserNotFound)
+        );
+        assert_eq!(
+            user_store.delete_user(&tenant, &email).await,
+            Err(UserStoreError::UserNotFound)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_users_are_scoped_to_their_tenant() {
+        let mut user_store = HashmapUserStore::default();
+        let acme = TenantId::parse("acme".to_owned()).unwrap();
+        let globex = TenantId::parse("globex".to_owned()).unwrap();
+        let email = Email::parse(Secret::new("test@test.com".to_owned())).unwrap();
+        let password = Password::parse(Secret::new("password".to_owned())).unwrap();
+        let other_password = Password::parse(Secret::new("other-password".to_owned())).unwrap();
+
+        user_store
+            .add_user(&acme, User::new(email.clone(), password.clone(), false))
+            .await
+            .unwrap();
+        user_store
+            .add_user(
+                &globex,
+                User::new(email.clone(), other_password.clone(), true),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            user_store.validate_user(&acme, &email, &password).await,
+            Ok(())
+        );
+        assert_eq!(
+            user_store.validate_user(&globex, &email, &password).await,
+            Err(UserStoreError::InvalidCredentials)
+        );
+        assert!(
+            user_store
+                .get_user(&globex, &email)
+                .await
+                .unwrap()
+                .requires_2fa
+        );
+        assert_eq!(
+            user_store.get_user(&TenantId::default(), &email).await,
+            Err(UserStoreError::UserNotFound)
+        );
+
+        let query = UserQuery {
+            search: None,
+            offset: 0,
+            limit: 10,
+        };
+        assert_eq!(user_store.list_users(&acme, &query).await.unwrap().total, 1);
+    }
 }
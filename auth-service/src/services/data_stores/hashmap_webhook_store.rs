@@ -0,0 +1,229 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::domain::{
+    data_stores::{WebhookStore, WebhookStoreError},
+    TenantId, Webhook, WebhookDelivery, WebhookDeliveryId, WebhookDeliveryQuery,
+    WebhookDeliveryStatus, WebhookId,
+};
+
+#[derive(Default)]
+pub struct HashmapWebhookStore {
+    webhooks: HashMap<(TenantId, WebhookId), Webhook>,
+    deliveries: HashMap<WebhookDeliveryId, WebhookDelivery>,
+}
+
+#[async_trait::async_trait]
+impl WebhookStore for HashmapWebhookStore {
+    async fn add_webhook(&mut self, webhook: Webhook) -> Result<(), WebhookStoreError> {
+        self.webhooks
+            .insert((webhook.tenant.clone(), webhook.id), webhook);
+        Ok(())
+    }
+
+    async fn get_webhook(
+        &self,
+        tenant: &TenantId,
+        id: &WebhookId,
+    ) -> Result<Webhook, WebhookStoreError> {
+        self.webhooks
+            .get(&(tenant.clone(), *id))
+            .cloned()
+            .ok_or(WebhookStoreError::WebhookNotFound)
+    }
+
+    async fn list_webhooks(&self, tenant: &TenantId) -> Result<Vec<Webhook>, WebhookStoreError> {
+        let mut webhooks: Vec<Webhook> = self
+            .webhooks
+            .values()
+            .filter(|webhook| webhook.tenant == *tenant)
+            .cloned()
+            .collect();
+        webhooks.sort_by_key(|webhook| webhook.created_at);
+        Ok(webhooks)
+    }
+
+    async fn delete_webhook(
+        &mut self,
+        tenant: &TenantId,
+        id: &WebhookId,
+    ) -> Result<(), WebhookStoreError> {
+        self.webhooks
+            .remove(&(tenant.clone(), *id))
+            .ok_or(WebhookStoreError::WebhookNotFound)?;
+        self.deliveries
+            .retain(|_, delivery| !(delivery.tenant == *tenant && delivery.webhook_id == *id));
+        Ok(())
+    }
+
+    async fn add_delivery(&mut self, delivery: WebhookDelivery) -> Result<(), WebhookStoreError> {
+        self.deliveries.insert(delivery.id, delivery);
+        Ok(())
+    }
+
+    async fn get_delivery(
+        &self,
+        tenant: &TenantId,
+        id: &WebhookDeliveryId,
+    ) -> Result<WebhookDelivery, WebhookStoreError> {
+        self.deliveries
+            .get(id)
+            .filter(|delivery| delivery.tenant == *tenant)
+            .cloned()
+            .ok_or(WebhookStoreError::DeliveryNotFound)
+    }
+
+    async fn list_deliveries(
+        &self,
+        tenant: &TenantId,
+        query: &WebhookDeliveryQuery,
+    ) -> Result<Vec<WebhookDelivery>, WebhookStoreError> {
+        let mut deliveries: Vec<&WebhookDelivery> = self
+            .deliveries
+            .values()
+            .filter(|delivery| delivery.tenant == *tenant && query.matches(delivery))
+            .collect();
+        deliveries.sort_by_key(|delivery| std::cmp::Reverse(delivery.created_at));
+
+        Ok(deliveries
+            .into_iter()
+            .skip(query.offset as usize)
+            .take(query.limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn claim_due_deliveries(
+        &mut self,
+        now: DateTime<Utc>,
+        lease: Duration,
+        limit: u64,
+    ) -> Result<Vec<WebhookDelivery>, WebhookStoreError> {
+        let mut due: Vec<&mut WebhookDelivery> = self
+            .deliveries
+            .values_mut()
+            .filter(|delivery| {
+                delivery.status == WebhookDeliveryStatus::Pending && delivery.next_attempt_at <= now
+            })
+            .collect();
+        due.sort_by_key(|delivery| delivery.next_attempt_at);
+
+        Ok(due
+            .into_iter()
+            .take(limit as usize)
+            .map(|delivery| {
+                delivery.next_attempt_at = now + lease;
+                delivery.clone()
+            })
+            .collect())
+    }
+
+    async fn update_delivery(
+        &mut self,
+        delivery: &WebhookDelivery,
+    ) -> Result<(), WebhookStoreError> {
+        let existing = self
+            .deliveries
+            .get_mut(&delivery.id)
+            .ok_or(WebhookStoreError::DeliveryNotFound)?;
+        *existing = delivery.clone();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::WebhookEventType;
+
+    fn webhook(tenant: &TenantId) -> Webhook {
+        Webhook::new(
+            tenant.clone(),
+            "https://crm.example.com/hooks".to_owned(),
+            vec![WebhookEventType::Signup],
+        )
+        .unwrap()
+    }
+
+    fn delivery(webhook: &Webhook) -> WebhookDelivery {
+        WebhookDelivery::new(webhook, WebhookEventType::Signup, "{}".to_owned())
+    }
+
+    #[tokio::test]
+    async fn test_claimed_deliveries_are_not_claimed_again_until_the_lease_ends() {
+        let mut store = HashmapWebhookStore::default();
+        let webhook = webhook(&TenantId::default());
+        store.add_webhook(webhook.clone()).await.unwrap();
+        let first = delivery(&webhook);
+        let mut delivered = delivery(&webhook);
+        delivered.record_success(200);
+        store.add_delivery(first.clone()).await.unwrap();
+        store.add_delivery(delivered).await.unwrap();
+
+        let now = Utc::now();
+        let claimed = store
+            .claim_due_deliveries(now, Duration::minutes(5), 10)
+            .await
+            .unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(claimed[0].id, first.id);
+
+        assert!(store
+            .claim_due_deliveries(now, Duration::minutes(5), 10)
+            .await
+            .unwrap()
+            .is_empty());
+        let later = now + Duration::minutes(6);
+        assert_eq!(
+            store
+                .claim_due_deliveries(later, Duration::minutes(5), 10)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_deleting_a_webhook_deletes_its_deliveries() {
+        let mut store = HashmapWebhookStore::default();
+        let tenant = TenantId::default();
+        let other_tenant = TenantId::parse("other".to_owned()).unwrap();
+        let webhook = webhook(&tenant);
+        let other = self::webhook(&other_tenant);
+        store.add_webhook(webhook.clone()).await.unwrap();
+        store.add_webhook(other.clone()).await.unwrap();
+        let removed = delivery(&webhook);
+        store.add_delivery(removed.clone()).await.unwrap();
+        store.add_delivery(delivery(&other)).await.unwrap();
+
+        assert_eq!(
+            store.delete_webhook(&other_tenant, &webhook.id).await,
+            Err(WebhookStoreError::WebhookNotFound)
+        );
+        store.delete_webhook(&tenant, &webhook.id).await.unwrap();
+
+        assert_eq!(
+            store.get_delivery(&tenant, &removed.id).await,
+            Err(WebhookStoreError::DeliveryNotFound)
+        );
+        let query = WebhookDeliveryQuery {
+            limit: 10,
+            ..WebhookDeliveryQuery::default()
+        };
+        assert!(store
+            .list_deliveries(&tenant, &query)
+            .await
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            store
+                .list_deliveries(&other_tenant, &query)
+                .await
+                .unwrap()
+                .len(),
+            1
+        );
+    }
+}
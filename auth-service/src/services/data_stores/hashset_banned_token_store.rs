@@ -1,12 +1,13 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
 use secrecy::{ExposeSecret, Secret};
 
-use crate::domain::{BannedTokenStore, BannedTokenStoreError};
+use crate::domain::{BannedTokenStore, BannedTokenStoreError, Email, TenantId};
 
 #[derive(Default)]
 pub struct HashsetBannedTokenStore {
     banned_tokens: HashSet<String>,
+    banned_users: HashMap<(TenantId, Email), i64>,
 }
 
 #[async_trait::async_trait]
@@ -16,9 +17,34 @@ impl BannedTokenStore for HashsetBannedTokenStore {
         Ok(())
     }
 
-    async fn check_if_token_is_banned(&self, token: &Secret<String>) -> Result<bool, BannedTokenStoreError> {
+    async fn check_if_token_is_banned(
+        &self,
+        token: &Secret<String>,
+    ) -> Result<bool, BannedTokenStoreError> {
         Ok(self.banned_tokens.contains(token.expose_secret()))
     }
+
+    async fn ban_user_tokens(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        issued_up_to: i64,
+    ) -> Result<(), BannedTokenStoreError> {
+        self.banned_users
+            .insert((tenant.clone(), email.clone()), issued_up_to);
+        Ok(())
+    }
+
+    async fn get_user_tokens_ban(
+        &self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<Option<i64>, BannedTokenStoreError> {
+        Ok(self
+            .banned_users
+            .get(&(tenant.clone(), email.clone()))
+            .copied())
+    }
 }
 
 #[cfg(test)]
@@ -42,7 +68,9 @@ mod tests {
         let banned_token = Secret::new("test_token".to_owned());
         let token = Secret::new("this should fail".to_owned());
 
-        store.banned_tokens.insert(banned_token.expose_secret().clone());
+        store
+            .banned_tokens
+            .insert(banned_token.expose_secret().clone());
 
         let banned_result = store.check_if_token_is_banned(&banned_token).await.unwrap();
         let allowed_result = store.check_if_token_is_banned(&token).await.unwrap();
@@ -50,5 +78,35 @@ mod tests {
         assert!(banned_result);
         assert!(!allowed_result);
     }
-}
 
+    #[tokio::test]
+    async fn test_ban_user_tokens() {
+        let mut store = HashsetBannedTokenStore::default();
+        let tenant = TenantId::default();
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+
+        assert_eq!(
+            store.get_user_tokens_ban(&tenant, &email).await.unwrap(),
+            None
+        );
+
+        store
+            .ban_user_tokens(&tenant, &email, 1_700_000_000)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            store.get_user_tokens_ban(&tenant, &email).await.unwrap(),
+            Some(1_700_000_000)
+        );
+
+        let other_tenant = TenantId::parse("acme".to_owned()).unwrap();
+        assert_eq!(
+            store
+                .get_user_tokens_ban(&other_tenant, &email)
+                .await
+                .unwrap(),
+            None
+        );
+    }
+}
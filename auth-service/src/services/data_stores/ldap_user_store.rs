@@ -0,0 +1,270 @@
+use std::time::Duration;
+
+use color_eyre::eyre::eyre;
+use ldap3::{ldap_escape, Ldap, LdapConnAsync, LdapConnSettings, LdapError, Scope, SearchEntry};
+use secrecy::{ExposeSecret, Secret};
+
+use crate::domain::{
+    data_stores::{UserStore, UserStoreError},
+    AccountStatus, Email, Password, PhoneNumber, SuspendedBy, TenantId, TwoFAChannel, User,
+    UserPage, UserQuery,
+};
+
+// LDAP's result code for a failed bind
+const INVALID_CREDENTIALS: u32 = 49;
+
+#[derive(Debug, Clone)]
+pub struct LdapSettings {
+    // e.g. `ldaps://ldap.example.com`
+    pub url: String,
+    // Where users are searched, e.g. `ou=people,dc=example,dc=com`
+    pub base_dn: String,
+    // `{email}` is replaced with the escaped email, e.g. `(&(objectClass=person)(mail={email}))`
+    pub user_filter: String,
+    // The account searches run as; anonymous when not set
+    pub bind_dn: Option<String>,
+    pub bind_password: Secret<String>,
+    // The directory's users all belong to this tenant
+    pub tenant: TenantId,
+    pub timeout: Duration,
+}
+
+// Users kept in an LDAP directory. `get_user` looks them up with a search and
+// `validate_user` checks their password by binding as them. The directory is never
+// written to, so everything else fails with `UserStoreError::ReadOnly`; use
+// `CompositeUserStore` to keep local state for directory users.
+pub struct LdapUserStore {
+    settings: LdapSettings,
+}
+
+impl LdapUserStore {
+    pub fn new(settings: LdapSettings) -> Self {
+        Self { settings }
+    }
+
+    pub fn tenant(&self) -> &TenantId {
+        &self.settings.tenant
+    }
+
+    // Opens a connection bound as the search account
+    async fn connect(&self) -> Result<Ldap, LdapError> {
+        let conn_settings = LdapConnSettings::new().set_conn_timeout(self.settings.timeout);
+        let (conn, mut ldap) =
+            LdapConnAsync::with_settings(conn_settings, &self.settings.url).await?;
+        ldap3::drive!(conn);
+
+        if let Some(bind_dn) = &self.settings.bind_dn {
+            ldap.with_timeout(self.settings.timeout)
+                .simple_bind(bind_dn, self.settings.bind_password.expose_secret())
+                .await?
+                .success()?;
+        }
+
+        Ok(ldap)
+    }
+
+    fn filter(&self, email: &Email) -> String {
+        self.settings.user_filter.replace(
+            "{email}",
+            &ldap_escape(email.as_ref().expose_secret().as_str()),
+        )
+    }
+
+    // The DN of the user's entry. More than one match is treated as an error rather than
+    // picking one, since binding as the wrong entry would sign in the wrong person.
+    async fn find_dn(&self, ldap: &mut Ldap, email: &Email) -> Result<String, UserStoreError> {
+        let (mut entries, _) = ldap
+            .with_timeout(self.settings.timeout)
+            .search(
+                &self.settings.base_dn,
+                Scope::Subtree,
+                &self.filter(email),
+                vec!["1.1"],
+            )
+            .await
+            .and_then(|result| result.success())
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        match entries.len() {
+            0 => Err(UserStoreError::UserNotFound),
+            1 => Ok(SearchEntry::construct(entries.remove(0)).dn),
+            n => Err(UserStoreError::UnexpectedError(eyre!(
+                "{} directory entries match the user filter",
+                n
+            ))),
+        }
+    }
+
+    async fn lookup(
+        &self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<(Ldap, String), UserStoreError> {
+        if *tenant != self.settings.tenant {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        let mut ldap = self
+            .connect()
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+        let dn = self.find_dn(&mut ldap, email).await;
+
+        match dn {
+            Ok(dn) => Ok((ldap, dn)),
+            Err(e) => {
+                let _ = ldap.unbind().await;
+                Err(e)
+            }
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl UserStore for LdapUserStore {
+    async fn add_user(&mut self, _tenant: &TenantId, _user: User) -> Result<(), UserStoreError> {
+        Err(UserStoreError::ReadOnly)
+    }
+
+    #[tracing::instrument(name = "Retrieving user from LDAP", skip_all)]
+    async fn get_user(&self, tenant: &TenantId, email: &Email) -> Result<User, UserStoreError> {
+        let (mut ldap, _) = self.lookup(tenant, email).await?;
+        let _ = ldap.unbind().await;
+
+        Ok(User::from_directory(email.clone()))
+    }
+
+    #[tracing::instrument(name = "Validating user credentials in LDAP", skip_all)]
+    async fn validate_user(
+        &self,
+        tenant: &TenantId,
+        email: &Email,
+        password: &Password,
+    ) -> Result<(), UserStoreError> {
+        let (mut ldap, dn) = self.lookup(tenant, email).await?;
+
+        // An empty password would make this an unauthenticated bind, which servers accept
+        let password = password.as_ref().expose_secret();
+        if password.is_empty() {
+            let _ = ldap.unbind().await;
+            return Err(UserStoreError::InvalidCredentials);
+        }
+
+        let result = ldap
+            .with_timeout(self.settings.timeout)
+            .simple_bind(&dn, password)
+            .await;
+        let _ = ldap.unbind().await;
+
+        match result {
+            Ok(result) if result.rc == 0 => Ok(()),
+            Ok(result) if result.rc == INVALID_CREDENTIALS => {
+                Err(UserStoreError::InvalidCredentials)
+            }
+            Ok(result) => Err(UserStoreError::UnexpectedError(eyre!(
+                "LDAP bind failed: {}",
+                result
+            ))),
+            Err(e) => Err(UserStoreError::UnexpectedError(e.into())),
+        }
+    }
+
+    async fn set_phone_number(
+        &mut self,
+        _tenant: &TenantId,
+        _email: &Email,
+        _phone_number: PhoneNumber,
+    ) -> Result<(), UserStoreError> {
+        Err(UserStoreError::ReadOnly)
+    }
+
+    async fn set_two_fa_channel(
+        &mut self,
+        _tenant: &TenantId,
+        _email: &Email,
+        _channel: TwoFAChannel,
+    ) -> Result<(), UserStoreError> {
+        Err(UserStoreError::ReadOnly)
+    }
+
+    // Directories can be huge, so they aren't listed
+    async fn list_users(
+        &self,
+        _tenant: &TenantId,
+        _query: &UserQuery,
+    ) -> Result<UserPage, UserStoreError> {
+        Ok(UserPage {
+            users: vec![],
+            total: 0,
+        })
+    }
+
+    async fn set_status(
+        &mut self,
+        _tenant: &TenantId,
+        _email: &Email,
+        _status: AccountStatus,
+        _reason: Option<String>,
+        _suspended_by: Option<SuspendedBy>,
+    ) -> Result<(), UserStoreError> {
+        Err(UserStoreError::ReadOnly)
+    }
+
+    async fn require_password_reset(
+        &mut self,
+        _tenant: &TenantId,
+        _email: &Email,
+    ) -> Result<(), UserStoreError> {
+        Err(UserStoreError::ReadOnly)
+    }
+
+    async fn set_password(
+        &mut self,
+        _tenant: &TenantId,
+        _email: &Email,
+        _password: Password,
+    ) -> Result<(), UserStoreError> {
+        Err(UserStoreError::ReadOnly)
+    }
+
+    async fn reset_two_fa(
+        &mut self,
+        _tenant: &TenantId,
+        _email: &Email,
+    ) -> Result<(), UserStoreError> {
+        Err(UserStoreError::ReadOnly)
+    }
+
+    async fn delete_user(
+        &mut self,
+        _tenant: &TenantId,
+        _email: &Email,
+    ) -> Result<(), UserStoreError> {
+        Err(UserStoreError::ReadOnly)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_filter_escapes_email() {
+        let store = LdapUserStore::new(LdapSettings {
+            url: "ldap://localhost".to_owned(),
+            base_dn: "dc=example,dc=com".to_owned(),
+            user_filter: "(&(objectClass=person)(mail={email}))".to_owned(),
+            bind_dn: None,
+            bind_password: Secret::new(String::new()),
+            tenant: TenantId::default(),
+            timeout: Duration::from_secs(1),
+        });
+
+        // A wildcard would otherwise match someone else's entry
+        let email = Email::parse(Secret::new("*@example.com".to_owned())).unwrap();
+        assert_eq!(
+            store.filter(&email),
+            r"(&(objectClass=person)(mail=\2a@example.com))"
+        );
+    }
+}
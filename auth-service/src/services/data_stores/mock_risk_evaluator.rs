@@ -0,0 +1,20 @@
+use crate::domain::{LoginContext, RiskAssessment, RiskEvaluator};
+use color_eyre::eyre::Result;
+
+// Treats every login as low risk, leaving the decision to the user's 2FA setting
+pub struct MockRiskEvaluator;
+
+#[async_trait::async_trait]
+impl RiskEvaluator for MockRiskEvaluator {
+    async fn assess(&self, _context: &LoginContext) -> Result<RiskAssessment> {
+        Ok(RiskAssessment::low())
+    }
+
+    async fn record_success(&mut self, _context: &LoginContext) -> Result<()> {
+        Ok(())
+    }
+
+    async fn record_failure(&mut self, _context: &LoginContext) -> Result<()> {
+        Ok(())
+    }
+}
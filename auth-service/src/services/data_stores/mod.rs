@@ -1,7 +1,43 @@
+pub mod composite_user_store;
+pub mod hashmap_api_key_store;
+pub mod hashmap_device_authorization_store;
+pub mod hashmap_federated_identity_store;
+pub mod hashmap_group_store;
+pub mod hashmap_identity_provider_store;
+pub mod hashmap_invitation_store;
+pub mod hashmap_magic_link_store;
+pub mod hashmap_oauth_client_store;
+pub mod hashmap_phone_verification_store;
+pub mod hashmap_role_store;
+pub mod hashmap_saml_provider_store;
+pub mod hashmap_saml_replay_cache;
+pub mod hashmap_tenant_store;
+pub mod hashmap_trusted_device_store;
+pub mod hashmap_two_fa_code_store;
 pub mod hashmap_user_store;
+pub mod hashmap_webhook_store;
 pub mod hashset_banned_token_store;
-pub mod hashmap_two_fa_code_store;
+pub mod ldap_user_store;
 pub mod mock_email_client;
+pub mod mock_risk_evaluator;
+pub mod mock_sms_client;
+pub mod postgres_api_key_store;
+pub mod postgres_audit_log;
+pub mod postgres_federated_identity_store;
+pub mod postgres_group_store;
+pub mod postgres_identity_provider_store;
+pub mod postgres_invitation_store;
+pub mod postgres_oauth_client_store;
+pub mod postgres_role_store;
+pub mod postgres_saml_provider_store;
+pub mod postgres_tenant_store;
+pub mod postgres_trusted_device_store;
 pub mod postgres_user_store;
+pub mod postgres_webhook_store;
 pub mod redis_banned_token_store;
-pub mod redis_two_fa_code_store;
\ No newline at end of file
+pub mod redis_device_authorization_store;
+pub mod redis_magic_link_store;
+pub mod redis_phone_verification_store;
+pub mod redis_saml_replay_cache;
+pub mod redis_two_fa_code_store;
+pub mod vec_audit_log;
@@ -0,0 +1,175 @@
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    domain::{
+        data_stores::{ApiKeyStore, ApiKeyStoreError},
+        ApiKey, ApiKeyId, Email, Permission, TenantId,
+    },
+    utils::hashing::{compute_api_key_hash, verify_api_key_hash},
+};
+
+pub struct PostgresApiKeyStore {
+    pool: PgPool,
+}
+
+impl PostgresApiKeyStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl ApiKeyStore for PostgresApiKeyStore {
+    #[tracing::instrument(name = "Adding API key to PostgreSQL", skip_all)]
+    async fn add_key(
+        &mut self,
+        api_key: ApiKey,
+        key: Secret<String>,
+    ) -> Result<(), ApiKeyStoreError> {
+        let key_hash = compute_api_key_hash(&key);
+        let scopes: Vec<String> = api_key
+            .scopes
+            .iter()
+            .map(|scope| scope.as_ref().to_owned())
+            .collect();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO api_keys (id, tenant_id, email, name, prefix, key_hash, scopes, created_at,
+                                  expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+            "#,
+            api_key.id.as_ref(),
+            api_key.tenant.as_ref(),
+            api_key.email.as_ref().expose_secret(),
+            api_key.name,
+            api_key.prefix,
+            key_hash,
+            &scopes,
+            api_key.created_at,
+            api_key.expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Validating API key in PostgreSQL", skip_all)]
+    async fn validate_key(&self, key: &Secret<String>) -> Result<ApiKey, ApiKeyStoreError> {
+        let prefix =
+            ApiKey::prefix_of(key.expose_secret()).ok_or(ApiKeyStoreError::InvalidApiKey)?;
+
+        let row = sqlx::query!(
+            r#"
+            SELECT id, tenant_id, email, name, prefix, key_hash, scopes, created_at, expires_at
+            FROM api_keys
+            WHERE prefix = $1
+            "#,
+            prefix,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?
+        .ok_or(ApiKeyStoreError::InvalidApiKey)?;
+
+        if !verify_api_key_hash(&row.key_hash, key) {
+            return Err(ApiKeyStoreError::InvalidApiKey);
+        }
+
+        to_api_key(ApiKeyRow {
+            id: row.id,
+            tenant_id: row.tenant_id,
+            email: row.email,
+            name: row.name,
+            prefix: row.prefix,
+            scopes: row.scopes,
+            created_at: row.created_at,
+            expires_at: row.expires_at,
+        })
+    }
+
+    #[tracing::instrument(name = "Listing API keys from PostgreSQL", skip_all)]
+    async fn list_keys(
+        &self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<Vec<ApiKey>, ApiKeyStoreError> {
+        let rows = sqlx::query_as!(
+            ApiKeyRow,
+            r#"
+            SELECT id, tenant_id, email, name, prefix, scopes, created_at, expires_at
+            FROM api_keys
+            WHERE tenant_id = $1 AND email = $2 AND expires_at > now()
+            ORDER BY created_at
+            "#,
+            tenant.as_ref(),
+            email.as_ref().expose_secret(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter().map(to_api_key).collect()
+    }
+
+    #[tracing::instrument(name = "Revoking API key in PostgreSQL", skip_all)]
+    async fn revoke_key(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        id: &ApiKeyId,
+    ) -> Result<(), ApiKeyStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM api_keys
+            WHERE id = $1 AND tenant_id = $2 AND email = $3
+            "#,
+            id.as_ref(),
+            tenant.as_ref(),
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| ApiKeyStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(ApiKeyStoreError::ApiKeyNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+struct ApiKeyRow {
+    id: Uuid,
+    tenant_id: String,
+    email: String,
+    name: String,
+    prefix: String,
+    scopes: Vec<String>,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+fn to_api_key(row: ApiKeyRow) -> Result<ApiKey, ApiKeyStoreError> {
+    Ok(ApiKey {
+        id: ApiKeyId::from(row.id),
+        tenant: TenantId::parse(row.tenant_id).map_err(ApiKeyStoreError::UnexpectedError)?,
+        email: Email::parse(Secret::new(row.email)).map_err(ApiKeyStoreError::UnexpectedError)?,
+        name: row.name,
+        prefix: row.prefix,
+        scopes: row
+            .scopes
+            .into_iter()
+            .map(Permission::parse)
+            .collect::<Result<_, _>>()
+            .map_err(ApiKeyStoreError::UnexpectedError)?,
+        created_at: row.created_at,
+        expires_at: row.expires_at,
+    })
+}
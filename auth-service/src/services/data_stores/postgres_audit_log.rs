@@ -0,0 +1,315 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::{Context, Report};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::{
+    data_stores::{AuditLog, AuditLogError},
+    AuditCheckpoint, AuditEvent, AuditEventId, AuditLink, AuditOutcome, AuditQuery, TenantId,
+    AUDIT_CHAIN_GENESIS_HASH,
+};
+
+// Held while an event is chained, so every instance of the service links to the same head
+const AUDIT_CHAIN_LOCK_KEY: i64 = 0x6175_6469_7463_6861;
+
+// Appends to the `audit_events` table, which refuses updates and deletes. Checkpoints
+// go in `audit_checkpoints`, which is append-only too.
+pub struct PostgresAuditLog {
+    pool: PgPool,
+}
+
+impl PostgresAuditLog {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl AuditLog for PostgresAuditLog {
+    #[tracing::instrument(name = "Appending audit event to PostgreSQL", skip_all)]
+    async fn append(&mut self, event: AuditEvent) -> Result<(), AuditLogError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| AuditLogError::UnexpectedError(e.into()))?;
+
+        // Released when the transaction ends, after the new head is visible
+        sqlx::query!("SELECT pg_advisory_xact_lock($1)", AUDIT_CHAIN_LOCK_KEY)
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| AuditLogError::UnexpectedError(e.into()))?;
+
+        let previous_hash = sqlx::query_scalar!(
+            r#"
+            SELECT hash AS "hash!"
+            FROM audit_events
+            WHERE hash IS NOT NULL
+            ORDER BY sequence DESC
+            LIMIT 1
+            "#,
+        )
+        .fetch_optional(&mut *transaction)
+        .await
+        .map_err(|e| AuditLogError::UnexpectedError(e.into()))?
+        .unwrap_or_else(|| AUDIT_CHAIN_GENESIS_HASH.to_owned());
+        let hash = event.chain_hash(&previous_hash);
+
+        sqlx::query!(
+            r#"
+            INSERT INTO audit_events (id, tenant_id, occurred_at, event, outcome, actor,
+                                      user_email, target, reason, ip_address, user_agent,
+                                      previous_hash, hash)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+            "#,
+            event.id.as_ref(),
+            event.tenant.as_ref(),
+            event.occurred_at,
+            event.event,
+            event.outcome.as_str(),
+            event.actor,
+            event.user,
+            event.target,
+            event.reason,
+            event.ip_address.map(|ip_address| ip_address.to_string()),
+            event.user_agent,
+            previous_hash,
+            hash,
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| AuditLogError::UnexpectedError(e.into()))?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| AuditLogError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Querying audit events from PostgreSQL", skip_all)]
+    async fn query(
+        &self,
+        tenant: &TenantId,
+        query: &AuditQuery,
+    ) -> Result<Vec<AuditEvent>, AuditLogError> {
+        let limit = i64::try_from(query.limit)
+            .wrap_err("page size is too large")
+            .map_err(AuditLogError::UnexpectedError)?;
+        let offset = i64::try_from(query.offset)
+            .wrap_err("page offset is too large")
+            .map_err(AuditLogError::UnexpectedError)?;
+
+        let rows = sqlx::query_as!(
+            AuditEventRow,
+            r#"
+            SELECT id, tenant_id, occurred_at, event, outcome, actor, user_email, target,
+                   reason, ip_address, user_agent
+            FROM audit_events
+            WHERE tenant_id = $1
+                AND ($2::TEXT IS NULL OR event = $2)
+                AND ($3::TEXT IS NULL OR actor = $3)
+                AND ($4::TEXT IS NULL OR user_email = $4)
+                AND ($5::TEXT IS NULL OR outcome = $5)
+                AND ($6::TIMESTAMPTZ IS NULL OR occurred_at >= $6)
+                AND ($7::TIMESTAMPTZ IS NULL OR occurred_at < $7)
+            ORDER BY sequence DESC
+            LIMIT $8 OFFSET $9
+            "#,
+            tenant.as_ref(),
+            query.event,
+            query.actor,
+            query.user,
+            query.outcome.map(|outcome| outcome.as_str()),
+            query.since,
+            query.until,
+            limit,
+            offset,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuditLogError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| to_audit_event(row).map_err(AuditLogError::UnexpectedError))
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Reading audit chain from PostgreSQL", skip_all)]
+    async fn chain(
+        &self,
+        after_sequence: i64,
+        limit: u64,
+    ) -> Result<Vec<AuditLink>, AuditLogError> {
+        let limit = i64::try_from(limit)
+            .wrap_err("page size is too large")
+            .map_err(AuditLogError::UnexpectedError)?;
+
+        let rows = sqlx::query_as!(
+            AuditLinkRow,
+            r#"
+            SELECT sequence, id, tenant_id, occurred_at, event, outcome, actor, user_email,
+                   target, reason, ip_address, user_agent, previous_hash, hash
+            FROM audit_events
+            WHERE sequence > $1
+            ORDER BY sequence
+            LIMIT $2
+            "#,
+            after_sequence,
+            limit,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuditLogError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| to_audit_link(row).map_err(AuditLogError::UnexpectedError))
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Reading audit chain head from PostgreSQL", skip_all)]
+    async fn chain_head(&self) -> Result<Option<AuditLink>, AuditLogError> {
+        let row = sqlx::query_as!(
+            AuditLinkRow,
+            r#"
+            SELECT sequence, id, tenant_id, occurred_at, event, outcome, actor, user_email,
+                   target, reason, ip_address, user_agent, previous_hash, hash
+            FROM audit_events
+            WHERE hash IS NOT NULL
+            ORDER BY sequence DESC
+            LIMIT 1
+            "#,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AuditLogError::UnexpectedError(e.into()))?;
+
+        row.map(|row| to_audit_link(row).map_err(AuditLogError::UnexpectedError))
+            .transpose()
+    }
+
+    // Another instance may have checkpointed the same head already
+    #[tracing::instrument(name = "Adding audit checkpoint to PostgreSQL", skip_all)]
+    async fn add_checkpoint(&mut self, checkpoint: AuditCheckpoint) -> Result<(), AuditLogError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO audit_checkpoints (sequence, hash, signed_at, signature)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT DO NOTHING
+            "#,
+            checkpoint.sequence,
+            checkpoint.hash,
+            checkpoint.signed_at,
+            checkpoint.signature,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| AuditLogError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving audit checkpoints from PostgreSQL", skip_all)]
+    async fn checkpoints(&self) -> Result<Vec<AuditCheckpoint>, AuditLogError> {
+        sqlx::query_as!(
+            AuditCheckpoint,
+            r#"
+            SELECT sequence, hash, signed_at, signature
+            FROM audit_checkpoints
+            ORDER BY sequence
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| AuditLogError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Retrieving latest audit checkpoint from PostgreSQL", skip_all)]
+    async fn latest_checkpoint(&self) -> Result<Option<AuditCheckpoint>, AuditLogError> {
+        sqlx::query_as!(
+            AuditCheckpoint,
+            r#"
+            SELECT sequence, hash, signed_at, signature
+            FROM audit_checkpoints
+            ORDER BY sequence DESC
+            LIMIT 1
+            "#,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| AuditLogError::UnexpectedError(e.into()))
+    }
+}
+
+struct AuditEventRow {
+    id: Uuid,
+    tenant_id: String,
+    occurred_at: DateTime<Utc>,
+    event: String,
+    outcome: String,
+    actor: Option<String>,
+    user_email: Option<String>,
+    target: Option<String>,
+    reason: Option<String>,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+}
+
+fn to_audit_event(row: AuditEventRow) -> Result<AuditEvent, Report> {
+    Ok(AuditEvent {
+        id: AuditEventId::from(row.id),
+        tenant: TenantId::parse(row.tenant_id)?,
+        occurred_at: row.occurred_at,
+        event: row.event,
+        outcome: AuditOutcome::parse(&row.outcome)?,
+        actor: row.actor,
+        user: row.user_email,
+        target: row.target,
+        reason: row.reason,
+        ip_address: row
+            .ip_address
+            .map(|ip_address| ip_address.parse())
+            .transpose()
+            .wrap_err("invalid IP address in the audit log")?,
+        user_agent: row.user_agent,
+    })
+}
+
+struct AuditLinkRow {
+    sequence: i64,
+    id: Uuid,
+    tenant_id: String,
+    occurred_at: DateTime<Utc>,
+    event: String,
+    outcome: String,
+    actor: Option<String>,
+    user_email: Option<String>,
+    target: Option<String>,
+    reason: Option<String>,
+    ip_address: Option<String>,
+    user_agent: Option<String>,
+    previous_hash: Option<String>,
+    hash: Option<String>,
+}
+
+fn to_audit_link(row: AuditLinkRow) -> Result<AuditLink, Report> {
+    let event = to_audit_event(AuditEventRow {
+        id: row.id,
+        tenant_id: row.tenant_id,
+        occurred_at: row.occurred_at,
+        event: row.event,
+        outcome: row.outcome,
+        actor: row.actor,
+        user_email: row.user_email,
+        target: row.target,
+        reason: row.reason,
+        ip_address: row.ip_address,
+        user_agent: row.user_agent,
+    })?;
+
+    Ok(AuditLink {
+        sequence: row.sequence,
+        event,
+        previous_hash: row.previous_hash,
+        hash: row.hash,
+    })
+}
@@ -0,0 +1,77 @@
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{FederatedIdentityStore, FederatedIdentityStoreError},
+    Email, FederatedIdentity, ProviderId, TenantId,
+};
+
+pub struct PostgresFederatedIdentityStore {
+    pool: PgPool,
+}
+
+impl PostgresFederatedIdentityStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl FederatedIdentityStore for PostgresFederatedIdentityStore {
+    #[tracing::instrument(name = "Adding federated identity to PostgreSQL", skip_all)]
+    async fn add_identity(
+        &mut self,
+        identity: FederatedIdentity,
+    ) -> Result<(), FederatedIdentityStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO federated_identities (tenant_id, provider_id, subject, email, created_at)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (tenant_id, provider_id, subject)
+            DO UPDATE SET email = EXCLUDED.email
+            "#,
+            identity.tenant.as_ref(),
+            identity.provider.as_ref(),
+            identity.subject,
+            identity.email.as_ref().expose_secret(),
+            identity.created_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| FederatedIdentityStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving federated identity from PostgreSQL", skip_all)]
+    async fn get_identity(
+        &self,
+        tenant: &TenantId,
+        provider: &ProviderId,
+        subject: &str,
+    ) -> Result<FederatedIdentity, FederatedIdentityStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT email, created_at
+            FROM federated_identities
+            WHERE tenant_id = $1 AND provider_id = $2 AND subject = $3
+            "#,
+            tenant.as_ref(),
+            provider.as_ref(),
+            subject,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| FederatedIdentityStoreError::UnexpectedError(e.into()))?
+        .ok_or(FederatedIdentityStoreError::IdentityNotFound)?;
+
+        Ok(FederatedIdentity {
+            tenant: tenant.clone(),
+            provider: provider.clone(),
+            subject: subject.to_owned(),
+            email: Email::parse(Secret::new(row.email))
+                .map_err(FederatedIdentityStoreError::UnexpectedError)?,
+            created_at: row.created_at,
+        })
+    }
+}
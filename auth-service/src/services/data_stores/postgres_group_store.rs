@@ -0,0 +1,229 @@
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::{PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::domain::{
+    data_stores::{GroupStore, GroupStoreError},
+    Email, Group, GroupId, TenantId,
+};
+
+pub struct PostgresGroupStore {
+    pool: PgPool,
+}
+
+impl PostgresGroupStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl GroupStore for PostgresGroupStore {
+    #[tracing::instrument(name = "Adding group to PostgreSQL", skip_all)]
+    async fn add_group(&mut self, group: Group) -> Result<(), GroupStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| GroupStoreError::UnexpectedError(e.into()))?;
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO groups (tenant_id, id, display_name, created_at)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT DO NOTHING
+            "#,
+            group.tenant.as_ref(),
+            group.id.as_ref(),
+            group.display_name,
+            group.created_at,
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| GroupStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(GroupStoreError::GroupAlreadyExists);
+        }
+
+        add_members(&mut transaction, &group).await?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| GroupStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Retrieving group from PostgreSQL", skip_all)]
+    async fn get_group(&self, tenant: &TenantId, id: &GroupId) -> Result<Group, GroupStoreError> {
+        let row = sqlx::query_as!(
+            GroupRow,
+            r#"
+            SELECT groups.tenant_id, groups.id, groups.display_name, groups.created_at,
+                   ARRAY_REMOVE(ARRAY_AGG(group_members.email ORDER BY group_members.email), NULL) AS "members!"
+            FROM groups
+            LEFT JOIN group_members
+                ON group_members.tenant_id = groups.tenant_id AND group_members.group_id = groups.id
+            WHERE groups.tenant_id = $1 AND groups.id = $2
+            GROUP BY groups.tenant_id, groups.id
+            "#,
+            tenant.as_ref(),
+            id.as_ref(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| GroupStoreError::UnexpectedError(e.into()))?
+        .ok_or(GroupStoreError::GroupNotFound)?;
+
+        to_group(row)
+    }
+
+    #[tracing::instrument(name = "Listing groups from PostgreSQL", skip_all)]
+    async fn list_groups(&self, tenant: &TenantId) -> Result<Vec<Group>, GroupStoreError> {
+        let rows = sqlx::query_as!(
+            GroupRow,
+            r#"
+            SELECT groups.tenant_id, groups.id, groups.display_name, groups.created_at,
+                   ARRAY_REMOVE(ARRAY_AGG(group_members.email ORDER BY group_members.email), NULL) AS "members!"
+            FROM groups
+            LEFT JOIN group_members
+                ON group_members.tenant_id = groups.tenant_id AND group_members.group_id = groups.id
+            WHERE groups.tenant_id = $1
+            GROUP BY groups.tenant_id, groups.id
+            ORDER BY groups.display_name
+            "#,
+            tenant.as_ref(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| GroupStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter().map(to_group).collect()
+    }
+
+    #[tracing::instrument(name = "Updating group in PostgreSQL", skip_all)]
+    async fn update_group(&mut self, group: Group) -> Result<(), GroupStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| GroupStoreError::UnexpectedError(e.into()))?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE groups
+            SET display_name = $3
+            WHERE tenant_id = $1 AND id = $2
+            "#,
+            group.tenant.as_ref(),
+            group.id.as_ref(),
+            group.display_name,
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| match e.as_database_error() {
+            Some(error) if error.is_unique_violation() => GroupStoreError::GroupAlreadyExists,
+            _ => GroupStoreError::UnexpectedError(e.into()),
+        })?;
+
+        if result.rows_affected() == 0 {
+            return Err(GroupStoreError::GroupNotFound);
+        }
+
+        sqlx::query!(
+            r#"
+            DELETE FROM group_members
+            WHERE tenant_id = $1 AND group_id = $2
+            "#,
+            group.tenant.as_ref(),
+            group.id.as_ref(),
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| GroupStoreError::UnexpectedError(e.into()))?;
+
+        add_members(&mut transaction, &group).await?;
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| GroupStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Deleting group from PostgreSQL", skip_all)]
+    async fn delete_group(
+        &mut self,
+        tenant: &TenantId,
+        id: &GroupId,
+    ) -> Result<(), GroupStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM groups
+            WHERE tenant_id = $1 AND id = $2
+            "#,
+            tenant.as_ref(),
+            id.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| GroupStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(GroupStoreError::GroupNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+async fn add_members(
+    transaction: &mut Transaction<'_, Postgres>,
+    group: &Group,
+) -> Result<(), GroupStoreError> {
+    let members: Vec<String> = group
+        .members
+        .iter()
+        .map(|email| email.as_ref().expose_secret().to_owned())
+        .collect();
+
+    sqlx::query!(
+        r#"
+        INSERT INTO group_members (tenant_id, group_id, email)
+        SELECT $1, $2, UNNEST($3::TEXT[])
+        "#,
+        group.tenant.as_ref(),
+        group.id.as_ref(),
+        &members,
+    )
+    .execute(&mut **transaction)
+    .await
+    .map_err(|e| GroupStoreError::UnexpectedError(e.into()))?;
+
+    Ok(())
+}
+
+struct GroupRow {
+    tenant_id: String,
+    id: Uuid,
+    display_name: String,
+    created_at: DateTime<Utc>,
+    members: Vec<String>,
+}
+
+fn to_group(row: GroupRow) -> Result<Group, GroupStoreError> {
+    let members = row
+        .members
+        .into_iter()
+        .map(|email| Email::parse(Secret::new(email)))
+        .collect::<Result<Vec<Email>, _>>()
+        .map_err(GroupStoreError::UnexpectedError)?;
+
+    Ok(Group {
+        id: GroupId::from(row.id),
+        tenant: TenantId::parse(row.tenant_id).map_err(GroupStoreError::UnexpectedError)?,
+        display_name: row.display_name,
+        members,
+        created_at: row.created_at,
+    })
+}
@@ -0,0 +1,145 @@
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{IdentityProviderStore, IdentityProviderStoreError},
+    IdentityProvider, ProviderId, TenantId,
+};
+
+pub struct PostgresIdentityProviderStore {
+    pool: PgPool,
+}
+
+impl PostgresIdentityProviderStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl IdentityProviderStore for PostgresIdentityProviderStore {
+    #[tracing::instrument(name = "Adding identity provider to PostgreSQL", skip_all)]
+    async fn add_provider(
+        &mut self,
+        provider: IdentityProvider,
+    ) -> Result<(), IdentityProviderStoreError> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO identity_providers (tenant_id, id, name, issuer, client_id, client_secret, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT DO NOTHING
+            "#,
+            provider.tenant.as_ref(),
+            provider.id.as_ref(),
+            provider.name,
+            provider.issuer,
+            provider.client_id,
+            provider.client_secret.expose_secret(),
+            provider.created_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| IdentityProviderStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(IdentityProviderStoreError::ProviderAlreadyExists);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving identity provider from PostgreSQL", skip_all)]
+    async fn get_provider(
+        &self,
+        tenant: &TenantId,
+        id: &ProviderId,
+    ) -> Result<IdentityProvider, IdentityProviderStoreError> {
+        let row = sqlx::query_as!(
+            IdentityProviderRow,
+            r#"
+            SELECT tenant_id, id, name, issuer, client_id, client_secret, created_at
+            FROM identity_providers
+            WHERE tenant_id = $1 AND id = $2
+            "#,
+            tenant.as_ref(),
+            id.as_ref(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| IdentityProviderStoreError::UnexpectedError(e.into()))?
+        .ok_or(IdentityProviderStoreError::ProviderNotFound)?;
+
+        to_provider(row)
+    }
+
+    #[tracing::instrument(name = "Listing identity providers from PostgreSQL", skip_all)]
+    async fn list_providers(
+        &self,
+        tenant: &TenantId,
+    ) -> Result<Vec<IdentityProvider>, IdentityProviderStoreError> {
+        let rows = sqlx::query_as!(
+            IdentityProviderRow,
+            r#"
+            SELECT tenant_id, id, name, issuer, client_id, client_secret, created_at
+            FROM identity_providers
+            WHERE tenant_id = $1
+            ORDER BY created_at
+            "#,
+            tenant.as_ref(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| IdentityProviderStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter().map(to_provider).collect()
+    }
+
+    #[tracing::instrument(name = "Deleting identity provider from PostgreSQL", skip_all)]
+    async fn delete_provider(
+        &mut self,
+        tenant: &TenantId,
+        id: &ProviderId,
+    ) -> Result<(), IdentityProviderStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM identity_providers
+            WHERE tenant_id = $1 AND id = $2
+            "#,
+            tenant.as_ref(),
+            id.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| IdentityProviderStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(IdentityProviderStoreError::ProviderNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+struct IdentityProviderRow {
+    tenant_id: String,
+    id: String,
+    name: String,
+    issuer: String,
+    client_id: String,
+    client_secret: String,
+    created_at: DateTime<Utc>,
+}
+
+fn to_provider(row: IdentityProviderRow) -> Result<IdentityProvider, IdentityProviderStoreError> {
+    Ok(IdentityProvider {
+        id: ProviderId::parse(row.id).map_err(IdentityProviderStoreError::UnexpectedError)?,
+        tenant: TenantId::parse(row.tenant_id)
+            .map_err(IdentityProviderStoreError::UnexpectedError)?,
+        name: row.name,
+        issuer: row.issuer,
+        client_id: row.client_id,
+        client_secret: Secret::new(row.client_secret),
+        created_at: row.created_at,
+    })
+}
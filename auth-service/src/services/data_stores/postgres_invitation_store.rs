@@ -0,0 +1,177 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Context;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::{
+    data_stores::{InvitationStore, InvitationStoreError},
+    Email, Invitation, InvitationId, InvitationStatus, Role, TenantId,
+};
+
+pub struct PostgresInvitationStore {
+    pool: PgPool,
+}
+
+impl PostgresInvitationStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl InvitationStore for PostgresInvitationStore {
+    #[tracing::instrument(name = "Adding invitation to PostgreSQL", skip_all)]
+    async fn add_invitation(&mut self, invitation: Invitation) -> Result<(), InvitationStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO invitations (id, tenant_id, email, role, invited_by, status, created_at,
+                                     expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            "#,
+            invitation.id.as_ref(),
+            invitation.tenant.as_ref(),
+            invitation.email.as_ref().expose_secret(),
+            invitation.role.as_ref().map(|role| role.as_ref()),
+            invitation.invited_by,
+            invitation.status.as_str(),
+            invitation.created_at,
+            invitation.expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| InvitationStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving invitation from PostgreSQL", skip_all)]
+    async fn get_invitation(
+        &self,
+        tenant: &TenantId,
+        id: &InvitationId,
+    ) -> Result<Invitation, InvitationStoreError> {
+        let row = sqlx::query_as!(
+            InvitationRow,
+            r#"
+            SELECT id, tenant_id, email, role, invited_by, status, created_at, expires_at
+            FROM invitations
+            WHERE id = $1 AND tenant_id = $2
+            "#,
+            id.as_ref(),
+            tenant.as_ref(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| InvitationStoreError::UnexpectedError(e.into()))?
+        .ok_or(InvitationStoreError::InvitationNotFound)?;
+
+        to_invitation(row)
+    }
+
+    #[tracing::instrument(name = "Listing invitations from PostgreSQL", skip_all)]
+    async fn list_invitations(
+        &self,
+        tenant: &TenantId,
+    ) -> Result<Vec<Invitation>, InvitationStoreError> {
+        let rows = sqlx::query_as!(
+            InvitationRow,
+            r#"
+            SELECT id, tenant_id, email, role, invited_by, status, created_at, expires_at
+            FROM invitations
+            WHERE tenant_id = $1
+            ORDER BY created_at DESC
+            "#,
+            tenant.as_ref(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| InvitationStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter().map(to_invitation).collect()
+    }
+
+    #[tracing::instrument(name = "Accepting invitation in PostgreSQL", skip_all)]
+    async fn accept_invitation(
+        &mut self,
+        tenant: &TenantId,
+        id: &InvitationId,
+    ) -> Result<(), InvitationStoreError> {
+        // A single conditional update, so two concurrent accepts can't both succeed
+        let result = sqlx::query!(
+            r#"
+            UPDATE invitations
+            SET status = 'accepted'
+            WHERE id = $1 AND tenant_id = $2 AND status = 'pending' AND expires_at > now()
+            "#,
+            id.as_ref(),
+            tenant.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| InvitationStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(InvitationStoreError::InvitationNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoking invitation in PostgreSQL", skip_all)]
+    async fn revoke_invitation(
+        &mut self,
+        tenant: &TenantId,
+        id: &InvitationId,
+    ) -> Result<(), InvitationStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE invitations
+            SET status = 'revoked'
+            WHERE id = $1 AND tenant_id = $2 AND status = 'pending'
+            "#,
+            id.as_ref(),
+            tenant.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| InvitationStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(InvitationStoreError::InvitationNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+struct InvitationRow {
+    id: Uuid,
+    tenant_id: String,
+    email: String,
+    role: Option<String>,
+    invited_by: String,
+    status: String,
+    created_at: DateTime<Utc>,
+    expires_at: DateTime<Utc>,
+}
+
+fn to_invitation(row: InvitationRow) -> Result<Invitation, InvitationStoreError> {
+    Ok(Invitation {
+        id: InvitationId::from(row.id),
+        tenant: TenantId::parse(row.tenant_id).map_err(InvitationStoreError::UnexpectedError)?,
+        email: Email::parse(Secret::new(row.email))
+            .map_err(InvitationStoreError::UnexpectedError)?,
+        role: row
+            .role
+            .map(Role::parse)
+            .transpose()
+            .wrap_err("invalid role stored for invitation")
+            .map_err(InvitationStoreError::UnexpectedError)?,
+        invited_by: row.invited_by,
+        status: InvitationStatus::parse(&row.status)
+            .map_err(InvitationStoreError::UnexpectedError)?,
+        created_at: row.created_at,
+        expires_at: row.expires_at,
+    })
+}
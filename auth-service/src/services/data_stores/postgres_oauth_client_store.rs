@@ -0,0 +1,160 @@
+use chrono::{DateTime, Utc};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use crate::{
+    domain::{
+        data_stores::{OAuthClientStore, OAuthClientStoreError},
+        ClientId, OAuthClient, Permission, TenantId,
+    },
+    utils::hashing::{compute_password_hash, verify_password_hash},
+};
+
+pub struct PostgresOAuthClientStore {
+    pool: PgPool,
+}
+
+impl PostgresOAuthClientStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl OAuthClientStore for PostgresOAuthClientStore {
+    #[tracing::instrument(name = "Adding OAuth client to PostgreSQL", skip_all)]
+    async fn add_client(
+        &mut self,
+        client: OAuthClient,
+        secret: Secret<String>,
+    ) -> Result<(), OAuthClientStoreError> {
+        let secret_hash = compute_password_hash(secret)
+            .await
+            .map_err(OAuthClientStoreError::UnexpectedError)?;
+        let scopes: Vec<String> = client
+            .scopes
+            .iter()
+            .map(|scope| scope.as_ref().to_owned())
+            .collect();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO oauth_clients (client_id, tenant_id, name, secret_hash, scopes, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            client.id.as_ref(),
+            client.tenant.as_ref(),
+            client.name,
+            secret_hash.expose_secret(),
+            &scopes,
+            client.created_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| OAuthClientStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Validating OAuth client in PostgreSQL", skip_all)]
+    async fn validate_client(
+        &self,
+        id: &ClientId,
+        secret: &Secret<String>,
+    ) -> Result<OAuthClient, OAuthClientStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT client_id, tenant_id, name, secret_hash, scopes, created_at
+            FROM oauth_clients
+            WHERE client_id = $1
+            "#,
+            id.as_ref(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| OAuthClientStoreError::UnexpectedError(e.into()))?
+        .ok_or(OAuthClientStoreError::InvalidClient)?;
+
+        verify_password_hash(Secret::new(row.secret_hash), secret.clone())
+            .await
+            .map_err(|_| OAuthClientStoreError::InvalidClient)?;
+
+        to_client(OAuthClientRow {
+            client_id: row.client_id,
+            tenant_id: row.tenant_id,
+            name: row.name,
+            scopes: row.scopes,
+            created_at: row.created_at,
+        })
+    }
+
+    #[tracing::instrument(name = "Listing OAuth clients from PostgreSQL", skip_all)]
+    async fn list_clients(
+        &self,
+        tenant: &TenantId,
+    ) -> Result<Vec<OAuthClient>, OAuthClientStoreError> {
+        let rows = sqlx::query_as!(
+            OAuthClientRow,
+            r#"
+            SELECT client_id, tenant_id, name, scopes, created_at
+            FROM oauth_clients
+            WHERE tenant_id = $1
+            ORDER BY created_at
+            "#,
+            tenant.as_ref(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| OAuthClientStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter().map(to_client).collect()
+    }
+
+    #[tracing::instrument(name = "Deleting OAuth client from PostgreSQL", skip_all)]
+    async fn delete_client(
+        &mut self,
+        tenant: &TenantId,
+        id: &ClientId,
+    ) -> Result<(), OAuthClientStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM oauth_clients
+            WHERE client_id = $1 AND tenant_id = $2
+            "#,
+            id.as_ref(),
+            tenant.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| OAuthClientStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(OAuthClientStoreError::ClientNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+struct OAuthClientRow {
+    client_id: String,
+    tenant_id: String,
+    name: String,
+    scopes: Vec<String>,
+    created_at: DateTime<Utc>,
+}
+
+fn to_client(row: OAuthClientRow) -> Result<OAuthClient, OAuthClientStoreError> {
+    Ok(OAuthClient {
+        id: ClientId::parse(row.client_id).map_err(OAuthClientStoreError::UnexpectedError)?,
+        tenant: TenantId::parse(row.tenant_id).map_err(OAuthClientStoreError::UnexpectedError)?,
+        name: row.name,
+        scopes: row
+            .scopes
+            .into_iter()
+            .map(Permission::parse)
+            .collect::<Result<_, _>>()
+            .map_err(OAuthClientStoreError::UnexpectedError)?,
+        created_at: row.created_at,
+    })
+}
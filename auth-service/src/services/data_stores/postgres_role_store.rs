@@ -0,0 +1,194 @@
+use color_eyre::eyre::Context;
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{RoleStore, RoleStoreError},
+    Email, Grants, Permission, Role, TenantId,
+};
+
+pub struct PostgresRoleStore {
+    pool: PgPool,
+}
+
+impl PostgresRoleStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl RoleStore for PostgresRoleStore {
+    #[tracing::instrument(name = "Adding role to PostgreSQL", skip_all)]
+    async fn add_role(
+        &mut self,
+        role: Role,
+        permissions: Vec<Permission>,
+    ) -> Result<(), RoleStoreError> {
+        let mut transaction = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO roles (name)
+            VALUES ($1)
+            ON CONFLICT DO NOTHING
+            "#,
+            role.as_ref(),
+        )
+        .execute(&mut *transaction)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(RoleStoreError::RoleAlreadyExists);
+        }
+
+        for permission in &permissions {
+            sqlx::query!(
+                r#"
+                INSERT INTO role_permissions (role, permission)
+                VALUES ($1, $2)
+                ON CONFLICT DO NOTHING
+                "#,
+                role.as_ref(),
+                permission.as_ref(),
+            )
+            .execute(&mut *transaction)
+            .await
+            .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+        }
+
+        transaction
+            .commit()
+            .await
+            .map_err(|e| RoleStoreError::UnexpectedError(e.into()))
+    }
+
+    #[tracing::instrument(name = "Assigning role in PostgreSQL", skip_all)]
+    async fn assign_role(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        role: &Role,
+    ) -> Result<(), RoleStoreError> {
+        let existing = sqlx::query!(
+            r#"
+            SELECT name
+            FROM roles
+            WHERE name = $1
+            "#,
+            role.as_ref(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        if existing.is_none() {
+            return Err(RoleStoreError::RoleNotFound);
+        }
+
+        // Assigning a role twice is a no-op
+        sqlx::query!(
+            r#"
+            INSERT INTO user_roles (tenant_id, email, role)
+            VALUES ($1, $2, $3)
+            ON CONFLICT DO NOTHING
+            "#,
+            tenant.as_ref(),
+            email.as_ref().expose_secret(),
+            role.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Unassigning role in PostgreSQL", skip_all)]
+    async fn unassign_role(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        role: &Role,
+    ) -> Result<(), RoleStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM user_roles
+            WHERE tenant_id = $1 AND email = $2 AND role = $3
+            "#,
+            tenant.as_ref(),
+            email.as_ref().expose_secret(),
+            role.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(RoleStoreError::RoleNotAssigned);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving grants from PostgreSQL", skip_all)]
+    async fn get_grants(&self, tenant: &TenantId, email: &Email) -> Result<Grants, RoleStoreError> {
+        // Roles without permissions still come back, with a NULL permission
+        let rows = sqlx::query!(
+            r#"
+            SELECT user_roles.role, role_permissions.permission AS "permission?"
+            FROM user_roles
+            LEFT JOIN role_permissions ON role_permissions.role = user_roles.role
+            WHERE user_roles.tenant_id = $1 AND user_roles.email = $2
+            "#,
+            tenant.as_ref(),
+            email.as_ref().expose_secret(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        let mut roles = Vec::new();
+        let mut permissions = Vec::new();
+
+        for row in rows {
+            roles.push(
+                Role::parse(row.role)
+                    .wrap_err("invalid role stored for user")
+                    .map_err(RoleStoreError::UnexpectedError)?,
+            );
+
+            if let Some(permission) = row.permission {
+                permissions.push(
+                    Permission::parse(permission)
+                        .wrap_err("invalid permission stored for role")
+                        .map_err(RoleStoreError::UnexpectedError)?,
+                );
+            }
+        }
+
+        Ok(Grants::new(roles, permissions))
+    }
+
+    #[tracing::instrument(name = "Checking role in PostgreSQL", skip_all)]
+    async fn role_exists(&self, role: &Role) -> Result<bool, RoleStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT name
+            FROM roles
+            WHERE name = $1
+            "#,
+            role.as_ref(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| RoleStoreError::UnexpectedError(e.into()))?;
+
+        Ok(row.is_some())
+    }
+}
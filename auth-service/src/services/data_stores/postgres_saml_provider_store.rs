@@ -0,0 +1,143 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{SamlProviderStore, SamlProviderStoreError},
+    ProviderId, SamlProvider, TenantId,
+};
+
+pub struct PostgresSamlProviderStore {
+    pool: PgPool,
+}
+
+impl PostgresSamlProviderStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl SamlProviderStore for PostgresSamlProviderStore {
+    #[tracing::instrument(name = "Adding SAML provider to PostgreSQL", skip_all)]
+    async fn add_provider(&mut self, provider: SamlProvider) -> Result<(), SamlProviderStoreError> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO saml_providers (tenant_id, id, name, entity_id, sso_url, certificate, email_attribute, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT DO NOTHING
+            "#,
+            provider.tenant.as_ref(),
+            provider.id.as_ref(),
+            provider.name,
+            provider.entity_id,
+            provider.sso_url,
+            provider.certificate,
+            provider.email_attribute,
+            provider.created_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SamlProviderStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(SamlProviderStoreError::ProviderAlreadyExists);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving SAML provider from PostgreSQL", skip_all)]
+    async fn get_provider(
+        &self,
+        tenant: &TenantId,
+        id: &ProviderId,
+    ) -> Result<SamlProvider, SamlProviderStoreError> {
+        let row = sqlx::query_as!(
+            SamlProviderRow,
+            r#"
+            SELECT tenant_id, id, name, entity_id, sso_url, certificate, email_attribute, created_at
+            FROM saml_providers
+            WHERE tenant_id = $1 AND id = $2
+            "#,
+            tenant.as_ref(),
+            id.as_ref(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| SamlProviderStoreError::UnexpectedError(e.into()))?
+        .ok_or(SamlProviderStoreError::ProviderNotFound)?;
+
+        to_provider(row)
+    }
+
+    #[tracing::instrument(name = "Listing SAML providers from PostgreSQL", skip_all)]
+    async fn list_providers(
+        &self,
+        tenant: &TenantId,
+    ) -> Result<Vec<SamlProvider>, SamlProviderStoreError> {
+        let rows = sqlx::query_as!(
+            SamlProviderRow,
+            r#"
+            SELECT tenant_id, id, name, entity_id, sso_url, certificate, email_attribute, created_at
+            FROM saml_providers
+            WHERE tenant_id = $1
+            ORDER BY created_at
+            "#,
+            tenant.as_ref(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| SamlProviderStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter().map(to_provider).collect()
+    }
+
+    #[tracing::instrument(name = "Deleting SAML provider from PostgreSQL", skip_all)]
+    async fn delete_provider(
+        &mut self,
+        tenant: &TenantId,
+        id: &ProviderId,
+    ) -> Result<(), SamlProviderStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM saml_providers
+            WHERE tenant_id = $1 AND id = $2
+            "#,
+            tenant.as_ref(),
+            id.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| SamlProviderStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(SamlProviderStoreError::ProviderNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+struct SamlProviderRow {
+    tenant_id: String,
+    id: String,
+    name: String,
+    entity_id: String,
+    sso_url: String,
+    certificate: String,
+    email_attribute: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+fn to_provider(row: SamlProviderRow) -> Result<SamlProvider, SamlProviderStoreError> {
+    Ok(SamlProvider {
+        id: ProviderId::parse(row.id).map_err(SamlProviderStoreError::UnexpectedError)?,
+        tenant: TenantId::parse(row.tenant_id).map_err(SamlProviderStoreError::UnexpectedError)?,
+        name: row.name,
+        entity_id: row.entity_id,
+        sso_url: row.sso_url,
+        certificate: row.certificate,
+        email_attribute: row.email_attribute,
+        created_at: row.created_at,
+    })
+}
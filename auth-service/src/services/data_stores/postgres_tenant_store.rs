@@ -0,0 +1,111 @@
+use color_eyre::eyre::Context;
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{TenantStore, TenantStoreError},
+    Tenant, TenantId, TenantSettings,
+};
+
+pub struct PostgresTenantStore {
+    pool: PgPool,
+}
+
+impl PostgresTenantStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl TenantStore for PostgresTenantStore {
+    #[tracing::instrument(name = "Adding tenant to PostgreSQL", skip_all)]
+    async fn add_tenant(&mut self, tenant: Tenant) -> Result<(), TenantStoreError> {
+        let result = sqlx::query!(
+            r#"
+            INSERT INTO tenants (id, hostname, require_2fa, allowed_email_domains)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT DO NOTHING
+            "#,
+            tenant.id.as_ref(),
+            tenant.hostname,
+            tenant.settings.require_2fa,
+            &tenant.settings.allowed_email_domains,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TenantStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(TenantStoreError::TenantAlreadyExists);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving tenant from PostgreSQL", skip_all)]
+    async fn get_tenant(&self, id: &TenantId) -> Result<Tenant, TenantStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT id, hostname, require_2fa, allowed_email_domains
+            FROM tenants
+            WHERE id = $1
+            "#,
+            id.as_ref(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| TenantStoreError::UnexpectedError(e.into()))?
+        .ok_or(TenantStoreError::TenantNotFound)?;
+
+        to_tenant(
+            row.id,
+            row.hostname,
+            row.require_2fa,
+            row.allowed_email_domains,
+        )
+    }
+
+    #[tracing::instrument(name = "Retrieving tenant by hostname from PostgreSQL", skip_all)]
+    async fn get_tenant_by_hostname(&self, hostname: &str) -> Result<Tenant, TenantStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT id, hostname, require_2fa, allowed_email_domains
+            FROM tenants
+            WHERE hostname = $1
+            "#,
+            hostname,
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| TenantStoreError::UnexpectedError(e.into()))?
+        .ok_or(TenantStoreError::TenantNotFound)?;
+
+        to_tenant(
+            row.id,
+            row.hostname,
+            row.require_2fa,
+            row.allowed_email_domains,
+        )
+    }
+}
+
+fn to_tenant(
+    id: String,
+    hostname: Option<String>,
+    require_2fa: bool,
+    allowed_email_domains: Vec<String>,
+) -> Result<Tenant, TenantStoreError> {
+    let id = TenantId::parse(id)
+        .wrap_err("invalid tenant id in PostgreSQL")
+        .map_err(TenantStoreError::UnexpectedError)?;
+
+    let tenant = Tenant::new(id).with_settings(TenantSettings {
+        require_2fa,
+        allowed_email_domains,
+    });
+
+    Ok(match hostname {
+        Some(hostname) => tenant.with_hostname(hostname),
+        None => tenant,
+    })
+}
@@ -0,0 +1,158 @@
+use color_eyre::eyre::Context;
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+
+use crate::domain::{
+    data_stores::{TrustedDeviceStore, TrustedDeviceStoreError},
+    Email, TenantId, TrustedDevice, TrustedDeviceId,
+};
+
+pub struct PostgresTrustedDeviceStore {
+    pool: PgPool,
+}
+
+impl PostgresTrustedDeviceStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl TrustedDeviceStore for PostgresTrustedDeviceStore {
+    #[tracing::instrument(name = "Adding trusted device to PostgreSQL", skip_all)]
+    async fn add_device(&mut self, device: TrustedDevice) -> Result<(), TrustedDeviceStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO trusted_devices (id, tenant_id, email, user_agent, ip_address, created_at,
+                                         expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+            device.id.as_ref(),
+            device.tenant.as_ref(),
+            device.email.as_ref().expose_secret(),
+            device.user_agent,
+            device.ip_address.map(|ip| ip.to_string()),
+            device.created_at,
+            device.expires_at
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TrustedDeviceStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Checking trusted device in PostgreSQL", skip_all)]
+    async fn is_trusted(
+        &self,
+        tenant: &TenantId,
+        email: &Email,
+        device_id: &TrustedDeviceId,
+    ) -> Result<bool, TrustedDeviceStoreError> {
+        let row = sqlx::query!(
+            r#"
+            SELECT id
+            FROM trusted_devices
+            WHERE id = $1 AND tenant_id = $2 AND email = $3 AND expires_at > now()
+            "#,
+            device_id.as_ref(),
+            tenant.as_ref(),
+            email.as_ref().expose_secret(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| TrustedDeviceStoreError::UnexpectedError(e.into()))?;
+
+        Ok(row.is_some())
+    }
+
+    #[tracing::instrument(name = "Listing trusted devices from PostgreSQL", skip_all)]
+    async fn list_devices(
+        &self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<Vec<TrustedDevice>, TrustedDeviceStoreError> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT id, tenant_id, email, user_agent, ip_address, created_at, expires_at
+            FROM trusted_devices
+            WHERE tenant_id = $1 AND email = $2 AND expires_at > now()
+            ORDER BY created_at
+            "#,
+            tenant.as_ref(),
+            email.as_ref().expose_secret(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| TrustedDeviceStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| {
+                Ok(TrustedDevice {
+                    id: TrustedDeviceId::from(row.id),
+                    tenant: TenantId::parse(row.tenant_id)
+                        .map_err(TrustedDeviceStoreError::UnexpectedError)?,
+                    email: Email::parse(Secret::new(row.email))
+                        .map_err(TrustedDeviceStoreError::UnexpectedError)?,
+                    user_agent: row.user_agent,
+                    ip_address: row
+                        .ip_address
+                        .map(|ip| ip.parse())
+                        .transpose()
+                        .wrap_err("invalid IP address stored for trusted device")
+                        .map_err(TrustedDeviceStoreError::UnexpectedError)?,
+                    created_at: row.created_at,
+                    expires_at: row.expires_at,
+                })
+            })
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Revoking trusted device in PostgreSQL", skip_all)]
+    async fn revoke_device(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        device_id: &TrustedDeviceId,
+    ) -> Result<(), TrustedDeviceStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM trusted_devices
+            WHERE id = $1 AND tenant_id = $2 AND email = $3
+            "#,
+            device_id.as_ref(),
+            tenant.as_ref(),
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TrustedDeviceStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(TrustedDeviceStoreError::DeviceNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Revoking all trusted devices in PostgreSQL", skip_all)]
+    async fn revoke_all_devices(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<(), TrustedDeviceStoreError> {
+        sqlx::query!(
+            r#"
+            DELETE FROM trusted_devices
+            WHERE tenant_id = $1 AND email = $2
+            "#,
+            tenant.as_ref(),
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| TrustedDeviceStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+}
@@ -1,15 +1,12 @@
 use crate::domain::{
     data_stores::{UserStore, UserStoreError},
-    Email, Password, User,
+    AccountStatus, Email, Password, PhoneNumber, SuspendedBy, TenantId, TwoFAChannel, User,
+    UserPage, UserQuery,
 };
-use argon2::{
-    password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash, PasswordHasher,
-    PasswordVerifier, Version,
-};
-use color_eyre::eyre::{eyre, Context, Result};
+use crate::utils::hashing::{compute_password_hash, verify_password_hash};
+use color_eyre::eyre::{Context, Result};
 use secrecy::{ExposeSecret, Secret};
 use sqlx::PgPool;
-use std::error::Error;
 
 pub struct PostgresUserStore {
     pool: PgPool,
@@ -24,19 +21,31 @@ impl PostgresUserStore {
 #[async_trait::async_trait]
 impl UserStore for PostgresUserStore {
     #[tracing::instrument(name = "Adding user to PostgreSQL", skip_all)]
-    async fn add_user(&mut self, user: User) -> Result<(), UserStoreError> {
+    async fn add_user(&mut self, tenant: &TenantId, user: User) -> Result<(), UserStoreError> {
         let password_hash = compute_password_hash(user.password.as_ref().to_owned())
             .await
             .map_err(UserStoreError::UnexpectedError)?;
 
         sqlx::query!(
             r#"
-            INSERT INTO users (email, password_hash, requires_2fa)
-            VALUES ($1, $2, $3)
+            INSERT INTO users (tenant_id, email, password_hash, requires_2fa, phone_number,
+                               two_fa_channel, status, status_reason, suspended_by,
+                               status_changed_at, managed_by_directory)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
             "#,
+            tenant.as_ref(),
             user.email.as_ref().expose_secret(),
             &password_hash.expose_secret(),
-            user.requires_2fa
+            user.requires_2fa,
+            user.phone_number
+                .as_ref()
+                .map(|p| p.as_ref().expose_secret().to_owned()),
+            user.two_fa_channel.as_str(),
+            user.status.as_str(),
+            user.status_reason,
+            user.suspended_by.map(|suspended_by| suspended_by.as_str()),
+            user.status_changed_at,
+            user.managed_by_directory,
         )
         .execute(&self.pool)
         .await
@@ -46,13 +55,16 @@ impl UserStore for PostgresUserStore {
     }
 
     #[tracing::instrument(name = "Retrieving user from PostgreSQL", skip_all)]
-    async fn get_user(&self, email: &Email) -> Result<User, UserStoreError> {
+    async fn get_user(&self, tenant: &TenantId, email: &Email) -> Result<User, UserStoreError> {
         sqlx::query!(
             r#"
-            SELECT email, password_hash, requires_2fa
+            SELECT email, password_hash, requires_2fa, phone_number, two_fa_channel,
+                   status, status_reason, suspended_by, status_changed_at, password_reset_required,
+                   managed_by_directory
             FROM users
-            WHERE email = $1
+            WHERE tenant_id = $1 AND email = $2
             "#,
+            tenant.as_ref(),
             email.as_ref().expose_secret(),
         )
         .fetch_optional(&self.pool)
@@ -65,6 +77,24 @@ impl UserStore for PostgresUserStore {
                 password: Password::parse(Secret::new(row.password_hash))
                     .map_err(UserStoreError::UnexpectedError)?,
                 requires_2fa: row.requires_2fa,
+                phone_number: row
+                    .phone_number
+                    .map(|p| PhoneNumber::parse(Secret::new(p)))
+                    .transpose()
+                    .map_err(UserStoreError::UnexpectedError)?,
+                two_fa_channel: TwoFAChannel::parse(&row.two_fa_channel)
+                    .map_err(UserStoreError::UnexpectedError)?,
+                status: AccountStatus::parse(&row.status)
+                    .map_err(UserStoreError::UnexpectedError)?,
+                status_reason: row.status_reason,
+                suspended_by: row
+                    .suspended_by
+                    .map(|suspended_by| SuspendedBy::parse(&suspended_by))
+                    .transpose()
+                    .map_err(UserStoreError::UnexpectedError)?,
+                status_changed_at: row.status_changed_at,
+                password_reset_required: row.password_reset_required,
+                managed_by_directory: row.managed_by_directory,
             })
         })
         .ok_or(UserStoreError::UserNotFound)?
@@ -73,10 +103,11 @@ impl UserStore for PostgresUserStore {
     #[tracing::instrument(name = "Validating user credentials in PostgreSQL", skip_all)]
     async fn validate_user(
         &self,
+        tenant: &TenantId,
         email: &Email,
         password: &Password,
     ) -> Result<(), UserStoreError> {
-        let user = self.get_user(email).await?;
+        let user = self.get_user(tenant, email).await?;
 
         verify_password_hash(
             user.password.as_ref().to_owned(),
@@ -85,52 +116,301 @@ impl UserStore for PostgresUserStore {
         .await
         .map_err(|_| UserStoreError::InvalidCredentials)
     }
-}
 
-#[tracing::instrument(name = "Verify password hash", skip_all)]
-async fn verify_password_hash(
-    expected_password_hash: Secret<String>, // Updated!
-    password_candidate: Secret<String>,     // Updated!
-) -> Result<()> {
-    let current_span: tracing::Span = tracing::Span::current();
-    let result = tokio::task::spawn_blocking(move || {
-        current_span.in_scope(|| {
-            let expected_password_hash: PasswordHash<'_> =
-                PasswordHash::new(expected_password_hash.expose_secret())?;
-
-            Argon2::default()
-                .verify_password(
-                    password_candidate.expose_secret().as_bytes(), // Updated!
-                    &expected_password_hash,
-                )
-                .wrap_err("failed to verify password hash")
-        })
-    })
-    .await;
+    #[tracing::instrument(name = "Setting phone number in PostgreSQL", skip_all)]
+    async fn set_phone_number(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        phone_number: PhoneNumber,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET phone_number = $3
+            WHERE tenant_id = $1 AND email = $2
+            "#,
+            tenant.as_ref(),
+            email.as_ref().expose_secret(),
+            phone_number.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
 
-    result?
-}
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Setting 2FA channel in PostgreSQL", skip_all)]
+    async fn set_two_fa_channel(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        channel: TwoFAChannel,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET two_fa_channel = $3
+            WHERE tenant_id = $1 AND email = $2
+            "#,
+            tenant.as_ref(),
+            email.as_ref().expose_secret(),
+            channel.as_str(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
 
-#[tracing::instrument(name = "Computing password hash", skip_all)]
-async fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>> {
-    // Updated!
-    let current_span: tracing::Span = tracing::Span::current();
-
-    let result = tokio::task::spawn_blocking(move || {
-        current_span.in_scope(|| {
-            let salt: SaltString = SaltString::generate(&mut rand::thread_rng());
-            let password_hash = Argon2::new(
-                Algorithm::Argon2id,
-                Version::V0x13,
-                Params::new(15000, 2, 1, None)?,
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Listing users from PostgreSQL", skip_all)]
+    async fn list_users(
+        &self,
+        tenant: &TenantId,
+        query: &UserQuery,
+    ) -> Result<UserPage, UserStoreError> {
+        let pattern = query
+            .search
+            .as_deref()
+            .map(|search| format!("%{}%", escape_like_pattern(search)));
+        let limit = i64::try_from(query.limit)
+            .wrap_err("page size is too large")
+            .map_err(UserStoreError::UnexpectedError)?;
+        let offset = i64::try_from(query.offset)
+            .wrap_err("page offset is too large")
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        // Byte order, so paging is stable and matches the in-memory store
+        let rows = sqlx::query!(
+            r#"
+            SELECT email, password_hash, requires_2fa, phone_number, two_fa_channel,
+                   status, status_reason, suspended_by, status_changed_at, password_reset_required,
+                   managed_by_directory, COUNT(*) OVER () AS "total!"
+            FROM users
+            WHERE tenant_id = $1 AND ($2::TEXT IS NULL OR email ILIKE $2)
+            ORDER BY email COLLATE "C"
+            LIMIT $3 OFFSET $4
+            "#,
+            tenant.as_ref(),
+            pattern,
+            limit,
+            offset,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        // A page past the end has no rows to read the total from
+        let total = match rows.first() {
+            Some(row) => row.total,
+            None => sqlx::query_scalar!(
+                r#"
+                SELECT COUNT(*) AS "total!"
+                FROM users
+                WHERE tenant_id = $1 AND ($2::TEXT IS NULL OR email ILIKE $2)
+                "#,
+                tenant.as_ref(),
+                pattern,
             )
-            .hash_password(password.expose_secret().as_bytes(), &salt)? // Updated!
-            .to_string();
+            .fetch_one(&self.pool)
+            .await
+            .map_err(|e| UserStoreError::UnexpectedError(e.into()))?,
+        };
 
-            Ok(Secret::new(password_hash)) // Updated!
+        let users = rows
+            .into_iter()
+            .map(|row| {
+                Ok(User {
+                    email: Email::parse(Secret::new(row.email))
+                        .map_err(UserStoreError::UnexpectedError)?,
+                    password: Password::parse(Secret::new(row.password_hash))
+                        .map_err(UserStoreError::UnexpectedError)?,
+                    requires_2fa: row.requires_2fa,
+                    phone_number: row
+                        .phone_number
+                        .map(|p| PhoneNumber::parse(Secret::new(p)))
+                        .transpose()
+                        .map_err(UserStoreError::UnexpectedError)?,
+                    two_fa_channel: TwoFAChannel::parse(&row.two_fa_channel)
+                        .map_err(UserStoreError::UnexpectedError)?,
+                    status: AccountStatus::parse(&row.status)
+                        .map_err(UserStoreError::UnexpectedError)?,
+                    status_reason: row.status_reason,
+                    suspended_by: row
+                        .suspended_by
+                        .map(|suspended_by| SuspendedBy::parse(&suspended_by))
+                        .transpose()
+                        .map_err(UserStoreError::UnexpectedError)?,
+                    status_changed_at: row.status_changed_at,
+                    password_reset_required: row.password_reset_required,
+                    managed_by_directory: row.managed_by_directory,
+                })
+            })
+            .collect::<Result<Vec<User>, UserStoreError>>()?;
+
+        Ok(UserPage {
+            users,
+            total: total as u64,
         })
-    })
-    .await;
+    }
+
+    #[tracing::instrument(name = "Setting account status in PostgreSQL", skip_all)]
+    async fn set_status(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        status: AccountStatus,
+        reason: Option<String>,
+        suspended_by: Option<SuspendedBy>,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET status = $3, status_reason = $4, suspended_by = $5, status_changed_at = NOW()
+            WHERE tenant_id = $1 AND email = $2
+            "#,
+            tenant.as_ref(),
+            email.as_ref().expose_secret(),
+            status.as_str(),
+            reason,
+            suspended_by.map(|suspended_by| suspended_by.as_str()),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Requiring password reset in PostgreSQL", skip_all)]
+    async fn require_password_reset(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET password_reset_required = TRUE
+            WHERE tenant_id = $1 AND email = $2
+            "#,
+            tenant.as_ref(),
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Setting password in PostgreSQL", skip_all)]
+    async fn set_password(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        password: Password,
+    ) -> Result<(), UserStoreError> {
+        let password_hash = compute_password_hash(password.as_ref().to_owned())
+            .await
+            .map_err(UserStoreError::UnexpectedError)?;
+
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET password_hash = $3, password_reset_required = FALSE
+            WHERE tenant_id = $1 AND email = $2
+            "#,
+            tenant.as_ref(),
+            email.as_ref().expose_secret(),
+            password_hash.expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Resetting 2FA in PostgreSQL", skip_all)]
+    async fn reset_two_fa(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE users
+            SET phone_number = NULL, two_fa_channel = $3
+            WHERE tenant_id = $1 AND email = $2
+            "#,
+            tenant.as_ref(),
+            email.as_ref().expose_secret(),
+            TwoFAChannel::Email.as_str(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Deleting user from PostgreSQL", skip_all)]
+    async fn delete_user(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<(), UserStoreError> {
+        let result = sqlx::query!(
+            r#"
+            DELETE FROM users
+            WHERE tenant_id = $1 AND email = $2
+            "#,
+            tenant.as_ref(),
+            email.as_ref().expose_secret(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| UserStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(UserStoreError::UserNotFound);
+        }
+
+        Ok(())
+    }
+}
 
-    result?
+// Searches match the text literally, so `%` and `_` in it aren't wildcards
+fn escape_like_pattern(search: &str) -> String {
+    search
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
 }
@@ -0,0 +1,348 @@
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::{Context, Report, Result};
+use secrecy::{ExposeSecret, Secret};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::domain::{
+    data_stores::{WebhookStore, WebhookStoreError},
+    TenantId, Webhook, WebhookDelivery, WebhookDeliveryId, WebhookDeliveryQuery,
+    WebhookDeliveryStatus, WebhookEventType, WebhookId,
+};
+
+pub struct PostgresWebhookStore {
+    pool: PgPool,
+}
+
+impl PostgresWebhookStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait::async_trait]
+impl WebhookStore for PostgresWebhookStore {
+    #[tracing::instrument(name = "Adding webhook to PostgreSQL", skip_all)]
+    async fn add_webhook(&mut self, webhook: Webhook) -> Result<(), WebhookStoreError> {
+        let events: Vec<String> = webhook
+            .events
+            .iter()
+            .map(|event| event.as_str().to_owned())
+            .collect();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO webhooks (tenant_id, id, url, events, secret, created_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            webhook.tenant.as_ref(),
+            webhook.id.as_ref(),
+            webhook.url,
+            &events,
+            webhook.secret.expose_secret(),
+            webhook.created_at,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| WebhookStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving webhook from PostgreSQL", skip_all)]
+    async fn get_webhook(
+        &self,
+        tenant: &TenantId,
+        id: &WebhookId,
+    ) -> Result<Webhook, WebhookStoreError> {
+        let row = sqlx::query_as!(
+            WebhookRow,
+            r#"
+            SELECT tenant_id, id, url, events, secret, created_at
+            FROM webhooks
+            WHERE tenant_id = $1 AND id = $2
+            "#,
+            tenant.as_ref(),
+            id.as_ref(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| WebhookStoreError::UnexpectedError(e.into()))?
+        .ok_or(WebhookStoreError::WebhookNotFound)?;
+
+        to_webhook(row).map_err(WebhookStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Listing webhooks from PostgreSQL", skip_all)]
+    async fn list_webhooks(&self, tenant: &TenantId) -> Result<Vec<Webhook>, WebhookStoreError> {
+        let rows = sqlx::query_as!(
+            WebhookRow,
+            r#"
+            SELECT tenant_id, id, url, events, secret, created_at
+            FROM webhooks
+            WHERE tenant_id = $1
+            ORDER BY created_at
+            "#,
+            tenant.as_ref(),
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| WebhookStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| to_webhook(row).map_err(WebhookStoreError::UnexpectedError))
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Deleting webhook from PostgreSQL", skip_all)]
+    async fn delete_webhook(
+        &mut self,
+        tenant: &TenantId,
+        id: &WebhookId,
+    ) -> Result<(), WebhookStoreError> {
+        let result = sqlx::query!(
+            "DELETE FROM webhooks WHERE tenant_id = $1 AND id = $2",
+            tenant.as_ref(),
+            id.as_ref(),
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| WebhookStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(WebhookStoreError::WebhookNotFound);
+        }
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Adding webhook delivery to PostgreSQL", skip_all)]
+    async fn add_delivery(&mut self, delivery: WebhookDelivery) -> Result<(), WebhookStoreError> {
+        sqlx::query!(
+            r#"
+            INSERT INTO webhook_deliveries (id, tenant_id, webhook_id, event_type, payload, status,
+                                            attempts, next_attempt_at, last_response_status,
+                                            last_error, created_at, delivered_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)
+            "#,
+            delivery.id.as_ref(),
+            delivery.tenant.as_ref(),
+            delivery.webhook_id.as_ref(),
+            delivery.event_type.as_str(),
+            delivery.payload,
+            delivery.status.as_str(),
+            delivery.attempts as i32,
+            delivery.next_attempt_at,
+            delivery.last_response_status.map(i32::from),
+            delivery.last_error,
+            delivery.created_at,
+            delivery.delivered_at,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| WebhookStoreError::UnexpectedError(e.into()))?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Retrieving webhook delivery from PostgreSQL", skip_all)]
+    async fn get_delivery(
+        &self,
+        tenant: &TenantId,
+        id: &WebhookDeliveryId,
+    ) -> Result<WebhookDelivery, WebhookStoreError> {
+        let row = sqlx::query_as!(
+            WebhookDeliveryRow,
+            r#"
+            SELECT id, tenant_id, webhook_id, event_type, payload, status, attempts,
+                   next_attempt_at, last_response_status, last_error, created_at, delivered_at
+            FROM webhook_deliveries
+            WHERE tenant_id = $1 AND id = $2
+            "#,
+            tenant.as_ref(),
+            id.as_ref(),
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| WebhookStoreError::UnexpectedError(e.into()))?
+        .ok_or(WebhookStoreError::DeliveryNotFound)?;
+
+        to_webhook_delivery(row).map_err(WebhookStoreError::UnexpectedError)
+    }
+
+    #[tracing::instrument(name = "Listing webhook deliveries from PostgreSQL", skip_all)]
+    async fn list_deliveries(
+        &self,
+        tenant: &TenantId,
+        query: &WebhookDeliveryQuery,
+    ) -> Result<Vec<WebhookDelivery>, WebhookStoreError> {
+        let limit = i64::try_from(query.limit)
+            .wrap_err("page size is too large")
+            .map_err(WebhookStoreError::UnexpectedError)?;
+        let offset = i64::try_from(query.offset)
+            .wrap_err("page offset is too large")
+            .map_err(WebhookStoreError::UnexpectedError)?;
+
+        let rows = sqlx::query_as!(
+            WebhookDeliveryRow,
+            r#"
+            SELECT id, tenant_id, webhook_id, event_type, payload, status, attempts,
+                   next_attempt_at, last_response_status, last_error, created_at, delivered_at
+            FROM webhook_deliveries
+            WHERE tenant_id = $1
+                AND ($2::UUID IS NULL OR webhook_id = $2)
+                AND ($3::TEXT IS NULL OR status = $3)
+            ORDER BY created_at DESC
+            LIMIT $4 OFFSET $5
+            "#,
+            tenant.as_ref(),
+            query.webhook.as_ref().map(|webhook| *webhook.as_ref()),
+            query.status.map(|status| status.as_str()),
+            limit,
+            offset,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| WebhookStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| to_webhook_delivery(row).map_err(WebhookStoreError::UnexpectedError))
+            .collect()
+    }
+
+    // Rows another worker has locked are skipped rather than waited for
+    #[tracing::instrument(name = "Claiming due webhook deliveries in PostgreSQL", skip_all)]
+    async fn claim_due_deliveries(
+        &mut self,
+        now: DateTime<Utc>,
+        lease: Duration,
+        limit: u64,
+    ) -> Result<Vec<WebhookDelivery>, WebhookStoreError> {
+        let limit = i64::try_from(limit)
+            .wrap_err("batch size is too large")
+            .map_err(WebhookStoreError::UnexpectedError)?;
+
+        let rows = sqlx::query_as!(
+            WebhookDeliveryRow,
+            r#"
+            UPDATE webhook_deliveries
+            SET next_attempt_at = $2
+            WHERE id IN (
+                SELECT id
+                FROM webhook_deliveries
+                WHERE status = 'pending' AND next_attempt_at <= $1
+                ORDER BY next_attempt_at
+                LIMIT $3
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, tenant_id, webhook_id, event_type, payload, status, attempts,
+                      next_attempt_at, last_response_status, last_error, created_at, delivered_at
+            "#,
+            now,
+            now + lease,
+            limit,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| WebhookStoreError::UnexpectedError(e.into()))?;
+
+        rows.into_iter()
+            .map(|row| to_webhook_delivery(row).map_err(WebhookStoreError::UnexpectedError))
+            .collect()
+    }
+
+    #[tracing::instrument(name = "Updating webhook delivery in PostgreSQL", skip_all)]
+    async fn update_delivery(
+        &mut self,
+        delivery: &WebhookDelivery,
+    ) -> Result<(), WebhookStoreError> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE webhook_deliveries
+            SET status = $3, attempts = $4, next_attempt_at = $5, last_response_status = $6,
+                last_error = $7, delivered_at = $8
+            WHERE tenant_id = $1 AND id = $2
+            "#,
+            delivery.tenant.as_ref(),
+            delivery.id.as_ref(),
+            delivery.status.as_str(),
+            delivery.attempts as i32,
+            delivery.next_attempt_at,
+            delivery.last_response_status.map(i32::from),
+            delivery.last_error,
+            delivery.delivered_at,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| WebhookStoreError::UnexpectedError(e.into()))?;
+
+        if result.rows_affected() == 0 {
+            return Err(WebhookStoreError::DeliveryNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+struct WebhookRow {
+    tenant_id: String,
+    id: Uuid,
+    url: String,
+    events: Vec<String>,
+    secret: String,
+    created_at: DateTime<Utc>,
+}
+
+fn to_webhook(row: WebhookRow) -> Result<Webhook, Report> {
+    Ok(Webhook {
+        id: WebhookId::from(row.id),
+        tenant: TenantId::parse(row.tenant_id)?,
+        url: row.url,
+        events: row
+            .events
+            .iter()
+            .map(|event| WebhookEventType::parse(event))
+            .collect::<Result<_>>()?,
+        secret: Secret::new(row.secret),
+        created_at: row.created_at,
+    })
+}
+
+struct WebhookDeliveryRow {
+    id: Uuid,
+    tenant_id: String,
+    webhook_id: Uuid,
+    event_type: String,
+    payload: String,
+    status: String,
+    attempts: i32,
+    next_attempt_at: DateTime<Utc>,
+    last_response_status: Option<i32>,
+    last_error: Option<String>,
+    created_at: DateTime<Utc>,
+    delivered_at: Option<DateTime<Utc>>,
+}
+
+fn to_webhook_delivery(row: WebhookDeliveryRow) -> Result<WebhookDelivery, Report> {
+    Ok(WebhookDelivery {
+        id: WebhookDeliveryId::from(row.id),
+        webhook_id: WebhookId::from(row.webhook_id),
+        tenant: TenantId::parse(row.tenant_id)?,
+        event_type: WebhookEventType::parse(&row.event_type)?,
+        payload: row.payload,
+        status: WebhookDeliveryStatus::parse(&row.status)?,
+        attempts: row
+            .attempts
+            .try_into()
+            .wrap_err("invalid webhook delivery attempts")?,
+        next_attempt_at: row.next_attempt_at,
+        last_response_status: row
+            .last_response_status
+            .map(u16::try_from)
+            .transpose()
+            .wrap_err("invalid webhook response status")?,
+        last_error: row.last_error,
+        created_at: row.created_at,
+        delivered_at: row.delivered_at,
+    })
+}
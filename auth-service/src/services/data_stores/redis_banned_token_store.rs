@@ -6,7 +6,10 @@ use secrecy::{ExposeSecret, Secret};
 use tokio::sync::RwLock;
 
 use crate::{
-    domain::data_stores::{BannedTokenStore, BannedTokenStoreError},
+    domain::{
+        data_stores::{BannedTokenStore, BannedTokenStoreError},
+        Email, TenantId,
+    },
     utils::auth::TOKEN_TTL_SECONDS,
 };
 
@@ -58,12 +61,64 @@ impl BannedTokenStore for RedisBannedTokenStore {
 
         Ok(is_banned)
     }
+
+    #[tracing::instrument(name = "Banning user tokens", skip_all)]
+    async fn ban_user_tokens(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+        issued_up_to: i64,
+    ) -> Result<(), BannedTokenStoreError> {
+        let key = get_user_key(tenant, email);
+
+        // Every token covered by the ban has expired once the TTL is up
+        let ttl: u64 = TOKEN_TTL_SECONDS
+            .try_into()
+            .wrap_err("failed to cast TOKEN_TTL_SECONDS to u64")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(&key, issued_up_to, ttl)
+            .wrap_err("failed to set user token ban in Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Getting user token ban", skip_all)]
+    async fn get_user_tokens_ban(
+        &self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<Option<i64>, BannedTokenStoreError> {
+        let key = get_user_key(tenant, email);
+
+        self.conn
+            .write()
+            .await
+            .get(&key)
+            .wrap_err("failed to get user token ban from Redis")
+            .map_err(BannedTokenStoreError::UnexpectedError)
+    }
 }
 
 // We are using a key prefix to prevent collisions and organize data!
 const BANNED_TOKEN_KEY_PREFIX: &str = "banned_token:";
+const BANNED_USER_KEY_PREFIX: &str = "banned_user_tokens:";
 
 #[tracing::instrument(name = "Get key", skip_all)]
 fn get_key(token: &str) -> String {
     format!("{}{}", BANNED_TOKEN_KEY_PREFIX, token)
 }
+
+fn get_user_key(tenant: &TenantId, email: &Email) -> String {
+    format!(
+        "{}{}:{}",
+        BANNED_USER_KEY_PREFIX,
+        tenant.as_ref(),
+        email.as_ref().expose_secret()
+    )
+}
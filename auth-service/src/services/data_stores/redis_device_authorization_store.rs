@@ -0,0 +1,231 @@
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{DeviceAuthorizationStore, DeviceAuthorizationStoreError},
+    ClientId, DeviceAuthorization, DeviceAuthorizationStatus, DeviceCode, Email, TenantId,
+    UserCode,
+};
+
+// Each authorization is stored under its device code, with a second key mapping its
+// user code to the device code. Both expire along with the authorization.
+pub struct RedisDeviceAuthorizationStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisDeviceAuthorizationStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl DeviceAuthorizationStore for RedisDeviceAuthorizationStore {
+    #[tracing::instrument(name = "Adding device authorization", skip_all)]
+    async fn add_authorization(
+        &mut self,
+        authorization: DeviceAuthorization,
+    ) -> Result<(), DeviceAuthorizationStoreError> {
+        let mut conn = self.conn.write().await;
+        let ttl = ttl_seconds(&authorization)?;
+
+        let _: () = conn
+            .set_ex(
+                get_user_code_key(&authorization.user_code),
+                authorization.device_code.as_ref().expose_secret(),
+                ttl,
+            )
+            .wrap_err("failed to set device user code in Redis")
+            .map_err(DeviceAuthorizationStoreError::UnexpectedError)?;
+
+        set_entry(&mut conn, &authorization, ttl)
+    }
+
+    #[tracing::instrument(name = "Getting device authorization by device code", skip_all)]
+    async fn get_by_device_code(
+        &self,
+        device_code: &DeviceCode,
+    ) -> Result<DeviceAuthorization, DeviceAuthorizationStoreError> {
+        get_entry(
+            &mut *self.conn.write().await,
+            device_code.as_ref().expose_secret(),
+        )
+    }
+
+    #[tracing::instrument(name = "Getting device authorization by user code", skip_all)]
+    async fn get_by_user_code(
+        &self,
+        user_code: &UserCode,
+    ) -> Result<DeviceAuthorization, DeviceAuthorizationStoreError> {
+        let mut conn = self.conn.write().await;
+
+        let device_code: Option<String> = conn
+            .get(get_user_code_key(user_code))
+            .wrap_err("failed to get device user code from Redis")
+            .map_err(DeviceAuthorizationStoreError::UnexpectedError)?;
+        let device_code =
+            device_code.ok_or(DeviceAuthorizationStoreError::AuthorizationNotFound)?;
+
+        get_entry(&mut conn, &device_code)
+    }
+
+    #[tracing::instrument(name = "Updating device authorization", skip_all)]
+    async fn update_authorization(
+        &mut self,
+        authorization: DeviceAuthorization,
+    ) -> Result<(), DeviceAuthorizationStoreError> {
+        let mut conn = self.conn.write().await;
+
+        let exists: bool = conn
+            .exists(get_key(authorization.device_code.as_ref().expose_secret()))
+            .wrap_err("failed to check device authorization in Redis")
+            .map_err(DeviceAuthorizationStoreError::UnexpectedError)?;
+        if !exists {
+            return Err(DeviceAuthorizationStoreError::AuthorizationNotFound);
+        }
+
+        let ttl = ttl_seconds(&authorization)?;
+        set_entry(&mut conn, &authorization, ttl)
+    }
+
+    #[tracing::instrument(name = "Removing device authorization", skip_all)]
+    async fn remove_authorization(
+        &mut self,
+        authorization: &DeviceAuthorization,
+    ) -> Result<(), DeviceAuthorizationStoreError> {
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .del(&[
+                get_key(authorization.device_code.as_ref().expose_secret()),
+                get_user_code_key(&authorization.user_code),
+            ])
+            .wrap_err("failed to delete device authorization from Redis")
+            .map_err(DeviceAuthorizationStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+}
+
+// The time the authorization has left, which is never updated once it's stored
+fn ttl_seconds(authorization: &DeviceAuthorization) -> Result<u64, DeviceAuthorizationStoreError> {
+    let remaining = (authorization.expires_at - Utc::now()).num_seconds();
+    if remaining <= 0 {
+        return Err(DeviceAuthorizationStoreError::AuthorizationNotFound);
+    }
+
+    Ok(remaining as u64)
+}
+
+#[tracing::instrument(name = "Getting device authorization entry", skip_all)]
+fn get_entry(
+    conn: &mut Connection,
+    device_code: &str,
+) -> Result<DeviceAuthorization, DeviceAuthorizationStoreError> {
+    let value: Option<String> = conn
+        .get(get_key(device_code))
+        .wrap_err("failed to get device authorization from Redis")
+        .map_err(DeviceAuthorizationStoreError::UnexpectedError)?;
+    let value = value.ok_or(DeviceAuthorizationStoreError::AuthorizationNotFound)?;
+
+    let entry: DeviceAuthorizationEntry = serde_json::from_str(&value)
+        .wrap_err("failed to deserialize device authorization")
+        .map_err(DeviceAuthorizationStoreError::UnexpectedError)?;
+
+    entry
+        .into_authorization(device_code)
+        .map_err(DeviceAuthorizationStoreError::UnexpectedError)
+}
+
+#[tracing::instrument(name = "Setting device authorization entry", skip_all)]
+fn set_entry(
+    conn: &mut Connection,
+    authorization: &DeviceAuthorization,
+    ttl: u64,
+) -> Result<(), DeviceAuthorizationStoreError> {
+    let entry = DeviceAuthorizationEntry {
+        user_code: authorization.user_code.as_ref().to_owned(),
+        client_id: authorization.client_id.as_ref().to_owned(),
+        tenant: authorization.tenant.as_ref().to_owned(),
+        status: authorization.status,
+        user: authorization
+            .user
+            .as_ref()
+            .map(|email| email.as_ref().expose_secret().to_owned()),
+        interval_seconds: authorization.interval_seconds,
+        last_polled_at: authorization.last_polled_at.map(|at| at.timestamp()),
+        expires_at: authorization.expires_at.timestamp(),
+    };
+    let json = serde_json::to_string(&entry)
+        .wrap_err("failed to serialize device authorization")
+        .map_err(DeviceAuthorizationStoreError::UnexpectedError)?;
+
+    let _: () = conn
+        .set_ex(
+            get_key(authorization.device_code.as_ref().expose_secret()),
+            json,
+            ttl,
+        )
+        .wrap_err("failed to set device authorization in Redis")
+        .map_err(DeviceAuthorizationStoreError::UnexpectedError)?;
+
+    Ok(())
+}
+
+#[derive(Serialize, Deserialize)]
+struct DeviceAuthorizationEntry {
+    user_code: String,
+    client_id: String,
+    tenant: String,
+    status: DeviceAuthorizationStatus,
+    user: Option<String>,
+    interval_seconds: i64,
+    last_polled_at: Option<i64>,
+    expires_at: i64,
+}
+
+impl DeviceAuthorizationEntry {
+    fn into_authorization(
+        self,
+        device_code: &str,
+    ) -> color_eyre::eyre::Result<DeviceAuthorization> {
+        Ok(DeviceAuthorization {
+            device_code: DeviceCode::parse(Secret::new(device_code.to_owned()))?,
+            user_code: UserCode::parse(self.user_code)?,
+            client_id: ClientId::parse(self.client_id)?,
+            tenant: TenantId::parse(self.tenant)?,
+            status: self.status,
+            user: self
+                .user
+                .map(|email| Email::parse(Secret::new(email)))
+                .transpose()?,
+            interval_seconds: self.interval_seconds,
+            last_polled_at: self.last_polled_at.map(timestamp_to_datetime).transpose()?,
+            expires_at: timestamp_to_datetime(self.expires_at)?,
+        })
+    }
+}
+
+fn timestamp_to_datetime(timestamp: i64) -> color_eyre::eyre::Result<DateTime<Utc>> {
+    DateTime::from_timestamp(timestamp, 0)
+        .ok_or_else(|| color_eyre::eyre::eyre!("invalid timestamp {}", timestamp))
+}
+
+const DEVICE_CODE_PREFIX: &str = "device_code:";
+const DEVICE_USER_CODE_PREFIX: &str = "device_user_code:";
+
+#[tracing::instrument(name = "Getting key", skip_all)]
+fn get_key(device_code: &str) -> String {
+    format!("{}{}", DEVICE_CODE_PREFIX, device_code)
+}
+
+#[tracing::instrument(name = "Getting user code key", skip_all)]
+fn get_user_code_key(user_code: &UserCode) -> String {
+    format!("{}{}", DEVICE_USER_CODE_PREFIX, user_code.as_ref())
+}
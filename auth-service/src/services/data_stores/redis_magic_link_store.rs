@@ -0,0 +1,103 @@
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::{
+    domain::{
+        data_stores::{MagicLinkId, MagicLinkStore, MagicLinkStoreError},
+        Email, TenantId,
+    },
+    utils::auth::MAGIC_LINK_TTL_SECONDS,
+};
+
+pub struct RedisMagicLinkStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisMagicLinkStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl MagicLinkStore for RedisMagicLinkStore {
+    #[tracing::instrument(name = "Adding magic link", skip_all)]
+    async fn add_link(
+        &mut self,
+        link_id: MagicLinkId,
+        tenant: TenantId,
+        email: Email,
+    ) -> Result<(), MagicLinkStoreError> {
+        let key = get_key(&link_id);
+
+        let entry = MagicLinkEntry {
+            tenant: tenant.as_ref().to_owned(),
+            email: email.as_ref().expose_secret().to_owned(),
+        };
+        let json = serde_json::to_string(&entry)
+            .wrap_err("failed to serialize magic link")
+            .map_err(MagicLinkStoreError::UnexpectedError)?;
+
+        let ttl: u64 = MAGIC_LINK_TTL_SECONDS
+            .try_into()
+            .wrap_err("failed to cast MAGIC_LINK_TTL_SECONDS to u64")
+            .map_err(MagicLinkStoreError::UnexpectedError)?;
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(&key, json, ttl)
+            .wrap_err("failed to set magic link in Redis")
+            .map_err(MagicLinkStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Consuming magic link", skip_all)]
+    async fn consume_link(
+        &mut self,
+        link_id: &MagicLinkId,
+    ) -> Result<(TenantId, Email), MagicLinkStoreError> {
+        let key = get_key(link_id);
+
+        // GETDEL reads and removes the link in one step, so of two requests racing for
+        // the same link only one gets it
+        let value: Option<String> = self
+            .conn
+            .write()
+            .await
+            .get_del(&key)
+            .wrap_err("failed to get magic link from Redis")
+            .map_err(MagicLinkStoreError::UnexpectedError)?;
+
+        let value = value.ok_or(MagicLinkStoreError::LinkNotFound)?;
+
+        let entry: MagicLinkEntry = serde_json::from_str(&value)
+            .wrap_err("failed to deserialize magic link")
+            .map_err(MagicLinkStoreError::UnexpectedError)?;
+
+        let tenant = TenantId::parse(entry.tenant).map_err(MagicLinkStoreError::UnexpectedError)?;
+        let email =
+            Email::parse(Secret::new(entry.email)).map_err(MagicLinkStoreError::UnexpectedError)?;
+
+        Ok((tenant, email))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct MagicLinkEntry {
+    tenant: String,
+    email: String,
+}
+
+const MAGIC_LINK_PREFIX: &str = "magic_link:";
+
+#[tracing::instrument(name = "Getting key", skip_all)]
+fn get_key(link_id: &MagicLinkId) -> String {
+    format!("{}{}", MAGIC_LINK_PREFIX, link_id.as_ref().expose_secret())
+}
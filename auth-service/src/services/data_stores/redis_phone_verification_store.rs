@@ -0,0 +1,128 @@
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{
+        PhoneVerificationStore, PhoneVerificationStoreError, TwoFACode,
+        PHONE_VERIFICATION_COOLDOWN_SECONDS, PHONE_VERIFICATION_TTL_SECONDS,
+    },
+    Email, PhoneNumber, TenantId,
+};
+
+pub struct RedisPhoneVerificationStore {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisPhoneVerificationStore {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl PhoneVerificationStore for RedisPhoneVerificationStore {
+    #[tracing::instrument(name = "Adding pending phone verification", skip_all)]
+    async fn add_pending(
+        &mut self,
+        tenant: TenantId,
+        email: Email,
+        phone_number: PhoneNumber,
+        code: TwoFACode,
+    ) -> Result<(), PhoneVerificationStoreError> {
+        let key = get_key(&tenant, &email);
+
+        // Only one request can start the cooldown, however many replicas take them
+        let started: Option<String> = redis::cmd("SET")
+            .arg(get_cooldown_key(&tenant, &email))
+            .arg(1)
+            .arg("NX")
+            .arg("EX")
+            .arg(PHONE_VERIFICATION_COOLDOWN_SECONDS)
+            .query(&mut *self.conn.write().await)
+            .wrap_err("failed to set phone verification cooldown in Redis")
+            .map_err(PhoneVerificationStoreError::UnexpectedError)?;
+        if started.is_none() {
+            return Err(PhoneVerificationStoreError::Cooldown);
+        }
+
+        let tuple = PendingTuple(
+            phone_number.as_ref().expose_secret().to_owned(),
+            code.as_ref().expose_secret().to_owned(),
+        );
+        let json = serde_json::to_string(&tuple)
+            .wrap_err("failed to serialize pending phone verification")
+            .map_err(PhoneVerificationStoreError::UnexpectedError)?;
+
+        let _: () = self
+            .conn
+            .write()
+            .await
+            .set_ex(&key, json, PHONE_VERIFICATION_TTL_SECONDS as u64)
+            .wrap_err("failed to set pending phone verification in Redis")
+            .map_err(PhoneVerificationStoreError::UnexpectedError)?;
+
+        Ok(())
+    }
+
+    #[tracing::instrument(name = "Taking pending phone verification", skip_all)]
+    async fn take_pending(
+        &mut self,
+        tenant: &TenantId,
+        email: &Email,
+    ) -> Result<(PhoneNumber, TwoFACode), PhoneVerificationStoreError> {
+        let key = get_key(tenant, email);
+
+        // GETDEL reads and removes the verification in one step, so a code can only be
+        // tried once however many requests race for it
+        let value: Option<String> = self
+            .conn
+            .write()
+            .await
+            .get_del(&key)
+            .wrap_err("failed to get pending phone verification from Redis")
+            .map_err(PhoneVerificationStoreError::UnexpectedError)?;
+
+        let value = value.ok_or(PhoneVerificationStoreError::VerificationNotFound)?;
+
+        let data: PendingTuple = serde_json::from_str(&value)
+            .wrap_err("failed to deserialize pending phone verification")
+            .map_err(PhoneVerificationStoreError::UnexpectedError)?;
+
+        let phone_number = PhoneNumber::parse(Secret::new(data.0))
+            .map_err(PhoneVerificationStoreError::UnexpectedError)?;
+
+        let code = TwoFACode::parse(Secret::new(data.1))
+            .map_err(PhoneVerificationStoreError::UnexpectedError)?;
+
+        Ok((phone_number, code))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct PendingTuple(pub String, pub String);
+
+const PHONE_VERIFICATION_PREFIX: &str = "phone_verification:";
+const PHONE_VERIFICATION_COOLDOWN_PREFIX: &str = "phone_verification_cooldown:";
+
+#[tracing::instrument(name = "Getting key", skip_all)]
+fn get_key(tenant: &TenantId, email: &Email) -> String {
+    format!(
+        "{}{}:{}",
+        PHONE_VERIFICATION_PREFIX,
+        tenant.as_ref(),
+        email.as_ref().expose_secret()
+    )
+}
+
+fn get_cooldown_key(tenant: &TenantId, email: &Email) -> String {
+    format!(
+        "{}{}:{}",
+        PHONE_VERIFICATION_COOLDOWN_PREFIX,
+        tenant.as_ref(),
+        email.as_ref().expose_secret()
+    )
+}
@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Context;
+use redis::{Commands, Connection, ExistenceCheck, SetExpiry, SetOptions};
+use tokio::sync::RwLock;
+
+use crate::domain::{
+    data_stores::{SamlReplayCache, SamlReplayCacheError},
+    ProviderId, TenantId,
+};
+
+pub struct RedisSamlReplayCache {
+    conn: Arc<RwLock<Connection>>,
+}
+
+impl RedisSamlReplayCache {
+    pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
+        Self { conn }
+    }
+}
+
+#[async_trait::async_trait]
+impl SamlReplayCache for RedisSamlReplayCache {
+    #[tracing::instrument(name = "Recording SAML assertion", skip_all)]
+    async fn record_assertion(
+        &mut self,
+        tenant: &TenantId,
+        provider: &ProviderId,
+        assertion_id: &str,
+        expires_at: DateTime<Utc>,
+    ) -> Result<(), SamlReplayCacheError> {
+        let key = get_key(tenant, provider, assertion_id);
+        let ttl = (expires_at - Utc::now()).num_seconds().max(1) as usize;
+
+        // Only set if it isn't there yet, so two replicas can't both accept the assertion
+        let options = SetOptions::default()
+            .conditional_set(ExistenceCheck::NX)
+            .with_expiration(SetExpiry::EX(ttl));
+        let recorded: Option<String> = self
+            .conn
+            .write()
+            .await
+            .set_options(&key, true, options)
+            .wrap_err("failed to record SAML assertion in Redis")
+            .map_err(SamlReplayCacheError::UnexpectedError)?;
+
+        match recorded {
+            Some(_) => Ok(()),
+            None => Err(SamlReplayCacheError::AssertionReplayed),
+        }
+    }
+}
+
+const SAML_ASSERTION_PREFIX: &str = "saml_assertion:";
+
+fn get_key(tenant: &TenantId, provider: &ProviderId, assertion_id: &str) -> String {
+    format!(
+        "{}{}:{}:{}",
+        SAML_ASSERTION_PREFIX,
+        tenant.as_ref(),
+        provider.as_ref(),
+        assertion_id
+    )
+}
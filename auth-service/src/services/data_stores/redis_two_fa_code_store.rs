@@ -1,22 +1,35 @@
-use color_eyre::eyre::Context;
-use redis::{Commands, Connection};
+use chrono::Utc;
+use color_eyre::eyre::{Context, Report};
+use redis::{Commands, Connection, ExistenceCheck, SetExpiry, SetOptions};
 use secrecy::{ExposeSecret, Secret};
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use tokio::sync::RwLock;
 
 use crate::domain::{
-    data_stores::{LoginAttemptId, TwoFACode, TwoFACodeStore, TwoFACodeStoreError},
-    Email,
+    data_stores::{
+        LoginAttemptId, TwoFAAttempt, TwoFACode, TwoFACodeStore, TwoFACodeStoreError,
+        TwoFAResendPolicy, MAX_TWO_FA_FAILED_ATTEMPTS,
+    },
+    AuthMethod, ClientFingerprint, Email, TenantId, TwoFAClientPolicy,
 };
 
 pub struct RedisTwoFACodeStore {
     conn: Arc<RwLock<Connection>>,
+    resend_policy: TwoFAResendPolicy,
 }
 
 impl RedisTwoFACodeStore {
     pub fn new(conn: Arc<RwLock<Connection>>) -> Self {
-        Self { conn }
+        Self {
+            conn,
+            resend_policy: TwoFAResendPolicy::default(),
+        }
+    }
+
+    pub fn with_resend_policy(mut self, resend_policy: TwoFAResendPolicy) -> Self {
+        self.resend_policy = resend_policy;
+        self
     }
 }
 
@@ -25,51 +38,33 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
     #[tracing::instrument(name = "Adding 2FA code", skip_all)]
     async fn add_code(
         &mut self,
-        email: Email,
         login_attempt_id: LoginAttemptId,
-        code: TwoFACode,
+        attempt: TwoFAAttempt,
     ) -> Result<(), TwoFACodeStoreError> {
-        // TODO:
-        // 1. Create a new key using the get_key helper function.
-        // 2. Create a TwoFATuple instance.
-        // 3. Use serde_json::to_string to serialize the TwoFATuple instance into a JSON string.
-        // Return TwoFACodeStoreError::UnexpectedError if serialization fails.
-        // 4. Call the set_ex command on the Redis connection to set a new key/value pair with an expiration time (TTL).
-        // The value should be the serialized 2FA tuple.
-        // The expiration time should be set to TEN_MINUTES_IN_SECONDS.
-        // Return TwoFACodeStoreError::UnexpectedError if casting fails or the call to set_ex fails.
-
-        let key = get_key(&email);
-
-        let tuple = TwoFATuple(
-            login_attempt_id.as_ref().expose_secret().to_string(),
-            code.as_ref().expose_secret().to_string(),
-        );
-        let json = serde_json::to_string(&tuple)
-            .wrap_err("failed to serialize 2FA tuple")
-            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        let key = get_key(&login_attempt_id);
 
-        let _: () = self
-            .conn
-            .write()
-            .await
-            .set_ex(&key, json, TEN_MINUTES_IN_SECONDS)
-            .wrap_err("failed to set 2FA code in Redis")
-            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        let entry = TwoFAEntry {
+            tenant: attempt.tenant.as_ref().to_owned(),
+            email: attempt.email.as_ref().expose_secret().to_string(),
+            code: attempt.code.as_ref().expose_secret().to_string(),
+            client: attempt.client,
+            first_factor: attempt.first_factor,
+            resend_count: 0,
+            last_sent_at: Utc::now().timestamp(),
+            failed_attempts: 0,
+        };
 
-        Ok(())
+        set_entry(&mut *self.conn.write().await, &key, &entry)
     }
 
     #[tracing::instrument(name = "Removing 2FA code", skip_all)]
-    async fn remove_code(&mut self, email: &Email) -> Result<(), TwoFACodeStoreError> {
-        // TODO:
-        // 1. Create a new key using the get_key helper function.
-        // 2. Call the del command on the Redis connection to delete the 2FA code entry.
-        // Return TwoFACodeStoreError::UnexpectedError if the operation fails.
-
-        let key = get_key(email);
+    async fn remove_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let key = get_key(login_attempt_id);
 
-        let _ = self
+        let _: () = self
             .conn
             .write()
             .await
@@ -83,36 +78,219 @@ impl TwoFACodeStore for RedisTwoFACodeStore {
     #[tracing::instrument(name = "Getting 2FA code", skip_all)]
     async fn get_code(
         &self,
+        login_attempt_id: &LoginAttemptId,
+        email: &Email,
+    ) -> Result<TwoFAAttempt, TwoFACodeStoreError> {
+        let key = get_key(login_attempt_id);
+
+        let entry = get_entry(&mut *self.conn.write().await, &key, email)?;
+
+        entry.into_attempt(email)
+    }
+
+    #[tracing::instrument(name = "Consuming 2FA code", skip_all)]
+    async fn consume_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
         email: &Email,
-    ) -> Result<(LoginAttemptId, TwoFACode), TwoFACodeStoreError> {
-         let key = get_key(email);
+        code: &TwoFACode,
+        client: &ClientFingerprint,
+        client_policy: &TwoFAClientPolicy,
+    ) -> Result<TwoFAAttempt, TwoFACodeStoreError> {
+        let key = get_key(login_attempt_id);
+        let mut conn = self.conn.write().await;
+
+        // GETDEL takes the entry out in one step, so of two requests racing for the same
+        // attempt only one gets it. Anything but the right code puts it back with the TTL
+        // it had left.
+        let (ttl_millis, value): (i64, Option<String>) = redis::pipe()
+            .atomic()
+            .pttl(&key)
+            .get_del(&key)
+            .query(&mut *conn)
+            .wrap_err("failed to take 2FA code from Redis")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-        match self.conn.write().await.get::<_, String>(&key) {
-            Ok(value) => {
-                let data: TwoFATuple = serde_json::from_str(&value)
-                    .wrap_err("failed to deserialize 2FA tuple")
-                    .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        let value = value.ok_or(TwoFACodeStoreError::LoginAttemptIdNotFound)?;
+        let mut entry: TwoFAEntry = serde_json::from_str(&value)
+            .wrap_err("failed to deserialize 2FA entry")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
 
-                let login_attempt_id = LoginAttemptId::parse(Secret::new(data.0))
-                    .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        if &entry.email != email.as_ref().expose_secret() {
+            restore_entry(&mut conn, &key, &entry, ttl_millis)?;
+            return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+        }
 
-                let email_code = TwoFACode::parse(Secret::new(data.1))
-                    .map_err(TwoFACodeStoreError::UnexpectedError)?;
+        if !client_policy.allows(&entry.client, client) {
+            restore_entry(&mut conn, &key, &entry, ttl_millis)?;
+            return Err(TwoFACodeStoreError::ClientNotAllowed);
+        }
 
-                Ok((login_attempt_id, email_code))
+        if &entry.code != code.as_ref().expose_secret() {
+            entry.failed_attempts += 1;
+            if entry.failed_attempts < MAX_TWO_FA_FAILED_ATTEMPTS {
+                restore_entry(&mut conn, &key, &entry, ttl_millis)?;
             }
-            Err(_) => Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+            return Err(TwoFACodeStoreError::IncorrectCode);
         }
+
+        entry.into_attempt(email)
     }
+
+    #[tracing::instrument(name = "Re-sending 2FA code", skip_all)]
+    async fn resend_code(
+        &mut self,
+        login_attempt_id: &LoginAttemptId,
+        email: &Email,
+        code: TwoFACode,
+    ) -> Result<(), TwoFACodeStoreError> {
+        let key = get_key(login_attempt_id);
+        let code = code.as_ref().expose_secret();
+        let policy = self.resend_policy;
+        let mut conn = self.conn.write().await;
+
+        // WATCH makes the write fail if another replica touched the entry after we read
+        // it, in which case the policy is checked again against what that replica wrote.
+        // KEEPTTL keeps the attempt's original expiry, so resending can't prolong it.
+        redis::transaction(&mut *conn, &[&key], |conn, pipe| {
+            let mut entry = match get_entry(conn, &key, email) {
+                Ok(entry) => entry,
+                Err(e) => return Ok(Some(Err(e))),
+            };
+
+            if entry.resend_count >= policy.max_resends {
+                return Ok(Some(Err(TwoFACodeStoreError::ResendLimitReached)));
+            }
+
+            let now = Utc::now().timestamp();
+            if now - entry.last_sent_at < policy.cooldown_seconds {
+                return Ok(Some(Err(TwoFACodeStoreError::ResendCooldown)));
+            }
+
+            entry.code = code.to_string();
+            entry.resend_count += 1;
+            entry.last_sent_at = now;
+
+            let json = match serde_json::to_string(&entry) {
+                Ok(json) => json,
+                Err(e) => {
+                    let e = Report::new(e).wrap_err("failed to serialize 2FA entry");
+                    return Ok(Some(Err(TwoFACodeStoreError::UnexpectedError(e))));
+                }
+            };
+
+            let options = SetOptions::default().with_expiration(SetExpiry::KEEPTTL);
+            let written: Option<()> = pipe.set_options(&key, json, options).ignore().query(conn)?;
+            Ok(written.map(Ok))
+        })
+        .wrap_err("failed to re-send 2FA code in Redis")
+        .map_err(TwoFACodeStoreError::UnexpectedError)?
+    }
+}
+
+#[tracing::instrument(name = "Getting 2FA entry", skip_all)]
+fn get_entry(
+    conn: &mut Connection,
+    key: &str,
+    email: &Email,
+) -> Result<TwoFAEntry, TwoFACodeStoreError> {
+    let entry: TwoFAEntry = match conn.get::<_, String>(key) {
+        Ok(value) => serde_json::from_str(&value)
+            .wrap_err("failed to deserialize 2FA entry")
+            .map_err(TwoFACodeStoreError::UnexpectedError)?,
+        Err(_) => return Err(TwoFACodeStoreError::LoginAttemptIdNotFound),
+    };
+
+    // An attempt started for another account is treated as unknown
+    if &entry.email != email.as_ref().expose_secret() {
+        return Err(TwoFACodeStoreError::LoginAttemptIdNotFound);
+    }
+
+    Ok(entry)
+}
+
+#[tracing::instrument(name = "Setting 2FA entry", skip_all)]
+fn set_entry(
+    conn: &mut Connection,
+    key: &str,
+    entry: &TwoFAEntry,
+) -> Result<(), TwoFACodeStoreError> {
+    let json = serde_json::to_string(entry)
+        .wrap_err("failed to serialize 2FA entry")
+        .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+    let _: () = conn
+        .set_ex(key, json, TEN_MINUTES_IN_SECONDS)
+        .wrap_err("failed to set 2FA code in Redis")
+        .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+    Ok(())
+}
+
+// Puts back an entry taken out by `consume_code`, unless it expired in the meantime or
+// a new one was stored under the same key
+#[tracing::instrument(name = "Restoring 2FA entry", skip_all)]
+fn restore_entry(
+    conn: &mut Connection,
+    key: &str,
+    entry: &TwoFAEntry,
+    ttl_millis: i64,
+) -> Result<(), TwoFACodeStoreError> {
+    let ttl_millis = match usize::try_from(ttl_millis) {
+        Ok(ttl_millis) if ttl_millis > 0 => ttl_millis,
+        _ => return Ok(()),
+    };
+
+    let json = serde_json::to_string(entry)
+        .wrap_err("failed to serialize 2FA entry")
+        .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+    let options = SetOptions::default()
+        .conditional_set(ExistenceCheck::NX)
+        .with_expiration(SetExpiry::PX(ttl_millis));
+
+    let _: Option<String> = conn
+        .set_options(key, json, options)
+        .wrap_err("failed to restore 2FA code in Redis")
+        .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+    Ok(())
 }
 
 #[derive(Serialize, Deserialize)]
-struct TwoFATuple(pub String, pub String);
+struct TwoFAEntry {
+    tenant: String,
+    email: String,
+    code: String,
+    client: ClientFingerprint,
+    first_factor: AuthMethod,
+    resend_count: u32,
+    last_sent_at: i64,
+    // Entries stored before misses were counted have none
+    #[serde(default)]
+    failed_attempts: u32,
+}
+
+impl TwoFAEntry {
+    fn into_attempt(self, email: &Email) -> Result<TwoFAAttempt, TwoFACodeStoreError> {
+        let code = TwoFACode::parse(Secret::new(self.code))
+            .map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        let tenant = TenantId::parse(self.tenant).map_err(TwoFACodeStoreError::UnexpectedError)?;
+
+        Ok(TwoFAAttempt::new(tenant, email.clone(), code, self.client)
+            .with_first_factor(self.first_factor))
+    }
+}
 
 const TEN_MINUTES_IN_SECONDS: u64 = 600;
 const TWO_FA_CODE_PREFIX: &str = "two_fa_code:";
 
 #[tracing::instrument(name = "Getting key", skip_all)]
-fn get_key(email: &Email) -> String {
-    format!("{}{}", TWO_FA_CODE_PREFIX, email.as_ref().expose_secret())
+fn get_key(login_attempt_id: &LoginAttemptId) -> String {
+    format!(
+        "{}{}",
+        TWO_FA_CODE_PREFIX,
+        login_attempt_id.as_ref().expose_secret()
+    )
 }
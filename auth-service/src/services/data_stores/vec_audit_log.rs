@@ -0,0 +1,165 @@
+use crate::domain::{
+    data_stores::{AuditLog, AuditLogError},
+    AuditCheckpoint, AuditEvent, AuditLink, AuditQuery, TenantId, AUDIT_CHAIN_GENESIS_HASH,
+};
+
+// Keeps events in the order they were appended, numbered from 1
+#[derive(Default)]
+pub struct VecAuditLog {
+    links: Vec<AuditLink>,
+    checkpoints: Vec<AuditCheckpoint>,
+}
+
+#[async_trait::async_trait]
+impl AuditLog for VecAuditLog {
+    async fn append(&mut self, event: AuditEvent) -> Result<(), AuditLogError> {
+        let previous_hash = self
+            .links
+            .last()
+            .and_then(|link| link.hash.as_deref())
+            .unwrap_or(AUDIT_CHAIN_GENESIS_HASH);
+        let link = AuditLink::new(self.links.len() as i64 + 1, event, previous_hash);
+        self.links.push(link);
+        Ok(())
+    }
+
+    async fn query(
+        &self,
+        tenant: &TenantId,
+        query: &AuditQuery,
+    ) -> Result<Vec<AuditEvent>, AuditLogError> {
+        Ok(self
+            .links
+            .iter()
+            .rev()
+            .map(|link| &link.event)
+            .filter(|event| event.tenant == *tenant && query.matches(event))
+            .skip(query.offset as usize)
+            .take(query.limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn chain(
+        &self,
+        after_sequence: i64,
+        limit: u64,
+    ) -> Result<Vec<AuditLink>, AuditLogError> {
+        Ok(self
+            .links
+            .iter()
+            .filter(|link| link.sequence > after_sequence)
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn chain_head(&self) -> Result<Option<AuditLink>, AuditLogError> {
+        Ok(self.links.last().cloned())
+    }
+
+    async fn add_checkpoint(&mut self, checkpoint: AuditCheckpoint) -> Result<(), AuditLogError> {
+        self.checkpoints.push(checkpoint);
+        Ok(())
+    }
+
+    async fn checkpoints(&self) -> Result<Vec<AuditCheckpoint>, AuditLogError> {
+        Ok(self.checkpoints.clone())
+    }
+
+    async fn latest_checkpoint(&self) -> Result<Option<AuditCheckpoint>, AuditLogError> {
+        Ok(self.checkpoints.last().cloned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{AuditOutcome, AuditRecord, ClientFingerprint};
+
+    fn event(tenant: &TenantId, record: AuditRecord) -> AuditEvent {
+        AuditEvent::new(tenant.clone(), record, &ClientFingerprint::default())
+    }
+
+    #[tokio::test]
+    async fn test_query_returns_newest_matching_events_first() {
+        let mut audit_log = VecAuditLog::default();
+        let tenant = TenantId::default();
+        let other_tenant = TenantId::parse("other".to_owned()).unwrap();
+
+        for i in 0..3 {
+            let record = AuditRecord::new("login")
+                .actor("alice@example.com")
+                .target(i);
+            audit_log.append(event(&tenant, record)).await.unwrap();
+        }
+        let failure = AuditRecord::new("login")
+            .actor("bob@example.com")
+            .failure("Incorrect credentials");
+        audit_log.append(event(&tenant, failure)).await.unwrap();
+        audit_log
+            .append(event(
+                &other_tenant,
+                AuditRecord::new("login").actor("alice@example.com"),
+            ))
+            .await
+            .unwrap();
+
+        let query = AuditQuery {
+            actor: Some("alice@example.com".to_owned()),
+            offset: 1,
+            limit: 10,
+            ..AuditQuery::default()
+        };
+        let targets: Vec<Option<String>> = audit_log
+            .query(&tenant, &query)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|event| event.target)
+            .collect();
+        assert_eq!(targets, vec![Some("1".to_owned()), Some("0".to_owned())]);
+
+        let query = AuditQuery {
+            outcome: Some(AuditOutcome::Failure),
+            limit: 10,
+            ..AuditQuery::default()
+        };
+        let events = audit_log.query(&tenant, &query).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].actor.as_deref(), Some("bob@example.com"));
+    }
+
+    #[tokio::test]
+    async fn test_chain_links_every_tenant_in_append_order() {
+        let mut audit_log = VecAuditLog::default();
+        let other_tenant = TenantId::parse("other".to_owned()).unwrap();
+
+        audit_log
+            .append(event(&TenantId::default(), AuditRecord::new("login")))
+            .await
+            .unwrap();
+        audit_log
+            .append(event(&other_tenant, AuditRecord::new("logout")))
+            .await
+            .unwrap();
+        audit_log
+            .append(event(&TenantId::default(), AuditRecord::new("signup")))
+            .await
+            .unwrap();
+
+        let links = audit_log.chain(0, 10).await.unwrap();
+        assert_eq!(links.len(), 3);
+        assert_eq!(
+            links[0].previous_hash.as_deref(),
+            Some(AUDIT_CHAIN_GENESIS_HASH)
+        );
+        assert_eq!(links[1].previous_hash, links[0].hash);
+        assert_eq!(links[2].previous_hash, links[1].hash);
+
+        let links = audit_log.chain(1, 1).await.unwrap();
+        assert_eq!(links.len(), 1);
+        assert_eq!(links[0].event.event, "logout");
+        assert_eq!(audit_log.chain_head().await.unwrap().unwrap().sequence, 3);
+    }
+}
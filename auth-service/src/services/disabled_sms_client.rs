@@ -0,0 +1,14 @@
+use color_eyre::eyre::{eyre, Result};
+
+use crate::domain::{PhoneNumber, SmsClient};
+
+// Used when no SMS provider is configured. Every message is refused, so phone numbers
+// can't be added and SMS can't be chosen for 2FA, while email keeps working.
+pub struct DisabledSmsClient;
+
+#[async_trait::async_trait]
+impl SmsClient for DisabledSmsClient {
+    async fn send_sms(&self, _recipient: &PhoneNumber, _content: &str) -> Result<()> {
+        Err(eyre!("SMS delivery is not configured"))
+    }
+}
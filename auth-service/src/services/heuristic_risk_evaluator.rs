@@ -0,0 +1,457 @@
+use std::{
+    collections::{BTreeMap, HashMap, VecDeque},
+    net::IpAddr,
+    sync::Arc,
+};
+
+use chrono::{DateTime, Duration, Utc};
+use color_eyre::eyre::Result;
+
+use crate::domain::{
+    Email, GeoIpLookup, GeoLocation, LoginContext, RiskAssessment, RiskEvaluator, RiskReason,
+    TenantId,
+};
+
+// Accounts tracked at once. Past this the least recently seen account is forgotten,
+// which only makes its next login look like a first one.
+const MAX_TRACKED_ACCOUNTS: usize = 100_000;
+// IP addresses and user agents remembered per account, most recent kept
+const MAX_KNOWN_CLIENTS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RiskPolicy {
+    // Combined reason weight at which a login is forced through 2FA
+    pub step_up_score: u32,
+    pub failure_burst_threshold: u32,
+    pub failure_window: Duration,
+    // Faster than a commercial flight between two logins counts as impossible travel
+    pub max_travel_speed_kmh: f64,
+    // GeoIP data is coarse, so short hops are never flagged
+    pub min_travel_distance_km: f64,
+}
+
+impl Default for RiskPolicy {
+    fn default() -> Self {
+        Self {
+            step_up_score: 2,
+            failure_burst_threshold: 5,
+            failure_window: Duration::minutes(15),
+            max_travel_speed_kmh: 1000.0,
+            min_travel_distance_km: 500.0,
+        }
+    }
+}
+
+// An account is an email within a tenant
+type AccountKey = (TenantId, Email);
+
+// Scores logins against what we have seen for the same account before. History is kept
+// in memory, so each replica learns on its own.
+pub struct HeuristicRiskEvaluator {
+    history: HashMap<AccountKey, LoginHistory>,
+    // Accounts by when they were last seen, oldest first, to know which one to forget
+    recency: BTreeMap<u64, AccountKey>,
+    clock: u64,
+    max_tracked_accounts: usize,
+    geoip: Option<Arc<dyn GeoIpLookup + Send + Sync>>,
+    policy: RiskPolicy,
+}
+
+impl Default for HeuristicRiskEvaluator {
+    fn default() -> Self {
+        Self {
+            history: HashMap::new(),
+            recency: BTreeMap::new(),
+            clock: 0,
+            max_tracked_accounts: MAX_TRACKED_ACCOUNTS,
+            geoip: None,
+            policy: RiskPolicy::default(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct LoginHistory {
+    last_seen: u64,
+    known_ip_addresses: VecDeque<IpAddr>,
+    known_user_agents: VecDeque<String>,
+    last_success: Option<(DateTime<Utc>, Option<GeoLocation>)>,
+    failures: VecDeque<DateTime<Utc>>,
+}
+
+fn account_key(context: &LoginContext) -> AccountKey {
+    (context.tenant.clone(), context.email.clone())
+}
+
+// Moves a known item to the back, or adds it there, dropping the oldest past the cap
+fn remember<T: PartialEq>(known: &mut VecDeque<T>, item: T) {
+    if let Some(index) = known.iter().position(|known| *known == item) {
+        known.remove(index);
+    }
+    known.push_back(item);
+    if known.len() > MAX_KNOWN_CLIENTS {
+        known.pop_front();
+    }
+}
+
+impl HeuristicRiskEvaluator {
+    pub fn with_geoip(mut self, geoip: Arc<dyn GeoIpLookup + Send + Sync>) -> Self {
+        self.geoip = Some(geoip);
+        self
+    }
+
+    pub fn with_policy(mut self, policy: RiskPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    #[cfg(test)]
+    fn with_max_tracked_accounts(mut self, max_tracked_accounts: usize) -> Self {
+        self.max_tracked_accounts = max_tracked_accounts;
+        self
+    }
+
+    // The account's history, marked as the most recently seen. A new account makes room
+    // by forgetting the least recently seen one.
+    fn touch(&mut self, context: &LoginContext) -> &mut LoginHistory {
+        self.clock += 1;
+        let now = self.clock;
+        let key = account_key(context);
+
+        match self.history.get_mut(&key) {
+            Some(history) => {
+                self.recency.remove(&history.last_seen);
+            }
+            None => {
+                if self.history.len() >= self.max_tracked_accounts {
+                    if let Some((_, oldest)) = self.recency.pop_first() {
+                        self.history.remove(&oldest);
+                    }
+                }
+            }
+        }
+        self.recency.insert(now, key.clone());
+
+        let history = self.history.entry(key).or_default();
+        history.last_seen = now;
+        history
+    }
+
+    fn locate(&self, ip_address: Option<IpAddr>) -> Option<GeoLocation> {
+        self.geoip.as_ref()?.locate(ip_address?)
+    }
+
+    fn recent_failures(&self, history: &LoginHistory, now: DateTime<Utc>) -> u32 {
+        let cutoff = now - self.policy.failure_window;
+        history.failures.iter().filter(|at| **at > cutoff).count() as u32
+    }
+
+    fn impossible_travel(
+        &self,
+        history: &LoginHistory,
+        context: &LoginContext,
+    ) -> Option<RiskReason> {
+        let (last_at, last_location) = history.last_success?;
+        let current_location = self.locate(context.client.ip_address)?;
+        let distance_km = last_location?.distance_km(&current_location);
+
+        if distance_km < self.policy.min_travel_distance_km {
+            return None;
+        }
+
+        // Treat near-simultaneous logins as a minute apart to avoid dividing by zero
+        let elapsed_minutes = (context.at - last_at).num_minutes().max(1);
+        let speed_kmh = distance_km / (elapsed_minutes as f64 / 60.0);
+
+        (speed_kmh > self.policy.max_travel_speed_kmh).then_some(RiskReason::ImpossibleTravel {
+            distance_km: distance_km.round() as u32,
+            elapsed_minutes,
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl RiskEvaluator for HeuristicRiskEvaluator {
+    async fn assess(&self, context: &LoginContext) -> Result<RiskAssessment> {
+        let Some(history) = self.history.get(&account_key(context)) else {
+            return Ok(RiskAssessment::low());
+        };
+
+        let mut reasons = Vec::new();
+
+        // Without a previous successful login there is nothing to compare against
+        if history.last_success.is_some() {
+            if let Some(ip_address) = context.client.ip_address {
+                if !history.known_ip_addresses.contains(&ip_address) {
+                    reasons.push(RiskReason::NewIpAddress);
+                }
+            }
+
+            if let Some(user_agent) = &context.client.user_agent {
+                if !history.known_user_agents.contains(user_agent) {
+                    reasons.push(RiskReason::NewUserAgent);
+                }
+            }
+
+            if let Some(reason) = self.impossible_travel(history, context) {
+                reasons.push(reason);
+            }
+        }
+
+        let failures = self.recent_failures(history, context.at);
+        if failures >= self.policy.failure_burst_threshold {
+            reasons.push(RiskReason::FailureBurst { failures });
+        }
+
+        Ok(RiskAssessment::new(reasons, self.policy.step_up_score))
+    }
+
+    async fn record_success(&mut self, context: &LoginContext) -> Result<()> {
+        let location = self.locate(context.client.ip_address);
+        let history = self.touch(context);
+
+        if let Some(ip_address) = context.client.ip_address {
+            remember(&mut history.known_ip_addresses, ip_address);
+        }
+        if let Some(user_agent) = &context.client.user_agent {
+            remember(&mut history.known_user_agents, user_agent.clone());
+        }
+        history.last_success = Some((context.at, location));
+        history.failures.clear();
+
+        Ok(())
+    }
+
+    async fn record_failure(&mut self, context: &LoginContext) -> Result<()> {
+        let cutoff = context.at - self.policy.failure_window;
+        let threshold = self.policy.failure_burst_threshold as usize;
+        let history = self.touch(context);
+
+        while history.failures.front().is_some_and(|at| *at <= cutoff) {
+            history.failures.pop_front();
+        }
+        history.failures.push_back(context.at);
+        // Reaching the threshold is all that matters, so a burst needs no more than that
+        while history.failures.len() > threshold {
+            history.failures.pop_front();
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use secrecy::Secret;
+
+    use super::*;
+    use crate::domain::ClientFingerprint;
+
+    struct StaticGeoIp(HashMap<IpAddr, GeoLocation>);
+
+    impl GeoIpLookup for StaticGeoIp {
+        fn locate(&self, ip_address: IpAddr) -> Option<GeoLocation> {
+            self.0.get(&ip_address).copied()
+        }
+    }
+
+    fn context(ip: &str, user_agent: &str) -> LoginContext {
+        context_for("risk@example.com", ip, user_agent)
+    }
+
+    fn context_for(email: &str, ip: &str, user_agent: &str) -> LoginContext {
+        context_in(TenantId::default(), email, ip, user_agent)
+    }
+
+    fn context_in(tenant: TenantId, email: &str, ip: &str, user_agent: &str) -> LoginContext {
+        LoginContext::new(
+            tenant,
+            Email::parse(Secret::new(email.to_owned())).unwrap(),
+            ClientFingerprint::new(Some(ip.parse().unwrap()), Some(user_agent.to_owned())),
+        )
+    }
+
+    #[tokio::test]
+    async fn first_login_is_low_risk() {
+        let evaluator = HeuristicRiskEvaluator::default();
+
+        let assessment = evaluator
+            .assess(&context("10.0.0.1", "firefox"))
+            .await
+            .unwrap();
+        assert_eq!(assessment, RiskAssessment::low());
+    }
+
+    #[tokio::test]
+    async fn new_ip_and_user_agent_steps_up() {
+        let mut evaluator = HeuristicRiskEvaluator::default();
+        evaluator
+            .record_success(&context("10.0.0.1", "firefox"))
+            .await
+            .unwrap();
+
+        let assessment = evaluator
+            .assess(&context("10.0.0.1", "firefox"))
+            .await
+            .unwrap();
+        assert!(assessment.reasons.is_empty());
+
+        let assessment = evaluator
+            .assess(&context("10.0.0.2", "firefox"))
+            .await
+            .unwrap();
+        assert_eq!(assessment.reasons, vec![RiskReason::NewIpAddress]);
+        assert!(!assessment.step_up);
+
+        let assessment = evaluator
+            .assess(&context("10.0.0.2", "curl"))
+            .await
+            .unwrap();
+        assert_eq!(
+            assessment.reasons,
+            vec![RiskReason::NewIpAddress, RiskReason::NewUserAgent]
+        );
+        assert!(assessment.step_up);
+    }
+
+    #[tokio::test]
+    async fn failure_burst_steps_up_until_next_success() {
+        let mut evaluator = HeuristicRiskEvaluator::default().with_policy(RiskPolicy {
+            failure_burst_threshold: 3,
+            ..RiskPolicy::default()
+        });
+        let login = context("10.0.0.1", "firefox");
+
+        // Failures past the threshold aren't kept
+        for _ in 0..10 {
+            evaluator.record_failure(&login).await.unwrap();
+        }
+
+        let assessment = evaluator.assess(&login).await.unwrap();
+        assert_eq!(
+            assessment.reasons,
+            vec![RiskReason::FailureBurst { failures: 3 }]
+        );
+        assert!(assessment.step_up);
+
+        evaluator.record_success(&login).await.unwrap();
+        let assessment = evaluator.assess(&login).await.unwrap();
+        assert!(!assessment.step_up);
+    }
+
+    #[tokio::test]
+    async fn impossible_travel_steps_up() {
+        let london: IpAddr = "81.2.69.142".parse().unwrap();
+        let sydney: IpAddr = "1.1.1.1".parse().unwrap();
+        let geoip = StaticGeoIp(HashMap::from([
+            (
+                london,
+                GeoLocation {
+                    latitude: 51.5074,
+                    longitude: -0.1278,
+                },
+            ),
+            (
+                sydney,
+                GeoLocation {
+                    latitude: -33.8688,
+                    longitude: 151.2093,
+                },
+            ),
+        ]));
+        let mut evaluator = HeuristicRiskEvaluator::default().with_geoip(Arc::new(geoip));
+
+        evaluator
+            .record_success(&context(&london.to_string(), "firefox"))
+            .await
+            .unwrap();
+
+        let assessment = evaluator
+            .assess(&context(&sydney.to_string(), "firefox"))
+            .await
+            .unwrap();
+        assert!(assessment
+            .reasons
+            .iter()
+            .any(|reason| matches!(reason, RiskReason::ImpossibleTravel { .. })));
+        assert!(assessment.step_up);
+    }
+
+    #[tokio::test]
+    async fn least_recently_seen_accounts_are_forgotten() {
+        let mut evaluator = HeuristicRiskEvaluator::default().with_max_tracked_accounts(2);
+        let first = context_for("first@example.com", "10.0.0.1", "firefox");
+        let second = context_for("second@example.com", "10.0.0.1", "firefox");
+        let third = context_for("third@example.com", "10.0.0.1", "firefox");
+
+        evaluator.record_success(&first).await.unwrap();
+        evaluator.record_success(&second).await.unwrap();
+        // Seen again, so the second account is now the oldest
+        evaluator.record_failure(&first).await.unwrap();
+        evaluator.record_failure(&third).await.unwrap();
+
+        assert_eq!(evaluator.history.len(), 2);
+        assert_eq!(evaluator.recency.len(), 2);
+        assert!(evaluator.history.contains_key(&account_key(&first)));
+        assert!(!evaluator.history.contains_key(&account_key(&second)));
+    }
+
+    #[tokio::test]
+    async fn known_clients_are_capped_per_account() {
+        let mut evaluator = HeuristicRiskEvaluator::default();
+
+        for i in 0..=MAX_KNOWN_CLIENTS {
+            let ip = format!("10.0.0.{}", i);
+            let user_agent = format!("agent-{}", i);
+            evaluator
+                .record_success(&context(&ip, &user_agent))
+                .await
+                .unwrap();
+        }
+
+        let history = &evaluator.history[&account_key(&context("10.0.0.1", "firefox"))];
+        assert_eq!(history.known_ip_addresses.len(), MAX_KNOWN_CLIENTS);
+        assert_eq!(history.known_user_agents.len(), MAX_KNOWN_CLIENTS);
+        // The oldest was forgotten, the latest is remembered
+        let assessment = evaluator
+            .assess(&context("10.0.0.0", "agent-20"))
+            .await
+            .unwrap();
+        assert_eq!(assessment.reasons, vec![RiskReason::NewIpAddress]);
+    }
+
+    #[tokio::test]
+    async fn same_email_in_another_tenant_has_its_own_history() {
+        let mut evaluator = HeuristicRiskEvaluator::default().with_policy(RiskPolicy {
+            failure_burst_threshold: 3,
+            ..RiskPolicy::default()
+        });
+        let other_tenant = TenantId::parse("other".to_owned()).unwrap();
+        let other_login = context_in(other_tenant, "risk@example.com", "10.0.0.2", "curl");
+
+        evaluator
+            .record_success(&context("10.0.0.1", "firefox"))
+            .await
+            .unwrap();
+        evaluator.record_success(&other_login).await.unwrap();
+        for _ in 0..3 {
+            evaluator.record_failure(&other_login).await.unwrap();
+        }
+
+        // The other tenant's account neither vouches for its clients nor brings its failures
+        let assessment = evaluator
+            .assess(&context("10.0.0.2", "curl"))
+            .await
+            .unwrap();
+        assert_eq!(
+            assessment.reasons,
+            vec![RiskReason::NewIpAddress, RiskReason::NewUserAgent]
+        );
+
+        let assessment = evaluator
+            .assess(&context("10.0.0.1", "firefox"))
+            .await
+            .unwrap();
+        assert!(assessment.reasons.is_empty());
+    }
+}
@@ -0,0 +1,34 @@
+use std::{net::IpAddr, path::Path};
+
+use color_eyre::eyre::{Context, Result};
+use maxminddb::{geoip2, Reader};
+
+use crate::domain::{GeoIpLookup, GeoLocation};
+
+// Looks up locations in a local MaxMind database file (e.g. GeoLite2-City.mmdb)
+pub struct MaxMindGeoIp {
+    reader: Reader<Vec<u8>>,
+}
+
+impl MaxMindGeoIp {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let reader = Reader::open_readfile(path.as_ref()).wrap_err(format!(
+            "failed to open GeoIP database at {}",
+            path.as_ref().display()
+        ))?;
+
+        Ok(Self { reader })
+    }
+}
+
+impl GeoIpLookup for MaxMindGeoIp {
+    fn locate(&self, ip_address: IpAddr) -> Option<GeoLocation> {
+        let city: geoip2::City = self.reader.lookup(ip_address).ok()?;
+        let location = city.location?;
+
+        Some(GeoLocation {
+            latitude: location.latitude?,
+            longitude: location.longitude?,
+        })
+    }
+}
@@ -1,2 +1,11 @@
 pub mod data_stores;
-pub mod postmark_email_client;
\ No newline at end of file
+pub mod disabled_sms_client;
+pub mod heuristic_risk_evaluator;
+pub mod maxmind_geoip;
+pub mod oidc_client;
+pub mod postmark_email_client;
+pub mod saml_service_provider;
+pub mod sliding_window_rate_limiter;
+pub mod twilio_sms_client;
+pub mod webhook_dispatcher;
+pub mod xml_signature;
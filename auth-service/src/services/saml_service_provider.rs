@@ -0,0 +1,531 @@
+use std::{collections::HashMap, io::Write};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{DateTime, Duration, SecondsFormat, Utc};
+use color_eyre::eyre::{eyre, Context, ContextCompat, Result};
+use flate2::{write::DeflateEncoder, Compression};
+use reqwest::Url;
+use roxmltree::{Document, Node, ParsingOptions};
+
+use crate::domain::SamlProvider;
+
+use super::xml_signature::verify_enveloped_signature;
+
+const PROTOCOL_NS: &str = "urn:oasis:names:tc:SAML:2.0:protocol";
+const ASSERTION_NS: &str = "urn:oasis:names:tc:SAML:2.0:assertion";
+const METADATA_NS: &str = "urn:oasis:names:tc:SAML:2.0:metadata";
+const POST_BINDING: &str = "urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST";
+const EMAIL_NAME_ID_FORMAT: &str = "urn:oasis:names:tc:SAML:1.1:nameid-format:emailAddress";
+const UNSPECIFIED_NAME_ID_FORMAT: &str = "urn:oasis:names:tc:SAML:1.1:nameid-format:unspecified";
+const BEARER: &str = "urn:oasis:names:tc:SAML:2.0:cm:bearer";
+const SUCCESS: &str = "urn:oasis:names:tc:SAML:2.0:status:Success";
+// Responses are a few kilobytes; bigger ones are refused before they're parsed
+const MAX_RESPONSE_LENGTH: usize = 256 * 1024;
+const MAX_RESPONSE_NODES: u32 = 10_000;
+// How far the provider's clock may be off from ours
+const CLOCK_SKEW_SECONDS: i64 = 60;
+
+// This service as a SAML 2.0 service provider for one identity provider: its
+// metadata, the AuthnRequest users are sent to the provider with, and validation of
+// the response the provider posts back to the Assertion Consumer Service.
+pub struct SamlServiceProvider {
+    pub entity_id: String,
+    pub acs_url: String,
+}
+
+impl SamlServiceProvider {
+    pub fn new(entity_id: String, acs_url: String) -> Self {
+        Self { entity_id, acs_url }
+    }
+
+    // What the provider is set up with. Assertions have to be signed; requests aren't.
+    pub fn metadata(&self) -> String {
+        format!(
+            concat!(
+                r#"<?xml version="1.0" encoding="UTF-8"?>"#,
+                r#"<md:EntityDescriptor xmlns:md="{}" entityID="{}">"#,
+                r#"<md:SPSSODescriptor AuthnRequestsSigned="false" WantAssertionsSigned="true" protocolSupportEnumeration="{}">"#,
+                r#"<md:NameIDFormat>{}</md:NameIDFormat>"#,
+                r#"<md:AssertionConsumerService Binding="{}" Location="{}" index="0" isDefault="true"/>"#,
+                r#"</md:SPSSODescriptor>"#,
+                r#"</md:EntityDescriptor>"#,
+            ),
+            METADATA_NS,
+            escape(&self.entity_id),
+            PROTOCOL_NS,
+            EMAIL_NAME_ID_FORMAT,
+            POST_BINDING,
+            escape(&self.acs_url),
+        )
+    }
+
+    // Where to send the user to sign in: the provider's SSO URL with the AuthnRequest,
+    // deflated and encoded for the HTTP-Redirect binding
+    pub fn authn_request_url(
+        &self,
+        provider: &SamlProvider,
+        request: &SamlAuthnRequest,
+    ) -> Result<Url> {
+        // The email comes from the NameID unless the provider sends it as an attribute
+        let name_id_policy = match provider.email_attribute {
+            Some(_) => String::new(),
+            None => format!(
+                r#"<samlp:NameIDPolicy Format="{}" AllowCreate="true"/>"#,
+                EMAIL_NAME_ID_FORMAT
+            ),
+        };
+        let authn_request = format!(
+            concat!(
+                r#"<samlp:AuthnRequest xmlns:samlp="{}" xmlns:saml="{}" ID="{}" Version="2.0" "#,
+                r#"IssueInstant="{}" Destination="{}" AssertionConsumerServiceURL="{}" ProtocolBinding="{}">"#,
+                r#"<saml:Issuer>{}</saml:Issuer>{}"#,
+                r#"</samlp:AuthnRequest>"#,
+            ),
+            PROTOCOL_NS,
+            ASSERTION_NS,
+            request.id,
+            Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true),
+            escape(&provider.sso_url),
+            escape(&self.acs_url),
+            POST_BINDING,
+            escape(&self.entity_id),
+            name_id_policy,
+        );
+
+        let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(authn_request.as_bytes())?;
+        let deflated = encoder.finish()?;
+
+        let mut url = Url::parse(&provider.sso_url).wrap_err("invalid SSO URL")?;
+        url.query_pairs_mut()
+            .append_pair("SAMLRequest", &STANDARD.encode(deflated));
+
+        Ok(url)
+    }
+
+    // Checks the response the provider posted for `request` and returns the assertion
+    // in it. Only what's inside the signed assertion is trusted.
+    #[tracing::instrument(name = "Validating SAML response", skip_all)]
+    pub fn validate_response(
+        &self,
+        provider: &SamlProvider,
+        request: &SamlAuthnRequest,
+        saml_response: &str,
+    ) -> Result<SamlAssertion> {
+        if saml_response.len() > MAX_RESPONSE_LENGTH {
+            return Err(eyre!("response is too large"));
+        }
+        let xml = STANDARD
+            .decode(saml_response.split_whitespace().collect::<String>())
+            .wrap_err("response is not valid base64")?;
+        let xml = String::from_utf8(xml).wrap_err("response is not UTF-8")?;
+
+        // DTDs are refused, so there are no entities to expand
+        let options = ParsingOptions {
+            allow_dtd: false,
+            nodes_limit: MAX_RESPONSE_NODES,
+        };
+        let document =
+            Document::parse_with_options(&xml, options).wrap_err("response is not valid XML")?;
+
+        let response = document.root_element();
+        if !response.has_tag_name((PROTOCOL_NS, "Response")) {
+            return Err(eyre!("not a SAML response"));
+        }
+        // Only responses to our own requests are accepted, not unsolicited ones
+        if response.attribute("InResponseTo") != Some(request.id.as_str()) {
+            return Err(eyre!("response is not for this sign-in"));
+        }
+        if response
+            .attribute("Destination")
+            .is_some_and(|destination| destination != self.acs_url)
+        {
+            return Err(eyre!("response is for another destination"));
+        }
+
+        let status = child(response, PROTOCOL_NS, "Status")
+            .and_then(|status| child(status, PROTOCOL_NS, "StatusCode"))?
+            .attribute("Value");
+        if status != Some(SUCCESS) {
+            return Err(eyre!(
+                "provider answered with status {}",
+                status.unwrap_or("none")
+            ));
+        }
+
+        if document
+            .descendants()
+            .any(|node| node.has_tag_name((ASSERTION_NS, "EncryptedAssertion")))
+        {
+            return Err(eyre!("encrypted assertions are not supported"));
+        }
+        let mut assertions = document
+            .descendants()
+            .filter(|node| node.has_tag_name((ASSERTION_NS, "Assertion")));
+        let assertion = match (assertions.next(), assertions.next()) {
+            (Some(assertion), None) if assertion.parent() == Some(response) => assertion,
+            _ => return Err(eyre!("response must hold exactly one assertion")),
+        };
+
+        verify_enveloped_signature(assertion, &provider.public_key()?)
+            .wrap_err("invalid assertion signature")?;
+
+        self.read_assertion(provider, request, assertion, Utc::now())
+    }
+
+    fn read_assertion(
+        &self,
+        provider: &SamlProvider,
+        request: &SamlAuthnRequest,
+        assertion: Node,
+        now: DateTime<Utc>,
+    ) -> Result<SamlAssertion> {
+        let skew = Duration::seconds(CLOCK_SKEW_SECONDS);
+
+        let issuer = text(child(assertion, ASSERTION_NS, "Issuer")?);
+        if issuer != provider.entity_id {
+            return Err(eyre!("assertion is from issuer {}", issuer));
+        }
+
+        let subject = child(assertion, ASSERTION_NS, "Subject")?;
+        let name_id = child(subject, ASSERTION_NS, "NameID")?;
+
+        // At least one bearer confirmation has to be for this sign-in, at our ACS
+        let confirmed_until = subject
+            .children()
+            .filter(|confirmation| {
+                confirmation.has_tag_name((ASSERTION_NS, "SubjectConfirmation"))
+                    && confirmation.attribute("Method") == Some(BEARER)
+            })
+            .filter_map(|confirmation| {
+                child(confirmation, ASSERTION_NS, "SubjectConfirmationData").ok()
+            })
+            .filter(|data| {
+                data.attribute("Recipient") == Some(self.acs_url.as_str())
+                    && data
+                        .attribute("InResponseTo")
+                        .is_none_or(|id| id == request.id)
+                    && data.attribute("NotBefore").is_none()
+            })
+            .filter_map(|data| parse_time(data.attribute("NotOnOrAfter")?).ok())
+            .filter(|not_on_or_after| now < *not_on_or_after + skew)
+            .max()
+            .ok_or(eyre!("assertion has no valid bearer subject confirmation"))?;
+
+        let conditions = child(assertion, ASSERTION_NS, "Conditions")?;
+        if let Some(not_before) = conditions.attribute("NotBefore") {
+            if now + skew < parse_time(not_before)? {
+                return Err(eyre!("assertion is not valid yet"));
+            }
+        }
+        let valid_until = match conditions.attribute("NotOnOrAfter") {
+            Some(not_on_or_after) => parse_time(not_on_or_after)?.min(confirmed_until),
+            None => confirmed_until,
+        };
+        if now >= valid_until + skew {
+            return Err(eyre!("assertion has expired"));
+        }
+
+        // Every audience restriction has to name us
+        let restrictions: Vec<Node> =
+            children(conditions, ASSERTION_NS, "AudienceRestriction").collect();
+        let is_for_us = !restrictions.is_empty()
+            && restrictions.iter().all(|restriction| {
+                children(*restriction, ASSERTION_NS, "Audience")
+                    .any(|audience| text(audience) == self.entity_id)
+            });
+        if !is_for_us {
+            return Err(eyre!("assertion is not meant for this service"));
+        }
+
+        let mut attributes: HashMap<String, Vec<String>> = HashMap::new();
+        for statement in children(assertion, ASSERTION_NS, "AttributeStatement") {
+            for attribute in children(statement, ASSERTION_NS, "Attribute") {
+                let Some(name) = attribute.attribute("Name") else {
+                    continue;
+                };
+                attributes
+                    .entry(name.to_owned())
+                    .or_default()
+                    .extend(children(attribute, ASSERTION_NS, "AttributeValue").map(text));
+            }
+        }
+
+        Ok(SamlAssertion {
+            id: assertion
+                .attribute("ID")
+                .context("assertion has no ID")?
+                .to_owned(),
+            name_id: text(name_id),
+            name_id_format: name_id.attribute("Format").map(str::to_owned),
+            attributes,
+            expires_at: valid_until + skew,
+        })
+    }
+}
+
+// An AuthnRequest, whose ID the provider's response has to be in response to
+#[derive(Debug, Clone)]
+pub struct SamlAuthnRequest {
+    pub id: String,
+}
+
+impl SamlAuthnRequest {
+    pub fn new() -> Self {
+        // IDs are NCNames, which can't start with a digit
+        Self {
+            id: format!("_{}", uuid::Uuid::new_v4()),
+        }
+    }
+}
+
+impl Default for SamlAuthnRequest {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// What a provider asserted about the user signing in
+#[derive(Debug, Clone)]
+pub struct SamlAssertion {
+    pub id: String,
+    pub name_id: String,
+    pub name_id_format: Option<String>,
+    pub attributes: HashMap<String, Vec<String>>,
+    // When the assertion stops being accepted
+    pub expires_at: DateTime<Utc>,
+}
+
+impl SamlAssertion {
+    // The user's email: the first value of `email_attribute` when set, otherwise the
+    // NameID as long as its format allows an email
+    pub fn email(&self, email_attribute: Option<&str>) -> Option<&str> {
+        match email_attribute {
+            Some(attribute) => self.attributes.get(attribute)?.first().map(String::as_str),
+            None => match self.name_id_format.as_deref() {
+                None | Some(EMAIL_NAME_ID_FORMAT) | Some(UNSPECIFIED_NAME_ID_FORMAT) => {
+                    Some(&self.name_id)
+                }
+                Some(_) => None,
+            },
+        }
+    }
+}
+
+fn child<'a, 'input>(
+    parent: Node<'a, 'input>,
+    namespace: &'static str,
+    name: &'static str,
+) -> Result<Node<'a, 'input>> {
+    children(parent, namespace, name)
+        .next()
+        .ok_or(eyre!("no {} element", name))
+}
+
+fn children<'a, 'input: 'a>(
+    parent: Node<'a, 'input>,
+    namespace: &'static str,
+    name: &'static str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    parent
+        .children()
+        .filter(move |child| child.has_tag_name((namespace, name)))
+}
+
+// All the text of an element. Reading only the first text node would be a mistake:
+// comments aren't signed, so one could cut `alice@example.com.evil.com` short.
+fn text(element: Node) -> String {
+    element
+        .children()
+        .filter(Node::is_text)
+        .filter_map(|text| text.text())
+        .collect::<String>()
+        .trim()
+        .to_owned()
+}
+
+fn parse_time(time: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(time)
+        .map(|time| time.with_timezone(&Utc))
+        .wrap_err(format!("invalid time {}", time))
+}
+
+fn escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use flate2::read::DeflateDecoder;
+
+    use super::*;
+    use crate::domain::{ProviderId, TenantId};
+
+    fn service_provider() -> SamlServiceProvider {
+        SamlServiceProvider::new(
+            "https://auth.example.com/saml/corp/metadata".to_owned(),
+            "https://auth.example.com/saml/corp/acs".to_owned(),
+        )
+    }
+
+    fn provider() -> SamlProvider {
+        SamlProvider {
+            id: ProviderId::parse("corp".to_owned()).unwrap(),
+            tenant: TenantId::default(),
+            name: "Corp SSO".to_owned(),
+            entity_id: "https://idp.example.com/metadata".to_owned(),
+            sso_url: "https://idp.example.com/sso?tenant=acme".to_owned(),
+            certificate: String::new(),
+            email_attribute: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn assertion(conditions: &str, confirmation: &str) -> String {
+        format!(
+            r#"<saml:Assertion xmlns:saml="{ns}" ID="_a1" Version="2.0" IssueInstant="2026-10-18T12:00:00Z">
+                <saml:Issuer>https://idp.example.com/metadata</saml:Issuer>
+                <saml:Subject>
+                    <saml:NameID Format="{format}">alice@example.com</saml:NameID>
+                    <saml:SubjectConfirmation Method="{bearer}"><saml:SubjectConfirmationData {confirmation}/></saml:SubjectConfirmation>
+                </saml:Subject>
+                <saml:Conditions {conditions}>
+                    <saml:AudienceRestriction><saml:Audience>https://auth.example.com/saml/corp/metadata</saml:Audience></saml:AudienceRestriction>
+                </saml:Conditions>
+                <saml:AttributeStatement>
+                    <saml:Attribute Name="mail"><saml:AttributeValue>alice@corp.example.com</saml:AttributeValue></saml:Attribute>
+                </saml:AttributeStatement>
+            </saml:Assertion>"#,
+            ns = ASSERTION_NS,
+            format = EMAIL_NAME_ID_FORMAT,
+            bearer = BEARER,
+        )
+    }
+
+    fn read(xml: &str, request: &SamlAuthnRequest, now: &str) -> Result<SamlAssertion> {
+        let document = Document::parse(xml).unwrap();
+        service_provider().read_assertion(
+            &provider(),
+            request,
+            document.root_element(),
+            parse_time(now).unwrap(),
+        )
+    }
+
+    const VALID_CONDITIONS: &str =
+        r#"NotBefore="2026-10-18T11:59:00Z" NotOnOrAfter="2026-10-18T12:05:00Z""#;
+
+    fn valid_confirmation(request: &SamlAuthnRequest) -> String {
+        format!(
+            r#"Recipient="https://auth.example.com/saml/corp/acs" InResponseTo="{}" NotOnOrAfter="2026-10-18T12:05:00Z""#,
+            request.id
+        )
+    }
+
+    #[test]
+    fn authn_request_is_deflated_into_the_sso_url() {
+        let request = SamlAuthnRequest::new();
+        let url = service_provider()
+            .authn_request_url(&provider(), &request)
+            .unwrap();
+
+        // Query parameters of the SSO URL are kept
+        assert_eq!(url.query_pairs().next().unwrap().1, "acme");
+        let encoded = url
+            .query_pairs()
+            .find(|(name, _)| name == "SAMLRequest")
+            .unwrap()
+            .1;
+        let mut xml = String::new();
+        DeflateDecoder::new(STANDARD.decode(encoded.as_bytes()).unwrap().as_slice())
+            .read_to_string(&mut xml)
+            .unwrap();
+
+        let document = Document::parse(&xml).unwrap();
+        let authn_request = document.root_element();
+        assert!(authn_request.has_tag_name((PROTOCOL_NS, "AuthnRequest")));
+        assert_eq!(authn_request.attribute("ID"), Some(request.id.as_str()));
+        assert_eq!(
+            authn_request.attribute("AssertionConsumerServiceURL"),
+            Some("https://auth.example.com/saml/corp/acs")
+        );
+        assert_eq!(
+            text(child(authn_request, ASSERTION_NS, "Issuer").unwrap()),
+            "https://auth.example.com/saml/corp/metadata"
+        );
+    }
+
+    #[test]
+    fn reads_valid_assertion() {
+        let request = SamlAuthnRequest::new();
+        let xml = assertion(VALID_CONDITIONS, &valid_confirmation(&request));
+
+        let assertion = read(&xml, &request, "2026-10-18T12:01:00Z").unwrap();
+        assert_eq!(assertion.id, "_a1");
+        assert_eq!(assertion.email(None), Some("alice@example.com"));
+        assert_eq!(
+            assertion.email(Some("mail")),
+            Some("alice@corp.example.com")
+        );
+        assert_eq!(assertion.email(Some("missing")), None);
+        assert_eq!(
+            assertion.expires_at,
+            parse_time("2026-10-18T12:06:00Z").unwrap()
+        );
+    }
+
+    #[test]
+    fn checks_validity_period_with_some_skew() {
+        let request = SamlAuthnRequest::new();
+        let xml = assertion(VALID_CONDITIONS, &valid_confirmation(&request));
+
+        assert!(read(&xml, &request, "2026-10-18T11:58:30Z").is_ok());
+        assert!(read(&xml, &request, "2026-10-18T11:57:00Z").is_err());
+        assert!(read(&xml, &request, "2026-10-18T12:05:30Z").is_ok());
+        assert!(read(&xml, &request, "2026-10-18T12:06:00Z").is_err());
+    }
+
+    #[test]
+    fn rejects_assertion_for_other_sign_in_or_recipient() {
+        let request = SamlAuthnRequest::new();
+
+        let other_request = SamlAuthnRequest::new();
+        let xml = assertion(VALID_CONDITIONS, &valid_confirmation(&other_request));
+        assert!(read(&xml, &request, "2026-10-18T12:01:00Z").is_err());
+
+        let xml = assertion(
+            VALID_CONDITIONS,
+            &valid_confirmation(&request).replace("auth.example.com", "evil.example.com"),
+        );
+        assert!(read(&xml, &request, "2026-10-18T12:01:00Z").is_err());
+    }
+
+    #[test]
+    fn rejects_assertion_for_other_audience() {
+        let request = SamlAuthnRequest::new();
+        let xml = assertion(VALID_CONDITIONS, &valid_confirmation(&request)).replace(
+            "<saml:Audience>https://auth.example.com",
+            "<saml:Audience>https://other.example.com",
+        );
+
+        assert!(read(&xml, &request, "2026-10-18T12:01:00Z").is_err());
+    }
+
+    #[test]
+    fn email_is_not_taken_from_other_name_id_formats() {
+        let request = SamlAuthnRequest::new();
+        let xml = assertion(VALID_CONDITIONS, &valid_confirmation(&request)).replace(
+            EMAIL_NAME_ID_FORMAT,
+            "urn:oasis:names:tc:SAML:2.0:nameid-format:persistent",
+        );
+
+        let assertion = read(&xml, &request, "2026-10-18T12:01:00Z").unwrap();
+        assert_eq!(assertion.email(None), None);
+    }
+}
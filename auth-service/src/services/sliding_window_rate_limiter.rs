@@ -0,0 +1,74 @@
+use std::collections::{HashMap, VecDeque};
+
+use chrono::{DateTime, Utc};
+use color_eyre::eyre::Result;
+
+use crate::domain::{RateLimitPolicy, RateLimiter};
+
+// Remembers recent attempts in memory, so each replica limits on its own
+#[derive(Default)]
+pub struct SlidingWindowRateLimiter {
+    attempts: HashMap<String, VecDeque<DateTime<Utc>>>,
+    policy: RateLimitPolicy,
+}
+
+impl SlidingWindowRateLimiter {
+    pub fn with_policy(mut self, policy: RateLimitPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+}
+
+#[async_trait::async_trait]
+impl RateLimiter for SlidingWindowRateLimiter {
+    async fn check(&mut self, key: &str) -> Result<bool> {
+        let now = Utc::now();
+        let cutoff = now - self.policy.window;
+
+        // Keys with nothing left in their window are dropped so the map can't grow forever
+        self.attempts.retain(|_, attempts| {
+            while attempts.front().is_some_and(|at| *at <= cutoff) {
+                attempts.pop_front();
+            }
+            !attempts.is_empty()
+        });
+
+        let attempts = self.attempts.entry(key.to_owned()).or_default();
+        attempts.push_back(now);
+
+        Ok(attempts.len() as u32 <= self.policy.max_attempts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(max_attempts: u32, window: chrono::Duration) -> SlidingWindowRateLimiter {
+        SlidingWindowRateLimiter::default().with_policy(RateLimitPolicy {
+            max_attempts,
+            window,
+        })
+    }
+
+    #[tokio::test]
+    async fn limits_each_key_separately() {
+        let mut limiter = limiter(2, chrono::Duration::minutes(1));
+
+        assert!(limiter.check("first").await.unwrap());
+        assert!(limiter.check("first").await.unwrap());
+        assert!(!limiter.check("first").await.unwrap());
+        assert!(limiter.check("second").await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn allows_attempts_again_once_window_passes() {
+        let mut limiter = limiter(1, chrono::Duration::milliseconds(50));
+
+        assert!(limiter.check("key").await.unwrap());
+        assert!(!limiter.check("key").await.unwrap());
+
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert!(limiter.check("key").await.unwrap());
+    }
+}
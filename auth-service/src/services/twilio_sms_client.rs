@@ -0,0 +1,181 @@
+use color_eyre::eyre::Result; // For improved error handling and reporting
+use reqwest::{Client, Url}; // For making HTTP requests
+use secrecy::{ExposeSecret, Secret}; // For securely handling sensitive data
+
+use crate::domain::{PhoneNumber, SmsClient}; // Import domain-specific modules
+
+// Define the TwilioSmsClient struct
+pub struct TwilioSmsClient {
+    http_client: Client,        // HTTP client for making requests
+    base_url: String,           // Base URL for the SMS service
+    sender: PhoneNumber,        // Phone number the messages are sent from
+    account_sid: String,        // Account the messages are billed to
+    auth_token: Secret<String>, // Auth token for the SMS service, wrapped in Secret for security
+}
+
+impl TwilioSmsClient {
+    // Constructor for creating a new TwilioSmsClient instance
+    pub fn new(
+        base_url: String,
+        sender: PhoneNumber,
+        account_sid: String,
+        auth_token: Secret<String>,
+        http_client: Client,
+    ) -> Self {
+        Self {
+            http_client,
+            base_url,
+            sender,
+            account_sid,
+            auth_token,
+        }
+    }
+}
+
+#[async_trait::async_trait]
+impl SmsClient for TwilioSmsClient {
+    #[tracing::instrument(name = "Sending SMS", skip_all)] // Trace this function, skipping logging its parameters
+    async fn send_sms(&self, recipient: &PhoneNumber, content: &str) -> Result<()> {
+        // Parse the base URL and join it with the messages endpoint of the account
+        let base = Url::parse(&self.base_url)?;
+        let url = base.join(&format!(
+            "/2010-04-01/Accounts/{}/Messages.json",
+            self.account_sid
+        ))?;
+
+        // Create the form body for sending the message
+        let request_body = SendSmsRequest {
+            from: self.sender.as_ref().expose_secret(),
+            to: recipient.as_ref().expose_secret(),
+            body: content,
+        };
+
+        // Build the HTTP POST request, authenticating with the account SID and auth token
+        let request = self
+            .http_client
+            .post(url)
+            .basic_auth(&self.account_sid, Some(self.auth_token.expose_secret()))
+            .form(&request_body);
+
+        // Send the request and handle the response
+        request.send().await?.error_for_status()?;
+
+        Ok(())
+    }
+}
+
+// Define the structure of the message request body
+// For more information about the request structure, see the API docs: https://www.twilio.com/docs/messaging/api/message-resource
+#[derive(serde::Serialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+struct SendSmsRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    body: &'a str,
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::constants::test;
+
+    use super::*;
+    use fake::faker::lorem::en::Sentence;
+    use fake::{Fake, Faker};
+    use wiremock::matchers::{any, body_string_contains, header_exists, method, path};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    use super::TwilioSmsClient;
+
+    const ACCOUNT_SID: &str = "AC00000000000000000000000000000000";
+
+    // Helper function to generate test content
+    fn content() -> String {
+        Sentence(1..2).fake()
+    }
+
+    // Helper function to generate a test phone number
+    fn phone_number() -> PhoneNumber {
+        PhoneNumber::parse(Secret::new("+14155552671".to_owned())).unwrap()
+    }
+
+    // Helper function to create a test SMS client
+    fn sms_client(base_url: String) -> TwilioSmsClient {
+        let http_client = Client::builder()
+            .timeout(test::sms_client::TIMEOUT)
+            .build()
+            .unwrap();
+        TwilioSmsClient::new(
+            base_url,
+            phone_number(),
+            ACCOUNT_SID.to_owned(),
+            Secret::new(Faker.fake()),
+            http_client,
+        )
+    }
+
+    // Test to ensure the SMS client sends the expected request
+    #[tokio::test]
+    async fn send_sms_sends_the_expected_request() {
+        let mock_server = MockServer::start().await;
+        let sms_client = sms_client(mock_server.uri());
+
+        // Set up the mock server to expect a specific request
+        Mock::given(header_exists("Authorization"))
+            .and(path(format!(
+                "/2010-04-01/Accounts/{}/Messages.json",
+                ACCOUNT_SID
+            )))
+            .and(method("POST"))
+            .and(body_string_contains("From="))
+            .and(body_string_contains("To="))
+            .and(body_string_contains("Body="))
+            .respond_with(ResponseTemplate::new(201))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Execute the send_sms function and check the outcome
+        let outcome = sms_client.send_sms(&phone_number(), &content()).await;
+
+        assert!(outcome.is_ok());
+    }
+
+    // Test to handle server error responses
+    #[tokio::test]
+    async fn send_sms_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let sms_client = sms_client(mock_server.uri());
+
+        // Set up the mock server to respond with a 500 error
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Execute the send_sms function and check the outcome
+        let outcome = sms_client.send_sms(&phone_number(), &content()).await;
+
+        assert!(outcome.is_err());
+    }
+
+    // Test to handle request timeouts
+    #[tokio::test]
+    async fn send_sms_times_out_if_the_server_takes_too_long() {
+        let mock_server = MockServer::start().await;
+        let sms_client = sms_client(mock_server.uri());
+
+        // Set up the mock server to delay the response
+        let response = ResponseTemplate::new(201).set_delay(std::time::Duration::from_secs(180)); // 3 minutes delay
+        Mock::given(any())
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Execute the send_sms function and check the outcome
+        let outcome = sms_client.send_sms(&phone_number(), &content()).await;
+
+        assert!(outcome.is_err());
+    }
+}
@@ -0,0 +1,361 @@
+use std::{net::SocketAddr, time::Duration};
+
+use chrono::Utc;
+use color_eyre::eyre::{eyre, Result};
+use hyper::client::connect::dns::Name;
+use reqwest::{
+    dns::{Addrs, Resolve, Resolving},
+    header::CONTENT_TYPE,
+    Client,
+};
+use tokio::task::JoinHandle;
+
+use crate::{
+    app_state::WebhookStoreType,
+    domain::{
+        data_stores::WebhookStoreError, is_public_address, Webhook, WebhookDelivery,
+        WebhookDeliveryStatus, WebhookRetryPolicy, WebhookUrlPolicy,
+    },
+};
+
+pub const WEBHOOK_ID_HEADER: &str = "X-Webhook-Id";
+pub const WEBHOOK_EVENT_HEADER: &str = "X-Webhook-Event";
+pub const WEBHOOK_TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+pub const WEBHOOK_SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+
+// Deliveries claimed per round
+const BATCH_SIZE: u64 = 20;
+// How long a claimed delivery is kept from other workers. Longer than a round can take
+// with every receiver timing out.
+const CLAIM_LEASE_MINUTES: i64 = 10;
+
+// Sends queued webhook deliveries. Any 2xx response counts as delivered; anything else,
+// including no response, is retried according to the retry policy. The HTTP client
+// shouldn't follow redirects, which could lead anywhere the URL policy doesn't allow, and
+// under `WebhookUrlPolicy::PublicHttps` should resolve hosts with `PublicAddressResolver`.
+pub struct WebhookDispatcher {
+    webhook_store: WebhookStoreType,
+    http_client: Client,
+    retry_policy: WebhookRetryPolicy,
+    url_policy: WebhookUrlPolicy,
+}
+
+impl WebhookDispatcher {
+    pub fn new(webhook_store: WebhookStoreType, http_client: Client) -> Self {
+        Self {
+            webhook_store,
+            http_client,
+            retry_policy: WebhookRetryPolicy::default(),
+            url_policy: WebhookUrlPolicy::default(),
+        }
+    }
+
+    pub fn with_retry_policy(mut self, retry_policy: WebhookRetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn with_url_policy(mut self, url_policy: WebhookUrlPolicy) -> Self {
+        self.url_policy = url_policy;
+        self
+    }
+
+    // Attempts every delivery that is due and returns how many were attempted
+    #[tracing::instrument(name = "Dispatching webhook deliveries", skip_all)]
+    pub async fn dispatch_due(&self) -> Result<usize> {
+        let deliveries = self
+            .webhook_store
+            .write()
+            .await
+            .claim_due_deliveries(
+                Utc::now(),
+                chrono::Duration::minutes(CLAIM_LEASE_MINUTES),
+                BATCH_SIZE,
+            )
+            .await?;
+
+        for mut delivery in deliveries.iter().cloned() {
+            let webhook = match self
+                .webhook_store
+                .read()
+                .await
+                .get_webhook(&delivery.tenant, &delivery.webhook_id)
+                .await
+            {
+                Ok(webhook) => webhook,
+                // Deleted since, along with the delivery
+                Err(WebhookStoreError::WebhookNotFound) => continue,
+                Err(e) => return Err(e.into()),
+            };
+
+            self.attempt(&webhook, &mut delivery).await;
+            if delivery.status == WebhookDeliveryStatus::DeadLetter {
+                tracing::warn!(
+                    "Giving up on webhook delivery {} after {} attempts",
+                    delivery.id,
+                    delivery.attempts
+                );
+            }
+
+            match self
+                .webhook_store
+                .write()
+                .await
+                .update_delivery(&delivery)
+                .await
+            {
+                Ok(()) | Err(WebhookStoreError::DeliveryNotFound) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        Ok(deliveries.len())
+    }
+
+    // Errors recorded on the delivery are shown to tenant admins, so they only say what
+    // went wrong in general terms; the details are logged
+    async fn attempt(&self, webhook: &Webhook, delivery: &mut WebhookDelivery) {
+        if let Err(e) = self.url_policy.check(&webhook.url).await {
+            tracing::warn!("Not sending webhook delivery {}: {:?}", delivery.id, e);
+            let error = "The webhook URL is not allowed".to_owned();
+            delivery.record_failure(None, error, &self.retry_policy);
+            return;
+        }
+
+        let timestamp = Utc::now().timestamp();
+
+        let response = self
+            .http_client
+            .post(&webhook.url)
+            .header(CONTENT_TYPE, "application/json")
+            .header(WEBHOOK_ID_HEADER, delivery.id.to_string())
+            .header(WEBHOOK_EVENT_HEADER, delivery.event_type.as_str())
+            .header(WEBHOOK_TIMESTAMP_HEADER, timestamp)
+            .header(
+                WEBHOOK_SIGNATURE_HEADER,
+                webhook.sign(timestamp, &delivery.payload),
+            )
+            .body(delivery.payload.clone())
+            .send()
+            .await;
+
+        match response {
+            Ok(response) if response.status().is_success() => {
+                delivery.record_success(response.status().as_u16())
+            }
+            Ok(response) => delivery.record_failure(
+                Some(response.status().as_u16()),
+                format!("Receiver responded with {}", response.status()),
+                &self.retry_policy,
+            ),
+            Err(e) => {
+                tracing::warn!("Failed to send webhook delivery {}: {:?}", delivery.id, e);
+                let error = if e.is_timeout() {
+                    "The receiver timed out"
+                } else if e.is_connect() {
+                    "Could not connect to the receiver"
+                } else {
+                    "Could not send the request"
+                };
+                delivery.record_failure(None, error.to_owned(), &self.retry_policy)
+            }
+        }
+    }
+}
+
+// Resolves hosts for the webhook HTTP client, refusing those with non-public addresses.
+// Checking the URL before an attempt isn't enough on its own: the client looks the host up
+// again when connecting, and a host can answer with a private address the second time.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PublicAddressResolver;
+
+impl PublicAddressResolver {
+    async fn lookup(host: &str) -> Result<Vec<SocketAddr>> {
+        let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host, 0)).await?.collect();
+
+        // Every address counts, since any of them may be the one connected to
+        if addresses.is_empty()
+            || !addresses
+                .iter()
+                .all(|address| is_public_address(address.ip()))
+        {
+            return Err(eyre!("{} doesn't resolve to a public address.", host));
+        }
+
+        Ok(addresses)
+    }
+}
+
+impl Resolve for PublicAddressResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        Box::pin(async move {
+            let addresses = Self::lookup(name.as_str()).await?;
+            Ok(Box::new(addresses.into_iter()) as Addrs)
+        })
+    }
+}
+
+pub fn spawn_webhook_dispatcher(
+    dispatcher: WebhookDispatcher,
+    interval: Duration,
+) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if let Err(e) = dispatcher.dispatch_due().await {
+                tracing::error!("Failed to dispatch webhook deliveries: {:?}", e);
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::sync::RwLock;
+    use wiremock::{
+        matchers::{header, header_exists, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::*;
+    use crate::{
+        domain::{TenantId, WebhookEventType},
+        services::data_stores::hashmap_webhook_store::HashmapWebhookStore,
+    };
+
+    const NO_DELAY: WebhookRetryPolicy = WebhookRetryPolicy {
+        base_delay_seconds: 0,
+        max_attempts: 2,
+    };
+
+    fn dispatcher(store: &WebhookStoreType) -> WebhookDispatcher {
+        WebhookDispatcher::new(store.clone(), Client::new()).with_url_policy(WebhookUrlPolicy::Any)
+    }
+
+    async fn queue(store: &WebhookStoreType, url: String) -> WebhookDelivery {
+        let webhook =
+            Webhook::new(TenantId::default(), url, vec![WebhookEventType::Signup]).unwrap();
+        let delivery = WebhookDelivery::new(&webhook, WebhookEventType::Signup, "{}".to_owned());
+        let mut store = store.write().await;
+        store.add_webhook(webhook).await.unwrap();
+        store.add_delivery(delivery.clone()).await.unwrap();
+        delivery
+    }
+
+    async fn status_of(store: &WebhookStoreType, delivery: &WebhookDelivery) -> WebhookDelivery {
+        store
+            .read()
+            .await
+            .get_delivery(&delivery.tenant, &delivery.id)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn delivers_signed_payloads() {
+        let receiver = MockServer::start().await;
+        let store: WebhookStoreType = Arc::new(RwLock::new(HashmapWebhookStore::default()));
+        let delivery = queue(&store, format!("{}/hooks", receiver.uri())).await;
+
+        Mock::given(path("/hooks"))
+            .and(method("POST"))
+            .and(header("Content-Type", "application/json"))
+            .and(header(WEBHOOK_EVENT_HEADER, "signup"))
+            .and(header_exists(WEBHOOK_SIGNATURE_HEADER))
+            .and(header_exists(WEBHOOK_TIMESTAMP_HEADER))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(1)
+            .mount(&receiver)
+            .await;
+
+        let dispatcher = dispatcher(&store);
+        assert_eq!(dispatcher.dispatch_due().await.unwrap(), 1);
+        // Nothing is due anymore
+        assert_eq!(dispatcher.dispatch_due().await.unwrap(), 0);
+
+        let delivery = status_of(&store, &delivery).await;
+        assert_eq!(delivery.status, WebhookDeliveryStatus::Delivered);
+        assert_eq!(delivery.last_response_status, Some(204));
+    }
+
+    #[tokio::test]
+    async fn dead_letters_deliveries_that_keep_failing() {
+        let receiver = MockServer::start().await;
+        let store: WebhookStoreType = Arc::new(RwLock::new(HashmapWebhookStore::default()));
+        let delivery = queue(&store, receiver.uri()).await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(2)
+            .mount(&receiver)
+            .await;
+
+        let dispatcher = dispatcher(&store).with_retry_policy(NO_DELAY);
+        dispatcher.dispatch_due().await.unwrap();
+        assert_eq!(
+            status_of(&store, &delivery).await.status,
+            WebhookDeliveryStatus::Pending
+        );
+        dispatcher.dispatch_due().await.unwrap();
+
+        let delivery = status_of(&store, &delivery).await;
+        assert_eq!(delivery.status, WebhookDeliveryStatus::DeadLetter);
+        assert_eq!(delivery.attempts, 2);
+        assert_eq!(delivery.last_response_status, Some(500));
+        assert_eq!(dispatcher.dispatch_due().await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn does_not_send_to_urls_the_policy_forbids() {
+        let receiver = MockServer::start().await;
+        let store: WebhookStoreType = Arc::new(RwLock::new(HashmapWebhookStore::default()));
+        let delivery = queue(&store, receiver.uri()).await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(0)
+            .mount(&receiver)
+            .await;
+
+        let dispatcher = WebhookDispatcher::new(store.clone(), Client::new());
+        dispatcher.dispatch_due().await.unwrap();
+
+        let delivery = status_of(&store, &delivery).await;
+        assert_eq!(delivery.status, WebhookDeliveryStatus::Pending);
+        assert_eq!(
+            delivery.last_error.as_deref(),
+            Some("The webhook URL is not allowed")
+        );
+    }
+
+    #[tokio::test]
+    async fn does_not_connect_to_hosts_resolving_to_private_addresses() {
+        let receiver = MockServer::start().await;
+        let store: WebhookStoreType = Arc::new(RwLock::new(HashmapWebhookStore::default()));
+        // The URL passes the policy, but the name connected to resolves to loopback
+        let port = receiver.address().port();
+        let delivery = queue(&store, format!("http://localhost:{}/hooks", port)).await;
+
+        Mock::given(method("POST"))
+            .respond_with(ResponseTemplate::new(204))
+            .expect(0)
+            .mount(&receiver)
+            .await;
+
+        let http_client = Client::builder()
+            .dns_resolver(Arc::new(PublicAddressResolver))
+            .build()
+            .unwrap();
+        let dispatcher = WebhookDispatcher::new(store.clone(), http_client)
+            .with_url_policy(WebhookUrlPolicy::Any);
+        dispatcher.dispatch_due().await.unwrap();
+
+        let delivery = status_of(&store, &delivery).await;
+        assert_eq!(delivery.status, WebhookDeliveryStatus::Pending);
+        assert!(delivery.last_error.is_some());
+        assert!(PublicAddressResolver::lookup("localhost").await.is_err());
+    }
+}
@@ -0,0 +1,362 @@
+use std::collections::{BTreeSet, HashMap};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use color_eyre::eyre::{eyre, Context, Result};
+use ring::signature::{UnparsedPublicKey, RSA_PKCS1_2048_8192_SHA256};
+use roxmltree::{Node, NodeId, NodeType};
+use sha2::{Digest, Sha256};
+
+const DSIG_NS: &str = "http://www.w3.org/2000/09/xmldsig#";
+const EXC_C14N: &str = "http://www.w3.org/2001/10/xml-exc-c14n#";
+const ENVELOPED_SIGNATURE: &str = "http://www.w3.org/2000/09/xmldsig#enveloped-signature";
+const RSA_SHA256: &str = "http://www.w3.org/2001/04/xmldsig-more#rsa-sha256";
+const SHA256: &str = "http://www.w3.org/2001/04/xmlenc#sha256";
+
+// Checks the enveloped XML signature of `element` with `public_key`, a DER
+// `RSAPublicKey`. Only what SAML identity providers actually use is supported: a single
+// reference to the element itself, exclusive canonicalization, RSA-SHA256 and SHA-256.
+// Anything else is refused rather than half understood.
+pub fn verify_enveloped_signature(element: Node, public_key: &[u8]) -> Result<()> {
+    let signature = only_child(element, "Signature")?;
+    let signed_info = only_child(signature, "SignedInfo")?;
+
+    let canonicalization = only_child(signed_info, "CanonicalizationMethod")?;
+    expect_algorithm(canonicalization, EXC_C14N)?;
+    expect_algorithm(only_child(signed_info, "SignatureMethod")?, RSA_SHA256)?;
+
+    // The reference has to be to `element`, and its ID can't be used by anything else.
+    // Otherwise a signed element could be moved elsewhere while a forged one is read.
+    let reference = only_child(signed_info, "Reference")?;
+    let id = element
+        .attribute("ID")
+        .ok_or(eyre!("signed element has no ID"))?;
+    if reference.attribute("URI") != Some(format!("#{}", id).as_str()) {
+        return Err(eyre!("signature is not for the signed element"));
+    }
+    let uses_of_id = element
+        .document()
+        .descendants()
+        .filter(|node| node.attribute("ID") == Some(id))
+        .count();
+    if uses_of_id != 1 {
+        return Err(eyre!("ID {} is used more than once", id));
+    }
+
+    let transforms: Vec<Node> = only_child(reference, "Transforms")?
+        .children()
+        .filter(Node::is_element)
+        .collect();
+    let transform = match transforms.as_slice() {
+        [enveloped, transform]
+            if has_algorithm(*enveloped, ENVELOPED_SIGNATURE)
+                && has_algorithm(*transform, EXC_C14N) =>
+        {
+            *transform
+        }
+        _ => return Err(eyre!("unsupported signature transforms")),
+    };
+    expect_algorithm(only_child(reference, "DigestMethod")?, SHA256)?;
+
+    let digest = decode_base64(only_child(reference, "DigestValue")?)?;
+    let canonical = canonicalize(
+        element,
+        Some(signature.id()),
+        &inclusive_prefixes(transform),
+    );
+    if Sha256::digest(canonical.as_bytes()).as_slice() != digest.as_slice() {
+        return Err(eyre!("digest of the signed element doesn't match"));
+    }
+
+    let signature_value = decode_base64(only_child(signature, "SignatureValue")?)?;
+    let canonical = canonicalize(signed_info, None, &inclusive_prefixes(canonicalization));
+    UnparsedPublicKey::new(&RSA_PKCS1_2048_8192_SHA256, public_key)
+        .verify(canonical.as_bytes(), &signature_value)
+        .map_err(|_| eyre!("signature doesn't match"))
+}
+
+// Exclusive XML Canonicalization 1.0, without comments, of `element` and everything
+// in it but `excluded`. Namespaces are declared where they're first used, plus the
+// ones listed in `inclusive_prefixes` ("#default" for the default namespace).
+pub fn canonicalize(
+    element: Node,
+    excluded: Option<NodeId>,
+    inclusive_prefixes: &[String],
+) -> String {
+    let mut canonical = String::new();
+    write_element(
+        &mut canonical,
+        element,
+        excluded,
+        inclusive_prefixes,
+        &HashMap::new(),
+    );
+    canonical
+}
+
+// `rendered` holds the namespaces declared by the output ancestors of `element`
+fn write_element(
+    out: &mut String,
+    element: Node,
+    excluded: Option<NodeId>,
+    inclusive_prefixes: &[String],
+    rendered: &HashMap<String, String>,
+) {
+    let input = element.document().input_text();
+    let name = qualified_name(element);
+
+    let attributes: Vec<(&str, &str, &str, &str)> = element
+        .attributes()
+        .map(|attribute| {
+            (
+                attribute.namespace().unwrap_or(""),
+                attribute.name(),
+                &input[attribute.range_qname()],
+                attribute.value(),
+            )
+        })
+        .collect();
+
+    let mut prefixes: BTreeSet<&str> = BTreeSet::from([prefix(name)]);
+    prefixes.extend(
+        attributes
+            .iter()
+            .map(|(_, _, name, _)| prefix(name))
+            .filter(|prefix| !prefix.is_empty() && *prefix != "xml"),
+    );
+    prefixes.extend(
+        inclusive_prefixes
+            .iter()
+            .map(|prefix| match prefix.as_str() {
+                "#default" => "",
+                prefix => prefix,
+            }),
+    );
+
+    let mut rendered = rendered.clone();
+    let mut declarations = vec![];
+    for prefix in prefixes {
+        let uri = if prefix.is_empty() {
+            Some(element.default_namespace().unwrap_or(""))
+        } else {
+            element.lookup_namespace_uri(Some(prefix))
+        };
+        // Inclusive prefixes may not be declared at all
+        let Some(uri) = uri else {
+            continue;
+        };
+
+        if rendered.get(prefix).map_or("", String::as_str) != uri {
+            declarations.push((prefix, uri));
+            rendered.insert(prefix.to_owned(), uri.to_owned());
+        }
+    }
+
+    out.push('<');
+    out.push_str(name);
+    for (prefix, uri) in declarations {
+        if prefix.is_empty() {
+            out.push_str(" xmlns=\"");
+        } else {
+            out.push_str(" xmlns:");
+            out.push_str(prefix);
+            out.push_str("=\"");
+        }
+        push_escaped_attribute(out, uri);
+        out.push('"');
+    }
+
+    let mut attributes = attributes;
+    attributes.sort_by(|a, b| (a.0, a.1).cmp(&(b.0, b.1)));
+    for (_, _, name, value) in attributes {
+        out.push(' ');
+        out.push_str(name);
+        out.push_str("=\"");
+        push_escaped_attribute(out, value);
+        out.push('"');
+    }
+    out.push('>');
+
+    for child in element.children() {
+        match child.node_type() {
+            NodeType::Element if Some(child.id()) != excluded => {
+                write_element(out, child, excluded, inclusive_prefixes, &rendered)
+            }
+            NodeType::Text => push_escaped_text(out, child.text().unwrap_or("")),
+            NodeType::PI => {
+                if let Some(pi) = child.pi() {
+                    out.push_str("<?");
+                    out.push_str(pi.target);
+                    if let Some(value) = pi.value.filter(|value| !value.is_empty()) {
+                        out.push(' ');
+                        out.push_str(value);
+                    }
+                    out.push_str("?>");
+                }
+            }
+            _ => {}
+        }
+    }
+
+    out.push_str("</");
+    out.push_str(name);
+    out.push('>');
+}
+
+// The element's name as written, prefix included, which the parsed tree doesn't keep
+fn qualified_name<'input>(element: Node<'_, 'input>) -> &'input str {
+    let tag = &element.document().input_text()[element.range()][1..];
+    let end = tag
+        .find(|c: char| c.is_whitespace() || c == '/' || c == '>')
+        .unwrap_or(tag.len());
+    &tag[..end]
+}
+
+fn prefix(name: &str) -> &str {
+    name.split_once(':').map_or("", |(prefix, _)| prefix)
+}
+
+fn push_escaped_text(out: &mut String, text: &str) {
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '\r' => out.push_str("&#xD;"),
+            c => out.push(c),
+        }
+    }
+}
+
+fn push_escaped_attribute(out: &mut String, value: &str) {
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '"' => out.push_str("&quot;"),
+            '\t' => out.push_str("&#x9;"),
+            '\n' => out.push_str("&#xA;"),
+            '\r' => out.push_str("&#xD;"),
+            c => out.push(c),
+        }
+    }
+}
+
+// The one child of `parent` in the signature namespace with this name
+fn only_child<'a, 'input>(parent: Node<'a, 'input>, name: &str) -> Result<Node<'a, 'input>> {
+    let mut children = parent
+        .children()
+        .filter(|child| child.has_tag_name((DSIG_NS, name)));
+
+    match (children.next(), children.next()) {
+        (Some(child), None) => Ok(child),
+        (None, _) => Err(eyre!("no {} element", name)),
+        (Some(_), Some(_)) => Err(eyre!("more than one {} element", name)),
+    }
+}
+
+fn has_algorithm(node: Node, algorithm: &str) -> bool {
+    node.attribute("Algorithm") == Some(algorithm)
+}
+
+fn expect_algorithm(node: Node, algorithm: &str) -> Result<()> {
+    if has_algorithm(node, algorithm) {
+        Ok(())
+    } else {
+        Err(eyre!(
+            "unsupported {} algorithm {}",
+            node.tag_name().name(),
+            node.attribute("Algorithm").unwrap_or("none")
+        ))
+    }
+}
+
+// The prefixes an exclusive canonicalization method lists to be treated inclusively
+fn inclusive_prefixes(method: Node) -> Vec<String> {
+    method
+        .children()
+        .find(|child| child.has_tag_name((EXC_C14N, "InclusiveNamespaces")))
+        .and_then(|namespaces| namespaces.attribute("PrefixList"))
+        .map(|list| list.split_whitespace().map(str::to_owned).collect())
+        .unwrap_or_default()
+}
+
+fn decode_base64(node: Node) -> Result<Vec<u8>> {
+    let text: String = node
+        .children()
+        .filter(Node::is_text)
+        .flat_map(|text| text.text().unwrap_or("").split_whitespace())
+        .collect();
+
+    STANDARD
+        .decode(text)
+        .wrap_err(format!("{} is not valid base64", node.tag_name().name()))
+}
+
+#[cfg(test)]
+mod tests {
+    use roxmltree::Document;
+
+    use super::*;
+
+    fn canonical(xml: &str, path: &[&str], inclusive_prefixes: &[&str]) -> String {
+        let document = Document::parse(xml).unwrap();
+        let mut element = document.root_element();
+        for name in path {
+            element = element
+                .children()
+                .find(|child| child.tag_name().name() == *name)
+                .unwrap();
+        }
+
+        let inclusive_prefixes: Vec<String> = inclusive_prefixes
+            .iter()
+            .map(|prefix| prefix.to_string())
+            .collect();
+        canonicalize(element, None, &inclusive_prefixes)
+    }
+
+    #[test]
+    fn sorts_attributes_and_escapes_content() {
+        let xml = r#"<root xmlns="urn:default" xmlns:a="urn:a" xmlns:unused="urn:unused"><a:child z='1' a:attr="&lt;x&gt;" b="2&#xA;"/><!-- dropped --><plain>1 &lt; 2 <![CDATA[&>]]> 3</plain></root>"#;
+
+        assert_eq!(
+            canonical(xml, &[], &[]),
+            r#"<root xmlns="urn:default"><a:child xmlns:a="urn:a" b="2&#xA;" z="1" a:attr="&lt;x>"></a:child><plain>1 &lt; 2 &amp;&gt; 3</plain></root>"#
+        );
+    }
+
+    #[test]
+    fn declares_namespaces_where_they_are_used() {
+        let xml = r#"<r xmlns="urn:d" xmlns:a="urn:a"><a:s><t xmlns=""/><a:u/></a:s></r>"#;
+
+        // Inherited namespaces are declared on the apex when it uses them, and only then
+        assert_eq!(
+            canonical(xml, &["s"], &[]),
+            r#"<a:s xmlns:a="urn:a"><t></t><a:u></a:u></a:s>"#
+        );
+        assert_eq!(
+            canonical(xml, &[], &[]),
+            r#"<r xmlns="urn:d"><a:s xmlns:a="urn:a"><t xmlns=""></t><a:u></a:u></a:s></r>"#
+        );
+    }
+
+    #[test]
+    fn declares_inclusive_namespaces_even_if_unused() {
+        let xml =
+            r#"<r xmlns:xs="urn:xs" xmlns:a="urn:a"><a:v xmlns:b="urn:b">xs:string</a:v></r>"#;
+
+        assert_eq!(
+            canonical(xml, &["v"], &["xs", "missing"]),
+            r#"<a:v xmlns:a="urn:a" xmlns:xs="urn:xs">xs:string</a:v>"#
+        );
+    }
+
+    #[test]
+    fn rejects_unsigned_elements() {
+        let xml = r#"<a:r xmlns:a="urn:a" ID="_1"><a:s/></a:r>"#;
+        let document = Document::parse(xml).unwrap();
+
+        let error = verify_enveloped_signature(document.root_element(), &[]).unwrap_err();
+        assert_eq!(error.to_string(), "no Signature element");
+    }
+}
@@ -0,0 +1,131 @@
+use std::time::Duration;
+
+use color_eyre::eyre::Result;
+use jsonwebtoken::jwk::JwkSet;
+use tokio::task::JoinHandle;
+
+use crate::{
+    app_state::{AppState, AuditLogType},
+    domain::{
+        AuditChainVerifier, AuditCheckpoint, AuditEvent, AuditRecord, BrokenAuditLink,
+        ClientFingerprint, TenantId,
+    },
+    utils::{
+        auth::{sign_audit_checkpoint, validate_audit_checkpoint},
+        webhooks::queue_webhook_deliveries,
+    },
+};
+
+// How many entries are read at a time while verifying the chain
+const CHAIN_PAGE_SIZE: u64 = 1000;
+
+// Appends an event to the audit log and queues it for the tenant's webhooks. A failure
+// to do either is logged rather than failing the request that caused the event.
+#[tracing::instrument(name = "Recording audit event", skip_all)]
+pub async fn record_audit_event(
+    state: &AppState,
+    tenant: &TenantId,
+    client: &ClientFingerprint,
+    record: AuditRecord,
+) {
+    let event = AuditEvent::new(tenant.clone(), record, client);
+    if let Err(e) = queue_webhook_deliveries(&state.webhook_store, &event).await {
+        tracing::error!("Failed to queue webhook deliveries: {:?}", e);
+    }
+    if let Err(e) = state.audit_log.write().await.append(event).await {
+        tracing::error!("Failed to record audit event: {:?}", e);
+    }
+}
+
+// Signs the head of the chain, unless nothing was appended since the last checkpoint
+#[tracing::instrument(name = "Creating audit checkpoint", skip_all)]
+pub async fn create_audit_checkpoint(audit_log: &AuditLogType) -> Result<Option<AuditCheckpoint>> {
+    let (head, latest) = {
+        let audit_log = audit_log.read().await;
+        (
+            audit_log.chain_head().await?,
+            audit_log.latest_checkpoint().await?,
+        )
+    };
+
+    let Some(head) = head else {
+        return Ok(None);
+    };
+    if latest.is_some_and(|latest| latest.sequence >= head.sequence) {
+        return Ok(None);
+    }
+
+    let checkpoint = sign_audit_checkpoint(&head)?;
+    audit_log
+        .write()
+        .await
+        .add_checkpoint(checkpoint.clone())
+        .await?;
+
+    Ok(Some(checkpoint))
+}
+
+pub fn spawn_audit_checkpointer(audit_log: AuditLogType, interval: Duration) -> JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            match create_audit_checkpoint(&audit_log).await {
+                Ok(Some(checkpoint)) => {
+                    tracing::debug!(
+                        "Checkpointed the audit log at entry {}",
+                        checkpoint.sequence
+                    )
+                }
+                Ok(None) => {}
+                Err(e) => tracing::error!("Failed to checkpoint the audit log: {:?}", e),
+            }
+        }
+    })
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditChainReport {
+    pub entries: u64,
+    pub checkpoints: usize,
+    pub broken_link: Option<BrokenAuditLink>,
+}
+
+// Walks the whole log, checking every link and every signed checkpoint on the way.
+// `keys` holds the public token signing keys, as published at `/.well-known/jwks.json`.
+#[tracing::instrument(name = "Verifying audit chain", skip_all)]
+pub async fn verify_audit_chain(
+    audit_log: &AuditLogType,
+    keys: &JwkSet,
+) -> Result<AuditChainReport> {
+    let audit_log = audit_log.read().await;
+    let checkpoints = audit_log.checkpoints().await?;
+    let checkpoint_count = checkpoints.len();
+    let mut verifier = AuditChainVerifier::new(checkpoints, |checkpoint| {
+        validate_audit_checkpoint(checkpoint, keys).is_ok()
+    });
+
+    let report = |entries, broken_link| AuditChainReport {
+        entries,
+        checkpoints: checkpoint_count,
+        broken_link,
+    };
+
+    let mut after_sequence = 0;
+    loop {
+        let links = audit_log.chain(after_sequence, CHAIN_PAGE_SIZE).await?;
+        let Some(last) = links.last() else {
+            break;
+        };
+        after_sequence = last.sequence;
+
+        for link in &links {
+            if let Err(broken_link) = verifier.check(link) {
+                return Ok(report(verifier.entries, Some(broken_link)));
+            }
+        }
+    }
+
+    let entries = verifier.entries;
+    Ok(report(entries, verifier.finish().err()))
+}
@@ -1,24 +1,116 @@
 use axum_extra::extract::cookie::{Cookie, SameSite};
-use chrono::Utc;
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Validation};
-use secrecy::{ExposeSecret, Secret};
-use serde::{Deserialize, Serialize};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{SubsecRound, Utc};
 use color_eyre::eyre::{eyre, Context, ContextCompat, Result};
 use core::convert::Into;
+use jsonwebtoken::{
+    decode, decode_header, encode,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, Jwk, JwkSet, KeyAlgorithm,
+        OctetKeyPairParameters, OctetKeyPairType, PublicKeyUse,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header, Validation,
+};
+use lazy_static::lazy_static;
+use ring::{
+    digest, hmac,
+    signature::{Ed25519KeyPair, KeyPair},
+};
+use secrecy::{ExposeSecret, Secret};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    app_state::{ApiKeyStoreType, AppState, BannedTokenStoreType, RoleStoreType, UserStoreType},
+    domain::{
+        email::Email, AccountStatus, ApiKey, ApiKeyStoreError, AuditCheckpoint, AuditLink,
+        AuthAPIError, AuthMethod, Grants, Invitation, MagicLinkId, OAuthClient, Permission,
+        ProviderId, TenantId, TrustedDevice, UserStoreError,
+    },
+    services::{oidc_client::OidcAuthorizationRequest, saml_service_provider::SamlAuthnRequest},
+};
+
+use super::constants::{
+    JWT_COOKIE_NAME, JWT_SECRET, OIDC_STATE_COOKIE_NAME, OIDC_STATE_TTL_SECONDS,
+    SAML_STATE_COOKIE_NAME, SAML_STATE_TTL_SECONDS, TRUSTED_DEVICE_COOKIE_NAME,
+};
+
+// Tokens issued at login and to OAuth clients are signed with an Ed25519 key, so other
+// services can check them locally against the public key published at
+// `/.well-known/jwks.json`. The key is derived from `JWT_SECRET`, which keeps every
+// replica signing with the same key. Audit checkpoints are signed with it too, so the
+// audit log can be checked without the secret. Tokens only this service reads, like
+// magic links and state cookies, stay signed with the secret itself.
+struct TokenSigningKey {
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    jwk: Jwk,
+}
+
+// PKCS#8 v1 wrapping of an Ed25519 seed, which is how `jsonwebtoken` takes the key
+const ED25519_PKCS8_PREFIX: [u8; 16] = [
+    0x30, 0x2e, 0x02, 0x01, 0x00, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x04, 0x22, 0x04, 0x20,
+];
+const TOKEN_SIGNING_KEY_LABEL: &[u8] = b"auth-token-signing-key";
 
+impl TokenSigningKey {
+    fn derive(secret: &Secret<String>) -> Self {
+        let key = hmac::Key::new(hmac::HMAC_SHA256, secret.expose_secret().as_bytes());
+        let seed = hmac::sign(&key, TOKEN_SIGNING_KEY_LABEL);
+        let key_pair = Ed25519KeyPair::from_seed_unchecked(seed.as_ref())
+            .expect("an HMAC-SHA256 tag is a valid Ed25519 seed");
+        let public_key = key_pair.public_key().as_ref();
 
-use crate::{domain::email::Email, app_state::BannedTokenStoreType};
+        let pkcs8 = [ED25519_PKCS8_PREFIX.as_slice(), seed.as_ref()].concat();
+        // Changes along with the key, so verifiers can tell when to fetch the JWKS again
+        let kid: String = digest::digest(&digest::SHA256, public_key).as_ref()[..8]
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
 
-use super::constants::{JWT_COOKIE_NAME, JWT_SECRET};
+        Self {
+            encoding_key: EncodingKey::from_ed_der(&pkcs8),
+            decoding_key: DecodingKey::from_ed_der(public_key),
+            jwk: Jwk {
+                common: CommonParameters {
+                    public_key_use: Some(PublicKeyUse::Signature),
+                    key_algorithm: Some(KeyAlgorithm::EdDSA),
+                    key_id: Some(kid),
+                    ..CommonParameters::default()
+                },
+                algorithm: AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: URL_SAFE_NO_PAD.encode(public_key),
+                }),
+            },
+        }
+    }
+}
+
+lazy_static! {
+    static ref TOKEN_SIGNING_KEY: TokenSigningKey = TokenSigningKey::derive(&JWT_SECRET);
+}
+
+// What `/.well-known/jwks.json` publishes
+pub fn token_signing_jwks() -> JwkSet {
+    JwkSet {
+        keys: vec![TOKEN_SIGNING_KEY.jwk.clone()],
+    }
+}
 
 #[tracing::instrument(name = "Generating auth cookie", skip_all)]
-pub fn generate_auth_cookie(email: &Email) -> Result<Cookie<'static>> {
-    let token = generate_auth_token(email)?;
+pub fn generate_auth_cookie(
+    tenant: &TenantId,
+    email: &Email,
+    amr: &[AuthMethod],
+    grants: &Grants,
+) -> Result<Cookie<'static>> {
+    let token = generate_auth_token(tenant, email, amr, grants)?;
     Ok(create_auth_cookie(token))
 }
 
 #[tracing::instrument(name = "Creating auth cookie", skip_all)]
-fn create_auth_cookie(token: String) -> Cookie<'static> {
+pub fn create_auth_cookie(token: String) -> Cookie<'static> {
     let cookie = Cookie::build((JWT_COOKIE_NAME, token))
         .path("/")
         .http_only(true)
@@ -31,52 +123,538 @@ fn create_auth_cookie(token: String) -> Cookie<'static> {
 pub const TOKEN_TTL_SECONDS: i64 = 600;
 
 #[tracing::instrument(name = "Generating auth token", skip_all)]
-fn generate_auth_token(email: &Email) -> Result<String> {
-    let delta = chrono::Duration::try_seconds(TOKEN_TTL_SECONDS)
-        .wrap_err("failed to create 10 minute time delta")?;
+pub fn generate_auth_token(
+    tenant: &TenantId,
+    email: &Email,
+    amr: &[AuthMethod],
+    grants: &Grants,
+) -> Result<String> {
+    let exp = compute_expiry(TOKEN_TTL_SECONDS)?;
+    let now = Utc::now().timestamp();
+    let issued_at = now.try_into().wrap_err(format!(
+        "failed to cast issue time to usize. issue time: {}",
+        now
+    ))?;
+
+    let sub = email.as_ref().expose_secret().to_owned();
+
+    let claims = Claims {
+        sub,
+        tenant: tenant.as_ref().to_owned(),
+        exp,
+        iat: issued_at,
+        auth_time: issued_at,
+        amr: amr.to_vec(),
+        roles: grants
+            .roles
+            .iter()
+            .map(|role| role.as_ref().to_owned())
+            .collect(),
+        permissions: grants
+            .permissions
+            .iter()
+            .map(|permission| permission.as_ref().to_owned())
+            .collect(),
+        client_id: None,
+    };
+
+    create_signed_token(&claims)
+}
+
+// Tokens of OAuth clients act on behalf of the client itself: `sub` is the client id
+// and the permissions are the scopes granted for the request.
+#[tracing::instrument(name = "Generating client token", skip_all)]
+pub fn generate_client_token(client: &OAuthClient, scopes: &[Permission]) -> Result<String> {
+    let exp = compute_expiry(TOKEN_TTL_SECONDS)?;
+    let now = Utc::now().timestamp();
+    let issued_at = now.try_into().wrap_err(format!(
+        "failed to cast issue time to usize. issue time: {}",
+        now
+    ))?;
+
+    let claims = Claims {
+        sub: client.id.as_ref().to_owned(),
+        tenant: client.tenant.as_ref().to_owned(),
+        exp,
+        iat: issued_at,
+        auth_time: issued_at,
+        amr: vec![],
+        roles: vec![],
+        permissions: scopes
+            .iter()
+            .map(|scope| scope.as_ref().to_owned())
+            .collect(),
+        client_id: Some(client.id.as_ref().to_owned()),
+    };
+
+    create_signed_token(&claims)
+}
+
+pub const MAGIC_LINK_TTL_SECONDS: i64 = 900;
+// Magic link tokens are signed with `JWT_SECRET` like invitation and trusted-device
+// tokens, so they carry their own audience to keep them from passing for either.
+const MAGIC_LINK_AUDIENCE: &str = "magic-link";
+
+#[tracing::instrument(name = "Generating magic link token", skip_all)]
+pub fn generate_magic_link_token(email: &Email, link_id: &MagicLinkId) -> Result<Secret<String>> {
+    let claims = MagicLinkClaims {
+        sub: email.as_ref().expose_secret().to_owned(),
+        jti: link_id.as_ref().expose_secret().to_owned(),
+        aud: MAGIC_LINK_AUDIENCE.to_owned(),
+        exp: compute_expiry(MAGIC_LINK_TTL_SECONDS)?,
+    };
+
+    create_token(&claims).map(Secret::new)
+}
+
+#[tracing::instrument(name = "Validating magic link token", skip_all)]
+pub fn validate_magic_link_token(token: &Secret<String>) -> Result<MagicLinkClaims> {
+    let mut validation = Validation::default();
+    validation.set_audience(&[MAGIC_LINK_AUDIENCE]);
+
+    decode::<MagicLinkClaims>(
+        token.expose_secret(),
+        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+        &validation,
+    )
+    .map(|data| data.claims)
+    .wrap_err("failed to decode magic link token")
+}
+
+// Invitation tokens only identify an invitation, which is looked up and checked
+// against the store when the token is used
+const INVITATION_AUDIENCE: &str = "invitation";
+
+#[tracing::instrument(name = "Generating invitation token", skip_all)]
+pub fn generate_invitation_token(invitation: &Invitation) -> Result<Secret<String>> {
+    let exp = invitation.expires_at.timestamp();
+    let claims = InvitationClaims {
+        sub: invitation.email.as_ref().expose_secret().to_owned(),
+        tenant: invitation.tenant.as_ref().to_owned(),
+        jti: invitation.id.to_string(),
+        aud: INVITATION_AUDIENCE.to_owned(),
+        exp: exp.try_into().wrap_err(format!(
+            "failed to cast exp time to usize. exp time: {}",
+            exp
+        ))?,
+    };
+
+    create_token(&claims).map(Secret::new)
+}
+
+#[tracing::instrument(name = "Validating invitation token", skip_all)]
+pub fn validate_invitation_token(token: &Secret<String>) -> Result<InvitationClaims> {
+    let mut validation = Validation::default();
+    validation.set_audience(&[INVITATION_AUDIENCE]);
+
+    decode::<InvitationClaims>(
+        token.expose_secret(),
+        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+        &validation,
+    )
+    .map(|data| data.claims)
+    .wrap_err("failed to decode invitation token")
+}
+
+// Trusted-device tokens are long-lived, so they carry their own audience to keep them
+// from ever being accepted as auth tokens.
+const TRUSTED_DEVICE_AUDIENCE: &str = "trusted-device";
+
+#[tracing::instrument(name = "Generating trusted device cookie", skip_all)]
+pub fn generate_trusted_device_cookie(device: &TrustedDevice) -> Result<Cookie<'static>> {
+    let exp = device.expires_at.timestamp();
+    let claims = TrustedDeviceClaims {
+        sub: device.email.as_ref().expose_secret().to_owned(),
+        jti: device.id.to_string(),
+        aud: TRUSTED_DEVICE_AUDIENCE.to_owned(),
+        exp: exp.try_into().wrap_err(format!(
+            "failed to cast exp time to usize. exp time: {}",
+            exp
+        ))?,
+    };
+
+    let token = create_token(&claims)?;
+    let max_age = (device.expires_at - Utc::now()).num_seconds().max(0);
+
+    let cookie = Cookie::build((TRUSTED_DEVICE_COOKIE_NAME, token))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::seconds(max_age))
+        .build();
+
+    Ok(cookie)
+}
+
+#[tracing::instrument(name = "Validating trusted device token", skip_all)]
+pub fn validate_trusted_device_token(token: &str) -> Result<TrustedDeviceClaims> {
+    let mut validation = Validation::default();
+    validation.set_audience(&[TRUSTED_DEVICE_AUDIENCE]);
+
+    decode::<TrustedDeviceClaims>(
+        token,
+        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+        &validation,
+    )
+    .map(|data| data.claims)
+    .wrap_err("failed to decode trusted device token")
+}
+
+// Keeps what a sign-in with an upstream provider needs in the browser until the
+// provider redirects back, so no server-side state is needed in between
+const OIDC_STATE_AUDIENCE: &str = "oidc-state";
+
+#[tracing::instrument(name = "Generating OIDC state cookie", skip_all)]
+pub fn generate_oidc_state_cookie(
+    tenant: &TenantId,
+    provider: &ProviderId,
+    request: &OidcAuthorizationRequest,
+) -> Result<Cookie<'static>> {
+    let claims = OidcStateClaims {
+        tenant: tenant.as_ref().to_owned(),
+        provider: provider.as_ref().to_owned(),
+        state: request.state.expose_secret().to_owned(),
+        nonce: request.nonce.expose_secret().to_owned(),
+        code_verifier: request.code_verifier.expose_secret().to_owned(),
+        aud: OIDC_STATE_AUDIENCE.to_owned(),
+        exp: compute_expiry(OIDC_STATE_TTL_SECONDS)?,
+    };
+
+    let token = create_token(&claims)?;
+
+    let cookie = Cookie::build((OIDC_STATE_COOKIE_NAME, token))
+        .path("/")
+        .http_only(true)
+        .same_site(SameSite::Lax)
+        .max_age(time::Duration::seconds(OIDC_STATE_TTL_SECONDS))
+        .build();
+
+    Ok(cookie)
+}
+
+#[tracing::instrument(name = "Validating OIDC state token", skip_all)]
+pub fn validate_oidc_state_token(token: &str) -> Result<OidcStateClaims> {
+    let mut validation = Validation::default();
+    validation.set_audience(&[OIDC_STATE_AUDIENCE]);
+
+    decode::<OidcStateClaims>(
+        token,
+        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+        &validation,
+    )
+    .map(|data| data.claims)
+    .wrap_err("failed to decode OIDC state token")
+}
+
+const SAML_STATE_AUDIENCE: &str = "saml-state";
+
+// Like the OIDC state cookie, but sent along when the provider posts back from its own
+// site, which `SameSite=Lax` cookies aren't. Browsers only allow that for secure cookies.
+#[tracing::instrument(name = "Generating SAML state cookie", skip_all)]
+pub fn generate_saml_state_cookie(
+    tenant: &TenantId,
+    provider: &ProviderId,
+    request: &SamlAuthnRequest,
+) -> Result<Cookie<'static>> {
+    let claims = SamlStateClaims {
+        tenant: tenant.as_ref().to_owned(),
+        provider: provider.as_ref().to_owned(),
+        request_id: request.id.clone(),
+        aud: SAML_STATE_AUDIENCE.to_owned(),
+        exp: compute_expiry(SAML_STATE_TTL_SECONDS)?,
+    };
+
+    let token = create_token(&claims)?;
+
+    let cookie = Cookie::build((SAML_STATE_COOKIE_NAME, token))
+        .path("/")
+        .http_only(true)
+        .secure(true)
+        .same_site(SameSite::None)
+        .max_age(time::Duration::seconds(SAML_STATE_TTL_SECONDS))
+        .build();
+
+    Ok(cookie)
+}
+
+#[tracing::instrument(name = "Validating SAML state token", skip_all)]
+pub fn validate_saml_state_token(token: &str) -> Result<SamlStateClaims> {
+    let mut validation = Validation::default();
+    validation.set_audience(&[SAML_STATE_AUDIENCE]);
+
+    decode::<SamlStateClaims>(
+        token,
+        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
+        &validation,
+    )
+    .map(|data| data.claims)
+    .wrap_err("failed to decode SAML state token")
+}
+
+// Checkpoints are signed like tokens but never expire. The audience keeps them from
+// being accepted as anything else.
+const AUDIT_CHECKPOINT_AUDIENCE: &str = "audit-checkpoint";
+
+#[tracing::instrument(name = "Signing audit checkpoint", skip_all)]
+pub fn sign_audit_checkpoint(head: &AuditLink) -> Result<AuditCheckpoint> {
+    let hash = head
+        .hash
+        .clone()
+        .ok_or(eyre!("the audit event is not chained"))?;
+    let signed_at = Utc::now().trunc_subsecs(0);
+    let claims = AuditCheckpointClaims {
+        sequence: head.sequence,
+        hash: hash.clone(),
+        aud: AUDIT_CHECKPOINT_AUDIENCE.to_owned(),
+        iat: signed_at.timestamp(),
+    };
+
+    Ok(AuditCheckpoint {
+        sequence: head.sequence,
+        hash,
+        signed_at,
+        signature: create_signed_token(&claims)?,
+    })
+}
+
+// Checks the checkpoint was signed with one of the published token signing keys, and
+// that the signature covers its entry
+#[tracing::instrument(name = "Validating audit checkpoint", skip_all)]
+pub fn validate_audit_checkpoint(checkpoint: &AuditCheckpoint, keys: &JwkSet) -> Result<()> {
+    let header = decode_header(&checkpoint.signature)
+        .wrap_err("failed to decode audit checkpoint header")?;
+    let jwk = match &header.kid {
+        Some(kid) => keys.find(kid),
+        None => keys.keys.first(),
+    }
+    .ok_or(eyre!("the audit checkpoint was signed with an unknown key"))?;
+    let key = DecodingKey::from_jwk(jwk).wrap_err("failed to read the token signing key")?;
+
+    let mut validation = Validation::new(Algorithm::EdDSA);
+    validation.set_audience(&[AUDIT_CHECKPOINT_AUDIENCE]);
+    validation.set_required_spec_claims(&["aud"]);
+    validation.validate_exp = false;
+
+    let claims = decode::<AuditCheckpointClaims>(&checkpoint.signature, &key, &validation)
+        .map(|data| data.claims)
+        .wrap_err("failed to decode audit checkpoint")?;
+
+    if claims.sequence != checkpoint.sequence
+        || claims.hash != checkpoint.hash
+        || claims.iat != checkpoint.signed_at.timestamp()
+    {
+        return Err(eyre!("the signature is for another audit checkpoint"));
+    }
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Computing token expiry", skip_all)]
+fn compute_expiry(ttl_seconds: i64) -> Result<usize> {
+    let delta = chrono::Duration::try_seconds(ttl_seconds).wrap_err(format!(
+        "failed to create {} second time delta",
+        ttl_seconds
+    ))?;
 
     let exp = Utc::now()
         .checked_add_signed(delta)
-        .ok_or(eyre!("failed to add 10 minutes to current time"))?
+        .ok_or(eyre!(
+            "failed to add {} seconds to current time",
+            ttl_seconds
+        ))?
         .timestamp();
 
-    let exp: usize = exp.try_into().wrap_err(format!(
+    exp.try_into().wrap_err(format!(
         "failed to cast exp time to usize. exp time: {}",
         exp
-    ))?;
-
-    let sub = email.as_ref().expose_secret().to_owned();
-
-    let claims = Claims { sub, exp };
-
-    create_token(&claims)
+    ))
 }
 
+// Besides checking the token itself, rejects tokens that were banned or revoked along
+// with the user's sessions, and tokens of accounts that are no longer active
 #[tracing::instrument(name = "Validating token", skip_all)]
 pub async fn validate_token(
     token: &Secret<String>,
     banned_token_store: BannedTokenStoreType,
-) -> Result<Claims> {
-    match banned_token_store.read().await.check_if_token_is_banned(token).await {
-        Ok(value) => {
-            if value {
-                return Err(eyre!("token is banned"));
-            }
-        }
-        Err(e) => return Err(e.into()),
+    user_store: UserStoreType,
+) -> Result<Claims, AuthAPIError> {
+    let banned_token_store = banned_token_store.read().await;
+
+    let is_banned = banned_token_store
+        .check_if_token_is_banned(token)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    if is_banned {
+        return Err(AuthAPIError::InvalidToken);
     }
 
-    decode::<Claims>(
+    let claims = decode::<Claims>(
         token.expose_secret(),
-        &DecodingKey::from_secret(JWT_SECRET.expose_secret().as_bytes()),
-        &Validation::default(),
+        &TOKEN_SIGNING_KEY.decoding_key,
+        &Validation::new(Algorithm::EdDSA),
     )
     .map(|data| data.claims)
-    .wrap_err("failed to decode token")
+    .map_err(|_| AuthAPIError::InvalidToken)?;
+
+    let tenant = claims.tenant_id()?;
+
+    // Client tokens have no user account behind them to check
+    if claims.is_client() {
+        return Ok(claims);
+    }
+
+    let email =
+        Email::parse(Secret::new(claims.sub.clone())).map_err(|_| AuthAPIError::InvalidToken)?;
+    let issued_up_to = banned_token_store
+        .get_user_tokens_ban(&tenant, &email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    if issued_up_to.is_some_and(|issued_up_to| claims.iat as i64 <= issued_up_to) {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    ensure_user_active(&tenant, &email, &user_store).await?;
+
+    Ok(claims)
+}
+
+// Resolves an API key to claims for its owner. Keys carry none of the owner's roles,
+// only the scopes they were created with that the owner still holds. Their auth time
+// is 0, so they never pass for a recent login.
+#[tracing::instrument(name = "Validating API key", skip_all)]
+pub async fn validate_api_key(
+    key: &Secret<String>,
+    api_key_store: ApiKeyStoreType,
+    user_store: UserStoreType,
+    role_store: RoleStoreType,
+) -> Result<Claims, AuthAPIError> {
+    let api_key = match api_key_store.read().await.validate_key(key).await {
+        Ok(api_key) => api_key,
+        Err(ApiKeyStoreError::InvalidApiKey) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    if api_key.is_expired() {
+        return Err(AuthAPIError::InvalidToken);
+    }
+
+    ensure_user_active(&api_key.tenant, &api_key.email, &user_store).await?;
+
+    let grants = role_store
+        .read()
+        .await
+        .get_grants(&api_key.tenant, &api_key.email)
+        .await
+        .map_err(|e| AuthAPIError::UnexpectedError(e.into()))?;
+    let permissions = api_key
+        .scopes
+        .iter()
+        .filter(|scope| grants.permissions.contains(scope))
+        .map(|scope| scope.as_ref().to_owned())
+        .collect();
+
+    Ok(Claims {
+        sub: api_key.email.as_ref().expose_secret().to_owned(),
+        tenant: api_key.tenant.as_ref().to_owned(),
+        exp: api_key.expires_at.timestamp() as usize,
+        iat: api_key.created_at.timestamp() as usize,
+        auth_time: 0,
+        amr: vec![AuthMethod::ApiKey],
+        roles: vec![],
+        permissions,
+        client_id: None,
+    })
+}
+
+// Accepts either kind of credential a client may send: an API key or a token issued
+// at login
+pub async fn validate_credential(
+    token: &Secret<String>,
+    state: &AppState,
+) -> Result<Claims, AuthAPIError> {
+    if ApiKey::is_api_key(token.expose_secret()) {
+        validate_api_key(
+            token,
+            state.api_key_store.clone(),
+            state.user_store.clone(),
+            state.role_store.clone(),
+        )
+        .await
+    } else {
+        validate_token(
+            token,
+            state.banned_token_store.clone(),
+            state.user_store.clone(),
+        )
+        .await
+    }
+}
+
+async fn ensure_user_active(
+    tenant: &TenantId,
+    email: &Email,
+    user_store: &UserStoreType,
+) -> Result<(), AuthAPIError> {
+    let user = match user_store.read().await.get_user(tenant, email).await {
+        Ok(user) => user,
+        Err(UserStoreError::UserNotFound) => return Err(AuthAPIError::InvalidToken),
+        Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+    };
+    if user.status != AccountStatus::Active {
+        return Err(AuthAPIError::AccountNotActive(user.status));
+    }
+
+    Ok(())
+}
+
+// Logs the user out everywhere by banning every token issued to them so far. Returns
+// the time of the ban.
+#[tracing::instrument(name = "Revoking all sessions", skip_all)]
+pub async fn revoke_all_sessions(
+    tenant: &TenantId,
+    email: &Email,
+    banned_token_store: &BannedTokenStoreType,
+) -> Result<i64> {
+    let revoked_at = Utc::now().timestamp();
+    banned_token_store
+        .write()
+        .await
+        .ban_user_tokens(tenant, email, revoked_at)
+        .await?;
+
+    Ok(revoked_at)
+}
+
+// Replaces the token of the session that revoked all sessions of its user, so that one
+// stays logged in. Token times are whole seconds, so the new token is dated the second
+// after the ban to outlive it. How and when the user authenticated carries over.
+#[tracing::instrument(name = "Generating replacement token", skip_all)]
+pub fn generate_replacement_token(claims: &Claims, revoked_at: i64) -> Result<String> {
+    let issued_at = revoked_at + 1;
+    let claims = Claims {
+        iat: issued_at
+            .try_into()
+            .wrap_err(format!("failed to cast issue time to usize: {}", issued_at))?,
+        exp: (issued_at + TOKEN_TTL_SECONDS)
+            .try_into()
+            .wrap_err(format!("failed to cast expiry to usize: {}", issued_at))?,
+        ..claims.clone()
+    };
+
+    create_signed_token(&claims)
+}
+
+#[tracing::instrument(name = "Creating signed token", skip_all)]
+fn create_signed_token<T: Serialize>(claims: &T) -> Result<String> {
+    let header = Header {
+        kid: TOKEN_SIGNING_KEY.jwk.common.key_id.clone(),
+        ..Header::new(Algorithm::EdDSA)
+    };
+
+    encode(&header, claims, &TOKEN_SIGNING_KEY.encoding_key).wrap_err("failed to create token")
 }
 
 #[tracing::instrument(name = "Creating token", skip_all)]
-fn create_token(claims: &Claims) -> Result<String> {
+fn create_token<T: Serialize>(claims: &T) -> Result<String> {
     encode(
         &jsonwebtoken::Header::default(),
         &claims,
@@ -85,12 +663,110 @@ fn create_token(claims: &Claims) -> Result<String> {
     .wrap_err("failed to create token")
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
+    // The tenant the user belongs to. Tokens issued before tenants existed belong
+    // to the default one.
+    #[serde(default = "default_tenant")]
+    pub tenant: String,
+    pub exp: usize,
+    #[serde(default)]
+    pub iat: usize,
+    // When the user last proved who they are, and how. Tokens issued before these
+    // claims existed decode with an auth time of 0, so they never count as recent.
+    #[serde(default)]
+    pub auth_time: usize,
+    #[serde(default)]
+    pub amr: Vec<AuthMethod>,
+    // Copied from the role store when the token is issued
+    #[serde(default)]
+    pub roles: Vec<String>,
+    #[serde(default)]
+    pub permissions: Vec<String>,
+    // Only set on tokens issued to OAuth clients, whose `sub` is then the client id
+    // rather than an email
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+}
+
+fn default_tenant() -> String {
+    TenantId::default().as_ref().to_owned()
+}
+
+impl Claims {
+    pub fn tenant_id(&self) -> Result<TenantId, AuthAPIError> {
+        TenantId::parse(self.tenant.clone()).map_err(|_| AuthAPIError::InvalidToken)
+    }
+
+    // Whether the user authenticated within the last `max_age_seconds`
+    pub fn authenticated_within(&self, max_age_seconds: i64) -> bool {
+        let auth_time = self.auth_time as i64;
+        Utc::now().timestamp() - auth_time <= max_age_seconds
+    }
+
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.permissions.iter().any(|granted| granted == permission)
+    }
+
+    pub fn is_client(&self) -> bool {
+        self.client_id.is_some()
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MagicLinkClaims {
+    pub sub: String,
+    pub jti: String,
+    pub aud: String,
+    pub exp: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InvitationClaims {
+    pub sub: String,
+    pub tenant: String,
+    pub jti: String,
+    pub aud: String,
     pub exp: usize,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TrustedDeviceClaims {
+    pub sub: String,
+    pub jti: String,
+    pub aud: String,
+    pub exp: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct OidcStateClaims {
+    pub tenant: String,
+    pub provider: String,
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    pub aud: String,
+    pub exp: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SamlStateClaims {
+    pub tenant: String,
+    pub provider: String,
+    pub request_id: String,
+    pub aud: String,
+    pub exp: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AuditCheckpointClaims {
+    pub sequence: i64,
+    pub hash: String,
+    pub aud: String,
+    pub iat: i64,
+}
+
 #[cfg(test)]
 mod tests {
     use std::sync::Arc;
@@ -98,14 +774,42 @@ mod tests {
     use secrecy::Secret;
     use tokio::sync::RwLock;
 
-    use crate::services::data_stores::hashset_banned_token_store::HashsetBannedTokenStore;
+    use crate::{
+        domain::{ClientFingerprint, Password, Permission, Role, SuspendedBy, User, UserStore},
+        services::data_stores::{
+            hashmap_user_store::HashmapUserStore,
+            hashset_banned_token_store::HashsetBannedTokenStore,
+        },
+    };
 
     use super::*;
 
+    // A user store holding an active account for each of `emails`
+    async fn user_store_with(emails: &[&Email]) -> UserStoreType {
+        let mut user_store = HashmapUserStore::default();
+        for email in emails {
+            let password = Password::parse(Secret::new("password123".to_owned())).unwrap();
+            user_store
+                .add_user(
+                    &TenantId::default(),
+                    User::new((*email).clone(), password, false),
+                )
+                .await
+                .unwrap();
+        }
+        Arc::new(RwLock::new(user_store))
+    }
+
     #[tokio::test]
     async fn test_generate_auth_cookie() {
         let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
-        let cookie = generate_auth_cookie(&email).unwrap();
+        let cookie = generate_auth_cookie(
+            &TenantId::default(),
+            &email,
+            &[AuthMethod::Password],
+            &Grants::default(),
+        )
+        .unwrap();
         assert_eq!(cookie.name(), JWT_COOKIE_NAME);
         assert_eq!(cookie.value().split('.').count(), 3);
         assert_eq!(cookie.path(), Some("/"));
@@ -127,16 +831,73 @@ mod tests {
     #[tokio::test]
     async fn test_generate_auth_token() {
         let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
-        let result = generate_auth_token(&email).unwrap();
+        let result = generate_auth_token(
+            &TenantId::default(),
+            &email,
+            &[AuthMethod::Password],
+            &Grants::default(),
+        )
+        .unwrap();
         assert_eq!(result.split('.').count(), 3);
     }
 
+    #[test]
+    fn test_auth_token_verifies_against_published_key() {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let token =
+            generate_auth_token(&TenantId::default(), &email, &[], &Grants::default()).unwrap();
+        let jwks = token_signing_jwks();
+
+        let header = jsonwebtoken::decode_header(&token).unwrap();
+        assert_eq!(header.alg, Algorithm::EdDSA);
+        let jwk = jwks.find(header.kid.as_deref().unwrap()).unwrap();
+        let key = DecodingKey::from_jwk(jwk).unwrap();
+        let claims = decode::<Claims>(&token, &key, &Validation::new(Algorithm::EdDSA)).unwrap();
+        assert_eq!(claims.claims.sub, "test@example.com");
+
+        // Tokens signed with the secret itself aren't auth tokens
+        let token = create_token(&claims.claims).unwrap();
+        assert!(decode::<Claims>(&token, &key, &Validation::new(Algorithm::EdDSA)).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_validate_client_token() {
+        let scope = Permission::parse("users:manage".to_owned()).unwrap();
+        let (client, _) = OAuthClient::register(
+            TenantId::default(),
+            "billing".to_owned(),
+            vec![scope.clone()],
+        );
+        let token = Secret::new(generate_client_token(&client, &[scope]).unwrap());
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+
+        // No user account is needed
+        let result = validate_token(&token, banned_token_store, user_store_with(&[]).await)
+            .await
+            .unwrap();
+        assert_eq!(result.sub, client.id.as_ref());
+        assert_eq!(result.client_id.as_deref(), Some(client.id.as_ref()));
+        assert_eq!(result.permissions, vec!["users:manage".to_owned()]);
+        assert!(result.is_client());
+    }
+
     #[tokio::test]
     async fn test_validate_token_with_valid_token() {
         let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
-        let token = Secret::new(generate_auth_token(&email).unwrap());
+        let token = Secret::new(
+            generate_auth_token(
+                &TenantId::default(),
+                &email,
+                &[AuthMethod::Password],
+                &Grants::default(),
+            )
+            .unwrap(),
+        );
         let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&token, banned_token_store).await.unwrap();
+        let user_store = user_store_with(&[&email]).await;
+        let result = validate_token(&token, banned_token_store, user_store)
+            .await
+            .unwrap();
         assert_eq!(result.sub, "test@example.com");
 
         let exp = Utc::now()
@@ -145,13 +906,382 @@ mod tests {
             .timestamp();
 
         assert!(result.exp > exp as usize);
+        assert_eq!(result.amr, vec![AuthMethod::Password]);
+        assert!(result.authenticated_within(5));
+    }
+
+    #[test]
+    fn test_stale_auth_time_is_not_recent() {
+        let claims = Claims {
+            sub: "test@example.com".to_owned(),
+            tenant: "default".to_owned(),
+            exp: compute_expiry(TOKEN_TTL_SECONDS).unwrap(),
+            iat: compute_expiry(0).unwrap(),
+            auth_time: compute_expiry(-301).unwrap(),
+            amr: vec![AuthMethod::Password],
+            roles: vec![],
+            permissions: vec![],
+            client_id: None,
+        };
+
+        assert!(!claims.authenticated_within(300));
+        assert!(claims.authenticated_within(600));
+    }
+
+    #[test]
+    fn test_claims_without_auth_time_are_not_recent() {
+        let claims: Claims = serde_json::from_str(r#"{"sub":"test@example.com","exp":1}"#).unwrap();
+
+        assert_eq!(claims.auth_time, 0);
+        assert_eq!(claims.tenant_id().unwrap(), TenantId::default());
+        assert!(claims.amr.is_empty());
+        assert!(!claims.authenticated_within(300));
+        assert!(!claims.has_permission("roles:manage"));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_all_sessions() {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let other = Email::parse(Secret::new("other@example.com".to_owned())).unwrap();
+        let token = Secret::new(
+            generate_auth_token(
+                &TenantId::default(),
+                &email,
+                &[AuthMethod::Password],
+                &Grants::default(),
+            )
+            .unwrap(),
+        );
+        let other_token = Secret::new(
+            generate_auth_token(
+                &TenantId::default(),
+                &other,
+                &[AuthMethod::Password],
+                &Grants::default(),
+            )
+            .unwrap(),
+        );
+        let banned_token_store: BannedTokenStoreType =
+            Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+
+        let user_store = user_store_with(&[&email, &other]).await;
+
+        revoke_all_sessions(&TenantId::default(), &email, &banned_token_store)
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            validate_token(&token, banned_token_store.clone(), user_store.clone()).await,
+            Err(AuthAPIError::InvalidToken)
+        ));
+        assert!(validate_token(&other_token, banned_token_store, user_store)
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_replacement_token_outlives_revoked_sessions() {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let token = Secret::new(
+            generate_auth_token(
+                &TenantId::default(),
+                &email,
+                &[AuthMethod::Password],
+                &Grants::default(),
+            )
+            .unwrap(),
+        );
+        let banned_token_store: BannedTokenStoreType =
+            Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let user_store = user_store_with(&[&email]).await;
+        let claims = validate_token(&token, banned_token_store.clone(), user_store.clone())
+            .await
+            .unwrap();
+
+        // Issued within the same second as the token it replaces
+        let revoked_at = revoke_all_sessions(&TenantId::default(), &email, &banned_token_store)
+            .await
+            .unwrap();
+        let replacement = Secret::new(generate_replacement_token(&claims, revoked_at).unwrap());
+
+        assert!(matches!(
+            validate_token(&token, banned_token_store.clone(), user_store.clone()).await,
+            Err(AuthAPIError::InvalidToken)
+        ));
+        let replaced = validate_token(&replacement, banned_token_store, user_store)
+            .await
+            .unwrap();
+        assert_eq!(replaced.auth_time, claims.auth_time);
+        assert_eq!(replaced.amr, claims.amr);
+    }
+
+    #[tokio::test]
+    async fn test_validate_token_looks_up_the_user_in_the_token_tenant() {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let acme = TenantId::parse("acme".to_owned()).unwrap();
+        let token = Secret::new(
+            generate_auth_token(&acme, &email, &[AuthMethod::Password], &Grants::default())
+                .unwrap(),
+        );
+        let banned_token_store: BannedTokenStoreType =
+            Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+
+        // The user only exists in the default tenant
+        let user_store = user_store_with(&[&email]).await;
+        assert!(matches!(
+            validate_token(&token, banned_token_store.clone(), user_store.clone()).await,
+            Err(AuthAPIError::InvalidToken)
+        ));
+
+        // Revoking the sessions of the default tenant's user leaves the other tenant alone
+        let password = Password::parse(Secret::new("password123".to_owned())).unwrap();
+        user_store
+            .write()
+            .await
+            .add_user(&acme, User::new(email.clone(), password, false))
+            .await
+            .unwrap();
+        revoke_all_sessions(&TenantId::default(), &email, &banned_token_store)
+            .await
+            .unwrap();
+
+        let claims = validate_token(&token, banned_token_store, user_store)
+            .await
+            .unwrap();
+        assert_eq!(claims.tenant_id().unwrap(), acme);
+    }
+
+    #[tokio::test]
+    async fn test_auth_token_carries_grants() {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let grants = Grants::new(
+            vec![Role::parse("admin".to_owned()).unwrap()],
+            vec![Permission::parse("roles:manage".to_owned()).unwrap()],
+        );
+        let token = Secret::new(
+            generate_auth_token(
+                &TenantId::default(),
+                &email,
+                &[AuthMethod::Password],
+                &grants,
+            )
+            .unwrap(),
+        );
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+
+        let user_store = user_store_with(&[&email]).await;
+
+        let claims = validate_token(&token, banned_token_store, user_store)
+            .await
+            .unwrap();
+        assert_eq!(claims.roles, vec!["admin".to_owned()]);
+        assert!(claims.has_permission("roles:manage"));
+        assert!(!claims.has_permission("users:read"));
+    }
+
+    #[tokio::test]
+    async fn test_validate_magic_link_token() {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let link_id = MagicLinkId::default();
+        let token = generate_magic_link_token(&email, &link_id).unwrap();
+
+        let claims = validate_magic_link_token(&token).unwrap();
+        assert_eq!(claims.sub, "test@example.com");
+        assert_eq!(&claims.jti, link_id.as_ref().expose_secret());
+    }
+
+    #[tokio::test]
+    async fn test_magic_link_token_is_not_an_auth_token() {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let token = generate_magic_link_token(&email, &MagicLinkId::default()).unwrap();
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let user_store = user_store_with(&[&email]).await;
+
+        assert!(validate_token(&token, banned_token_store, user_store)
+            .await
+            .is_err());
+
+        let auth_token = Secret::new(
+            generate_auth_token(
+                &TenantId::default(),
+                &email,
+                &[AuthMethod::Password],
+                &Grants::default(),
+            )
+            .unwrap(),
+        );
+        assert!(validate_magic_link_token(&auth_token).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_invitation_token_round_trip() {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let acme = TenantId::parse("acme".to_owned()).unwrap();
+        let invitation = Invitation::new(
+            acme,
+            email.clone(),
+            None,
+            "admin".to_owned(),
+            chrono::Duration::days(7),
+        );
+
+        let token = generate_invitation_token(&invitation).unwrap();
+        let claims = validate_invitation_token(&token).unwrap();
+        assert_eq!(claims.sub, "test@example.com");
+        assert_eq!(claims.tenant, "acme");
+        assert_eq!(claims.jti, invitation.id.to_string());
+
+        // Neither token works as the other
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let user_store = user_store_with(&[&email]).await;
+        assert!(validate_token(&token, banned_token_store, user_store)
+            .await
+            .is_err());
+        assert!(validate_magic_link_token(&token).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_trusted_device_cookie_round_trip() {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let device = TrustedDevice::new(
+            TenantId::default(),
+            email,
+            ClientFingerprint::default(),
+            chrono::Duration::days(30),
+        );
+
+        let user_store = user_store_with(&[&device.email]).await;
+        let cookie = generate_trusted_device_cookie(&device).unwrap();
+        assert_eq!(cookie.name(), TRUSTED_DEVICE_COOKIE_NAME);
+        assert_eq!(cookie.http_only(), Some(true));
+        assert!(cookie.max_age().unwrap() > time::Duration::days(29));
+
+        let claims = validate_trusted_device_token(cookie.value()).unwrap();
+        assert_eq!(claims.sub, "test@example.com");
+        assert_eq!(claims.jti, device.id.to_string());
+
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let token = Secret::new(cookie.value().to_owned());
+        assert!(validate_token(&token, banned_token_store, user_store)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_oidc_state_cookie_round_trip() {
+        let provider = ProviderId::parse("corp".to_owned()).unwrap();
+        let request = OidcAuthorizationRequest::new("http://localhost/callback".to_owned());
+
+        let cookie = generate_oidc_state_cookie(&TenantId::default(), &provider, &request).unwrap();
+        assert_eq!(cookie.name(), OIDC_STATE_COOKIE_NAME);
+        assert_eq!(cookie.http_only(), Some(true));
+
+        let claims = validate_oidc_state_token(cookie.value()).unwrap();
+        assert_eq!(claims.provider, "corp");
+        assert_eq!(&claims.state, request.state.expose_secret());
+        assert_eq!(&claims.code_verifier, request.code_verifier.expose_secret());
+
+        let user_store = user_store_with(&[]).await;
+        let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let token = Secret::new(cookie.value().to_owned());
+        assert!(validate_token(&token, banned_token_store, user_store)
+            .await
+            .is_err());
     }
 
     #[tokio::test]
     async fn test_validate_token_with_invalid_token() {
         let token = Secret::new("invalid_token".to_owned());
         let banned_token_store = Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
-        let result = validate_token(&token, banned_token_store).await;
+        let user_store = user_store_with(&[]).await;
+        let result = validate_token(&token, banned_token_store, user_store).await;
         assert!(result.is_err());
     }
+
+    #[tokio::test]
+    async fn test_validate_token_rejects_inactive_accounts() {
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let token = Secret::new(
+            generate_auth_token(
+                &TenantId::default(),
+                &email,
+                &[AuthMethod::Password],
+                &Grants::default(),
+            )
+            .unwrap(),
+        );
+        let banned_token_store: BannedTokenStoreType =
+            Arc::new(RwLock::new(HashsetBannedTokenStore::default()));
+        let user_store = user_store_with(&[&email]).await;
+
+        user_store
+            .write()
+            .await
+            .set_status(
+                &TenantId::default(),
+                &email,
+                AccountStatus::Suspended,
+                None,
+                Some(SuspendedBy::Admin),
+            )
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            validate_token(&token, banned_token_store.clone(), user_store).await,
+            Err(AuthAPIError::AccountNotActive(AccountStatus::Suspended))
+        ));
+
+        // Tokens of users that no longer exist are simply invalid
+        let user_store = user_store_with(&[]).await;
+        assert!(matches!(
+            validate_token(&token, banned_token_store, user_store).await,
+            Err(AuthAPIError::InvalidToken)
+        ));
+    }
+
+    #[test]
+    fn test_audit_checkpoint_signatures_cover_the_checkpointed_entry() {
+        let event = crate::domain::AuditEvent::new(
+            TenantId::default(),
+            crate::domain::AuditRecord::new("login"),
+            &ClientFingerprint::default(),
+        );
+        let head = AuditLink::new(7, event, crate::domain::AUDIT_CHAIN_GENESIS_HASH);
+
+        let keys = token_signing_jwks();
+        let checkpoint = sign_audit_checkpoint(&head).unwrap();
+        assert_eq!(checkpoint.sequence, 7);
+        assert_eq!(Some(&checkpoint.hash), head.hash.as_ref());
+        assert!(validate_audit_checkpoint(&checkpoint, &keys).is_ok());
+
+        let moved = AuditCheckpoint {
+            sequence: 8,
+            ..checkpoint.clone()
+        };
+        assert!(validate_audit_checkpoint(&moved, &keys).is_err());
+
+        // Only the public key is needed, and no other key will do
+        let other_keys = JwkSet {
+            keys: vec![TokenSigningKey::derive(&Secret::new("another secret".to_owned())).jwk],
+        };
+        assert!(validate_audit_checkpoint(&checkpoint, &other_keys).is_err());
+        let mut renamed_keys = other_keys.clone();
+        renamed_keys.keys[0].common.key_id = keys.keys[0].common.key_id.clone();
+        assert!(validate_audit_checkpoint(&checkpoint, &renamed_keys).is_err());
+
+        // Auth tokens are signed tokens too, but not checkpoints
+        let email = Email::parse(Secret::new("test@example.com".to_owned())).unwrap();
+        let forged = AuditCheckpoint {
+            signature: generate_auth_token(
+                &TenantId::default(),
+                &email,
+                &[AuthMethod::Password],
+                &Grants::default(),
+            )
+            .unwrap(),
+            ..checkpoint
+        };
+        assert!(validate_audit_checkpoint(&forged, &keys).is_err());
+    }
 }
@@ -8,6 +8,23 @@ lazy_static! {
     pub static ref DATABASE_URL: Secret<String> = set_database_url();
     pub static ref REDIS_HOST_NAME: String = set_redis_host();
     pub static ref POSTMARK_AUTH_TOKEN: Secret<String> = set_postmark_auth_token();
+    pub static ref AUTH_SERVICE_URL: String = set_auth_service_url();
+    pub static ref TWILIO_ACCOUNT_SID: Option<String> = set_twilio_account_sid();
+    pub static ref TWILIO_AUTH_TOKEN: Option<Secret<String>> = set_twilio_auth_token();
+    pub static ref TWO_FA_CODE_STORE: String = set_two_fa_code_store();
+    pub static ref TWO_FA_REQUIRE_SAME_CLIENT: bool = set_two_fa_require_same_client();
+    pub static ref TRUSTED_DEVICE_TTL_DAYS: i64 = set_trusted_device_ttl_days();
+    pub static ref GEOIP_DATABASE_PATH: Option<String> = set_geoip_database_path();
+    pub static ref MAX_AUTH_AGE_SECONDS: i64 = set_max_auth_age_seconds();
+    pub static ref ADMIN_API_TOKEN: Option<Secret<String>> = set_admin_api_token();
+    pub static ref LDAP_URL: Option<String> = set_ldap_url();
+    pub static ref LDAP_BASE_DN: String = set_ldap_base_dn();
+    pub static ref LDAP_USER_FILTER: String = set_ldap_user_filter();
+    pub static ref LDAP_BIND_DN: Option<String> = set_ldap_bind_dn();
+    pub static ref LDAP_BIND_PASSWORD: Secret<String> = set_ldap_bind_password();
+    pub static ref LDAP_TENANT: String = set_ldap_tenant();
+    pub static ref LDAP_SIGNUPS: String = set_ldap_signups();
+    pub static ref WEBHOOK_ALLOW_PRIVATE_URLS: bool = set_webhook_allow_private_urls();
 }
 
 
@@ -41,18 +58,180 @@ fn set_postmark_auth_token() -> Secret<String> {
     )
 }
 
+fn set_auth_service_url() -> String {
+    dotenv().ok();
+    std_env::var(env::AUTH_SERVICE_URL_ENV_VAR).unwrap_or(DEFAULT_AUTH_SERVICE_URL.to_owned())
+}
+
+fn set_twilio_account_sid() -> Option<String> {
+    dotenv().ok();
+    std_env::var(env::TWILIO_ACCOUNT_SID_ENV_VAR)
+        .ok()
+        .filter(|sid| !sid.is_empty())
+}
+
+fn set_twilio_auth_token() -> Option<Secret<String>> {
+    dotenv().ok();
+    std_env::var(env::TWILIO_AUTH_TOKEN_ENV_VAR)
+        .ok()
+        .filter(|token| !token.is_empty())
+        .map(Secret::new)
+}
+
+fn set_two_fa_code_store() -> String {
+    dotenv().ok();
+    std_env::var(env::TWO_FA_CODE_STORE_ENV_VAR).unwrap_or(DEFAULT_TWO_FA_CODE_STORE.to_owned())
+}
+
+fn set_two_fa_require_same_client() -> bool {
+    dotenv().ok();
+    std_env::var(env::TWO_FA_REQUIRE_SAME_CLIENT_ENV_VAR)
+        .map(|value| value.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
+fn set_trusted_device_ttl_days() -> i64 {
+    dotenv().ok();
+    match std_env::var(env::TRUSTED_DEVICE_TTL_DAYS_ENV_VAR) {
+        Ok(days) => days
+            .parse()
+            .expect("TRUSTED_DEVICE_TTL_DAYS must be a whole number of days."),
+        Err(_) => DEFAULT_TRUSTED_DEVICE_TTL_DAYS,
+    }
+}
+
+fn set_geoip_database_path() -> Option<String> {
+    dotenv().ok();
+    std_env::var(env::GEOIP_DATABASE_PATH_ENV_VAR)
+        .ok()
+        .filter(|path| !path.is_empty())
+}
+
+fn set_max_auth_age_seconds() -> i64 {
+    dotenv().ok();
+    match std_env::var(env::MAX_AUTH_AGE_SECONDS_ENV_VAR) {
+        Ok(seconds) => seconds
+            .parse()
+            .expect("MAX_AUTH_AGE_SECONDS must be a whole number of seconds."),
+        Err(_) => DEFAULT_MAX_AUTH_AGE_SECONDS,
+    }
+}
+
+fn set_admin_api_token() -> Option<Secret<String>> {
+    dotenv().ok();
+    std_env::var(env::ADMIN_API_TOKEN_ENV_VAR)
+        .ok()
+        .filter(|token| !token.is_empty())
+        .map(Secret::new)
+}
+
+fn set_ldap_url() -> Option<String> {
+    dotenv().ok();
+    std_env::var(env::LDAP_URL_ENV_VAR)
+        .ok()
+        .filter(|url| !url.is_empty())
+}
+
+fn set_ldap_base_dn() -> String {
+    dotenv().ok();
+    std_env::var(env::LDAP_BASE_DN_ENV_VAR).expect("LDAP_BASE_DN must be set when LDAP_URL is.")
+}
+
+fn set_ldap_user_filter() -> String {
+    dotenv().ok();
+    std_env::var(env::LDAP_USER_FILTER_ENV_VAR).unwrap_or(DEFAULT_LDAP_USER_FILTER.to_owned())
+}
+
+fn set_ldap_bind_dn() -> Option<String> {
+    dotenv().ok();
+    std_env::var(env::LDAP_BIND_DN_ENV_VAR)
+        .ok()
+        .filter(|dn| !dn.is_empty())
+}
+
+fn set_ldap_bind_password() -> Secret<String> {
+    dotenv().ok();
+    Secret::new(std_env::var(env::LDAP_BIND_PASSWORD_ENV_VAR).unwrap_or_default())
+}
+
+fn set_ldap_tenant() -> String {
+    dotenv().ok();
+    std_env::var(env::LDAP_TENANT_ENV_VAR).unwrap_or(DEFAULT_LDAP_TENANT.to_owned())
+}
+
+fn set_ldap_signups() -> String {
+    dotenv().ok();
+    std_env::var(env::LDAP_SIGNUPS_ENV_VAR).unwrap_or(DEFAULT_LDAP_SIGNUPS.to_owned())
+}
+
+fn set_webhook_allow_private_urls() -> bool {
+    dotenv().ok();
+    std_env::var(env::WEBHOOK_ALLOW_PRIVATE_URLS_ENV_VAR)
+        .map(|value| value.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
 pub mod env {
     pub const JWT_SECRET_ENV_VAR: &str = "JWT_SECRET";
     pub const DATABASE_URL_ENV_VAR: &str = "DATABASE_URL";
     pub const REDIS_HOST_NAME_ENV_VAR: &str = "REDIS_HOST_NAME";
     pub const POSTMARK_AUTH_TOKEN_ENV_VAR: &str = "POSTMARK_AUTH_TOKEN";
+    pub const AUTH_SERVICE_URL_ENV_VAR: &str = "AUTH_SERVICE_URL";
+    pub const TWILIO_ACCOUNT_SID_ENV_VAR: &str = "TWILIO_ACCOUNT_SID";
+    pub const TWILIO_AUTH_TOKEN_ENV_VAR: &str = "TWILIO_AUTH_TOKEN";
+    pub const TWO_FA_CODE_STORE_ENV_VAR: &str = "TWO_FA_CODE_STORE";
+    pub const TWO_FA_REQUIRE_SAME_CLIENT_ENV_VAR: &str = "TWO_FA_REQUIRE_SAME_CLIENT";
+    pub const TRUSTED_DEVICE_TTL_DAYS_ENV_VAR: &str = "TRUSTED_DEVICE_TTL_DAYS";
+    pub const GEOIP_DATABASE_PATH_ENV_VAR: &str = "GEOIP_DATABASE_PATH";
+    pub const MAX_AUTH_AGE_SECONDS_ENV_VAR: &str = "MAX_AUTH_AGE_SECONDS";
+    pub const ADMIN_API_TOKEN_ENV_VAR: &str = "ADMIN_API_TOKEN";
+    pub const LDAP_URL_ENV_VAR: &str = "LDAP_URL";
+    pub const LDAP_BASE_DN_ENV_VAR: &str = "LDAP_BASE_DN";
+    pub const LDAP_USER_FILTER_ENV_VAR: &str = "LDAP_USER_FILTER";
+    pub const LDAP_BIND_DN_ENV_VAR: &str = "LDAP_BIND_DN";
+    pub const LDAP_BIND_PASSWORD_ENV_VAR: &str = "LDAP_BIND_PASSWORD";
+    pub const LDAP_TENANT_ENV_VAR: &str = "LDAP_TENANT";
+    pub const LDAP_SIGNUPS_ENV_VAR: &str = "LDAP_SIGNUPS";
+    pub const WEBHOOK_ALLOW_PRIVATE_URLS_ENV_VAR: &str = "WEBHOOK_ALLOW_PRIVATE_URLS";
 }
 
-pub const JWT_COOKIE_NAME: &str = "jwt";
+// Read back by `auth_extractor::extract_token`, here and in downstream services
+pub const JWT_COOKIE_NAME: &str = auth_extractor::JWT_COOKIE_NAME;
+pub const TRUSTED_DEVICE_COOKIE_NAME: &str = "trusted_device";
+// Remembers a sign-in with an upstream identity provider until it redirects back
+pub const OIDC_STATE_COOKIE_NAME: &str = "oidc_state";
+pub const OIDC_STATE_TTL_SECONDS: i64 = 600;
+// Same for SAML, where the provider posts back from another site
+pub const SAML_STATE_COOKIE_NAME: &str = "saml_state";
+pub const SAML_STATE_TTL_SECONDS: i64 = 600;
+// Names the tenant of a request; without it the tenant is looked up by Host
+pub const TENANT_HEADER_NAME: &str = "x-tenant-id";
+pub const SCIM_CONTENT_TYPE: &str = "application/scim+json";
 pub const DEFAULT_REDIS_HOSTNAME: &str = "127.0.0.1";
+pub const DEFAULT_AUTH_SERVICE_URL: &str = "http://localhost:3000";
+// "memory" keeps 2FA codes in-process; use "redis" when running more than one replica
+pub const DEFAULT_TWO_FA_CODE_STORE: &str = "memory";
+pub const DEFAULT_TRUSTED_DEVICE_TTL_DAYS: i64 = 30;
+pub const DEFAULT_MAX_AUTH_AGE_SECONDS: i64 = 300;
+pub const INVITATION_TTL_DAYS: i64 = 7;
+pub const API_KEY_DEFAULT_TTL_DAYS: i64 = 90;
+pub const API_KEY_MAX_TTL_DAYS: i64 = 365;
+pub const DEVICE_CODE_TTL_SECONDS: i64 = 900;
+pub const DEVICE_CODE_POLL_INTERVAL_SECONDS: i64 = 5;
+pub const MAGIC_LINK_MAX_REQUESTS: u32 = 5;
+pub const MAGIC_LINK_RATE_LIMIT_WINDOW_MINUTES: i64 = 15;
+// `{email}` is replaced with the escaped email of the user
+pub const DEFAULT_LDAP_USER_FILTER: &str = "(mail={email})";
+pub const DEFAULT_LDAP_TENANT: &str = "default";
+// "delegate" lets people outside the directory sign up as local users; "reject" doesn't
+pub const DEFAULT_LDAP_SIGNUPS: &str = "delegate";
 
 pub mod prod {
+    use std::time::Duration;
+
     pub const APP_ADDRESS: &str = "0.0.0.0:3000";
+    pub const TWO_FA_CODE_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+    pub const AUDIT_CHECKPOINT_INTERVAL: Duration = Duration::from_secs(15 * 60);
     pub mod email_client {
         use std::time::Duration;
 
@@ -61,6 +240,29 @@ pub mod prod {
         pub const SENDER: &str = "bogdan@codeiron.io";
         pub const TIMEOUT: Duration = std::time::Duration::from_secs(10);
     }
+    pub mod sms_client {
+        use std::time::Duration;
+
+        pub const BASE_URL: &str = "https://api.twilio.com";
+        pub const SENDER: &str = "+15005550006";
+        pub const TIMEOUT: Duration = std::time::Duration::from_secs(10);
+    }
+    pub mod oidc_client {
+        use std::time::Duration;
+
+        pub const TIMEOUT: Duration = std::time::Duration::from_secs(10);
+    }
+    pub mod ldap {
+        use std::time::Duration;
+
+        pub const TIMEOUT: Duration = std::time::Duration::from_secs(10);
+    }
+    pub mod webhook_dispatcher {
+        use std::time::Duration;
+
+        pub const TIMEOUT: Duration = std::time::Duration::from_secs(10);
+        pub const INTERVAL: Duration = std::time::Duration::from_secs(5);
+    }
 }
 
 pub mod test {
@@ -71,4 +273,16 @@ pub mod test {
         pub const SENDER: &str = "test@email.com";
         pub const TIMEOUT: Duration = std::time::Duration::from_millis(200);
     }
+    pub mod sms_client {
+        use std::time::Duration;
+
+        pub const SENDER: &str = "+15005550006";
+        pub const TIMEOUT: Duration = std::time::Duration::from_millis(200);
+    }
+    pub mod webhook_dispatcher {
+        use std::time::Duration;
+
+        pub const TIMEOUT: Duration = std::time::Duration::from_millis(200);
+        pub const INTERVAL: Duration = std::time::Duration::from_millis(50);
+    }
 }
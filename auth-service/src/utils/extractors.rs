@@ -0,0 +1,423 @@
+use std::{convert::Infallible, fmt::Display, marker::PhantomData, net::SocketAddr};
+
+use axum::{
+    async_trait,
+    extract::{ConnectInfo, FromRequestParts},
+    http::{
+        header::{HOST, USER_AGENT},
+        request::Parts,
+        HeaderMap,
+    },
+};
+use secrecy::{ExposeSecret, Secret};
+
+use crate::{
+    app_state::AppState,
+    domain::{
+        data_stores::TenantStoreError, permissions::ManageUsers, AuditRecord, AuthAPIError,
+        AuthMethod, ClientFingerprint, Email, RequiredPermission, ScimError, Tenant, TenantId,
+    },
+};
+
+use super::{
+    audit::record_audit_event,
+    auth::{validate_credential, Claims},
+    constants::TENANT_HEADER_NAME,
+    hashing::constant_time_eq,
+};
+
+// Extractor for the tenant a request is made for: the one named by the `X-Tenant-ID`
+// header, else the one whose hostname matches the `Host` header, else the default one.
+// Naming an unknown tenant is an error rather than a silent fallback.
+#[derive(Debug, Clone)]
+pub struct CurrentTenant(pub Tenant);
+
+#[async_trait]
+impl FromRequestParts<AppState> for CurrentTenant {
+    type Rejection = AuthAPIError;
+
+    #[tracing::instrument(name = "Resolving tenant", skip_all)]
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        // Several extractors of one request may need the tenant, so it is looked up once
+        if let Some(tenant) = parts.extensions.get::<CurrentTenant>() {
+            return Ok(tenant.clone());
+        }
+
+        let tenant_store = state.tenant_store.read().await;
+
+        let result = match tenant_header(&parts.headers) {
+            Some(header) => {
+                let id = TenantId::parse(header).map_err(|_| AuthAPIError::TenantNotFound)?;
+                tenant_store.get_tenant(&id).await
+            }
+            None => match host_name(&parts.headers) {
+                Some(host) => match tenant_store.get_tenant_by_hostname(&host).await {
+                    Err(TenantStoreError::TenantNotFound) => {
+                        tenant_store.get_tenant(&TenantId::default()).await
+                    }
+                    result => result,
+                },
+                None => tenant_store.get_tenant(&TenantId::default()).await,
+            },
+        };
+
+        let tenant = match result {
+            Ok(tenant) => Self(tenant),
+            Err(TenantStoreError::TenantNotFound) => return Err(AuthAPIError::TenantNotFound),
+            Err(e) => return Err(AuthAPIError::UnexpectedError(e.into())),
+        };
+
+        parts.extensions.insert(tenant.clone());
+        Ok(tenant)
+    }
+}
+
+fn tenant_header(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(TENANT_HEADER_NAME)
+        .and_then(|value| value.to_str().ok())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+        .map(str::to_owned)
+}
+
+// The `Host` header without its port, e.g. `acme.example.com` for `acme.example.com:3000`
+fn host_name(headers: &HeaderMap) -> Option<String> {
+    let host = headers.get(HOST).and_then(|value| value.to_str().ok())?;
+    let name = match host.rsplit_once(':') {
+        Some((name, port)) if port.chars().all(|c| c.is_ascii_digit()) => name,
+        _ => host,
+    };
+
+    Some(name.to_ascii_lowercase()).filter(|name| !name.is_empty())
+}
+
+// Extractor for routes that need a logged-in user. Requests without a valid,
+// non-banned token or API key in the `jwt` cookie or a bearer header, or whose
+// account is no longer active, are rejected before the handler runs.
+pub struct AuthenticatedUser {
+    // Taken from the token, so it's the tenant the user signed in to
+    pub tenant: TenantId,
+    pub email: Email,
+    pub token: Secret<String>,
+    pub claims: Claims,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for AuthenticatedUser {
+    type Rejection = AuthAPIError;
+
+    #[tracing::instrument(name = "Authenticating request", skip_all)]
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let token = extract_auth_token(&parts.headers).ok_or(AuthAPIError::MissingToken)?;
+
+        let claims = validate_credential(&token, state).await?;
+
+        Self::from_claims(token, claims)
+    }
+}
+
+impl AuthenticatedUser {
+    fn from_claims(token: Secret<String>, claims: Claims) -> Result<Self, AuthAPIError> {
+        // OAuth clients act on their own behalf, never as a user
+        if claims.is_client() {
+            return Err(AuthAPIError::InvalidToken);
+        }
+
+        let tenant = claims.tenant_id()?;
+        let email = Email::parse(Secret::new(claims.sub.clone()))
+            .map_err(|_| AuthAPIError::InvalidToken)?;
+
+        Ok(Self {
+            tenant,
+            email,
+            token,
+            claims,
+        })
+    }
+}
+
+// Reads the auth token the same way downstream services do, from an `Authorization:
+// Bearer` header or else the `jwt` cookie
+pub fn extract_auth_token(headers: &HeaderMap) -> Option<Secret<String>> {
+    auth_extractor::extract_token(headers).map(Secret::new)
+}
+
+// Extractor for routes that manage the user's account or session. API keys are
+// meant for calling other services with the scopes they were given, so requests made
+// with one are rejected here whatever its scopes.
+pub struct InteractiveUser(pub AuthenticatedUser);
+
+#[async_trait]
+impl FromRequestParts<AppState> for InteractiveUser {
+    type Rejection = AuthAPIError;
+
+    #[tracing::instrument(name = "Checking for an interactive login", skip_all)]
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let user = AuthenticatedUser::from_request_parts(parts, state).await?;
+
+        if user.claims.amr == [AuthMethod::ApiKey] {
+            return Err(AuthAPIError::ApiKeyNotAllowed);
+        }
+
+        Ok(Self(user))
+    }
+}
+
+// Extractor for sensitive routes. On top of an interactive login, the user must have
+// logged in or reauthenticated within the configured max auth age.
+pub struct RecentlyAuthenticatedUser(pub AuthenticatedUser);
+
+#[async_trait]
+impl FromRequestParts<AppState> for RecentlyAuthenticatedUser {
+    type Rejection = AuthAPIError;
+
+    #[tracing::instrument(name = "Checking authentication age", skip_all)]
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let InteractiveUser(user) = InteractiveUser::from_request_parts(parts, state).await?;
+
+        if !user.claims.authenticated_within(state.max_auth_age_seconds) {
+            return Err(AuthAPIError::ReauthenticationRequired);
+        }
+
+        Ok(Self(user))
+    }
+}
+
+// Route guard for users whose token grants the permission `P`, e.g.
+// `RequirePermission<ManageRoles>`. Users without it get a 403.
+pub struct RequirePermission<P> {
+    pub user: AuthenticatedUser,
+    permission: PhantomData<fn() -> P>,
+}
+
+#[async_trait]
+impl<P: RequiredPermission> FromRequestParts<AppState> for RequirePermission<P> {
+    type Rejection = AuthAPIError;
+
+    #[tracing::instrument(name = "Checking permission", skip_all)]
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let user = AuthenticatedUser::from_request_parts(parts, state).await?;
+
+        if !user.claims.has_permission(P::NAME) {
+            return Err(AuthAPIError::MissingPermission);
+        }
+
+        Ok(Self {
+            user,
+            permission: PhantomData,
+        })
+    }
+}
+
+// Extractor for the `/admin` routes. Callers present either the static admin token
+// as a bearer token, which works for every tenant, or a user's or OAuth client's token
+// granting `users:manage` within the tenant of the request.
+pub enum AdminCaller {
+    Token,
+    User(Box<AuthenticatedUser>),
+    Client(String),
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for AdminCaller {
+    type Rejection = AuthAPIError;
+
+    #[tracing::instrument(name = "Authenticating admin", skip_all)]
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let result = Self::authenticate(parts, state).await;
+
+        // Someone presented a token that doesn't open the admin API
+        if let Err(e @ (AuthAPIError::InvalidToken | AuthAPIError::MissingPermission)) = &result {
+            let Ok(auditor) = Auditor::from_request_parts(parts, state).await;
+            auditor
+                .record(AuditRecord::new("admin_access").failure(e))
+                .await;
+        }
+
+        result
+    }
+}
+
+impl AdminCaller {
+    async fn authenticate(parts: &mut Parts, state: &AppState) -> Result<Self, AuthAPIError> {
+        let token = extract_auth_token(&parts.headers).ok_or(AuthAPIError::MissingToken)?;
+
+        if let Some(admin_token) = &state.admin_token {
+            if constant_time_eq(
+                admin_token.expose_secret().as_bytes(),
+                token.expose_secret().as_bytes(),
+            ) {
+                return Ok(Self::Token);
+            }
+        }
+
+        let claims = validate_credential(&token, state).await?;
+        if !claims.has_permission(ManageUsers::NAME) {
+            return Err(AuthAPIError::MissingPermission);
+        }
+
+        // Callers can only manage the tenant they belong to
+        let CurrentTenant(tenant) = CurrentTenant::from_request_parts(parts, state).await?;
+        if claims.tenant_id()? != tenant.id {
+            return Err(AuthAPIError::MissingPermission);
+        }
+
+        match claims.client_id.clone() {
+            Some(client_id) => Ok(Self::Client(client_id)),
+            None => Ok(Self::User(Box::new(AuthenticatedUser::from_claims(
+                token, claims,
+            )?))),
+        }
+    }
+
+    // Who made the call, for the audit trail
+    pub fn name(&self) -> &str {
+        match self {
+            AdminCaller::Token => "admin-token",
+            AdminCaller::User(user) => user.email.as_ref().expose_secret(),
+            AdminCaller::Client(client_id) => client_id,
+        }
+    }
+}
+
+// Extractor for the `/scim/v2` routes. Provisioning clients authenticate with a bearer
+// token like any `AdminCaller`, but are answered with SCIM errors.
+pub struct ScimCaller {
+    pub admin: AdminCaller,
+    pub tenant: Tenant,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for ScimCaller {
+    type Rejection = ScimError;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let admin = AdminCaller::from_request_parts(parts, state).await?;
+        let CurrentTenant(tenant) = CurrentTenant::from_request_parts(parts, state).await?;
+
+        Ok(Self { admin, tenant })
+    }
+}
+
+// Records audit events of a request, adding the tenant of the request and the client's
+// IP address and user agent. Nothing is recorded for requests naming an unknown tenant.
+pub struct Auditor {
+    state: AppState,
+    tenant: Option<TenantId>,
+    client: ClientFingerprint,
+}
+
+#[async_trait]
+impl FromRequestParts<AppState> for Auditor {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        state: &AppState,
+    ) -> Result<Self, Self::Rejection> {
+        let tenant = CurrentTenant::from_request_parts(parts, state)
+            .await
+            .ok()
+            .map(|CurrentTenant(tenant)| tenant.id);
+        let Ok(client) = ClientFingerprint::from_request_parts(parts, state).await;
+
+        Ok(Self {
+            state: state.clone(),
+            tenant,
+            client,
+        })
+    }
+}
+
+impl Auditor {
+    pub async fn record(&self, record: AuditRecord) {
+        if let Some(tenant) = &self.tenant {
+            record_audit_event(&self.state, tenant, &self.client, record).await;
+        }
+    }
+
+    // Records the outcome of a request: a failure with the error as the reason, if it
+    // failed
+    pub async fn record_result<T, E: Display>(&self, record: AuditRecord, result: &Result<T, E>) {
+        match result {
+            Ok(_) => self.record(record).await,
+            Err(e) => self.record(record.failure(e)).await,
+        }
+    }
+
+    pub fn client(&self) -> &ClientFingerprint {
+        &self.client
+    }
+}
+
+// Records the peer address and user agent of the caller. The peer address is only
+// available when the server is started with connect info, otherwise it is left empty.
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for ClientFingerprint {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let ip_address = parts
+            .extensions
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+
+        let user_agent = parts
+            .headers
+            .get(USER_AGENT)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_owned);
+
+        Ok(ClientFingerprint::new(ip_address, user_agent))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    #[test]
+    fn strips_the_port_from_the_host() {
+        let host = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(HOST, HeaderValue::from_str(value).unwrap());
+            host_name(&headers)
+        };
+
+        assert_eq!(
+            host("acme.example.com:3000"),
+            Some("acme.example.com".to_owned())
+        );
+        assert_eq!(
+            host("ACME.example.com"),
+            Some("acme.example.com".to_owned())
+        );
+        assert_eq!(host("[::1]:3000"), Some("[::1]".to_owned()));
+        assert_eq!(host("[::1]"), Some("[::1]".to_owned()));
+        assert_eq!(host_name(&HeaderMap::new()), None);
+    }
+}
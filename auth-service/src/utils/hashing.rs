@@ -0,0 +1,93 @@
+use argon2::{
+    password_hash::SaltString, Algorithm, Argon2, Params, PasswordHash, PasswordHasher,
+    PasswordVerifier, Version,
+};
+use color_eyre::eyre::{Context, Result};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+
+// Argon2 hashing for the secrets users choose, which are worth slowing guesses down
+
+#[tracing::instrument(name = "Verify password hash", skip_all)]
+pub async fn verify_password_hash(
+    expected_password_hash: Secret<String>, // Updated!
+    password_candidate: Secret<String>,     // Updated!
+) -> Result<()> {
+    let current_span: tracing::Span = tracing::Span::current();
+    let result = tokio::task::spawn_blocking(move || {
+        current_span.in_scope(|| {
+            let expected_password_hash: PasswordHash<'_> =
+                PasswordHash::new(expected_password_hash.expose_secret())?;
+
+            Argon2::default()
+                .verify_password(
+                    password_candidate.expose_secret().as_bytes(), // Updated!
+                    &expected_password_hash,
+                )
+                .wrap_err("failed to verify password hash")
+        })
+    })
+    .await;
+
+    result?
+}
+
+#[tracing::instrument(name = "Computing password hash", skip_all)]
+pub async fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>> {
+    // Updated!
+    let current_span: tracing::Span = tracing::Span::current();
+
+    let result = tokio::task::spawn_blocking(move || {
+        current_span.in_scope(|| {
+            let salt: SaltString = SaltString::generate(&mut rand::thread_rng());
+            let password_hash = Argon2::new(
+                Algorithm::Argon2id,
+                Version::V0x13,
+                Params::new(15000, 2, 1, None)?,
+            )
+            .hash_password(password.expose_secret().as_bytes(), &salt)? // Updated!
+            .to_string();
+
+            Ok(Secret::new(password_hash)) // Updated!
+        })
+    })
+    .await;
+
+    result?
+}
+
+// API keys are long random values, so a fast hash is enough to keep them safe at rest
+// and checking one doesn't cost more than the request it authenticates
+pub fn compute_api_key_hash(key: &Secret<String>) -> String {
+    format!("{:x}", Sha256::digest(key.expose_secret().as_bytes()))
+}
+
+pub fn verify_api_key_hash(expected_key_hash: &str, key_candidate: &Secret<String>) -> bool {
+    constant_time_eq(
+        expected_key_hash.as_bytes(),
+        compute_api_key_hash(key_candidate).as_bytes(),
+    )
+}
+
+// Compares secrets without leaking how much of them matched through timing
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn api_key_hash_only_matches_the_same_key() {
+        let key = Secret::new("pat_3kTq9ZxA_secret".to_owned());
+        let key_hash = compute_api_key_hash(&key);
+
+        assert_eq!(key_hash.len(), 64);
+        assert!(verify_api_key_hash(&key_hash, &key));
+        assert!(!verify_api_key_hash(
+            &key_hash,
+            &Secret::new("pat_3kTq9ZxA_other".to_owned())
+        ));
+    }
+}
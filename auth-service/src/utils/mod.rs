@@ -1,3 +1,7 @@
-pub mod constants;
+pub mod audit;
 pub mod auth;
-pub mod tracing;
\ No newline at end of file
+pub mod constants;
+pub mod extractors;
+pub mod hashing;
+pub mod tracing;
+pub mod webhooks;
@@ -0,0 +1,35 @@
+use color_eyre::eyre::Result;
+
+use crate::{
+    app_state::WebhookStoreType,
+    domain::{AuditEvent, WebhookDelivery, WebhookEventType, WebhookPayload},
+};
+
+// Queues the event for every webhook of its tenant that subscribes to it. The
+// dispatcher sends them from the background.
+#[tracing::instrument(name = "Queueing webhook deliveries", skip_all)]
+pub async fn queue_webhook_deliveries(
+    webhook_store: &WebhookStoreType,
+    event: &AuditEvent,
+) -> Result<()> {
+    let Some(event_type) = WebhookEventType::of(event) else {
+        return Ok(());
+    };
+
+    let webhooks = webhook_store
+        .read()
+        .await
+        .list_webhooks(&event.tenant)
+        .await?;
+    let payload = serde_json::to_string(&WebhookPayload::new(event_type, event))?;
+
+    for webhook in webhooks
+        .iter()
+        .filter(|webhook| webhook.subscribes_to(event_type))
+    {
+        let delivery = WebhookDelivery::new(webhook, event_type, payload.clone());
+        webhook_store.write().await.add_delivery(delivery).await?;
+    }
+
+    Ok(())
+}
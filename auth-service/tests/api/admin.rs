@@ -0,0 +1,413 @@
+use std::time::Duration;
+
+use crate::helpers::{get_random_email, TestApp, TEST_ADMIN_TOKEN};
+use auth_service::{
+    domain::AccountStatus,
+    routes::{ListUsersResponse, TokenResponse, TwoFactorAuthResponse, UserDetailsResponse},
+    ErrorResponse,
+};
+use secrecy::ExposeSecret;
+use uuid::Uuid;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+const ADMIN: Option<&str> = Some(TEST_ADMIN_TOKEN);
+
+async fn signup(app: &TestApp, email: &str, requires_2fa: bool) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": requires_2fa
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+async fn login(app: &TestApp, email: &str, password: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "email": email,
+        "password": password,
+    }))
+    .await
+}
+
+// Logs in without touching the cookie jar and returns the token
+async fn login_for_token(app: &TestApp, email: &str) -> String {
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "tokenDelivery": "body",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .token
+}
+
+async fn error_message(response: reqwest::Response) -> String {
+    response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .error
+}
+
+#[tokio::test]
+async fn should_reject_callers_without_admin_access() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_admin("/users", None).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app.get_admin("/users", Some("not-the-admin-token")).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+    let response = login(&app, &random_email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_admin("/users", None).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_message(response).await, "Missing permission");
+
+    let response = app
+        .put_admin(
+            &format!("/users/{}/status", random_email),
+            None,
+            &serde_json::json!({ "status": "suspended" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_list_users_with_search_and_pagination() {
+    let mut app = TestApp::new().await;
+
+    let tag = Uuid::new_v4().simple().to_string();
+    for i in 0..3 {
+        signup(&app, &format!("{}-{}@example.com", tag, i), false).await;
+    }
+    signup(&app, &get_random_email(), false).await;
+
+    let response = app
+        .get_admin(&format!("/users?search={}&perPage=2", tag), ADMIN)
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let page = response
+        .json::<ListUsersResponse>()
+        .await
+        .expect("Could not deserialize response body to ListUsersResponse");
+    assert_eq!(page.total, 3);
+    assert_eq!(page.page, 1);
+    assert_eq!(page.per_page, 2);
+    let emails: Vec<String> = page.users.into_iter().map(|user| user.email).collect();
+    assert_eq!(
+        emails,
+        vec![
+            format!("{}-0@example.com", tag),
+            format!("{}-1@example.com", tag)
+        ]
+    );
+
+    let response = app
+        .get_admin(&format!("/users?search={}&perPage=2&page=2", tag), ADMIN)
+        .await;
+    let page = response
+        .json::<ListUsersResponse>()
+        .await
+        .expect("Could not deserialize response body to ListUsersResponse");
+    assert_eq!(page.users.len(), 1);
+    assert_eq!(page.users[0].email, format!("{}-2@example.com", tag));
+    assert_eq!(page.total, 3);
+
+    let response = app.get_admin("/users?search=%25", ADMIN).await;
+    let page = response
+        .json::<ListUsersResponse>()
+        .await
+        .expect("Could not deserialize response body to ListUsersResponse");
+    assert_eq!(page.total, 0);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_allow_users_with_admin_role() {
+    let mut app = TestApp::new().await;
+
+    let admin_email = get_random_email();
+    signup(&app, &admin_email, false).await;
+    app.assign_role(&admin_email, "admin").await;
+    let response = login(&app, &admin_email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let random_email = get_random_email();
+    signup(&app, &random_email, true).await;
+
+    let response = app
+        .get_admin(&format!("/users/{}", random_email), None)
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let details = response
+        .json::<UserDetailsResponse>()
+        .await
+        .expect("Could not deserialize response body to UserDetailsResponse");
+    assert_eq!(details.user.email, random_email);
+    assert!(details.user.requires_2fa);
+    assert_eq!(details.user.two_fa_channel, "email");
+    assert_eq!(details.user.status, AccountStatus::Active);
+    assert_eq!(details.user.status_reason, None);
+    assert!(details.roles.is_empty());
+
+    let response = app
+        .get_admin(&format!("/users/{}", admin_email), None)
+        .await;
+    let details = response
+        .json::<UserDetailsResponse>()
+        .await
+        .expect("Could not deserialize response body to UserDetailsResponse");
+    assert_eq!(details.roles, vec!["admin".to_owned()]);
+
+    let response = app
+        .get_admin(&format!("/users/{}", get_random_email()), None)
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+    assert_eq!(error_message(response).await, "User not found");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_suspend_and_reactivate_user() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+    let token = login_for_token(&app, &random_email).await;
+
+    let response = app
+        .put_admin(
+            &format!("/users/{}/status", random_email),
+            ADMIN,
+            &serde_json::json!({ "status": "suspended", "reason": "Spam" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    // Suspending ends the sessions the user already has
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .get_admin(&format!("/users/{}", random_email), ADMIN)
+        .await;
+    let details = response
+        .json::<UserDetailsResponse>()
+        .await
+        .expect("Could not deserialize response body to UserDetailsResponse");
+    assert_eq!(details.user.status, AccountStatus::Suspended);
+    assert_eq!(details.user.status_reason.as_deref(), Some("Spam"));
+
+    let response = login(&app, &random_email, "password123").await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_message(response).await, "Account suspended");
+
+    // A wrong password still looks like any other failed login
+    let response = login(&app, &random_email, "wrongpassword").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .put_admin(
+            &format!("/users/{}/status", random_email),
+            ADMIN,
+            &serde_json::json!({ "status": "active" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    // Tokens issued within the second of the revocation are still covered by it
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    let token = login_for_token(&app, &random_email).await;
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_2fa_of_inactive_accounts() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email, true).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = login(&app, &random_email, "password123").await;
+    assert_eq!(response.status().as_u16(), 206);
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let code = app.get_2fa_code(&login_attempt_id, &random_email).await;
+
+    // Flagged while the code is in flight, without touching existing sessions
+    app.set_account_status(&random_email, AccountStatus::PendingVerification)
+        .await;
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": code.as_ref().expose_secret(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        error_message(response).await,
+        "Account pending verification"
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_tokens_of_accounts_suspended_outside_the_api() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+    let token = login_for_token(&app, &random_email).await;
+
+    // No sessions are revoked here, so the token is turned away for the status alone
+    app.set_account_status(&random_email, AccountStatus::Suspended)
+        .await;
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_message(response).await, "Account suspended");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_force_password_reset() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+
+    let response = app
+        .post_admin(&format!("/users/{}/reset-password", random_email), ADMIN)
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = login(&app, &random_email, "password123").await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_message(response).await, "Password reset required");
+
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_magic_link(&serde_json::json!({ "email": random_email }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = app.get_magic_link_token().await;
+    let response = app.post_consume_magic_link(&token).await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    let response = app
+        .post_password(&serde_json::json!({ "newPassword": "newpassword123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = login(&app, &random_email, "password123").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = login(&app, &random_email, "newpassword123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reset_2fa_and_revoke_sessions() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+    let token = login_for_token(&app, &random_email).await;
+
+    let response = app
+        .post_admin(&format!("/users/{}/reset-2fa", random_email), ADMIN)
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    // Resetting 2FA leaves sessions alone
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_admin(&format!("/users/{}/revoke-sessions", random_email), ADMIN)
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    for action in ["reset-password", "reset-2fa", "revoke-sessions"] {
+        let response = app
+            .post_admin(&format!("/users/{}/{}", get_random_email(), action), ADMIN)
+            .await;
+        assert_eq!(response.status().as_u16(), 404, "Failed for {}", action);
+    }
+
+    let response = app
+        .put_admin(
+            &format!("/users/{}/status", get_random_email()),
+            ADMIN,
+            &serde_json::json!({ "status": "suspended" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.clean_up().await;
+}
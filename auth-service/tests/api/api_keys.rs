@@ -0,0 +1,270 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::{
+    domain::{AccountStatus, AuthMethod},
+    routes::{ApiKeyResponse, CreateApiKeyResponse},
+    utils::auth::Claims,
+    ErrorResponse,
+};
+
+async fn signup_and_login(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+async fn create_api_key(app: &TestApp, scopes: &[&str]) -> CreateApiKeyResponse {
+    let response = app
+        .post_api_key(&serde_json::json!({ "name": "ci", "scopes": scopes }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    response
+        .json::<CreateApiKeyResponse>()
+        .await
+        .expect("Could not deserialize response body to CreateApiKeyResponse")
+}
+
+async fn verify(app: &TestApp, key: &str) -> reqwest::Response {
+    app.post_verify_token(&serde_json::json!({ "token": key }))
+        .await
+}
+
+async fn error_message(response: reqwest::Response) -> String {
+    response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .error
+}
+
+#[tokio::test]
+async fn should_create_list_use_and_revoke_api_key() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    let created = create_api_key(&app, &[]).await;
+    assert_eq!(created.api_key.name, "ci");
+    assert!(created.api_key.prefix.starts_with("pat_"));
+    assert!(created
+        .key
+        .starts_with(&format!("{}_", created.api_key.prefix)));
+
+    // Listings never show the key itself
+    let response = app.get_api_keys().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response.text().await.unwrap();
+    assert!(!body.contains(&created.key));
+    let api_keys: Vec<ApiKeyResponse> = serde_json::from_str(&body).unwrap();
+    assert_eq!(api_keys.len(), 1);
+    assert_eq!(api_keys[0].id, created.api_key.id);
+
+    let response = verify(&app, &created.key).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let claims = response
+        .json::<Claims>()
+        .await
+        .expect("Could not deserialize response body to Claims");
+    assert_eq!(claims.sub, random_email);
+    assert_eq!(claims.amr, vec![AuthMethod::ApiKey]);
+
+    let response = app.delete_api_key(&created.api_key.id).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = verify(&app, &created.key).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.delete_api_key(&created.api_key.id).await;
+    assert_eq!(response.status().as_u16(), 404);
+    assert_eq!(error_message(response).await, "API key not found");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_limit_api_key_to_its_scopes() {
+    let mut app = TestApp::new().await;
+
+    let admin_email = get_random_email();
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": admin_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.assign_role(&admin_email, "admin").await;
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": admin_email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let scoped = create_api_key(&app, &["users:manage"]).await;
+    let unscoped = create_api_key(&app, &[]).await;
+
+    let response = verify(&app, &scoped.key).await;
+    let claims = response
+        .json::<Claims>()
+        .await
+        .expect("Could not deserialize response body to Claims");
+    assert_eq!(claims.permissions, vec!["users:manage".to_owned()]);
+    assert!(claims.roles.is_empty());
+
+    let response = app.get_admin("/users", Some(&scoped.key)).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app.get_admin("/users", Some(&unscoped.key)).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    // Scopes the user doesn't hold can't be granted
+    let response = app
+        .post_api_key(&serde_json::json!({ "name": "ci", "scopes": ["billing:manage"] }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    // Keys lose whatever their owner loses
+    let response = app.delete_user_role(&admin_email, "admin").await;
+    assert_eq!(response.status().as_u16(), 204);
+    let response = app.get_admin("/users", Some(&scoped.key)).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_create_api_key_with_api_key() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app, &get_random_email()).await;
+    let created = create_api_key(&app, &[]).await;
+
+    let response = app
+        .http_client
+        .post(format!("{}/api-keys", &app.address))
+        .bearer_auth(&created.key)
+        .json(&serde_json::json!({ "name": "another" }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_message(response).await, "API keys can't be used here");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_manage_account_with_api_key() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+    app.assign_role(&random_email, "admin").await;
+    let created = create_api_key(&app, &["users:manage"]).await;
+
+    let requests = [
+        app.http_client.get(format!("{}/api-keys", &app.address)),
+        app.http_client
+            .delete(format!("{}/api-keys/{}", &app.address, created.api_key.id)),
+        app.http_client
+            .get(format!("{}/trusted-devices", &app.address)),
+        app.http_client
+            .delete(format!("{}/trusted-devices/some-device", &app.address)),
+        app.http_client
+            .get(format!("{}/audit-events", &app.address)),
+        app.http_client
+            .get(format!("{}/device/authorizations/ABCD-EFGH", &app.address)),
+        app.http_client.post(format!(
+            "{}/device/authorizations/ABCD-EFGH/deny",
+            &app.address
+        )),
+        app.http_client
+            .post(format!("{}/phone-number/verify", &app.address))
+            .json(&serde_json::json!({ "code": "123456" })),
+        app.http_client
+            .post(format!("{}/reauthenticate", &app.address))
+            .json(&serde_json::json!({ "password": "password123" })),
+        app.http_client.post(format!("{}/logout", &app.address)),
+    ];
+    for request in requests {
+        let response = request
+            .bearer_auth(&created.key)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_eq!(response.status().as_u16(), 403);
+        assert_eq!(error_message(response).await, "API keys can't be used here");
+    }
+
+    // None of it happened: the key is still there and still works where its scope does
+    let response = app.get_api_keys().await;
+    let api_keys: Vec<ApiKeyResponse> = response.json().await.unwrap();
+    assert_eq!(api_keys.len(), 1);
+    let response = app.get_admin("/users", Some(&created.key)).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_for_invalid_name_or_expiry() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app, &get_random_email()).await;
+
+    let invalid_bodies = [
+        serde_json::json!({ "name": "  " }),
+        serde_json::json!({ "name": "x".repeat(101) }),
+        serde_json::json!({ "name": "ci", "expiresInDays": 0 }),
+        serde_json::json!({ "name": "ci", "expiresInDays": 366 }),
+        serde_json::json!({ "name": "ci", "scopes": ["Not A Scope"] }),
+    ];
+
+    for body in invalid_bodies.iter() {
+        let response = app.post_api_key(body).await;
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for input: {:?}",
+            body
+        );
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_api_key_of_inactive_account() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+    let created = create_api_key(&app, &[]).await;
+
+    app.set_account_status(&random_email, AccountStatus::Suspended)
+        .await;
+
+    let response = verify(&app, &created.key).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = verify(&app, &format!("{}_forged", created.api_key.prefix)).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
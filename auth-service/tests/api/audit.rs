@@ -0,0 +1,321 @@
+use crate::helpers::{get_random_email, TestApp, TEST_ADMIN_TOKEN};
+use auth_service::{
+    domain::AuditOutcome,
+    routes::{AuditEventResponse, ListAuditEventsResponse, TwoFactorAuthResponse},
+    utils::{
+        audit::{create_audit_checkpoint, verify_audit_chain},
+        auth::token_signing_jwks,
+    },
+};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+const ADMIN: Option<&str> = Some(TEST_ADMIN_TOKEN);
+
+async fn signup(app: &TestApp, email: &str, requires_2fa: bool) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": requires_2fa
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+async fn login(app: &TestApp, email: &str, password: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "email": email,
+        "password": password,
+    }))
+    .await
+}
+
+async fn query_audit_events(app: &TestApp, query: &str) -> Vec<AuditEventResponse> {
+    let response = app
+        .get_admin(&format!("/audit-events?{}", query), ADMIN)
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<ListAuditEventsResponse>()
+        .await
+        .expect("Could not deserialize response body to ListAuditEventsResponse")
+        .events
+}
+
+async fn own_audit_events(app: &TestApp) -> Vec<AuditEventResponse> {
+    let response = app.get_audit_events().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<Vec<AuditEventResponse>>()
+        .await
+        .expect("Could not deserialize response body to audit events")
+}
+
+#[tokio::test]
+async fn should_record_successful_and_failed_logins() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+
+    let response = login(&app, &random_email, "wrong-password").await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = login(&app, &random_email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Newest first
+    let events = query_audit_events(&app, &format!("event=login&user={}", random_email)).await;
+    assert_eq!(events.len(), 2);
+    assert_eq!(events[0].outcome, AuditOutcome::Success);
+    assert_eq!(events[0].actor.as_deref(), Some(random_email.as_str()));
+    assert_eq!(events[0].reason.as_deref(), Some("pwd"));
+    assert_eq!(events[1].outcome, AuditOutcome::Failure);
+    assert_eq!(events[1].reason.as_deref(), Some("Incorrect credentials"));
+
+    let events = query_audit_events(&app, "event=login&outcome=failure").await;
+    assert_eq!(events.len(), 1);
+
+    let events = query_audit_events(&app, &format!("event=signup&user={}", random_email)).await;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].outcome, AuditOutcome::Success);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_record_failed_2fa_verifications() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email, true).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = login(&app, &random_email, "password123").await;
+    assert_eq!(response.status().as_u16(), 206);
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": "123456",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let query = format!("user={}&event=two_fa_challenge", random_email);
+    let events = query_audit_events(&app, &query).await;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].reason.as_deref(), Some("pwd"));
+
+    let events = query_audit_events(&app, &format!("user={}&outcome=failure", random_email)).await;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event, "two_fa_verification");
+    assert_eq!(events[0].reason.as_deref(), Some("Incorrect credentials"));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_show_users_only_their_own_activity() {
+    let mut app = TestApp::new().await;
+
+    let other_email = get_random_email();
+    signup(&app, &other_email, false).await;
+    let response = login(&app, &other_email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+    let response = login(&app, &random_email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let events = own_audit_events(&app).await;
+    assert!(events
+        .iter()
+        .all(|event| event.user.as_deref() == Some(random_email.as_str())));
+    let names: Vec<&str> = events.iter().map(|event| event.event.as_str()).collect();
+    assert!(names.contains(&"signup"));
+    assert!(names.contains(&"login"));
+    assert!(!names.contains(&"logout"));
+
+    // The other user's logout was recorded in their own history
+    let events = query_audit_events(&app, &format!("user={}&event=logout", other_email)).await;
+    assert_eq!(events.len(), 1);
+
+    let response = app.post_logout().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app.get_audit_events().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_record_admin_actions_with_the_admin_as_actor() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+
+    let response = app
+        .post_admin(&format!("/users/{}/reset-2fa", random_email), ADMIN)
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let events = query_audit_events(&app, "actor=admin-token").await;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].event, "two_fa_reset");
+    assert_eq!(events[0].user.as_deref(), Some(random_email.as_str()));
+
+    // Users see what was done to their account
+    let response = login(&app, &random_email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+    let events = own_audit_events(&app).await;
+    assert!(events.iter().any(|event| event.event == "two_fa_reset"));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_record_admin_actions_that_failed() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let response = app
+        .post_admin(&format!("/users/{}/revoke-sessions", random_email), ADMIN)
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let events = query_audit_events(&app, "event=sessions_revoked&outcome=failure").await;
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].actor.as_deref(), Some("admin-token"));
+    assert_eq!(events[0].user.as_deref(), Some(random_email.as_str()));
+    assert_eq!(events[0].reason.as_deref(), Some("User not found"));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_page_and_filter_by_time() {
+    let mut app = TestApp::new().await;
+
+    for _ in 0..3 {
+        signup(&app, &get_random_email(), false).await;
+    }
+
+    let events = query_audit_events(&app, "event=signup&perPage=2").await;
+    assert_eq!(events.len(), 2);
+    let events = query_audit_events(&app, "event=signup&perPage=2&page=2").await;
+    assert_eq!(events.len(), 1);
+
+    let events = query_audit_events(&app, "event=signup&since=2000-01-01T00:00:00Z").await;
+    assert_eq!(events.len(), 3);
+    let events = query_audit_events(&app, "event=signup&until=2000-01-01T00:00:00Z").await;
+    assert!(events.is_empty());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_non_admins_and_invalid_filters() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .get_admin("/audit-events", Some("not-the-admin-token"))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+    let response = login(&app, &random_email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.get_admin("/audit-events", None).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    // Both denied attempts are on record
+    let events = query_audit_events(&app, "event=admin_access").await;
+    assert_eq!(events.len(), 2);
+    assert!(events
+        .iter()
+        .all(|event| event.outcome == AuditOutcome::Failure));
+
+    let response = app.get_admin("/audit-events?outcome=maybe", ADMIN).await;
+    assert_eq!(response.status().as_u16(), 400);
+    let response = app.get_admin("/audit-events?since=yesterday", ADMIN).await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_detect_tampering_with_the_audit_chain() {
+    let mut app = TestApp::new().await;
+
+    for _ in 0..3 {
+        signup(&app, &get_random_email(), false).await;
+    }
+
+    let report = verify_audit_chain(&app.audit_log, &token_signing_jwks())
+        .await
+        .unwrap();
+    assert_eq!(report.entries, 3);
+    assert_eq!(report.broken_link, None);
+
+    let checkpoint = create_audit_checkpoint(&app.audit_log)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(checkpoint.sequence, 3);
+    // Nothing new to sign
+    assert!(create_audit_checkpoint(&app.audit_log)
+        .await
+        .unwrap()
+        .is_none());
+
+    signup(&app, &get_random_email(), false).await;
+    let report = verify_audit_chain(&app.audit_log, &token_signing_jwks())
+        .await
+        .unwrap();
+    assert_eq!((report.entries, report.checkpoints), (4, 1));
+    assert_eq!(report.broken_link, None);
+
+    // Rows can't be edited without first switching off the append-only trigger
+    app.execute_sql(
+        r#"
+        ALTER TABLE audit_events DISABLE TRIGGER audit_events_append_only;
+        UPDATE audit_events SET outcome = 'failure' WHERE sequence = 2;
+        "#,
+    )
+    .await;
+
+    let report = verify_audit_chain(&app.audit_log, &token_signing_jwks())
+        .await
+        .unwrap();
+    assert_eq!(report.entries, 1);
+    let broken_link = report
+        .broken_link
+        .expect("The tampered entry went unnoticed");
+    assert_eq!(broken_link.sequence, 2);
+    assert_eq!(broken_link.reason, "does not match its hash");
+
+    app.clean_up().await;
+}
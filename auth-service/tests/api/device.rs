@@ -0,0 +1,214 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::{
+    domain::AuthMethod,
+    routes::{AccessTokenResponse, DeviceAuthorizationResponse, DeviceCodeResponse},
+    utils::auth::Claims,
+    ErrorResponse, OAuthErrorResponse,
+};
+
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+async fn signup_and_login(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+async fn request_device_code(app: &TestApp) -> DeviceCodeResponse {
+    let response = app.post_device_code(&[("client_id", "cli")]).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers().get("cache-control").unwrap(), "no-store");
+
+    response
+        .json::<DeviceCodeResponse>()
+        .await
+        .expect("Could not deserialize response body to DeviceCodeResponse")
+}
+
+async fn poll(app: &TestApp, client_id: &str, device_code: &str) -> reqwest::Response {
+    app.post_token(
+        &[
+            ("grant_type", DEVICE_CODE_GRANT),
+            ("client_id", client_id),
+            ("device_code", device_code),
+        ],
+        None,
+    )
+    .await
+}
+
+async fn oauth_error(response: reqwest::Response) -> String {
+    response
+        .json::<OAuthErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to OAuthErrorResponse")
+        .error
+}
+
+#[tokio::test]
+async fn should_issue_token_once_user_approves_device() {
+    let mut app = TestApp::new().await;
+
+    let device = request_device_code(&app).await;
+    assert!(device.verification_uri.ends_with("/device.html"));
+    assert!(device
+        .verification_uri_complete
+        .ends_with(&device.user_code));
+    assert_eq!(device.interval, 5);
+
+    let response = poll(&app, "cli", &device.device_code).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "authorization_pending");
+
+    // Polling again right away is too fast
+    let response = poll(&app, "cli", &device.device_code).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "slow_down");
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    // The code is accepted however the user types it in
+    let typed_code = device.user_code.replace('-', "").to_lowercase();
+    let response = app.get_device_authorization(&typed_code).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let authorization = response
+        .json::<DeviceAuthorizationResponse>()
+        .await
+        .expect("Could not deserialize response body to DeviceAuthorizationResponse");
+    assert_eq!(authorization.client_id, "cli");
+    assert_eq!(authorization.user_code, device.user_code);
+
+    let response = app.post_device_decision(&typed_code, "approve").await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = poll(&app, "cli", &device.device_code).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers().get("cache-control").unwrap(), "no-store");
+    let token = response
+        .json::<AccessTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to AccessTokenResponse");
+    assert_eq!(token.token_type, "Bearer");
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token.access_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let claims = response
+        .json::<Claims>()
+        .await
+        .expect("Could not deserialize response body to Claims");
+    assert_eq!(claims.sub, random_email);
+    assert_eq!(claims.amr, vec![AuthMethod::Device]);
+    assert_eq!(claims.client_id, None);
+
+    // The device code can only be used once
+    let response = poll(&app, "cli", &device.device_code).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "expired_token");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_deny_device() {
+    let mut app = TestApp::new().await;
+
+    let device = request_device_code(&app).await;
+    signup_and_login(&app, &get_random_email()).await;
+
+    let response = app.post_device_decision(&device.user_code, "deny").await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    // Decided codes can't be decided again
+    let response = app.post_device_decision(&device.user_code, "approve").await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = poll(&app, "cli", &device.device_code).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "access_denied");
+
+    let response = poll(&app, "cli", &device.device_code).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "expired_token");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_unknown_user_code_or_unauthenticated_decision() {
+    let mut app = TestApp::new().await;
+
+    let device = request_device_code(&app).await;
+
+    let response = app.post_device_decision(&device.user_code, "approve").await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    signup_and_login(&app, &get_random_email()).await;
+
+    for user_code in ["BCDF-GHJK", "not-a-code"] {
+        let response = app.get_device_authorization(user_code).await;
+        assert_eq!(
+            response.status().as_u16(),
+            404,
+            "Failed for input: {}",
+            user_code
+        );
+        let error = response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error;
+        assert_eq!(error, "Device code not found");
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_invalid_device_token_requests() {
+    let mut app = TestApp::new().await;
+
+    let response = app.post_device_code(&[("scope", "users:manage")]).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_request");
+
+    let device = request_device_code(&app).await;
+
+    // The code belongs to the client that asked for it
+    let response = poll(&app, "other-cli", &device.device_code).await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_grant");
+
+    let response = poll(&app, "cli", "not-a-device-code").await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_grant");
+
+    let response = app
+        .post_token(
+            &[
+                ("grant_type", DEVICE_CODE_GRANT),
+                ("device_code", device.device_code.as_str()),
+            ],
+            None,
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_request");
+
+    app.clean_up().await;
+}
@@ -1,26 +1,78 @@
 use auth_service::{
-    app_state::{AppState, BannedTokenStoreType, EmailClientType, TwoFACodeStoreType}, domain::Email, get_postgres_pool, get_redis_client, services::{data_stores::{
-        hashmap_two_fa_code_store::HashmapTwoFACodeStore, hashmap_user_store::HashmapUserStore,
-        hashset_banned_token_store::HashsetBannedTokenStore, mock_email_client::MockEmailClient,
-        postgres_user_store::PostgresUserStore, redis_banned_token_store::RedisBannedTokenStore,
-    }, postmark_email_client::PostmarkEmailClient}, utils::constants::{test, DATABASE_URL, DEFAULT_REDIS_HOSTNAME}, Application
+    app_state::{
+        ApiKeyStoreType, AppState, AuditLogType, BannedTokenStoreType, InvitationStoreType,
+        RoleStoreType, TenantStoreType, TwoFACodeStoreType, UserStoreType, WebhookStoreType,
+    },
+    domain::{
+        AccountStatus, Email, LoginAttemptId, PhoneNumber, Role, SuspendedBy, Tenant, TenantId,
+        TwoFAClientPolicy, TwoFACode, TwoFAResendPolicy, WebhookRetryPolicy, WebhookUrlPolicy,
+    },
+    get_postgres_pool, get_redis_client,
+    services::{
+        data_stores::{
+            hashmap_two_fa_code_store::HashmapTwoFACodeStore,
+            postgres_api_key_store::PostgresApiKeyStore, postgres_audit_log::PostgresAuditLog,
+            postgres_federated_identity_store::PostgresFederatedIdentityStore,
+            postgres_group_store::PostgresGroupStore,
+            postgres_identity_provider_store::PostgresIdentityProviderStore,
+            postgres_invitation_store::PostgresInvitationStore,
+            postgres_oauth_client_store::PostgresOAuthClientStore,
+            postgres_role_store::PostgresRoleStore,
+            postgres_saml_provider_store::PostgresSamlProviderStore,
+            postgres_tenant_store::PostgresTenantStore,
+            postgres_trusted_device_store::PostgresTrustedDeviceStore,
+            postgres_user_store::PostgresUserStore, postgres_webhook_store::PostgresWebhookStore,
+            redis_banned_token_store::RedisBannedTokenStore,
+            redis_device_authorization_store::RedisDeviceAuthorizationStore,
+            redis_saml_replay_cache::RedisSamlReplayCache,
+        },
+        heuristic_risk_evaluator::HeuristicRiskEvaluator,
+        postmark_email_client::PostmarkEmailClient,
+        twilio_sms_client::TwilioSmsClient,
+        webhook_dispatcher::{spawn_webhook_dispatcher, WebhookDispatcher},
+    },
+    utils::constants::{test, DATABASE_URL, DEFAULT_REDIS_HOSTNAME},
+    Application,
 };
 use reqwest::{cookie::Jar, Client};
 use secrecy::{ExposeSecret, Secret};
 use sqlx::{
-    postgres::{PgConnectOptions, PgPoolOptions}, Connection, Executor, PgConnection, PgPool
+    postgres::{PgConnectOptions, PgPoolOptions},
+    Connection, Executor, PgConnection, PgPool,
 };
-use wiremock::MockServer;
 use std::{str::FromStr, sync::Arc};
-use tokio::sync::RwLock;
+use tokio::{sync::RwLock, task::JoinHandle};
 use uuid::Uuid;
+use wiremock::MockServer;
+
+// A short cooldown keeps the resend tests fast
+pub const TEST_RESEND_POLICY: TwoFAResendPolicy = TwoFAResendPolicy {
+    cooldown_seconds: 1,
+    max_resends: 2,
+};
+
+// Failed deliveries are retried right away, and given up on quickly
+pub const TEST_WEBHOOK_RETRY_POLICY: WebhookRetryPolicy = WebhookRetryPolicy {
+    base_delay_seconds: 0,
+    max_attempts: 3,
+};
+
+// Static token accepted by the `/admin` routes in tests
+pub const TEST_ADMIN_TOKEN: &str = "test-admin-token";
 
 pub struct TestApp {
     pub address: String,
     pub cookie_jar: Arc<Jar>,
     pub banned_token_store: BannedTokenStoreType,
     pub two_fa_code_store: TwoFACodeStoreType,
+    pub role_store: RoleStoreType,
+    pub user_store: UserStoreType,
+    pub tenant_store: TenantStoreType,
+    pub invitation_store: InvitationStoreType,
+    pub audit_log: AuditLogType,
+    pub webhook_dispatcher: JoinHandle<()>,
     pub email_server: MockServer,
+    pub sms_server: MockServer,
     pub http_client: reqwest::Client,
     pub db_name: String,
     pub clean_up_called: bool,
@@ -28,6 +80,11 @@ pub struct TestApp {
 
 impl TestApp {
     pub async fn new() -> Self {
+        Self::with_config(|app_state| app_state).await
+    }
+
+    // Builds the app with tweaks on top of the default test configuration
+    pub async fn with_config(configure: impl FnOnce(AppState) -> AppState) -> Self {
         let pg_pool = configure_postgresql().await;
         let redis_conn = configure_redis();
         let db_name = match pg_pool.connect_options().get_database() {
@@ -36,19 +93,78 @@ impl TestApp {
                 panic!("Failed to retrieve db name")
             }
         };
-        let user_store = Arc::new(RwLock::new(PostgresUserStore::new(pg_pool)));
-        let banned_token_store = Arc::new(RwLock::new(RedisBannedTokenStore::new(Arc::new(RwLock::new(redis_conn)))));
-        let two_fa_code_store = Arc::new(RwLock::new(HashmapTwoFACodeStore::default()));
+        let user_store: UserStoreType =
+            Arc::new(RwLock::new(PostgresUserStore::new(pg_pool.clone())));
+        let role_store: RoleStoreType =
+            Arc::new(RwLock::new(PostgresRoleStore::new(pg_pool.clone())));
+        let tenant_store: TenantStoreType =
+            Arc::new(RwLock::new(PostgresTenantStore::new(pg_pool.clone())));
+        let invitation_store: InvitationStoreType =
+            Arc::new(RwLock::new(PostgresInvitationStore::new(pg_pool.clone())));
+        let api_key_store: ApiKeyStoreType =
+            Arc::new(RwLock::new(PostgresApiKeyStore::new(pg_pool.clone())));
+        let oauth_client_store =
+            Arc::new(RwLock::new(PostgresOAuthClientStore::new(pg_pool.clone())));
+        let identity_provider_store = Arc::new(RwLock::new(PostgresIdentityProviderStore::new(
+            pg_pool.clone(),
+        )));
+        let federated_identity_store = Arc::new(RwLock::new(PostgresFederatedIdentityStore::new(
+            pg_pool.clone(),
+        )));
+        let saml_provider_store =
+            Arc::new(RwLock::new(PostgresSamlProviderStore::new(pg_pool.clone())));
+        let group_store = Arc::new(RwLock::new(PostgresGroupStore::new(pg_pool.clone())));
+        let audit_log: AuditLogType = Arc::new(RwLock::new(PostgresAuditLog::new(pg_pool.clone())));
+        let webhook_store: WebhookStoreType =
+            Arc::new(RwLock::new(PostgresWebhookStore::new(pg_pool.clone())));
+        let webhook_dispatcher = spawn_webhook_dispatcher(
+            configure_webhook_dispatcher(webhook_store.clone()),
+            test::webhook_dispatcher::INTERVAL,
+        );
+        let trusted_device_store = Arc::new(RwLock::new(PostgresTrustedDeviceStore::new(pg_pool)));
+        let redis_conn = Arc::new(RwLock::new(redis_conn));
+        let banned_token_store =
+            Arc::new(RwLock::new(RedisBannedTokenStore::new(redis_conn.clone())));
+        let device_authorization_store = Arc::new(RwLock::new(RedisDeviceAuthorizationStore::new(
+            redis_conn.clone(),
+        )));
+        let saml_replay_cache = Arc::new(RwLock::new(RedisSamlReplayCache::new(redis_conn)));
+        let two_fa_code_store = Arc::new(RwLock::new(
+            HashmapTwoFACodeStore::default().with_resend_policy(TEST_RESEND_POLICY),
+        ));
         let email_server = MockServer::start().await;
         let base_url = email_server.uri();
         let email_client = Arc::new(RwLock::new(configure_postmark_email_client(base_url)));
+        let sms_server = MockServer::start().await;
+        let sms_client = Arc::new(RwLock::new(configure_twilio_sms_client(sms_server.uri())));
 
         let app_state = AppState::new(
-            user_store,
+            user_store.clone(),
             banned_token_store.clone(),
             two_fa_code_store.clone(),
             email_client,
-        );
+        )
+        .with_sms_client(sms_client)
+        .with_trusted_device_store(trusted_device_store)
+        .with_role_store(role_store.clone())
+        .with_tenant_store(tenant_store.clone())
+        .with_invitation_store(invitation_store.clone())
+        .with_api_key_store(api_key_store)
+        .with_oauth_client_store(oauth_client_store)
+        .with_device_authorization_store(device_authorization_store)
+        .with_identity_provider_store(identity_provider_store)
+        .with_federated_identity_store(federated_identity_store)
+        .with_saml_provider_store(saml_provider_store)
+        .with_saml_replay_cache(saml_replay_cache)
+        .with_group_store(group_store)
+        .with_audit_log(audit_log.clone())
+        .with_webhook_store(webhook_store)
+        // Receivers are mock servers on localhost
+        .with_webhook_url_policy(WebhookUrlPolicy::Any)
+        .with_risk_evaluator(Arc::new(RwLock::new(HeuristicRiskEvaluator::default())))
+        .with_two_fa_client_policy(TwoFAClientPolicy::SameClient)
+        .with_admin_token(Secret::new(TEST_ADMIN_TOKEN.to_owned()));
+        let app_state = configure(app_state);
 
         let app = Application::build(app_state, test::APP_ADDRESS)
             .await
@@ -60,8 +176,10 @@ impl TestApp {
         let _ = tokio::spawn(app.run());
 
         let cookie_jar = Arc::new(Jar::default());
+        // Redirects are left to the tests, e.g. to check where a login sends the browser
         let http_client = reqwest::Client::builder()
             .cookie_provider(cookie_jar.clone())
+            .redirect(reqwest::redirect::Policy::none())
             .build()
             .unwrap();
 
@@ -70,16 +188,51 @@ impl TestApp {
             cookie_jar,
             banned_token_store,
             two_fa_code_store,
+            role_store,
+            user_store,
+            tenant_store,
+            invitation_store,
+            audit_log,
+            webhook_dispatcher,
             email_server,
+            sms_server,
             http_client,
             db_name,
             clean_up_called: false,
         }
     }
 
+    // Reads the code sent for a pending login attempt straight from the store
+    pub async fn get_2fa_code(&self, login_attempt_id: &str, email: &str) -> TwoFACode {
+        let login_attempt_id = LoginAttemptId::parse(Secret::new(login_attempt_id.to_owned()))
+            .expect("Invalid login attempt id");
+        let email = Email::parse(Secret::new(email.to_owned())).expect("Invalid email");
+
+        self.two_fa_code_store
+            .read()
+            .await
+            .get_code(&login_attempt_id, &email)
+            .await
+            .expect("No 2FA code for login attempt")
+            .code
+    }
+
+    // Runs SQL straight against the test database, e.g. to tamper with what the app stored
+    pub async fn execute_sql(&self, sql: &str) {
+        let db_conn_string = format!("{}/{}", DATABASE_URL.expose_secret(), self.db_name);
+        let mut connection = PgConnection::connect(&db_conn_string)
+            .await
+            .expect("Failed to connect to Postgres");
+
+        connection
+            .execute(sql)
+            .await
+            .expect("Failed to execute SQL");
+    }
+
     pub async fn get_root(&self) -> reqwest::Response {
         self.http_client
-            .get(&format!("{}/", &self.address))
+            .get(format!("{}/", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -92,16 +245,93 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
             .json(body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_magic_link<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/login/magic-link", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Pulls the token out of the link in the last email sent through the mock Postmark server
+    pub async fn get_magic_link_token(&self) -> String {
+        self.get_last_email_link()
+            .await
+            .split("token=")
+            .nth(1)
+            .expect("Email does not contain a magic link")
+            .to_owned()
+    }
+
+    pub async fn get_invitation_token(&self) -> String {
+        self.get_last_email_link()
+            .await
+            .split("invitation=")
+            .nth(1)
+            .expect("Email does not contain an invitation link")
+            .to_owned()
+    }
+
+    async fn get_last_email_link(&self) -> String {
+        let requests = self
+            .email_server
+            .received_requests()
+            .await
+            .expect("Request recording is disabled");
+
+        let body: serde_json::Value =
+            serde_json::from_slice(&requests.last().expect("No email was sent").body)
+                .expect("Email body is not JSON");
+
+        body["TextBody"]
+            .as_str()
+            .expect("Email has no text body")
+            .to_owned()
+    }
+
+    // What opening the emailed link does
+    pub async fn get_magic_link_confirmation(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/login/magic-link/consume", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // What submitting the confirmation page does
+    pub async fn post_consume_magic_link(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/login/magic-link/consume", &self.address))
+            .form(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn post_logout(&self) -> reqwest::Response {
         self.http_client
-            .post(&format!("{}/logout", &self.address))
+            .post(format!("{}/logout", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_logout_with_bearer(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/logout", &self.address))
+            .bearer_auth(token)
             .send()
             .await
             .expect("Failed to execute request.")
@@ -112,7 +342,7 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.http_client
-            .post(&format!("{}/signup", &self.address))
+            .post(format!("{}/signup", &self.address))
             .json(body)
             .send()
             .await
@@ -131,6 +361,79 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_resend_2fa<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/resend-2fa", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_trusted_devices(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/trusted-devices", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_trusted_devices_with_bearer(&self, token: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/trusted-devices", &self.address))
+            .bearer_auth(token)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_trusted_device(&self, device_id: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/trusted-devices/{}", &self.address, device_id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_api_key<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/api-keys", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_api_keys(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/api-keys", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_api_key(&self, id: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/api-keys/{}", &self.address, id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_audit_events(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/audit-events", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn post_verify_token<Body>(&self, body: &Body) -> reqwest::Response
     where
         Body: serde::Serialize,
@@ -143,11 +446,370 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn post_reauthenticate<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/reauthenticate", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_phone_number<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/phone-number", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_verify_phone_number<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/phone-number/verify", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_2fa_channel<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/2fa-channel", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_user_roles(&self, email: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/users/{}/roles", &self.address, email))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn put_user_role(&self, email: &str, role: &str) -> reqwest::Response {
+        self.http_client
+            .put(format!("{}/users/{}/roles/{}", &self.address, email, role))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_user_role(&self, email: &str, role: &str) -> reqwest::Response {
+        self.http_client
+            .delete(format!("{}/users/{}/roles/{}", &self.address, email, role))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Without an admin token the request is authenticated by the `jwt` cookie instead
+    pub async fn get_admin(&self, path: &str, admin_token: Option<&str>) -> reqwest::Response {
+        let mut request = self
+            .http_client
+            .get(format!("{}/admin{}", &self.address, path));
+        if let Some(admin_token) = admin_token {
+            request = request.bearer_auth(admin_token);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin(&self, path: &str, admin_token: Option<&str>) -> reqwest::Response {
+        let mut request = self
+            .http_client
+            .post(format!("{}/admin{}", &self.address, path));
+        if let Some(admin_token) = admin_token {
+            request = request.bearer_auth(admin_token);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn post_admin_with_body<Body>(
+        &self,
+        path: &str,
+        admin_token: Option<&str>,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let mut request = self
+            .http_client
+            .post(format!("{}/admin{}", &self.address, path))
+            .json(body);
+        if let Some(admin_token) = admin_token {
+            request = request.bearer_auth(admin_token);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn delete_admin(&self, path: &str, admin_token: Option<&str>) -> reqwest::Response {
+        let mut request = self
+            .http_client
+            .delete(format!("{}/admin{}", &self.address, path));
+        if let Some(admin_token) = admin_token {
+            request = request.bearer_auth(admin_token);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn put_admin<Body>(
+        &self,
+        path: &str,
+        admin_token: Option<&str>,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let mut request = self
+            .http_client
+            .put(format!("{}/admin{}", &self.address, path))
+            .json(body);
+        if let Some(admin_token) = admin_token {
+            request = request.bearer_auth(admin_token);
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn post_accept_invitation<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/invitations/accept", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Form-encoded like OAuth clients send it, with the client's credentials in an
+    // HTTP Basic header when given
+    pub async fn post_token<Body>(
+        &self,
+        body: &Body,
+        basic_auth: Option<(&str, &str)>,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        let mut request = self
+            .http_client
+            .post(format!("{}/token", &self.address))
+            .form(body);
+        if let Some((client_id, client_secret)) = basic_auth {
+            request = request.basic_auth(client_id, Some(client_secret));
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn get_sign_in_providers(&self) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/oidc/providers", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_oidc_login(&self, provider: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/oidc/{}/login", &self.address, provider))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_oidc_callback(
+        &self,
+        provider: &str,
+        query: &[(&str, &str)],
+    ) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/oidc/{}/callback", &self.address, provider))
+            .query(query)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_saml_metadata(&self, provider: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/saml/{}/metadata", &self.address, provider))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_saml_login(&self, provider: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!("{}/saml/{}/login", &self.address, provider))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_saml_acs(&self, provider: &str, form: &[(&str, &str)]) -> reqwest::Response {
+        self.http_client
+            .post(format!("{}/saml/{}/acs", &self.address, provider))
+            .form(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn send_scim(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        token: Option<&str>,
+        body: Option<&serde_json::Value>,
+    ) -> reqwest::Response {
+        let mut request = self
+            .http_client
+            .request(method, format!("{}/scim/v2{}", &self.address, path));
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        if let Some(body) = body {
+            request = request
+                .header("Content-Type", "application/scim+json")
+                .body(body.to_string());
+        }
+
+        request.send().await.expect("Failed to execute request.")
+    }
+
+    pub async fn post_device_code<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/device/code", &self.address))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_device_authorization(&self, user_code: &str) -> reqwest::Response {
+        self.http_client
+            .get(format!(
+                "{}/device/authorizations/{}",
+                &self.address, user_code
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // `decision` is either "approve" or "deny"
+    pub async fn post_device_decision(&self, user_code: &str, decision: &str) -> reqwest::Response {
+        self.http_client
+            .post(format!(
+                "{}/device/authorizations/{}/{}",
+                &self.address, user_code, decision
+            ))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_password<Body>(&self, body: &Body) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}/password", &self.address))
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    // Grants a role straight through the store, e.g. to bootstrap the first admin
+    pub async fn assign_role(&self, email: &str, role: &str) {
+        let email = Email::parse(Secret::new(email.to_owned())).expect("Invalid email");
+        let role = Role::parse(role.to_owned()).expect("Invalid role");
+
+        self.role_store
+            .write()
+            .await
+            .assign_role(&TenantId::default(), &email, &role)
+            .await
+            .expect("Failed to assign role");
+    }
+
+    // Changes the status straight through the store, as an admin would, leaving the
+    // user's sessions alone
+    pub async fn set_account_status(&self, email: &str, status: AccountStatus) {
+        let email = Email::parse(Secret::new(email.to_owned())).expect("Invalid email");
+        let suspended_by = (status == AccountStatus::Suspended).then_some(SuspendedBy::Admin);
+
+        self.user_store
+            .write()
+            .await
+            .set_status(&TenantId::default(), &email, status, None, suspended_by)
+            .await
+            .expect("Failed to set account status");
+    }
+
+    pub async fn add_tenant(&self, tenant: Tenant) {
+        self.tenant_store
+            .write()
+            .await
+            .add_tenant(tenant)
+            .await
+            .expect("Failed to add tenant");
+    }
+
+    // Sends a request on behalf of a tenant through the `X-Tenant-ID` header
+    pub async fn post_with_tenant<Body>(
+        &self,
+        path: &str,
+        tenant: &str,
+        body: &Body,
+    ) -> reqwest::Response
+    where
+        Body: serde::Serialize,
+    {
+        self.http_client
+            .post(format!("{}{}", &self.address, path))
+            .header("X-Tenant-ID", tenant)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn clean_up(&mut self) {
         if self.clean_up_called {
             return;
         }
 
+        // Stop polling the database before it goes away
+        self.webhook_dispatcher.abort();
         delete_database(&self.db_name).await;
 
         self.clean_up_called = true;
@@ -173,7 +835,11 @@ async fn configure_postgresql() -> PgPool {
 
     configure_database(&postgresql_conn_url, &db_name).await;
 
-    let postgresql_conn_url_with_db = Secret::new(format!("{}/{}", postgresql_conn_url.expose_secret(), db_name));
+    let postgresql_conn_url_with_db = Secret::new(format!(
+        "{}/{}",
+        postgresql_conn_url.expose_secret(),
+        db_name
+    ));
 
     get_postgres_pool(&postgresql_conn_url_with_db)
         .await
@@ -257,3 +923,32 @@ fn configure_postmark_email_client(base_url: String) -> PostmarkEmailClient {
 
     PostmarkEmailClient::new(base_url, sender, postmark_auth_token, http_client)
 }
+
+fn configure_webhook_dispatcher(webhook_store: WebhookStoreType) -> WebhookDispatcher {
+    let http_client = Client::builder()
+        .timeout(test::webhook_dispatcher::TIMEOUT)
+        .redirect(reqwest::redirect::Policy::none())
+        .build()
+        .expect("Failed to build HTTP client");
+
+    WebhookDispatcher::new(webhook_store, http_client)
+        .with_retry_policy(TEST_WEBHOOK_RETRY_POLICY)
+        .with_url_policy(WebhookUrlPolicy::Any)
+}
+
+fn configure_twilio_sms_client(base_url: String) -> TwilioSmsClient {
+    let sender = PhoneNumber::parse(Secret::new(test::sms_client::SENDER.to_owned())).unwrap();
+
+    let http_client = Client::builder()
+        .timeout(test::sms_client::TIMEOUT)
+        .build()
+        .expect("Failed to build HTTP client");
+
+    TwilioSmsClient::new(
+        base_url,
+        sender,
+        "AC00000000000000000000000000000000".to_owned(),
+        Secret::new("auth_token".to_owned()),
+        http_client,
+    )
+}
@@ -0,0 +1,371 @@
+use crate::helpers::{get_random_email, TestApp, TEST_ADMIN_TOKEN};
+use auth_service::{
+    domain::{Email, Invitation, InvitationStatus, TenantId},
+    routes::{
+        CreateApiKeyResponse, InvitationResponse, ListInvitationsResponse, UserRolesResponse,
+    },
+    utils::auth::generate_invitation_token,
+    ErrorResponse,
+};
+use secrecy::{ExposeSecret, Secret};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+const ADMIN: Option<&str> = Some(TEST_ADMIN_TOKEN);
+
+async fn mount_email_server(app: &TestApp) {
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+}
+
+// Invites the email and returns the invitation along with the emailed token
+async fn invite(app: &TestApp, email: &str, role: Option<&str>) -> (InvitationResponse, String) {
+    let response = app
+        .post_admin_with_body(
+            "/invitations",
+            ADMIN,
+            &serde_json::json!({ "email": email, "role": role }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let invitation = response
+        .json::<InvitationResponse>()
+        .await
+        .expect("Could not deserialize response body to InvitationResponse");
+
+    (invitation, app.get_invitation_token().await)
+}
+
+async fn accept(app: &TestApp, token: &str) -> reqwest::Response {
+    app.post_accept_invitation(&serde_json::json!({
+        "token": token,
+        "password": "password123",
+    }))
+    .await
+}
+
+async fn roles_of(app: &TestApp, email: &str) -> Vec<String> {
+    app.get_user_roles(email)
+        .await
+        .json::<UserRolesResponse>()
+        .await
+        .expect("Could not deserialize response body to UserRolesResponse")
+        .roles
+}
+
+async fn error_message(response: reqwest::Response) -> String {
+    response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .error
+}
+
+#[tokio::test]
+async fn should_create_list_and_revoke_invitations() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let random_email = get_random_email();
+    let (invitation, _) = invite(&app, &random_email, Some("admin")).await;
+    assert_eq!(invitation.email, random_email);
+    assert_eq!(invitation.role, Some("admin".to_owned()));
+    assert_eq!(invitation.invited_by, "admin-token");
+    assert_eq!(invitation.status, InvitationStatus::Pending);
+
+    let (other, _) = invite(&app, &get_random_email(), None).await;
+
+    let response = app.get_admin("/invitations", ADMIN).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let invitations = response
+        .json::<ListInvitationsResponse>()
+        .await
+        .expect("Could not deserialize response body to ListInvitationsResponse")
+        .invitations;
+    let ids: Vec<_> = invitations
+        .iter()
+        .map(|invitation| invitation.id.as_str())
+        .collect();
+    assert_eq!(ids, vec![other.id.as_str(), invitation.id.as_str()]);
+
+    let response = app
+        .delete_admin(&format!("/invitations/{}", invitation.id), ADMIN)
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let invitations = app
+        .get_admin("/invitations", ADMIN)
+        .await
+        .json::<ListInvitationsResponse>()
+        .await
+        .expect("Could not deserialize response body to ListInvitationsResponse")
+        .invitations;
+    assert_eq!(invitations[1].status, InvitationStatus::Revoked);
+
+    // Only pending invitations can be revoked
+    let response = app
+        .delete_admin(&format!("/invitations/{}", invitation.id), ADMIN)
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+    assert_eq!(error_message(response).await, "Invitation not found");
+
+    let response = app.delete_admin("/invitations/not-an-id", ADMIN).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_invitations_with_unknown_roles_or_invalid_emails() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_admin_with_body(
+            "/invitations",
+            ADMIN,
+            &serde_json::json!({ "email": get_random_email(), "role": "no-such-role" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+    assert_eq!(error_message(response).await, "Role not found");
+
+    let response = app
+        .post_admin_with_body(
+            "/invitations",
+            ADMIN,
+            &serde_json::json!({ "email": "not_an_email" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_callers_without_admin_access() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_admin_with_body(
+            "/invitations",
+            None,
+            &serde_json::json!({ "email": get_random_email() }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = app.get_admin("/invitations", None).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_role_management_to_preassign_roles() {
+    let mut app = TestApp::new().await;
+    mount_email_server(&app).await;
+
+    let admin_email = get_random_email();
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": admin_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.assign_role(&admin_email, "admin").await;
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": admin_email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // A key that can manage users but not roles
+    let response = app
+        .post_api_key(&serde_json::json!({ "name": "ci", "scopes": ["users:manage"] }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    let key = response
+        .json::<CreateApiKeyResponse>()
+        .await
+        .expect("Could not deserialize response body to CreateApiKeyResponse")
+        .key;
+
+    let response = app
+        .post_admin_with_body(
+            "/invitations",
+            Some(&key),
+            &serde_json::json!({ "email": get_random_email(), "role": "admin" }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = app
+        .post_admin_with_body(
+            "/invitations",
+            Some(&key),
+            &serde_json::json!({ "email": get_random_email() }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_create_account_with_role_when_accepted() {
+    let mut app = TestApp::new().await;
+    mount_email_server(&app).await;
+
+    let random_email = get_random_email();
+    let (_, token) = invite(&app, &random_email, Some("admin")).await;
+
+    // A new account needs a valid password, and a rejected one doesn't use up the invitation
+    let response = app
+        .post_accept_invitation(&serde_json::json!({ "token": token, "password": "short" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    let response = app
+        .post_accept_invitation(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = accept(&app, &token).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The invited role makes the new user an admin
+    assert_eq!(
+        roles_of(&app, &random_email).await,
+        vec!["admin".to_owned()]
+    );
+    let response = app.get_admin("/invitations", None).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let invitations = response
+        .json::<ListInvitationsResponse>()
+        .await
+        .expect("Could not deserialize response body to ListInvitationsResponse")
+        .invitations;
+    assert_eq!(invitations[0].status, InvitationStatus::Accepted);
+
+    // The token can't be used again
+    let response = accept(&app, &token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_attach_existing_account_when_accepted() {
+    let mut app = TestApp::new().await;
+    mount_email_server(&app).await;
+
+    let random_email = get_random_email();
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let (_, token) = invite(&app, &random_email, Some("admin")).await;
+
+    // No password is needed, and the existing one is kept
+    let response = app
+        .post_accept_invitation(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(
+        roles_of(&app, &random_email).await,
+        vec!["admin".to_owned()]
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_revoked_expired_and_invalid_invitations() {
+    let mut app = TestApp::new().await;
+    mount_email_server(&app).await;
+
+    let (invitation, token) = invite(&app, &get_random_email(), None).await;
+    let response = app
+        .delete_admin(&format!("/invitations/{}", invitation.id), ADMIN)
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+    let response = accept(&app, &token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Stored straight away so the invitation has already expired
+    let email = Email::parse(Secret::new(get_random_email())).unwrap();
+    let expired = Invitation::new(
+        TenantId::default(),
+        email,
+        None,
+        "admin-token".to_owned(),
+        chrono::Duration::seconds(-1),
+    );
+    let token = generate_invitation_token(&expired).unwrap();
+    app.invitation_store
+        .write()
+        .await
+        .add_invitation(expired)
+        .await
+        .unwrap();
+    let response = accept(&app, token.expose_secret()).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = accept(&app, "not-a-token").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
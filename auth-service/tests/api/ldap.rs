@@ -0,0 +1,480 @@
+use std::{sync::Arc, time::Duration};
+
+use secrecy::Secret;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::RwLock,
+    task::JoinHandle,
+};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::{
+    domain::{AccountStatus, Email, TenantId},
+    services::data_stores::{
+        composite_user_store::{CompositeUserStore, SignupPolicy},
+        ldap_user_store::{LdapSettings, LdapUserStore},
+    },
+    ErrorResponse,
+};
+
+const BASE_DN: &str = "ou=people,dc=example,dc=com";
+const SERVICE_DN: &str = "cn=auth-service,dc=example,dc=com";
+const SERVICE_PASSWORD: &str = "service-password";
+
+// BER tags of the LDAP messages the stand-in handles (RFC 4511)
+const BIND_REQUEST: u8 = 0x60;
+const BIND_RESPONSE: u8 = 0x61;
+const UNBIND_REQUEST: u8 = 0x42;
+const SEARCH_REQUEST: u8 = 0x63;
+const SEARCH_RESULT_ENTRY: u8 = 0x64;
+const SEARCH_RESULT_DONE: u8 = 0x65;
+const AND_FILTER: u8 = 0xa0;
+const EQUALITY_FILTER: u8 = 0xa3;
+
+const SUCCESS: u8 = 0;
+const INVALID_CREDENTIALS: u8 = 49;
+const INSUFFICIENT_ACCESS_RIGHTS: u8 = 50;
+
+#[derive(Clone)]
+struct DirectoryEntry {
+    dn: String,
+    mail: String,
+    password: String,
+}
+
+// An in-process LDAP server that speaks just enough of the protocol for the user store:
+// simple binds, and searches by equality filters for the search account
+struct LdapStandIn {
+    url: String,
+    entries: Arc<RwLock<Vec<DirectoryEntry>>>,
+    server: JoinHandle<()>,
+}
+
+impl LdapStandIn {
+    async fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ldap://{}", listener.local_addr().unwrap());
+        let entries = Arc::new(RwLock::new(vec![]));
+
+        let served = entries.clone();
+        let server = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, served.clone()));
+            }
+        });
+
+        Self {
+            url,
+            entries,
+            server,
+        }
+    }
+
+    async fn add_user(&self, email: &str, password: &str) {
+        let uid = email.split('@').next().unwrap();
+        self.entries.write().await.push(DirectoryEntry {
+            dn: format!("uid={},{}", uid, BASE_DN),
+            mail: email.to_owned(),
+            password: password.to_owned(),
+        });
+    }
+
+    async fn remove_user(&self, email: &str) {
+        self.entries
+            .write()
+            .await
+            .retain(|entry| entry.mail != email);
+    }
+
+    // Stops accepting connections, as if the directory were down
+    fn stop(&self) {
+        self.server.abort();
+    }
+
+    fn user_store(&self) -> LdapUserStore {
+        LdapUserStore::new(LdapSettings {
+            url: self.url.clone(),
+            base_dn: BASE_DN.to_owned(),
+            user_filter: "(&(objectClass=person)(mail={email}))".to_owned(),
+            bind_dn: Some(SERVICE_DN.to_owned()),
+            bind_password: Secret::new(SERVICE_PASSWORD.to_owned()),
+            tenant: TenantId::default(),
+            timeout: Duration::from_secs(2),
+        })
+    }
+}
+
+// One BER element: its tag and contents
+struct Ber {
+    tag: u8,
+    content: Vec<u8>,
+}
+
+impl Ber {
+    fn children(&self) -> Vec<Ber> {
+        let mut bytes = self.content.as_slice();
+        let mut children = vec![];
+
+        while !bytes.is_empty() {
+            let tag = bytes[0];
+            let (length, header) = match bytes[1] {
+                length if length < 0x80 => (length as usize, 2),
+                long => {
+                    let count = (long & 0x7f) as usize;
+                    let length = bytes[2..2 + count]
+                        .iter()
+                        .fold(0, |length, byte| length << 8 | *byte as usize);
+                    (length, 2 + count)
+                }
+            };
+            children.push(Ber {
+                tag,
+                content: bytes[header..header + length].to_vec(),
+            });
+            bytes = &bytes[header + length..];
+        }
+
+        children
+    }
+
+    fn text(&self) -> String {
+        String::from_utf8_lossy(&self.content).into_owned()
+    }
+}
+
+fn encode(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut encoded = vec![tag];
+    match content.len() {
+        length if length < 0x80 => encoded.push(length as u8),
+        length if length <= 0xff => encoded.extend([0x81, length as u8]),
+        length => encoded.extend([0x82, (length >> 8) as u8, length as u8]),
+    }
+    encoded.extend_from_slice(content);
+    encoded
+}
+
+fn ldap_result(tag: u8, code: u8) -> Vec<u8> {
+    encode(
+        tag,
+        &[encode(0x0a, &[code]), encode(0x04, b""), encode(0x04, b"")].concat(),
+    )
+}
+
+async fn read_message(stream: &mut TcpStream) -> std::io::Result<Ber> {
+    let tag = stream.read_u8().await?;
+    let length = match stream.read_u8().await? {
+        length if length < 0x80 => length as usize,
+        long => {
+            let mut length = 0;
+            for _ in 0..(long & 0x7f) {
+                length = length << 8 | stream.read_u8().await? as usize;
+            }
+            length
+        }
+    };
+    let mut content = vec![0; length];
+    stream.read_exact(&mut content).await?;
+
+    Ok(Ber { tag, content })
+}
+
+async fn reply(stream: &mut TcpStream, message_id: &Ber, op: Vec<u8>) {
+    let message = encode(0x30, &[encode(0x02, &message_id.content), op].concat());
+    stream.write_all(&message).await.unwrap();
+}
+
+// The attribute/value pairs of equality filters, and of those within `&` filters
+fn equality_conditions(filter: &Ber, conditions: &mut Vec<(String, String)>) {
+    match filter.tag {
+        EQUALITY_FILTER => {
+            let pair = filter.children();
+            conditions.push((pair[0].text().to_lowercase(), pair[1].text()));
+        }
+        AND_FILTER => {
+            for child in filter.children() {
+                equality_conditions(&child, conditions);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn matches(entry: &DirectoryEntry, conditions: &[(String, String)]) -> bool {
+    conditions
+        .iter()
+        .all(|(attribute, value)| match attribute.as_str() {
+            "objectclass" => value.eq_ignore_ascii_case("person"),
+            "mail" => value.eq_ignore_ascii_case(&entry.mail),
+            _ => false,
+        })
+}
+
+async fn serve(mut stream: TcpStream, entries: Arc<RwLock<Vec<DirectoryEntry>>>) {
+    let mut bound_dn: Option<String> = None;
+
+    while let Ok(message) = read_message(&mut stream).await {
+        let parts = message.children();
+        let (message_id, op) = (&parts[0], &parts[1]);
+
+        match op.tag {
+            BIND_REQUEST => {
+                let fields = op.children();
+                let (dn, password) = (fields[1].text(), fields[2].text());
+                let valid = (dn == SERVICE_DN && password == SERVICE_PASSWORD)
+                    || entries
+                        .read()
+                        .await
+                        .iter()
+                        .any(|entry| entry.dn == dn && entry.password == password);
+
+                bound_dn = valid.then_some(dn);
+                let code = if valid { SUCCESS } else { INVALID_CREDENTIALS };
+                reply(&mut stream, message_id, ldap_result(BIND_RESPONSE, code)).await;
+            }
+            SEARCH_REQUEST => {
+                if bound_dn.as_deref() != Some(SERVICE_DN) {
+                    let done = ldap_result(SEARCH_RESULT_DONE, INSUFFICIENT_ACCESS_RIGHTS);
+                    reply(&mut stream, message_id, done).await;
+                    continue;
+                }
+
+                let fields = op.children();
+                let base = fields[0].text();
+                let mut conditions = vec![];
+                equality_conditions(&fields[6], &mut conditions);
+
+                let found: Vec<DirectoryEntry> = entries
+                    .read()
+                    .await
+                    .iter()
+                    .filter(|entry| entry.dn.ends_with(&base) && matches(entry, &conditions))
+                    .cloned()
+                    .collect();
+                for entry in found {
+                    let mail = encode(
+                        0x30,
+                        &[
+                            encode(0x04, b"mail"),
+                            encode(0x31, &encode(0x04, entry.mail.as_bytes())),
+                        ]
+                        .concat(),
+                    );
+                    let op = encode(
+                        SEARCH_RESULT_ENTRY,
+                        &[encode(0x04, entry.dn.as_bytes()), encode(0x30, &mail)].concat(),
+                    );
+                    reply(&mut stream, message_id, op).await;
+                }
+
+                reply(
+                    &mut stream,
+                    message_id,
+                    ldap_result(SEARCH_RESULT_DONE, SUCCESS),
+                )
+                .await;
+            }
+            UNBIND_REQUEST => return,
+            _ => return,
+        }
+    }
+}
+
+async fn app_with_directory(directory: &LdapStandIn, signups: SignupPolicy) -> TestApp {
+    let directory = directory.user_store();
+
+    TestApp::with_config(|mut app_state| {
+        let local = app_state.user_store.clone();
+        app_state.user_store = Arc::new(RwLock::new(
+            CompositeUserStore::new(directory, local).with_signup_policy(signups),
+        ));
+        app_state
+    })
+    .await
+}
+
+async fn login(app: &TestApp, email: &str, password: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "email": email,
+        "password": password,
+    }))
+    .await
+}
+
+async fn signup(app: &TestApp, email: &str) -> reqwest::Response {
+    app.post_signup(&serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    }))
+    .await
+}
+
+async fn error_message(response: reqwest::Response) -> String {
+    response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .error
+}
+
+#[tokio::test]
+async fn should_log_in_directory_user_with_directory_password() {
+    let directory = LdapStandIn::start().await;
+    let mut app = app_with_directory(&directory, SignupPolicy::Delegate).await;
+
+    let email = get_random_email();
+    directory.add_user(&email, "directory-password").await;
+
+    let response = login(&app, &email, "wrong-password").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = login(&app, &email, "directory-password").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The user got a local row, but their password stays in the directory
+    let user = app
+        .user_store
+        .read()
+        .await
+        .get_user(
+            &TenantId::default(),
+            &Email::parse(Secret::new(email.clone())).unwrap(),
+        )
+        .await
+        .expect("Directory user has no local row");
+    assert!(user.managed_by_directory);
+
+    let response = app
+        .post_password(&serde_json::json!({ "newPassword": "newpassword123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(
+        error_message(response).await,
+        "Account is managed by the directory"
+    );
+
+    let response = signup(&app, &email).await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    // Local state like the account status still applies
+    app.set_account_status(&email, AccountStatus::Suspended)
+        .await;
+    let response = login(&app, &email, "directory-password").await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_apply_signup_policy_to_people_outside_directory() {
+    let directory = LdapStandIn::start().await;
+
+    let mut app = app_with_directory(&directory, SignupPolicy::Delegate).await;
+    let email = get_random_email();
+    let response = signup(&app, &email).await;
+    assert_eq!(response.status().as_u16(), 201);
+    let response = login(&app, &email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+    app.clean_up().await;
+
+    let mut app = app_with_directory(&directory, SignupPolicy::Reject).await;
+    let response = signup(&app, &get_random_email()).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(error_message(response).await, "Sign-up is disabled");
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_lock_out_users_removed_from_directory() {
+    let directory = LdapStandIn::start().await;
+    let mut app = app_with_directory(&directory, SignupPolicy::Delegate).await;
+
+    let removed_email = get_random_email();
+    directory
+        .add_user(&removed_email, "directory-password")
+        .await;
+    let response = login(&app, &removed_email, "directory-password").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    directory.remove_user(&removed_email).await;
+    let response = login(&app, &removed_email, "directory-password").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // Its local row is hidden too, so no other way in works either
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    let response = app
+        .post_magic_link(&serde_json::json!({ "email": removed_email }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_keep_local_users_signing_in_while_directory_is_down() {
+    let directory = LdapStandIn::start().await;
+    let mut app = app_with_directory(&directory, SignupPolicy::Delegate).await;
+
+    let directory_email = get_random_email();
+    directory
+        .add_user(&directory_email, "directory-password")
+        .await;
+    let response = login(&app, &directory_email, "directory-password").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let local_email = get_random_email();
+    let response = signup(&app, &local_email).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    directory.stop();
+
+    let response = login(&app, &local_email, "password123").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = login(&app, &directory_email, "directory-password").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_look_up_directory_users_without_asking_the_directory_every_time() {
+    let directory = LdapStandIn::start().await;
+    let mut app = app_with_directory(&directory, SignupPolicy::Delegate).await;
+
+    let email = get_random_email();
+    directory.add_user(&email, "directory-password").await;
+    let response = login(&app, &email, "directory-password").await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    directory.stop();
+
+    // The login vouched for the user, so token checks don't need the directory for a while
+    let user = app
+        .user_store
+        .read()
+        .await
+        .get_user(
+            &TenantId::default(),
+            &Email::parse(Secret::new(email.clone())).unwrap(),
+        )
+        .await
+        .expect("Directory user was looked up live");
+    assert!(user.managed_by_directory);
+
+    // Passwords still are checked live
+    let response = login(&app, &email, "directory-password").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
@@ -1,7 +1,7 @@
 use crate::helpers::{get_random_email, TestApp};
 use auth_service::{
-    domain::{Email, LoginAttemptId, TwoFACodeStore},
-    routes::TwoFactorAuthResponse,
+    domain::{Email, LoginAttemptId},
+    routes::{TokenResponse, TwoFactorAuthResponse},
     utils::constants::JWT_COOKIE_NAME,
     ErrorResponse,
 };
@@ -86,16 +86,52 @@ async fn should_return_206_if_valid_credentials_and_2fa_enabled() {
 
     let two_fa_code_store = app.two_fa_code_store.read().await;
     let email = Email::parse(Secret::new(random_email)).unwrap();
-    match two_fa_code_store.get_code(&email).await {
-        Ok((login_attempt_id, _)) => {
-            assert_eq!(
-                login_attempt_id,
-                LoginAttemptId::parse(Secret::new(json_body.login_attempt_id)).unwrap()
-            );
-        }
-        Err(err) => eprintln!("Error getting 2FA code: {:?}", err),
-    }
+    let login_attempt_id = LoginAttemptId::parse(Secret::new(json_body.login_attempt_id)).unwrap();
+    let attempt = two_fa_code_store
+        .get_code(&login_attempt_id, &email)
+        .await
+        .expect("Login attempt was not stored");
+    assert_eq!(attempt.email, email);
+}
+
+#[tokio::test]
+async fn should_return_token_in_body_if_requested() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
 
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "tokenDelivery": "body",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .cookies()
+        .all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+
+    let token = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .token;
+
+    // The token works as a bearer token on authenticated routes
+    let response = app.get_trusted_devices_with_bearer(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
 }
 
 #[tokio::test]
@@ -124,6 +160,11 @@ async fn should_return_422_if_malformed_credentials() {
             "email": random_email,
             "password": 12,
         }),
+        serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+            "tokenDelivery": "header",
+        }),
     ];
 
     for test_case in test_cases.iter() {
@@ -208,3 +249,88 @@ async fn should_return_401_if_incorrect_credentials() {
 
     app.clean_up().await;
 }
+
+#[tokio::test]
+async fn should_force_2fa_after_burst_of_failed_logins() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let wrong_login_body = serde_json::json!({
+        "email": random_email,
+        "password": "wrong-password",
+    });
+
+    for _ in 0..5 {
+        let response = app.post_login(&wrong_login_body).await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    // The user never opted into 2FA, but the failure burst steps the login up
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_force_2fa_for_new_user_agent_alone() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .http_client
+        .post(format!("{}/login", &app.address))
+        .header(reqwest::header::USER_AGENT, "some-other-browser")
+        .json(&login_body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
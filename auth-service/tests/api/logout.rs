@@ -1,4 +1,4 @@
-use auth_service::{utils::constants::JWT_COOKIE_NAME, ErrorResponse};
+use auth_service::{routes::TokenResponse, utils::constants::JWT_COOKIE_NAME, ErrorResponse};
 use reqwest::Url;
 use secrecy::Secret;
 
@@ -59,6 +59,49 @@ async fn should_return_200_if_valid_jwt_cookie() {
     assert!(contains_token);
 }
 
+#[tokio::test]
+async fn should_return_200_and_ban_bearer_token() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "tokenDelivery": "body",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .token;
+
+    let response = app.post_logout_with_bearer(&token).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The banned token is rejected from then on
+    let response = app.post_logout_with_bearer(&token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.get_trusted_devices_with_bearer(&token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
 #[tokio::test]
 async fn should_return_400_if_logout_called_twice_in_a_row() {
     let mut app = TestApp::new().await;
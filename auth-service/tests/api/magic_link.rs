@@ -0,0 +1,341 @@
+use std::{collections::HashMap, sync::Arc};
+
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::{
+    domain::RateLimitPolicy, services::sliding_window_rate_limiter::SlidingWindowRateLimiter,
+    utils::constants::JWT_COOKIE_NAME, ErrorResponse,
+};
+use secrecy::ExposeSecret;
+use tokio::sync::RwLock;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+async fn signup(app: &TestApp, email: &str, requires_2fa: bool) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": requires_2fa
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+#[tokio::test]
+async fn should_return_200_and_send_email_if_user_exists() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_magic_link(&serde_json::json!({ "email": random_email }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_200_without_email_if_user_does_not_exist() {
+    let mut app = TestApp::new().await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_magic_link(&serde_json::json!({ "email": get_random_email() }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_rate_limit_links_per_address() {
+    let mut app = TestApp::with_config(|app_state| {
+        app_state.with_magic_link_rate_limiter(Arc::new(RwLock::new(
+            SlidingWindowRateLimiter::default().with_policy(RateLimitPolicy {
+                max_attempts: 2,
+                window: chrono::Duration::minutes(15),
+            }),
+        )))
+    })
+    .await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    for _ in 0..2 {
+        let response = app
+            .post_magic_link(&serde_json::json!({ "email": random_email }))
+            .await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    let response = app
+        .post_magic_link(&serde_json::json!({ "email": random_email }))
+        .await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    // Addresses without an account are limited the same way
+    let unknown_email = get_random_email();
+    for _ in 0..2 {
+        let response = app
+            .post_magic_link(&serde_json::json!({ "email": unknown_email }))
+            .await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+    let response = app
+        .post_magic_link(&serde_json::json!({ "email": unknown_email }))
+        .await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_email() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_magic_link(&serde_json::json!({ "email": "not_an_email" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+
+    let test_cases = [
+        serde_json::json!({}),
+        serde_json::json!({ "email": 12 }),
+        serde_json::json!({ "mail": get_random_email() }),
+    ];
+
+    for test_case in test_cases.iter() {
+        let response = app.post_magic_link(test_case).await;
+        assert_eq!(
+            response.status().as_u16(),
+            422,
+            "Failed for input: {:?}",
+            test_case
+        );
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_set_auth_cookie_when_link_is_consumed() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_magic_link(&serde_json::json!({ "email": random_email }))
+        .await;
+
+    let token = app.get_magic_link_token().await;
+
+    let response = app.post_consume_magic_link(&token).await;
+
+    assert_eq!(response.status().as_u16(), 303);
+    assert_eq!(response.headers().get("location").unwrap(), "/");
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    assert!(!auth_cookie.value().is_empty());
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_consume_link_when_it_is_opened() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_magic_link(&serde_json::json!({ "email": random_email }))
+        .await;
+
+    let token = app.get_magic_link_token().await;
+
+    // Scanners and previews that open the link only get the confirmation page
+    for _ in 0..2 {
+        let response = app.get_magic_link_confirmation(&token).await;
+        assert_eq!(response.status().as_u16(), 200);
+        assert!(response
+            .cookies()
+            .all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+
+        let page = response
+            .text()
+            .await
+            .expect("Could not read confirmation page");
+        assert!(page.contains(r#"method="post""#));
+        assert!(page.contains(&token));
+    }
+
+    let response = app.post_consume_magic_link(&token).await;
+    assert_eq!(response.status().as_u16(), 303);
+    assert!(response
+        .cookies()
+        .any(|cookie| cookie.name() == JWT_COOKIE_NAME));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_link_is_used_twice() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email, false).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_magic_link(&serde_json::json!({ "email": random_email }))
+        .await;
+
+    let token = app.get_magic_link_token().await;
+
+    let response = app.post_consume_magic_link(&token).await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    let response = app.post_consume_magic_link(&token).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Invalid token".to_owned()
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_redirect_to_2fa_form_if_user_requires_2fa() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email, true).await;
+
+    // One email for the link and one for the 2FA code
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    app.post_magic_link(&serde_json::json!({ "email": random_email }))
+        .await;
+
+    let token = app.get_magic_link_token().await;
+
+    let response = app.post_consume_magic_link(&token).await;
+
+    assert_eq!(response.status().as_u16(), 303);
+    assert!(response
+        .cookies()
+        .all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+
+    // The start page picks the login attempt up from the fragment
+    let location = response
+        .headers()
+        .get("location")
+        .unwrap()
+        .to_str()
+        .unwrap();
+    let fragment = location
+        .strip_prefix("/#")
+        .expect("Not sent to the 2FA form");
+    let params: HashMap<String, String> = url::form_urlencoded::parse(fragment.as_bytes())
+        .into_owned()
+        .collect();
+    assert_eq!(params["email"], random_email);
+
+    let login_attempt_id = &params["loginAttemptId"];
+    let two_fa_code = app.get_2fa_code(login_attempt_id, &random_email).await;
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": two_fa_code.as_ref().expose_secret(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .cookies()
+        .any(|cookie| cookie.name() == JWT_COOKIE_NAME));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_invalid_token() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_magic_link_confirmation("invalid_token").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_consume_magic_link("invalid_token").await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
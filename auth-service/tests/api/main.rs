@@ -1,7 +1,26 @@
+mod admin;
+mod api_keys;
+mod audit;
+mod device;
 mod helpers;
+mod invitations;
+mod ldap;
 mod login;
 mod logout;
+mod magic_link;
+mod oauth_clients;
+mod oidc;
+mod password;
+mod phone_number;
+mod reauthenticate;
+mod resend_2fa;
+mod roles;
 mod root;
+mod saml;
+mod scim;
 mod signup;
+mod tenants;
+mod trusted_devices;
 mod verify_2fa;
 mod verify_token;
+mod webhooks;
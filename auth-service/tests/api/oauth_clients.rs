@@ -0,0 +1,312 @@
+use std::sync::Arc;
+
+use crate::helpers::{get_random_email, TestApp, TEST_ADMIN_TOKEN};
+use auth_service::{
+    domain::RateLimitPolicy,
+    routes::{AccessTokenResponse, CreateOAuthClientResponse, OAuthClientResponse},
+    services::sliding_window_rate_limiter::SlidingWindowRateLimiter,
+    utils::auth::Claims,
+    ErrorResponse, OAuthErrorResponse,
+};
+use tokio::sync::RwLock;
+
+async fn register_client(app: &TestApp, scopes: &[&str]) -> CreateOAuthClientResponse {
+    let response = app
+        .post_admin_with_body(
+            "/clients",
+            Some(TEST_ADMIN_TOKEN),
+            &serde_json::json!({ "name": "app-service", "scopes": scopes }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    response
+        .json::<CreateOAuthClientResponse>()
+        .await
+        .expect("Could not deserialize response body to CreateOAuthClientResponse")
+}
+
+async fn request_token(app: &TestApp, client: &CreateOAuthClientResponse) -> reqwest::Response {
+    app.post_token(
+        &[("grant_type", "client_credentials")],
+        Some((&client.client.client_id, &client.client_secret)),
+    )
+    .await
+}
+
+async fn oauth_error(response: reqwest::Response) -> String {
+    response
+        .json::<OAuthErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to OAuthErrorResponse")
+        .error
+}
+
+#[tokio::test]
+async fn should_issue_token_to_registered_client() {
+    let mut app = TestApp::new().await;
+
+    let client = register_client(&app, &["users:manage"]).await;
+    assert!(client.client.client_id.starts_with("client_"));
+    assert_eq!(client.client.scopes, vec!["users:manage".to_owned()]);
+
+    // Listings never show the secret
+    let response = app.get_admin("/clients", Some(TEST_ADMIN_TOKEN)).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let body = response.text().await.unwrap();
+    assert!(!body.contains(&client.client_secret));
+    let clients: Vec<OAuthClientResponse> = serde_json::from_str(&body).unwrap();
+    assert_eq!(clients.len(), 1);
+    assert_eq!(clients[0].client_id, client.client.client_id);
+
+    let response = request_token(&app, &client).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert_eq!(response.headers().get("cache-control").unwrap(), "no-store");
+    let token = response
+        .json::<AccessTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to AccessTokenResponse");
+    assert_eq!(token.token_type, "Bearer");
+    assert_eq!(token.scope, "users:manage");
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token.access_token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let claims = response
+        .json::<Claims>()
+        .await
+        .expect("Could not deserialize response body to Claims");
+    assert_eq!(claims.sub, client.client.client_id);
+    assert_eq!(
+        claims.client_id.as_deref(),
+        Some(client.client.client_id.as_str())
+    );
+    assert_eq!(claims.permissions, vec!["users:manage".to_owned()]);
+
+    // The client may call the admin API with its scope, but is never taken for a user
+    let response = app.get_admin("/users", Some(&token.access_token)).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app
+        .get_trusted_devices_with_bearer(&token.access_token)
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_accept_credentials_in_body_and_narrow_scope() {
+    let mut app = TestApp::new().await;
+
+    let client = register_client(&app, &["users:manage", "roles:manage"]).await;
+
+    let response = app
+        .post_token(
+            &[
+                ("grant_type", "client_credentials"),
+                ("client_id", client.client.client_id.as_str()),
+                ("client_secret", client.client_secret.as_str()),
+                ("scope", "roles:manage"),
+            ],
+            None,
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = response
+        .json::<AccessTokenResponse>()
+        .await
+        .expect("Could not deserialize response body to AccessTokenResponse");
+    assert_eq!(token.scope, "roles:manage");
+
+    let response = app.get_admin("/users", Some(&token.access_token)).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    // Scopes the client wasn't registered with can't be asked for
+    let response = app
+        .post_token(
+            &[
+                ("grant_type", "client_credentials"),
+                ("scope", "users:manage app:admin"),
+            ],
+            Some((&client.client.client_id, &client.client_secret)),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_scope");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_invalid_client_credentials() {
+    let mut app = TestApp::new().await;
+
+    let client = register_client(&app, &[]).await;
+    let client_id = client.client.client_id.as_str();
+
+    let invalid_credentials = [
+        Some((client_id, "wrong-secret")),
+        Some(("client_unknown", client.client_secret.as_str())),
+        Some(("not a client id", "secret")),
+        None,
+    ];
+
+    for credentials in invalid_credentials.iter() {
+        let response = app
+            .post_token(&[("grant_type", "client_credentials")], *credentials)
+            .await;
+        assert_eq!(
+            response.status().as_u16(),
+            401,
+            "Failed for input: {:?}",
+            credentials
+        );
+        assert_eq!(response.headers().get("www-authenticate").unwrap(), "Basic");
+        assert_eq!(oauth_error(response).await, "invalid_client");
+    }
+
+    // Credentials may only be sent one way
+    let response = app
+        .post_token(
+            &[
+                ("grant_type", "client_credentials"),
+                ("client_id", client_id),
+            ],
+            Some((client_id, &client.client_secret)),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_request");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_missing_or_unsupported_grant_type() {
+    let mut app = TestApp::new().await;
+
+    let client = register_client(&app, &[]).await;
+    let credentials = Some((
+        client.client.client_id.as_str(),
+        client.client_secret.as_str(),
+    ));
+
+    let response = app
+        .post_token(&[("grant_type", "password")], credentials)
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "unsupported_grant_type");
+
+    let response = app
+        .post_token(&[("scope", "users:manage")], credentials)
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(oauth_error(response).await, "invalid_request");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_rate_limit_token_requests_per_client() {
+    let mut app = TestApp::with_config(|app_state| {
+        app_state.with_client_rate_limiter(Arc::new(RwLock::new(
+            SlidingWindowRateLimiter::default().with_policy(RateLimitPolicy {
+                max_attempts: 2,
+                window: chrono::Duration::minutes(1),
+            }),
+        )))
+    })
+    .await;
+
+    let client = register_client(&app, &[]).await;
+    let other_client = register_client(&app, &[]).await;
+
+    // Failed attempts count too
+    let response = app
+        .post_token(
+            &[("grant_type", "client_credentials")],
+            Some((&client.client.client_id, "wrong-secret")),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = request_token(&app, &client).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = request_token(&app, &client).await;
+    assert_eq!(response.status().as_u16(), 429);
+    assert_eq!(oauth_error(response).await, "too_many_requests");
+
+    let response = request_token(&app, &other_client).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_delete_client() {
+    let mut app = TestApp::new().await;
+
+    let client = register_client(&app, &[]).await;
+    let path = format!("/clients/{}", client.client.client_id);
+
+    let response = app.delete_admin(&path, Some(TEST_ADMIN_TOKEN)).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = request_token(&app, &client).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.delete_admin(&path, Some(TEST_ADMIN_TOKEN)).await;
+    assert_eq!(response.status().as_u16(), 404);
+    let error = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse")
+        .error;
+    assert_eq!(error, "OAuth client not found");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_let_admins_grant_scopes_they_lack() {
+    let mut app = TestApp::new().await;
+
+    let admin_email = get_random_email();
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": admin_email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    app.assign_role(&admin_email, "admin").await;
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": admin_email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_admin_with_body(
+            "/clients",
+            None,
+            &serde_json::json!({ "name": "billing", "scopes": ["billing:manage"] }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    let response = app
+        .post_admin_with_body(
+            "/clients",
+            None,
+            &serde_json::json!({ "name": "reports", "scopes": ["users:manage"] }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,116 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::{
+    domain::AuthMethod,
+    routes::TokenResponse,
+    utils::{auth::Claims, constants::JWT_COOKIE_NAME},
+};
+
+async fn signup_and_login(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+async fn login_for_token(app: &TestApp, email: &str) -> String {
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "tokenDelivery": "body",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .token
+}
+
+#[tokio::test]
+async fn should_log_out_other_sessions_when_password_changes() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+    let other_session = login_for_token(&app, &random_email).await;
+
+    let response = app
+        .post_password(&serde_json::json!({ "newPassword": "newpassword123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": other_session }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The session that made the change stays logged in, as recently as before
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": auth_cookie.value() }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app.get_trusted_devices().await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = app
+        .post_password(&serde_json::json!({ "newPassword": "newpassword456" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_new_token_in_body_if_asked() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    let response = app
+        .post_password(&serde_json::json!({
+            "newPassword": "newpassword123",
+            "tokenDelivery": "body",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .cookies()
+        .all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+    let token = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .token;
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let claims = response.json::<Claims>().await.unwrap();
+    assert_eq!(claims.amr, vec![AuthMethod::Password]);
+
+    // The cookie went out with the other sessions
+    let response = app.get_trusted_devices().await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
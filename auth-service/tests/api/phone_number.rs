@@ -0,0 +1,294 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::{routes::TwoFactorAuthResponse, ErrorResponse};
+use secrecy::ExposeSecret;
+use wiremock::{
+    matchers::{method, path, path_regex},
+    Mock, ResponseTemplate,
+};
+
+// Pulls the code out of the last message sent through the mock Twilio server
+async fn get_last_sms_code(app: &TestApp) -> String {
+    let requests = app
+        .sms_server
+        .received_requests()
+        .await
+        .expect("Request recording is disabled");
+
+    let body = String::from_utf8(requests.last().expect("No SMS was sent").body.clone())
+        .expect("SMS body is not UTF-8");
+
+    let digits: String = body
+        .chars()
+        .rev()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+
+    digits.chars().rev().collect()
+}
+
+async fn mount_sms_server(app: &TestApp, expected_messages: u64) {
+    Mock::given(path_regex(r"^/2010-04-01/Accounts/.+/Messages\.json$"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(201))
+        .expect(expected_messages)
+        .mount(&app.sms_server)
+        .await;
+}
+
+async fn signup_and_login(app: &TestApp, email: &str) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+#[tokio::test]
+async fn should_return_400_if_not_logged_in() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": "+14155552671" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Missing token".to_owned()
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_phone_number() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app, &get_random_email()).await;
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": "555-CALL-NOW" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_verify_phone_number_with_texted_code() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app, &get_random_email()).await;
+    mount_sms_server(&app, 1).await;
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": "+14155552671" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let code = get_last_sms_code(&app).await;
+
+    let response = app
+        .post_verify_phone_number(&serde_json::json!({ "code": code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_2fa_channel(&serde_json::json!({ "channel": "sms" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_429_if_phone_number_added_again_too_soon() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app, &get_random_email()).await;
+    mount_sms_server(&app, 1).await;
+
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": "+14155552671" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // Neither another number nor a wrong guess gets a new code texted right away
+    let code = get_last_sms_code(&app).await;
+    let wrong_code = if code == "123456" { "654321" } else { "123456" };
+    let response = app
+        .post_verify_phone_number(&serde_json::json!({ "code": wrong_code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    let response = app
+        .post_phone_number(&serde_json::json!({ "phoneNumber": "+14155552672" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_wrong_verification_code() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app, &get_random_email()).await;
+    mount_sms_server(&app, 1).await;
+
+    app.post_phone_number(&serde_json::json!({ "phoneNumber": "+14155552671" }))
+        .await;
+
+    let code = get_last_sms_code(&app).await;
+    let wrong_code = if code == "123456" { "654321" } else { "123456" };
+
+    let response = app
+        .post_verify_phone_number(&serde_json::json!({ "code": wrong_code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The pending verification is discarded after a wrong guess
+    let response = app
+        .post_verify_phone_number(&serde_json::json!({ "code": code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_sms_channel_without_verified_phone_number() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app, &get_random_email()).await;
+
+    let response = app
+        .post_2fa_channel(&serde_json::json!({ "channel": "sms" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Phone number not verified".to_owned()
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_422_if_unknown_channel() {
+    let mut app = TestApp::new().await;
+
+    signup_and_login(&app, &get_random_email()).await;
+
+    let response = app
+        .post_2fa_channel(&serde_json::json!({ "channel": "pigeon" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 422);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_send_2fa_code_by_sms_if_channel_is_sms() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    // Only the first login goes out by email
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // One message to verify the number and one for the second login
+    mount_sms_server(&app, 2).await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let two_fa_code = app.get_2fa_code(&login_attempt_id, &random_email).await;
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": two_fa_code.as_ref().expose_secret(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.post_phone_number(&serde_json::json!({ "phoneNumber": "+14155552671" }))
+        .await;
+    let code = get_last_sms_code(&app).await;
+    let response = app
+        .post_verify_phone_number(&serde_json::json!({ "code": code }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app
+        .post_2fa_channel(&serde_json::json!({ "channel": "sms" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let json_body = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse");
+
+    let code = get_last_sms_code(&app).await;
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": json_body.login_attempt_id,
+            "2FACode": code,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,266 @@
+use std::time::Duration;
+
+use crate::helpers::{get_random_email, TestApp, TEST_ADMIN_TOKEN};
+use auth_service::{
+    domain::AuthMethod,
+    routes::TwoFactorAuthResponse,
+    utils::{
+        auth::{validate_token, Claims},
+        constants::JWT_COOKIE_NAME,
+    },
+    ErrorResponse,
+};
+use secrecy::{ExposeSecret, Secret};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+async fn signup_and_login(app: &TestApp, email: &str) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+async fn get_auth_claims(app: &TestApp, response: &reqwest::Response) -> Claims {
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+
+    validate_token(
+        &Secret::new(auth_cookie.value().to_owned()),
+        app.banned_token_store.clone(),
+        app.user_store.clone(),
+    )
+    .await
+    .expect("Auth cookie is not a valid token")
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_reauthenticate(&serde_json::json!({ "password": "password123" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    let response = app
+        .post_reauthenticate(&serde_json::json!({ "loginAttemptId": "abc" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 422);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_incorrect_password() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    let response = app
+        .post_reauthenticate(&serde_json::json!({ "password": "wrongpassword" }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_reauthentication_once_auth_is_stale() {
+    let mut app = TestApp::with_config(|app_state| app_state.with_max_auth_age_seconds(1)).await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    let response = app
+        .post_2fa_channel(&serde_json::json!({ "channel": "email" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Reauthentication required".to_owned()
+    );
+
+    let response = app
+        .post_reauthenticate(&serde_json::json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let claims = get_auth_claims(&app, &response).await;
+    assert_eq!(claims.amr, vec![AuthMethod::Password]);
+
+    let response = app
+        .post_2fa_channel(&serde_json::json!({ "channel": "email" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_reject_password_while_reset_is_required() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_and_login(&app, &random_email).await;
+
+    let response = app
+        .post_admin(
+            &format!("/users/{}/reset-password", random_email),
+            Some(TEST_ADMIN_TOKEN),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    // Tokens issued in the second of the reset are revoked with it
+    tokio::time::sleep(Duration::from_secs(1)).await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    app.post_magic_link(&serde_json::json!({ "email": random_email }))
+        .await;
+    let token = app.get_magic_link_token().await;
+    let response = app.post_consume_magic_link(&token).await;
+    assert_eq!(response.status().as_u16(), 303);
+
+    let response = app
+        .post_reauthenticate(&serde_json::json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Password reset required".to_owned()
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_2fa_code_for_2fa_users() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": random_email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let two_fa_code = app.get_2fa_code(&login_attempt_id, &random_email).await;
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": two_fa_code.as_ref().expose_secret(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The password alone only starts a new 2FA challenge
+    let response = app
+        .post_reauthenticate(&serde_json::json!({ "password": "password123" }))
+        .await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let two_fa_code = app.get_2fa_code(&login_attempt_id, &random_email).await;
+
+    let response = app
+        .post_reauthenticate(&serde_json::json!({
+            "loginAttemptId": login_attempt_id,
+            "2FACode": "123456",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .post_reauthenticate(&serde_json::json!({
+            "loginAttemptId": login_attempt_id,
+            "2FACode": two_fa_code.as_ref().expose_secret(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let claims = get_auth_claims(&app, &response).await;
+    assert_eq!(
+        claims.amr,
+        vec![
+            AuthMethod::Password,
+            AuthMethod::OneTimeCode,
+            AuthMethod::MultiFactor
+        ]
+    );
+
+    app.clean_up().await;
+}
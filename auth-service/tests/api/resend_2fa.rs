@@ -0,0 +1,214 @@
+use std::time::Duration;
+
+use crate::helpers::{get_random_email, TestApp, TEST_RESEND_POLICY};
+use auth_service::{routes::TwoFactorAuthResponse, ErrorResponse};
+use secrecy::ExposeSecret;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+async fn start_2fa_login(app: &TestApp, email: &str, expected_emails: u64) -> String {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(expected_emails)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id
+}
+
+async fn wait_for_cooldown() {
+    let cooldown = TEST_RESEND_POLICY.cooldown_seconds as u64;
+    tokio::time::sleep(Duration::from_millis(cooldown * 1000 + 100)).await;
+}
+
+#[tokio::test]
+async fn should_return_200_and_rotate_code_after_cooldown() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let login_attempt_id = start_2fa_login(&app, &random_email, 2).await;
+
+    let first_code = app.get_2fa_code(&login_attempt_id, &random_email).await;
+
+    wait_for_cooldown().await;
+
+    let response = app
+        .post_resend_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    // The login attempt carries on with the new code
+    let second_code = app.get_2fa_code(&login_attempt_id, &random_email).await;
+
+    if first_code != second_code {
+        let response = app
+            .post_verify_2fa(&serde_json::json!({
+                "email": random_email,
+                "loginAttemptId": login_attempt_id,
+                "2FACode": first_code.as_ref().expose_secret(),
+            }))
+            .await;
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": second_code.as_ref().expose_secret(),
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_429_during_cooldown() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let login_attempt_id = start_2fa_login(&app, &random_email, 1).await;
+
+    let response = app
+        .post_resend_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 429);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Too many requests".to_owned()
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_429_after_max_resends() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let max_resends = TEST_RESEND_POLICY.max_resends as u64;
+    let login_attempt_id = start_2fa_login(&app, &random_email, max_resends + 1).await;
+
+    let body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": login_attempt_id,
+    });
+
+    for _ in 0..max_resends {
+        wait_for_cooldown().await;
+        let response = app.post_resend_2fa(&body).await;
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    wait_for_cooldown().await;
+    let response = app.post_resend_2fa(&body).await;
+    assert_eq!(response.status().as_u16(), 429);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_login_attempt_id_does_not_match() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    start_2fa_login(&app, &random_email, 1).await;
+
+    let response = app
+        .post_resend_2fa(&serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": "a1a2a3a4-b1b2-c1c2-d1d2-d3d4d5d6d7d8",
+        }))
+        .await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_invalid_input() {
+    let mut app = TestApp::new().await;
+
+    let test_cases = [
+        serde_json::json!({
+            "email": "random_email",
+            "loginAttemptId": "a1a2a3a4-b1b2-c1c2-d1d2-d3d4d5d6d7d8",
+        }),
+        serde_json::json!({
+            "email": get_random_email(),
+            "loginAttemptId": "not_a_uuid",
+        }),
+    ];
+
+    for test_case in test_cases.iter() {
+        let response = app.post_resend_2fa(test_case).await;
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for input: {:?}",
+            test_case
+        );
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_422_if_malformed_input() {
+    let mut app = TestApp::new().await;
+
+    let test_cases = [
+        serde_json::json!({ "email": get_random_email() }),
+        serde_json::json!({ "loginAttemptId": "a1a2a3a4-b1b2-c1c2-d1d2-d3d4d5d6d7d8" }),
+        serde_json::json!({ "email": 12, "loginAttemptId": true }),
+    ];
+
+    for test_case in test_cases.iter() {
+        let response = app.post_resend_2fa(test_case).await;
+        assert_eq!(
+            response.status().as_u16(),
+            422,
+            "Failed for input: {:?}",
+            test_case
+        );
+    }
+
+    app.clean_up().await;
+}
@@ -0,0 +1,195 @@
+use auth_service::{
+    routes::UserRolesResponse,
+    utils::{auth::Claims, constants::JWT_COOKIE_NAME},
+    ErrorResponse,
+};
+
+use crate::helpers::{get_random_email, TestApp};
+
+async fn signup(app: &TestApp, email: &str) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+// Logs in and returns the token from the auth cookie
+async fn login(app: &TestApp, email: &str) -> String {
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let auth_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found");
+    auth_cookie.value().to_owned()
+}
+
+async fn signup_admin(app: &TestApp) -> String {
+    let admin_email = get_random_email();
+    signup(app, &admin_email).await;
+    app.assign_role(&admin_email, "admin").await;
+    login(app, &admin_email).await;
+    admin_email
+}
+
+#[tokio::test]
+async fn should_return_400_if_jwt_cookie_missing() {
+    let mut app = TestApp::new().await;
+
+    let response = app.put_user_role(&get_random_email(), "admin").await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_403_without_manage_roles_permission() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+    login(&app, &random_email).await;
+
+    // Users can't promote themselves
+    let response = app.put_user_role(&random_email, "admin").await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Missing permission".to_owned()
+    );
+
+    let response = app.get_user_roles(&random_email).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_embed_assigned_roles_in_tokens() {
+    let mut app = TestApp::new().await;
+
+    signup_admin(&app).await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    let response = app.put_user_role(&random_email, "admin").await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.get_user_roles(&random_email).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let grants = response
+        .json::<UserRolesResponse>()
+        .await
+        .expect("Could not deserialize response body to UserRolesResponse");
+    assert_eq!(grants.roles, vec!["admin".to_owned()]);
+    assert!(grants.permissions.contains(&"app:admin".to_owned()));
+    assert!(grants.permissions.contains(&"roles:manage".to_owned()));
+
+    let token = login(&app, &random_email).await;
+
+    let response = app
+        .post_verify_token(&serde_json::json!({
+            "token": token,
+            "requiredPermission": "roles:manage",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let claims = response
+        .json::<Claims>()
+        .await
+        .expect("Could not deserialize response body to Claims");
+    assert_eq!(claims.roles, vec!["admin".to_owned()]);
+    assert!(claims.has_permission("app:admin"));
+    assert!(claims.has_permission("roles:manage"));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_unassign_roles() {
+    let mut app = TestApp::new().await;
+
+    let admin_email = signup_admin(&app).await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+
+    let response = app.put_user_role(&random_email, "admin").await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.delete_user_role(&random_email, "admin").await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.delete_user_role(&random_email, "admin").await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    // Already issued tokens keep their grants until they expire
+    let response = app.delete_user_role(&admin_email, "admin").await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.get_user_roles(&admin_email).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = login(&app, &admin_email).await;
+
+    let response = app
+        .post_verify_token(&serde_json::json!({
+            "token": token,
+            "requiredPermission": "roles:manage",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_404_for_unknown_users_and_roles() {
+    let mut app = TestApp::new().await;
+
+    let admin_email = signup_admin(&app).await;
+
+    let test_cases = [
+        (get_random_email(), "admin", "User not found"),
+        ("not-an-email".to_owned(), "admin", "User not found"),
+        (admin_email.clone(), "superuser", "Role not found"),
+        (admin_email.clone(), "Not A Role", "Role not found"),
+    ];
+
+    for (email, role, error) in test_cases {
+        let response = app.put_user_role(&email, role).await;
+        assert_eq!(
+            response.status().as_u16(),
+            404,
+            "Failed for {} {}",
+            email,
+            role
+        );
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            error.to_owned()
+        );
+    }
+
+    app.clean_up().await;
+}
@@ -0,0 +1,498 @@
+use crate::helpers::{get_random_email, TestApp, TEST_ADMIN_TOKEN};
+use auth_service::{
+    routes::{ScimGroupResponse, ScimListResponse, ScimUserResponse, TokenResponse},
+    ErrorResponse, ScimErrorResponse,
+};
+use reqwest::Method;
+use serde_json::json;
+
+const ADMIN: Option<&str> = Some(TEST_ADMIN_TOKEN);
+const USER_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:User";
+const GROUP_SCHEMA: &str = "urn:ietf:params:scim:schemas:core:2.0:Group";
+const PATCH_OP_SCHEMA: &str = "urn:ietf:params:scim:api:messages:2.0:PatchOp";
+
+async fn signup(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+}
+
+async fn login(app: &TestApp, email: &str) -> reqwest::Response {
+    app.post_login(&json!({
+        "email": email,
+        "password": "password123",
+        "tokenDelivery": "body",
+    }))
+    .await
+}
+
+async fn login_for_token(app: &TestApp, email: &str) -> String {
+    let response = login(app, email).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .token
+}
+
+async fn create_user(app: &TestApp, email: &str) -> ScimUserResponse {
+    let response = app
+        .send_scim(
+            Method::POST,
+            "/Users",
+            ADMIN,
+            Some(&json!({ "schemas": [USER_SCHEMA], "userName": email, "active": true })),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    response
+        .json::<ScimUserResponse>()
+        .await
+        .expect("Could not deserialize response body to ScimUserResponse")
+}
+
+async fn patch_user(
+    app: &TestApp,
+    email: &str,
+    operations: serde_json::Value,
+) -> reqwest::Response {
+    app.send_scim(
+        Method::PATCH,
+        &format!("/Users/{}", email),
+        ADMIN,
+        Some(&json!({ "schemas": [PATCH_OP_SCHEMA], "Operations": operations })),
+    )
+    .await
+}
+
+async fn list<T: serde::de::DeserializeOwned>(app: &TestApp, path: &str) -> ScimListResponse<T> {
+    let response = app.send_scim(Method::GET, path, ADMIN, None).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<ScimListResponse<T>>()
+        .await
+        .expect("Could not deserialize response body to ScimListResponse")
+}
+
+async fn scim_error(response: reqwest::Response) -> ScimErrorResponse {
+    response
+        .json::<ScimErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ScimErrorResponse")
+}
+
+#[tokio::test]
+async fn should_require_a_bearer_token_with_user_management() {
+    let mut app = TestApp::new().await;
+
+    let response = app.send_scim(Method::GET, "/Users", None, None).await;
+    assert_eq!(response.status().as_u16(), 401);
+    assert_eq!(response.headers()["www-authenticate"], "Bearer");
+    assert_eq!(response.headers()["content-type"], "application/scim+json");
+    let error = scim_error(response).await;
+    assert_eq!(
+        error.schemas,
+        vec!["urn:ietf:params:scim:api:messages:2.0:Error"]
+    );
+    assert_eq!(error.status, "401");
+
+    let response = app
+        .send_scim(Method::GET, "/Users", Some("not-the-admin-token"), None)
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+    let token = login_for_token(&app, &random_email).await;
+    let response = app
+        .send_scim(Method::GET, "/Users", Some(&token), None)
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_provision_and_filter_users() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let response = app
+        .send_scim(
+            Method::POST,
+            "/Users",
+            ADMIN,
+            Some(&json!({ "schemas": [USER_SCHEMA], "userName": random_email })),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    assert_eq!(response.headers()["content-type"], "application/scim+json");
+    let user = response
+        .json::<ScimUserResponse>()
+        .await
+        .expect("Could not deserialize response body to ScimUserResponse");
+    assert_eq!(user.id, random_email);
+    assert_eq!(user.user_name, random_email);
+    assert!(user.active);
+    assert_eq!(user.meta.resource_type, "User");
+
+    // Provisioned users have no password until they choose one
+    let response = login(&app, &random_email).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app
+        .send_scim(
+            Method::POST,
+            "/Users",
+            ADMIN,
+            Some(&json!({ "schemas": [USER_SCHEMA], "userName": random_email })),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 409);
+    assert_eq!(
+        scim_error(response).await.scim_type.as_deref(),
+        Some("uniqueness")
+    );
+
+    let users: ScimListResponse<ScimUserResponse> = list(
+        &app,
+        &format!("/Users?filter=userName%20eq%20%22{}%22", random_email),
+    )
+    .await;
+    assert_eq!(users.total_results, 1);
+    assert_eq!(users.resources[0].user_name, random_email);
+
+    let users: ScimListResponse<ScimUserResponse> = list(
+        &app,
+        "/Users?filter=userName%20eq%20%22nobody@example.com%22",
+    )
+    .await;
+    assert_eq!(users.total_results, 0);
+    assert!(users.resources.is_empty());
+
+    let response = app
+        .send_scim(
+            Method::GET,
+            "/Users?filter=name.familyName%20co%20%22x%22",
+            ADMIN,
+            None,
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        scim_error(response).await.scim_type.as_deref(),
+        Some("invalidFilter")
+    );
+
+    let response = app
+        .send_scim(Method::GET, "/Users/nobody@example.com", ADMIN, None)
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_deprovision_user_and_revoke_sessions() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+    let token = login_for_token(&app, &random_email).await;
+
+    // Some clients capitalize the operation and send booleans as strings
+    let response = patch_user(
+        &app,
+        &random_email,
+        json!([{ "op": "Replace", "value": { "active": "False" } }]),
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let user = response
+        .json::<ScimUserResponse>()
+        .await
+        .expect("Could not deserialize response body to ScimUserResponse");
+    assert!(!user.active);
+
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = login(&app, &random_email).await;
+    assert_eq!(response.status().as_u16(), 403);
+    let error = response
+        .json::<ErrorResponse>()
+        .await
+        .expect("Could not deserialize response body to ErrorResponse");
+    assert_eq!(error.error, "Account suspended");
+
+    let response = patch_user(
+        &app,
+        &random_email,
+        json!([{ "op": "replace", "path": "active", "value": true }]),
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 200);
+    login_for_token(&app, &random_email).await;
+
+    let response = patch_user(
+        &app,
+        &random_email,
+        json!([{ "op": "remove", "path": "active" }]),
+    )
+    .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        scim_error(response).await.scim_type.as_deref(),
+        Some("mutability")
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_replace_user_but_not_their_user_name() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    create_user(&app, &random_email).await;
+    let path = format!("/Users/{}", random_email);
+
+    let response = app
+        .send_scim(
+            Method::PUT,
+            &path,
+            ADMIN,
+            Some(&json!({ "schemas": [USER_SCHEMA], "userName": get_random_email() })),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        scim_error(response).await.scim_type.as_deref(),
+        Some("mutability")
+    );
+
+    let response = app
+        .send_scim(
+            Method::PUT,
+            &path,
+            ADMIN,
+            Some(&json!({
+                "schemas": [USER_SCHEMA],
+                "userName": random_email,
+                "password": "password123",
+                "active": true,
+            })),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    login_for_token(&app, &random_email).await;
+
+    let response = app
+        .send_scim(
+            Method::PUT,
+            &path,
+            ADMIN,
+            Some(&json!({ "schemas": [USER_SCHEMA], "userName": random_email, "active": false })),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let response = login(&app, &random_email).await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_reactivate_users_suspended_by_an_admin() {
+    let mut app = TestApp::new().await;
+
+    // Even with the reason SCIM gives its own suspensions
+    for reason in ["Spam", "Deprovisioned by the provisioning system"] {
+        let random_email = get_random_email();
+        signup(&app, &random_email).await;
+        let response = app
+            .put_admin(
+                &format!("/users/{}/status", random_email),
+                ADMIN,
+                &json!({ "status": "suspended", "reason": reason }),
+            )
+            .await;
+        assert_eq!(response.status().as_u16(), 204);
+
+        let path = format!("/Users/{}", random_email);
+        for body in [
+            json!({ "schemas": [USER_SCHEMA], "userName": random_email }),
+            json!({ "schemas": [USER_SCHEMA], "userName": random_email, "active": true }),
+        ] {
+            let response = app.send_scim(Method::PUT, &path, ADMIN, Some(&body)).await;
+            assert_eq!(response.status().as_u16(), 200);
+            let user = response
+                .json::<ScimUserResponse>()
+                .await
+                .expect("Could not deserialize response body to ScimUserResponse");
+            assert!(!user.active);
+        }
+
+        let response = patch_user(
+            &app,
+            &random_email,
+            json!([{ "op": "replace", "path": "active", "value": true }]),
+        )
+        .await;
+        assert_eq!(response.status().as_u16(), 200);
+
+        let response = login(&app, &random_email).await;
+        assert_eq!(response.status().as_u16(), 403);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_delete_user_and_revoke_sessions() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup(&app, &random_email).await;
+    let token = login_for_token(&app, &random_email).await;
+    let path = format!("/Users/{}", random_email);
+
+    let response = app.send_scim(Method::DELETE, &path, ADMIN, None).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.post_verify_token(&json!({ "token": token })).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.send_scim(Method::GET, &path, ADMIN, None).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    let response = app.send_scim(Method::DELETE, &path, ADMIN, None).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    // The address can be provisioned again
+    create_user(&app, &random_email).await;
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_manage_groups_and_their_members() {
+    let mut app = TestApp::new().await;
+
+    let alice = get_random_email();
+    let bob = get_random_email();
+    create_user(&app, &alice).await;
+    create_user(&app, &bob).await;
+
+    let response = app
+        .send_scim(
+            Method::POST,
+            "/Groups",
+            ADMIN,
+            Some(&json!({
+                "schemas": [GROUP_SCHEMA],
+                "displayName": "Engineering",
+                "members": [{ "value": "nobody@example.com" }],
+            })),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    assert_eq!(
+        scim_error(response).await.scim_type.as_deref(),
+        Some("invalidValue")
+    );
+
+    let response = app
+        .send_scim(
+            Method::POST,
+            "/Groups",
+            ADMIN,
+            Some(&json!({
+                "schemas": [GROUP_SCHEMA],
+                "displayName": "Engineering",
+                "members": [{ "value": alice }],
+            })),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+    let group = response
+        .json::<ScimGroupResponse>()
+        .await
+        .expect("Could not deserialize response body to ScimGroupResponse");
+    assert_eq!(group.display_name, "Engineering");
+    assert_eq!(group.members.len(), 1);
+    assert_eq!(group.members[0].value, alice);
+    let path = format!("/Groups/{}", group.id);
+
+    let response = app
+        .send_scim(
+            Method::POST,
+            "/Groups",
+            ADMIN,
+            Some(&json!({ "schemas": [GROUP_SCHEMA], "displayName": "Engineering" })),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    let groups: ScimListResponse<ScimGroupResponse> =
+        list(&app, "/Groups?filter=displayName%20eq%20%22Engineering%22").await;
+    assert_eq!(groups.total_results, 1);
+    assert_eq!(groups.resources[0].id, group.id);
+
+    let response = app
+        .send_scim(
+            Method::PATCH,
+            &path,
+            ADMIN,
+            Some(&json!({
+                "schemas": [PATCH_OP_SCHEMA],
+                "Operations": [
+                    { "op": "add", "path": "members", "value": [{ "value": bob }] },
+                    { "op": "remove", "path": format!("members[value eq \"{}\"]", alice) },
+                    { "op": "replace", "path": "displayName", "value": "Platform" },
+                ],
+            })),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+    let group = response
+        .json::<ScimGroupResponse>()
+        .await
+        .expect("Could not deserialize response body to ScimGroupResponse");
+    assert_eq!(group.display_name, "Platform");
+    let members: Vec<String> = group
+        .members
+        .into_iter()
+        .map(|member| member.value)
+        .collect();
+    assert_eq!(members, vec![bob.clone()]);
+
+    // Deleted users leave their groups
+    let response = app
+        .send_scim(Method::DELETE, &format!("/Users/{}", bob), ADMIN, None)
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+    let response = app.send_scim(Method::GET, &path, ADMIN, None).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let group = response
+        .json::<ScimGroupResponse>()
+        .await
+        .expect("Could not deserialize response body to ScimGroupResponse");
+    assert!(group.members.is_empty());
+
+    let response = app.send_scim(Method::DELETE, &path, ADMIN, None).await;
+    assert_eq!(response.status().as_u16(), 204);
+    let response = app.send_scim(Method::GET, &path, ADMIN, None).await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.clean_up().await;
+}
@@ -0,0 +1,215 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::{
+    domain::{Tenant, TenantId, TenantSettings},
+    routes::TokenResponse,
+    utils::auth::Claims,
+    ErrorResponse,
+};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+fn tenant(id: &str) -> Tenant {
+    Tenant::new(TenantId::parse(id.to_owned()).unwrap())
+}
+
+#[tokio::test]
+async fn should_scope_users_to_their_tenant() {
+    let mut app = TestApp::new().await;
+    app.add_tenant(tenant("acme")).await;
+
+    let email = get_random_email();
+
+    // The same email signs up separately with each tenant
+    let default_signup = serde_json::json!({
+        "email": email,
+        "password": "default-password",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&default_signup).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let acme_signup = serde_json::json!({
+        "email": email,
+        "password": "acme-password",
+        "requires2FA": false
+    });
+    let response = app.post_with_tenant("/signup", "acme", &acme_signup).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app.post_with_tenant("/signup", "acme", &acme_signup).await;
+    assert_eq!(response.status().as_u16(), 409);
+
+    // Each account only knows its own password
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "default-password",
+        "tokenDelivery": "body"
+    });
+    let response = app.post_with_tenant("/login", "acme", &login_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "acme-password",
+        "tokenDelivery": "body"
+    });
+    let response = app.post_with_tenant("/login", "acme", &login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .token;
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let claims = response
+        .json::<Claims>()
+        .await
+        .expect("Could not deserialize response body to Claims");
+    assert_eq!(claims.sub, email);
+    assert_eq!(claims.tenant, "acme");
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_resolve_tenant_from_host() {
+    let mut app = TestApp::new().await;
+    app.add_tenant(tenant("globex").with_hostname("globex.example.com"))
+        .await;
+
+    let email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    let response = app
+        .http_client
+        .post(format!("{}/signup", &app.address))
+        .header("Host", "globex.example.com:3000")
+        .json(&signup_body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+
+    // Hosts without a tenant of their own fall back to the default tenant
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    let response = app.post_with_tenant("/login", "globex", &login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_404_for_unknown_tenant() {
+    let mut app = TestApp::new().await;
+
+    let signup_body = serde_json::json!({
+        "email": get_random_email(),
+        "password": "password123",
+        "requires2FA": false
+    });
+
+    for tenant in ["initech", "Not A Tenant"] {
+        let response = app.post_with_tenant("/signup", tenant, &signup_body).await;
+        assert_eq!(response.status().as_u16(), 404);
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Tenant not found".to_owned()
+        );
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_only_allow_signups_from_allowed_email_domains() {
+    let mut app = TestApp::new().await;
+    app.add_tenant(tenant("acme").with_settings(TenantSettings {
+        require_2fa: false,
+        allowed_email_domains: vec!["acme.com".to_owned()],
+    }))
+    .await;
+
+    let signup_body = serde_json::json!({
+        "email": "wile.e.coyote@example.com",
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_with_tenant("/signup", "acme", &signup_body).await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Email domain not allowed".to_owned()
+    );
+
+    let signup_body = serde_json::json!({
+        "email": "wile.e.coyote@acme.com",
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_with_tenant("/signup", "acme", &signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_2fa_when_the_tenant_does() {
+    let mut app = TestApp::new().await;
+    app.add_tenant(tenant("acme").with_settings(TenantSettings {
+        require_2fa: true,
+        allowed_email_domains: vec![],
+    }))
+    .await;
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_with_tenant("/signup", "acme", &signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+    });
+    let response = app.post_with_tenant("/login", "acme", &login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    app.clean_up().await;
+}
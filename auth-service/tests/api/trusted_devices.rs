@@ -0,0 +1,184 @@
+use crate::helpers::{get_random_email, TestApp};
+use auth_service::{
+    routes::{TrustedDeviceResponse, TwoFactorAuthResponse},
+    utils::constants::TRUSTED_DEVICE_COOKIE_NAME,
+    ErrorResponse,
+};
+use secrecy::ExposeSecret;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+async fn signup_2fa_user(app: &TestApp, email: &str, expected_emails: u64) {
+    let signup_body = serde_json::json!({
+        "email": email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(expected_emails)
+        .mount(&app.email_server)
+        .await;
+}
+
+async fn login(app: &TestApp, email: &str) -> reqwest::Response {
+    app.post_login(&serde_json::json!({
+        "email": email,
+        "password": "password123",
+    }))
+    .await
+}
+
+async fn complete_2fa(app: &TestApp, email: &str, remember_device: bool) -> reqwest::Response {
+    let response = login(app, email).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let two_fa_code = app.get_2fa_code(&login_attempt_id, email).await;
+
+    let response = app
+        .post_verify_2fa(&serde_json::json!({
+            "email": email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": two_fa_code.as_ref().expose_secret(),
+            "rememberDevice": remember_device,
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+}
+
+#[tokio::test]
+async fn should_skip_2fa_on_remembered_device() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    // Only the first login sends a code
+    signup_2fa_user(&app, &random_email, 1).await;
+
+    let response = complete_2fa(&app, &random_email, true).await;
+
+    let device_cookie = response
+        .cookies()
+        .find(|cookie| cookie.name() == TRUSTED_DEVICE_COOKIE_NAME)
+        .expect("No trusted device cookie found");
+    assert!(!device_cookie.value().is_empty());
+    assert!(device_cookie.http_only());
+
+    app.post_logout().await;
+
+    let response = login(&app, &random_email).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_2fa_if_device_not_remembered() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_2fa_user(&app, &random_email, 2).await;
+
+    let response = complete_2fa(&app, &random_email, false).await;
+    assert!(response
+        .cookies()
+        .all(|cookie| cookie.name() != TRUSTED_DEVICE_COOKIE_NAME));
+
+    app.post_logout().await;
+
+    let response = login(&app, &random_email).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_list_and_revoke_trusted_devices() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_2fa_user(&app, &random_email, 2).await;
+
+    complete_2fa(&app, &random_email, true).await;
+
+    let response = app.get_trusted_devices().await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let devices = response
+        .json::<Vec<TrustedDeviceResponse>>()
+        .await
+        .expect("Could not deserialize response body to Vec<TrustedDeviceResponse>");
+    assert_eq!(devices.len(), 1);
+    assert_eq!(devices[0].ip_address.as_deref(), Some("127.0.0.1"));
+
+    let response = app.delete_trusted_device(&devices[0].id).await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    let response = app.get_trusted_devices().await;
+    let devices = response
+        .json::<Vec<TrustedDeviceResponse>>()
+        .await
+        .expect("Could not deserialize response body to Vec<TrustedDeviceResponse>");
+    assert!(devices.is_empty());
+
+    // The browser still holds the cookie, but the device is no longer trusted
+    app.post_logout().await;
+
+    let response = login(&app, &random_email).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_404_if_device_not_found() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    signup_2fa_user(&app, &random_email, 1).await;
+
+    complete_2fa(&app, &random_email, false).await;
+
+    for device_id in ["a1a2a3a4-b1b2-c1c2-d1d2-d3d4d5d6d7d8", "not-a-device-id"] {
+        let response = app.delete_trusted_device(device_id).await;
+        assert_eq!(response.status().as_u16(), 404);
+        assert_eq!(
+            response
+                .json::<ErrorResponse>()
+                .await
+                .expect("Could not deserialize response body to ErrorResponse")
+                .error,
+            "Trusted device not found".to_owned()
+        );
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_if_not_logged_in() {
+    let mut app = TestApp::new().await;
+
+    let response = app.get_trusted_devices().await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    let response = app
+        .delete_trusted_device("a1a2a3a4-b1b2-c1c2-d1d2-d3d4d5d6d7d8")
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
@@ -1,11 +1,11 @@
 use crate::helpers::{get_random_email, TestApp};
 use auth_service::{
-    domain::{Email, LoginAttemptId, TwoFACode, TwoFACodeStore},
-    routes::TwoFactorAuthResponse,
+    domain::data_stores::MAX_TWO_FA_FAILED_ATTEMPTS,
+    routes::{TokenResponse, TwoFactorAuthResponse},
     utils::constants::JWT_COOKIE_NAME,
     ErrorResponse,
 };
-use secrecy::{ExposeSecret, Secret};
+use secrecy::ExposeSecret;
 use wiremock::{
     matchers::{method, path},
     Mock, ResponseTemplate,
@@ -44,17 +44,16 @@ async fn should_return_200_if_correct_code() {
 
     assert_eq!(response.status().as_u16(), 206);
 
-    let (login_attempt_id, two_fa_code) = app
-        .two_fa_code_store
-        .read()
-        .await
-        .get_code(&Email::parse(Secret::new(random_email.clone())).unwrap())
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
         .await
-        .unwrap();
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let two_fa_code = app.get_2fa_code(&login_attempt_id, &random_email).await;
 
     let body = serde_json::json!({
         "email": random_email,
-        "loginAttemptId": login_attempt_id.as_ref().expose_secret(),
+        "loginAttemptId": login_attempt_id,
         "2FACode": two_fa_code.as_ref().expose_secret(),
     });
 
@@ -72,6 +71,71 @@ async fn should_return_200_if_correct_code() {
     app.clean_up().await;
 }
 
+#[tokio::test]
+async fn should_return_token_in_body_if_requested() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "tokenDelivery": "body",
+    });
+
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let two_fa_code = app.get_2fa_code(&login_attempt_id, &random_email).await;
+
+    let body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": two_fa_code.as_ref().expose_secret(),
+        "tokenDelivery": "body",
+    });
+
+    let response = app.post_verify_2fa(&body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(response
+        .cookies()
+        .all(|cookie| cookie.name() != JWT_COOKIE_NAME));
+
+    let token = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .token;
+
+    let response = app
+        .post_verify_token(&serde_json::json!({ "token": token }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
 #[tokio::test]
 async fn should_return_400_if_invalid_input() {
     let mut app = TestApp::new().await;
@@ -200,15 +264,17 @@ async fn should_return_401_if_old_code() {
         "password": "password123",
     });
 
-    let first_response = app.post_login(&login_body).await;
-
-    let (_, first_2FA_code) = app
-        .two_fa_code_store
-        .read()
+    let first_login_attempt_id = app
+        .post_login(&login_body)
         .await
-        .get_code(&Email::parse(Secret::new(random_email.clone())).unwrap())
+        .json::<TwoFactorAuthResponse>()
         .await
-        .unwrap();
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let first_two_fa_code = app
+        .get_2fa_code(&first_login_attempt_id, &random_email)
+        .await;
 
     let second_response = app.post_login(&login_body).await;
 
@@ -223,7 +289,7 @@ async fn should_return_401_if_old_code() {
     let body = serde_json::json!({
         "email": random_email,
         "loginAttemptId": login_attempt_id,
-        "2FACode": first_2FA_code.as_ref().expose_secret(),
+        "2FACode": first_two_fa_code.as_ref().expose_secret(),
     });
 
     let response = app.post_verify_2fa(&body).await;
@@ -270,19 +336,21 @@ async fn should_return_401_if_same_code_twice() {
         "password": "password123",
     });
 
-    let first_response = app.post_login(&login_body).await;
-
-    let (first_login_attempt_id, first_two_fa_code) = app
-        .two_fa_code_store
-        .read()
+    let first_login_attempt_id = app
+        .post_login(&login_body)
         .await
-        .get_code(&Email::parse(Secret::new(random_email.clone())).unwrap())
+        .json::<TwoFactorAuthResponse>()
         .await
-        .unwrap();
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let first_two_fa_code = app
+        .get_2fa_code(&first_login_attempt_id, &random_email)
+        .await;
 
     let body = serde_json::json!({
         "email": random_email,
-        "loginAttemptId": first_login_attempt_id.as_ref().expose_secret(),
+        "loginAttemptId": first_login_attempt_id,
         "2FACode": first_two_fa_code.as_ref().expose_secret(),
     });
 
@@ -355,3 +423,246 @@ async fn should_return_422_if_malformed_input() {
 
     app.clean_up().await;
 }
+
+#[tokio::test]
+async fn should_allow_concurrent_login_attempts() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let mut attempts = Vec::new();
+    for _ in 0..2 {
+        let login_attempt_id = app
+            .post_login(&login_body)
+            .await
+            .json::<TwoFactorAuthResponse>()
+            .await
+            .expect("Could not deserialize response body to TwoFactorAuthResponse")
+            .login_attempt_id;
+        let two_fa_code = app.get_2fa_code(&login_attempt_id, &random_email).await;
+        attempts.push((login_attempt_id, two_fa_code));
+    }
+
+    // The second login must not have overwritten the first one's code
+    for (login_attempt_id, two_fa_code) in attempts {
+        let body = serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": two_fa_code.as_ref().expose_secret(),
+        });
+
+        let response = app.post_verify_2fa(&body).await;
+
+        assert_eq!(response.status().as_u16(), 200);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_login_attempt_belongs_to_another_email() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let two_fa_code = app.get_2fa_code(&login_attempt_id, &random_email).await;
+
+    let body = serde_json::json!({
+        "email": get_random_email(),
+        "loginAttemptId": login_attempt_id,
+        "2FACode": two_fa_code.as_ref().expose_secret(),
+    });
+
+    let response = app.post_verify_2fa(&body).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_401_if_code_is_used_from_another_client() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let response = app.post_login(&login_body).await;
+
+    assert_eq!(response.status().as_u16(), 206);
+
+    let login_attempt_id = response
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+    let two_fa_code = app.get_2fa_code(&login_attempt_id, &random_email).await;
+
+    let body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": two_fa_code.as_ref().expose_secret(),
+    });
+
+    // TestApp requires 2FA codes to be redeemed by the client that logged in
+    let response = app
+        .http_client
+        .post(format!("{}/verify-2fa", &app.address))
+        .header(reqwest::header::USER_AGENT, "some-other-browser")
+        .json(&body)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    // The attempt is still usable from the original client
+    let response = app.post_verify_2fa(&body).await;
+
+    assert_eq!(response.status().as_u16(), 200);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_drop_login_attempt_after_too_many_incorrect_codes() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": true
+    });
+
+    let response = app.post_signup(&signup_body).await;
+
+    assert_eq!(response.status().as_u16(), 201);
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+
+    let login_attempt_id = app
+        .post_login(&login_body)
+        .await
+        .json::<TwoFactorAuthResponse>()
+        .await
+        .expect("Could not deserialize response body to TwoFactorAuthResponse")
+        .login_attempt_id;
+
+    let two_fa_code = app.get_2fa_code(&login_attempt_id, &random_email).await;
+    let wrong_code = if two_fa_code.as_ref().expose_secret() == "111111" {
+        "222222"
+    } else {
+        "111111"
+    };
+
+    for _ in 0..MAX_TWO_FA_FAILED_ATTEMPTS {
+        let body = serde_json::json!({
+            "email": random_email,
+            "loginAttemptId": login_attempt_id,
+            "2FACode": wrong_code,
+        });
+
+        let response = app.post_verify_2fa(&body).await;
+
+        assert_eq!(response.status().as_u16(), 401);
+    }
+
+    // The right code no longer helps once the attempt is gone
+    let body = serde_json::json!({
+        "email": random_email,
+        "loginAttemptId": login_attempt_id,
+        "2FACode": two_fa_code.as_ref().expose_secret(),
+    });
+
+    let response = app.post_verify_2fa(&body).await;
+
+    assert_eq!(response.status().as_u16(), 401);
+
+    app.clean_up().await;
+}
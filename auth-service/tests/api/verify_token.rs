@@ -1,4 +1,11 @@
-use auth_service::{utils::constants::JWT_COOKIE_NAME, ErrorResponse};
+use auth_service::{
+    domain::AuthMethod,
+    routes::TokenResponse,
+    utils::{auth::Claims, constants::JWT_COOKIE_NAME},
+    ErrorResponse,
+};
+
+use auth_extractor::{JwksVerifier, TokenVerifier};
 
 use crate::helpers::{get_random_email, TestApp};
 
@@ -44,6 +51,14 @@ async fn should_return_200_valid_token() {
 
     assert_eq!(response.status().as_u16(), 200);
 
+    // Downstream services read who the token belongs to from the response
+    let claims = response
+        .json::<Claims>()
+        .await
+        .expect("Could not deserialize response body to Claims");
+    assert_eq!(claims.sub, random_email);
+    assert_eq!(claims.amr, vec![AuthMethod::Password]);
+
     app.clean_up().await;
 }
 
@@ -106,3 +121,86 @@ async fn should_return_422_if_malformed_input() {
 
     app.clean_up().await;
 }
+
+#[tokio::test]
+async fn should_return_403_if_required_permission_missing() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "tokenDelivery": "body",
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    let token = response
+        .json::<TokenResponse>()
+        .await
+        .expect("Could not deserialize response body to TokenResponse")
+        .token;
+
+    let response = app
+        .post_verify_token(&serde_json::json!({
+            "token": token,
+            "requiredPermission": "roles:manage",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+    assert_eq!(
+        response
+            .json::<ErrorResponse>()
+            .await
+            .expect("Could not deserialize response body to ErrorResponse")
+            .error,
+        "Missing permission".to_owned()
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_publish_the_key_login_tokens_are_signed_with() {
+    let mut app = TestApp::new().await;
+
+    let random_email = get_random_email();
+    let signup_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+        "requires2FA": false
+    });
+    let response = app.post_signup(&signup_body).await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let login_body = serde_json::json!({
+        "email": random_email,
+        "password": "password123",
+    });
+    let response = app.post_login(&login_body).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let token = response
+        .cookies()
+        .find(|cookie| cookie.name() == JWT_COOKIE_NAME)
+        .expect("No auth cookie found")
+        .value()
+        .to_owned();
+
+    // Downstream services can check the token without asking the auth service
+    let verifier = JwksVerifier::fetch(&format!("{}/.well-known/jwks.json", app.address))
+        .await
+        .expect("Could not fetch the JWKS");
+    let claims = verifier.verify(&token).await.expect("Token was rejected");
+    assert_eq!(claims.sub, random_email);
+
+    app.clean_up().await;
+}
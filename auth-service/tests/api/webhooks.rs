@@ -0,0 +1,441 @@
+use std::time::Duration;
+
+use crate::helpers::{get_random_email, TestApp, TEST_ADMIN_TOKEN, TEST_WEBHOOK_RETRY_POLICY};
+use auth_service::{
+    domain::{WebhookDeliveryStatus, WebhookEventType, WebhookPayload, WebhookUrlPolicy},
+    routes::{
+        CreateWebhookResponse, ListWebhookDeliveriesResponse, WebhookDeliveryResponse,
+        WebhookResponse,
+    },
+    services::webhook_dispatcher::{
+        WEBHOOK_EVENT_HEADER, WEBHOOK_ID_HEADER, WEBHOOK_SIGNATURE_HEADER, WEBHOOK_TIMESTAMP_HEADER,
+    },
+};
+use ring::hmac;
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
+
+const ADMIN: Option<&str> = Some(TEST_ADMIN_TOKEN);
+
+async fn create_webhook(app: &TestApp, url: String, events: &[&str]) -> CreateWebhookResponse {
+    let response = app
+        .post_admin_with_body(
+            "/webhooks",
+            ADMIN,
+            &serde_json::json!({ "url": url, "events": events }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    response
+        .json::<CreateWebhookResponse>()
+        .await
+        .expect("Could not deserialize response body to CreateWebhookResponse")
+}
+
+async fn signup_and_login(app: &TestApp, email: &str) {
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "email": email,
+            "password": "password123",
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+}
+
+async fn list_deliveries(app: &TestApp, query: &str) -> Vec<WebhookDeliveryResponse> {
+    let response = app
+        .get_admin(&format!("/webhook-deliveries?{}", query), ADMIN)
+        .await;
+    assert_eq!(response.status().as_u16(), 200);
+
+    response
+        .json::<ListWebhookDeliveriesResponse>()
+        .await
+        .expect("Could not deserialize response body to ListWebhookDeliveriesResponse")
+        .deliveries
+}
+
+// Deliveries are sent from the background, so poll until they have all settled
+async fn wait_for_deliveries(
+    app: &TestApp,
+    webhook_id: &str,
+    status: WebhookDeliveryStatus,
+    count: usize,
+) -> Vec<WebhookDeliveryResponse> {
+    let query = format!("webhookId={}", webhook_id);
+    for _ in 0..100 {
+        let deliveries = list_deliveries(app, &query).await;
+        if deliveries.len() == count && deliveries.iter().all(|delivery| delivery.status == status)
+        {
+            return deliveries;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("Webhook deliveries did not reach {:?} in time", status);
+}
+
+#[tokio::test]
+async fn should_deliver_signed_payloads_for_subscribed_events() {
+    let mut app = TestApp::new().await;
+    let receiver = MockServer::start().await;
+    Mock::given(path("/hooks"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&receiver)
+        .await;
+
+    let created = create_webhook(&app, format!("{}/hooks", receiver.uri()), &["login"]).await;
+    assert!(created.secret.starts_with("whsec_"));
+    assert_eq!(created.webhook.events, vec![WebhookEventType::Login]);
+
+    let random_email = get_random_email();
+    // The signup isn't subscribed to, the login is
+    signup_and_login(&app, &random_email).await;
+
+    let deliveries = wait_for_deliveries(
+        &app,
+        &created.webhook.id,
+        WebhookDeliveryStatus::Delivered,
+        1,
+    )
+    .await;
+    assert_eq!(deliveries[0].event_type, WebhookEventType::Login);
+    assert_eq!(deliveries[0].attempts, 1);
+    assert_eq!(deliveries[0].last_response_status, Some(200));
+    assert!(deliveries[0].delivered_at.is_some());
+
+    let requests = receiver.received_requests().await.unwrap();
+    assert_eq!(requests.len(), 1);
+    let request = &requests[0];
+    let header = |name: &str| {
+        request
+            .headers
+            .get(name)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_owned()
+    };
+    assert_eq!(header(WEBHOOK_ID_HEADER), deliveries[0].id);
+    assert_eq!(header(WEBHOOK_EVENT_HEADER), "login");
+
+    // Receivers can check the body came from us with the secret they were given
+    let signed = format!(
+        "{}.{}",
+        header(WEBHOOK_TIMESTAMP_HEADER),
+        String::from_utf8_lossy(&request.body)
+    );
+    let key = hmac::Key::new(hmac::HMAC_SHA256, created.secret.as_bytes());
+    let expected: String = hmac::sign(&key, signed.as_bytes())
+        .as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect();
+    assert_eq!(
+        header(WEBHOOK_SIGNATURE_HEADER),
+        format!("sha256={}", expected)
+    );
+
+    let payload: WebhookPayload = serde_json::from_slice(&request.body).unwrap();
+    assert_eq!(payload.event_type, WebhookEventType::Login);
+    assert_eq!(payload.user.as_deref(), Some(random_email.as_str()));
+    assert_eq!(
+        serde_json::to_value(&payload).unwrap(),
+        deliveries[0].payload
+    );
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_dead_letter_failing_deliveries_and_replay_them() {
+    let mut app = TestApp::new().await;
+    let receiver = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .mount(&receiver)
+        .await;
+
+    let created = create_webhook(&app, receiver.uri(), &["signup"]).await;
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": get_random_email(),
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let deliveries = wait_for_deliveries(
+        &app,
+        &created.webhook.id,
+        WebhookDeliveryStatus::DeadLetter,
+        1,
+    )
+    .await;
+    let dead_letter = &deliveries[0];
+    assert_eq!(dead_letter.attempts, TEST_WEBHOOK_RETRY_POLICY.max_attempts);
+    assert_eq!(dead_letter.last_response_status, Some(503));
+    assert_eq!(
+        dead_letter.last_error.as_deref(),
+        Some("Receiver responded with 503 Service Unavailable")
+    );
+    assert!(dead_letter.next_attempt_at.is_none());
+    assert_eq!(
+        receiver.received_requests().await.unwrap().len(),
+        TEST_WEBHOOK_RETRY_POLICY.max_attempts as usize
+    );
+
+    let dead_letters = list_deliveries(&app, "status=dead_letter").await;
+    assert_eq!(dead_letters.len(), 1);
+    assert!(list_deliveries(&app, "status=delivered").await.is_empty());
+
+    // Once the receiver is fixed the delivery can be sent again
+    receiver.reset().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(202))
+        .expect(1)
+        .mount(&receiver)
+        .await;
+
+    let response = app
+        .post_admin(
+            &format!("/webhook-deliveries/{}/replay", dead_letter.id),
+            ADMIN,
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 202);
+    let replayed = response.json::<WebhookDeliveryResponse>().await.unwrap();
+    assert_eq!(replayed.status, WebhookDeliveryStatus::Pending);
+    assert_eq!(replayed.attempts, 0);
+
+    let deliveries = wait_for_deliveries(
+        &app,
+        &created.webhook.id,
+        WebhookDeliveryStatus::Delivered,
+        1,
+    )
+    .await;
+    assert_eq!(deliveries[0].id, dead_letter.id);
+    assert_eq!(deliveries[0].last_response_status, Some(202));
+    // The very same body is sent again
+    assert_eq!(deliveries[0].payload, dead_letter.payload);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_list_and_delete_webhooks() {
+    let mut app = TestApp::new().await;
+    let receiver = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&receiver)
+        .await;
+
+    let first = create_webhook(&app, receiver.uri(), &["signup", "logout"]).await;
+    let second = create_webhook(
+        &app,
+        "https://crm.example.com/hooks".to_owned(),
+        &["two_fa_failure"],
+    )
+    .await;
+
+    let response = app.get_admin("/webhooks", ADMIN).await;
+    assert_eq!(response.status().as_u16(), 200);
+    let webhooks = response.json::<Vec<WebhookResponse>>().await.unwrap();
+    let ids: Vec<&str> = webhooks.iter().map(|webhook| webhook.id.as_str()).collect();
+    assert_eq!(
+        ids,
+        vec![first.webhook.id.as_str(), second.webhook.id.as_str()]
+    );
+
+    signup_and_login(&app, &get_random_email()).await;
+    wait_for_deliveries(
+        &app,
+        &first.webhook.id,
+        WebhookDeliveryStatus::DeadLetter,
+        1,
+    )
+    .await;
+
+    let response = app
+        .delete_admin(&format!("/webhooks/{}", first.webhook.id), ADMIN)
+        .await;
+    assert_eq!(response.status().as_u16(), 204);
+
+    // Its deliveries go with it
+    assert!(list_deliveries(&app, "").await.is_empty());
+    let webhooks = app
+        .get_admin("/webhooks", ADMIN)
+        .await
+        .json::<Vec<WebhookResponse>>()
+        .await
+        .unwrap();
+    assert_eq!(webhooks.len(), 1);
+    assert_eq!(webhooks[0].id, second.webhook.id);
+
+    let response = app
+        .delete_admin(&format!("/webhooks/{}", first.webhook.id), ADMIN)
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_400_for_invalid_webhooks() {
+    let mut app = TestApp::new().await;
+
+    let test_cases = [
+        serde_json::json!({ "url": "ftp://crm.example.com/hooks", "events": ["signup"] }),
+        serde_json::json!({ "url": "not a url", "events": ["signup"] }),
+        serde_json::json!({ "url": "https://crm.example.com/hooks", "events": [] }),
+        serde_json::json!({ "url": "https://crm.example.com/hooks", "events": ["password_reset"] }),
+    ];
+
+    for test_case in test_cases.iter() {
+        let response = app
+            .post_admin_with_body("/webhooks", ADMIN, test_case)
+            .await;
+        assert_eq!(
+            response.status().as_u16(),
+            400,
+            "Failed for input: {:?}",
+            test_case
+        );
+    }
+
+    let response = app
+        .get_admin("/webhook-deliveries?status=lost", ADMIN)
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+    let response = app
+        .get_admin("/webhook-deliveries?webhookId=nope", ADMIN)
+        .await;
+    assert_eq!(response.status().as_u16(), 400);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_refuse_webhooks_to_private_or_plain_http_urls() {
+    let mut app = TestApp::with_config(|app_state| {
+        app_state.with_webhook_url_policy(WebhookUrlPolicy::PublicHttps)
+    })
+    .await;
+
+    let test_cases = [
+        "http://93.184.215.14/hooks",
+        "https://127.0.0.1:3000/admin/users",
+        "https://localhost/hooks",
+        "https://10.0.0.8/hooks",
+        "https://169.254.169.254/latest/meta-data",
+        "https://[::1]/hooks",
+    ];
+
+    for url in test_cases {
+        let body = serde_json::json!({ "url": url, "events": ["signup"] });
+        let response = app.post_admin_with_body("/webhooks", ADMIN, &body).await;
+        assert_eq!(response.status().as_u16(), 400, "Failed for URL: {}", url);
+    }
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_not_follow_redirects() {
+    let mut app = TestApp::new().await;
+    let receiver = MockServer::start().await;
+    Mock::given(path("/hooks"))
+        .respond_with(
+            ResponseTemplate::new(307)
+                .insert_header("Location", format!("{}/elsewhere", receiver.uri())),
+        )
+        .mount(&receiver)
+        .await;
+    Mock::given(path("/elsewhere"))
+        .respond_with(ResponseTemplate::new(204))
+        .expect(0)
+        .mount(&receiver)
+        .await;
+
+    let created = create_webhook(&app, format!("{}/hooks", receiver.uri()), &["signup"]).await;
+    let response = app
+        .post_signup(&serde_json::json!({
+            "email": get_random_email(),
+            "password": "password123",
+            "requires2FA": false
+        }))
+        .await;
+    assert_eq!(response.status().as_u16(), 201);
+
+    let deliveries = wait_for_deliveries(
+        &app,
+        &created.webhook.id,
+        WebhookDeliveryStatus::DeadLetter,
+        1,
+    )
+    .await;
+    assert_eq!(deliveries[0].last_response_status, Some(307));
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_return_404_for_unknown_webhook_deliveries() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .post_admin(
+            &format!("/webhook-deliveries/{}/replay", uuid::Uuid::new_v4()),
+            ADMIN,
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+    let response = app
+        .post_admin("/webhook-deliveries/nope/replay", ADMIN)
+        .await;
+    assert_eq!(response.status().as_u16(), 404);
+
+    app.clean_up().await;
+}
+
+#[tokio::test]
+async fn should_require_admin_to_manage_webhooks() {
+    let mut app = TestApp::new().await;
+
+    let response = app
+        .get_admin("/webhooks", Some("not-the-admin-token"))
+        .await;
+    assert_eq!(response.status().as_u16(), 401);
+
+    signup_and_login(&app, &get_random_email()).await;
+    let response = app.get_admin("/webhooks", None).await;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = app.get_admin("/webhook-deliveries", None).await;
+    assert_eq!(response.status().as_u16(), 403);
+    let response = app
+        .post_admin_with_body(
+            "/webhooks",
+            None,
+            &serde_json::json!({ "url": "https://evil.example.com", "events": ["login"] }),
+        )
+        .await;
+    assert_eq!(response.status().as_u16(), 403);
+
+    app.clean_up().await;
+}